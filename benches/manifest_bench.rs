@@ -0,0 +1,135 @@
+//! Manifest verification: parsing throughput, and peak memory of
+//! `verify_reader` as the manifest grows
+//!
+//! The memory report is printed before the criterion benches run. Peak
+//! heap use should be the same for every manifest size, since entries are
+//! verified in bounded batches instead of being collected first.
+
+use criterion::{black_box, criterion_group, Criterion, Throughput};
+use hashing::checksum::{verify_reader, ChecksumReader};
+use hashing::{hash_string, Algorithm};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fs;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Tracks live and peak heap bytes
+struct Counting;
+
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let live = LIVE.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(live, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        LIVE.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+const FILES: usize = 8;
+const CONTENT: &str = "manifest bench";
+
+/// A checksum file generated line by line, so the manifest itself never
+/// sits in memory
+struct Manifest {
+    digest: String,
+    entries: usize,
+    next: usize,
+    line: Vec<u8>,
+    offset: usize,
+}
+
+impl Manifest {
+    fn new(entries: usize) -> Self {
+        Self {
+            digest: hash_string(CONTENT, Algorithm::Sha256).unwrap(),
+            entries,
+            next: 0,
+            line: Vec::new(),
+            offset: 0,
+        }
+    }
+}
+
+impl Read for Manifest {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.offset == self.line.len() {
+            if self.next == self.entries {
+                return Ok(0);
+            }
+            self.line.clear();
+            let line = format!("{}  file{}\n", self.digest, self.next % FILES);
+            self.line.extend_from_slice(line.as_bytes());
+            self.offset = 0;
+            self.next += 1;
+        }
+        let n = buf.len().min(self.line.len() - self.offset);
+        buf[..n].copy_from_slice(&self.line[self.offset..self.offset + n]);
+        self.offset += n;
+        Ok(n)
+    }
+}
+
+fn peak_memory(base: &Path, entries: usize) -> usize {
+    let reader = BufReader::new(Manifest::new(entries));
+    let baseline = LIVE.load(Ordering::Relaxed);
+    PEAK.store(baseline, Ordering::Relaxed);
+    let summary = verify_reader(reader, Algorithm::Sha256, base, 1, None, |_, _| {}).unwrap();
+    assert_eq!(summary.ok, entries);
+    PEAK.load(Ordering::Relaxed) - baseline
+}
+
+fn report_memory(base: &Path) {
+    println!("verify_reader peak heap use:");
+    for entries in [10_000, 100_000, 1_000_000] {
+        println!(
+            "  {:>9} entries: {:>9} bytes",
+            entries,
+            peak_memory(base, entries)
+        );
+    }
+}
+
+fn bench_parse(c: &mut Criterion) {
+    let mut text = Vec::new();
+    Manifest::new(100_000).read_to_end(&mut text).unwrap();
+    let mut group = c.benchmark_group("manifest_parse");
+    group.throughput(Throughput::Bytes(text.len() as u64));
+    group.bench_function("checksum_reader", |b| {
+        b.iter(|| {
+            let mut entries = ChecksumReader::new(black_box(text.as_slice()));
+            let mut count = 0;
+            while let Some(entry) = entries.next_entry() {
+                black_box(entry.unwrap());
+                count += 1;
+            }
+            count
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_parse);
+
+fn main() {
+    let dir = tempfile::tempdir().unwrap();
+    for i in 0..FILES {
+        fs::write(dir.path().join(format!("file{}", i)), CONTENT).unwrap();
+    }
+    report_memory(dir.path());
+    benches();
+    Criterion::default().configure_from_args().final_summary();
+}
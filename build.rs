@@ -0,0 +1,7 @@
+//! Records the target triple for `buildinfo`
+
+fn main() {
+    let target = std::env::var("TARGET").unwrap_or_default();
+    println!("cargo:rustc-env=HASHING_TARGET={}", target);
+    println!("cargo:rerun-if-changed=build.rs");
+}
@@ -0,0 +1,122 @@
+//! The single table of algorithm names and aliases
+//!
+//! Lookups ignore case and the separators people put in algorithm names, so
+//! `SHA-256`, `sha_256` and `sha256` all resolve to SHA-256. User-defined
+//! aliases (e.g. from the CLI config file) are layered on top through
+//! [`AliasRegistry`].
+
+use crate::{Algorithm, HashError, Result};
+use std::collections::BTreeMap;
+
+/// Additional spellings of each algorithm, besides [`Algorithm::name`]
+pub(crate) fn builtin_aliases(algorithm: Algorithm) -> &'static [&'static str] {
+    match algorithm {
+        Algorithm::Md5 => &[],
+        Algorithm::Sha1 => &["sha-1"],
+        Algorithm::Sha224 => &["sha-224", "sha2-224"],
+        Algorithm::Sha256 => &["sha-256", "sha2-256"],
+        Algorithm::Sha384 => &["sha-384", "sha2-384"],
+        Algorithm::Sha512 => &["sha-512", "sha2-512"],
+        Algorithm::Sha512_224 => &["sha512_224", "sha-512/224", "sha2-512/224"],
+        Algorithm::Sha512_256 => &["sha512_256", "sha-512/256", "sha2-512/256"],
+        Algorithm::Sha3_224 => &["sha3_224"],
+        Algorithm::Sha3_256 => &["sha3_256"],
+        Algorithm::Sha3_384 => &["sha3_384"],
+        Algorithm::Sha3_512 => &["sha3_512"],
+        Algorithm::Shake128 => &["shake-128"],
+        Algorithm::Shake256 => &["shake-256"],
+        Algorithm::Blake2b512 => &["blake2b512", "blake2b-512", "b2"],
+        Algorithm::Blake2s256 => &["blake2s256", "blake2s-256"],
+        Algorithm::Blake3 => &["b3"],
+        Algorithm::Keccak224 => &["keccak-224"],
+        Algorithm::Keccak256 => &["keccak-256"],
+        Algorithm::Keccak384 => &["keccak-384"],
+        Algorithm::Keccak512 => &["keccak-512"],
+        Algorithm::Sha256d => &["double-sha256", "sha256-sha256"],
+        Algorithm::Hash160 => &["hash-160", "ripemd160-sha256"],
+        Algorithm::Xxh32 => &["xxhash32"],
+        Algorithm::Xxh64 => &["xxhash64", "xxhash"],
+        Algorithm::Xxh3 => &["xxh3-64", "xxhash3"],
+        Algorithm::Xxh128 => &["xxh3-128"],
+        Algorithm::Crc32 => &["crc-32"],
+        Algorithm::Crc32c => &["crc-32c", "castagnoli"],
+    }
+}
+
+/// Canonical lookup key: lowercase with `-`, `_`, `/` and spaces removed
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| !matches!(c, '-' | '_' | '/' | ' '))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Resolve a built-in algorithm name or alias
+///
+/// # Examples
+///
+/// ```
+/// use hashing::alias::lookup_builtin;
+/// use hashing::Algorithm;
+///
+/// assert_eq!(lookup_builtin("SHA2-256"), Some(Algorithm::Sha256));
+/// assert_eq!(lookup_builtin("sha_256"), Some(Algorithm::Sha256));
+/// assert_eq!(lookup_builtin("b3"), Some(Algorithm::Blake3));
+/// ```
+pub fn lookup_builtin(name: &str) -> Option<Algorithm> {
+    let key = normalize(name);
+    Algorithm::all().into_iter().find(|&algorithm| {
+        normalize(algorithm.name()) == key
+            || builtin_aliases(algorithm)
+                .iter()
+                .any(|alias| normalize(alias) == key)
+    })
+}
+
+/// Built-in names plus user-defined aliases
+#[derive(Debug, Clone, Default)]
+pub struct AliasRegistry {
+    user: BTreeMap<String, Algorithm>,
+}
+
+impl AliasRegistry {
+    /// A registry with only the built-in names
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a user-defined alias
+    ///
+    /// Aliases may not redefine a built-in name, so `sha256` always means
+    /// SHA-256 no matter what a config file says.
+    pub fn insert(&mut self, alias: &str, algorithm: Algorithm) -> Result<()> {
+        if normalize(alias).is_empty() {
+            return Err(HashError::InvalidInput("empty algorithm alias".to_string()));
+        }
+        if let Some(builtin) = lookup_builtin(alias) {
+            if builtin != algorithm {
+                return Err(HashError::InvalidInput(format!(
+                    "alias {} would shadow the built-in name of {}",
+                    alias,
+                    builtin.name()
+                )));
+            }
+        }
+        self.user.insert(normalize(alias), algorithm);
+        Ok(())
+    }
+
+    /// Resolve a name, trying built-in names first and then user aliases
+    pub fn resolve(&self, name: &str) -> Result<Algorithm> {
+        lookup_builtin(name)
+            .or_else(|| self.user.get(&normalize(name)).copied())
+            .ok_or_else(|| HashError::UnsupportedAlgorithm(name.to_string()))
+    }
+
+    /// User-defined aliases (normalized) and their algorithms, sorted by alias
+    pub fn user_aliases(&self) -> impl Iterator<Item = (&str, Algorithm)> {
+        self.user
+            .iter()
+            .map(|(alias, algorithm)| (alias.as_str(), *algorithm))
+    }
+}
@@ -0,0 +1,111 @@
+//! Hashing inside async runtimes (tokio)
+//!
+//! The functions at the crate root read with blocking I/O. Inside a tokio
+//! service that either stalls a worker thread for as long as a large file
+//! takes to read, or needs a `spawn_blocking` wrapper per call.
+//! [`hash_reader_async`] and [`hash_file_async`] read through tokio's
+//! [`AsyncRead`] instead: the task yields while it waits for data and only
+//! the hashing of each chunk runs on the worker. [`AsyncHasher`] is the
+//! incremental form, for data that arrives piece by piece such as a
+//! request body; it is `Send`, so it can be held across `.await`s and
+//! moved between tasks.
+//!
+//! ```no_run
+//! use hashing::{hash_file_async, Algorithm, AsyncHasher};
+//!
+//! # async fn run() -> hashing::Result<()> {
+//! let digest = hash_file_async("upload.bin", Algorithm::Sha256).await?;
+//! println!("{}", digest);
+//!
+//! let mut hasher = AsyncHasher::new(Algorithm::Blake3);
+//! hasher.update(b"header");
+//! hasher.update_reader(tokio::io::empty()).await?;
+//! println!("{}", hasher.finalize());
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Available with the `async` feature.
+
+use crate::hmac::HmacHasher;
+use crate::multi::{state, State};
+use crate::{Algorithm, Digest, Result};
+use std::io;
+use std::path::Path;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// Bytes read from the input at a time
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Hash everything `reader` yields until EOF, returned as lowercase hex
+pub async fn hash_reader_async<R: AsyncRead + Unpin>(
+    reader: R,
+    algorithm: Algorithm,
+) -> Result<String> {
+    let mut hasher = AsyncHasher::new(algorithm);
+    hasher.update_reader(reader).await?;
+    Ok(hasher.finalize().to_hex())
+}
+
+/// Hash a file's contents with streaming, returned as lowercase hex
+pub async fn hash_file_async<P: AsRef<Path>>(path: P, algorithm: Algorithm) -> Result<String> {
+    let file = tokio::fs::File::open(path).await?;
+    hash_reader_async(file, algorithm).await
+}
+
+/// A digest computed from data fed to it in pieces, from blocking code or
+/// from an [`AsyncRead`]
+pub struct AsyncHasher {
+    algorithm: Algorithm,
+    state: Box<dyn State>,
+}
+
+impl AsyncHasher {
+    /// Hash with `algorithm`
+    pub fn new(algorithm: Algorithm) -> Self {
+        // Only a key can make a state fail
+        let state = state(algorithm, None).expect("unkeyed state");
+        Self { algorithm, state }
+    }
+
+    /// Compute an HMAC under `key` instead; fails for algorithms without
+    /// HMAC (see [`crate::hmac::supports`])
+    pub fn hmac(key: &[u8], algorithm: Algorithm) -> Result<Self> {
+        Ok(Self {
+            algorithm,
+            state: Box::new(HmacHasher::new(key, algorithm)?),
+        })
+    }
+
+    /// Algorithm the digest is computed with
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    /// Feed `data`
+    pub fn update(&mut self, data: &[u8]) {
+        self.state.update(data);
+    }
+
+    /// Feed everything `reader` yields until EOF, returning the number of
+    /// bytes read
+    pub async fn update_reader<R: AsyncRead + Unpin>(&mut self, mut reader: R) -> Result<u64> {
+        let mut buffer = vec![0u8; CHUNK_SIZE];
+        let mut total = 0;
+        loop {
+            let count = match reader.read(&mut buffer).await {
+                Ok(0) => return Ok(total),
+                Ok(count) => count,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            };
+            self.state.update(&buffer[..count]);
+            total += count as u64;
+        }
+    }
+
+    /// The digest of everything fed so far
+    pub fn finalize(self) -> Digest {
+        Digest::computed(self.algorithm, self.state.finalize())
+    }
+}
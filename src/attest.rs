@@ -0,0 +1,105 @@
+//! in-toto attestation subjects
+//!
+//! An in-toto Statement (and so a SLSA provenance attestation) names the
+//! artifacts it is about in its `subject` array: one entry per artifact,
+//! with a `name` and a `digest` map from algorithm name to lowercase hex.
+//! [`subjects`] builds that array for files and directory trees.
+//!
+//! ```no_run
+//! use hashing::attest::subjects;
+//! use hashing::Algorithm;
+//!
+//! let subject = subjects(&["dist/app.tar.gz"], &[Algorithm::Sha256, Algorithm::Sha512])?;
+//! println!("{}", serde_json::to_string_pretty(&subject).unwrap());
+//! # Ok::<(), hashing::HashError>(())
+//! ```
+
+use crate::walk::{walk_files, SortOrder, WalkOptions};
+use crate::{hash_file, Algorithm, HashError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// One element of a Statement's `subject` array
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Subject {
+    /// Artifact name, here the path with `/` separators
+    pub name: String,
+    /// Lowercase hex digest keyed by in-toto algorithm name (`sha256`, ...)
+    pub digest: BTreeMap<String, String>,
+}
+
+/// Name of an algorithm in an in-toto `DigestSet`, if it has one
+pub fn digest_name(algorithm: Algorithm) -> Option<&'static str> {
+    match algorithm {
+        Algorithm::Md5 => Some("md5"),
+        Algorithm::Sha1 => Some("sha1"),
+        Algorithm::Sha224 => Some("sha224"),
+        Algorithm::Sha256 => Some("sha256"),
+        Algorithm::Sha384 => Some("sha384"),
+        Algorithm::Sha512 => Some("sha512"),
+        Algorithm::Sha512_224 => Some("sha512_224"),
+        Algorithm::Sha512_256 => Some("sha512_256"),
+        Algorithm::Sha3_224 => Some("sha3_224"),
+        Algorithm::Sha3_256 => Some("sha3_256"),
+        Algorithm::Sha3_384 => Some("sha3_384"),
+        Algorithm::Sha3_512 => Some("sha3_512"),
+        Algorithm::Shake128 => Some("shake128"),
+        Algorithm::Shake256 => Some("shake256"),
+        Algorithm::Blake2b512 => Some("blake2b"),
+        Algorithm::Blake2s256 => Some("blake2s"),
+        _ => None,
+    }
+}
+
+/// Subject for a single file, named `name`
+pub fn file_subject<P: AsRef<Path>>(
+    path: P,
+    name: impl Into<String>,
+    algorithms: &[Algorithm],
+) -> Result<Subject> {
+    let mut digest = BTreeMap::new();
+    for &algorithm in algorithms {
+        let key = digest_name(algorithm).ok_or_else(|| {
+            HashError::UnsupportedAlgorithm(format!(
+                "{} has no in-toto digest name",
+                algorithm.name()
+            ))
+        })?;
+        digest.insert(key.to_string(), hash_file(path.as_ref(), algorithm)?);
+    }
+    Ok(Subject {
+        name: name.into(),
+        digest,
+    })
+}
+
+/// Subjects for files and, recursively in name order, directories
+///
+/// Each subject is named by its path as given (or below the given
+/// directory), with `/` separators.
+pub fn subjects<P: AsRef<Path>>(paths: &[P], algorithms: &[Algorithm]) -> Result<Vec<Subject>> {
+    let options = WalkOptions::new().with_sort(SortOrder::Name);
+    let mut subjects = Vec::new();
+    for path in paths {
+        let path = path.as_ref();
+        if path.is_dir() {
+            for file in walk_files(path, &options) {
+                let file = file?;
+                subjects.push(file_subject(&file, subject_name(&file), algorithms)?);
+            }
+        } else {
+            subjects.push(file_subject(path, subject_name(path), algorithms)?);
+        }
+    }
+    Ok(subjects)
+}
+
+fn subject_name(path: &Path) -> String {
+    let name = path.to_string_lossy();
+    if std::path::MAIN_SEPARATOR == '\\' {
+        name.replace('\\', "/")
+    } else {
+        name.into_owned()
+    }
+}
@@ -0,0 +1,186 @@
+//! Hashes used by authentication protocols
+//!
+//! - [`tls_server_end_point`] computes the RFC 5929 `tls-server-end-point`
+//!   channel binding of a server certificate, used by SCRAM-*-PLUS and
+//!   GSS-API to tie an authentication exchange to a TLS connection.
+//! - [`client_data_hash`], [`rp_id_hash`] and [`assertion_signed_data`] are
+//!   the WebAuthn hashes a relying party needs to check an authenticator's
+//!   signature.
+//!
+//! ```
+//! use hashing::auth::{assertion_signed_data, client_data_hash};
+//!
+//! let client_data_json = br#"{"type":"webauthn.get","challenge":"..."}"#;
+//! let authenticator_data = [0u8; 37];
+//! let signed = assertion_signed_data(&authenticator_data, client_data_json);
+//! assert_eq!(&signed[37..], &client_data_hash(client_data_json));
+//! ```
+
+use crate::builder::digest_bytes;
+use crate::fixed::{hash_bytes_fixed, Sha256};
+use crate::{Algorithm, HashError, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+/// Hash algorithm the `tls-server-end-point` binding uses for a certificate
+///
+/// This is the hash of the certificate's signature algorithm, except that
+/// MD5 and SHA-1 are replaced by SHA-256. Certificates whose signature
+/// algorithm has no single hash (Ed25519, RSASSA-PSS, ...) have no defined
+/// binding and are rejected. `certificate` may be DER or PEM.
+pub fn end_point_algorithm(certificate: &[u8]) -> Result<Algorithm> {
+    let der = certificate_der(certificate)?;
+    let oid = signature_algorithm_oid(&der)?;
+
+    const RSA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01];
+    const ECDSA: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04];
+    const DSA_SHA1: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x38, 0x04, 0x03];
+    const DSA_SHA2: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x03];
+
+    let algorithm = match oid {
+        [prefix @ .., last] if prefix == RSA => match last {
+            // md5WithRSAEncryption and sha1WithRSAEncryption
+            0x04 | 0x05 => Some(Algorithm::Sha256),
+            0x0b => Some(Algorithm::Sha256),
+            0x0c => Some(Algorithm::Sha384),
+            0x0d => Some(Algorithm::Sha512),
+            0x0e => Some(Algorithm::Sha224),
+            _ => None,
+        },
+        // ecdsa-with-SHA1
+        [prefix @ .., 0x01] if prefix == ECDSA => Some(Algorithm::Sha256),
+        [prefix @ .., 0x03, last] if prefix == ECDSA => match last {
+            0x01 => Some(Algorithm::Sha224),
+            0x02 => Some(Algorithm::Sha256),
+            0x03 => Some(Algorithm::Sha384),
+            0x04 => Some(Algorithm::Sha512),
+            _ => None,
+        },
+        _ if oid == DSA_SHA1 => Some(Algorithm::Sha256),
+        [prefix @ .., last] if prefix == DSA_SHA2 => match last {
+            0x01 => Some(Algorithm::Sha224),
+            0x02 => Some(Algorithm::Sha256),
+            _ => None,
+        },
+        _ => None,
+    };
+    algorithm.ok_or_else(|| {
+        HashError::UnsupportedAlgorithm(format!(
+            "tls-server-end-point is not defined for signature algorithm {}",
+            oid_to_string(oid)
+        ))
+    })
+}
+
+/// The `tls-server-end-point` channel binding data (RFC 5929) of a server
+/// certificate given as DER or PEM
+pub fn tls_server_end_point(certificate: &[u8]) -> Result<Vec<u8>> {
+    let algorithm = end_point_algorithm(certificate)?;
+    digest_bytes(&certificate_der(certificate)?, algorithm)
+}
+
+/// SHA-256 of the `clientDataJSON` bytes exactly as the client sent them
+pub fn client_data_hash(client_data_json: &[u8]) -> [u8; 32] {
+    hash_bytes_fixed::<Sha256>(client_data_json)
+}
+
+/// SHA-256 of a relying party ID, as found at the start of authenticator data
+pub fn rp_id_hash(rp_id: &str) -> [u8; 32] {
+    hash_bytes_fixed::<Sha256>(rp_id.as_bytes())
+}
+
+/// Whether authenticator data was produced for `rp_id`
+pub fn matches_rp_id(authenticator_data: &[u8], rp_id: &str) -> bool {
+    authenticator_data.len() >= 32 && authenticator_data[..32] == rp_id_hash(rp_id)
+}
+
+/// The bytes an authenticator signs: `authenticatorData || SHA-256(clientDataJSON)`
+pub fn assertion_signed_data(authenticator_data: &[u8], client_data_json: &[u8]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(authenticator_data.len() + 32);
+    data.extend_from_slice(authenticator_data);
+    data.extend_from_slice(&client_data_hash(client_data_json));
+    data
+}
+
+/// DER bytes of a certificate given as DER or PEM
+pub(crate) fn certificate_der(certificate: &[u8]) -> Result<Vec<u8>> {
+    const BEGIN: &str = "-----BEGIN CERTIFICATE-----";
+    const END: &str = "-----END CERTIFICATE-----";
+
+    let text = match std::str::from_utf8(certificate) {
+        Ok(text) if text.contains(BEGIN) => text,
+        _ => return Ok(certificate.to_vec()),
+    };
+    let start = text.find(BEGIN).expect("checked above") + BEGIN.len();
+    let end = text[start..]
+        .find(END)
+        .ok_or_else(|| HashError::InvalidInput("unterminated PEM certificate".to_string()))?;
+    let body: String = text[start..start + end]
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    STANDARD
+        .decode(body)
+        .map_err(|e| HashError::InvalidInput(format!("invalid PEM certificate: {}", e)))
+}
+
+/// OID of `Certificate.signatureAlgorithm`, the second element of the
+/// outer SEQUENCE
+fn signature_algorithm_oid(der: &[u8]) -> Result<&[u8]> {
+    let invalid = || HashError::InvalidInput("not a DER X.509 certificate".to_string());
+
+    let (certificate, _) = der_element(der, 0x30).ok_or_else(invalid)?;
+    let (_, rest) = der_element(certificate, 0x30).ok_or_else(invalid)?;
+    let (algorithm_identifier, _) = der_element(rest, 0x30).ok_or_else(invalid)?;
+    let (oid, _) = der_element(algorithm_identifier, 0x06).ok_or_else(invalid)?;
+    Ok(oid)
+}
+
+/// Split a DER element with the expected tag into its contents and the
+/// bytes after it
+pub(crate) fn der_element(data: &[u8], tag: u8) -> Option<(&[u8], &[u8])> {
+    let (&actual, rest) = data.split_first()?;
+    if actual != tag {
+        return None;
+    }
+    let (&first, rest) = rest.split_first()?;
+    let (len, rest) = if first < 0x80 {
+        (first as usize, rest)
+    } else {
+        let count = (first & 0x7f) as usize;
+        if count == 0 || count > 4 || rest.len() < count {
+            return None;
+        }
+        let len = rest[..count]
+            .iter()
+            .fold(0usize, |len, &byte| (len << 8) | byte as usize);
+        (len, &rest[count..])
+    };
+    if rest.len() < len {
+        return None;
+    }
+    Some(rest.split_at(len))
+}
+
+/// Dotted form of an encoded OID, for error messages
+fn oid_to_string(oid: &[u8]) -> String {
+    let mut arcs = Vec::new();
+    let mut value = 0u64;
+    for &byte in oid {
+        value = (value << 7) | (byte & 0x7f) as u64;
+        if byte & 0x80 == 0 {
+            if arcs.is_empty() {
+                let first = (value / 40).min(2);
+                arcs.push(first);
+                arcs.push(value - first * 40);
+            } else {
+                arcs.push(value);
+            }
+            value = 0;
+        }
+    }
+    arcs.iter()
+        .map(u64::to_string)
+        .collect::<Vec<_>>()
+        .join(".")
+}
@@ -7,11 +7,60 @@
 //! Command-line interface for the hashing tool
 
 use anyhow::{Context, Result};
-use clap::{Parser, ValueEnum};
-use hashing::{hash_file, hash_string, Algorithm, HashResult};
-use std::fs;
+use clap::builder::{PossibleValue, TypedValueParser};
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use hashing::alias::AliasRegistry;
+use hashing::attest;
+use hashing::buildinfo::build_info;
+use hashing::checksum::{self, CheckStatus, CheckSummary, ChecksumEntry};
+use hashing::chunk::{ChunkIndex, ChunkIndexFormat, Chunker};
+use hashing::cachekey::{self, CacheKey, EntryKind, MissingInput};
+use hashing::cloud;
+use hashing::config::Config;
+use hashing::convert::{self, DigestFormat};
+use hashing::dkim::{self, BodyCanonicalization};
+use hashing::ethereum;
+use hashing::hmac;
+use hashing::inspect;
+use hashing::job::{HashJob, JobEvent, VerifyJob};
+use hashing::journal::Journal;
+use hashing::key::KeySource;
+use hashing::keyed;
+use hashing::manifest::{self, ChecksumFormat, Manifest, ManifestSplit, MergeOptions};
+use hashing::naming;
+use hashing::newline;
+use hashing::pins::{PinStatus, Pins, PINS_FILE};
+use hashing::progress::{ProgressBar, ProgressTracker};
+use hashing::pkgmanifest::{self, FieldStatus, ManifestFormat};
+use hashing::pagecache::UncachedReader;
+use hashing::plugin::{self, FormatPlugin, PluginSession};
+use hashing::release;
+use hashing::sbom::{self, SbomFormat, SbomStatus};
+use hashing::seal;
+use hashing::sigstore;
+use hashing::ssh;
+use hashing::stamp;
+use hashing::tabular::{delimiter_for_path, hash_csv_columns, ColumnSelector, CsvHashOptions};
+use hashing::template::Template;
+use hashing::tree::{merkle_root, FileDigest};
+use hashing::trust::TrustStore;
+use hashing::units::{SizeFormat, SizeUnits};
+use hashing::walk::{
+    parse_age, parse_size, relative_path, walk_files, Glob, SortOrder, WalkOptions,
+};
+use hashing::{
+    group_by_digest, hash_bytes, Algorithm, AlgorithmFamily, Digest, DigestGroup, Encoding,
+    HashError, HashResult, Hashing, InputKind, InputPolicy, MultiHasher,
+};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufWriter, IsTerminal, Read, Write};
+use std::ffi::OsStr;
 use std::path::{Path, PathBuf};
-use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
 use clap_version_flag::colorful_version;
 
 #[derive(Parser)]
@@ -19,320 +68,4481 @@ use clap_version_flag::colorful_version;
 #[command(author = "hadi cahyadi <cumulus13@gmail.com>")]
 #[command(version)]
 #[command(about = "Generate cryptographic hashes for strings and files", long_about = "A Rust library and CLI tool for generating cryptographic hashes. Supports multiple algorithms with zero-copy streaming for efficient processing of large files.")]
+#[command(args_conflicts_with_subcommands = true)]
 struct Cli {
-    /// Input: file path or string to hash
-    #[arg(value_name = "INPUT")]
-    input: String,
+    #[command(subcommand)]
+    command: Option<Command>,
 
-    /// Hash algorithm to use
-    #[arg(short, long, default_value = "sha256")]
-    algorithm: String,
+    #[command(flatten)]
+    args: HashArgs,
+}
+
+/// Subcommands; each one maps onto the flat invocation it replaces
+#[derive(Subcommand)]
+enum Command {
+    /// Hash the contents of one or more files
+    File {
+        #[arg(value_name = "PATH", required = true)]
+        paths: Vec<String>,
+
+        /// Show a progress bar on stderr
+        #[arg(long)]
+        progress: bool,
+
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+
+    /// Hash a literal string
+    String {
+        #[arg(value_name = "TEXT")]
+        text: String,
+
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+
+    /// Recursively hash every file under a directory
+    Dir {
+        #[arg(value_name = "DIR")]
+        dir: String,
+
+        #[command(flatten)]
+        output: OutputArgs,
+
+        #[command(flatten)]
+        walk: Box<WalkArgs>,
+    },
+
+    /// Replace CSV/TSV column values with their digests (salt with --key-*)
+    Csv {
+        /// CSV or TSV file, or - for stdin
+        #[arg(value_name = "FILE")]
+        file: String,
+
+        #[command(flatten)]
+        csv: CsvArgs,
+
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+
+    /// Verify the files listed in checksum files, like `sha256sum -c`
+    Check {
+        /// Checksum file, URL or - for stdin
+        #[arg(value_name = "LIST", required = true)]
+        lists: Vec<String>,
+
+        /// Skip listed files that do not exist instead of failing
+        #[arg(long)]
+        ignore_missing: bool,
+
+        /// Resolve the listed paths against DIR instead of the current directory
+        #[arg(long, value_name = "DIR")]
+        root: Option<PathBuf>,
+
+        /// First verify a detached signature (file or URL) over the list
+        #[arg(long, value_name = "SIG")]
+        signature: Option<String>,
+
+        /// Public key (PEM) trusted to sign the list, besides [trust.keys] in the config
+        #[arg(long, value_name = "KEY", requires = "signature")]
+        trusted_key: Vec<PathBuf>,
+
+        /// Exit non-zero for improperly formatted checksum lines
+        #[arg(long)]
+        strict: bool,
+
+        /// Warn about each improperly formatted checksum line
+        #[arg(long)]
+        warn: bool,
+    },
+
+    /// Verify an input against an expected digest
+    Verify {
+        #[arg(value_name = "INPUT")]
+        input: String,
+
+        /// Expected digest, @checksum-file or URL
+        #[arg(value_name = "EXPECTED")]
+        expected: String,
+
+        #[command(flatten)]
+        mode: InputModeArgs,
+
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+
+    /// Compare two files or strings by hash
+    Compare {
+        #[arg(value_name = "INPUT1")]
+        input1: String,
+
+        #[arg(value_name = "INPUT2")]
+        input2: String,
+
+        #[command(flatten)]
+        mode: InputModeArgs,
+
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+
+    /// Measure the throughput of each algorithm in memory or on real files
+    Bench {
+        /// File or directory to hash instead of an in-memory buffer, including the I/O
+        #[arg(value_name = "PATH", conflicts_with = "size")]
+        path: Option<PathBuf>,
+
+        /// Algorithm to benchmark (default: all)
+        #[arg(short, long, value_parser = AlgorithmParser)]
+        algorithm: Option<Algorithm>,
+
+        /// Size of the buffer hashed per algorithm (e.g. 64M)
+        #[arg(long, default_value = "64M", value_name = "SIZE")]
+        size: String,
+
+        #[command(flatten)]
+        units: UnitArgs,
+    },
+
+    /// Show a file's size, type, first and last bytes and digests in one read
+    Inspect {
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+
+        /// Digest algorithm, repeatable (default: md5, sha1, sha256, blake3)
+        #[arg(short, long = "algorithm", value_name = "ALGORITHM", value_parser = AlgorithmParser)]
+        algorithms: Vec<Algorithm>,
+
+        /// Bytes to show from each end of the file
+        #[arg(long, default_value_t = 64, value_name = "N")]
+        bytes: usize,
+
+        #[command(flatten)]
+        units: UnitArgs,
+    },
+
+    /// Compute the DKIM body hash (bh=) of an email message
+    DkimBodyHash {
+        /// Message file (.eml), or - for stdin
+        #[arg(value_name = "FILE")]
+        file: String,
+
+        /// Body canonicalization, or the whole c= tag (e.g. relaxed/relaxed)
+        #[arg(short, long, default_value = "simple", value_name = "CANON")]
+        canonicalization: String,
+
+        /// sha256 (rsa-sha256, ed25519-sha256) or sha1 (rsa-sha1)
+        #[arg(short, long, default_value = "sha256", value_parser = AlgorithmParser)]
+        algorithm: Algorithm,
+
+        /// Hash only the first BYTES canonicalized bytes (the l= tag)
+        #[arg(short, long, value_name = "BYTES")]
+        length: Option<u64>,
+
+        /// The input is a bare body, without headers
+        #[arg(long)]
+        body_only: bool,
+
+        /// bh= value from the signature; exit with status 1 if it differs
+        #[arg(long, value_name = "BH")]
+        expect: Option<String>,
+
+        /// Print the canonicalized body instead of its hash
+        #[arg(long, conflicts_with = "expect")]
+        show_canonical: bool,
+    },
+
+    /// Print the Authenticode digest of a PE file or the build-id of an ELF file
+    Executable {
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+
+        /// Authenticode hash algorithm (PE files)
+        #[arg(short, long, default_value = "sha256", value_parser = AlgorithmParser)]
+        algorithm: Algorithm,
+
+        /// Expected digest or build-id (hex); exit with status 1 if it differs
+        #[arg(long, value_name = "HEX")]
+        expect: Option<String>,
+
+        /// Only print the digest or build-id
+        #[arg(short, long)]
+        quiet: bool,
+    },
+
+    /// Verify the entry digests recorded in a signed JAR or APK
+    Jar {
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+
+        /// Only print entries that failed
+        #[arg(short, long)]
+        quiet: bool,
+    },
+
+    /// Verify the digests embedded in a .deb or .rpm package
+    Package {
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+
+        /// Only print checks that failed
+        #[arg(short, long)]
+        quiet: bool,
+    },
+
+    /// Write SBOM file checksums for a directory, or check an SBOM against it
+    Sbom {
+        /// Directory to scan, or that the SBOM's paths are relative to
+        #[arg(value_name = "DIR", default_value = ".")]
+        dir: PathBuf,
+
+        /// Document format (spdx, cyclonedx)
+        #[arg(short, long, default_value = "spdx", value_name = "FORMAT")]
+        format: SbomFormat,
+
+        /// Checksum algorithm, repeatable (default: sha1 and sha256)
+        #[arg(short, long = "algorithm", value_name = "ALGORITHM", value_parser = AlgorithmParser)]
+        algorithms: Vec<Algorithm>,
+
+        /// Check the checksums of this SBOM (SPDX or CycloneDX JSON) instead
+        #[arg(long, value_name = "SBOM", conflicts_with_all = ["format", "algorithms"])]
+        verify: Option<PathBuf>,
+
+        /// Write the fragment to FILE instead of stdout
+        #[arg(short, long, value_name = "FILE", conflicts_with = "verify")]
+        output: Option<PathBuf>,
+
+        /// Only print files that failed verification
+        #[arg(short, long)]
+        quiet: bool,
+    },
+
+    /// Print the in-toto `subject` array (name and digests) for artifacts
+    AttestSubjects {
+        /// Files or directories (hashed recursively)
+        #[arg(value_name = "PATH", required = true)]
+        paths: Vec<PathBuf>,
+
+        /// Digest algorithm, repeatable (default: sha256)
+        #[arg(short, long = "algorithm", value_name = "ALGORITHM", value_parser = AlgorithmParser)]
+        algorithms: Vec<Algorithm>,
+
+        /// Write the array to FILE instead of stdout
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+
+    /// Check a cosign blob signature (.sig with certificate or key, or a bundle)
+    VerifyBlob {
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+
+        /// Signature file written by `cosign sign-blob --output-signature`
+        #[arg(long, value_name = "SIG", requires = "certificate")]
+        signature: Option<PathBuf>,
+
+        /// Signing certificate or public key (PEM)
+        #[arg(long, alias = "key", value_name = "CERT", requires = "signature")]
+        certificate: Option<PathBuf>,
+
+        /// cosign or Sigstore bundle holding signature and certificate
+        #[arg(long, value_name = "BUNDLE", conflicts_with_all = ["signature", "certificate"])]
+        bundle: Option<PathBuf>,
+    },
+
+    /// Copy input to files and/or stdout while hashing it in the same pass
+    Tee {
+        /// Input file, or - for stdin
+        #[arg(value_name = "INPUT")]
+        input: String,
+
+        /// Output files; - is stdout (default: stdout)
+        #[arg(value_name = "OUTPUT")]
+        outputs: Vec<String>,
+
+        #[arg(short, long, default_value = "sha256", value_parser = AlgorithmParser)]
+        algorithm: Algorithm,
+
+        /// Append to output files instead of truncating them
+        #[arg(long)]
+        append: bool,
+
+        /// Also write the checksum line to FILE
+        #[arg(long, value_name = "FILE")]
+        digest_file: Option<PathBuf>,
+    },
+
+    /// Rewrite a stamp file of input digests only when the inputs' contents change
+    Stamp {
+        /// Stamp file to update
+        #[arg(value_name = "STAMP")]
+        stamp: PathBuf,
+
+        /// Input files or directories (hashed recursively)
+        #[arg(value_name = "INPUT", required = true)]
+        inputs: Vec<PathBuf>,
+
+        #[arg(short, long, default_value = "sha256", value_parser = AlgorithmParser)]
+        algorithm: Algorithm,
+
+        /// Also write a Make/Ninja depfile listing the inputs of STAMP
+        #[arg(long, value_name = "FILE")]
+        depfile: Option<PathBuf>,
+
+        /// Do not report whether the stamp changed
+        #[arg(short, long)]
+        quiet: bool,
+    },
+
+    /// Print a stable CI cache key over files, environment variables and literals
+    Cachekey {
+        /// Files or directories whose contents enter the key
+        #[arg(long, num_args = 1.., value_name = "PATH")]
+        inputs: Vec<PathBuf>,
+
+        /// Environment variables whose values enter the key
+        #[arg(long, num_args = 1.., value_name = "NAME")]
+        env: Vec<String>,
+
+        /// Literal strings that enter the key (e.g. a cache version)
+        #[arg(long, num_args = 1.., value_name = "TEXT")]
+        literal: Vec<String>,
+
+        #[arg(short, long, default_value = "sha256", value_parser = AlgorithmParser)]
+        algorithm: Algorithm,
+
+        /// What to do with missing input files (fail, skip, mark)
+        #[arg(long, default_value = "fail", value_name = "POLICY")]
+        missing: MissingInput,
+
+        /// Text to put in front of the key
+        #[arg(long, default_value = "", value_name = "TEXT")]
+        prefix: String,
+
+        /// List the inputs and their digests on stderr
+        #[arg(long)]
+        explain: bool,
+    },
+
+    /// Cut a file into content-defined chunks and print its chunk index
+    Chunks {
+        /// File to chunk, or - for stdin
+        #[arg(value_name = "FILE")]
+        file: String,
+
+        #[arg(short, long, default_value = "sha256", value_parser = AlgorithmParser)]
+        algorithm: Algorithm,
+
+        /// Average chunk size; chunks are a quarter to four times as long
+        #[arg(long, default_value = "64K", value_name = "SIZE", value_parser = parse_size)]
+        avg_size: u64,
+
+        /// Index format (text, json, csv)
+        #[arg(short, long, default_value = "text", value_name = "FORMAT")]
+        format: ChunkIndexFormat,
+
+        /// Write the index to FILE instead of stdout
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        /// Text index of chunks already stored; report how much is new
+        #[arg(long, value_name = "INDEX")]
+        known: Option<PathBuf>,
+
+        /// Do not print the chunk summary on stderr
+        #[arg(short, long)]
+        quiet: bool,
+    },
+
+    /// Re-encode digests (hex, base64, Nix base32, SRI, multihash, ...)
+    Convert {
+        /// Digests: SRI (sha256-...), prefixed (sha256:...), multihash, ni: or bare
+        #[arg(value_name = "DIGEST", required = true)]
+        digests: Vec<String>,
+
+        /// Output format (hex, base64, base64url, nix32, sri, prefixed, multihash, ni)
+        #[arg(short, long, default_value = "sri", value_name = "FORMAT")]
+        to: DigestFormat,
+
+        /// Algorithm of bare digests
+        #[arg(short, long, value_name = "ALGORITHM", value_parser = AlgorithmParser)]
+        algorithm: Option<Algorithm>,
+    },
+
+    /// Write SHA256SUMS, SHA512SUMS, an SRI map and a JSON manifest for a dist directory
+    Release {
+        /// Directory holding the release artifacts (hashed recursively)
+        #[arg(value_name = "DIST")]
+        dist: PathBuf,
+
+        /// Directory to write the files to (default: DIST)
+        #[arg(short, long, value_name = "DIR")]
+        output: Option<PathBuf>,
+
+        /// Do not list the files written
+        #[arg(short, long)]
+        quiet: bool,
+    },
+
+    /// Fill in or check the SHA-256 fields of a Homebrew, Scoop or winget manifest
+    FillManifest {
+        /// Formula (.rb), Scoop manifest (.json) or winget manifest (.yaml), edited in place
+        #[arg(value_name = "MANIFEST")]
+        manifest: PathBuf,
+
+        /// Downloaded artifacts, matched to the manifest's URLs by file name
+        #[arg(value_name = "ARTIFACT", required = true)]
+        artifacts: Vec<PathBuf>,
+
+        /// Manifest format (homebrew, scoop, winget; default: from the extension)
+        #[arg(long, value_name = "FORMAT")]
+        format: Option<ManifestFormat>,
+
+        /// Replace digests that do not match instead of failing
+        #[arg(long)]
+        force: bool,
+
+        /// Report what would change without writing the manifest
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Git hooks checking pinned file digests (see .hashpins.toml)
+    Hook {
+        #[command(subcommand)]
+        action: HookAction,
+    },
+
+    /// Combine and clean up checksum files
+    Manifest {
+        #[command(subcommand)]
+        action: ManifestAction,
+    },
+
+    /// List all available algorithms
+    List,
+}
+
+#[derive(Subcommand)]
+enum HookAction {
+    /// Fail unless every pinned file still has its pinned digest
+    PreCommit {
+        /// Pins file; pinned paths are relative to its directory
+        #[arg(long, default_value = PINS_FILE, value_name = "FILE")]
+        pins: PathBuf,
+
+        /// Only report files that fail
+        #[arg(short, long)]
+        quiet: bool,
+    },
+
+    /// Pin files to their current digests, adding or updating entries
+    Pin {
+        /// Files to pin, relative to the pins file's directory
+        #[arg(value_name = "FILE", required = true)]
+        files: Vec<PathBuf>,
+
+        #[arg(short, long, default_value = "sha256", value_parser = AlgorithmParser)]
+        algorithm: Algorithm,
+
+        /// Pins file, created if missing
+        #[arg(long, default_value = PINS_FILE, value_name = "FILE")]
+        pins: PathBuf,
+    },
+
+    /// Install a Git pre-commit hook that runs `hash hook pre-commit`
+    Install {
+        /// Replace an existing pre-commit hook
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ManifestAction {
+    /// Merge checksum files into one, normalizing digests and path separators
+    Merge {
+        /// Checksum files to merge, in order
+        #[arg(value_name = "FILE", required = true)]
+        files: Vec<PathBuf>,
+
+        /// Keep only the first entry for each path
+        #[arg(long)]
+        dedupe: bool,
+
+        /// Sort entries by path
+        #[arg(long)]
+        sort: bool,
+
+        /// Write the merged manifest to FILE instead of stdout
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+
+    /// Translate a checksum list to another format without re-hashing
+    Convert {
+        /// Checksum file, JSON/JSONL results, hashdeep file or SFV file
+        #[arg(value_name = "FILE")]
+        input: PathBuf,
+
+        /// Format to write (checksum, json, jsonl, hashdeep, sfv)
+        #[arg(long, value_name = "FORMAT")]
+        to: ChecksumFormat,
+
+        /// Format of FILE (default: from its extension)
+        #[arg(long, value_name = "FORMAT")]
+        from: Option<ChecksumFormat>,
+
+        /// Algorithm of a checksum file's digests (default: from its name, as
+        /// in SHA256SUMS or app.sha256); also picks the digest to keep when
+        /// the input has several per file
+        #[arg(short, long, value_name = "NAME")]
+        algorithm: Option<String>,
+
+        /// Write the converted list to FILE instead of stdout
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+
+    /// Decrypt a manifest exported with --encrypt, checking it was not modified
+    Decrypt {
+        #[arg(value_name = "FILE")]
+        input: PathBuf,
+
+        /// Take the passphrase from environment variable NAME instead of a prompt
+        #[arg(long, value_name = "NAME")]
+        passphrase_env: Option<String>,
+
+        /// Write the manifest to FILE instead of stdout
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+}
+
+/// The flat (subcommand-less) invocation, also the normalized form of every subcommand
+#[derive(Args, Default)]
+struct HashArgs {
+    /// Input: file path, string to hash, or - for stdin (the default when stdin is piped);
+    /// several are hashed concurrently
+    #[arg(value_name = "INPUT")]
+    input: Vec<String>,
 
     /// List all available algorithms
     #[arg(short = 'l', long)]
     list_algorithms: bool,
 
-    /// Export result to file
-    #[arg(short, long, value_name = "FILE")]
-    export: Option<PathBuf>,
+    #[command(flatten)]
+    mode: InputModeArgs,
+
+    /// Verify hash against an expected value, @checksum-file or URL
+    #[arg(short = 'c', long, value_name = "EXPECTED")]
+    verify: Option<String>,
+
+    /// Check an s3://, gs:// or az:// INPUT against the checksums its provider stores
+    #[arg(long, conflicts_with_all = ["verify", "compare", "recursive", "all_algorithms"])]
+    check_stored: bool,
+
+    /// Verify the files listed in a checksum file, like `sha256sum -c` (- for stdin; repeatable)
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = [
+            "input", "verify", "compare", "recursive", "all_algorithms", "encoding"
+        ]
+    )]
+    check: Vec<String>,
+
+    /// With --check, skip listed files that do not exist instead of failing
+    #[arg(long, requires = "check")]
+    ignore_missing: bool,
+
+    /// With --check, first verify a detached signature (file or URL) over the list
+    #[arg(long, value_name = "SIG", requires = "check")]
+    signature: Option<String>,
+
+    /// Public key (PEM) trusted to sign --check lists, besides [trust.keys] in the config
+    #[arg(long, value_name = "KEY", requires = "signature")]
+    trusted_key: Vec<PathBuf>,
+
+    /// With --check, resolve the listed paths against DIR instead of the current directory
+    #[arg(long, value_name = "DIR", requires = "check")]
+    root: Option<PathBuf>,
+
+    /// With --check, exit non-zero for improperly formatted checksum lines
+    #[arg(long, requires = "check")]
+    strict: bool,
+
+    /// With --check, warn about each improperly formatted checksum line
+    #[arg(long, requires = "check")]
+    warn: bool,
+
+    /// Compare two files or strings by hash
+    #[arg(short = 'C', long, value_name = "INPUT2")]
+    compare: Option<String>,
+
+    /// Recursively hash every file under the INPUT directory
+    #[arg(short = 'r', long)]
+    recursive: bool,
+
+    /// Hash the value of an environment variable (kept out of shell history)
+    #[arg(long, value_name = "VAR", conflicts_with_all = ["input", "prompt"])]
+    input_env: Option<String>,
+
+    /// Prompt for the string to hash without echoing it
+    #[arg(long, conflicts_with = "input")]
+    prompt: bool,
+
+    /// Treat INPUT as an Ethereum address or public key and print the EIP-55 checksummed address
+    #[arg(long, conflicts_with_all = ["recursive", "compare", "verify", "all_algorithms"])]
+    eip55: bool,
+
+    /// Print the table of exit codes and exit
+    #[arg(long)]
+    help_exit_codes: bool,
+
+    #[command(flatten)]
+    output: OutputArgs,
+
+    #[command(flatten)]
+    walk: WalkArgs,
+
+    #[command(flatten)]
+    csv: CsvArgs,
+}
+
+/// Explicit file/string selection, overriding the "path exists" heuristic
+#[derive(Args, Default)]
+struct InputModeArgs {
+    /// Treat input as a file path (error if it does not exist)
+    #[arg(long, conflicts_with = "string")]
+    file: bool,
+
+    /// Treat input as a string even if it matches a file path
+    #[arg(short = 's', long)]
+    string: bool,
+}
+
+impl InputModeArgs {
+    fn policy(&self) -> InputPolicy {
+        if self.file {
+            InputPolicy::File
+        } else if self.string {
+            InputPolicy::String
+        } else {
+            InputPolicy::Auto
+        }
+    }
+}
+
+/// Options shared by every hashing mode
+#[derive(Args, Default)]
+struct OutputArgs {
+    /// Hash algorithm to use
+    #[arg(short, long, default_value = "sha256", value_parser = AlgorithmParser)]
+    algorithm: Algorithm,
+
+    /// Export result to file
+    #[arg(short, long, value_name = "FILE")]
+    export: Option<PathBuf>,
+
+    /// Export format
+    #[arg(short = 'f', long, default_value = "text")]
+    format: ExportFormat,
+
+    /// Write the export with format plugin NAME ([formats] in the config or hash-format-NAME)
+    #[arg(long, value_name = "NAME", requires = "export", conflicts_with = "format")]
+    format_plugin: Option<String>,
+
+    /// Print one line per result from TEMPLATE, e.g. '{path}\t{algo}\t{digest}\t{size}'
+    #[arg(long, value_name = "TEMPLATE", conflicts_with_all = ["format", "format_plugin"])]
+    template: Option<String>,
+
+    /// Compute hashes for all algorithms
+    #[arg(short = 'A', long)]
+    all_algorithms: bool,
+
+    /// Limit --all-algorithms to these families, e.g. sha2,blake
+    #[arg(long, value_name = "LIST", value_delimiter = ',', requires = "all_algorithms")]
+    families: Vec<AlgorithmFamily>,
+
+    /// Quiet mode - only output the hash
+    #[arg(short, long)]
+    quiet: bool,
+
+    /// Digest encoding: hex, HEX, base64, base64url, base32 or binary (raw bytes to stdout)
+    #[arg(long, value_name = "ENCODING", default_value = "hex")]
+    encoding: Encoding,
+
+    /// Digest length in bytes, for BLAKE2 (up to its full size), BLAKE3 and SHAKE
+    #[arg(long, value_name = "BYTES", conflicts_with = "all_algorithms")]
+    length: Option<usize>,
+
+    #[command(flatten)]
+    key: KeyArgs,
+
+    /// Use the key in BLAKE2's or BLAKE3's own keyed mode instead of HMAC
+    #[arg(long)]
+    keyed: bool,
+
+    /// Parse the input as a JSON/CBOR/MessagePack document and hash its canonical encoding
+    #[arg(long, value_name = "FORMAT")]
+    canonical: Option<CanonicalArg>,
+
+    /// Drop one trailing newline (\n, \r\n or \r) from string or stdin input, as `echo -n` would
+    #[arg(long, visible_alias = "no-trailing-newline")]
+    strip_newline: bool,
+
+    #[command(flatten)]
+    reads: ReadArgs,
+}
+
+/// How files are read, including extra work that catches faulty hardware
+#[derive(Args, Default, Clone, Copy)]
+struct ReadArgs {
+    /// Compute every digest twice on separate threads and fail if they differ (catches bad RAM/CPU)
+    #[arg(long)]
+    paranoid: bool,
+
+    /// Read every file twice, dropping its cached pages in between, and fail if the digests differ
+    #[arg(long)]
+    double_read: bool,
+
+    /// Read files past the page cache (O_DIRECT on Linux, F_NOCACHE on macOS)
+    #[arg(long)]
+    uncached: bool,
+
+    /// Keep the kernel reading SIZE ahead of each file (e.g. 64M), for high-latency storage
+    #[arg(long, value_name = "SIZE", value_parser = parse_size, conflicts_with = "uncached")]
+    read_ahead: Option<u64>,
+
+    /// Threads hashing a large BLAKE3 file, memory-mapped (0 = one per core, the default)
+    #[arg(long, value_name = "N")]
+    threads: Option<usize>,
+
+    /// Hash up to N of several INPUTs at once (default: one per core)
+    #[arg(short = 'j', long, value_name = "N")]
+    jobs: Option<usize>,
+
+    /// Units of the progress bar drawn for `--progress`, if one is
+    #[arg(skip)]
+    progress_bar: Option<SizeFormat>,
+
+    /// Digest length from `--length`, if one was given
+    #[arg(skip)]
+    output_size: Option<usize>,
+}
+
+impl ReadArgs {
+    /// A hasher for `algorithm` (an HMAC with `key`) reading files as requested
+    fn hashing(self, algorithm: Algorithm, key: Option<Key>) -> Hashing<'static> {
+        let hashing = Hashing::new()
+            .algorithm(algorithm)
+            .paranoid(self.paranoid)
+            .double_read(self.double_read)
+            .uncached(self.uncached)
+            .read_ahead(self.read_ahead.unwrap_or(0));
+        #[cfg(feature = "rayon")]
+        let hashing = hashing.threads(self.threads.unwrap_or(0));
+        let hashing = match self.output_size {
+            Some(bytes) => hashing.output_size(bytes),
+            None => hashing,
+        };
+        match key {
+            Some(Key { bytes, blake: true }) => hashing.blake_key(bytes),
+            Some(Key { bytes, blake: false }) => hashing.key(bytes),
+            None => hashing,
+        }
+    }
+
+    /// [`ReadArgs::hashing`], drawing a `--progress` bar for an input of
+    /// `total` bytes
+    fn hashing_with_bar(
+        self,
+        algorithm: Algorithm,
+        key: Option<Key>,
+        total: Option<u64>,
+    ) -> Hashing<'static> {
+        let hashing = self.hashing(algorithm, key);
+        match self.bar(total) {
+            Some(mut bar) => hashing.progress(move |bytes| bar.update(bytes)),
+            None => hashing,
+        }
+    }
+
+    /// A progress bar on stderr for an input of `total` bytes, with `--progress`
+    fn bar(self, total: Option<u64>) -> Option<ProgressBar<io::Stderr>> {
+        let format = self.progress_bar?;
+        Some(ProgressBar::new(io::stderr(), total).with_size_format(format))
+    }
+
+    /// A hasher computing all of `algorithms` from one read, or `None` when
+    /// a self-check needs a separate pass per algorithm
+    fn multi_hasher(self, algorithms: &[Algorithm], key: Option<Key>) -> Option<MultiHasher> {
+        // MultiHasher computes HMACs only
+        if self.paranoid || self.double_read || key.is_some_and(|key| key.blake) {
+            return None;
+        }
+        // Threads only pay off with a core for more than one algorithm
+        let multi = MultiHasher::new(algorithms)
+            .parallel(checksum::default_jobs() > 1)
+            .uncached(self.uncached)
+            .read_ahead(self.read_ahead.unwrap_or(0));
+        Some(match key {
+            Some(key) => multi.key(key.bytes),
+            None => multi,
+        })
+    }
+}
+
+/// Canonical encodings selectable with `--canonical`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum CanonicalArg {
+    /// JSON Canonicalization Scheme (RFC 8785)
+    Json,
+    /// Deterministic CBOR (RFC 8949)
+    Cbor,
+    /// MessagePack with sorted map keys
+    Msgpack,
+}
+
+/// Key material; supplying a key switches from plain hashing to HMAC, or
+/// with `--keyed` to the algorithm's own keyed mode
+#[derive(Args, Default)]
+#[group(multiple = false)]
+struct KeyArgs {
+    /// Use KEY's UTF-8 bytes as the HMAC key (visible to other local users; prefer --key-env)
+    #[arg(long, visible_alias = "key", value_name = "KEY")]
+    hmac_key: Option<String>,
+
+    /// Read the HMAC key (raw bytes) from FILE
+    #[arg(long, alias = "hmac-key-file", value_name = "FILE")]
+    key_file: Option<PathBuf>,
+
+    /// Use a hex-encoded HMAC key
+    #[arg(long, value_name = "HEX")]
+    key_hex: Option<String>,
+
+    /// Read the HMAC key from an environment variable
+    #[arg(long, value_name = "VAR")]
+    key_env: Option<String>,
+
+    /// Read the HMAC key (raw bytes) from stdin
+    #[arg(long)]
+    key_stdin: bool,
+}
+
+impl KeyArgs {
+    fn source(&self) -> Option<KeySource> {
+        if let Some(ref text) = self.hmac_key {
+            Some(KeySource::Text(text.clone()))
+        } else if let Some(ref path) = self.key_file {
+            Some(KeySource::File(path.clone()))
+        } else if let Some(ref hex_key) = self.key_hex {
+            Some(KeySource::Hex(hex_key.clone()))
+        } else if let Some(ref var) = self.key_env {
+            Some(KeySource::Env(var.clone()))
+        } else if self.key_stdin {
+            Some(KeySource::Stdin)
+        } else {
+            None
+        }
+    }
+
+    /// Load the key, if one was requested, warning about exposed key files
+    fn load(&self, quiet: bool) -> Result<Option<Vec<u8>>> {
+        let Some(source) = self.source() else {
+            return Ok(None);
+        };
+        if !quiet {
+            if let Some(warning) = source.permission_warning() {
+                eprintln!("warning: {}", warning);
+            }
+        }
+        let key = source.load().context("Failed to load key")?;
+        Ok(Some(key))
+    }
+}
+
+/// A loaded key and the construction it keys
+#[derive(Clone, Copy)]
+struct Key<'a> {
+    bytes: &'a [u8],
+    /// BLAKE2's or BLAKE3's own keyed mode (`--keyed`) rather than HMAC
+    blake: bool,
+}
+
+impl<'a> Key<'a> {
+    fn new(bytes: Option<&'a [u8]>, blake: bool) -> Option<Self> {
+        bytes.map(|bytes| Self { bytes, blake })
+    }
+
+    /// Whether `algorithm` can be keyed with this key
+    fn supports(self, algorithm: Algorithm) -> bool {
+        if self.blake {
+            keyed::key_sizes(algorithm).is_some_and(|sizes| sizes.contains(&self.bytes.len()))
+        } else {
+            hmac::supports(algorithm)
+        }
+    }
+}
+
+/// Options for recursive directory scans
+#[derive(Args, Default)]
+struct WalkArgs {
+    /// Resume a recursive scan, skipping paths recorded in the journal
+    #[arg(long, value_name = "JOURNAL")]
+    resume: Option<PathBuf>,
+
+    /// Order of entries within each directory in recursive mode (name, size, mtime, none)
+    #[arg(long, default_value = "none", value_name = "ORDER")]
+    sort: SortOrder,
+
+    /// Maximum directory depth in recursive mode (1 = top-level files only)
+    #[arg(long, value_name = "N")]
+    max_depth: Option<usize>,
+
+    /// Skip files smaller than SIZE in recursive mode (e.g. 4K, 10M)
+    #[arg(long, value_name = "SIZE")]
+    min_size: Option<String>,
+
+    /// Skip files larger than SIZE in recursive mode (e.g. 2G)
+    #[arg(long, value_name = "SIZE")]
+    max_size: Option<String>,
+
+    /// Only hash files modified within AGE (e.g. 12h, 7d) or after a reference file
+    #[arg(long, value_name = "AGE|FILE")]
+    newer_than: Option<String>,
+
+    /// Do not cross file system boundaries (mount points) in recursive mode
+    #[arg(long)]
+    one_file_system: bool,
+
+    /// Skip a directory or file in recursive mode, relative to the scanned directory (repeatable)
+    #[arg(long, value_name = "PATH")]
+    skip: Vec<PathBuf>,
+
+    /// Do not skip /proc, /sys and /dev when scanning from /
+    #[arg(long)]
+    no_default_skips: bool,
+
+    /// Only hash files matching GLOB in recursive mode (repeatable; e.g. '*.rs', 'src/**')
+    #[arg(long, value_name = "GLOB")]
+    include: Vec<Glob>,
+
+    /// Skip files and directories matching GLOB in recursive mode (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    exclude: Vec<Glob>,
+
+    /// Print one Merkle root over the relative paths and digests instead of the file list
+    #[arg(
+        long,
+        conflicts_with_all = ["group_by", "resume", "split_by_dir", "split_every", "dry_run"]
+    )]
+    merkle_root: bool,
+
+    /// Encrypt and authenticate the exported manifest under a passphrase
+    #[arg(
+        long,
+        requires = "export",
+        conflicts_with_all = ["resume", "split_by_dir", "split_every", "format_plugin"]
+    )]
+    encrypt: bool,
+
+    /// Take the passphrase of an encrypted manifest from environment variable NAME, not a prompt
+    #[arg(long, value_name = "NAME")]
+    passphrase_env: Option<String>,
+
+    /// Hash every hard link separately instead of reusing the first digest
+    #[arg(long)]
+    no_hard_link_dedup: bool,
+
+    /// Give up on a file after this long in recursive mode (e.g. 30s, 5m)
+    #[arg(long, value_name = "DURATION")]
+    timeout: Option<String>,
+
+    /// Cluster recursive results by digest, listing each digest once with its paths
+    #[arg(long, value_name = "KEY", value_parser = ["digest"])]
+    group_by: Option<String>,
+
+    /// Write one export file per top-level subdirectory in recursive mode (NAME-DIR.EXT)
+    #[arg(long, requires = "export", conflicts_with = "split_every")]
+    split_by_dir: bool,
+
+    /// Start a new export file every N entries in recursive mode (NAME-0001.EXT, ...)
+    #[arg(long, value_name = "N", requires = "export")]
+    split_every: Option<usize>,
+
+    /// List the files a recursive scan would hash, with counts and total bytes, without hashing
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Only hash files tracked by Git in recursive mode
+    #[arg(long, conflicts_with = "git_changed")]
+    git_tracked: bool,
+
+    /// Only hash files changed since REV (default HEAD), or untracked, in recursive mode
+    #[arg(
+        long,
+        value_name = "REV",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "HEAD"
+    )]
+    git_changed: Option<String>,
+
+    /// Show a progress bar on stderr (--progress=json: JSON Lines records, in recursive mode)
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "bar"
+    )]
+    progress: Option<ProgressFormat>,
+
+    #[command(flatten)]
+    units: UnitArgs,
+}
+
+/// Units for the sizes and throughput printed in summaries
+#[derive(Args, Default)]
+struct UnitArgs {
+    /// Print sizes with SI prefixes (kB, MB, GB: powers of 1000)
+    #[arg(long, overrides_with = "binary_units")]
+    si: bool,
+
+    /// Print sizes with binary prefixes (KiB, MiB, GiB: powers of 1024), the default
+    #[arg(long, overrides_with = "si")]
+    binary_units: bool,
+}
+
+impl UnitArgs {
+    /// Size format for the chosen units and the locale's decimal point
+    fn format(&self) -> SizeFormat {
+        let units = if self.si { SizeUnits::Si } else { SizeUnits::Binary };
+        SizeFormat::from_env().with_units(units)
+    }
+}
+
+/// Progress output formats selectable with `--progress`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ProgressFormat {
+    /// A bar with the bytes read, throughput and time left
+    Bar,
+    /// JSON Lines records, at most one `progress` record per half second
+    Json,
+}
+
+/// Least time between two periodic `--progress` records
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Options for hashing columns of CSV/TSV input
+#[derive(Args, Default)]
+struct CsvArgs {
+    /// Replace a CSV column's values with their digests: header name or 1-based position (repeatable)
+    #[arg(long = "csv-column", value_name = "COLUMN")]
+    columns: Vec<ColumnSelector>,
+
+    /// CSV field delimiter (default: tab for .tsv/.tab files, comma otherwise)
+    #[arg(long, value_name = "CHAR", requires = "columns")]
+    delimiter: Option<char>,
+
+    /// The CSV input has no header row, so columns must be given by position
+    #[arg(long, requires = "columns")]
+    no_header: bool,
+}
+
+impl HashArgs {
+    /// The first positional input; only valid after `main` has checked
+    /// there is one
+    fn input(&self) -> &str {
+        self.input.first().map_or("", String::as_str)
+    }
+}
+
+impl Command {
+    /// Translate a subcommand into the equivalent flat invocation
+    fn into_args(self) -> HashArgs {
+        match self {
+            Command::File {
+                paths,
+                progress,
+                output,
+            } => HashArgs {
+                input: paths,
+                mode: InputModeArgs {
+                    file: true,
+                    string: false,
+                },
+                output,
+                walk: WalkArgs {
+                    progress: progress.then_some(ProgressFormat::Bar),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            Command::String { text, output } => HashArgs {
+                input: vec![text],
+                mode: InputModeArgs {
+                    file: false,
+                    string: true,
+                },
+                output,
+                ..Default::default()
+            },
+            Command::Dir { dir, output, walk } => HashArgs {
+                input: vec![dir],
+                recursive: true,
+                output,
+                walk: *walk,
+                ..Default::default()
+            },
+            Command::Csv { file, csv, output } => HashArgs {
+                input: vec![file],
+                output,
+                csv,
+                ..Default::default()
+            },
+            Command::Check {
+                lists,
+                ignore_missing,
+                root,
+                signature,
+                trusted_key,
+                strict,
+                warn,
+            } => HashArgs {
+                check: lists,
+                ignore_missing,
+                root,
+                signature,
+                trusted_key,
+                strict,
+                warn,
+                ..Default::default()
+            },
+            Command::Verify { input, expected, mode, output } => HashArgs {
+                input: vec![input],
+                verify: Some(expected),
+                mode,
+                output,
+                ..Default::default()
+            },
+            Command::Compare { input1, input2, mode, output } => HashArgs {
+                input: vec![input1],
+                compare: Some(input2),
+                mode,
+                output,
+                ..Default::default()
+            },
+            Command::Bench { .. }
+            | Command::Inspect { .. }
+            | Command::DkimBodyHash { .. }
+            | Command::Executable { .. }
+            | Command::Jar { .. }
+            | Command::Package { .. }
+            | Command::Sbom { .. }
+            | Command::AttestSubjects { .. }
+            | Command::VerifyBlob { .. }
+            | Command::Tee { .. }
+            | Command::Stamp { .. }
+            | Command::Cachekey { .. }
+            | Command::Chunks { .. }
+            | Command::Convert { .. }
+            | Command::Release { .. }
+            | Command::FillManifest { .. }
+            | Command::Hook { .. }
+            | Command::Manifest { .. }
+            | Command::List => unreachable!("handled in main"),
+        }
+    }
+}
+
+/// Algorithm names and aliases, including user aliases from the config file
+static ALIASES: OnceLock<AliasRegistry> = OnceLock::new();
+
+fn aliases() -> &'static AliasRegistry {
+    ALIASES.get_or_init(AliasRegistry::new)
+}
+
+/// Export-format plugins registered in the config file
+static FORMATS: OnceLock<BTreeMap<String, PathBuf>> = OnceLock::new();
+
+fn configured_formats() -> &'static BTreeMap<String, PathBuf> {
+    FORMATS.get_or_init(BTreeMap::new)
+}
+
+/// Keys trusted to sign checksum lists, from `[trust.keys]` in the config file
+static TRUSTED_KEYS: OnceLock<BTreeMap<String, PathBuf>> = OnceLock::new();
+
+fn configured_trusted_keys() -> &'static BTreeMap<String, PathBuf> {
+    TRUSTED_KEYS.get_or_init(BTreeMap::new)
+}
+
+/// Parses `--algorithm` through the alias registry
+///
+/// Help and completions list the canonical names from the library, while
+/// parsing also accepts every alias spelling and user-defined aliases.
+#[derive(Clone)]
+struct AlgorithmParser;
+
+/// `--algorithm` value leaving the choice to [`Algorithm::fastest_secure`]
+const AUTO_ALGORITHM: &str = "auto";
+
+/// Buffer for reading checksum lists, enough to see the first entries
+const CHECK_BUFFER_SIZE: usize = 64 * 1024;
+
+impl TypedValueParser for AlgorithmParser {
+    type Value = Algorithm;
+
+    fn parse_ref(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: &OsStr,
+    ) -> std::result::Result<Algorithm, clap::Error> {
+        use clap::error::{ContextKind, ContextValue, ErrorKind};
+
+        let name = value.to_string_lossy();
+        if name.eq_ignore_ascii_case(AUTO_ALGORITHM) {
+            return Ok(Algorithm::fastest_secure());
+        }
+        aliases().resolve(&name).map_err(|_| {
+            let mut err = clap::Error::new(ErrorKind::InvalidValue).with_cmd(cmd);
+            if let Some(arg) = arg {
+                err.insert(ContextKind::InvalidArg, ContextValue::String(arg.to_string()));
+            }
+            err.insert(ContextKind::InvalidValue, ContextValue::String(name.into_owned()));
+            let names = Algorithm::all()
+                .iter()
+                .map(|a| a.name().to_string())
+                .chain([AUTO_ALGORITHM.to_string()])
+                .collect();
+            err.insert(ContextKind::ValidValue, ContextValue::Strings(names));
+            err
+        })
+    }
+
+    fn possible_values(&self) -> Option<Box<dyn Iterator<Item = PossibleValue> + '_>> {
+        let auto = PossibleValue::new(AUTO_ALGORITHM)
+            .help("BLAKE3 or SHA-256, whichever is faster on this machine");
+        Some(Box::new(
+            Algorithm::value_variants()
+                .iter()
+                .filter_map(ValueEnum::to_possible_value)
+                .chain([auto]),
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Default, ValueEnum)]
+enum ExportFormat {
+    /// Plain text format (hash only)
+    #[default]
+    Text,
+    /// JSON format with metadata
+    Json,
+    /// Checksums format (hash filename)
+    Checksum,
+    /// JSON Lines format, one object per line (streamed in recursive mode)
+    Jsonl,
+    /// RFC 6920 named-information URI (ni:///sha-256;...)
+    Ni,
+    /// Sigstore/cosign blob digest (sha256:...)
+    Cosign,
+}
+
+/// Process exit codes, part of the CLI's public contract
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExitStatus {
+    Success = 0,
+    Mismatch = 1,
+    Usage = 2,
+    Io = 3,
+    Parse = 4,
+}
+
+impl ExitStatus {
+    const ALL: [ExitStatus; 5] = [
+        ExitStatus::Success,
+        ExitStatus::Mismatch,
+        ExitStatus::Usage,
+        ExitStatus::Io,
+        ExitStatus::Parse,
+    ];
+
+    fn description(self) -> &'static str {
+        match self {
+            ExitStatus::Success => "Success (hash computed, verification passed, inputs match)",
+            ExitStatus::Mismatch => "Verification failed or compared inputs differ",
+            ExitStatus::Usage => "Usage error (invalid or conflicting command-line options)",
+            ExitStatus::Io => {
+                "I/O error (missing or unreadable file, timeout, write failure, failed self-check)"
+            }
+            ExitStatus::Parse => "Parse error (malformed digest, checksum file, size or duration)",
+        }
+    }
+
+    fn exit(self) -> ! {
+        std::process::exit(self as i32)
+    }
+
+    /// Pick the exit status for an error by inspecting its cause chain
+    fn for_error(err: &anyhow::Error) -> Self {
+        for cause in err.chain() {
+            if cause.is::<UsageError>() || cause.is::<clap::Error>() {
+                return ExitStatus::Usage;
+            }
+            if let Some(err) = cause.downcast_ref::<HashError>() {
+                return match err {
+                    HashError::Io(_)
+                    | HashError::Timeout(_)
+                    | HashError::ExportError(_)
+                    | HashError::SelfCheck(_) => ExitStatus::Io,
+                    HashError::UnsupportedAlgorithm(_) | HashError::InvalidInput(_) => {
+                        ExitStatus::Parse
+                    }
+                };
+            }
+            if cause.is::<io::Error>() {
+                return ExitStatus::Io;
+            }
+            if cause.is::<serde_json::Error>() {
+                return ExitStatus::Parse;
+            }
+        }
+        ExitStatus::Io
+    }
+}
+
+/// An invalid combination of command-line options (exit status 2)
+#[derive(Debug)]
+struct UsageError(String);
+
+impl std::fmt::Display for UsageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for UsageError {}
+
+fn usage_error(message: impl Into<String>) -> anyhow::Error {
+    UsageError(message.into()).into()
+}
+
+fn print_exit_codes() {
+    println!("Exit codes:");
+    println!();
+    for status in ExitStatus::ALL {
+        println!("  {}  {}", status as i32, status.description());
+    }
+}
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("Error: {:?}", err);
+        ExitStatus::for_error(&err).exit();
+    }
+}
+
+/// Parse the command line
+///
+/// A lone input that names a subcommand but is also an existing path (as
+/// `is_path` tells) is hashed with the flat form, as it was before there
+/// were subcommands: `hash check` hashes the file `check` if there is one.
+fn parse_cli<F: Fn(&str) -> bool>(argv: &[String], is_path: F) -> clap::error::Result<Cli> {
+    let shadowed = argv.get(1).is_some_and(|first| {
+        Cli::command().find_subcommand(first).is_some() && is_path(first)
+    });
+    if shadowed {
+        let flat = HashArgs::augment_args(clap::Command::new("hash"))
+            .try_get_matches_from(argv)
+            .and_then(|matches| HashArgs::from_arg_matches(&matches));
+        if let Ok(args) = flat {
+            if args.input.len() == 1 {
+                return Ok(Cli { command: None, args });
+            }
+        }
+    }
+    Cli::try_parse_from(argv)
+}
+
+fn run() -> Result<()> {
+    let os_args: Vec<String> = std::env::args().collect();
+    if let Some(tool) = os_args.first().and_then(|arg0| SumTool::invoked_as(arg0)) {
+        return run_sum(tool, &os_args);
+    }
+
+    let config = Config::load_default().context("Failed to load config file")?;
+    // Only fails if a value was set already, which cannot happen before parsing
+    let _ = ALIASES.set(config.alias_registry().context("Invalid [aliases] in config file")?);
+    let _ = FORMATS.set(config.formats);
+    let _ = TRUSTED_KEYS.set(config.trust.keys);
+
+    let version_flags = ["-V", "--version"];
+    let list_flags = ["-l", "--list-algorithms"];
+
+    if os_args.len() >= 2 {
+        if os_args[1..].iter().any(|arg| version_flags.contains(&arg.as_str())) {
+            if os_args[1..].iter().any(|arg| arg == "--json") {
+                let info = serde_json::to_string_pretty(&build_info())
+                    .context("Failed to serialize build metadata")?;
+                println!("{}", info);
+                return Ok(());
+            }
+            let version = colorful_version!();
+            version.print_and_exit();    
+        } else if os_args[1..].iter().any(|arg| list_flags.contains(&arg.as_str())) {
+            list_algorithms();
+            return Ok(());
+        }
+        
+    }
+
+    let cli = parse_cli(&os_args, |arg| Path::new(arg).exists()).unwrap_or_else(|e| e.exit());
+
+    let mut args = match cli.command {
+        Some(Command::List) => {
+            list_algorithms();
+            return Ok(());
+        }
+        Some(Command::Bench { path, algorithm, size, units }) => {
+            return run_bench(algorithm, &size, path.as_deref(), units.format());
+        }
+        Some(Command::Inspect { file, algorithms, bytes, units }) => {
+            return run_inspect(&file, algorithms, bytes, units.format());
+        }
+        Some(command @ Command::DkimBodyHash { .. }) => {
+            return run_dkim_body_hash(command);
+        }
+        Some(Command::Executable { file, algorithm, expect, quiet }) => {
+            return run_executable(&file, algorithm, expect.as_deref(), quiet);
+        }
+        Some(Command::Jar { file, quiet }) => {
+            return run_jar(&file, quiet);
+        }
+        Some(Command::Package { file, quiet }) => {
+            return run_package(&file, quiet);
+        }
+        Some(command @ Command::Sbom { .. }) => {
+            return run_sbom(command);
+        }
+        Some(command @ Command::VerifyBlob { .. }) => {
+            return run_verify_blob(command);
+        }
+        Some(command @ Command::Tee { .. }) => {
+            return run_tee(command);
+        }
+        Some(command @ Command::Stamp { .. }) => {
+            return run_stamp(command);
+        }
+        Some(command @ Command::Cachekey { .. }) => {
+            return run_cachekey(command);
+        }
+        Some(command @ Command::Chunks { .. }) => {
+            return run_chunks(command);
+        }
+        Some(Command::Release { dist, output, quiet }) => {
+            let artifacts = release::scan_dist(&dist)
+                .with_context(|| format!("Failed to hash artifacts in: {}", dist.display()))?;
+            let output = output.unwrap_or(dist);
+            fs::create_dir_all(&output)?;
+            let written = release::write_release_files(&output, &artifacts).with_context(|| {
+                format!("Failed to write release files to: {}", output.display())
+            })?;
+            if !quiet {
+                for path in written {
+                    println!("{}", path.display());
+                }
+                eprintln!("{} artifacts", artifacts.len());
+            }
+            return Ok(());
+        }
+        Some(command @ Command::FillManifest { .. }) => {
+            return run_fill_manifest(command);
+        }
+        Some(Command::Hook { action }) => {
+            return run_hook(action);
+        }
+        Some(Command::Manifest { action }) => {
+            return run_manifest(action);
+        }
+        Some(Command::Convert { digests, to, algorithm }) => {
+            for digest in &digests {
+                println!("{}", convert::convert_digest(digest, algorithm, to)?);
+            }
+            return Ok(());
+        }
+        Some(Command::AttestSubjects { paths, mut algorithms, output }) => {
+            if algorithms.is_empty() {
+                algorithms.push(Algorithm::Sha256);
+            }
+            let subjects = attest::subjects(&paths, &algorithms)?;
+            let json = serde_json::to_string_pretty(&subjects)
+                .context("Failed to serialize subjects")?;
+            match output {
+                Some(path) => fs::write(&path, json + "\n")
+                    .with_context(|| format!("Failed to write subjects: {}", path.display()))?,
+                None => println!("{}", json),
+            }
+            return Ok(());
+        }
+        Some(command) => command.into_args(),
+        None => cli.args,
+    };
+
+    // List algorithms if requested
+    if args.list_algorithms {
+        list_algorithms();
+        return Ok(());
+    }
+
+    if args.help_exit_codes {
+        print_exit_codes();
+        return Ok(());
+    }
+
+    if !args.check.is_empty() {
+        if args.signature.is_some() && args.check.len() > 1 {
+            return Err(usage_error("--signature covers a single checksum list"));
+        }
+        // A mismatch in any list outweighs a file another could not read
+        let mut status = ExitStatus::Success;
+        for list in &args.check {
+            match check_list(list, &args)? {
+                ExitStatus::Success => {}
+                ExitStatus::Io if status == ExitStatus::Mismatch => {}
+                worse => status = worse,
+            }
+        }
+        if status != ExitStatus::Success {
+            status.exit();
+        }
+        return Ok(());
+    }
+
+    if let Some(secret) = read_secret_input(&args)? {
+        args.input = vec![secret];
+        args.mode = InputModeArgs {
+            file: false,
+            string: true,
+        };
+    }
+
+    if args.input.is_empty() && !args.mode.string && !io::stdin().is_terminal() {
+        // Data piped in without an INPUT, as in `tar cf - dir | hash`
+        args.input = vec!["-".to_string()];
+    }
+    if args.input.is_empty() {
+        Cli::command()
+            .error(
+                clap::error::ErrorKind::MissingRequiredArgument,
+                "an INPUT or a subcommand is required",
+            )
+            .exit();
+    }
+    if args.walk.resume.is_some() && !args.recursive {
+        return Err(usage_error("--resume requires --recursive"));
+    }
+    if args.walk.merkle_root && !args.recursive {
+        return Err(usage_error("--merkle-root requires --recursive"));
+    }
+    if args.walk.encrypt && !args.recursive {
+        return Err(usage_error("--encrypt requires --recursive"));
+    }
+    if cfg!(not(feature = "rayon")) && args.output.reads.threads.is_some_and(|n| n != 1) {
+        return Err(usage_error("--threads requires a build with the `rayon` feature"));
+    }
+    match args.walk.progress {
+        Some(ProgressFormat::Json) if !args.recursive => {
+            return Err(usage_error("--progress=json requires --recursive"));
+        }
+        Some(ProgressFormat::Bar) if args.recursive => {
+            return Err(usage_error("--recursive reports progress with --progress=json"));
+        }
+        _ => {}
+    }
+    if args.walk.progress == Some(ProgressFormat::Bar) {
+        args.output.reads.progress_bar = Some(args.walk.units.format());
+    }
+    if args.output.keyed {
+        check_keyed(&args)?;
+    }
+    if let Some(length) = args.output.length {
+        check_length(&args, length)?;
+        args.output.reads.output_size = Some(length);
+    }
+    check_encoding(&args)?;
+    check_stdin(&args)?;
+
+    if args.input.len() > 1 {
+        return process_inputs(&args);
+    }
+    if args.eip55 {
+        return process_eip55(&args);
+    }
+    if args.check_stored {
+        return check_stored(&args);
+    }
+
+    if !args.output.quiet && !args.recursive && args.csv.columns.is_empty() {
+        warn_if_ambiguous(args.input(), args.mode.policy());
+        if let Some(ref input2) = args.compare {
+            warn_if_ambiguous(input2, args.mode.policy());
+        }
+    }
+
+    let key = args.output.key.load(args.output.quiet)?;
+    let key = Key::new(key.as_deref(), args.output.keyed);
+
+    // CSV column mode if requested
+    if !args.csv.columns.is_empty() {
+        return process_csv(&args, key);
+    }
+
+    // Compare mode if requested
+    if let Some(ref input2) = args.compare {
+        return compare_inputs(args.input(), input2, &args, key);
+    }
+
+    // Recursive mode if requested
+    if args.recursive {
+        return process_recursive(&args, key);
+    }
+
+    // Process input
+    if args.output.all_algorithms {
+        process_all_algorithms(&args, key)?;
+    } else {
+        process_single_algorithm(&args, key)?;
+    }
+
+    Ok(())
+}
+
+/// Several INPUTs: each is hashed with the one algorithm, up to `--jobs`
+/// at once, and listed in the order given
+fn process_inputs(args: &HashArgs) -> Result<()> {
+    let single = [
+        (args.verify.is_some(), "--verify"),
+        (args.compare.is_some(), "--compare"),
+        (args.recursive, "--recursive"),
+        (args.check_stored, "--check-stored"),
+        (args.walk.progress.is_some(), "--progress"),
+        (!args.csv.columns.is_empty(), "--csv-column"),
+        (args.eip55, "--eip55"),
+        (args.output.all_algorithms, "--all-algorithms"),
+        (args.output.canonical.is_some(), "--canonical"),
+        (args.output.encoding == Encoding::Binary, "--encoding binary"),
+    ];
+    if let Some((_, flag)) = single.iter().find(|(set, _)| *set) {
+        return Err(usage_error(format!("{} takes a single INPUT", flag)));
+    }
+    // Several INPUTs name files (or -), not strings, unless --string says so
+    let policy = match args.mode.policy() {
+        InputPolicy::Auto => InputPolicy::File,
+        policy => policy,
+    };
+    let key = args.output.key.load(args.output.quiet)?;
+    let key = Key::new(key.as_deref(), args.output.keyed);
+
+    let algorithm = args.output.algorithm;
+    let template = output_template(args)?;
+    let reads = args.output.reads;
+    let jobs = reads.jobs.unwrap_or_else(checksum::default_jobs);
+    let hash = |input: &str| {
+        let strip_newline = args.output.strip_newline;
+        compute_hash(input, algorithm, policy, key, None, strip_newline, reads)
+    };
+
+    // Exports and JSON arrays are written once every input is hashed;
+    // other output as each one is
+    let json = template.is_none() && matches!(args.output.format, ExportFormat::Json);
+    let streamed = args.output.export.is_none() && !json;
+    let mut results = Vec::new();
+    let mut lines = Vec::new();
+    let mut errors = 0usize;
+    in_input_order(&args.input, jobs, hash, |input, hashed| {
+        let (digest, input_type, input_path) = match hashed {
+            Ok(hashed) => hashed,
+            Err(e) => {
+                match e.chain().nth(1) {
+                    Some(_) => eprintln!("Error: {}: {}", e, e.root_cause()),
+                    None => eprintln!("Error: {}", e),
+                }
+                errors += 1;
+                return Ok(());
+            }
+        };
+        let digest = encode_digest(&digest, algorithm, args.output.encoding)?;
+        let mut result = new_result(algorithm, key, digest, &input_type);
+        if let Some(ref path) = input_path {
+            result = result.with_path(path);
+        }
+        let line = match (&template, &args.output.format) {
+            (Some(template), _) => {
+                template.render(&result, input_size(input, &input_type, input_path.as_deref()))
+            }
+            (None, ExportFormat::Jsonl) => result.to_json_line()?,
+            (None, ExportFormat::Ni | ExportFormat::Cosign) => {
+                format!("{}  {}", styled_digest(&result, &args.output.format)?, input)
+            }
+            (None, _) => format!("{}  {}", result.digest, input),
+        };
+        if streamed {
+            println!("{}", line);
+        } else {
+            lines.push(line);
+        }
+        results.push(result);
+        Ok(())
+    })?;
+
+    match (&args.output.export, &args.output.format_plugin) {
+        (Some(export_path), Some(name)) => {
+            export_with_plugin(&results, export_path, name)?;
+            println!("Exported all results to: {}", export_path.display());
+        }
+        (Some(export_path), None) if json => {
+            export_all_results(&results, export_path, &args.output.format)?
+        }
+        (Some(export_path), None) => {
+            export_lines(&lines, export_path)?;
+            if !args.output.quiet {
+                println!("Exported all results to: {}", export_path.display());
+            }
+        }
+        (None, _) if json => {
+            let json = serde_json::to_string_pretty(&results)
+                .context("Failed to serialize results to JSON")?;
+            println!("{}", json);
+        }
+        (None, _) => {}
+    }
+
+    if errors > 0 {
+        ExitStatus::Io.exit();
+    }
+    Ok(())
+}
+
+/// Run `hash` over `inputs` on up to `jobs` threads, passing the results
+/// to `report` in the order of `inputs`; an error from `report` stops
+/// the remaining inputs from being started
+fn in_input_order<T, H, R>(inputs: &[String], jobs: usize, hash: H, mut report: R) -> Result<()>
+where
+    T: Send,
+    H: Fn(&str) -> T + Sync,
+    R: FnMut(&str, T) -> Result<()>,
+{
+    let jobs = jobs.clamp(1, inputs.len().max(1));
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+    thread::scope(|scope| {
+        for _ in 0..jobs {
+            let sender = sender.clone();
+            let (next, hash) = (&next, &hash);
+            scope.spawn(move || loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(input) = inputs.get(index) else {
+                    break;
+                };
+                if sender.send((index, hash(input))).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+
+        let mut pending = BTreeMap::new();
+        let mut reported = 0;
+        for (index, hashed) in receiver {
+            pending.insert(index, hashed);
+            while let Some(hashed) = pending.remove(&reported) {
+                report(&inputs[reported], hashed)?;
+                reported += 1;
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Reject combinations that would read standard input twice
+fn check_stdin(args: &HashArgs) -> Result<()> {
+    let policy = args.mode.policy();
+    let inputs = args.input.iter().map(String::as_str).chain(args.compare.as_deref());
+    let from_stdin = inputs.filter(|input| policy.resolve(input) == InputKind::Stdin).count();
+    if from_stdin > 1 {
+        return Err(usage_error("only one input can be read from stdin"));
+    }
+    if from_stdin == 1 && args.output.key.key_stdin {
+        return Err(usage_error("--key-stdin cannot be used when the input is read from stdin"));
+    }
+    Ok(())
+}
+
+/// Reject `--encoding` where digests are compared, listed per file or
+/// must stay hex for another format
+fn check_encoding(args: &HashArgs) -> Result<()> {
+    let encoding = args.output.encoding;
+    if encoding == Encoding::Hex {
+        return Ok(());
+    }
+    if args.recursive
+        || args.compare.is_some()
+        || args.verify.is_some()
+        || !args.csv.columns.is_empty()
+        || args.eip55
+    {
+        return Err(usage_error(
+            "--encoding applies to the digests of a single input, not with -r, -C, -c, \
+             --csv-column or --eip55",
+        ));
+    }
+    if matches!(args.output.format, ExportFormat::Ni | ExportFormat::Cosign) {
+        return Err(usage_error("-f ni and -f cosign set their own digest encoding"));
+    }
+    if encoding == Encoding::Binary {
+        if args.output.all_algorithms {
+            return Err(usage_error("--encoding binary takes a single algorithm, not -A"));
+        }
+        if args.output.export.is_some() || args.output.template.is_some() {
+            return Err(usage_error(
+                "--encoding binary writes to stdout; redirect it instead of --export or --template",
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// `hex`, a digest of `algorithm` as computed, in `encoding`
+fn encode_digest(hex: &str, algorithm: Algorithm, encoding: Encoding) -> Result<String> {
+    match encoding {
+        Encoding::Hex => Ok(hex.to_string()),
+        _ => Ok(digest_bytes(hex, algorithm)?.encode(encoding)?),
+    }
+}
+
+/// A digest computed here, of the algorithm's usual length or `--length`
+fn digest_bytes(hex: &str, algorithm: Algorithm) -> Result<Digest> {
+    let bytes = hex::decode(hex)
+        .map_err(|e| HashError::InvalidInput(format!("invalid hex digest {}: {}", hex, e)))?;
+    Ok(Digest::new(algorithm, bytes)?)
+}
+
+/// Reject `--keyed` without a key, or with a mode that computes HMACs only
+fn check_keyed(args: &HashArgs) -> Result<()> {
+    if args.output.key.source().is_none() {
+        return Err(usage_error(
+            "--keyed needs a key: pass --key, --key-file, --key-hex, --key-env or --key-stdin",
+        ));
+    }
+    let hmac_only = [
+        (args.recursive, "--recursive"),
+        (!args.csv.columns.is_empty(), "--csv-column"),
+        (args.check_stored, "--check-stored"),
+    ];
+    match hmac_only.iter().find(|(set, _)| *set) {
+        Some((_, flag)) => Err(usage_error(format!("--keyed cannot be used with {}", flag))),
+        None => Ok(()),
+    }
+}
+
+/// Reject a `--length` the algorithm cannot produce, or one given with a
+/// mode that always uses the algorithm's usual length
+fn check_length(args: &HashArgs, length: usize) -> Result<()> {
+    let algorithm = args.output.algorithm;
+    let sizes = algorithm.output_sizes();
+    if !sizes.contains(&length) {
+        return Err(usage_error(if sizes.start() == sizes.end() {
+            format!(
+                "{} digests are always {} bytes; drop --length",
+                algorithm.name(),
+                sizes.start()
+            )
+        } else if length == 0 {
+            "--length must be at least 1".to_string()
+        } else {
+            format!("{} digests are at most {} bytes", algorithm.name(), sizes.end())
+        }));
+    }
+    let fixed = [
+        (args.recursive, "--recursive"),
+        (!args.csv.columns.is_empty(), "--csv-column"),
+        (args.check_stored, "--check-stored"),
+        (args.output.key.source().is_some() && !args.output.keyed, "an HMAC key"),
+    ];
+    match fixed.iter().find(|(set, _)| *set) {
+        Some((_, what)) => Err(usage_error(format!("--length cannot be used with {}", what))),
+        None => Ok(()),
+    }
+}
+
+fn list_algorithms() {
+    println!("Available hash algorithms:");
+    println!();
+    
+    for algorithm in Algorithm::all() {
+        println!(
+            "  {:<15} {:<15} {}",
+            algorithm.display_name(),
+            algorithm.name(),
+            algorithm.description()
+        );
+    }
+
+    let mut user_aliases = aliases().user_aliases().peekable();
+    if user_aliases.peek().is_some() {
+        println!();
+        println!("Aliases from the config file:");
+        println!();
+        for (alias, algorithm) in user_aliases {
+            println!("  {:<15} {}", alias, algorithm.name());
+        }
+    }
+
+    let plugins = plugin::list_plugins(configured_formats());
+    if !plugins.is_empty() {
+        println!();
+        println!("Export-format plugins (--format-plugin):");
+        println!();
+        for plugin in plugins {
+            println!("  {:<15} {}", plugin.name(), plugin.program().display());
+        }
+    }
+}
+
+/// Verify the files a checksum list names, printing `path: OK` or
+/// `path: FAILED` per entry as `sha256sum -c` does
+///
+/// The algorithm is the one the first BSD-style line names, else the one
+/// the list's file name implies (`SHA512SUMS`, `app.md5`), else `-a`.
+/// Listed paths are relative to `--root`, by default the current directory.
+/// Improperly formatted lines are skipped and counted, failing the check
+/// only with `--strict`. Nothing is written: the list may sit on read-only
+/// media or a server. Returns the exit status the list alone would give.
+fn check_list(list: &str, args: &HashArgs) -> Result<ExitStatus> {
+    let source: Box<dyn Read + Send> = if let Some(ref signature) = args.signature {
+        Box::new(io::Cursor::new(read_signed_list(list, signature, args)?))
+    } else if list == "-" {
+        Box::new(io::stdin())
+    } else if checksum::is_url(list) {
+        Box::new(io::Cursor::new(read_list_source(list)?))
+    } else {
+        let file = File::open(list)
+            .with_context(|| format!("Failed to open checksum file: {}", list))?;
+        Box::new(file)
+    };
+    let mut reader = io::BufReader::with_capacity(CHECK_BUFFER_SIZE, source);
+    if seal::is_sealed(reader.fill_buf()?) {
+        let mut sealed = Vec::new();
+        reader.read_to_end(&mut sealed)?;
+        let passphrase = read_passphrase(args.walk.passphrase_env.as_deref(), false)?;
+        let plain = seal::open(&sealed, &passphrase)
+            .with_context(|| format!("Failed to decrypt checksum file: {}", list))?;
+        reader = io::BufReader::with_capacity(CHECK_BUFFER_SIZE, Box::new(io::Cursor::new(plain)));
+    }
+    let head = String::from_utf8_lossy(reader.fill_buf()?).into_owned();
+    let tagged = head
+        .lines()
+        .find(|line| checksum::parse_line(line).is_some())
+        .and_then(checksum::tagged_algorithm);
+    let algorithm = tagged
+        .or_else(|| manifest::algorithm_from_file_name(list))
+        .unwrap_or(args.output.algorithm);
+
+    let quiet = args.output.quiet;
+    let report = |entry: &ChecksumEntry, line: usize, status: &CheckStatus| {
+        let verdict = match status {
+            CheckStatus::Malformed => {
+                if args.warn {
+                    eprintln!(
+                        "{}: {}: improperly formatted {} checksum line",
+                        list,
+                        line,
+                        algorithm.name().to_uppercase()
+                    );
+                }
+                return;
+            }
+            CheckStatus::Ok | CheckStatus::Cached if quiet => return,
+            CheckStatus::Ok | CheckStatus::Cached => "OK",
+            CheckStatus::Failed => "FAILED",
+            CheckStatus::Missing if args.ignore_missing => return,
+            CheckStatus::Missing => "FAILED open or read",
+            CheckStatus::Unreadable(message) => {
+                eprintln!("{}: {}", entry.path, message);
+                "FAILED open or read"
+            }
+        };
+        // Like `sha256sum -c`, only names with line breaks come out escaped
+        let (prefix, shown) = match entry.path.contains(['\n', '\r']) {
+            true => checksum::escape_name(&entry.path),
+            false => ("", entry.path.clone()),
+        };
+        println!("{}{}: {}", prefix, shown, verdict);
+    };
+    let root = args.root.as_deref().unwrap_or(Path::new("."));
+    let job = VerifyJob::from_reader(reader)
+        .with_algorithm(algorithm)
+        .with_base(root)
+        .spawn();
+    let mut summary = CheckSummary::default();
+    for event in job.events() {
+        match event {
+            JobEvent::Checked {
+                entry,
+                line,
+                status,
+            } => {
+                summary.add(&status);
+                report(&entry, line, &status);
+            }
+            JobEvent::Failed { message, .. } => {
+                let err = HashError::Io(io::Error::new(io::ErrorKind::Other, message));
+                return Err(err).with_context(|| format!("Failed to check {}", list));
+            }
+            _ => {}
+        }
+    }
+
+    let missing = if args.ignore_missing { 0 } else { summary.missing };
+    let verified = summary.ok + summary.failed + summary.unreadable;
+    if verified + summary.missing == 0 {
+        let message = format!("no properly formatted checksum lines found in {}", list);
+        return Err(HashError::InvalidInput(message).into());
+    }
+    if verified == 0 && args.ignore_missing {
+        eprintln!("warning: {}: no file was verified", list);
+        return Ok(ExitStatus::Io);
+    }
+    let plural = |count: usize, noun: &str| match count {
+        1 => format!("1 {}", noun),
+        _ => format!("{} {}s", count, noun),
+    };
+    if summary.malformed > 0 {
+        let lines = match summary.malformed {
+            1 => "1 line is".to_string(),
+            count => format!("{} lines are", count),
+        };
+        eprintln!("warning: {} improperly formatted", lines);
+    }
+    if summary.failed > 0 {
+        eprintln!(
+            "warning: {} did NOT match",
+            plural(summary.failed, "computed checksum")
+        );
+    }
+    if missing + summary.unreadable > 0 {
+        eprintln!(
+            "warning: {} could not be read",
+            plural(missing + summary.unreadable, "listed file")
+        );
+    }
+    if summary.failed > 0 {
+        return Ok(ExitStatus::Mismatch);
+    }
+    if missing + summary.unreadable > 0 {
+        return Ok(ExitStatus::Io);
+    }
+    if args.strict && summary.malformed > 0 {
+        return Ok(ExitStatus::Mismatch);
+    }
+    Ok(ExitStatus::Success)
+}
+
+/// The bytes of a checksum list or signature: a file, a URL or stdin (`-`)
+fn read_list_source(source: &str) -> Result<Vec<u8>> {
+    if source == "-" {
+        let mut data = Vec::new();
+        io::stdin().lock().read_to_end(&mut data).context("Failed to read stdin")?;
+        Ok(data)
+    } else if checksum::is_url(source) {
+        let text =
+            checksum::read_source(source).with_context(|| format!("Failed to fetch {}", source))?;
+        Ok(text.into_bytes())
+    } else {
+        fs::read(source).with_context(|| format!("Failed to read {}", source))
+    }
+}
+
+/// A checksum list whose detached signature was made by a trusted key;
+/// exits with the mismatch status before any file is read otherwise
+fn read_signed_list(list: &str, signature: &str, args: &HashArgs) -> Result<Vec<u8>> {
+    let mut trust = TrustStore::from_files(configured_trusted_keys())
+        .context("Invalid [trust.keys] in config file")?;
+    for path in &args.trusted_key {
+        trust.add_file(path.display().to_string(), path)?;
+    }
+    if trust.is_empty() {
+        return Err(usage_error(
+            "--signature needs a trusted key: pass --trusted-key or add [trust.keys] to the config",
+        ));
+    }
+
+    let manifest = read_list_source(list)?;
+    let signed = signing_key(&trust, &manifest, &read_list_source(signature)?)
+        .with_context(|| format!("Failed to verify the signature of {}", list))?;
+    match signed {
+        Some(name) if !args.output.quiet => {
+            println!("✓ {}: signed by trusted key {}", list, name)
+        }
+        Some(_) => {}
+        None => {
+            eprintln!("✗ {}: not signed by a trusted key, or modified since", list);
+            ExitStatus::Mismatch.exit();
+        }
+    }
+    Ok(manifest)
+}
+
+/// Name of the trusted key that made `signature` over `manifest`
+#[cfg(feature = "sigstore")]
+fn signing_key(trust: &TrustStore, manifest: &[u8], signature: &[u8]) -> Result<Option<String>> {
+    Ok(trust.verify(manifest, signature)?.map(|key| key.name.clone()))
+}
+
+#[cfg(not(feature = "sigstore"))]
+fn signing_key(_trust: &TrustStore, _manifest: &[u8], _signature: &[u8]) -> Result<Option<String>> {
+    Err(usage_error("--signature requires a build with the `sigstore` feature"))
+}
+
+fn process_single_algorithm(args: &HashArgs, key: Option<Key>) -> Result<()> {
+    let algorithm = args.output.algorithm;
+    let template = output_template(args)?;
+
+    // Check the expected digest before spending time on the input
+    let expected = match &args.verify {
+        Some(source) => {
+            let expected = checksum::normalize_expected(&resolve_expected(source, args.input())?);
+            if let Some(tagged) = expected.algorithm.filter(|&tagged| tagged != algorithm) {
+                return Err(usage_error(format!(
+                    "expected digest is labelled {} but the algorithm is {}; pass -a {}",
+                    tagged.name(),
+                    algorithm.name(),
+                    tagged.name()
+                )));
+            }
+            // A digest of the wrong length cannot match; say why instead
+            match args.output.length {
+                Some(length) if expected.hex.len() != length * 2 => {
+                    return Err(usage_error(format!(
+                        "expected digest has {} hex characters but --length {} gives {}",
+                        expected.hex.len(),
+                        length,
+                        length * 2
+                    )));
+                }
+                Some(_) => {}
+                None => {
+                    Digest::parse_for(&expected.hex, algorithm)?;
+                }
+            }
+            Some(expected)
+        }
+        None => None,
+    };
+
+    let (digest, input_type, input_path) = compute_hash(
+        args.input(),
+        algorithm,
+        args.mode.policy(),
+        key,
+        args.output.canonical,
+        args.output.strip_newline,
+        args.output.reads,
+    )?;
+
+    // Verify if requested
+    if let Some(expected) = expected {
+        let matches = expected.hex == digest;
+        if args.output.quiet {
+            if matches {
+                ExitStatus::Success
+            } else {
+                ExitStatus::Mismatch
+            }
+            .exit();
+        } else if matches {
+            println!("✓ Hash verification PASSED");
+            println!("{}: {}", algorithm_label(algorithm, key), digest);
+        } else {
+            eprintln!("✗ Hash verification FAILED");
+            eprintln!("Expected: {}", expected.hex);
+            eprintln!("Got:      {}", digest);
+            ExitStatus::Mismatch.exit();
+        }
+        return Ok(());
+    }
+
+    let encoding = args.output.encoding;
+    if encoding == Encoding::Binary {
+        let bytes = digest_bytes(&digest, algorithm)?.into_bytes();
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(&bytes)?;
+        stdout.flush()?;
+        return Ok(());
+    }
+    let digest = encode_digest(&digest, algorithm, encoding)?;
+
+    let mut result = new_result(algorithm, key, digest.clone(), &input_type);
+    if let Some(path) = input_path.as_deref() {
+        result = result.with_path(path);
+    }
+    if let Some(template) = template {
+        let size = input_size(args.input(), &input_type, input_path.as_deref());
+        let line = template.render(&result, size);
+        println!("{}", line);
+        if let Some(export_path) = &args.output.export {
+            export_lines(&[line], export_path)?;
+            if !args.output.quiet {
+                println!("Exported to: {}", export_path.display());
+            }
+        }
+        return Ok(());
+    }
+    let shown = match args.output.format {
+        ExportFormat::Ni | ExportFormat::Cosign => styled_digest(&result, &args.output.format)?,
+        _ => digest,
+    };
+
+    // Display result
+    if args.output.quiet {
+        println!("{}", shown);
+    } else {
+        display_result(algorithm, key, &shown, &input_type, input_path.as_deref());
+    }
+
+    // Export if requested
+    match (&args.output.export, &args.output.format_plugin) {
+        (Some(export_path), Some(name)) => {
+            export_with_plugin(std::slice::from_ref(&result), export_path, name)?;
+            println!("Exported to: {}", export_path.display());
+        }
+        (Some(export_path), None) => export_result(&result, export_path, &args.output.format)?,
+        (None, _) => {}
+    }
+
+    Ok(())
+}
+
+/// Print the EIP-55 form of an address or of a public key's address
+///
+/// A mixed-case address is a checksummed one; it is verified and a wrong
+/// checksum exits with the mismatch status.
+fn process_eip55(args: &HashArgs) -> Result<()> {
+    let input = args.input().trim();
+    let hex_part = input.strip_prefix("0x").unwrap_or(input);
+
+    if hex_part.len() == 128 || hex_part.len() == 130 {
+        let public_key = hex::decode(hex_part)
+            .map_err(|e| HashError::InvalidInput(format!("invalid public key: {}", e)))?;
+        let address = ethereum::address_from_public_key(&public_key)?;
+        if args.output.quiet {
+            println!("{}", address);
+        } else {
+            println!("Public key: {}", input);
+            println!("Address:    {}", address);
+        }
+        return Ok(());
+    }
+
+    let address = ethereum::to_checksum_address(&input.to_ascii_lowercase())?;
+    let checksummed = hex_part.bytes().any(|b| b.is_ascii_lowercase())
+        && hex_part.bytes().any(|b| b.is_ascii_uppercase());
+    if checksummed && address[2..] != *hex_part {
+        if !args.output.quiet {
+            eprintln!("✗ EIP-55 checksum INVALID");
+            eprintln!("Expected: {}", address);
+            eprintln!("Got:      {}", input);
+        }
+        ExitStatus::Mismatch.exit();
+    }
+
+    if args.output.quiet {
+        println!("{}", address);
+    } else {
+        if checksummed {
+            println!("✓ EIP-55 checksum valid");
+        }
+        println!("Address: {}", address);
+    }
+    Ok(())
+}
+
+/// Stream CSV from the input to stdout or the export file, hashing the selected columns
+fn process_csv(args: &HashArgs, key: Option<Key>) -> Result<()> {
+    if args.recursive
+        || args.compare.is_some()
+        || args.verify.is_some()
+        || args.output.all_algorithms
+        || args.output.canonical.is_some()
+    {
+        return Err(usage_error(
+            "--csv-column cannot be combined with -r, -C, -c, -A or --canonical",
+        ));
+    }
+
+    let input = args.input();
+    if input == "-" && args.output.key.key_stdin {
+        return Err(usage_error("--key-stdin cannot be used when the CSV is read from stdin"));
+    }
+    let delimiter = match args.csv.delimiter {
+        Some(delimiter) if delimiter.is_ascii() => delimiter as u8,
+        Some(delimiter) => {
+            return Err(usage_error(format!("delimiter must be ASCII, got {:?}", delimiter)))
+        }
+        None => delimiter_for_path(input),
+    };
+
+    let mut options = CsvHashOptions::new(args.output.algorithm)
+        .with_delimiter(delimiter)
+        .with_headers(!args.csv.no_header);
+    options.columns = args.csv.columns.clone();
+    if let Some(salt) = key {
+        options = options.with_salt(salt.bytes);
+    }
+
+    let reader: Box<dyn Read> = if input == "-" {
+        Box::new(io::stdin().lock())
+    } else {
+        let file = File::open(input).with_context(|| format!("Failed to open: {}", input))?;
+        Box::new(file)
+    };
+
+    match &args.output.export {
+        Some(path) => {
+            let file = File::create(path)
+                .with_context(|| format!("Failed to write to file: {}", path.display()))?;
+            let rows = hash_csv_columns(reader, BufWriter::new(file), &options)?;
+            if !args.output.quiet {
+                println!("Hashed {} rows, exported to: {}", rows, path.display());
+            }
+        }
+        None => {
+            hash_csv_columns(reader, io::stdout().lock(), &options)?;
+        }
+    }
+    Ok(())
+}
+
+fn process_all_algorithms(args: &HashArgs, key: Option<Key>) -> Result<()> {
+    let mut results = Vec::new();
+    let template = output_template(args)?;
+    let listed = !args.output.quiet && template.is_none();
+    let algorithms = selected_algorithms(key, &args.output.families)?;
+    let width = label_width(&algorithms, key);
+
+    if listed {
+        println!("Computing hashes for all algorithms...");
+    }
+
+    let (digests, input_type, input_path) = compute_hashes(
+        args.input(),
+        &algorithms,
+        args.mode.policy(),
+        key,
+        args.output.canonical,
+        args.output.strip_newline,
+        args.output.reads,
+    )?;
+
+    let mut family = None;
+    for (algorithm, digest) in algorithms.into_iter().zip(digests) {
+        let digest = encode_digest(&digest, algorithm, args.output.encoding)?;
+        if listed {
+            if family != Some(algorithm.family()) {
+                family = Some(algorithm.family());
+                println!();
+                println!("{}", algorithm.family().display_name());
+            }
+            let label = format!("{}:", algorithm_label(algorithm, key));
+            println!("  {:<width$} {}", label, digest, width = width);
+        }
+
+        let mut result = new_result(algorithm, key, digest, &input_type);
+        if let Some(ref path) = input_path {
+            result = result.with_path(path);
+        }
+        results.push(result);
+    }
+
+    if let Some(template) = template {
+        let input_path = results.first().and_then(|result| result.input_path.as_deref());
+        let size = input_size(args.input(), &input_type, input_path);
+        let lines: Vec<String> =
+            results.iter().map(|result| template.render(result, size)).collect();
+        for line in &lines {
+            println!("{}", line);
+        }
+        if let Some(export_path) = &args.output.export {
+            export_lines(&lines, export_path)?;
+            if !args.output.quiet {
+                println!("Exported all results to: {}", export_path.display());
+            }
+        }
+        return Ok(());
+    }
+
+    // Export if requested
+    match (&args.output.export, &args.output.format_plugin) {
+        (Some(export_path), Some(name)) => {
+            export_with_plugin(&results, export_path, name)?;
+            println!("Exported all results to: {}", export_path.display());
+        }
+        (Some(export_path), None) => {
+            export_all_results(&results, export_path, &args.output.format)?
+        }
+        (None, _) => {}
+    }
+
+    Ok(())
+}
+
+fn compute_hash(
+    input: &str,
+    algorithm: Algorithm,
+    policy: InputPolicy,
+    key: Option<Key>,
+    canonical: Option<CanonicalArg>,
+    strip_newline: bool,
+    reads: ReadArgs,
+) -> Result<(String, String, Option<String>)> {
+    if let Some(remote) = remote_input(input, policy, canonical) {
+        let (reader, input_type) = remote?;
+        let digest = reads
+            .hashing_with_bar(algorithm, key, None)
+            .hash_reader(reader)
+            .with_context(|| format!("Failed to hash {}", input))?;
+        return Ok((digest, input_type.to_string(), Some(input.to_string())));
+    }
+    let kind = policy.resolve(input);
+    let text = string_input(input, kind, strip_newline);
+    let (input_type, input_path) = match kind {
+        InputKind::File => ("file".to_string(), Some(input.to_string())),
+        InputKind::String => ("string".to_string(), None),
+        InputKind::Stdin => ("stdin".to_string(), None),
+    };
+    let hashing = || reads.hashing(algorithm, key);
+
+    if let Some(canonical) = canonical {
+        let document = canonical_bytes(text, kind, canonical)?;
+        let digest = hashing()
+            .hash_bytes(&document)
+            .with_context(|| format!("Failed to hash document: {}", input))?;
+        return Ok((digest, input_type, input_path));
+    }
+
+    match kind {
+        InputKind::File => {
+            let size = fs::metadata(input).ok().map(|metadata| metadata.len());
+            let digest = reads
+                .hashing_with_bar(algorithm, key, size)
+                .hash_file(input)
+                .with_context(|| format!("Failed to hash file: {}", input))?;
+            Ok((digest, "file".to_string(), Some(input.to_string())))
+        }
+        InputKind::String => {
+            let digest = hashing()
+                .hash_string(text)
+                .with_context(|| "Failed to hash string")?;
+            Ok((digest, "string".to_string(), None))
+        }
+        InputKind::Stdin => {
+            let digest = reads
+                .hashing_with_bar(algorithm, key, None)
+                .hash_reader(stdin_reader(strip_newline))
+                .with_context(|| "Failed to hash stdin")?;
+            Ok((digest, "stdin".to_string(), None))
+        }
+    }
+}
+
+/// Digests of `input` under each of `algorithms`, read once unless a
+/// canonical form or a self-check calls for one pass per algorithm
+fn compute_hashes(
+    input: &str,
+    algorithms: &[Algorithm],
+    policy: InputPolicy,
+    key: Option<Key>,
+    canonical: Option<CanonicalArg>,
+    strip_newline: bool,
+    reads: ReadArgs,
+) -> Result<(Vec<String>, String, Option<String>)> {
+    let multi = match canonical {
+        Some(_) => None,
+        None => reads.multi_hasher(algorithms, key),
+    };
+    let Some(multi) = multi else {
+        if policy.resolve(input) == InputKind::Stdin {
+            return stdin_hashes(algorithms, key, canonical, strip_newline, reads);
+        }
+        let mut digests = Vec::with_capacity(algorithms.len());
+        let mut input_type = String::new();
+        let mut input_path = None;
+        for &algorithm in algorithms {
+            let digest;
+            (digest, input_type, input_path) =
+                compute_hash(input, algorithm, policy, key, canonical, strip_newline, reads)?;
+            digests.push(digest);
+        }
+        return Ok((digests, input_type, input_path));
+    };
+
+    let hex = |digests: Vec<Digest>| digests.iter().map(|d| d.to_hex()).collect();
+    if let Some(remote) = remote_input(input, policy, canonical) {
+        let (reader, input_type) = remote?;
+        let digests = match reads.bar(None) {
+            Some(mut bar) => multi.hash_reader_with_progress(reader, |bytes| bar.update(bytes)),
+            None => multi.hash_reader(reader),
+        };
+        let digests = digests.with_context(|| format!("Failed to hash {}", input))?;
+        return Ok((hex(digests), input_type.to_string(), Some(input.to_string())));
+    }
+    let kind = policy.resolve(input);
+    match kind {
+        InputKind::File => {
+            let size = fs::metadata(input).ok().map(|metadata| metadata.len());
+            let digests = match reads.bar(size) {
+                Some(mut bar) => multi.hash_file_with_progress(input, |bytes| bar.update(bytes)),
+                None => multi.hash_file(input),
+            };
+            let digests = digests.with_context(|| format!("Failed to hash file: {}", input))?;
+            Ok((hex(digests), "file".to_string(), Some(input.to_string())))
+        }
+        InputKind::String => {
+            let digests = multi
+                .hash_bytes(string_input(input, kind, strip_newline).as_bytes())
+                .with_context(|| "Failed to hash string")?;
+            Ok((hex(digests), "string".to_string(), None))
+        }
+        InputKind::Stdin => {
+            let stdin = stdin_reader(strip_newline);
+            let digests = match reads.bar(None) {
+                Some(mut bar) => multi.hash_reader_with_progress(stdin, |bytes| bar.update(bytes)),
+                None => multi.hash_reader(stdin),
+            };
+            let digests = digests.with_context(|| "Failed to hash stdin")?;
+            Ok((hex(digests), "stdin".to_string(), None))
+        }
+    }
+}
+
+/// The object or remote file `input` names, opened for reading, with its
+/// input type; `None` unless it is an s3://, gs://, az://, sftp:// or
+/// ssh:// URL not meant as a plain string
+fn remote_input(
+    input: &str,
+    policy: InputPolicy,
+    canonical: Option<CanonicalArg>,
+) -> Option<Result<(Box<dyn Read>, &'static str)>> {
+    if policy == InputPolicy::String {
+        return None;
+    }
+    let object = cloud::is_object_url(input);
+    if !object && !ssh::is_remote_url(input) {
+        return None;
+    }
+    if canonical.is_some() {
+        return Some(Err(usage_error(format!("--canonical cannot read {}", input))));
+    }
+    Some(match object {
+        true => open_object(input).map(|reader| (reader, "object")),
+        false => open_remote(input).map(|reader| (reader, "remote")),
+    })
+}
+
+#[cfg(feature = "cloud")]
+fn open_object(input: &str) -> Result<Box<dyn Read>> {
+    let url: cloud::ObjectUrl = input.parse()?;
+    Ok(Box::new(cloud::open_object(&url)?))
+}
+
+#[cfg(not(feature = "cloud"))]
+fn open_object(_input: &str) -> Result<Box<dyn Read>> {
+    Err(usage_error(
+        "hashing objects in cloud storage requires a build with the `cloud` feature",
+    ))
+}
+
+#[cfg(feature = "ssh")]
+fn open_remote(input: &str) -> Result<Box<dyn Read>> {
+    let remote: ssh::RemotePath = input.parse()?;
+    Ok(Box::new(ssh::open_remote(&remote)?))
+}
+
+#[cfg(not(feature = "ssh"))]
+fn open_remote(_input: &str) -> Result<Box<dyn Read>> {
+    Err(usage_error("hashing files over SSH requires a build with the `ssh` feature"))
+}
+
+/// `--check-stored`: hash an object and compare it with the checksums its
+/// provider stores, reading it once
+#[cfg(feature = "cloud")]
+fn check_stored(args: &HashArgs) -> Result<()> {
+    let input = args.input();
+    if !cloud::is_object_url(input) {
+        return Err(usage_error("--check-stored needs an s3://, gs:// or az:// INPUT"));
+    }
+    let url: cloud::ObjectUrl = input.parse()?;
+    let algorithm = args.output.algorithm;
+    let object = cloud::hash_object(&url, algorithm)
+        .with_context(|| format!("Failed to hash object: {}", input))?;
+    let Some(matches) = object.stored_ok() else {
+        return Err(HashError::InvalidInput(format!(
+            "{}: the provider stores no whole-object checksum to compare with",
+            input
+        ))
+        .into());
+    };
+    if !args.output.quiet {
+        println!("{}: {}", algorithm.name(), object.digest);
+        for (stored, ok) in &object.stored {
+            let mark = if *ok { "✓" } else { "✗" };
+            println!(
+                "{} stored {} ({}): {}",
+                mark,
+                stored.algorithm.name(),
+                stored.header,
+                hex::encode(&stored.digest)
+            );
+        }
+    }
+    if !matches {
+        if !args.output.quiet {
+            eprintln!("✗ {} does not match its stored checksum", input);
+        }
+        ExitStatus::Mismatch.exit();
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "cloud"))]
+fn check_stored(_args: &HashArgs) -> Result<()> {
+    Err(usage_error("--check-stored requires a build with the `cloud` feature"))
+}
+
+/// Digests of stdin with one pass per algorithm, over a copy in memory
+/// since stdin can only be read once
+fn stdin_hashes(
+    algorithms: &[Algorithm],
+    key: Option<Key>,
+    canonical: Option<CanonicalArg>,
+    strip_newline: bool,
+    reads: ReadArgs,
+) -> Result<(Vec<String>, String, Option<String>)> {
+    let mut data = Vec::new();
+    stdin_reader(strip_newline).read_to_end(&mut data).context("Failed to read stdin")?;
+    if let Some(canonical) = canonical {
+        let text = String::from_utf8(data).context("stdin is not a UTF-8 JSON document")?;
+        data = canonical_bytes(&text, InputKind::String, canonical)?;
+    }
+    let mut digests = Vec::with_capacity(algorithms.len());
+    for &algorithm in algorithms {
+        let digest = reads
+            .hashing(algorithm, key)
+            .hash_bytes(&data)
+            .with_context(|| "Failed to hash stdin")?;
+        digests.push(digest);
+    }
+    Ok((digests, "stdin".to_string(), None))
+}
+
+/// Standard input, streamed as it arrives; `--strip-newline` holds back
+/// its final line ending
+fn stdin_reader(strip_newline: bool) -> Box<dyn Read> {
+    let stdin = io::stdin().lock();
+    if strip_newline {
+        Box::new(newline::StripTrailingNewline::new(stdin))
+    } else {
+        Box::new(stdin)
+    }
+}
+
+/// `input` as hashed: a string loses its trailing newline with
+/// `--strip-newline`, while a file name is left alone and the file hashed
+/// as it is
+fn string_input(input: &str, kind: InputKind, strip_newline: bool) -> &str {
+    match kind {
+        InputKind::String if strip_newline => newline::strip_trailing(input),
+        _ => input,
+    }
+}
+
+/// Re-encode a structured document canonically before hashing
+///
+/// Files are parsed according to their extension (`.cbor`, `.msgpack`,
+/// `.mpk`, anything else as JSON); string inputs are parsed as JSON.
+#[cfg(feature = "serde-hash")]
+fn canonical_bytes(input: &str, kind: InputKind, canonical: CanonicalArg) -> Result<Vec<u8>> {
+    use hashing::canonical::{canonicalize_document, CanonicalFormat};
+
+    let (document, from) = match kind {
+        InputKind::File => {
+            let document =
+                fs::read(input).with_context(|| format!("Failed to read file: {}", input))?;
+            let extension = Path::new(input)
+                .extension()
+                .map(|ext| ext.to_string_lossy().to_lowercase());
+            let from = match extension.as_deref() {
+                Some("cbor") => CanonicalFormat::Cbor,
+                Some("msgpack") | Some("mpk") => CanonicalFormat::MessagePack,
+                _ => CanonicalFormat::Json,
+            };
+            (document, from)
+        }
+        InputKind::String => (input.as_bytes().to_vec(), CanonicalFormat::Json),
+        InputKind::Stdin => {
+            let mut document = Vec::new();
+            io::stdin().lock().read_to_end(&mut document).context("Failed to read stdin")?;
+            (document, CanonicalFormat::Json)
+        }
+    };
+    let to = match canonical {
+        CanonicalArg::Json => CanonicalFormat::Json,
+        CanonicalArg::Cbor => CanonicalFormat::Cbor,
+        CanonicalArg::Msgpack => CanonicalFormat::MessagePack,
+    };
+    canonicalize_document(&document, from, to)
+        .with_context(|| format!("Failed to canonicalize document: {}", input))
+}
+
+#[cfg(not(feature = "serde-hash"))]
+fn canonical_bytes(_input: &str, _kind: InputKind, _canonical: CanonicalArg) -> Result<Vec<u8>> {
+    Err(usage_error(
+        "--canonical requires a build with the `serde-hash` feature",
+    ))
+}
+
+/// Display name of the algorithm, prefixed with `HMAC-` or `KEYED-` when keyed
+fn algorithm_label(algorithm: Algorithm, key: Option<Key>) -> String {
+    new_result(algorithm, key, String::new(), "").algorithm.to_uppercase()
+}
+
+/// A result whose `algorithm` field records whether the digest is an HMAC
+/// or a keyed BLAKE digest
+fn new_result(
+    algorithm: Algorithm,
+    key: Option<Key>,
+    digest: String,
+    input_type: &str,
+) -> HashResult {
+    let mut result = HashResult::new(algorithm, digest, input_type);
+    match key {
+        Some(Key { blake: true, .. }) => result.algorithm = format!("keyed-{}", result.algorithm),
+        Some(Key { blake: false, .. }) => result.algorithm = format!("hmac-{}", result.algorithm),
+        None => {}
+    }
+    result
+}
+
+/// The algorithms `--all-algorithms` covers; a key skips those it cannot key
+fn keyed_algorithms(key: Option<Key>) -> Vec<Algorithm> {
+    let mut algorithms = Algorithm::all();
+    if let Some(key) = key {
+        algorithms.retain(|&algorithm| key.supports(algorithm));
+    }
+    algorithms
+}
+
+/// [`keyed_algorithms`] limited to `families`, or all of them when empty
+fn selected_algorithms(
+    key: Option<Key>,
+    families: &[AlgorithmFamily],
+) -> Result<Vec<Algorithm>> {
+    let mut algorithms = keyed_algorithms(key);
+    if !families.is_empty() {
+        algorithms.retain(|algorithm| families.contains(&algorithm.family()));
+    }
+    if algorithms.is_empty() {
+        return Err(usage_error(match key {
+            Some(Key { blake: true, .. }) => {
+                "no algorithm in the selected families has a keyed mode for this key"
+            }
+            _ => "no algorithm in the selected families supports HMAC",
+        }));
+    }
+    Ok(algorithms)
+}
+
+/// Column width that aligns the digests after `LABEL:` for `algorithms`
+fn label_width(algorithms: &[Algorithm], key: Option<Key>) -> usize {
+    algorithms
+        .iter()
+        .map(|&algorithm| algorithm_label(algorithm, key).len() + 1)
+        .max()
+        .unwrap_or_default()
+}
+
+/// Tell the user when an input was taken as a file only because it exists
+fn warn_if_ambiguous(input: &str, policy: InputPolicy) {
+    if policy.is_ambiguous(input) {
+        eprintln!(
+            "warning: '{}' exists, hashing the file; pass --file or --string to be explicit",
+            input
+        );
+    }
+}
+
+/// The passphrase of an encrypted manifest, from `env` or typed at the
+/// terminal; a new passphrase is asked for twice
+fn read_passphrase(env: Option<&str>, confirm: bool) -> Result<Vec<u8>> {
+    if let Some(var) = env {
+        let value = std::env::var(var)
+            .map_err(|_| usage_error(format!("environment variable {} is not set", var)))?;
+        return Ok(value.into_bytes());
+    }
+    let passphrase = rpassword::prompt_password("Manifest passphrase: ")
+        .context("Failed to read the passphrase from the terminal")?;
+    if confirm {
+        let again = rpassword::prompt_password("Repeat passphrase: ")
+            .context("Failed to read the passphrase from the terminal")?;
+        if again != passphrase {
+            return Err(usage_error("the passphrases do not match"));
+        }
+    }
+    Ok(passphrase.into_bytes())
+}
+
+/// Read a string input from `--input-env` or `--prompt`, if requested
+///
+/// Secrets read this way never appear in the process list or shell history.
+fn read_secret_input(args: &HashArgs) -> Result<Option<String>> {
+    if let Some(ref var) = args.input_env {
+        let value = std::env::var(var)
+            .map_err(|_| usage_error(format!("environment variable {} is not set", var)))?;
+        return Ok(Some(value));
+    }
+    if args.prompt {
+        let value = rpassword::prompt_password("Input to hash: ")
+            .context("Failed to read input from the terminal")?;
+        return Ok(Some(value));
+    }
+    Ok(None)
+}
+
+/// Expand `@file`, `@url` and bare URLs into the expected digest for `input`
+fn resolve_expected(source: &str, input: &str) -> Result<String> {
+    let location = match source.strip_prefix('@') {
+        Some(location) => location,
+        None if checksum::is_url(source) => source,
+        None => return Ok(source.to_string()),
+    };
+
+    let contents = checksum::read_source(location)
+        .with_context(|| format!("Failed to read expected digest from {}", location))?;
+    checksum::expected_digest(&contents, input)
+        .with_context(|| format!("Failed to find expected digest in {}", location))
+}
+
+fn display_result(
+    algorithm: Algorithm,
+    key: Option<Key>,
+    digest: &str,
+    input_type: &str,
+    input_path: Option<&str>,
+) {
+    println!("Algorithm:  {}", algorithm_label(algorithm, key));
+    println!("Input type: {}", input_type);
+    if let Some(path) = input_path {
+        println!("File path:  {}", path);
+    }
+    println!("Hash:       {}", digest);
+}
+
+/// The RFC 6920 `ni:///` URI of a plain digest
+fn ni_uri(result: &HashResult) -> Result<String> {
+    let algorithm = result
+        .algorithm
+        .parse::<Algorithm>()
+        .ok()
+        .filter(|&algorithm| naming::ni_algorithm_name(algorithm).is_some())
+        .ok_or_else(|| {
+            usage_error(format!(
+                "--format ni needs sha256, sha384, sha512 or a SHA-3 digest, not {}",
+                result.algorithm
+            ))
+        })?;
+    let digest = hex::decode(&result.digest).context("Failed to decode digest")?;
+    Ok(naming::ni_uri(algorithm, &digest)?)
+}
+
+/// The sigstore `sha256:<hex>` form of a plain digest
+fn cosign_digest(result: &HashResult) -> Result<String> {
+    let algorithm = result
+        .algorithm
+        .parse::<Algorithm>()
+        .ok()
+        .filter(|&algorithm| sigstore::digest_algorithm_name(algorithm).is_some())
+        .ok_or_else(|| {
+            usage_error(format!(
+                "--format cosign needs a sha256, sha384 or sha512 digest, not {}",
+                result.algorithm
+            ))
+        })?;
+    let digest = hex::decode(&result.digest).context("Failed to decode digest")?;
+    Ok(sigstore::blob_digest(algorithm, &digest)?)
+}
+
+/// A digest as written by the formats that restyle it (ni, cosign)
+fn styled_digest(result: &HashResult, format: &ExportFormat) -> Result<String> {
+    match format {
+        ExportFormat::Ni => ni_uri(result),
+        ExportFormat::Cosign => cosign_digest(result),
+        _ => Ok(result.digest.clone()),
+    }
+}
+
+fn export_result(result: &HashResult, path: &Path, format: &ExportFormat) -> Result<()> {
+    let content = match format {
+        ExportFormat::Text => result.digest.clone(),
+        ExportFormat::Json => result.to_json()?,
+        ExportFormat::Jsonl => result.to_json_line()?,
+        ExportFormat::Ni | ExportFormat::Cosign => styled_digest(result, format)?,
+        ExportFormat::Checksum => {
+            if let Some(ref file_path) = result.input_path {
+                format!("{}  {}", result.digest, file_path)
+            } else {
+                result.digest.clone()
+            }
+        }
+    };
+
+    // Ensure parent directory exists
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+    }
+
+    // Write to file
+    fs::write(path, content.as_bytes())
+        .with_context(|| format!("Failed to write to file: {}", path.display()))?;
+
+    println!("Exported to: {}", path.display());
+    Ok(())
+}
+
+/// The parsed `--template`, if one was given
+fn output_template(args: &HashArgs) -> Result<Option<Template>> {
+    let template = args.output.template.as_deref().map(Template::parse).transpose();
+    template.map_err(|e| usage_error(e.to_string()))
+}
+
+/// Size of a hashed input for `{size}`: the file's length, or the string's;
+/// unknown for stdin, which has been consumed
+fn input_size(input: &str, input_type: &str, input_path: Option<&str>) -> Option<u64> {
+    match input_path {
+        Some(path) => fs::metadata(path).ok().map(|metadata| metadata.len()),
+        None if input_type == "stdin" => None,
+        None => Some(input.len() as u64),
+    }
+}
+
+/// Write `lines` to an export file, one per line
+fn export_lines(lines: &[String], path: &Path) -> Result<()> {
+    let mut file = BufWriter::new(create_export(path)?);
+    for line in lines {
+        writeln!(file, "{}", line)?;
+    }
+    file.flush()?;
+    Ok(())
+}
+
+/// Run the format plugin `name` over `results`, writing its output to `path`
+fn export_with_plugin(results: &[HashResult], path: &Path, name: &str) -> Result<()> {
+    let plugin = FormatPlugin::find(name, configured_formats())?;
+    plugin.render(results, create_export(path)?)?;
+    Ok(())
+}
+
+/// Create an export file, and its directory if needed
+fn create_export(path: &Path) -> Result<File> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+    }
+    File::create(path).with_context(|| format!("Failed to write to file: {}", path.display()))
+}
+
+fn export_all_results(results: &[HashResult], base_path: &Path, format: &ExportFormat) -> Result<()> {
+    match format {
+        ExportFormat::Json => {
+            let json = serde_json::to_string_pretty(results)
+                .context("Failed to serialize results to JSON")?;
+            fs::write(base_path, json.as_bytes())
+                .with_context(|| format!("Failed to write to file: {}", base_path.display()))?;
+            println!("Exported all results to: {}", base_path.display());
+        }
+        ExportFormat::Jsonl => {
+            let mut content = String::new();
+            for result in results {
+                content.push_str(&result.to_json_line()?);
+                content.push('\n');
+            }
+            fs::write(base_path, content.as_bytes())
+                .with_context(|| format!("Failed to write to file: {}", base_path.display()))?;
+            println!("Exported all results to: {}", base_path.display());
+        }
+        ExportFormat::Ni | ExportFormat::Cosign => {
+            // Only some algorithms have a registered ni or sigstore name
+            let mut content = String::new();
+            for result in results {
+                if let Ok(uri) = styled_digest(result, format) {
+                    content.push_str(&uri);
+                    content.push('\n');
+                }
+            }
+            fs::write(base_path, content.as_bytes())
+                .with_context(|| format!("Failed to write to file: {}", base_path.display()))?;
+            println!("Exported all results to: {}", base_path.display());
+        }
+        ExportFormat::Text | ExportFormat::Checksum => {
+            let parent = base_path.parent().unwrap_or_else(|| Path::new("."));
+            let stem = base_path.file_stem().unwrap_or_default().to_string_lossy();
+            let ext = base_path.extension().unwrap_or_default().to_string_lossy();
+
+            for result in results {
+                let filename = if ext.is_empty() {
+                    format!("{}.{}", stem, result.algorithm)
+                } else {
+                    format!("{}.{}.{}", stem, result.algorithm, ext)
+                };
+                let file_path = parent.join(filename);
+
+                let content = match format {
+                    ExportFormat::Text => result.digest.clone(),
+                    ExportFormat::Checksum => {
+                        if let Some(ref path) = result.input_path {
+                            format!("{}  {}", result.digest, path)
+                        } else {
+                            result.digest.clone()
+                        }
+                    }
+                    _ => unreachable!(),
+                };
+
+                fs::write(&file_path, content.as_bytes())
+                    .with_context(|| format!("Failed to write to file: {}", file_path.display()))?;
+            }
+            println!("Exported all results to: {}.*", base_path.display());
+        }
+    }
+
+    Ok(())
+}
+
+fn process_recursive(args: &HashArgs, key: Option<Key>) -> Result<()> {
+    let algorithm = args.output.algorithm;
+
+    let root = Path::new(args.input());
+    if !root.is_dir() {
+        return Err(usage_error(format!("Not a directory: {}", root.display())));
+    }
+    let walk_options = build_walk_options(args)?;
+    let timeout = match args.walk.timeout {
+        Some(ref timeout) => Some(parse_age(timeout).map_err(|e| usage_error(e.to_string()))?),
+        None => None,
+    };
+    if key.is_some() && timeout.is_some() {
+        return Err(usage_error("--timeout cannot be combined with an HMAC key"));
+    }
+    let reads = args.output.reads;
+    let custom_reads = reads.paranoid || reads.double_read || reads.uncached;
+    if (custom_reads || reads.read_ahead.is_some()) && timeout.is_some() {
+        return Err(usage_error(
+            "--timeout cannot be combined with --paranoid, --double-read, --uncached \
+             or --read-ahead",
+        ));
+    }
+    if args.output.canonical.is_some() {
+        return Err(usage_error("--canonical is not supported in recursive mode"));
+    }
+
+    let template = output_template(args)?;
+    if template.is_some() && args.walk.group_by.is_some() {
+        return Err(usage_error("--template cannot be combined with --group-by"));
+    }
+
+    if args.walk.dry_run {
+        return preview_recursive(args, root, &walk_options);
+    }
+
+    let mut journal = match &args.walk.resume {
+        Some(journal_path) => {
+            if let ExportFormat::Json = args.output.format {
+                return Err(usage_error(
+                    "--resume cannot append to a JSON array; use --format jsonl",
+                ));
+            }
+            if args.walk.group_by.is_some() {
+                return Err(usage_error("--resume cannot be combined with --group-by"));
+            }
+            let journal = Journal::open(journal_path)
+                .with_context(|| format!("Failed to open journal: {}", journal_path.display()))?;
+            if !args.output.quiet && !journal.is_empty() {
+                eprintln!("Resuming: {} files already completed", journal.len());
+            }
+            Some(journal)
+        }
+        None => None,
+    };
+
+    let split = match (args.walk.split_by_dir, args.walk.split_every) {
+        (true, _) => Some(ManifestSplit::TopDirectory),
+        (false, Some(0)) => return Err(usage_error("--split-every needs at least 1 entry")),
+        (false, Some(entries)) => Some(ManifestSplit::Every(entries)),
+        (false, None) => None,
+    };
+    if let Some(split) = split {
+        if args.walk.group_by.is_some() || matches!(args.output.format, ExportFormat::Json) {
+            return Err(usage_error(
+                "Split exports are written as files are hashed; \
+                 use --format jsonl and no --group-by",
+            ));
+        }
+        if journal.is_some() && matches!(split, ManifestSplit::Every(_)) {
+            return Err(usage_error("--split-every cannot be combined with --resume"));
+        }
+    }
+    let format_plugin = args.output.format_plugin.as_deref();
+    let mut session: Option<PluginSession> = match (format_plugin, &args.output.export) {
+        (Some(name), Some(export_path)) => {
+            if args.walk.group_by.is_some() || split.is_some() || journal.is_some() {
+                return Err(usage_error(
+                    "--format-plugin cannot be combined with --group-by, split exports or --resume",
+                ));
+            }
+            let plugin = FormatPlugin::find(name, configured_formats())?;
+            Some(plugin.spawn(create_export(export_path)?)?)
+        }
+        _ => None,
+    };
+    let mut parts = match (split, &args.output.export) {
+        (Some(split), Some(export_path)) => {
+            if let Some(parent) = export_path.parent() {
+                fs::create_dir_all(parent)
+                    .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+            }
+            Some(SplitOutput::new(export_path, split, root, journal.is_some()))
+        }
+        _ => None,
+    };
+
+    let mut sealed = match &args.output.export {
+        Some(export_path) if args.walk.encrypt => {
+            let passphrase = read_passphrase(args.walk.passphrase_env.as_deref(), true)?;
+            let file = BufWriter::new(create_export(export_path)?);
+            Some(seal::SealWriter::new(file, &passphrase)?)
+        }
+        _ => None,
+    };
+    let mut out: Box<dyn Write> = match &args.output.export {
+        Some(_) if parts.is_some() || session.is_some() => Box::new(io::sink()),
+        Some(_) if sealed.is_some() => Box::new(sealed.as_mut().expect("sealed export")),
+        Some(export_path) => {
+            if let Some(parent) = export_path.parent() {
+                fs::create_dir_all(parent)
+                    .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+            }
+            // A resumed scan continues the output of the interrupted one
+            let file = fs::OpenOptions::new()
+                .write(true)
+                .create(true)
+                .append(journal.is_some())
+                .truncate(journal.is_none())
+                .open(export_path)
+                .with_context(|| format!("Failed to write to file: {}", export_path.display()))?;
+            Box::new(BufWriter::new(file))
+        }
+        // The root alone goes to stdout
+        None if args.walk.merkle_root => Box::new(io::sink()),
+        None => Box::new(io::stdout().lock()),
+    };
+
+    // JSON arrays and digest groups need every result up front; all other
+    // output is written (and flushed) as each file is hashed.
+    let collect = args.walk.group_by.is_some() || matches!(args.output.format, ExportFormat::Json);
+    let mut collected = Vec::new();
+    let mut leaves = Vec::new();
+    let mut hashed = 0usize;
+    let mut linked = 0usize;
+    let mut errors = 0usize;
+    let mut totals = DirTotals::new(root);
+    // With --progress=json, stderr carries only JSON records
+    let mut progress = args
+        .walk
+        .progress
+        .map(|_| ProgressTracker::new(io::stderr(), PROGRESS_INTERVAL));
+
+    let mut job = HashJob::new(root)
+        .with_algorithm(algorithm)
+        .with_walk_options(walk_options)
+        .with_hard_link_dedup(!args.walk.no_hard_link_dedup)
+        .with_paranoid(reads.paranoid)
+        .with_double_read(reads.double_read)
+        .with_uncached(reads.uncached)
+        .with_read_ahead(reads.read_ahead.unwrap_or(0));
+    if let Some(key) = key {
+        job = job.with_key(key.bytes);
+    }
+    if let Some(timeout) = timeout {
+        job = job.with_timeout(timeout);
+    }
+    if let Some(journal) = journal.as_ref() {
+        job = job.with_skip(journal.completed());
+    }
+    if progress.is_some() {
+        job = job.with_progress_interval(PROGRESS_INTERVAL);
+    }
+    let job = job.spawn();
+
+    for event in job.events() {
+        let (path, digest, size, linked_to) = match event {
+            JobEvent::Started { path } => {
+                if let Some(progress) = progress.as_mut() {
+                    progress.start_file(&path);
+                }
+                continue;
+            }
+            JobEvent::Progress { bytes, .. } => {
+                if let Some(progress) = progress.as_mut() {
+                    progress.bytes(bytes);
+                }
+                continue;
+            }
+            JobEvent::Failed { path, message } => {
+                match (progress.as_mut(), &path) {
+                    (Some(progress), _) => progress.fail_file(path.as_deref(), message),
+                    (None, Some(path)) => eprintln!("Error: {}: {}", path.display(), message),
+                    (None, None) => eprintln!("Error: {}", message),
+                }
+                errors += 1;
+                continue;
+            }
+            JobEvent::Hashed {
+                path,
+                digest,
+                size,
+                linked_to,
+            } => {
+                totals.add(&path, size);
+                (path, digest, size, linked_to)
+            }
+            JobEvent::Checked { .. } | JobEvent::Finished(_) => continue,
+        };
+
+        if args.walk.merkle_root {
+            leaves.push(FileDigest {
+                path: relative_path(root, &path),
+                digest: Digest::parse_for(&digest, algorithm)?,
+            });
+        }
+        let result = new_result(algorithm, key, digest, "file").with_path(&path);
+        let result = match linked_to {
+            Some(first) => {
+                linked += 1;
+                result.with_linked_to(first)
+            }
+            None => {
+                hashed += 1;
+                result
+            }
+        };
+        if let Some(session) = session.as_mut() {
+            session.write_result(&result)?;
+        } else if collect {
+            collected.push(result);
+        } else {
+            let line = match (&template, &args.output.format) {
+                (Some(template), _) => template.render(&result, Some(size)),
+                (None, ExportFormat::Jsonl) => result.to_json_line()?,
+                (None, ExportFormat::Ni | ExportFormat::Cosign) => {
+                    format!("{}  {}", styled_digest(&result, &args.output.format)?, path.display())
+                }
+                (None, _) => format!("{}  {}", result.digest, path.display()),
+            };
+            let out: &mut dyn Write = match parts.as_mut() {
+                Some(parts) => parts.writer_for(&path)?,
+                None => &mut out,
+            };
+            writeln!(out, "{}", line)?;
+            out.flush()?;
+        }
+
+        // Only journal a path once its result has been written out
+        if let Some(journal) = journal.as_mut() {
+            journal.record(&path)?;
+        }
+        if let Some(progress) = progress.as_mut() {
+            progress.finish_file();
+        }
+    }
+    let status = job.wait()?;
+    if let Some(session) = session {
+        session.finish()?;
+    }
+
+    if args.walk.group_by.is_some() {
+        write_groups(&mut out, &group_by_digest(&collected), &args.output.format)?;
+    } else if let ExportFormat::Json = args.output.format {
+        let json = serde_json::to_string_pretty(&collected)
+            .context("Failed to serialize results to JSON")?;
+        writeln!(out, "{}", json)?;
+    }
+    out.flush()?;
+    drop(out);
+    // The tag goes last; without it the export cannot be opened
+    if let Some(sealed) = sealed {
+        sealed.finish()?;
+    }
+
+    // A root missing the unreadable files would not describe the tree
+    if args.walk.merkle_root && errors == 0 {
+        println!("{}", merkle_root(&leaves, algorithm)?);
+    }
+
+    let written = match parts {
+        Some(parts) => parts.finish()?,
+        None => args.output.export.iter().cloned().collect(),
+    };
+
+    if let Some(progress) = progress {
+        progress.finish().context("Failed to write progress")?;
+    } else if !args.output.quiet {
+        match written.as_slice() {
+            [export_path] if split.is_none() => {
+                println!("Exported all results to: {}", export_path.display())
+            }
+            written => {
+                for part_path in written {
+                    println!("Exported results to: {}", part_path.display());
+                }
+            }
+        }
+        let sizes = args.walk.units.format();
+        let read = format!(
+            "{} in {:.1}s, {}",
+            sizes.size(status.bytes_done),
+            status.elapsed.as_secs_f64(),
+            sizes.rate(status.bytes_done, status.elapsed)
+        );
+        // The list itself does not name the algorithm, which --algorithm auto leaves open
+        let label = algorithm_label(algorithm, key);
+        if linked > 0 {
+            eprintln!(
+                "{} files hashed with {} ({}), {} hard links reused, {} errors",
+                hashed, label, read, linked, errors
+            );
+        } else {
+            eprintln!("{} files hashed with {} ({}), {} errors", hashed, label, read, errors);
+        }
+        for line in totals.lines(&sizes) {
+            eprintln!("{}", line);
+        }
+    }
+
+    if errors > 0 {
+        ExitStatus::Io.exit();
+    }
+    Ok(())
+}
+
+/// Files and bytes per top-level directory of a recursive scan; files
+/// directly inside the scanned directory count towards the directory itself
+struct DirTotals {
+    root: PathBuf,
+    dirs: BTreeMap<PathBuf, (u64, u64)>,
+}
+
+impl DirTotals {
+    fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            dirs: BTreeMap::new(),
+        }
+    }
+
+    fn add(&mut self, path: &Path, size: u64) {
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        let mut components = relative.components();
+        let dir = match (components.next(), components.next()) {
+            (Some(first), Some(_)) => self.root.join(first),
+            _ => self.root.clone(),
+        };
+        let (files, bytes) = self.dirs.entry(dir).or_default();
+        *files += 1;
+        *bytes += size;
+    }
+
+    /// One `SIZE  FILES files  DIR` line per directory; none when every
+    /// file is in the same one
+    fn lines(&self, sizes: &SizeFormat) -> Vec<String> {
+        if self.dirs.len() < 2 {
+            return Vec::new();
+        }
+        self.dirs
+            .iter()
+            .map(|(dir, (files, bytes))| {
+                format!("{:>12}  {:>8} files  {}", sizes.size(*bytes), files, dir.display())
+            })
+            .collect()
+    }
+}
+
+/// Export files of a split recursive scan, each opened when its first
+/// entry arrives
+struct SplitOutput {
+    export: PathBuf,
+    split: ManifestSplit,
+    root: PathBuf,
+    /// Continue existing files (a resumed scan) instead of replacing them
+    append: bool,
+    entries: usize,
+    current: Option<(String, BufWriter<File>)>,
+    written: Vec<PathBuf>,
+}
+
+impl SplitOutput {
+    fn new(export: &Path, split: ManifestSplit, root: &Path, append: bool) -> Self {
+        Self {
+            export: export.to_path_buf(),
+            split,
+            root: root.to_path_buf(),
+            append,
+            entries: 0,
+            current: None,
+            written: Vec::new(),
+        }
+    }
+
+    /// The file the entry for `path` goes to, switching files when the
+    /// part changes
+    fn writer_for(&mut self, path: &Path) -> Result<&mut BufWriter<File>> {
+        let part = self.split.part(&self.root, path, self.entries);
+        self.entries += 1;
+        if self.current.as_ref().map(|(current, _)| current) != Some(&part) {
+            if let Some((_, mut file)) = self.current.take() {
+                file.flush()?;
+            }
+            let part_path = manifest::part_path(&self.export, &part);
+            // A part that comes round again is continued, not replaced
+            let seen = self.written.contains(&part_path);
+            let append = self.append || seen;
+            let file = fs::OpenOptions::new()
+                .write(true)
+                .create(true)
+                .append(append)
+                .truncate(!append)
+                .open(&part_path)
+                .with_context(|| format!("Failed to write to file: {}", part_path.display()))?;
+            if !seen {
+                self.written.push(part_path);
+            }
+            self.current = Some((part, BufWriter::new(file)));
+        }
+        Ok(&mut self.current.as_mut().expect("part file just opened").1)
+    }
+
+    /// Flush the last file and return every file written, in order
+    fn finish(mut self) -> Result<Vec<PathBuf>> {
+        if let Some((_, mut file)) = self.current.take() {
+            file.flush()?;
+        }
+        Ok(self.written)
+    }
+}
+
+/// List what a recursive scan would hash, without reading file contents
+/// or writing exports and journals
+fn preview_recursive(args: &HashArgs, root: &Path, walk_options: &WalkOptions) -> Result<()> {
+    // Only read an existing journal; opening it normally would create it
+    let journal = match &args.walk.resume {
+        Some(journal_path) if journal_path.exists() => Some(
+            Journal::open(journal_path)
+                .with_context(|| format!("Failed to open journal: {}", journal_path.display()))?,
+        ),
+        _ => None,
+    };
+
+    let sizes = args.walk.units.format();
+    let mut files = 0u64;
+    let mut bytes = 0u64;
+    let mut resumed = 0u64;
+    let mut errors = 0u64;
+    let mut totals = DirTotals::new(root);
+
+    for entry in walk_files(root, walk_options) {
+        let path = match entry {
+            Ok(path) => path,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                errors += 1;
+                continue;
+            }
+        };
+        if journal.as_ref().is_some_and(|j| j.is_completed(&path)) {
+            resumed += 1;
+            continue;
+        }
+
+        let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        files += 1;
+        bytes += size;
+        totals.add(&path, size);
+        if !args.output.quiet {
+            println!("{:>12}  {}", sizes.size(size), path.display());
+        }
+    }
+
+    println!();
+    println!(
+        "Dry run: {} files, {} ({} bytes) would be hashed",
+        files,
+        sizes.size(bytes),
+        bytes
+    );
+    for line in totals.lines(&sizes) {
+        println!("{}", line);
+    }
+    if resumed > 0 {
+        println!("         {} files already completed in the journal", resumed);
+    }
+    if errors > 0 {
+        println!("         {} entries could not be read", errors);
+    }
+    if let Some(export_path) = &args.output.export {
+        println!("         results would be written to {}", export_path.display());
+    }
+
+    Ok(())
+}
 
-    /// Export format
-    #[arg(short = 'f', long, default_value = "text")]
-    format: ExportFormat,
+fn write_groups(out: &mut dyn Write, groups: &[DigestGroup], format: &ExportFormat) -> Result<()> {
+    match format {
+        ExportFormat::Json => {
+            let json = serde_json::to_string_pretty(groups)
+                .context("Failed to serialize results to JSON")?;
+            writeln!(out, "{}", json)?;
+        }
+        ExportFormat::Jsonl => {
+            for group in groups {
+                let line = serde_json::to_string(group)
+                    .context("Failed to serialize results to JSON")?;
+                writeln!(out, "{}", line)?;
+            }
+        }
+        ExportFormat::Text | ExportFormat::Checksum | ExportFormat::Ni | ExportFormat::Cosign => {
+            for group in groups {
+                let count = group.paths.len();
+                let noun = if count == 1 { "file" } else { "files" };
+                writeln!(out, "{}  {} {}", group.digest, count, noun)?;
+                for path in &group.paths {
+                    writeln!(out, "    {}", path)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
 
-    /// Compute hashes for all algorithms
-    #[arg(short = 'A', long)]
-    all_algorithms: bool,
+fn build_walk_options(args: &HashArgs) -> Result<WalkOptions> {
+    let mut options = WalkOptions::new()
+        .with_sort(args.walk.sort)
+        .with_one_file_system(args.walk.one_file_system);
 
-    /// Treat input as a string even if it matches a file path
-    #[arg(short = 's', long)]
-    string: bool,
+    for path in &args.walk.skip {
+        options = options.with_skip_path(path);
+    }
+    for glob in &args.walk.include {
+        options = options.with_include(glob.clone());
+    }
+    for glob in &args.walk.exclude {
+        options = options.with_exclude(glob.clone());
+    }
+    let scanning_root = Path::new(args.input())
+        .canonicalize()
+        .is_ok_and(|p| p == Path::new("/"));
+    if scanning_root && !args.walk.no_default_skips {
+        options = options.with_system_skips();
+    }
 
-    /// Quiet mode - only output the hash
-    #[arg(short, long)]
-    quiet: bool,
+    if let Some(depth) = args.walk.max_depth {
+        options = options.with_max_depth(depth);
+    }
+    if let Some(ref size) = args.walk.min_size {
+        options = options.with_min_size(parse_size(size)?);
+    }
+    if let Some(ref size) = args.walk.max_size {
+        options = options.with_max_size(parse_size(size)?);
+    }
+    if let Some(ref newer) = args.walk.newer_than {
+        // A reference file works like `find -newer`; anything else is an age
+        let threshold = if Path::new(newer).exists() {
+            fs::metadata(newer)
+                .and_then(|m| m.modified())
+                .with_context(|| format!("Failed to read modification time: {}", newer))?
+        } else {
+            let age = parse_age(newer).map_err(|e| usage_error(e.to_string()))?;
+            let too_old = || usage_error(format!("--newer-than reaches too far back: {}", newer));
+            std::time::SystemTime::now().checked_sub(age).ok_or_else(too_old)?
+        };
+        options = options.with_newer_than(threshold);
+    }
+    if args.walk.git_tracked || args.walk.git_changed.is_some() {
+        options = options.with_only_paths(git_selection(args)?);
+    }
 
-    /// Verify hash against expected value
-    #[arg(short = 'c', long, value_name = "EXPECTED")]
-    verify: Option<String>,
+    Ok(options)
+}
 
-    /// Compare two files or strings by hash
-    #[arg(short = 'C', long, value_name = "INPUT2")]
-    compare: Option<String>,
+#[cfg(feature = "git")]
+fn git_selection(args: &HashArgs) -> Result<Vec<PathBuf>> {
+    let root = args.input();
+    Ok(match &args.walk.git_changed {
+        Some(base) => hashing::git::changed_files(root, Some(base))?,
+        None => hashing::git::tracked_files(root)?,
+    })
 }
 
-#[derive(Debug, Clone, ValueEnum)]
-enum ExportFormat {
-    /// Plain text format (hash only)
-    Text,
-    /// JSON format with metadata
-    Json,
-    /// Checksums format (hash filename)
-    Checksum,
+#[cfg(not(feature = "git"))]
+fn git_selection(_args: &HashArgs) -> Result<Vec<PathBuf>> {
+    Err(usage_error("--git-tracked and --git-changed require a build with the `git` feature"))
 }
 
-fn main() -> Result<()> {
-    let os_args: Vec<String> = std::env::args().collect();
-    let version_flags = ["-V", "--version"];
-    let list_flags = ["-l", "--list-algorithms"];
+fn run_bench(
+    algorithm: Option<Algorithm>,
+    size: &str,
+    path: Option<&Path>,
+    sizes: SizeFormat,
+) -> Result<()> {
+    let algorithms = match algorithm {
+        Some(algorithm) => vec![algorithm],
+        None => Algorithm::all(),
+    };
+    if let Some(path) = path {
+        return bench_files(&algorithms, path, sizes);
+    }
+    let size = parse_size(size)? as usize;
+    let data = vec![0xA5u8; size];
 
-    if os_args.len() >= 2 {
-        if os_args[1..].iter().any(|arg| version_flags.contains(&arg.as_str())) {
-            let version = colorful_version!();
-            version.print_and_exit();    
-        } else if os_args[1..].iter().any(|arg| list_flags.contains(&arg.as_str())) {
-            list_algorithms();
-            return Ok(());
+    println!("Hashing {} in memory per algorithm", sizes.size(size as u64));
+    println!();
+
+    for algorithm in algorithms {
+        let start = Instant::now();
+        hash_bytes(&data, algorithm)?;
+        let rate = sizes.rate(size as u64, start.elapsed());
+        println!("{:<15} {:>14}", format!("{}:", algorithm.name().to_uppercase()), rate);
+    }
+
+    Ok(())
+}
+
+/// Benchmark hashing the files at `root` from the page cache and from storage
+fn bench_files(algorithms: &[Algorithm], root: &Path, sizes: SizeFormat) -> Result<()> {
+    let files: Vec<PathBuf> = if root.is_dir() {
+        walk_files(root, &WalkOptions::new()).collect::<hashing::Result<_>>()?
+    } else {
+        vec![root.to_path_buf()]
+    };
+    let mut total = 0;
+    // Read everything once so the cached column measures a warm page cache
+    for file in &files {
+        let mut reader =
+            File::open(file).with_context(|| format!("Failed to open: {}", file.display()))?;
+        total += io::copy(&mut reader, &mut io::sink())?;
+    }
+    if let Some(file) = files.first() {
+        if !UncachedReader::open(file)?.bypasses_cache() {
+            eprintln!(
+                "warning: {} cannot be read past the page cache here; \
+                 the uncached column reads normally after dropping cached pages",
+                file.display()
+            );
         }
-        
     }
 
-    let cli = Cli::parse();
+    let noun = if files.len() == 1 { "file" } else { "files" };
+    println!(
+        "Hashing {} in {} {} from {}",
+        sizes.size(total),
+        files.len(),
+        noun,
+        root.display()
+    );
+    println!("  cached:   files already in the page cache");
+    println!("  uncached: files read from storage, past the page cache");
+    println!();
+    println!("{:<15} {:<10} {:>14} {:>14}", "ALGORITHM", "BACKEND", "CACHED", "UNCACHED");
 
-    // List algorithms if requested
-    if cli.list_algorithms {
-        list_algorithms();
-        return Ok(());
+    let info = build_info();
+    for &algorithm in algorithms {
+        let mut rates = Vec::new();
+        for uncached in [false, true] {
+            let start = Instant::now();
+            for file in &files {
+                Hashing::new()
+                    .algorithm(algorithm)
+                    .uncached(uncached)
+                    .hash_file(file)
+                    .with_context(|| format!("Failed to hash file: {}", file.display()))?;
+            }
+            rates.push(sizes.rate(total, start.elapsed()));
+        }
+        println!(
+            "{:<15} {:<10} {:>14} {:>14}",
+            format!("{}:", algorithm.name().to_uppercase()),
+            info.backend(algorithm),
+            rates[0],
+            rates[1]
+        );
     }
 
-    // Compare mode if requested
-    if let Some(ref input2) = cli.compare {
-        return compare_inputs(&cli.input, input2, &cli);
+    Ok(())
+}
+
+fn run_tee(command: Command) -> Result<()> {
+    let Command::Tee { input, mut outputs, algorithm, append, digest_file } = command else {
+        unreachable!("called with tee only")
+    };
+    if outputs.is_empty() {
+        outputs.push("-".to_string());
     }
 
-    // Process input
-    if cli.all_algorithms {
-        process_all_algorithms(&cli)?;
+    let reader: Box<dyn Read> = if input == "-" {
+        Box::new(io::stdin().lock())
     } else {
-        process_single_algorithm(&cli)?;
+        Box::new(File::open(&input).with_context(|| format!("Failed to open: {}", input))?)
+    };
+    let mut writers: Vec<Box<dyn Write>> = Vec::with_capacity(outputs.len());
+    for output in &outputs {
+        if output == "-" {
+            writers.push(Box::new(io::stdout().lock()));
+        } else {
+            let file = fs::OpenOptions::new()
+                .write(true)
+                .create(true)
+                .append(append)
+                .truncate(!append)
+                .open(output)
+                .with_context(|| format!("Failed to write to file: {}", output))?;
+            writers.push(Box::new(BufWriter::new(file)));
+        }
     }
 
+    let digest = Hashing::new()
+        .algorithm(algorithm)
+        .hash_tee(reader, FanOut(writers))?;
+    let line = format!("{}  {}", digest, input);
+    if outputs.iter().any(|output| output == "-") {
+        // stdout carries the data
+        eprintln!("{}", line);
+    } else {
+        println!("{}", line);
+    }
+    if let Some(path) = digest_file {
+        fs::write(&path, line + "\n")
+            .with_context(|| format!("Failed to write to file: {}", path.display()))?;
+    }
     Ok(())
 }
 
-fn list_algorithms() {
-    println!("Available hash algorithms:");
-    println!();
-    
-    let algorithms = vec![
-        ("MD5", "md5", "128-bit (insecure, legacy use only)"),
-        ("SHA-1", "sha1", "160-bit (insecure, legacy use only)"),
-        ("SHA-224", "sha224", "224-bit SHA-2"),
-        ("SHA-256", "sha256", "256-bit SHA-2 (recommended)"),
-        ("SHA-384", "sha384", "384-bit SHA-2"),
-        ("SHA-512", "sha512", "512-bit SHA-2"),
-        ("SHA-512/224", "sha512-224", "224-bit SHA-2 variant"),
-        ("SHA-512/256", "sha512-256", "256-bit SHA-2 variant"),
-        ("SHA3-224", "sha3-224", "224-bit SHA-3"),
-        ("SHA3-256", "sha3-256", "256-bit SHA-3"),
-        ("SHA3-384", "sha3-384", "384-bit SHA-3"),
-        ("SHA3-512", "sha3-512", "512-bit SHA-3"),
-        ("BLAKE2b", "blake2b", "512-bit BLAKE2b"),
-        ("BLAKE2s", "blake2s", "256-bit BLAKE2s"),
-        ("BLAKE3", "blake3", "256-bit BLAKE3 (fast, modern)"),
-        ("Keccak-224", "keccak224", "224-bit Keccak"),
-        ("Keccak-256", "keccak256", "256-bit Keccak"),
-        ("Keccak-384", "keccak384", "384-bit Keccak"),
-        ("Keccak-512", "keccak512", "512-bit Keccak"),
-    ];
-
-    for (name, code, desc) in algorithms {
-        println!("  {:<15} {:<15} {}", name, code, desc);
+fn run_stamp(command: Command) -> Result<()> {
+    let Command::Stamp { stamp: path, inputs, algorithm, depfile, quiet } = command else {
+        unreachable!("called with stamp only")
+    };
+    let files = stamp::input_files(&inputs)?;
+    let contents = stamp::stamp_contents(&files, algorithm)?;
+    let changed = stamp::update_stamp(&path, &contents)
+        .with_context(|| format!("Failed to write stamp: {}", path.display()))?;
+    if let Some(depfile) = depfile {
+        stamp::update_stamp(&depfile, &stamp::depfile(&path, &files))
+            .with_context(|| format!("Failed to write depfile: {}", depfile.display()))?;
     }
+    if !quiet {
+        let state = if changed { "updated" } else { "unchanged" };
+        println!("{}: {} ({} inputs)", path.display(), state, files.len());
+    }
+    Ok(())
 }
 
-fn process_single_algorithm(cli: &Cli) -> Result<()> {
-    let algorithm = Algorithm::from_str(&cli.algorithm)
-        .with_context(|| format!("Invalid algorithm: {}", cli.algorithm))?;
+/// Algorithms `hash inspect` shows when none are given: the digests most
+/// often looked up in malware and package databases, plus BLAKE3
+const INSPECT_ALGORITHMS: [Algorithm; 4] =
+    [Algorithm::Md5, Algorithm::Sha1, Algorithm::Sha256, Algorithm::Blake3];
 
-    let (digest, input_type, input_path) = compute_hash(&cli.input, algorithm, cli.string)?;
+fn run_inspect(
+    file: &Path,
+    mut algorithms: Vec<Algorithm>,
+    bytes: usize,
+    sizes: SizeFormat,
+) -> Result<()> {
+    if algorithms.is_empty() {
+        algorithms.extend(INSPECT_ALGORITHMS);
+    }
+    let report = inspect::inspect_file(file, &algorithms, bytes)
+        .with_context(|| format!("Failed to inspect: {}", file.display()))?;
 
-    // Verify if requested
-    if let Some(expected) = &cli.verify {
-        let matches = digest.eq_ignore_ascii_case(expected.trim());
-        if cli.quiet {
-            std::process::exit(if matches { 0 } else { 1 });
-        } else if matches {
-            println!("✓ Hash verification PASSED");
-            println!("{}: {}", algorithm.name().to_uppercase(), digest);
+    println!("File: {}", file.display());
+    if report.size < 1024 {
+        println!("Size: {} bytes", report.size);
+    } else {
+        println!("Size: {} bytes ({})", report.size, sizes.size(report.size));
+    }
+    println!("Type: {}", report.file_type);
+    if !report.head.is_empty() {
+        println!();
+        if report.size <= bytes as u64 {
+            println!("Contents:");
         } else {
-            eprintln!("✗ Hash verification FAILED");
-            eprintln!("Expected: {}", expected);
-            eprintln!("Got:      {}", digest);
-            std::process::exit(1);
+            println!("First {} bytes:", report.head.len());
+        }
+        print!("{}", inspect::hex_dump(&report.head, 0));
+        if report.size > bytes as u64 {
+            // Bytes already shown at the start are not repeated
+            let shown = report.tail_offset().max(bytes as u64);
+            let tail = &report.tail[(shown - report.tail_offset()) as usize..];
+            println!("Last {} bytes:", tail.len());
+            print!("{}", inspect::hex_dump(tail, shown));
         }
-        return Ok(());
     }
+    println!();
+    let width = label_width(&algorithms, None);
+    for digest in &report.digests {
+        let label = format!("{}:", algorithm_label(digest.algorithm(), None));
+        println!("{:<width$} {}", label, digest, width = width);
+    }
+    Ok(())
+}
 
-    // Display result
-    if cli.quiet {
-        println!("{}", digest);
-    } else {
-        display_result(algorithm, &digest, &input_type, input_path.as_deref());
+fn run_cachekey(command: Command) -> Result<()> {
+    let Command::Cachekey { inputs, env, literal, algorithm, missing, prefix, explain } = command
+    else {
+        unreachable!("called with cachekey only")
+    };
+    if inputs.is_empty() && env.is_empty() && literal.is_empty() {
+        return Err(usage_error("hash cachekey needs at least one --inputs, --env or --literal"));
     }
 
-    // Export if requested
-    if let Some(export_path) = &cli.export {
-        let mut result = HashResult::new(algorithm, digest.clone(), &input_type);
-        if let Some(path) = input_path {
-            result = result.with_path(path);
+    let mut key = CacheKey::new().with_algorithm(algorithm).with_missing(missing);
+    for path in inputs {
+        key = key.with_file(path);
+    }
+    for name in env {
+        key = key.with_env(name);
+    }
+    for value in literal {
+        key = key.with_literal(value);
+    }
+    let entries = key.entries()?;
+    if explain {
+        for entry in &entries {
+            let digest = entry.digest.as_deref().unwrap_or(match entry.kind {
+                EntryKind::Env => "(unset)",
+                _ => "(missing)",
+            });
+            eprintln!("{:<8} {}  {}", entry.kind, digest, entry.name);
         }
-        export_result(&result, export_path, &cli.format)?;
+    }
+    println!("{}{}", prefix, cachekey::key_for(&entries, algorithm)?);
+    Ok(())
+}
+
+fn run_chunks(command: Command) -> Result<()> {
+    let Command::Chunks { file, algorithm, avg_size, format, output, known, quiet } = command
+    else {
+        unreachable!("called with chunks only")
+    };
+    let avg_size = u32::try_from(avg_size).unwrap_or(u32::MAX);
+    let chunker = Chunker::new(avg_size)
+        .map_err(|e| usage_error(e.to_string()))?
+        .with_algorithm(algorithm);
+    let known = match known {
+        Some(path) => {
+            let text = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read chunk index: {}", path.display()))?;
+            let index = ChunkIndex::parse(&text)
+                .with_context(|| format!("Invalid chunk index: {}", path.display()))?;
+            if index.chunker != chunker {
+                return Err(usage_error(format!(
+                    "{} was chunked with other sizes or another algorithm",
+                    path.display()
+                )));
+            }
+            Some(index)
+        }
+        None => None,
+    };
+
+    let reader: Box<dyn Read> = if file == "-" {
+        Box::new(io::stdin().lock())
+    } else {
+        Box::new(File::open(&file).with_context(|| format!("Failed to open: {}", file))?)
+    };
+    let index = chunker
+        .index(reader)
+        .with_context(|| format!("Failed to chunk: {}", file))?;
+    match output {
+        Some(path) => {
+            let writer = File::create(&path)
+                .with_context(|| format!("Failed to write to file: {}", path.display()))?;
+            index.write_to(BufWriter::new(writer), format)?;
+        }
+        None => index.write_to(io::stdout().lock(), format)?,
     }
 
+    if !quiet {
+        eprintln!(
+            "{} chunks ({} unique), {} bytes ({} after deduplication)",
+            index.chunks.len(),
+            index.unique_chunks(),
+            index.total_len(),
+            index.unique_len()
+        );
+        if let Some(known) = known {
+            let missing = index.missing_from(&known);
+            let new_bytes: u64 = missing.iter().map(|chunk| chunk.len).sum();
+            eprintln!("{} chunks, {} bytes not in the known index", missing.len(), new_bytes);
+        }
+    }
     Ok(())
 }
 
-fn process_all_algorithms(cli: &Cli) -> Result<()> {
-    let mut results = Vec::new();
+fn run_fill_manifest(command: Command) -> Result<()> {
+    let Command::FillManifest { manifest, artifacts, format, force, dry_run } = command else {
+        unreachable!("called with fill-manifest only")
+    };
+    let format = match format.or_else(|| ManifestFormat::from_path(&manifest)) {
+        Some(format) => format,
+        None => return Err(usage_error("cannot tell the manifest format; pass --format")),
+    };
+    let text = fs::read_to_string(&manifest)
+        .with_context(|| format!("Failed to read manifest: {}", manifest.display()))?;
+    let fill = pkgmanifest::fill_manifest(&text, format, &artifacts, force)?;
 
-    if !cli.quiet {
-        println!("Computing hashes for all algorithms...");
-        println!();
+    for field in &fill.fields {
+        let label = match field.status {
+            FieldStatus::Filled => "filled",
+            FieldStatus::Verified => "OK",
+            FieldStatus::Mismatch => "FAILED",
+            FieldStatus::Updated => "updated",
+            FieldStatus::Unmatched => "no artifact",
+        };
+        let target = match (&field.artifact, &field.url) {
+            (Some(artifact), _) => artifact.display().to_string(),
+            (None, Some(url)) => url.clone(),
+            (None, None) => String::new(),
+        };
+        println!("{}:{}: {}: {}", manifest.display(), field.line, target, label);
+        if field.status == FieldStatus::Mismatch {
+            eprintln!("  manifest has {} (use --force to replace it)", field.previous);
+        }
     }
+    if !fill.is_ok() {
+        ExitStatus::Mismatch.exit();
+    }
+    if fill.changed() && !dry_run {
+        fs::write(&manifest, &fill.text)
+            .with_context(|| format!("Failed to write manifest: {}", manifest.display()))?;
+    }
+    Ok(())
+}
 
-    for algorithm in Algorithm::all() {
-        let (digest, input_type, input_path) = compute_hash(&cli.input, algorithm, cli.string)?;
-        
-        if !cli.quiet {
-            println!("{:<15} {}", format!("{}:", algorithm.name().to_uppercase()), digest);
+fn run_manifest(action: ManifestAction) -> Result<()> {
+    match action {
+        ManifestAction::Merge { files, dedupe, sort, output } => {
+            let manifests = files
+                .iter()
+                .map(|file| {
+                    Manifest::load(file)
+                        .with_context(|| format!("Failed to read manifest: {}", file.display()))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let options = MergeOptions::new().with_dedupe(dedupe).with_sort(sort);
+            let merged = manifest::merge(&manifests, options)?;
+            match output {
+                Some(path) => {
+                    fs::write(&path, merged.to_text())
+                        .with_context(|| format!("Failed to write to file: {}", path.display()))?;
+                    eprintln!("{} entries written to {}", merged.len(), path.display());
+                }
+                None => print!("{}", merged.to_text()),
+            }
+            Ok(())
+        }
+        ManifestAction::Convert { input, to, from, algorithm, output } => {
+            let from = from.unwrap_or_else(|| ChecksumFormat::from_path(&input));
+            let text = fs::read_to_string(&input)
+                .with_context(|| format!("Failed to read manifest: {}", input.display()))?;
+            let read_as = algorithm.clone().or_else(|| {
+                manifest::algorithm_from_file_name(&input).map(|a| a.name().to_string())
+            });
+            if from == ChecksumFormat::Checksum && read_as.is_none() {
+                return Err(usage_error(format!(
+                    "Cannot tell the algorithm of {}; pass --algorithm",
+                    input.display()
+                )));
+            }
+            let mut records = manifest::read_records(&text, from, read_as.as_deref())
+                .with_context(|| format!("Failed to parse manifest: {}", input.display()))?;
+            if to == ChecksumFormat::Hashdeep {
+                // hashdeep lists sizes; take them from the files next to the input
+                let base = input.parent().unwrap_or(Path::new(""));
+                manifest::fill_sizes(&mut records, base).context("Failed to read file sizes")?;
+            }
+            let converted = manifest::write_records(&records, to, algorithm.as_deref())?;
+            match output {
+                Some(path) => {
+                    fs::write(&path, converted)
+                        .with_context(|| format!("Failed to write to file: {}", path.display()))?;
+                    eprintln!("{} files written to {}", records.len(), path.display());
+                }
+                None => print!("{}", converted),
+            }
+            Ok(())
+        }
+        ManifestAction::Decrypt { input, passphrase_env, output } => {
+            let sealed = fs::read(&input)
+                .with_context(|| format!("Failed to read manifest: {}", input.display()))?;
+            let passphrase = read_passphrase(passphrase_env.as_deref(), false)?;
+            let plain = seal::open(&sealed, &passphrase)
+                .with_context(|| format!("Failed to decrypt manifest: {}", input.display()))?;
+            match output {
+                Some(path) => fs::write(&path, plain)
+                    .with_context(|| format!("Failed to write to file: {}", path.display()))?,
+                None => io::stdout().write_all(&plain)?,
+            }
+            Ok(())
         }
+    }
+}
 
-        let mut result = HashResult::new(algorithm, digest, &input_type);
-        if let Some(ref path) = input_path {
-            result = result.with_path(path);
+fn run_hook(action: HookAction) -> Result<()> {
+    match action {
+        HookAction::PreCommit { pins: path, quiet } => {
+            let pins = Pins::load(&path)
+                .with_context(|| format!("Failed to read pins file: {}", path.display()))?;
+            let checks = pins.check(pins_root(&path))?;
+            let mut counts = [0usize; 3];
+            for check in &checks {
+                let (index, label) = match check.status {
+                    PinStatus::Verified => (0, "OK"),
+                    PinStatus::Mismatch => (1, "FAILED"),
+                    PinStatus::Missing => (2, "MISSING"),
+                };
+                counts[index] += 1;
+                if !quiet || check.status != PinStatus::Verified {
+                    println!("{}: {}", check.path, label);
+                }
+            }
+            if counts[1] + counts[2] > 0 {
+                eprintln!();
+                eprintln!(
+                    "{} verified, {} failed, {} missing; commit rejected by {}",
+                    counts[0],
+                    counts[1],
+                    counts[2],
+                    path.display()
+                );
+                ExitStatus::Mismatch.exit();
+            }
+            Ok(())
+        }
+        HookAction::Pin { files, algorithm, pins: path } => {
+            let mut pins = if path.exists() {
+                Pins::load(&path)
+                    .with_context(|| format!("Failed to read pins file: {}", path.display()))?
+            } else {
+                Pins::default()
+            };
+            let root = pins_root(&path);
+            for file in &files {
+                if file.is_absolute() {
+                    return Err(usage_error(format!(
+                        "pinned paths must be relative to the pins file: {}",
+                        file.display()
+                    )));
+                }
+                let name = file.to_string_lossy().replace('\\', "/");
+                let name = name.strip_prefix("./").unwrap_or(&name);
+                pins.pin(root, name, algorithm)
+                    .with_context(|| format!("Failed to pin: {}", file.display()))?;
+                println!("{}: pinned", name);
+            }
+            pins.save(&path)
+                .with_context(|| format!("Failed to write pins file: {}", path.display()))?;
+            Ok(())
+        }
+        HookAction::Install { force } => {
+            let output = std::process::Command::new("git")
+                .args(["rev-parse", "--git-path", "hooks/pre-commit"])
+                .output()
+                .context("Failed to run git")?;
+            if !output.status.success() {
+                return Err(usage_error("hash hook install must run inside a Git repository"));
+            }
+            let hook = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim());
+            if hook.exists() && !force {
+                return Err(usage_error(format!(
+                    "{} already exists (use --force to replace it)",
+                    hook.display()
+                )));
+            }
+            if let Some(dir) = hook.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(&hook, PRE_COMMIT_HOOK)
+                .with_context(|| format!("Failed to write hook: {}", hook.display()))?;
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                fs::set_permissions(&hook, fs::Permissions::from_mode(0o755))?;
+            }
+            println!("{}: installed", hook.display());
+            Ok(())
         }
-        results.push(result);
     }
+}
 
-    // Export if requested
-    if let Some(export_path) = &cli.export {
-        export_all_results(&results, export_path, &cli.format)?;
+/// Script installed by `hash hook install`; Git runs it from the work tree root
+const PRE_COMMIT_HOOK: &str = "#!/bin/sh
+# Installed by `hash hook install`
+exec hash hook pre-commit
+";
+
+/// Directory that paths in the pins file at `path` are relative to
+fn pins_root(path: &Path) -> &Path {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
     }
+}
 
-    Ok(())
+/// Writer duplicating everything to several writers
+struct FanOut(Vec<Box<dyn Write>>);
+
+impl Write for FanOut {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for writer in &mut self.0 {
+            writer.write_all(buf)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.iter_mut().try_for_each(|writer| writer.flush())
+    }
 }
 
-fn compute_hash(
-    input: &str,
-    algorithm: Algorithm,
-    force_string: bool,
-) -> Result<(String, String, Option<String>)> {
-    // Check if input is a file path (unless forced to treat as string)
-    if !force_string && Path::new(input).exists() {
-        let digest = hash_file(input, algorithm)
-            .with_context(|| format!("Failed to hash file: {}", input))?;
-        Ok((digest, "file".to_string(), Some(input.to_string())))
+fn run_dkim_body_hash(command: Command) -> Result<()> {
+    let Command::DkimBodyHash {
+        file,
+        canonicalization,
+        algorithm,
+        length,
+        body_only,
+        expect,
+        show_canonical,
+    } = command
+    else {
+        unreachable!("called with dkim-body-hash only")
+    };
+    let canonicalization: BodyCanonicalization =
+        canonicalization.parse().map_err(|e: HashError| usage_error(e.to_string()))?;
+
+    let mut message = Vec::new();
+    if file == "-" {
+        io::stdin().lock().read_to_end(&mut message)?;
+    } else {
+        message = fs::read(&file).with_context(|| format!("Failed to read file: {}", file))?;
+    }
+    let body = if body_only {
+        &message[..]
     } else {
-        let digest = hash_string(input, algorithm)
-            .with_context(|| "Failed to hash string")?;
-        Ok((digest, "string".to_string(), None))
+        dkim::message_body(&message)
+    };
+
+    if show_canonical {
+        io::stdout().write_all(&dkim::canonicalize_body(body, canonicalization))?;
+        return Ok(());
+    }
+    let bh = dkim::body_hash(body, canonicalization, algorithm, length)?;
+    match expect {
+        Some(expected) if expected.split_whitespace().collect::<String>() != bh => {
+            eprintln!("✗ Body hash MISMATCH");
+            eprintln!("Expected: {}", expected);
+            eprintln!("Got:      {}", bh);
+            ExitStatus::Mismatch.exit();
+        }
+        Some(_) => println!("✓ Body hash matches: {}", bh),
+        None => println!("{}", bh),
     }
+    Ok(())
 }
 
-fn display_result(algorithm: Algorithm, digest: &str, input_type: &str, input_path: Option<&str>) {
-    println!("Algorithm:  {}", algorithm.name().to_uppercase());
-    println!("Input type: {}", input_type);
-    if let Some(path) = input_path {
-        println!("File path:  {}", path);
+#[cfg(feature = "executable")]
+fn run_executable(path: &Path, algorithm: Algorithm, expect: Option<&str>, quiet: bool) -> Result<()> {
+    use hashing::executable::{self, ExecutableFormat};
+
+    let data = fs::read(path).with_context(|| format!("Failed to read file: {}", path.display()))?;
+    let (label, value) = match executable::detect_format(&data) {
+        Some(ExecutableFormat::Pe) => {
+            let digest = executable::authenticode_digest(&data, algorithm)?;
+            if !quiet {
+                let signed = executable::is_authenticode_signed(&data)?;
+                println!("Format:     PE ({})", if signed { "signed" } else { "unsigned" });
+            }
+            (format!("Authenticode {}", algorithm.display_name()), hex::encode(digest))
+        }
+        Some(ExecutableFormat::Elf) => {
+            let id = executable::elf_build_id(&data)?.ok_or_else(|| {
+                HashError::InvalidInput(format!("{} has no GNU build-id", path.display()))
+            })?;
+            if !quiet {
+                println!("Format:     ELF");
+            }
+            ("Build ID".to_string(), hex::encode(id))
+        }
+        None => {
+            return Err(HashError::InvalidInput(format!(
+                "{} is neither a PE nor an ELF file",
+                path.display()
+            ))
+            .into())
+        }
+    };
+
+    if quiet {
+        println!("{}", value);
+    } else {
+        println!("{:<11} {}", format!("{}:", label), value);
     }
-    println!("Hash:       {}", digest);
+    if let Some(expected) = expect {
+        if !expected.trim().eq_ignore_ascii_case(&value) {
+            if !quiet {
+                eprintln!("✗ Expected: {}", expected.trim());
+            }
+            ExitStatus::Mismatch.exit();
+        }
+    }
+    Ok(())
 }
 
-fn export_result(result: &HashResult, path: &Path, format: &ExportFormat) -> Result<()> {
-    let content = match format {
-        ExportFormat::Text => result.digest.clone(),
-        ExportFormat::Json => result.to_json()?,
-        ExportFormat::Checksum => {
-            if let Some(ref file_path) = result.input_path {
-                format!("{}  {}", result.digest, file_path)
-            } else {
-                result.digest.clone()
+#[cfg(not(feature = "executable"))]
+fn run_executable(
+    _path: &Path,
+    _algorithm: Algorithm,
+    _expect: Option<&str>,
+    _quiet: bool,
+) -> Result<()> {
+    Err(usage_error(
+        "hash executable requires a build with the `executable` feature",
+    ))
+}
+
+fn run_sbom(command: Command) -> Result<()> {
+    let Command::Sbom { dir, format, mut algorithms, verify, output, quiet } = command else {
+        unreachable!("run_sbom called with another command");
+    };
+
+    if let Some(document) = verify {
+        let contents = fs::read_to_string(&document)
+            .with_context(|| format!("Failed to read SBOM: {}", document.display()))?;
+        let report = sbom::verify_sbom(&contents, &dir)?;
+        let mut counts = [0usize; 4];
+        for check in &report.checks {
+            let (index, label) = match check.status {
+                SbomStatus::Verified => (0, "OK"),
+                SbomStatus::Mismatch => (1, "FAILED"),
+                SbomStatus::Missing => (2, "MISSING"),
+                SbomStatus::Unchecked => (3, "UNCHECKED"),
+            };
+            counts[index] += 1;
+            if !quiet || matches!(check.status, SbomStatus::Mismatch | SbomStatus::Missing) {
+                println!("{}: {}", check.path, label);
             }
         }
+        if !quiet {
+            eprintln!();
+            eprintln!(
+                "{} verified, {} failed, {} missing, {} without a supported checksum ({})",
+                counts[0], counts[1], counts[2], counts[3], report.format
+            );
+        }
+        if !report.is_ok() {
+            ExitStatus::Mismatch.exit();
+        }
+        return Ok(());
+    }
+
+    if algorithms.is_empty() {
+        algorithms = vec![Algorithm::Sha1, Algorithm::Sha256];
+    }
+    let files = sbom::scan_tree(&dir, &WalkOptions::new(), &algorithms)?;
+    let fragment = sbom::fragment(format, &files)?;
+    let json = serde_json::to_string_pretty(&fragment).context("Failed to serialize SBOM")?;
+    match output {
+        Some(path) => fs::write(&path, json + "\n")
+            .with_context(|| format!("Failed to write SBOM: {}", path.display()))?,
+        None => println!("{}", json),
+    }
+    Ok(())
+}
+
+#[cfg(feature = "sigstore")]
+fn run_verify_blob(command: Command) -> Result<()> {
+    use sigstore::{BlobSignature, BlobVerification};
+
+    let Command::VerifyBlob { file, signature, certificate, bundle } = command else {
+        unreachable!("run_verify_blob called with another command");
+    };
+    let read = |path: &Path| {
+        fs::read(path).with_context(|| format!("Failed to read file: {}", path.display()))
+    };
+    let signature = match (bundle, signature, certificate) {
+        (Some(bundle), _, _) => {
+            BlobSignature::from_bundle(&String::from_utf8_lossy(&read(&bundle)?))?
+        }
+        (None, Some(signature), Some(certificate)) => {
+            BlobSignature::from_detached(&read(&signature)?, &read(&certificate)?)?
+        }
+        _ => return Err(usage_error("pass --bundle, or --signature with --certificate")),
     };
 
-    // Ensure parent directory exists
-    if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent)
-            .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+    match sigstore::verify_blob_file(&file, &signature)? {
+        BlobVerification::Verified => println!("✓ Verified OK: {}", file.display()),
+        BlobVerification::DigestMismatch => {
+            eprintln!("✗ Digest in the bundle does not match {}", file.display());
+            ExitStatus::Mismatch.exit();
+        }
+        BlobVerification::BadSignature => {
+            eprintln!("✗ Invalid signature for {}", file.display());
+            ExitStatus::Mismatch.exit();
+        }
     }
+    Ok(())
+}
 
-    // Write to file
-    fs::write(path, content.as_bytes())
-        .with_context(|| format!("Failed to write to file: {}", path.display()))?;
+#[cfg(not(feature = "sigstore"))]
+fn run_verify_blob(_command: Command) -> Result<()> {
+    Err(usage_error("hash verify-blob requires a build with the `sigstore` feature"))
+}
 
-    println!("Exported to: {}", path.display());
+#[cfg(feature = "archive")]
+fn run_jar(path: &Path, quiet: bool) -> Result<()> {
+    let report = hashing::jar::verify_jar_file(path)
+        .with_context(|| format!("Failed to verify archive: {}", path.display()))?;
+    if report.entries.is_empty() && report.signature_files.is_empty() {
+        println!("{}: no entry digests in the manifest (not signed)", path.display());
+        return Ok(());
+    }
+    print_entry_checks(report.signature_files.iter().chain(&report.entries), quiet);
+    if !report.is_ok() {
+        ExitStatus::Mismatch.exit();
+    }
     Ok(())
 }
 
-fn export_all_results(results: &[HashResult], base_path: &Path, format: &ExportFormat) -> Result<()> {
-    match format {
-        ExportFormat::Json => {
-            let json = serde_json::to_string_pretty(results)
-                .context("Failed to serialize results to JSON")?;
-            fs::write(base_path, json.as_bytes())
-                .with_context(|| format!("Failed to write to file: {}", base_path.display()))?;
-            println!("Exported all results to: {}", base_path.display());
-        }
-        ExportFormat::Text | ExportFormat::Checksum => {
-            let parent = base_path.parent().unwrap_or_else(|| Path::new("."));
-            let stem = base_path.file_stem().unwrap_or_default().to_string_lossy();
-            let ext = base_path.extension().unwrap_or_default().to_string_lossy();
-
-            for result in results {
-                let filename = if ext.is_empty() {
-                    format!("{}.{}", stem, result.algorithm)
-                } else {
-                    format!("{}.{}.{}", stem, result.algorithm, ext)
-                };
-                let file_path = parent.join(filename);
+#[cfg(feature = "archive")]
+fn run_package(path: &Path, quiet: bool) -> Result<()> {
+    let report = hashing::package::verify_package_file(path)
+        .with_context(|| format!("Failed to verify package: {}", path.display()))?;
+    if report.checks.is_empty() {
+        println!("{}: no embedded digests to check", path.display());
+        return Ok(());
+    }
+    print_entry_checks(report.checks.iter(), quiet);
+    if !report.is_ok() {
+        ExitStatus::Mismatch.exit();
+    }
+    Ok(())
+}
 
-                let content = match format {
-                    ExportFormat::Text => result.digest.clone(),
-                    ExportFormat::Checksum => {
-                        if let Some(ref path) = result.input_path {
-                            format!("{}  {}", result.digest, path)
-                        } else {
-                            result.digest.clone()
-                        }
-                    }
-                    _ => unreachable!(),
-                };
+/// One `name: STATUS` line per check, then a summary on stderr
+#[cfg(feature = "archive")]
+fn print_entry_checks<'a>(
+    checks: impl Iterator<Item = &'a hashing::jar::EntryCheck>,
+    quiet: bool,
+) {
+    use hashing::jar::EntryStatus;
 
-                fs::write(&file_path, content.as_bytes())
-                    .with_context(|| format!("Failed to write to file: {}", file_path.display()))?;
-            }
-            println!("Exported all results to: {}.*", base_path.display());
+    let mut counts = [0usize; 4];
+    for check in checks {
+        let (index, label) = match check.status {
+            EntryStatus::Verified => (0, "OK"),
+            EntryStatus::Mismatch => (1, "FAILED"),
+            EntryStatus::Missing => (2, "MISSING"),
+            EntryStatus::Unlisted => (3, "UNLISTED"),
+        };
+        counts[index] += 1;
+        if !quiet || matches!(check.status, EntryStatus::Mismatch | EntryStatus::Missing) {
+            println!("{}: {}", check.name, label);
         }
     }
 
-    Ok(())
+    if !quiet {
+        eprintln!();
+        eprintln!(
+            "{} verified, {} failed, {} missing, {} unlisted",
+            counts[0], counts[1], counts[2], counts[3]
+        );
+    }
+}
+
+#[cfg(not(feature = "archive"))]
+fn run_jar(_path: &Path, _quiet: bool) -> Result<()> {
+    Err(usage_error("hash jar requires a build with the `archive` feature"))
 }
 
-fn compare_inputs(input1: &str, input2: &str, cli: &Cli) -> Result<()> {
-    if cli.all_algorithms {
-        compare_all_algorithms(input1, input2, cli)
+#[cfg(not(feature = "archive"))]
+fn run_package(_path: &Path, _quiet: bool) -> Result<()> {
+    Err(usage_error("hash package requires a build with the `archive` feature"))
+}
+
+fn compare_inputs(input1: &str, input2: &str, args: &HashArgs, key: Option<Key>) -> Result<()> {
+    if args.output.all_algorithms {
+        compare_all_algorithms(input1, input2, args, key)
     } else {
-        compare_single_algorithm(input1, input2, cli)
+        compare_single_algorithm(input1, input2, args, key)
     }
 }
 
-fn compare_single_algorithm(input1: &str, input2: &str, cli: &Cli) -> Result<()> {
-    let algorithm = Algorithm::from_str(&cli.algorithm)
-        .with_context(|| format!("Invalid algorithm: {}", cli.algorithm))?;
+fn compare_single_algorithm(
+    input1: &str,
+    input2: &str,
+    args: &HashArgs,
+    key: Option<Key>,
+) -> Result<()> {
+    let algorithm = args.output.algorithm;
 
-    let (hash1, type1, path1) = compute_hash(input1, algorithm, cli.string)?;
-    let (hash2, type2, path2) = compute_hash(input2, algorithm, cli.string)?;
+    let policy = args.mode.policy();
+    let (canonical, strip, reads) = (
+        args.output.canonical,
+        args.output.strip_newline,
+        args.output.reads,
+    );
+    let (hash1, type1, path1) =
+        compute_hash(input1, algorithm, policy, key, canonical, strip, reads)?;
+    let (hash2, type2, path2) =
+        compute_hash(input2, algorithm, policy, key, canonical, strip, reads)?;
 
     let matches = hash1 == hash2;
 
-    if cli.quiet {
-        std::process::exit(if matches { 0 } else { 1 });
+    if args.output.quiet {
+        if matches {
+            ExitStatus::Success
+        } else {
+            ExitStatus::Mismatch
+        }
+        .exit();
     }
 
     // Display comparison results
-    println!("Comparing using {}", algorithm.name().to_uppercase());
+    println!("Comparing using {}", algorithm_label(algorithm, key));
     println!();
     println!("Input 1: {} ({})", path1.as_deref().unwrap_or(input1), type1);
     println!("Hash 1:  {}", hash1);
@@ -346,23 +4556,40 @@ fn compare_single_algorithm(input1: &str, input2: &str, cli: &Cli) -> Result<()>
         Ok(())
     } else {
         println!("✗ NO MATCH - Inputs are different");
-        std::process::exit(1);
+        ExitStatus::Mismatch.exit();
     }
 }
 
-fn compare_all_algorithms(input1: &str, input2: &str, cli: &Cli) -> Result<()> {
+fn compare_all_algorithms(
+    input1: &str,
+    input2: &str,
+    args: &HashArgs,
+    key: Option<Key>,
+) -> Result<()> {
     let mut all_match = true;
     let mut match_count = 0;
     let mut mismatch_count = 0;
 
-    if !cli.quiet {
+    let algorithms = selected_algorithms(key, &args.output.families)?;
+    let width = label_width(&algorithms, key);
+
+    if !args.output.quiet {
         println!("Comparing with all algorithms...");
-        println!();
     }
 
-    for algorithm in Algorithm::all() {
-        let (hash1, _, _) = compute_hash(input1, algorithm, cli.string)?;
-        let (hash2, _, _) = compute_hash(input2, algorithm, cli.string)?;
+    let policy = args.mode.policy();
+    let (canonical, strip, reads) = (
+        args.output.canonical,
+        args.output.strip_newline,
+        args.output.reads,
+    );
+    let (hashes1, _, _) =
+        compute_hashes(input1, &algorithms, policy, key, canonical, strip, reads)?;
+    let (hashes2, _, _) =
+        compute_hashes(input2, &algorithms, policy, key, canonical, strip, reads)?;
+
+    let mut family = None;
+    for ((algorithm, hash1), hash2) in algorithms.into_iter().zip(hashes1).zip(hashes2) {
 
         let matches = hash1 == hash2;
         
@@ -373,7 +4600,7 @@ fn compare_all_algorithms(input1: &str, input2: &str, cli: &Cli) -> Result<()> {
             all_match = false;
         }
 
-        if !cli.quiet {
+        if !args.output.quiet {
             let status = if matches { "✓" } else { "✗" };
             let diff_info = if matches { 
                 String::new() 
@@ -381,16 +4608,22 @@ fn compare_all_algorithms(input1: &str, input2: &str, cli: &Cli) -> Result<()> {
                 format!("{} ≠ {}", &hash1[..16], &hash2[..16])
             };
             
-            println!("{} {:<15} {} | {}", 
+            if family != Some(algorithm.family()) {
+                family = Some(algorithm.family());
+                println!();
+                println!("{}", algorithm.family().display_name());
+            }
+            println!("  {} {:<width$} {} | {}",
                 status,
-                format!("{}:", algorithm.name().to_uppercase()),
+                format!("{}:", algorithm_label(algorithm, key)),
                 if matches { "MATCH" } else { "DIFFERENT" },
-                diff_info
+                diff_info,
+                width = width
             );
         }
     }
 
-    if !cli.quiet {
+    if !args.output.quiet {
         println!();
         println!("Results: {} matches, {} mismatches", match_count, mismatch_count);
         
@@ -404,8 +4637,564 @@ fn compare_all_algorithms(input1: &str, input2: &str, cli: &Cli) -> Result<()> {
     if all_match {
         Ok(())
     } else {
+        ExitStatus::Mismatch.exit();
+    }
+}
+
+/// Checksum tools whose command line this binary takes on when run under
+/// their name, through a link or a renamed copy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SumTool {
+    /// GNU coreutils `b2sum`: BLAKE2b, lengths in bits
+    B2sum,
+    /// The BLAKE3 project's `b3sum`: lengths in bytes, keyed and key derivation modes
+    B3sum,
+}
+
+impl SumTool {
+    /// The tool `argv[0]` names, if it names one
+    fn invoked_as(arg0: &str) -> Option<Self> {
+        match Path::new(arg0).file_stem()?.to_str()? {
+            "b2sum" => Some(SumTool::B2sum),
+            "b3sum" => Some(SumTool::B3sum),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            SumTool::B2sum => "b2sum",
+            SumTool::B3sum => "b3sum",
+        }
+    }
+
+    fn algorithm(self) -> Algorithm {
+        match self {
+            SumTool::B2sum => Algorithm::Blake2b512,
+            SumTool::B3sum => Algorithm::Blake3,
+        }
+    }
+
+    /// Tag of a `--tag` line for a digest of `len` bytes
+    fn tag(self, len: usize) -> String {
+        match self {
+            SumTool::B2sum if len == 64 => "BLAKE2b".to_string(),
+            SumTool::B2sum => format!("BLAKE2b-{}", len * 8),
+            SumTool::B3sum => "BLAKE3".to_string(),
+        }
+    }
+
+    /// Digest length in bytes that `tag` announces, if it is one of this tool's tags
+    fn tagged_length(self, tag: &str) -> Option<usize> {
+        match (self, tag) {
+            (SumTool::B2sum, "BLAKE2b") => Some(64),
+            (SumTool::B2sum, tag) => {
+                let bits: usize = tag.strip_prefix("BLAKE2b-")?.parse().ok()?;
+                (bits % 8 == 0 && (8..=512).contains(&bits)).then_some(bits / 8)
+            }
+            (SumTool::B3sum, "BLAKE3") => Some(32),
+            (SumTool::B3sum, _) => None,
+        }
+    }
+
+    /// Whether a listed digest of `len` bytes is one `--check` can verify
+    fn checks_length(self, len: usize) -> bool {
+        match self {
+            SumTool::B2sum => (1..=64).contains(&len),
+            SumTool::B3sum => len == 32,
+        }
+    }
+}
+
+/// `b2sum` command line, as in GNU coreutils
+#[derive(Parser)]
+#[command(name = "b2sum", version)]
+#[command(about = "Print or check BLAKE2b (512-bit) checksums")]
+struct B2sumArgs {
+    /// Files to hash, or checksum lists with --check; none or `-` reads stdin
+    #[arg(value_name = "FILE")]
+    files: Vec<String>,
+
+    /// Read in binary mode, marking each line with `*` (the digest is the same)
+    #[arg(short, long, overrides_with = "text")]
+    binary: bool,
+
+    /// Read checksums from the FILEs and check them
+    #[arg(short, long)]
+    check: bool,
+
+    /// Digest length in bits: a multiple of 8 up to 512 (0 means 512)
+    #[arg(short, long, value_name = "BITS")]
+    length: Option<usize>,
+
+    /// Create a BSD-style checksum line
+    #[arg(long, conflicts_with_all = ["check", "text"])]
+    tag: bool,
+
+    /// Read in text mode (the default)
+    #[arg(short, long, overrides_with = "binary")]
+    text: bool,
+
+    /// End each output line with NUL instead of newline, and don't escape file names
+    #[arg(short, long)]
+    zero: bool,
+
+    /// Don't fail or report status for missing files
+    #[arg(long, requires = "check")]
+    ignore_missing: bool,
+
+    /// Don't print OK for each successfully verified file
+    #[arg(long, requires = "check")]
+    quiet: bool,
+
+    /// Don't output anything; the exit status shows success
+    #[arg(long, requires = "check")]
+    status: bool,
+
+    /// Exit non-zero for improperly formatted checksum lines
+    #[arg(long, requires = "check")]
+    strict: bool,
+
+    /// Warn about improperly formatted checksum lines
+    #[arg(short, long, requires = "check")]
+    warn: bool,
+}
+
+/// `b3sum` command line, as in the BLAKE3 project
+#[derive(Parser)]
+#[command(name = "b3sum", version)]
+#[command(about = "Print or check BLAKE3 checksums")]
+struct B3sumArgs {
+    /// Files to hash, or checksum lists with --check; none or `-` reads stdin
+    #[arg(value_name = "FILE")]
+    files: Vec<String>,
+
+    /// Digest length in bytes (default 32); longer output extends the shorter
+    #[arg(short, long, value_name = "LEN")]
+    length: Option<usize>,
+
+    /// Threads hashing a large file (a hint; needs a build with the `rayon` feature)
+    #[arg(long, value_name = "NUM")]
+    num_threads: Option<usize>,
+
+    /// Use the keyed mode, reading the 32-byte key from stdin
+    #[arg(long, conflicts_with = "derive_key")]
+    keyed: bool,
+
+    /// Use the key derivation mode, with CONTEXT as the context string
+    #[arg(long, value_name = "CONTEXT")]
+    derive_key: Option<String>,
+
+    /// Don't memory-map files
+    #[arg(long)]
+    no_mmap: bool,
+
+    /// Omit file names from the output
+    #[arg(long, conflicts_with = "tag")]
+    no_names: bool,
+
+    /// Write the raw digest bytes instead of hex; takes a single input
+    #[arg(long, conflicts_with_all = ["no_names", "tag"])]
+    raw: bool,
+
+    /// Output BSD-style checksum lines: BLAKE3 (<file>) = <hash>
+    #[arg(long)]
+    tag: bool,
+
+    /// Read BLAKE3 checksums from the FILEs and check them
+    #[arg(
+        short,
+        long,
+        conflicts_with_all = ["keyed", "derive_key", "length", "no_names", "raw", "tag"]
+    )]
+    check: bool,
+
+    /// Skip printing OK for each checked file
+    #[arg(long, requires = "check")]
+    quiet: bool,
+}
+
+/// The key a `b3sum` invocation hashes with
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum SumKey {
+    /// `--keyed`, with the key read from stdin
+    Keyed(Vec<u8>),
+    /// `--derive-key` and its context string
+    Context(String),
+}
+
+/// How a computed digest is printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SumStyle {
+    /// `<hex>  <file>`, or `<hex> *<file>` in binary mode
+    Plain,
+    /// `--tag`: `<TAG> (<file>) = <hex>`
+    Tag,
+    /// `--no-names`: the hex digest alone
+    NoNames,
+    /// `--raw`: the digest bytes
+    Raw,
+}
+
+/// Options of `b2sum --check` and `b3sum --check`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct SumCheck {
+    ignore_missing: bool,
+    quiet: bool,
+    status: bool,
+    strict: bool,
+    warn: bool,
+}
+
+/// A `b2sum` or `b3sum` invocation, normalized
+struct SumJob {
+    tool: SumTool,
+    files: Vec<String>,
+    /// Digest length in bytes, if not the algorithm's usual one
+    length: Option<usize>,
+    key: Option<SumKey>,
+    style: SumStyle,
+    binary: bool,
+    zero: bool,
+    no_mmap: bool,
+    reads: ReadArgs,
+    check: Option<SumCheck>,
+}
+
+impl SumJob {
+    fn from_b2sum(args: B2sumArgs) -> Result<Self> {
+        let length = match args.length {
+            None | Some(0) | Some(512) => None,
+            Some(bits) if bits % 8 != 0 => {
+                return Err(usage_error(format!("invalid length {}: not a multiple of 8", bits)))
+            }
+            Some(bits) if bits > 512 => {
+                return Err(usage_error(format!(
+                    "invalid length {}: BLAKE2b digests are at most 512 bits",
+                    bits
+                )))
+            }
+            Some(bits) => Some(bits / 8),
+        };
+        let check = args.check.then_some(SumCheck {
+            ignore_missing: args.ignore_missing,
+            quiet: args.quiet,
+            status: args.status,
+            strict: args.strict,
+            warn: args.warn,
+        });
+        Ok(Self {
+            tool: SumTool::B2sum,
+            files: args.files,
+            length,
+            key: None,
+            style: if args.tag { SumStyle::Tag } else { SumStyle::Plain },
+            binary: args.binary,
+            zero: args.zero,
+            no_mmap: false,
+            reads: ReadArgs::default(),
+            check,
+        })
+    }
+
+    fn from_b3sum(args: B3sumArgs) -> Result<Self> {
+        let length = match args.length {
+            Some(0) => return Err(usage_error("--length must be at least 1")),
+            Some(32) | None => None,
+            Some(bytes) => Some(bytes),
+        };
+        if args.raw && args.files.len() > 1 {
+            return Err(usage_error("--raw takes a single input"));
+        }
+        let key = if args.keyed {
+            if args.files.is_empty() || args.files.iter().any(|file| file == "-") {
+                return Err(usage_error(
+                    "--keyed reads the key from stdin, so stdin cannot also be an input",
+                ));
+            }
+            Some(SumKey::Keyed(read_sum_key(io::stdin().lock())?))
+        } else {
+            args.derive_key.map(SumKey::Context)
+        };
+        let style = if args.raw {
+            SumStyle::Raw
+        } else if args.no_names {
+            SumStyle::NoNames
+        } else if args.tag {
+            SumStyle::Tag
+        } else {
+            SumStyle::Plain
+        };
+        let check = args.check.then_some(SumCheck {
+            quiet: args.quiet,
+            ..SumCheck::default()
+        });
+        Ok(Self {
+            tool: SumTool::B3sum,
+            files: args.files,
+            length,
+            key,
+            style,
+            binary: false,
+            zero: false,
+            no_mmap: args.no_mmap,
+            reads: ReadArgs {
+                threads: args.num_threads,
+                ..ReadArgs::default()
+            },
+            check,
+        })
+    }
+
+    /// The inputs, stdin if none were named
+    fn files(&self) -> Vec<&str> {
+        if self.files.is_empty() {
+            vec!["-"]
+        } else {
+            self.files.iter().map(String::as_str).collect()
+        }
+    }
+
+    /// Digest of `file` (stdin for `-`), `output_size` bytes long if given
+    fn digest(&self, file: &str, output_size: Option<usize>) -> hashing::Result<Digest> {
+        let reads = ReadArgs {
+            output_size,
+            ..self.reads
+        };
+        let key = match self.key {
+            Some(SumKey::Keyed(ref key)) => Key::new(Some(key), true),
+            _ => None,
+        };
+        let mut hashing = reads.hashing(self.tool.algorithm(), key);
+        if let Some(SumKey::Context(ref context)) = self.key {
+            hashing = hashing.derive_key(context.as_str());
+        }
+        if file == "-" {
+            hashing.hash_reader_raw(io::stdin().lock())
+        } else if self.no_mmap {
+            hashing.hash_reader_raw(File::open(file)?)
+        } else {
+            hashing.hash_file_raw(file)
+        }
+    }
+
+    /// Print the digest of every input; false if one could not be read
+    fn print_sums(&self) -> Result<bool> {
+        let mut out = io::stdout().lock();
+        let mut ok = true;
+        for file in self.files() {
+            match self.digest(file, self.length) {
+                Ok(digest) => self.write_sum(&mut out, file, &digest)?,
+                Err(err) => {
+                    eprintln!("{}: {}: {}", self.tool.name(), file, sum_error(&err));
+                    ok = false;
+                }
+            }
+        }
+        out.flush()?;
+        Ok(ok)
+    }
+
+    fn write_sum(&self, out: &mut impl Write, file: &str, digest: &Digest) -> io::Result<()> {
+        let end = if self.zero { '\0' } else { '\n' };
+        let (prefix, name) = if self.zero {
+            ("", file.to_string())
+        } else {
+            checksum::escape_name(file)
+        };
+        let hex = digest.to_hex();
+        match self.style {
+            SumStyle::Plain => {
+                let mode = if self.binary { '*' } else { ' ' };
+                write!(out, "{}{} {}{}{}", prefix, hex, mode, name, end)
+            }
+            SumStyle::Tag => {
+                let tag = self.tool.tag(digest.len());
+                write!(out, "{}{} ({}) = {}{}", prefix, tag, name, hex, end)
+            }
+            SumStyle::NoNames => write!(out, "{}{}", hex, end),
+            SumStyle::Raw => out.write_all(digest.as_bytes()),
+        }
+    }
+
+    /// Expected digest and file name of a checksum line this tool would
+    /// have written, plain or tagged
+    fn parse_line(&self, line: &str) -> Option<(Vec<u8>, String)> {
+        let (escaped, line) = match line.strip_prefix('\\') {
+            Some(line) => (true, line),
+            None => (false, line),
+        };
+        let tagged = line
+            .split_once(" (")
+            .and_then(|(tag, rest)| Some((self.tool.tagged_length(tag)?, rest)));
+        let (hex, name) = match tagged {
+            Some((len, rest)) => {
+                let (name, hex) = rest.rsplit_once(") = ")?;
+                (hex.len() == len * 2).then_some((hex, name))?
+            }
+            None => {
+                let (hex, rest) = line.split_once(' ')?;
+                (hex, rest.strip_prefix([' ', '*'])?)
+            }
+        };
+        let expected = hex::decode(hex).ok()?;
+        let length_fits = match self.length {
+            Some(len) => expected.len() == len,
+            None => self.tool.checks_length(expected.len()),
+        };
+        let name = if escaped {
+            checksum::unescape_name(name)?
+        } else {
+            name.to_string()
+        };
+        (length_fits && !name.is_empty()).then_some((expected, name))
+    }
+
+    /// Verify the files `list` names; false if any failed or none could be
+    fn check_list(&self, list: &str, check: SumCheck) -> Result<bool> {
+        let tool = self.tool.name();
+        let read = if list == "-" {
+            let mut bytes = Vec::new();
+            io::stdin().lock().read_to_end(&mut bytes).map(|_| bytes)
+        } else {
+            fs::read(list)
+        };
+        let bytes = match read {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                eprintln!("{}: {}: {}", tool, list, err);
+                return Ok(false);
+            }
+        };
+        let text = String::from_utf8_lossy(&bytes);
+        let delimiter = if self.zero { '\0' } else { '\n' };
+        let (mut proper, mut improper, mut verified, mut failed, mut unreadable) = (0, 0, 0, 0, 0);
+        for (number, line) in text.split(delimiter).enumerate() {
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((expected, name)) = self.parse_line(line) else {
+                improper += 1;
+                if check.warn {
+                    eprintln!(
+                        "{}: {}: {}: improperly formatted {} checksum line",
+                        tool,
+                        list,
+                        number + 1,
+                        self.tool.tag(self.tool.algorithm().output_size())
+                    );
+                }
+                continue;
+            };
+            proper += 1;
+            let (prefix, shown) = checksum::escape_name(&name);
+            let output_size = Some(expected.len()).filter(|len| {
+                *len != self.tool.algorithm().output_size()
+            });
+            match self.digest(&name, output_size) {
+                Ok(digest) => {
+                    verified += 1;
+                    let matches = digest.as_bytes() == expected.as_slice();
+                    if !matches {
+                        failed += 1;
+                    }
+                    let silent = check.status || (matches && check.quiet);
+                    if !silent {
+                        let verdict = if matches { "OK" } else { "FAILED" };
+                        println!("{}{}: {}", prefix, shown, verdict);
+                    }
+                }
+                Err(HashError::Io(ref err))
+                    if check.ignore_missing && err.kind() == io::ErrorKind::NotFound => {}
+                Err(err) => {
+                    unreadable += 1;
+                    if !check.status {
+                        eprintln!("{}: {}: {}", tool, name, sum_error(&err));
+                        println!("{}{}: FAILED open or read", prefix, shown);
+                    }
+                }
+            }
+        }
+
+        let warn = |message: String| {
+            if !check.status {
+                eprintln!("{}: WARNING: {}", tool, message);
+            }
+        };
+        let plural = |count: usize, singular: &str, plural: &str| match count {
+            1 => format!("1 {}", singular),
+            _ => format!("{} {}", count, plural),
+        };
+        if proper == 0 {
+            eprintln!("{}: {}: no properly formatted checksum lines found", tool, list);
+            return Ok(false);
+        }
+        if improper > 0 {
+            let lines = plural(improper, "line is", "lines are");
+            warn(format!("{} improperly formatted", lines));
+        }
+        if unreadable > 0 {
+            let files = plural(unreadable, "listed file", "listed files");
+            warn(format!("{} could not be read", files));
+        }
+        if failed > 0 {
+            let checksums = plural(failed, "computed checksum", "computed checksums");
+            warn(format!("{} did NOT match", checksums));
+        }
+        if check.ignore_missing && verified + unreadable == 0 {
+            if !check.status {
+                eprintln!("{}: {}: no file was verified", tool, list);
+            }
+            return Ok(false);
+        }
+        Ok(failed + unreadable == 0 && !(check.strict && improper > 0))
+    }
+}
+
+/// Run as `b2sum` or `b3sum`, exiting 1 on any failure as they do
+fn run_sum(tool: SumTool, os_args: &[String]) -> Result<()> {
+    let job = match tool {
+        SumTool::B2sum => SumJob::from_b2sum(B2sumArgs::parse_from(os_args))?,
+        SumTool::B3sum => SumJob::from_b3sum(B3sumArgs::parse_from(os_args))?,
+    };
+    let ok = match job.check {
+        Some(check) => {
+            let mut ok = true;
+            for list in job.files() {
+                ok &= job.check_list(list, check)?;
+            }
+            ok
+        }
+        None => job.print_sums()?,
+    };
+    if !ok {
         std::process::exit(1);
     }
+    Ok(())
+}
+
+/// `err` the way the coreutils report it, without the I/O error prefix
+fn sum_error(err: &HashError) -> String {
+    match err {
+        HashError::Io(err) => err.to_string(),
+        err => err.to_string(),
+    }
+}
+
+/// The 32-byte `b3sum --keyed` key, which must be all `reader` yields
+fn read_sum_key(reader: impl Read) -> Result<Vec<u8>> {
+    let mut key = Vec::new();
+    reader.take(33).read_to_end(&mut key)?;
+    if key.len() != 32 {
+        let got = if key.len() > 32 { "more".to_string() } else { key.len().to_string() };
+        return Err(HashError::InvalidInput(format!(
+            "--keyed needs exactly 32 key bytes on stdin, got {}",
+            got
+        ))
+        .into());
+    }
+    Ok(key)
 }
 
 #[cfg(test)]
@@ -414,9 +5203,289 @@ mod tests {
 
     #[test]
     fn test_compute_hash_string() {
-        let (digest, input_type, path) = compute_hash("test", Algorithm::Sha256, true).unwrap();
+        let reads = ReadArgs::default();
+        let (digest, input_type, path) =
+            compute_hash("test", Algorithm::Sha256, InputPolicy::String, None, None, false, reads)
+                .unwrap();
         assert_eq!(input_type, "string");
         assert!(path.is_none());
         assert_eq!(digest.len(), 64);
+
+        let policy = InputPolicy::String;
+        let (stripped, _, _) =
+            compute_hash("test\r\n", Algorithm::Sha256, policy, None, None, true, reads).unwrap();
+        assert_eq!(stripped, digest);
+        let (kept, _, _) =
+            compute_hash("test\n", Algorithm::Sha256, policy, None, None, false, reads).unwrap();
+        assert_ne!(kept, digest);
+    }
+
+    #[test]
+    fn test_input_env() {
+        std::env::set_var("HASH_TEST_SECRET", "hunter2");
+        let cli = Cli::try_parse_from(["hash", "--input-env", "HASH_TEST_SECRET"]).unwrap();
+        let secret = read_secret_input(&cli.args).unwrap();
+        assert_eq!(secret.as_deref(), Some("hunter2"));
+
+        let cli = Cli::try_parse_from(["hash", "--input-env", "HASH_TEST_UNSET_VAR"]).unwrap();
+        assert!(read_secret_input(&cli.args).is_err());
+
+        // A positional input alongside a secret source is ambiguous
+        assert!(Cli::try_parse_from(["hash", "x", "--input-env", "HASH_TEST_SECRET"]).is_err());
+    }
+
+    #[test]
+    fn test_key_sources() {
+        let cli = Cli::try_parse_from(["hash", "msg", "-s", "--key-hex", "6b6579"]).unwrap();
+        assert_eq!(cli.args.output.key.load(true).unwrap().as_deref(), Some(&b"key"[..]));
+
+        let (mac, _, _) = compute_hash(
+            "msg",
+            Algorithm::Sha256,
+            InputPolicy::String,
+            Key::new(Some(b"key"), false),
+            None,
+            false,
+            ReadArgs::default(),
+        )
+        .unwrap();
+        assert_ne!(mac, hashing::hash_string("msg", Algorithm::Sha256).unwrap());
+
+        // --keyed uses BLAKE2's own keyed mode instead of HMAC-BLAKE2b
+        let keyed_mac = |blake| {
+            let key = Key::new(Some(b"key"), blake);
+            let policy = InputPolicy::String;
+            let reads = ReadArgs::default();
+            compute_hash("msg", Algorithm::Blake2b512, policy, key, None, false, reads).unwrap().0
+        };
+        let hmac = hmac::hmac_string(b"key", "msg", Algorithm::Blake2b512).unwrap();
+        assert_eq!(keyed_mac(false), hmac);
+        assert_ne!(keyed_mac(true), keyed_mac(false));
+        let cli = Cli::try_parse_from(["hash", "msg", "--keyed", "--key", "key"]).unwrap();
+        assert!(cli.args.output.keyed);
+        assert_eq!(cli.args.output.key.hmac_key.as_deref(), Some("key"));
+
+        // Only one key source may be given
+        assert!(Cli::try_parse_from(["hash", "msg", "--key-hex", "00", "--key-env", "K"]).is_err());
+
+        let cli = Cli::try_parse_from(["hash", "msg", "--hmac-key", "key"]).unwrap();
+        assert_eq!(cli.args.output.key.load(true).unwrap().as_deref(), Some(&b"key"[..]));
+        let cli = Cli::try_parse_from(["hash", "msg", "--hmac-key-file", "k.key"]).unwrap();
+        assert_eq!(cli.args.output.key.key_file.as_deref(), Some(Path::new("k.key")));
+    }
+
+    #[test]
+    fn test_exit_status_classification() {
+        let io = anyhow::Error::from(HashError::Io(io::Error::from(io::ErrorKind::NotFound)))
+            .context("Failed to hash file");
+        assert_eq!(ExitStatus::for_error(&io), ExitStatus::Io);
+
+        let parse = anyhow::Error::from(HashError::UnsupportedAlgorithm("nope".into()));
+        assert_eq!(ExitStatus::for_error(&parse), ExitStatus::Parse);
+
+        let usage = usage_error("--resume requires --recursive");
+        assert_eq!(ExitStatus::for_error(&usage), ExitStatus::Usage);
+    }
+
+    #[test]
+    fn test_algorithm_argument_validation() {
+        let cli = Cli::try_parse_from(["hash", "x", "-a", "SHA3-256"]).unwrap();
+        assert_eq!(cli.args.output.algorithm, Algorithm::Sha3_256);
+
+        let cli = Cli::try_parse_from(["hash", "x", "-a", "blake2b512"]).unwrap();
+        assert_eq!(cli.args.output.algorithm, Algorithm::Blake2b512);
+
+        let cli = Cli::try_parse_from(["hash", "x", "-a", "SHA2-256"]).unwrap();
+        assert_eq!(cli.args.output.algorithm, Algorithm::Sha256);
+
+        let cli = Cli::try_parse_from(["hash", "x", "-a", "Auto"]).unwrap();
+        assert_eq!(cli.args.output.algorithm, Algorithm::fastest_secure());
+
+        let err = Cli::try_parse_from(["hash", "x", "-a", "sha9"]).err().unwrap();
+        assert_eq!(err.kind(), clap::error::ErrorKind::InvalidValue);
+    }
+
+    #[test]
+    fn test_paths_named_like_subcommands() {
+        let argv = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        let exists = |arg: &str| arg == "list" || arg == "dir";
+
+        // An existing path wins over the subcommand of the same name
+        let cli = parse_cli(&argv(&["hash", "list", "-a", "md5"]), exists).unwrap();
+        assert!(cli.command.is_none());
+        assert_eq!(cli.args.input(), "list");
+        assert_eq!(cli.args.output.algorithm, Algorithm::Md5);
+        let cli = parse_cli(&argv(&["hash", "list"]), |_| false).unwrap();
+        assert!(matches!(cli.command, Some(Command::List)));
+
+        // With more inputs the subcommand is meant; -- and ./ always mean the path
+        let cli = parse_cli(&argv(&["hash", "dir", "src"]), exists).unwrap();
+        assert!(matches!(cli.command, Some(Command::Dir { .. })));
+        for explicit in [&["hash", "--", "list"][..], &["hash", "./list"]] {
+            let cli = parse_cli(&argv(explicit), |_| false).unwrap();
+            assert!(cli.command.is_none());
+        }
+    }
+
+    #[test]
+    fn test_subcommands_map_to_flat_invocation() {
+        let cli = Cli::try_parse_from(["hash", "string", "README.md", "-a", "md5"]).unwrap();
+        let args = cli.command.unwrap().into_args();
+        assert_eq!(args.input(), "README.md");
+        assert_eq!(args.mode.policy(), InputPolicy::String);
+        assert_eq!(args.output.algorithm, Algorithm::Md5);
+
+        let cli = Cli::try_parse_from(["hash", "dir", "src", "--sort", "size"]).unwrap();
+        let args = cli.command.unwrap().into_args();
+        assert!(args.recursive);
+        assert_eq!(args.walk.sort, SortOrder::Size);
+
+        let argv = ["hash", "dir", "src", "--include", "*.rs", "--exclude", "bin", "--merkle-root"];
+        let args = Cli::try_parse_from(argv).unwrap().command.unwrap().into_args();
+        assert_eq!(args.walk.include, [Glob::new("*.rs").unwrap()]);
+        assert_eq!(args.walk.exclude[0].as_str(), "bin");
+        assert!(args.walk.merkle_root);
+
+        let cli = Cli::try_parse_from(["hash", "csv", "users.tsv", "--csv-column", "email"]).unwrap();
+        let args = cli.command.unwrap().into_args();
+        assert_eq!(args.csv.columns, [ColumnSelector::Name("email".to_string())]);
+
+        let argv = ["hash", "--check", "SUMS", "--signature", "SUMS.sig", "--root", "/data"];
+        let args = Cli::try_parse_from(argv).unwrap().args;
+        assert_eq!(args.signature.as_deref(), Some("SUMS.sig"));
+        assert_eq!(args.root.as_deref(), Some(Path::new("/data")));
+        let argv = ["hash", "--check", "SUMS", "--trusted-key", "k.pem"];
+        assert!(Cli::try_parse_from(argv).is_err());
+
+        let argv = ["hash", "--check-stored", "s3://releases/app.tar.gz", "-a", "md5"];
+        let args = Cli::try_parse_from(argv).unwrap().args;
+        assert!(args.check_stored);
+        assert_eq!(args.input(), "s3://releases/app.tar.gz");
+        let argv = ["hash", "--check-stored", "s3://releases/app.tar.gz", "-c", "abc"];
+        assert!(Cli::try_parse_from(argv).is_err());
+
+        let args = Cli::try_parse_from(["hash", "a.iso", "b.iso", "-j", "2"]).unwrap().args;
+        assert_eq!(args.input, ["a.iso", "b.iso"]);
+        assert_eq!(args.output.reads.jobs, Some(2));
+        let args = Cli::try_parse_from(["hash", "file", "a.iso", "b.iso"]).unwrap();
+        assert_eq!(args.command.unwrap().into_args().input.len(), 2);
+
+        let argv = ["hash", "file", "disk.img", "-a", "blake3", "--threads", "4"];
+        let args = Cli::try_parse_from(argv).unwrap().command.unwrap().into_args();
+        assert_eq!(args.output.reads.threads, Some(4));
+
+        let argv = ["hash", "file", "disk.img", "--progress"];
+        let args = Cli::try_parse_from(argv).unwrap().command.unwrap().into_args();
+        assert_eq!(args.walk.progress, Some(ProgressFormat::Bar));
+        assert_eq!(args.input, ["disk.img"]);
+        let args = Cli::try_parse_from(["hash", "-r", "/data", "--progress=json"]).unwrap().args;
+        assert_eq!(args.walk.progress, Some(ProgressFormat::Json));
+
+        let args = Cli::try_parse_from(["hash", "-", "-a", "blake3"]).unwrap().args;
+        assert_eq!(args.mode.policy().resolve(args.input()), InputKind::Stdin);
+        assert!(check_stdin(&args).is_ok());
+        assert!(check_stdin(&Cli::try_parse_from(["hash", "-", "-C", "-"]).unwrap().args).is_err());
+        let args = Cli::try_parse_from(["hash", "file", "-", "--key-stdin"]).unwrap();
+        assert!(check_stdin(&args.command.unwrap().into_args()).is_err());
+
+        let cli = Cli::try_parse_from(["hash", "string", "hi", "--encoding", "base32"]).unwrap();
+        assert_eq!(cli.command.unwrap().into_args().output.encoding, Encoding::Base32);
+        assert!(Cli::try_parse_from(["hash", "--check", "SUMS", "--encoding", "base64"]).is_err());
+
+        let argv = ["hash", "inspect", "a.bin", "-a", "md5", "-a", "sha512", "--bytes", "16"];
+        match Cli::try_parse_from(argv).unwrap().command {
+            Some(Command::Inspect { algorithms, bytes, .. }) => {
+                assert_eq!(algorithms, [Algorithm::Md5, Algorithm::Sha512]);
+                assert_eq!(bytes, 16);
+            }
+            _ => panic!("expected the inspect subcommand"),
+        }
+
+        let argv = ["hash", "chunks", "disk.img", "--avg-size", "16K", "-f", "json"];
+        match Cli::try_parse_from(argv).unwrap().command {
+            Some(Command::Chunks { avg_size, format, algorithm, .. }) => {
+                assert_eq!(avg_size, 16 * 1024);
+                assert_eq!(format, ChunkIndexFormat::Json);
+                assert_eq!(algorithm, Algorithm::Sha256);
+            }
+            _ => panic!("expected the chunks subcommand"),
+        }
+
+        let argv = ["hash", "dir", ".", "-e", "base.sealed", "--encrypt", "--passphrase-env", "PW"];
+        let args = Cli::try_parse_from(argv).unwrap().command.unwrap().into_args();
+        assert!(args.walk.encrypt);
+        assert_eq!(args.walk.passphrase_env.as_deref(), Some("PW"));
+        assert!(Cli::try_parse_from(["hash", "dir", ".", "--encrypt"]).is_err());
+
+        let argv = ["hash", "--check", "SHA256SUMS", "--ignore-missing"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        assert_eq!(cli.args.check, ["SHA256SUMS"]);
+        assert!(cli.args.ignore_missing);
+        assert!(Cli::try_parse_from(["hash", "--check", "SHA256SUMS", "a.txt"]).is_err());
+
+        let argv = ["hash", "check", "SHA256SUMS", "MD5SUMS", "--ignore-missing", "--root", "/d"];
+        let args = Cli::try_parse_from(argv).unwrap().command.unwrap().into_args();
+        assert_eq!(args.check, ["SHA256SUMS", "MD5SUMS"]);
+        assert!(args.ignore_missing);
+        assert_eq!(args.root.as_deref(), Some(Path::new("/d")));
+        assert!(args.input.is_empty());
+        let argv = ["hash", "check", "SUMS", "--signature", "SUMS.sig", "--trusted-key", "k.pem"];
+        let args = Cli::try_parse_from(argv).unwrap().command.unwrap().into_args();
+        assert_eq!(args.signature.as_deref(), Some("SUMS.sig"));
+        assert_eq!(args.trusted_key, [PathBuf::from("k.pem")]);
+        assert!(Cli::try_parse_from(["hash", "check", "SUMS", "--trusted-key", "k.pem"]).is_err());
+        assert!(Cli::try_parse_from(["hash", "check"]).is_err());
+        let argv = ["hash", "--check", "A", "--check", "B"];
+        assert_eq!(Cli::try_parse_from(argv).unwrap().args.check, ["A", "B"]);
+
+        // The flat form keeps working
+        let cli = Cli::try_parse_from(["hash", "hello", "-q"]).unwrap();
+        assert!(cli.command.is_none());
+        assert_eq!(cli.args.input(), "hello");
+    }
+    #[test]
+    fn test_sum_personalities() {
+        assert_eq!(SumTool::invoked_as("/usr/local/bin/b2sum"), Some(SumTool::B2sum));
+        assert_eq!(SumTool::invoked_as("b3sum.exe"), Some(SumTool::B3sum));
+        assert_eq!(SumTool::invoked_as("hash"), None);
+
+        // b2sum lengths are in bits, and 512 is the plain BLAKE2b-512
+        let b2sum = |argv: &[&str]| SumJob::from_b2sum(B2sumArgs::try_parse_from(argv).unwrap());
+        let job = b2sum(&["b2sum", "-l", "256", "--tag", "a"]).unwrap();
+        assert_eq!((job.length, job.style), (Some(32), SumStyle::Tag));
+        assert_eq!(b2sum(&["b2sum", "-l", "512"]).unwrap().length, None);
+        assert!(b2sum(&["b2sum", "-l", "100"]).is_err());
+        assert!(b2sum(&["b2sum", "-l", "1024"]).is_err());
+        assert!(B2sumArgs::try_parse_from(["b2sum", "--tag", "-c", "SUMS"]).is_err());
+        assert!(B2sumArgs::try_parse_from(["b2sum", "--quiet", "a"]).is_err());
+
+        // b3sum lengths are in bytes; --check takes no hashing options
+        let b3sum = |argv: &[&str]| SumJob::from_b3sum(B3sumArgs::try_parse_from(argv).unwrap());
+        let job = b3sum(&["b3sum", "--derive-key", "ctx", "-l", "64", "a"]).unwrap();
+        assert_eq!(job.key, Some(SumKey::Context("ctx".to_string())));
+        assert_eq!(job.length, Some(64));
+        assert_eq!(b3sum(&["b3sum", "--no-names"]).unwrap().style, SumStyle::NoNames);
+        assert!(b3sum(&["b3sum", "--raw", "a", "b"]).is_err());
+        assert!(b3sum(&["b3sum", "--keyed"]).is_err());
+        assert!(B3sumArgs::try_parse_from(["b3sum", "-c", "--length", "16", "SUMS"]).is_err());
+        assert!(B3sumArgs::try_parse_from(["b3sum", "--keyed", "--derive-key", "x"]).is_err());
+        assert!(read_sum_key(&[7u8; 32][..]).is_ok());
+        assert!(read_sum_key(&[7u8; 33][..]).is_err());
+
+        // Check lines parse back from plain, binary, tagged and escaped output
+        let job = b2sum(&["b2sum", "-c"]).unwrap();
+        let digest = Digest::new(Algorithm::Blake2b512, vec![0xab; 32]).unwrap();
+        let mut line = Vec::new();
+        let tagged = SumJob { style: SumStyle::Tag, ..b2sum(&["b2sum"]).unwrap() };
+        tagged.write_sum(&mut line, "odd\\name", &digest).unwrap();
+        let line = String::from_utf8(line).unwrap();
+        assert!(line.starts_with("\\BLAKE2b-256 (odd\\\\name) = abab"));
+        let parsed = job.parse_line(line.trim_end()).unwrap();
+        assert_eq!(parsed, (vec![0xab; 32], "odd\\name".to_string()));
+        assert_eq!(job.parse_line(&format!("{} *a b", "00".repeat(64))).unwrap().1, "a b");
+        assert!(job.parse_line("BLAKE3 (a) = 00").is_none());
+        assert!(job.parse_line(&format!("{}  a", "00".repeat(65))).is_none());
+        assert_eq!(checksum::escape_name("a\nb\n"), ("\\", "a\\nb\\n".to_string()));
+        assert!(checksum::unescape_name("trailing\\").is_none());
     }
 }
@@ -0,0 +1,783 @@
+//! Fluent builder consolidating hashing options
+//!
+//! The free functions ([`crate::hash_string`], [`crate::hash_file`], ...)
+//! cover the common case. [`Hashing`] is the single entry point for
+//! everything else: output encoding, I/O buffer size, HMAC and BLAKE keys,
+//! progress reporting, paranoid self-checks, and how files are read.
+
+use crate::noncrypto::Checksum;
+use crate::pagecache::{self, ReadAheadReader, UncachedReader};
+use crate::{hmac, keyed, Algorithm, Encoding, HashError, Result};
+use sha2::Digest;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Write};
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::thread;
+
+/// Default size of the buffer used to read files and streams
+pub const DEFAULT_BUFFER_SIZE: usize = 8192;
+
+/// Size of the pieces the paranoid check digest is fed in: one BLAKE3
+/// chunk, which keeps BLAKE3 off its multi-chunk SIMD path
+const CHECK_PIECE_SIZE: usize = 1024;
+
+/// Buffers queued for the paranoid check before the reader waits for it
+const CHECK_QUEUE_LEN: usize = 16;
+
+/// Configurable hasher built up with chained calls
+///
+/// # Examples
+///
+/// ```
+/// use hashing::{Algorithm, Encoding, Hashing};
+///
+/// let digest = Hashing::new()
+///     .algorithm(Algorithm::Sha256)
+///     .encoding(Encoding::Base64)
+///     .hash_string("hello")
+///     .unwrap();
+/// assert_eq!(digest, "LPJNul+wow4m6DsqxbninhsWHlwfp0JecwQzYpOLmCQ=");
+/// ```
+pub struct Hashing<'a> {
+    algorithm: Algorithm,
+    encoding: Encoding,
+    buffer_size: usize,
+    key: Option<(KeyMode, Vec<u8>)>,
+    output_size: Option<usize>,
+    progress: Option<Box<dyn FnMut(u64) + 'a>>,
+    paranoid: bool,
+    double_read: bool,
+    reads: FileReads,
+    #[cfg(feature = "rayon")]
+    threads: usize,
+}
+
+impl<'a> Hashing<'a> {
+    /// SHA-256, hex output, 8 KiB buffer, no key, no progress reporting
+    pub fn new() -> Self {
+        Self {
+            algorithm: Algorithm::Sha256,
+            encoding: Encoding::Hex,
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            key: None,
+            output_size: None,
+            progress: None,
+            paranoid: false,
+            double_read: false,
+            reads: FileReads::default(),
+            #[cfg(feature = "rayon")]
+            threads: 0,
+        }
+    }
+
+    /// Hash algorithm to use
+    pub fn algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Text encoding of the returned digest
+    ///
+    /// [`Encoding::Binary`] is not text: the string-returning methods fail
+    /// with it, and the `_raw` ones give the bytes.
+    pub fn encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Size of each read from files and streams (minimum 1 byte)
+    pub fn buffer_size(mut self, bytes: usize) -> Self {
+        self.buffer_size = bytes.max(1);
+        self
+    }
+
+    /// Compute an HMAC with this key instead of a plain hash
+    pub fn key(mut self, key: impl Into<Vec<u8>>) -> Self {
+        self.key = Some((KeyMode::Hmac, key.into()));
+        self
+    }
+
+    /// Hash in BLAKE2's or BLAKE3's own keyed mode with this key, instead
+    /// of an HMAC or a plain hash
+    ///
+    /// Replaces a key given with [`Hashing::key`], and the other way
+    /// round. Hashing fails with [`HashError::UnsupportedAlgorithm`] for
+    /// other algorithms and with [`HashError::InvalidInput`] for a key of
+    /// the wrong length (see [`keyed::key_sizes`]: BLAKE3 keys are exactly
+    /// 32 bytes). Works with [`Hashing::output_size`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hashing::{Algorithm, Hashing};
+    ///
+    /// let mac = Hashing::new()
+    ///     .algorithm(Algorithm::Blake2s256)
+    ///     .blake_key("secret")
+    ///     .output_size(16)
+    ///     .hash_string("hello")
+    ///     .unwrap();
+    /// assert_eq!(mac.len(), 32);
+    /// ```
+    pub fn blake_key(mut self, key: impl Into<Vec<u8>>) -> Self {
+        self.key = Some((KeyMode::Blake, key.into()));
+        self
+    }
+
+    /// Derive a key from the hashed input with BLAKE3's `derive_key` mode
+    /// under `context`, instead of hashing it plainly
+    ///
+    /// The context string should be hardcoded, globally unique and
+    /// application-specific, such as `"example.com 2026-10-16 session
+    /// tokens v1"`. Replaces a key given with [`Hashing::key`] or
+    /// [`Hashing::blake_key`]. Hashing fails with
+    /// [`HashError::UnsupportedAlgorithm`] for algorithms other than
+    /// BLAKE3. Works with [`Hashing::output_size`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hashing::{Algorithm, Hashing};
+    ///
+    /// let context = "example.com 2026-10-16 session tokens v1";
+    /// let key = Hashing::new()
+    ///     .algorithm(Algorithm::Blake3)
+    ///     .derive_key(context)
+    ///     .hash_bytes_raw(b"key material")?;
+    /// assert_eq!(key.as_bytes(), blake3::derive_key(context, b"key material"));
+    /// # Ok::<(), hashing::HashError>(())
+    /// ```
+    pub fn derive_key(mut self, context: impl Into<String>) -> Self {
+        self.key = Some((KeyMode::Context, context.into().into_bytes()));
+        self
+    }
+
+    /// Produce digests of `bytes` bytes instead of the algorithm's usual
+    /// [`Algorithm::output_size`]
+    ///
+    /// Only BLAKE2, BLAKE3 and the SHAKE functions have a choice (see
+    /// [`Algorithm::output_sizes`]); hashing fails with
+    /// [`HashError::InvalidInput`] for a length the algorithm cannot produce,
+    /// and when combined with an HMAC key. BLAKE3 and SHAKE output is a
+    /// stream, so a shorter digest is a prefix of a longer one; a BLAKE2
+    /// digest of each length is unrelated to the others.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hashing::{Algorithm, Hashing};
+    ///
+    /// let long = Hashing::new()
+    ///     .algorithm(Algorithm::Blake3)
+    ///     .output_size(64)
+    ///     .hash_string("hello")
+    ///     .unwrap();
+    /// assert_eq!(long.len(), 128);
+    /// assert!(long.starts_with(&hashing::hash_string("hello", Algorithm::Blake3).unwrap()));
+    /// ```
+    pub fn output_size(mut self, bytes: usize) -> Self {
+        self.output_size = Some(bytes);
+        self
+    }
+
+    /// Call `callback` with the total number of bytes consumed after each read
+    pub fn progress(mut self, callback: impl FnMut(u64) + 'a) -> Self {
+        self.progress = Some(Box::new(callback));
+        self
+    }
+
+    /// Compute every digest twice and fail with [`HashError::SelfCheck`]
+    /// if the two disagree
+    ///
+    /// The second digest runs on another thread over its own copy of the
+    /// data, fed in smaller pieces so that BLAKE3 takes its one-chunk code
+    /// path instead of the SIMD one. A mismatch means memory or the CPU
+    /// corrupted one of them, which matters on long archival scans; the
+    /// price is a second core's worth of hashing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hashing::{Algorithm, Hashing};
+    ///
+    /// let digest = Hashing::new()
+    ///     .algorithm(Algorithm::Blake3)
+    ///     .paranoid(true)
+    ///     .hash_string("hello")
+    ///     .unwrap();
+    /// assert_eq!(digest, hashing::hash_string("hello", Algorithm::Blake3).unwrap());
+    /// ```
+    pub fn paranoid(mut self, enabled: bool) -> Self {
+        self.paranoid = enabled;
+        self
+    }
+
+    /// Have [`Hashing::hash_file`] read the file twice and fail with
+    /// [`HashError::SelfCheck`] if the two digests differ
+    ///
+    /// The file's cached pages are dropped before the second read (see
+    /// [`pagecache::drop_cached`]), so on Linux it comes from the disk and a
+    /// difference points at flaky storage, cabling or controller. Progress
+    /// is reported for both reads.
+    pub fn double_read(mut self, enabled: bool) -> Self {
+        self.double_read = enabled;
+        self
+    }
+
+    /// Have [`Hashing::hash_file`] read past the page cache (see
+    /// [`UncachedReader`]), so that verifying a file exercises the disk
+    /// rather than a copy in memory
+    pub fn uncached(mut self, enabled: bool) -> Self {
+        self.reads.uncached = enabled;
+        self
+    }
+
+    /// Have [`Hashing::hash_file`] keep the kernel reading `bytes` ahead
+    /// (see [`ReadAheadReader`]); 0, the default, leaves it to the kernel
+    ///
+    /// Files are always read with a sequential-access hint. A larger
+    /// window mostly pays off on high-latency storage such as a NAS, and
+    /// never changes the digest. Ignored for uncached reads.
+    pub fn read_ahead(mut self, bytes: u64) -> Self {
+        self.reads.read_ahead = bytes;
+        self
+    }
+
+    /// Hash large BLAKE3 files on `threads` threads; 0, the default, uses
+    /// one per core
+    ///
+    /// Only files that [`Hashing::hash_file`] memory-maps are split across
+    /// threads: BLAKE3 files of at least [`crate::mmap::MMAP_THRESHOLD`]
+    /// bytes, hashed without a key, progress callback or self-check.
+    #[cfg(feature = "rayon")]
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Hash a byte slice
+    pub fn hash_bytes(&mut self, data: &[u8]) -> Result<String> {
+        self.hash_reader(data)
+    }
+
+    /// Hash the UTF-8 bytes of a string
+    pub fn hash_string(&mut self, input: &str) -> Result<String> {
+        self.hash_bytes(input.as_bytes())
+    }
+
+    /// Hash a file's contents with streaming
+    pub fn hash_file<P: AsRef<Path>>(&mut self, path: P) -> Result<String> {
+        self.check_encoding()?;
+        let digest = self.hash_file_raw(path)?;
+        digest.encode(self.encoding)
+    }
+
+    /// Hash everything `reader` yields until EOF
+    pub fn hash_reader<R: Read>(&mut self, reader: R) -> Result<String> {
+        self.check_encoding()?;
+        let digest = self.hash_reader_raw(reader)?;
+        digest.encode(self.encoding)
+    }
+
+    /// Fail before any reading if the digest cannot be returned as text
+    fn check_encoding(&self) -> Result<()> {
+        if self.encoding.is_text() {
+            Ok(())
+        } else {
+            Err(HashError::InvalidInput(
+                "binary digests are not text; use the _raw methods".to_string(),
+            ))
+        }
+    }
+
+    /// Hash a byte slice, returning the raw digest (the encoding is not used)
+    pub fn hash_bytes_raw(&mut self, data: &[u8]) -> Result<crate::Digest> {
+        self.hash_reader_raw(data)
+    }
+
+    /// Hash a file's contents, returning the raw digest
+    pub fn hash_file_raw<P: AsRef<Path>>(&mut self, path: P) -> Result<crate::Digest> {
+        let path = path.as_ref();
+        #[cfg(feature = "mmap")]
+        if self.maps(path) {
+            return self.hash_mapped(path);
+        }
+        let digest = self.hash_reader_raw(self.reads.open(path, false)?)?;
+        if self.double_read {
+            let again = self.hash_reader_raw(self.reads.open(path, true)?)?;
+            check_reread(path, &digest.to_hex(), &again.to_hex())?;
+        }
+        Ok(digest)
+    }
+
+    /// Whether `path` takes the memory-mapped BLAKE3 path, which nothing
+    /// but the digest can observe
+    #[cfg(feature = "mmap")]
+    fn maps(&self, path: &Path) -> bool {
+        self.algorithm == Algorithm::Blake3
+            && self.key.is_none()
+            && self.output_size.is_none()
+            && self.progress.is_none()
+            && !self.paranoid
+            && !self.double_read
+            && !self.reads.uncached
+            && crate::mmap::worth_mapping(path)
+    }
+
+    #[cfg(feature = "mmap")]
+    fn hash_mapped(&self, path: &Path) -> Result<crate::Digest> {
+        #[cfg(feature = "rayon")]
+        if self.threads != 0 {
+            return crate::mmap::blake3_file_threads(path, self.threads);
+        }
+        crate::mmap::blake3_file(path)
+    }
+
+    /// Hash everything `reader` yields, returning the raw digest
+    pub fn hash_reader_raw<R: Read>(&mut self, reader: R) -> Result<crate::Digest> {
+        let buffered = BufReader::with_capacity(self.buffer_size, reader);
+        let mut reader = ProgressReader::new(buffered, self.progress.as_deref_mut());
+
+        let key = self.key.as_ref().map(|(mode, key)| (*mode, key.as_slice()));
+        match key {
+            Some((KeyMode::Blake, key)) => keyed::check_key(self.algorithm, key)?,
+            Some((KeyMode::Context, _)) => keyed::check_derive(self.algorithm)?,
+            _ => {}
+        }
+        if let Some(len) = self.output_size {
+            if matches!(key, Some((KeyMode::Hmac, _))) {
+                return Err(HashError::InvalidInput(
+                    "HMAC digests have the algorithm's own length; drop the output size"
+                        .to_string(),
+                ));
+            }
+            crate::digest::check_length(self.algorithm, len)?;
+        }
+        let (algorithm, size) = (self.algorithm, self.output_size);
+        let bytes = if self.paranoid {
+            checked_digest(&mut reader, key, algorithm, size, self.buffer_size)?
+        } else {
+            keyed_digest(&mut reader, key, algorithm, size, self.buffer_size)?
+        };
+        Ok(crate::Digest::computed(algorithm, bytes))
+    }
+
+    /// Hash everything `reader` yields while copying it to `writer`
+    ///
+    /// The input is read once, so a download can be saved and hashed in
+    /// one pass. `writer` is flushed before the digest is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hashing::{Algorithm, Hashing};
+    ///
+    /// let mut copy = Vec::new();
+    /// let digest = Hashing::new()
+    ///     .algorithm(Algorithm::Sha256)
+    ///     .hash_tee("hello".as_bytes(), &mut copy)
+    ///     .unwrap();
+    /// assert_eq!(copy, b"hello");
+    /// assert_eq!(digest, hashing::hash_string("hello", Algorithm::Sha256).unwrap());
+    /// ```
+    pub fn hash_tee<R: Read, W: Write>(&mut self, reader: R, mut writer: W) -> Result<String> {
+        let digest = self.hash_reader(TeeReader {
+            inner: reader,
+            writer: &mut writer,
+        })?;
+        writer.flush()?;
+        Ok(digest)
+    }
+}
+
+impl Default for Hashing<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Hashing<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Never print key material
+        f.debug_struct("Hashing")
+            .field("algorithm", &self.algorithm)
+            .field("encoding", &self.encoding)
+            .field("buffer_size", &self.buffer_size)
+            .field("keyed", &self.key.as_ref().map(|(mode, _)| *mode))
+            .field("output_size", &self.output_size)
+            .field("progress", &self.progress.is_some())
+            .field("paranoid", &self.paranoid)
+            .field("double_read", &self.double_read)
+            .field("uncached", &self.reads.uncached)
+            .field("read_ahead", &self.reads.read_ahead)
+            .finish()
+    }
+}
+
+/// Reader adapter reporting the running byte count to a callback
+pub(crate) struct ProgressReader<'c, 'f, R> {
+    inner: R,
+    consumed: u64,
+    callback: Option<&'c mut (dyn FnMut(u64) + 'f)>,
+}
+
+impl<'c, 'f, R> ProgressReader<'c, 'f, R> {
+    pub(crate) fn new(inner: R, callback: Option<&'c mut (dyn FnMut(u64) + 'f)>) -> Self {
+        Self {
+            inner,
+            consumed: 0,
+            callback,
+        }
+    }
+}
+
+impl<R: Read> Read for ProgressReader<'_, '_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let count = self.inner.read(buf)?;
+        if count > 0 {
+            self.consumed += count as u64;
+            if let Some(callback) = self.callback.as_mut() {
+                callback(self.consumed);
+            }
+        }
+        Ok(count)
+    }
+}
+
+/// Reader adapter copying everything read to a writer
+struct TeeReader<R, W> {
+    inner: R,
+    writer: W,
+}
+
+impl<R: Read, W: Write> Read for TeeReader<R, W> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let count = self.inner.read(buf)?;
+        self.writer.write_all(&buf[..count])?;
+        Ok(count)
+    }
+}
+
+/// Reader adapter sending a copy of everything read to the check thread
+struct CopyReader<R> {
+    inner: R,
+    copies: SyncSender<Vec<u8>>,
+}
+
+impl<R: Read> Read for CopyReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let count = self.inner.read(buf)?;
+        if count > 0 {
+            // Only fails if the check thread panicked, which joining reports
+            let _ = self.copies.send(buf[..count].to_vec());
+        }
+        Ok(count)
+    }
+}
+
+/// Reader over the buffers a [`CopyReader`] sent; ends when it is dropped
+struct ChannelReader {
+    copies: Receiver<Vec<u8>>,
+    current: Vec<u8>,
+    offset: usize,
+}
+
+impl Read for ChannelReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.offset == self.current.len() {
+            match self.copies.recv() {
+                Ok(next) => {
+                    self.current = next;
+                    self.offset = 0;
+                }
+                Err(_) => return Ok(0),
+            }
+        }
+        let count = buf.len().min(self.current.len() - self.offset);
+        buf[..count].copy_from_slice(&self.current[self.offset..self.offset + count]);
+        self.offset += count;
+        Ok(count)
+    }
+}
+
+/// What a key given to [`Hashing`] is used for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KeyMode {
+    /// HMAC over the algorithm
+    Hmac,
+    /// The keyed mode built into BLAKE2 and BLAKE3
+    Blake,
+    /// BLAKE3's key derivation, the "key" being its UTF-8 context string
+    Context,
+}
+
+/// Raw digest of `output_size` bytes, or HMAC or keyed BLAKE digest with
+/// `key`, of everything `reader` yields
+fn keyed_digest<R: Read>(
+    reader: R,
+    key: Option<(KeyMode, &[u8])>,
+    algorithm: Algorithm,
+    output_size: Option<usize>,
+    buffer_size: usize,
+) -> Result<Vec<u8>> {
+    match (key, output_size) {
+        (Some((KeyMode::Hmac, key)), _) => hmac::mac_reader(key, reader, algorithm),
+        (Some((KeyMode::Blake, key)), len) => {
+            let len = len.unwrap_or(algorithm.output_size());
+            keyed::digest_reader(key, reader, algorithm, len, buffer_size)
+        }
+        (Some((KeyMode::Context, context)), len) => {
+            let context = std::str::from_utf8(context).expect("context set from a String");
+            let len = len.unwrap_or(algorithm.output_size());
+            keyed::derive_reader(context, reader, len, buffer_size)
+        }
+        (None, Some(len)) => sized_digest(reader, algorithm, len, buffer_size),
+        (None, None) => digest_reader(reader, algorithm, buffer_size),
+    }
+}
+
+/// [`keyed_digest`] computed twice, the second time on another thread
+fn checked_digest<R: Read>(
+    reader: R,
+    key: Option<(KeyMode, &[u8])>,
+    algorithm: Algorithm,
+    output_size: Option<usize>,
+    buffer_size: usize,
+) -> Result<Vec<u8>> {
+    let (copies, received) = mpsc::sync_channel(CHECK_QUEUE_LEN);
+    let (digest, check) = thread::scope(|scope| {
+        let check = scope.spawn(move || {
+            let reader = ChannelReader {
+                copies: received,
+                current: Vec::new(),
+                offset: 0,
+            };
+            keyed_digest(reader, key, algorithm, output_size, CHECK_PIECE_SIZE)
+        });
+        // The copy reader is dropped when this returns, ending the check's input
+        let reader = CopyReader {
+            inner: reader,
+            copies,
+        };
+        let digest = keyed_digest(reader, key, algorithm, output_size, buffer_size);
+        let check = check
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+        (digest, check)
+    });
+    let (digest, check) = (digest?, check?);
+    if digest != check {
+        return Err(HashError::SelfCheck(format!(
+            "two {} computations over the same data disagree ({} and {}); \
+             memory or CPU may be faulty",
+            algorithm.name(),
+            hex::encode(&digest),
+            hex::encode(&check)
+        )));
+    }
+    Ok(digest)
+}
+
+/// How files are opened for hashing
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct FileReads {
+    /// Read past the page cache
+    pub uncached: bool,
+    /// Read-ahead window in bytes, 0 for the kernel's default
+    pub read_ahead: u64,
+}
+
+impl FileReads {
+    /// `path` opened for hashing; a `reread` of a cached file drops its
+    /// cached pages first
+    pub fn open(&self, path: &Path, reread: bool) -> io::Result<Box<dyn Read>> {
+        if self.uncached {
+            return Ok(Box::new(UncachedReader::open(path)?));
+        }
+        let file = File::open(path)?;
+        if reread {
+            pagecache::drop_cached(&file)?;
+        }
+        Ok(Box::new(ReadAheadReader::new(file, self.read_ahead)))
+    }
+}
+
+/// The error for a file whose second read gave a different digest
+pub(crate) fn check_reread(path: &Path, first: &str, second: &str) -> Result<()> {
+    if first == second {
+        return Ok(());
+    }
+    Err(HashError::SelfCheck(format!(
+        "two reads of {} gave different digests ({} and {}); the storage may be faulty",
+        path.display(),
+        first,
+        second
+    )))
+}
+
+/// Raw digest of a byte slice
+pub(crate) fn digest_bytes(data: &[u8], algorithm: Algorithm) -> Result<Vec<u8>> {
+    digest_reader(data, algorithm, DEFAULT_BUFFER_SIZE)
+}
+
+/// First 16 bytes of a byte slice's digest as two big-endian words
+///
+/// Used where only well-mixed bits are needed (rings, sketches). Digests
+/// shorter than 16 bytes (CRC-32, xxHash) are stretched with the digests of
+/// `data` followed by a counter byte.
+pub(crate) fn digest_words(data: &[u8], algorithm: Algorithm) -> (u64, u64) {
+    // Hashing an in-memory slice performs no I/O and cannot fail
+    let mut digest = digest_bytes(data, algorithm).expect("in-memory digest");
+    let mut counter = 0u8;
+    while digest.len() < 16 {
+        counter += 1;
+        let suffix = [counter];
+        let more = digest_reader(data.chain(&suffix[..]), algorithm, DEFAULT_BUFFER_SIZE);
+        digest.extend(more.expect("in-memory digest"));
+    }
+    let mut first = [0u8; 8];
+    let mut second = [0u8; 8];
+    first.copy_from_slice(&digest[..8]);
+    second.copy_from_slice(&digest[8..16]);
+    (u64::from_be_bytes(first), u64::from_be_bytes(second))
+}
+
+/// Raw digest of everything `reader` yields
+pub(crate) fn digest_reader<R: Read>(
+    reader: R,
+    algorithm: Algorithm,
+    buffer_size: usize,
+) -> Result<Vec<u8>> {
+    use blake2::{Blake2b512, Blake2s256};
+    use md5::Md5;
+    use sha2::{Sha224, Sha256, Sha384, Sha512, Sha512_224, Sha512_256};
+    use sha3::{
+        Keccak224, Keccak256, Keccak384, Keccak512, Sha3_224, Sha3_256, Sha3_384, Sha3_512,
+        Shake128, Shake256,
+    };
+
+    match algorithm {
+        Algorithm::Md5 => stream::<Md5, R>(reader, buffer_size),
+        Algorithm::Sha1 => {
+            let mut hasher = crate::sha1_smol::Sha1::new();
+            for_each_chunk(reader, buffer_size, |chunk| hasher.update(chunk))?;
+            Ok(hasher.digest().bytes().to_vec())
+        }
+        Algorithm::Sha224 => stream::<Sha224, R>(reader, buffer_size),
+        Algorithm::Sha256 => stream::<Sha256, R>(reader, buffer_size),
+        Algorithm::Sha384 => stream::<Sha384, R>(reader, buffer_size),
+        Algorithm::Sha512 => stream::<Sha512, R>(reader, buffer_size),
+        Algorithm::Sha512_224 => stream::<Sha512_224, R>(reader, buffer_size),
+        Algorithm::Sha512_256 => stream::<Sha512_256, R>(reader, buffer_size),
+        Algorithm::Sha3_224 => stream::<Sha3_224, R>(reader, buffer_size),
+        Algorithm::Sha3_256 => stream::<Sha3_256, R>(reader, buffer_size),
+        Algorithm::Sha3_384 => stream::<Sha3_384, R>(reader, buffer_size),
+        Algorithm::Sha3_512 => stream::<Sha3_512, R>(reader, buffer_size),
+        Algorithm::Shake128 => xof::<Shake128, R>(reader, buffer_size, 32),
+        Algorithm::Shake256 => xof::<Shake256, R>(reader, buffer_size, 64),
+        Algorithm::Blake2b512 => stream::<Blake2b512, R>(reader, buffer_size),
+        Algorithm::Blake2s256 => stream::<Blake2s256, R>(reader, buffer_size),
+        Algorithm::Blake3 => {
+            let mut hasher = blake3::Hasher::new();
+            for_each_chunk(reader, buffer_size, |chunk| {
+                hasher.update(chunk);
+            })?;
+            Ok(hasher.finalize().as_bytes().to_vec())
+        }
+        Algorithm::Keccak224 => stream::<Keccak224, R>(reader, buffer_size),
+        Algorithm::Keccak256 => stream::<Keccak256, R>(reader, buffer_size),
+        Algorithm::Keccak384 => stream::<Keccak384, R>(reader, buffer_size),
+        Algorithm::Keccak512 => stream::<Keccak512, R>(reader, buffer_size),
+        Algorithm::Sha256d | Algorithm::Hash160 => {
+            let sha256 = stream::<Sha256, R>(reader, buffer_size)?;
+            Ok(crate::finish_sha256_composite(algorithm, &sha256))
+        }
+        Algorithm::Xxh32
+        | Algorithm::Xxh64
+        | Algorithm::Xxh3
+        | Algorithm::Xxh128
+        | Algorithm::Crc32
+        | Algorithm::Crc32c => {
+            let mut checksum = Checksum::new(algorithm).expect("non-cryptographic");
+            for_each_chunk(reader, buffer_size, |chunk| checksum.update(chunk))?;
+            Ok(checksum.finalize())
+        }
+    }
+}
+
+/// Raw `len`-byte digest of everything `reader` yields, for a length in
+/// the algorithm's [`Algorithm::output_sizes`]
+pub(crate) fn sized_digest<R: Read>(
+    reader: R,
+    algorithm: Algorithm,
+    len: usize,
+    buffer_size: usize,
+) -> Result<Vec<u8>> {
+    use blake2::digest::{Update, VariableOutput};
+    use blake2::{Blake2bVar, Blake2sVar};
+    use sha3::{Shake128, Shake256};
+
+    let mut digest = vec![0u8; len];
+    match algorithm {
+        Algorithm::Blake2b512 => {
+            let mut hasher = Blake2bVar::new(len).expect("checked BLAKE2b length");
+            for_each_chunk(reader, buffer_size, |chunk| hasher.update(chunk))?;
+            hasher.finalize_variable(&mut digest).expect("sized buffer");
+        }
+        Algorithm::Blake2s256 => {
+            let mut hasher = Blake2sVar::new(len).expect("checked BLAKE2s length");
+            for_each_chunk(reader, buffer_size, |chunk| hasher.update(chunk))?;
+            hasher.finalize_variable(&mut digest).expect("sized buffer");
+        }
+        Algorithm::Blake3 => {
+            let mut hasher = blake3::Hasher::new();
+            for_each_chunk(reader, buffer_size, |chunk| {
+                hasher.update(chunk);
+            })?;
+            hasher.finalize_xof().fill(&mut digest);
+        }
+        Algorithm::Shake128 => return xof::<Shake128, R>(reader, buffer_size, len),
+        Algorithm::Shake256 => return xof::<Shake256, R>(reader, buffer_size, len),
+        _ => {
+            debug_assert_eq!(len, algorithm.output_size());
+            return digest_reader(reader, algorithm, buffer_size);
+        }
+    }
+    Ok(digest)
+}
+
+fn stream<D: Digest, R: Read>(reader: R, buffer_size: usize) -> Result<Vec<u8>> {
+    let mut hasher = D::new();
+    for_each_chunk(reader, buffer_size, |chunk| hasher.update(chunk))?;
+    Ok(hasher.finalize().to_vec())
+}
+
+/// The first `len` bytes an extendable-output function gives for `reader`
+fn xof<D, R>(reader: R, buffer_size: usize, len: usize) -> Result<Vec<u8>>
+where
+    D: Default + sha3::digest::Update + sha3::digest::ExtendableOutput,
+    R: Read,
+{
+    let mut hasher = D::default();
+    for_each_chunk(reader, buffer_size, |chunk| hasher.update(chunk))?;
+    let mut digest = vec![0u8; len];
+    hasher.finalize_xof_into(&mut digest);
+    Ok(digest)
+}
+
+pub(crate) fn for_each_chunk<R: Read>(
+    mut reader: R,
+    buffer_size: usize,
+    mut f: impl FnMut(&[u8]),
+) -> Result<()> {
+    let mut buffer = vec![0u8; buffer_size];
+    loop {
+        let count = reader.read(&mut buffer)?;
+        if count == 0 {
+            return Ok(());
+        }
+        f(&buffer[..count]);
+    }
+}
@@ -0,0 +1,172 @@
+//! Build metadata for inventory
+//!
+//! [`build_info`] describes what a compiled binary can do: its version,
+//! target triple, the optional crate features it was built with, the
+//! algorithms it supports and the hardware-accelerated backends the
+//! hashing crates pick on the running CPU. `hash --version --json` prints
+//! it, so a fleet inventory can tell deployed binaries apart.
+//!
+//! ```
+//! use hashing::buildinfo::build_info;
+//!
+//! let info = build_info();
+//! assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+//! assert!(info.algorithms.iter().any(|name| name == "sha256"));
+//! ```
+
+use crate::Algorithm;
+use serde::Serialize;
+
+/// Optional crate features and whether each was compiled in
+const FEATURES: [(&str, bool); 7] = [
+    ("archive", cfg!(feature = "archive")),
+    ("executable", cfg!(feature = "executable")),
+    ("git", cfg!(feature = "git")),
+    ("http", cfg!(feature = "http")),
+    ("perf", cfg!(feature = "perf")),
+    ("serde-hash", cfg!(feature = "serde-hash")),
+    ("sigstore", cfg!(feature = "sigstore")),
+];
+
+/// What a binary was built with and what it uses at run time
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BuildInfo {
+    /// Package name
+    pub name: &'static str,
+    /// Package version
+    pub version: &'static str,
+    /// Target triple, e.g. `x86_64-unknown-linux-gnu`
+    pub target: &'static str,
+    /// `release` or `debug`
+    pub profile: &'static str,
+    /// Optional features compiled in
+    pub features: Vec<&'static str>,
+    /// Names of the supported algorithms
+    pub algorithms: Vec<String>,
+    /// CPU features relevant to hashing found on this machine
+    pub cpu_features: Vec<&'static str>,
+    /// Implementation each accelerated algorithm uses on this machine
+    pub backends: Vec<Backend>,
+}
+
+/// The implementation picked for one algorithm
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Backend {
+    /// Algorithm name
+    pub algorithm: &'static str,
+    /// Implementation, e.g. `sha-ni`, `avx2` or `portable`
+    pub backend: &'static str,
+}
+
+/// Metadata of the running binary
+pub fn build_info() -> BuildInfo {
+    let cpu = cpu_features();
+    BuildInfo {
+        name: env!("CARGO_PKG_NAME"),
+        version: env!("CARGO_PKG_VERSION"),
+        target: env!("HASHING_TARGET"),
+        profile: if cfg!(debug_assertions) {
+            "debug"
+        } else {
+            "release"
+        },
+        features: FEATURES
+            .iter()
+            .filter(|(_, enabled)| *enabled)
+            .map(|(name, _)| *name)
+            .collect(),
+        algorithms: Algorithm::all()
+            .iter()
+            .map(|algorithm| algorithm.name().to_string())
+            .collect(),
+        backends: backends(&cpu),
+        cpu_features: cpu,
+    }
+}
+
+impl BuildInfo {
+    /// Implementation `algorithm` uses on this machine: that of the SHA-256,
+    /// SHA-512 or BLAKE3 core it is built on, otherwise `portable`
+    pub fn backend(&self, algorithm: Algorithm) -> &'static str {
+        let core = match algorithm {
+            Algorithm::Sha224 | Algorithm::Sha256 | Algorithm::Sha256d | Algorithm::Hash160 => {
+                "sha256"
+            }
+            Algorithm::Sha384
+            | Algorithm::Sha512
+            | Algorithm::Sha512_224
+            | Algorithm::Sha512_256 => "sha512",
+            Algorithm::Blake3 => "blake3",
+            _ => return "portable",
+        };
+        self.backends
+            .iter()
+            .find(|backend| backend.algorithm == core)
+            .map_or("portable", |backend| backend.backend)
+    }
+}
+
+/// Hashing-related CPU features detected at run time
+pub fn cpu_features() -> Vec<&'static str> {
+    let mut found = Vec::new();
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        macro_rules! detect {
+            ($($feature:tt),*) => {
+                $(if std::arch::is_x86_feature_detected!($feature) {
+                    found.push($feature);
+                })*
+            };
+        }
+        detect!("sse2", "ssse3", "sse4.1", "avx2", "avx512f", "avx512vl", "sha");
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        macro_rules! detect {
+            ($($feature:tt),*) => {
+                $(if std::arch::is_aarch64_feature_detected!($feature) {
+                    found.push($feature);
+                })*
+            };
+        }
+        detect!("neon", "sha2", "sha3");
+    }
+    found
+}
+
+/// Backends of the SHA-2 and BLAKE3 crates, which select them the same way
+/// from the same CPU features; everything else is portable code
+fn backends(cpu: &[&str]) -> Vec<Backend> {
+    let has = |features: &[&str]| features.iter().all(|feature| cpu.contains(feature));
+    let sha256 = if has(&["sha", "sse2", "ssse3", "sse4.1"]) {
+        "sha-ni"
+    } else {
+        "portable"
+    };
+    let sha512 = if has(&["avx2"]) { "avx2" } else { "portable" };
+    let blake3 = if has(&["avx512f", "avx512vl"]) {
+        "avx512"
+    } else if has(&["avx2"]) {
+        "avx2"
+    } else if has(&["sse4.1"]) {
+        "sse4.1"
+    } else if has(&["sse2"]) {
+        "sse2"
+    } else {
+        "portable"
+    };
+    vec![
+        Backend {
+            algorithm: "sha256",
+            backend: sha256,
+        },
+        Backend {
+            algorithm: "sha512",
+            backend: sha512,
+        },
+        Backend {
+            algorithm: "blake3",
+            backend: blake3,
+        },
+    ]
+}
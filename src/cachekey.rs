@@ -0,0 +1,234 @@
+//! Stable cache keys from files, environment variables and literals
+//!
+//! CI caches are keyed by a digest of whatever determines the cached
+//! content: lock files, toolchain files, flags in the environment, a
+//! manual version bump. [`CacheKey`] collects those inputs and hashes them
+//! in a fixed order, so the key only depends on what was declared and on
+//! its contents, never on argument order or the machine.
+//!
+//! ```no_run
+//! use hashing::cachekey::{CacheKey, MissingInput};
+//!
+//! let key = CacheKey::new()
+//!     .with_file("Cargo.lock")
+//!     .with_file("rust-toolchain.toml")
+//!     .with_env("RUSTFLAGS")
+//!     .with_literal("v2")
+//!     .with_missing(MissingInput::Mark)
+//!     .compute()?;
+//! println!("cargo-{}", key);
+//! # Ok::<(), hashing::HashError>(())
+//! ```
+
+use crate::builder::digest_bytes;
+use crate::combine::combine_ordered;
+use crate::walk::{walk_files, WalkOptions};
+use crate::{hash_file, Algorithm, HashError, Result};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// What to do with a declared file that does not exist
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MissingInput {
+    /// Fail, so a typo cannot silently produce a key (the default)
+    #[default]
+    Fail,
+    /// Leave it out of the key, as if it had not been declared
+    Skip,
+    /// Include its absence in the key
+    Mark,
+}
+
+impl fmt::Display for MissingInput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            MissingInput::Fail => "fail",
+            MissingInput::Skip => "skip",
+            MissingInput::Mark => "mark",
+        })
+    }
+}
+
+impl FromStr for MissingInput {
+    type Err = HashError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "fail" | "error" => Ok(MissingInput::Fail),
+            "skip" | "ignore" => Ok(MissingInput::Skip),
+            "mark" => Ok(MissingInput::Mark),
+            _ => Err(HashError::InvalidInput(format!(
+                "unknown missing-input policy: {} (expected fail, skip or mark)",
+                s
+            ))),
+        }
+    }
+}
+
+/// Kind of a cache key input
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EntryKind {
+    /// A file's contents
+    File,
+    /// An environment variable's value
+    Env,
+    /// A literal string
+    Literal,
+}
+
+impl EntryKind {
+    fn label(self) -> &'static str {
+        match self {
+            EntryKind::File => "file",
+            EntryKind::Env => "env",
+            EntryKind::Literal => "literal",
+        }
+    }
+}
+
+impl fmt::Display for EntryKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.label())
+    }
+}
+
+/// One input as it enters the key
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CacheKeyEntry {
+    /// What the input is
+    pub kind: EntryKind,
+    /// Path with `/` separators, variable name, or the literal itself
+    pub name: String,
+    /// Hex digest of the contents or value; `None` for a missing file or
+    /// an unset variable
+    pub digest: Option<String>,
+}
+
+/// Declared inputs of a cache key
+#[derive(Debug, Clone, Default)]
+pub struct CacheKey {
+    algorithm: Algorithm,
+    files: Vec<PathBuf>,
+    env: Vec<String>,
+    literals: Vec<String>,
+    missing: MissingInput,
+}
+
+impl CacheKey {
+    /// No inputs, SHA-256, failing on missing files
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Hash algorithm for the inputs and the key
+    pub fn with_algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Add a file, or every file below a directory
+    pub fn with_file<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.files.push(path.into());
+        self
+    }
+
+    /// Add an environment variable; unset and empty are told apart
+    pub fn with_env(mut self, name: impl Into<String>) -> Self {
+        self.env.push(name.into());
+        self
+    }
+
+    /// Add a literal string, such as a manual version bump
+    pub fn with_literal(mut self, value: impl Into<String>) -> Self {
+        self.literals.push(value.into());
+        self
+    }
+
+    /// What to do with declared files that do not exist
+    pub fn with_missing(mut self, policy: MissingInput) -> Self {
+        self.missing = policy;
+        self
+    }
+
+    /// The inputs in the order they are hashed: by kind, then name, without
+    /// duplicates
+    pub fn entries(&self) -> Result<Vec<CacheKeyEntry>> {
+        let mut entries = Vec::new();
+        for path in &self.files {
+            if path.is_dir() {
+                for file in walk_files(path, &WalkOptions::new()) {
+                    let file = file?;
+                    let digest = hash_file(&file, self.algorithm)?;
+                    entries.push(entry(EntryKind::File, path_name(&file), Some(digest)));
+                }
+            } else if path.exists() {
+                let digest = hash_file(path, self.algorithm)?;
+                entries.push(entry(EntryKind::File, path_name(path), Some(digest)));
+            } else {
+                match self.missing {
+                    MissingInput::Fail => {
+                        return Err(HashError::InvalidInput(format!(
+                            "cache key input does not exist: {}",
+                            path.display()
+                        )))
+                    }
+                    MissingInput::Skip => {}
+                    MissingInput::Mark => {
+                        entries.push(entry(EntryKind::File, path_name(path), None))
+                    }
+                }
+            }
+        }
+        for name in &self.env {
+            let digest = match std::env::var_os(name) {
+                Some(value) => Some(hex::encode(digest_bytes(
+                    value.to_string_lossy().as_bytes(),
+                    self.algorithm,
+                )?)),
+                None => None,
+            };
+            entries.push(entry(EntryKind::Env, name.clone(), digest));
+        }
+        for literal in &self.literals {
+            let digest = hex::encode(digest_bytes(literal.as_bytes(), self.algorithm)?);
+            entries.push(entry(EntryKind::Literal, literal.clone(), Some(digest)));
+        }
+        entries.sort();
+        entries.dedup();
+        Ok(entries)
+    }
+
+    /// The key: a hex digest over every entry's kind, name and digest
+    pub fn compute(&self) -> Result<String> {
+        let entries = self.entries()?;
+        key_for(&entries, self.algorithm)
+    }
+}
+
+/// The key over already collected entries
+pub fn key_for(entries: &[CacheKeyEntry], algorithm: Algorithm) -> Result<String> {
+    let parts = entries
+        .iter()
+        .map(|entry| {
+            let digest = entry.digest.as_deref().unwrap_or_default();
+            let fields = [entry.kind.label(), &entry.name, digest];
+            combine_ordered(&fields, algorithm)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    combine_ordered(&parts, algorithm).map(hex::encode)
+}
+
+fn entry(kind: EntryKind, name: String, digest: Option<String>) -> CacheKeyEntry {
+    CacheKeyEntry { kind, name, digest }
+}
+
+fn path_name(path: &Path) -> String {
+    let name = path.to_string_lossy();
+    let name = name.strip_prefix("./").unwrap_or(&name);
+    if std::path::MAIN_SEPARATOR == '\\' {
+        name.replace('\\', "/")
+    } else {
+        name.to_string()
+    }
+}
@@ -0,0 +1,311 @@
+//! Hashing of serializable values through a canonical encoding
+//!
+//! A value is serialized deterministically, so the same data hashes the
+//! same across crate versions, platforms and languages, regardless of
+//! struct field order or `HashMap` iteration order:
+//!
+//! - [`CanonicalFormat::Json`] follows the JSON Canonicalization Scheme
+//!   (JCS, RFC 8785): keys sorted by UTF-16 code units, no whitespace and
+//!   ECMAScript number formatting.
+//! - [`CanonicalFormat::Cbor`] follows the core deterministic encoding of
+//!   RFC 8949 §4.2: shortest-form integers and floats, definite lengths and
+//!   map keys sorted by their encoded bytes.
+//! - [`CanonicalFormat::MessagePack`] applies the same rules to MessagePack:
+//!   shortest-form integers, 64-bit floats, maps (not arrays) for structs
+//!   and map keys sorted by their encoded bytes.
+//!
+//! Existing documents in any of these formats can be re-encoded with
+//! [`canonicalize_document`], so a JSON file, its CBOR twin and a Go or
+//! Python service producing the same data all hash identically.
+//!
+//! Requires the `serde-hash` feature.
+
+use crate::{hash_bytes, Algorithm, HashError, Result};
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use std::str::FromStr;
+
+/// Deterministic encoding used before hashing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CanonicalFormat {
+    /// JSON Canonicalization Scheme (RFC 8785)
+    #[default]
+    Json,
+    /// Deterministically encoded CBOR (RFC 8949 §4.2)
+    Cbor,
+    /// MessagePack with sorted map keys and shortest-form integers
+    MessagePack,
+}
+
+impl FromStr for CanonicalFormat {
+    type Err = HashError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "json" | "jcs" => Ok(CanonicalFormat::Json),
+            "cbor" => Ok(CanonicalFormat::Cbor),
+            "msgpack" | "messagepack" => Ok(CanonicalFormat::MessagePack),
+            _ => Err(HashError::InvalidInput(format!(
+                "unknown canonical format: {}",
+                s
+            ))),
+        }
+    }
+}
+
+/// Serialize `value` canonically and hash the resulting bytes
+///
+/// # Examples
+///
+/// ```
+/// use hashing::canonical::{hash_serialize, CanonicalFormat};
+/// use hashing::Algorithm;
+/// use std::collections::HashMap;
+///
+/// let a: HashMap<_, _> = [("b", 2), ("a", 1)].into_iter().collect();
+/// let b: HashMap<_, _> = [("a", 1), ("b", 2)].into_iter().collect();
+/// assert_eq!(
+///     hash_serialize(&a, Algorithm::Sha256, CanonicalFormat::Json).unwrap(),
+///     hash_serialize(&b, Algorithm::Sha256, CanonicalFormat::Json).unwrap(),
+/// );
+/// ```
+pub fn hash_serialize<T: Serialize + ?Sized>(
+    value: &T,
+    algorithm: Algorithm,
+    format: CanonicalFormat,
+) -> Result<String> {
+    hash_bytes(&to_canonical_bytes(value, format)?, algorithm)
+}
+
+/// The canonical encoding of `value` that [`hash_serialize`] hashes
+pub fn to_canonical_bytes<T: Serialize + ?Sized>(
+    value: &T,
+    format: CanonicalFormat,
+) -> Result<Vec<u8>> {
+    match format {
+        CanonicalFormat::Json => {
+            let value = serde_json::to_value(value).map_err(serialize_error)?;
+            let mut out = String::new();
+            write_jcs(&value, &mut out)?;
+            Ok(out.into_bytes())
+        }
+        CanonicalFormat::Cbor => {
+            let value = ciborium::value::Value::serialized(value).map_err(serialize_error)?;
+            let mut out = Vec::new();
+            ciborium::ser::into_writer(&canonical_cbor(value, encode_cbor)?, &mut out)
+                .map_err(serialize_error)?;
+            Ok(out)
+        }
+        CanonicalFormat::MessagePack => {
+            // Going through the CBOR data model keeps structs as maps
+            let value = ciborium::value::Value::serialized(value).map_err(serialize_error)?;
+            encode_msgpack(&canonical_cbor(value, encode_msgpack)?)
+        }
+    }
+}
+
+/// Parse a document in format `from` and re-encode it canonically as `to`
+///
+/// # Examples
+///
+/// ```
+/// use hashing::canonical::{canonicalize_document, CanonicalFormat};
+///
+/// let doc = br#"{ "b": [1, 2.0], "a": "x" }"#;
+/// let out = canonicalize_document(doc, CanonicalFormat::Json, CanonicalFormat::Json);
+/// assert_eq!(out.unwrap(), br#"{"a":"x","b":[1,2]}"#);
+/// ```
+pub fn canonicalize_document(
+    document: &[u8],
+    from: CanonicalFormat,
+    to: CanonicalFormat,
+) -> Result<Vec<u8>> {
+    let parse_error =
+        |e: &dyn std::fmt::Display| HashError::InvalidInput(format!("invalid document: {}", e));
+    match from {
+        CanonicalFormat::Json => {
+            let value: JsonValue = serde_json::from_slice(document).map_err(|e| parse_error(&e))?;
+            to_canonical_bytes(&value, to)
+        }
+        CanonicalFormat::Cbor => {
+            let value: ciborium::value::Value =
+                ciborium::de::from_reader(document).map_err(|e| parse_error(&e))?;
+            to_canonical_bytes(&value, to)
+        }
+        CanonicalFormat::MessagePack => {
+            let value: ciborium::value::Value =
+                rmp_serde::from_slice(document).map_err(|e| parse_error(&e))?;
+            to_canonical_bytes(&value, to)
+        }
+    }
+}
+
+/// Hash a document after re-encoding it canonically
+pub fn hash_document(
+    document: &[u8],
+    from: CanonicalFormat,
+    to: CanonicalFormat,
+    algorithm: Algorithm,
+) -> Result<String> {
+    hash_bytes(&canonicalize_document(document, from, to)?, algorithm)
+}
+
+fn serialize_error(err: impl std::fmt::Display) -> HashError {
+    HashError::InvalidInput(format!("cannot serialize value: {}", err))
+}
+
+fn write_jcs(value: &JsonValue, out: &mut String) -> Result<()> {
+    match value {
+        JsonValue::Null | JsonValue::Bool(_) | JsonValue::String(_) => {
+            // serde_json's escaping of strings matches JSON.stringify
+            out.push_str(&serde_json::to_string(value).map_err(serialize_error)?);
+        }
+        JsonValue::Number(number) => {
+            let number = number
+                .as_f64()
+                .ok_or_else(|| serialize_error("number out of range"))?;
+            out.push_str(&format_es_number(number)?);
+        }
+        JsonValue::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_jcs(item, out)?;
+            }
+            out.push(']');
+        }
+        JsonValue::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+            out.push('{');
+            for (i, (key, item)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&serde_json::to_string(key).map_err(serialize_error)?);
+                out.push(':');
+                write_jcs(item, out)?;
+            }
+            out.push('}');
+        }
+    }
+    Ok(())
+}
+
+/// Format a number like ECMAScript's `Number.prototype.toString`
+fn format_es_number(value: f64) -> Result<String> {
+    if !value.is_finite() {
+        return Err(serialize_error("NaN and infinity are not valid JSON"));
+    }
+    if value == 0.0 {
+        return Ok("0".to_string());
+    }
+
+    // Shortest round-tripping digits, as `d.ddde<exp>`
+    let scientific = format!("{:e}", value.abs());
+    let (mantissa, exponent) = scientific
+        .split_once('e')
+        .ok_or_else(|| serialize_error("unexpected float format"))?;
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let exponent: i32 = exponent.parse().map_err(serialize_error)?;
+
+    // ECMAScript describes the value as 0.<digits> * 10^point
+    let k = digits.len() as i32;
+    let point = exponent + 1;
+    let mut out = String::new();
+    if value < 0.0 {
+        out.push('-');
+    }
+    if k <= point && point <= 21 {
+        out.push_str(&digits);
+        out.push_str(&"0".repeat((point - k) as usize));
+    } else if 0 < point && point <= 21 {
+        out.push_str(&digits[..point as usize]);
+        out.push('.');
+        out.push_str(&digits[point as usize..]);
+    } else if -6 < point && point <= 0 {
+        out.push_str("0.");
+        out.push_str(&"0".repeat(-point as usize));
+        out.push_str(&digits);
+    } else {
+        out.push_str(&digits[..1]);
+        if k > 1 {
+            out.push('.');
+            out.push_str(&digits[1..]);
+        }
+        out.push('e');
+        out.push(if point > 0 { '+' } else { '-' });
+        out.push_str(&(point - 1).abs().to_string());
+    }
+    Ok(out)
+}
+
+fn encode_cbor(value: &ciborium::value::Value) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    ciborium::ser::into_writer(value, &mut out).map_err(serialize_error)?;
+    Ok(out)
+}
+
+fn encode_msgpack(value: &ciborium::value::Value) -> Result<Vec<u8>> {
+    rmp_serde::to_vec(value).map_err(serialize_error)
+}
+
+/// Sort every map by the `encode`d bytes of its keys, recursively
+fn canonical_cbor(
+    value: ciborium::value::Value,
+    encode: fn(&ciborium::value::Value) -> Result<Vec<u8>>,
+) -> Result<ciborium::value::Value> {
+    use ciborium::value::Value;
+
+    Ok(match value {
+        Value::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .map(|item| canonical_cbor(item, encode))
+                .collect::<Result<_>>()?,
+        ),
+        Value::Map(entries) => {
+            let mut keyed = Vec::with_capacity(entries.len());
+            for (key, item) in entries {
+                let key = canonical_cbor(key, encode)?;
+                keyed.push((encode(&key)?, key, canonical_cbor(item, encode)?));
+            }
+            keyed.sort_by(|a, b| a.0.cmp(&b.0));
+            Value::Map(
+                keyed
+                    .into_iter()
+                    .map(|(_, key, item)| (key, item))
+                    .collect(),
+            )
+        }
+        Value::Tag(tag, inner) => Value::Tag(tag, Box::new(canonical_cbor(*inner, encode)?)),
+        other => other,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_es_number_formatting() {
+        let cases = [
+            (1.0, "1"),
+            (-0.0, "0"),
+            (0.5, "0.5"),
+            (123.456, "123.456"),
+            (1e21, "1e+21"),
+            (1e20, "100000000000000000000"),
+            (1e-7, "1e-7"),
+            (0.000001, "0.000001"),
+            (-1.5e-10, "-1.5e-10"),
+            (9007199254740991.0, "9007199254740991"),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(format_es_number(value).unwrap(), expected, "{}", value);
+        }
+        assert!(format_es_number(f64::NAN).is_err());
+    }
+}
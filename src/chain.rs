@@ -0,0 +1,229 @@
+//! Tamper-evident, append-only hash chains
+//!
+//! Every record of a [`ChainLog`] carries the digest of the record before
+//! it, so editing, removing or reordering any record breaks every digest
+//! after it. Keeping a copy of the latest digest ([`ChainLog::head`])
+//! somewhere else also detects truncation.
+//!
+//! A record's digest covers, in order: the previous digest (all zero bytes
+//! for the first record), then the index, the timestamp in seconds since
+//! the Unix epoch and the entry's length as 64-bit big-endian integers,
+//! then the entry's UTF-8 bytes. Timestamps may not go backwards.
+//!
+//! File-backed logs are stored as JSON Lines, one record per line, and
+//! each record is flushed as soon as it is appended.
+//!
+//! ```
+//! use hashing::chain::ChainLog;
+//! use hashing::Algorithm;
+//!
+//! let mut log = ChainLog::new(Algorithm::Sha256);
+//! log.append("user alice logged in").unwrap();
+//! log.append("user alice deleted report.pdf").unwrap();
+//! assert!(log.verify().is_ok());
+//! ```
+
+use crate::builder::digest_bytes;
+use crate::{Algorithm, HashError, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// One link of the chain
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainRecord {
+    /// Position in the chain, starting at 0
+    pub index: u64,
+    /// Seconds since the Unix epoch when the entry was appended
+    pub timestamp: u64,
+    /// The logged entry
+    pub entry: String,
+    /// Hex digest of the previous record (zeros for the first record)
+    pub prev: String,
+    /// Hex digest of this record
+    pub digest: String,
+}
+
+/// An append-only log whose records are linked by their digests
+pub struct ChainLog {
+    algorithm: Algorithm,
+    records: Vec<ChainRecord>,
+    file: Option<File>,
+}
+
+impl ChainLog {
+    /// An empty in-memory log
+    pub fn new(algorithm: Algorithm) -> Self {
+        Self {
+            algorithm,
+            records: Vec::new(),
+            file: None,
+        }
+    }
+
+    /// Open a file-backed log, creating it if it does not exist yet
+    ///
+    /// Existing records are loaded and verified; a broken chain is an
+    /// error rather than something to append to.
+    pub fn open<P: AsRef<Path>>(path: P, algorithm: Algorithm) -> Result<Self> {
+        let path = path.as_ref();
+        let mut log = Self::new(algorithm);
+        if path.exists() {
+            let reader = BufReader::new(File::open(path)?);
+            for (number, line) in reader.lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let record = serde_json::from_str(&line).map_err(|e| {
+                    HashError::InvalidInput(format!(
+                        "{}:{}: invalid chain record: {}",
+                        path.display(),
+                        number + 1,
+                        e
+                    ))
+                })?;
+                log.records.push(record);
+            }
+            log.verify()?;
+        }
+        log.file = Some(OpenOptions::new().create(true).append(true).open(path)?);
+        Ok(log)
+    }
+
+    /// Append an entry stamped with the current time
+    pub fn append(&mut self, entry: impl Into<String>) -> Result<&ChainRecord> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or(0);
+        // Never go backwards, even if the system clock does
+        let timestamp = self
+            .records
+            .last()
+            .map_or(now, |last| now.max(last.timestamp));
+        self.append_at(entry, timestamp)
+    }
+
+    /// Append an entry with an explicit timestamp (seconds since the epoch)
+    pub fn append_at(&mut self, entry: impl Into<String>, timestamp: u64) -> Result<&ChainRecord> {
+        let entry = entry.into();
+        let index = self.records.len() as u64;
+        if let Some(last) = self.records.last() {
+            if timestamp < last.timestamp {
+                return Err(HashError::InvalidInput(format!(
+                    "timestamp {} is before the previous record's {}",
+                    timestamp, last.timestamp
+                )));
+            }
+        }
+        let prev = self.head_or_genesis();
+        let digest = record_digest(&prev, index, timestamp, &entry, self.algorithm)?;
+        let record = ChainRecord {
+            index,
+            timestamp,
+            entry,
+            prev,
+            digest,
+        };
+
+        if let Some(file) = self.file.as_mut() {
+            let line = serde_json::to_string(&record)
+                .map_err(|e| HashError::ExportError(e.to_string()))?;
+            writeln!(file, "{}", line)?;
+            file.flush()?;
+        }
+        self.records.push(record);
+        Ok(self.records.last().expect("just pushed"))
+    }
+
+    /// Check every link, reporting the first record that does not match
+    pub fn verify(&self) -> Result<()> {
+        verify_records(&self.records, self.algorithm)
+    }
+
+    /// Digest of the latest record, or `None` for an empty log
+    pub fn head(&self) -> Option<&str> {
+        self.records.last().map(|record| record.digest.as_str())
+    }
+
+    /// All records, oldest first
+    pub fn records(&self) -> &[ChainRecord] {
+        &self.records
+    }
+
+    /// Number of records
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Whether the log has no records
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Algorithm linking the records
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    fn head_or_genesis(&self) -> String {
+        match self.head() {
+            Some(head) => head.to_string(),
+            None => "0".repeat(self.algorithm.output_size() * 2),
+        }
+    }
+}
+
+/// Verify a sequence of records, e.g. read from another system's export
+pub fn verify_records(records: &[ChainRecord], algorithm: Algorithm) -> Result<()> {
+    let mut prev = "0".repeat(algorithm.output_size() * 2);
+    let mut last_timestamp = 0;
+    for (position, record) in records.iter().enumerate() {
+        let broken = |reason: &str| {
+            HashError::InvalidInput(format!("chain broken at record {}: {}", position, reason))
+        };
+        if record.index != position as u64 {
+            return Err(broken("index out of sequence"));
+        }
+        if record.prev != prev {
+            return Err(broken("previous digest does not match"));
+        }
+        if record.timestamp < last_timestamp {
+            return Err(broken("timestamp goes backwards"));
+        }
+        let expected = record_digest(
+            &record.prev,
+            record.index,
+            record.timestamp,
+            &record.entry,
+            algorithm,
+        )?;
+        if record.digest != expected {
+            return Err(broken("digest does not match contents"));
+        }
+        prev = record.digest.clone();
+        last_timestamp = record.timestamp;
+    }
+    Ok(())
+}
+
+fn record_digest(
+    prev: &str,
+    index: u64,
+    timestamp: u64,
+    entry: &str,
+    algorithm: Algorithm,
+) -> Result<String> {
+    let prev = hex::decode(prev)
+        .map_err(|e| HashError::InvalidInput(format!("invalid previous digest: {}", e)))?;
+    let mut data = Vec::with_capacity(prev.len() + 24 + entry.len());
+    data.extend_from_slice(&prev);
+    data.extend_from_slice(&index.to_be_bytes());
+    data.extend_from_slice(&timestamp.to_be_bytes());
+    data.extend_from_slice(&(entry.len() as u64).to_be_bytes());
+    data.extend_from_slice(entry.as_bytes());
+    digest_bytes(&data, algorithm).map(hex::encode)
+}
@@ -0,0 +1,828 @@
+//! Parsing of checksum files and expected-digest sources
+//!
+//! Checksum files follow the coreutils layout (`<digest>  <path>`, with an
+//! optional `*` marking binary mode) or the BSD one (`SHA256 (<path>) =
+//! <digest>`, also written by `sha256sum --tag`). A file holding a bare
+//! digest is also accepted, which covers the common `<artifact>.sha256`
+//! download companion. A line starting with a backslash has an escaped
+//! name (see [`escape_name`]), as coreutils writes for names holding a
+//! backslash or a line break.
+//! [`verify_entries`] checks the files a checksum file lists, several at a
+//! time, reporting them in list order; [`verify_entries_cached`] skips the
+//! ones a [`StatCache`] shows as untouched since they last verified.
+//! [`ChecksumReader`] and [`verify_reader`] do the same for checksum files
+//! too large to load, reading them as a stream.
+
+use crate::statcache::{FileStamp, StatCache};
+use crate::{hash_file, Algorithm, Digest, HashError, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufRead};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+/// A single `<digest>  <path>` entry of a checksum file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChecksumEntry {
+    pub digest: String,
+    pub path: String,
+}
+
+/// Parse one line of a checksum file
+///
+/// Returns `None` for blank lines and `#` comments. A line holding only a
+/// digest, or an escaped name that does not unescape, yields an entry
+/// with an empty path.
+pub fn parse_line(line: &str) -> Option<ChecksumEntry> {
+    let (digest, path, escaped) = line_spans(line)?;
+    let path = match escaped {
+        true => unescape_name(&line[path]).unwrap_or_default(),
+        false => line[path].to_string(),
+    };
+    Some(ChecksumEntry {
+        digest: line[digest].to_string(),
+        path,
+    })
+}
+
+/// `name` as coreutils writes it in a checksum line: with backslashes,
+/// newlines and carriage returns escaped, in which case the line starts
+/// with a backslash (the returned prefix)
+///
+/// # Examples
+///
+/// ```
+/// use hashing::checksum::{escape_name, unescape_name};
+///
+/// assert_eq!(escape_name("plain.txt"), ("", "plain.txt".to_string()));
+/// assert_eq!(escape_name("we\\ird.txt"), ("\\", "we\\\\ird.txt".to_string()));
+/// assert_eq!(unescape_name("new\\nline").unwrap(), "new\nline");
+/// ```
+pub fn escape_name(name: &str) -> (&'static str, String) {
+    if !name.contains(['\\', '\n', '\r']) {
+        return ("", name.to_string());
+    }
+    let escaped = name
+        .replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r");
+    ("\\", escaped)
+}
+
+/// The name [`escape_name`] escaped, or `None` for a stray backslash
+pub fn unescape_name(name: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(name.len());
+    let mut chars = name.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next()? {
+            '\\' => unescaped.push('\\'),
+            'n' => unescaped.push('\n'),
+            'r' => unescaped.push('\r'),
+            _ => return None,
+        }
+    }
+    Some(unescaped)
+}
+
+/// Algorithm named by the tag of a BSD-style line (`SHA256 (app.tar) =
+/// ...`); `None` for other lines and tags naming no supported algorithm
+///
+/// # Examples
+///
+/// ```
+/// use hashing::checksum::{parse_line, tagged_algorithm};
+/// use hashing::Algorithm;
+///
+/// let line = "MD5 (app.tar) = 5d41402abc4b2a76b9719d911017c592";
+/// assert_eq!(tagged_algorithm(line), Some(Algorithm::Md5));
+/// assert_eq!(parse_line(line).unwrap().path, "app.tar");
+/// assert_eq!(tagged_algorithm("d41d8cd98f00b204e9800998ecf8427e  empty"), None);
+/// ```
+pub fn tagged_algorithm(line: &str) -> Option<Algorithm> {
+    let content = line.trim_end_matches(['\r', '\n']);
+    let content = content.trim_start();
+    let content = content.strip_prefix('\\').unwrap_or(content);
+    tagged_spans(content)?;
+    content[..content.find(" (")?].parse().ok()
+}
+
+/// Byte ranges of the digest and the path within a checksum file line, and
+/// whether the path is escaped
+fn line_spans(line: &str) -> Option<(Range<usize>, Range<usize>, bool)> {
+    let content = line.trim_end_matches(['\r', '\n']);
+    if content.trim().is_empty() || content.trim_start().starts_with('#') {
+        return None;
+    }
+    let trimmed = content.trim_start();
+    let (escaped, trimmed) = match trimmed.strip_prefix('\\') {
+        Some(rest) => (true, rest),
+        None => (false, trimmed),
+    };
+    let start = content.len() - trimmed.len();
+    let (digest, path) = match tagged_spans(trimmed) {
+        Some(spans) => spans,
+        None => plain_spans(trimmed),
+    };
+    Some((
+        start + digest.start..start + digest.end,
+        start + path.start..start + path.end,
+        escaped,
+    ))
+}
+
+/// Byte ranges of the digest and the path within a coreutils-style line
+fn plain_spans(content: &str) -> (Range<usize>, Range<usize>) {
+    let trimmed = content.trim_start();
+    let start = content.len() - trimmed.len();
+    let digest_len = trimmed.find(char::is_whitespace).unwrap_or(trimmed.len());
+    let rest = &trimmed[digest_len..];
+
+    // Two spaces separate digest and path; a `*` marks binary mode
+    let rest = rest.strip_prefix(' ').unwrap_or(rest);
+    let path = rest.strip_prefix(['*', ' ']).unwrap_or(rest);
+
+    let digest = start..start + digest_len;
+    (digest, content.len() - path.len()..content.len())
+}
+
+/// Byte ranges of the digest and the path within a BSD-style line
+fn tagged_spans(content: &str) -> Option<(Range<usize>, Range<usize>)> {
+    let trimmed = content.trim_start();
+    let start = content.len() - trimmed.len();
+    let open = trimmed.find(" (")?;
+    // The tag is one word; a coreutils line has two spaces before its path
+    if open == 0 || trimmed[..open].contains(char::is_whitespace) {
+        return None;
+    }
+    let close = trimmed.rfind(") = ")?;
+    let digest = &trimmed[close + 4..];
+    if close < open + 2 || digest.is_empty() || digest.contains(char::is_whitespace) {
+        return None;
+    }
+    Some((
+        start + close + 4..content.len(),
+        start + open + 2..start + close,
+    ))
+}
+
+/// A checksum file entry borrowed from a [`ChecksumReader`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EntryRef<'a> {
+    pub digest: &'a str,
+    pub path: &'a str,
+    /// 1-based line number in the checksum file
+    pub line: usize,
+}
+
+impl EntryRef<'_> {
+    /// An owned copy of the entry
+    pub fn to_entry(&self) -> ChecksumEntry {
+        ChecksumEntry {
+            digest: self.digest.to_string(),
+            path: self.path.to_string(),
+        }
+    }
+}
+
+/// Streaming parser for checksum files of any size
+///
+/// Lines are read one at a time into a buffer that is reused, so parsing
+/// allocates nothing per line and holds only the current line in memory.
+///
+/// ```
+/// use hashing::checksum::ChecksumReader;
+///
+/// let mut reader = ChecksumReader::new("# sums\naaaa  a.txt\nbbbb *b.bin\n".as_bytes());
+/// let mut paths = Vec::new();
+/// while let Some(entry) = reader.next_entry() {
+///     let entry = entry?;
+///     paths.push((entry.line, entry.path.to_string()));
+/// }
+/// assert_eq!(paths, [(2, "a.txt".to_string()), (3, "b.bin".to_string())]);
+/// # Ok::<(), hashing::HashError>(())
+/// ```
+pub struct ChecksumReader<R> {
+    reader: R,
+    buffer: String,
+    /// The current entry's name, when it had to be unescaped
+    name: String,
+    line: usize,
+}
+
+impl<R: BufRead> ChecksumReader<R> {
+    /// Parse checksum lines from `reader`
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buffer: String::new(),
+            name: String::new(),
+            line: 0,
+        }
+    }
+
+    /// The next entry, skipping blank lines and comments; `None` at the end
+    /// of the input
+    ///
+    /// As with [`parse_line`], the path is empty for a line holding only a
+    /// digest or a badly escaped name.
+    pub fn next_entry(&mut self) -> Option<Result<EntryRef<'_>>> {
+        let (digest, path, escaped) = loop {
+            self.buffer.clear();
+            match self.reader.read_line(&mut self.buffer) {
+                Ok(0) => return None,
+                Ok(_) => self.line += 1,
+                Err(e) => return Some(Err(e.into())),
+            }
+            if let Some(spans) = line_spans(&self.buffer) {
+                break spans;
+            }
+        };
+        let path = if escaped {
+            self.name = unescape_name(&self.buffer[path]).unwrap_or_default();
+            self.name.as_str()
+        } else {
+            &self.buffer[path]
+        };
+        Some(Ok(EntryRef {
+            digest: &self.buffer[digest],
+            path,
+            line: self.line,
+        }))
+    }
+}
+
+/// Pick the expected digest for `input` out of a checksum file's contents
+///
+/// If the file has a single entry, its digest is used regardless of the
+/// recorded name. Otherwise the entry whose file name matches the file name
+/// of `input` is selected.
+///
+/// # Examples
+///
+/// ```
+/// use hashing::checksum::expected_digest;
+///
+/// let sums = "aaaa  alpha.iso\nbbbb  beta.iso\n";
+/// assert_eq!(expected_digest(sums, "downloads/beta.iso").unwrap(), "bbbb");
+/// assert_eq!(expected_digest("cccc\n", "anything").unwrap(), "cccc");
+/// ```
+pub fn expected_digest(contents: &str, input: &str) -> Result<String> {
+    let entries: Vec<ChecksumEntry> = contents.lines().filter_map(parse_line).collect();
+
+    match entries.as_slice() {
+        [] => Err(HashError::InvalidInput(
+            "checksum file contains no digest".to_string(),
+        )),
+        [only] => Ok(only.digest.clone()),
+        _ => {
+            let wanted = Path::new(input).file_name();
+            entries
+                .iter()
+                .find(|entry| Path::new(&entry.path).file_name() == wanted)
+                .map(|entry| entry.digest.clone())
+                .ok_or_else(|| {
+                    HashError::InvalidInput(format!("no checksum entry for {}", input))
+                })
+        }
+    }
+}
+
+/// An expected digest after normalization
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpectedDigest {
+    /// Algorithm named by a `sha256:`-style prefix, if it was recognized
+    pub algorithm: Option<Algorithm>,
+    /// Lowercase hex digest with prefixes, separators and whitespace removed
+    pub hex: String,
+}
+
+/// Normalize a digest pasted from an arbitrary source
+///
+/// Accepts surrounding and embedded whitespace, uppercase hex, a `0x`
+/// prefix, an `algo:` or `algo=` label (e.g. `sha256:`), and colon-separated
+/// byte pairs as printed for certificate fingerprints.
+///
+/// # Examples
+///
+/// ```
+/// use hashing::checksum::normalize_expected;
+/// use hashing::Algorithm;
+///
+/// let expected = normalize_expected("  SHA256:0xABCD ef01\n");
+/// assert_eq!(expected.algorithm, Some(Algorithm::Sha256));
+/// assert_eq!(expected.hex, "abcdef01");
+/// ```
+pub fn normalize_expected(input: &str) -> ExpectedDigest {
+    let compact: String = input.chars().filter(|c| !c.is_whitespace()).collect();
+    let mut rest = compact.as_str();
+    let mut algorithm = None;
+
+    // A label is anything before the first ':' or '=' that isn't itself hex
+    if let Some(split) = rest.find([':', '=']) {
+        let label = &rest[..split];
+        if !label.is_empty() && !label.chars().all(|c| c.is_ascii_hexdigit()) {
+            algorithm = Algorithm::from_str(label).ok();
+            rest = &rest[split + 1..];
+        }
+    }
+
+    let rest = rest
+        .strip_prefix("0x")
+        .or_else(|| rest.strip_prefix("0X"))
+        .unwrap_or(rest);
+
+    let hex = if rest.contains(':') {
+        rest.split(':').collect::<String>()
+    } else {
+        rest.to_string()
+    };
+
+    ExpectedDigest {
+        algorithm,
+        hex: hex.to_ascii_lowercase(),
+    }
+}
+
+/// Compare a computed hex digest against a user-supplied expected value,
+/// normalizing the expected value first
+pub fn digests_match(expected: &str, actual: &str) -> bool {
+    normalize_expected(expected).hex == actual.to_ascii_lowercase()
+}
+
+/// Outcome of checking one listed file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckStatus {
+    /// The file has the listed digest
+    Ok,
+    /// The file was not read: its size and modification time are those it
+    /// had when it last verified (see [`verify_entries_cached`])
+    Cached,
+    /// The file's digest differs
+    Failed,
+    /// The file does not exist
+    Missing,
+    /// The file could not be read
+    Unreadable(String),
+    /// The line is not a checksum line for the algorithm: it names no file,
+    /// or its digest is not hex of the right length; nothing was read
+    Malformed,
+}
+
+/// Counts of [`CheckStatus`] values over a whole check
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CheckSummary {
+    pub ok: usize,
+    pub cached: usize,
+    pub failed: usize,
+    pub missing: usize,
+    pub unreadable: usize,
+    /// Lines that were skipped as [`CheckStatus::Malformed`]
+    pub malformed: usize,
+}
+
+impl CheckSummary {
+    /// True when every listed file was present and matched (or was assumed
+    /// to from the cache); malformed lines do not count, as with
+    /// `sha256sum -c` without `--strict`
+    pub fn is_ok(&self) -> bool {
+        self.failed == 0 && self.missing == 0 && self.unreadable == 0
+    }
+
+    /// Count one entry's outcome, e.g. from a [`VerifyJob`] event
+    ///
+    /// [`VerifyJob`]: crate::job::VerifyJob
+    pub fn add(&mut self, status: &CheckStatus) {
+        match status {
+            CheckStatus::Ok => self.ok += 1,
+            CheckStatus::Cached => self.cached += 1,
+            CheckStatus::Failed => self.failed += 1,
+            CheckStatus::Missing => self.missing += 1,
+            CheckStatus::Unreadable(_) => self.unreadable += 1,
+            CheckStatus::Malformed => self.malformed += 1,
+        }
+    }
+}
+
+/// Number of files to hash at once when the caller has no preference: the
+/// available parallelism
+pub fn default_jobs() -> usize {
+    thread::available_parallelism().map_or(1, |jobs| jobs.get())
+}
+
+/// Check that every entry's digest is hex of the length `algorithm`
+/// produces, so that a list made with another algorithm is rejected
+/// before any file is hashed
+///
+/// # Examples
+///
+/// ```
+/// use hashing::checksum::{parse_line, validate_entries};
+/// use hashing::Algorithm;
+///
+/// let entry = parse_line("aaf4c61ddcc5e8a2dabede0f3b482cd9aea9434d  hello.txt").unwrap();
+/// let err = validate_entries(&[entry], Algorithm::Sha256).unwrap_err();
+/// assert!(err.to_string().contains("hello.txt: expected 64 hex characters for sha256"));
+/// ```
+pub fn validate_entries(entries: &[ChecksumEntry], algorithm: Algorithm) -> Result<()> {
+    for entry in entries {
+        validate_digest(&entry.digest, algorithm, &entry.path)?;
+    }
+    Ok(())
+}
+
+/// [`Digest::parse_for`] on a listed digest, with `location` in the error
+fn validate_digest(digest: &str, algorithm: Algorithm, location: &str) -> Result<()> {
+    match Digest::parse_for(&normalize_expected(digest).hex, algorithm) {
+        Ok(_) => Ok(()),
+        Err(HashError::InvalidInput(message)) => Err(HashError::InvalidInput(format!(
+            "{}: {}",
+            location, message
+        ))),
+        Err(e) => Err(e),
+    }
+}
+
+/// Check each entry's file (relative paths below `base`) against its digest
+/// with `algorithm`, hashing up to `jobs` files at a time
+///
+/// `report` is called on the calling thread once per entry, in list order,
+/// as soon as that entry and all before it are done; results that finish
+/// early wait in a buffer. With `jobs` of 1 the files are hashed one after
+/// another on the calling thread. An entry whose digest does not fit
+/// `algorithm` is reported as [`CheckStatus::Failed`]; call
+/// [`validate_entries`] first to reject such a list up front.
+pub fn verify_entries<F>(
+    entries: &[ChecksumEntry],
+    algorithm: Algorithm,
+    base: &Path,
+    jobs: usize,
+    report: F,
+) -> CheckSummary
+where
+    F: FnMut(&ChecksumEntry, &CheckStatus),
+{
+    verify_entries_until(entries, algorithm, base, jobs, None, None, report)
+}
+
+/// [`verify_entries`], or with a `cache` [`verify_entries_cached`], that
+/// stops starting new files once `cancel` is set; entries not started by
+/// then are not reported
+pub(crate) fn verify_entries_until<F>(
+    entries: &[ChecksumEntry],
+    algorithm: Algorithm,
+    base: &Path,
+    jobs: usize,
+    cancel: Option<&AtomicBool>,
+    cache: Option<&mut StatCache>,
+    mut report: F,
+) -> CheckSummary
+where
+    F: FnMut(&ChecksumEntry, &CheckStatus),
+{
+    let mut summary = CheckSummary::default();
+    let mut updates = Vec::new();
+    let known = cache.as_deref();
+    let check = |entry: &ChecksumEntry| match known {
+        Some(known) => check_cached(&entry.digest, &entry.path, algorithm, base, known),
+        None => (check_file(&entry.digest, &entry.path, algorithm, base), None),
+    };
+    in_order(entries, jobs, cancel, check, |entry, (status, update)| {
+        summary.add(&status);
+        report(entry, &status);
+        updates.extend(update.map(|update| update.settle(&status)));
+    });
+    if let Some(cache) = cache {
+        for update in updates {
+            update.apply(cache, algorithm);
+        }
+    }
+    summary
+}
+
+/// [`verify_entries`], skipping files whose size and modification time
+/// `cache` says are unchanged since they last verified against the same
+/// digest; those are reported as [`CheckStatus::Cached`]
+///
+/// The cache is updated with the files that were hashed: matches are
+/// recorded, anything else is forgotten.
+pub fn verify_entries_cached<F>(
+    entries: &[ChecksumEntry],
+    algorithm: Algorithm,
+    base: &Path,
+    jobs: usize,
+    cache: &mut StatCache,
+    report: F,
+) -> CheckSummary
+where
+    F: FnMut(&ChecksumEntry, &CheckStatus),
+{
+    verify_entries_until(entries, algorithm, base, jobs, None, Some(cache), report)
+}
+
+/// Entries verified per batch by [`verify_reader`]
+const BATCH_ENTRIES: usize = 4096;
+
+/// Verify the entries of a checksum file as they are read from `reader`
+///
+/// Works like [`verify_entries`] (or, given a `cache`, like
+/// [`verify_entries_cached`]) but never holds the whole list: entries are
+/// read in batches of a few thousand into one reused buffer, so memory use
+/// stays flat for manifests of any length. Each batch is finished before
+/// the next is read. A line that names no file or whose digest does not
+/// fit `algorithm` (see [`validate_entries`]) is reported as
+/// [`CheckStatus::Malformed`] in its place and skipped, as `sha256sum -c`
+/// does. Stops at the first read error, which includes a line that is not
+/// UTF-8; the entries of that batch are not checked.
+///
+/// ```no_run
+/// use hashing::checksum::{default_jobs, verify_reader, CheckStatus, EntryRef};
+/// use hashing::Algorithm;
+/// use std::io::BufReader;
+/// use std::path::Path;
+///
+/// let file = BufReader::new(std::fs::File::open("SHA256SUMS")?);
+/// let report = |entry: EntryRef, status: &CheckStatus| println!("{}: {:?}", entry.path, status);
+/// let jobs = default_jobs();
+/// let summary = verify_reader(file, Algorithm::Sha256, Path::new("."), jobs, None, report)?;
+/// assert!(summary.is_ok());
+/// # Ok::<(), hashing::HashError>(())
+/// ```
+pub fn verify_reader<R, F>(
+    reader: R,
+    algorithm: Algorithm,
+    base: &Path,
+    jobs: usize,
+    cache: Option<&mut StatCache>,
+    report: F,
+) -> Result<CheckSummary>
+where
+    R: BufRead,
+    F: FnMut(EntryRef<'_>, &CheckStatus),
+{
+    verify_reader_until(reader, algorithm, base, jobs, None, cache, report)
+}
+
+/// [`verify_reader`] that stops starting new files, and reading, once
+/// `cancel` is set; entries not started by then are not reported
+pub(crate) fn verify_reader_until<R, F>(
+    reader: R,
+    algorithm: Algorithm,
+    base: &Path,
+    jobs: usize,
+    cancel: Option<&AtomicBool>,
+    mut cache: Option<&mut StatCache>,
+    mut report: F,
+) -> Result<CheckSummary>
+where
+    R: BufRead,
+    F: FnMut(EntryRef<'_>, &CheckStatus),
+{
+    let mut entries = ChecksumReader::new(reader);
+    let mut batch = EntryBatch::default();
+    let mut updates = Vec::new();
+    let mut summary = CheckSummary::default();
+    loop {
+        batch.clear();
+        while batch.spans.len() < BATCH_ENTRIES {
+            match entries.next_entry() {
+                Some(entry) => {
+                    let entry = entry?;
+                    let malformed = entry.path.is_empty()
+                        || validate_digest(entry.digest, algorithm, entry.path).is_err();
+                    batch.push(entry, malformed);
+                }
+                None => break,
+            }
+        }
+        if batch.spans.is_empty() {
+            return Ok(summary);
+        }
+
+        let known = cache.as_deref();
+        let check = |span: &EntrySpan| {
+            let entry = batch.get(span);
+            if span.malformed {
+                return (CheckStatus::Malformed, None);
+            }
+            match known {
+                Some(known) => check_cached(entry.digest, entry.path, algorithm, base, known),
+                None => (check_file(entry.digest, entry.path, algorithm, base), None),
+            }
+        };
+        in_order(&batch.spans, jobs, cancel, check, |span, (status, update)| {
+            summary.add(&status);
+            report(batch.get(span), &status);
+            updates.extend(update.map(|update| update.settle(&status)));
+        });
+        if let Some(cache) = cache.as_deref_mut() {
+            for update in updates.drain(..) {
+                update.apply(cache, algorithm);
+            }
+        }
+        if cancel.is_some_and(|cancel| cancel.load(Ordering::Relaxed)) {
+            return Ok(summary);
+        }
+    }
+}
+
+/// Entries of one [`verify_reader`] batch, packed into a single buffer
+#[derive(Default)]
+struct EntryBatch {
+    text: String,
+    spans: Vec<EntrySpan>,
+}
+
+struct EntrySpan {
+    line: usize,
+    malformed: bool,
+    digest: Range<usize>,
+    path: Range<usize>,
+}
+
+impl EntryBatch {
+    fn clear(&mut self) {
+        self.text.clear();
+        self.spans.clear();
+    }
+
+    fn push(&mut self, entry: EntryRef<'_>, malformed: bool) {
+        let start = self.text.len();
+        self.text.push_str(entry.digest);
+        self.text.push_str(entry.path);
+        let middle = start + entry.digest.len();
+        self.spans.push(EntrySpan {
+            line: entry.line,
+            malformed,
+            digest: start..middle,
+            path: middle..self.text.len(),
+        });
+    }
+
+    fn get(&self, span: &EntrySpan) -> EntryRef<'_> {
+        EntryRef {
+            digest: &self.text[span.digest.clone()],
+            path: &self.text[span.path.clone()],
+            line: span.line,
+        }
+    }
+}
+
+/// What a hashed file means for the [`StatCache`]
+struct CacheUpdate {
+    path: PathBuf,
+    /// Present when the file matched and its stamp can be recorded
+    stamp: Option<FileStamp>,
+    digest: String,
+}
+
+impl CacheUpdate {
+    /// Keep the stamp only if the file turned out to match
+    fn settle(mut self, status: &CheckStatus) -> Self {
+        if *status != CheckStatus::Ok {
+            self.stamp = None;
+        }
+        self
+    }
+
+    fn apply(self, cache: &mut StatCache, algorithm: Algorithm) {
+        match self.stamp {
+            Some(stamp) => cache.record(&self.path, stamp, algorithm, &self.digest),
+            None => cache.forget(&self.path),
+        }
+    }
+}
+
+/// Check one file unless `known` says it is unchanged since it matched
+fn check_cached(
+    digest: &str,
+    path: &str,
+    algorithm: Algorithm,
+    base: &Path,
+    known: &StatCache,
+) -> (CheckStatus, Option<CacheUpdate>) {
+    let full = base.join(path);
+    let expected = normalize_expected(digest).hex;
+    // Stat before hashing, so a change during hashing is not recorded
+    let stamp = FileStamp::of(&full).ok();
+    if let Some(stamp) = stamp {
+        if known.is_fresh(&full, stamp, algorithm, &expected) {
+            return (CheckStatus::Cached, None);
+        }
+    }
+    let status = check_file(digest, path, algorithm, base);
+    let update = CacheUpdate {
+        path: full,
+        stamp,
+        digest: expected,
+    };
+    (status, Some(update))
+}
+
+/// Run `check` over `entries` on up to `jobs` threads, passing the results
+/// to `report` in list order; once `cancel` is set no further entry is
+/// started
+pub(crate) fn in_order<E, T, C, R>(
+    entries: &[E],
+    jobs: usize,
+    cancel: Option<&AtomicBool>,
+    check: C,
+    mut report: R,
+) where
+    E: Sync,
+    T: Send,
+    C: Fn(&E) -> T + Sync,
+    R: FnMut(&E, T),
+{
+    let cancelled = || cancel.is_some_and(|cancel| cancel.load(Ordering::Relaxed));
+    let jobs = jobs.clamp(1, entries.len().max(1));
+    if jobs == 1 {
+        for entry in entries {
+            if cancelled() {
+                break;
+            }
+            report(entry, check(entry));
+        }
+        return;
+    }
+
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+    thread::scope(|scope| {
+        for _ in 0..jobs {
+            let sender = sender.clone();
+            let (next, check, cancelled) = (&next, &check, &cancelled);
+            scope.spawn(move || loop {
+                if cancelled() {
+                    break;
+                }
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(entry) = entries.get(index) else {
+                    break;
+                };
+                if sender.send((index, check(entry))).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+
+        let mut pending = BTreeMap::new();
+        let mut emitted = 0;
+        for (index, result) in receiver {
+            pending.insert(index, result);
+            while let Some(result) = pending.remove(&emitted) {
+                report(&entries[emitted], result);
+                emitted += 1;
+            }
+        }
+    });
+}
+
+fn check_file(digest: &str, path: &str, algorithm: Algorithm, base: &Path) -> CheckStatus {
+    match hash_file(base.join(path), algorithm) {
+        Ok(actual) if digests_match(digest, &actual) => CheckStatus::Ok,
+        Ok(_) => CheckStatus::Failed,
+        Err(HashError::Io(e)) if e.kind() == io::ErrorKind::NotFound => CheckStatus::Missing,
+        Err(e) => CheckStatus::Unreadable(e.to_string()),
+    }
+}
+
+/// Whether `source` looks like an HTTP(S) URL
+pub fn is_url(source: &str) -> bool {
+    source.starts_with("http://") || source.starts_with("https://")
+}
+
+/// Read the text of a checksum file from a local path or, with the `http`
+/// feature, from an HTTP(S) URL
+pub fn read_source(source: &str) -> Result<String> {
+    if is_url(source) {
+        fetch_url(source)
+    } else {
+        Ok(fs::read_to_string(source)?)
+    }
+}
+
+#[cfg(feature = "http")]
+fn fetch_url(url: &str) -> Result<String> {
+    ureq::get(url)
+        .call()
+        .map_err(|e| HashError::InvalidInput(format!("failed to fetch {}: {}", url, e)))?
+        .into_string()
+        .map_err(HashError::Io)
+}
+
+#[cfg(not(feature = "http"))]
+fn fetch_url(url: &str) -> Result<String> {
+    Err(HashError::InvalidInput(format!(
+        "cannot fetch {}: built without the `http` feature",
+        url
+    )))
+}
@@ -0,0 +1,442 @@
+//! Content-defined chunking and chunk indexes
+//!
+//! A [`Chunker`] cuts a stream where its content says so rather than every
+//! so many bytes, using the gear hash and normalized chunking of FastCDC.
+//! An insertion or deletion then changes only the chunks around it, and
+//! the chunks after it come out the same, which is what lets backup tools
+//! such as casync and borg store each chunk once across versions of a file.
+//!
+//! A [`ChunkIndex`] lists each chunk's digest, offset and length, in the
+//! spirit of a casync `.caibx` file: text, JSON or CSV to write out, and
+//! [`ChunkIndex::missing_from`] to find the chunks a store does not hold
+//! yet. The boundaries are this crate's own, so an index is not
+//! interchangeable with casync's or borg's.
+//!
+//! ```
+//! use hashing::chunk::Chunker;
+//!
+//! let data: Vec<u8> = (0..200_000u32)
+//!     .map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8)
+//!     .collect();
+//! let chunker = Chunker::new(4096)?;
+//! let index = chunker.index(&data[..])?;
+//! assert_eq!(index.total_len(), 200_000);
+//!
+//! // Prepending bytes leaves all but the first chunk as they were
+//! let edited = [&b"new header"[..], &data].concat();
+//! let new = chunker.index(&edited[..])?;
+//! assert!(new.missing_from(&index).len() <= 2);
+//! # Ok::<(), hashing::HashError>(())
+//! ```
+
+use crate::delta::read_full;
+use crate::{hash_bytes_raw, Algorithm, Digest, HashError, Result};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::fmt;
+use std::io::{Read, Write};
+use std::str::FromStr;
+
+/// Average chunk size casync uses unless told otherwise
+pub const DEFAULT_AVG_SIZE: u32 = 64 * 1024;
+
+/// Smallest average size accepted by [`Chunker::new`]
+const MIN_AVG_SIZE: u32 = 256;
+
+/// Largest average size accepted by [`Chunker::new`]
+const MAX_AVG_SIZE: u32 = 1 << 28;
+
+/// Random values for the gear hash, one per byte value
+const GEAR: [u64; 256] = gear_table();
+
+/// SplitMix64 from a fixed seed, so chunk boundaries never change
+const fn gear_table() -> [u64; 256] {
+    let mut table = [0u64; 256];
+    let mut state = 0x6a09_e667_f3bc_c908u64;
+    let mut i = 0;
+    while i < 256 {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+}
+
+/// Cuts streams into content-defined chunks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Chunker {
+    min_size: u32,
+    avg_size: u32,
+    max_size: u32,
+    algorithm: Algorithm,
+}
+
+impl Default for Chunker {
+    /// casync's sizes (16 KiB, 64 KiB, 256 KiB) with SHA-256 chunk digests
+    fn default() -> Self {
+        Self::new(DEFAULT_AVG_SIZE).expect("valid default size")
+    }
+}
+
+impl Chunker {
+    /// Chunks of `avg_size` bytes on average, between a quarter and four
+    /// times that, digested with SHA-256
+    ///
+    /// `avg_size` must be between 256 bytes and 256 MiB.
+    pub fn new(avg_size: u32) -> Result<Self> {
+        if !(MIN_AVG_SIZE..=MAX_AVG_SIZE).contains(&avg_size) {
+            return Err(HashError::InvalidInput(format!(
+                "the average chunk size must be between {} and {} bytes, not {}",
+                MIN_AVG_SIZE, MAX_AVG_SIZE, avg_size
+            )));
+        }
+        Ok(Self {
+            min_size: avg_size / 4,
+            avg_size,
+            max_size: avg_size * 4,
+            algorithm: Algorithm::Sha256,
+        })
+    }
+
+    /// Use other bounds around the average size
+    pub fn with_limits(mut self, min_size: u32, max_size: u32) -> Result<Self> {
+        if min_size == 0 || min_size > self.avg_size || max_size < self.avg_size {
+            return Err(HashError::InvalidInput(format!(
+                "chunk size limits must satisfy 0 < min <= {} <= max, not {} and {}",
+                self.avg_size, min_size, max_size
+            )));
+        }
+        self.min_size = min_size;
+        self.max_size = max_size;
+        Ok(self)
+    }
+
+    /// Digest chunks with `algorithm`
+    pub fn with_algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    pub fn min_size(&self) -> u32 {
+        self.min_size
+    }
+
+    pub fn avg_size(&self) -> u32 {
+        self.avg_size
+    }
+
+    pub fn max_size(&self) -> u32 {
+        self.max_size
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    /// The chunks of everything `reader` yields, with their data
+    ///
+    /// At most [`Chunker::max_size`] bytes of the stream are in memory at
+    /// a time, plus the chunk being returned.
+    pub fn chunks<R: Read>(&self, reader: R) -> Chunks<R> {
+        Chunks {
+            chunker: *self,
+            reader,
+            buffer: Vec::new(),
+            offset: 0,
+            eof: false,
+        }
+    }
+
+    /// Index of everything `reader` yields
+    pub fn index<R: Read>(&self, reader: R) -> Result<ChunkIndex> {
+        let chunks = self
+            .chunks(reader)
+            .map(|chunk| chunk.map(|(chunk, _)| chunk))
+            .collect::<Result<_>>()?;
+        Ok(ChunkIndex {
+            chunker: *self,
+            chunks,
+        })
+    }
+
+    /// Length of the chunk at the start of `data`, which holds at least
+    /// `max_size` bytes unless the stream ends sooner
+    fn cut(&self, data: &[u8]) -> usize {
+        let (min, avg, max) = (
+            self.min_size as usize,
+            self.avg_size as usize,
+            self.max_size as usize,
+        );
+        if data.len() <= min {
+            return data.len();
+        }
+        let end = data.len().min(max);
+        // Harder to cut before the average size, easier after it, which
+        // keeps chunk sizes close to it
+        let bits = 31 - self.avg_size.leading_zeros();
+        let strict = !0u64 << (64 - (bits + 2));
+        let loose = !0u64 << (64 - (bits - 2));
+        let mut hash = 0u64;
+        for (i, &byte) in data.iter().enumerate().take(end).skip(min) {
+            hash = (hash << 1).wrapping_add(GEAR[byte as usize]);
+            let mask = if i < avg { strict } else { loose };
+            if hash & mask == 0 {
+                return i + 1;
+            }
+        }
+        end
+    }
+}
+
+/// Iterator over the chunks of a stream, from [`Chunker::chunks`]
+pub struct Chunks<R> {
+    chunker: Chunker,
+    reader: R,
+    buffer: Vec<u8>,
+    offset: u64,
+    eof: bool,
+}
+
+impl<R: Read> Chunks<R> {
+    /// Read until a largest possible chunk is buffered or the stream ends
+    fn fill(&mut self) -> Result<()> {
+        let max = self.chunker.max_size as usize;
+        if self.eof || self.buffer.len() >= max {
+            return Ok(());
+        }
+        let filled = self.buffer.len();
+        self.buffer.resize(max, 0);
+        let count = read_full(&mut self.reader, &mut self.buffer[filled..])?;
+        self.buffer.truncate(filled + count);
+        self.eof = filled + count < max;
+        Ok(())
+    }
+}
+
+impl<R: Read> Iterator for Chunks<R> {
+    type Item = Result<(Chunk, Vec<u8>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Err(e) = self.fill() {
+            return Some(Err(e));
+        }
+        if self.buffer.is_empty() {
+            return None;
+        }
+        let len = self.chunker.cut(&self.buffer);
+        let data: Vec<u8> = self.buffer.drain(..len).collect();
+        let chunk = hash_bytes_raw(&data, self.chunker.algorithm).map(|digest| Chunk {
+            offset: self.offset,
+            len: len as u64,
+            digest,
+        });
+        self.offset += len as u64;
+        Some(chunk.map(|chunk| (chunk, data)))
+    }
+}
+
+/// One chunk of a stream
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    /// Offset of the chunk in the stream
+    pub offset: u64,
+    pub len: u64,
+    pub digest: Digest,
+}
+
+/// The chunks of a stream and how they were cut
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkIndex {
+    pub chunker: Chunker,
+    /// Chunks in stream order, each starting where the one before ends
+    pub chunks: Vec<Chunk>,
+}
+
+/// Ways to write a [`ChunkIndex`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChunkIndexFormat {
+    /// A `# chunk-index` header line, then `<digest> <offset> <length>`
+    /// lines, read back by [`ChunkIndex::parse`]
+    #[default]
+    Text,
+    /// One JSON object with the sizes, algorithm and chunk list
+    Json,
+    /// `digest,offset,length` rows under a header
+    Csv,
+}
+
+impl fmt::Display for ChunkIndexFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ChunkIndexFormat::Text => "text",
+            ChunkIndexFormat::Json => "json",
+            ChunkIndexFormat::Csv => "csv",
+        })
+    }
+}
+
+impl FromStr for ChunkIndexFormat {
+    type Err = HashError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" | "txt" => Ok(ChunkIndexFormat::Text),
+            "json" => Ok(ChunkIndexFormat::Json),
+            "csv" => Ok(ChunkIndexFormat::Csv),
+            _ => Err(HashError::InvalidInput(format!(
+                "unknown chunk index format: {} (expected text, json or csv)",
+                s
+            ))),
+        }
+    }
+}
+
+impl ChunkIndex {
+    /// Length of the indexed stream
+    pub fn total_len(&self) -> u64 {
+        self.chunks.iter().map(|chunk| chunk.len).sum()
+    }
+
+    /// Number of distinct chunks
+    pub fn unique_chunks(&self) -> usize {
+        self.unique().count()
+    }
+
+    /// Bytes left to store once repeated chunks are stored only once
+    pub fn unique_len(&self) -> u64 {
+        self.unique().map(|chunk| chunk.len).sum()
+    }
+
+    /// First occurrence of each distinct chunk, in stream order
+    fn unique(&self) -> impl Iterator<Item = &Chunk> {
+        let mut seen = HashSet::new();
+        self.chunks
+            .iter()
+            .filter(move |chunk| seen.insert(&chunk.digest))
+    }
+
+    /// Distinct chunks that `store` does not list, in stream order: what a
+    /// backup of this stream must upload to a store already holding `store`
+    ///
+    /// Chunks only match when both indexes were cut by equal [`Chunker`]s.
+    pub fn missing_from(&self, store: &ChunkIndex) -> Vec<&Chunk> {
+        let known: HashSet<&Digest> = store.chunks.iter().map(|chunk| &chunk.digest).collect();
+        self.unique()
+            .filter(|chunk| !known.contains(&chunk.digest))
+            .collect()
+    }
+
+    /// The index as one JSON object
+    pub fn to_json(&self) -> Value {
+        let chunks: Vec<Value> = self
+            .chunks
+            .iter()
+            .map(|chunk| {
+                json!({
+                    "digest": chunk.digest.to_hex(),
+                    "offset": chunk.offset,
+                    "length": chunk.len,
+                })
+            })
+            .collect();
+        json!({
+            "algorithm": self.chunker.algorithm.name(),
+            "min_size": self.chunker.min_size,
+            "avg_size": self.chunker.avg_size,
+            "max_size": self.chunker.max_size,
+            "size": self.total_len(),
+            "chunks": chunks,
+        })
+    }
+
+    /// The index in the text format read by [`ChunkIndex::parse`]
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "# chunk-index algorithm={} min={} avg={} max={}\n",
+            self.chunker.algorithm.name(),
+            self.chunker.min_size,
+            self.chunker.avg_size,
+            self.chunker.max_size
+        );
+        for chunk in &self.chunks {
+            text.push_str(&format!("{} {} {}\n", chunk.digest, chunk.offset, chunk.len));
+        }
+        text
+    }
+
+    /// Write the index in `format`
+    pub fn write_to<W: Write>(&self, mut writer: W, format: ChunkIndexFormat) -> Result<()> {
+        match format {
+            ChunkIndexFormat::Text => writer.write_all(self.to_text().as_bytes())?,
+            ChunkIndexFormat::Json => {
+                let json = serde_json::to_string_pretty(&self.to_json())
+                    .map_err(|e| HashError::ExportError(e.to_string()))?;
+                writeln!(writer, "{}", json)?;
+            }
+            ChunkIndexFormat::Csv => {
+                writeln!(writer, "digest,offset,length")?;
+                for chunk in &self.chunks {
+                    writeln!(writer, "{},{},{}", chunk.digest, chunk.offset, chunk.len)?;
+                }
+            }
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Parse an index written in [`ChunkIndexFormat::Text`]
+    ///
+    /// Each chunk must start where the one before it ends.
+    pub fn parse(text: &str) -> Result<Self> {
+        let invalid = |line: usize, why: &str| {
+            HashError::InvalidInput(format!("chunk index line {}: {}", line, why))
+        };
+        let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line.trim()));
+        let header = lines
+            .next()
+            .and_then(|(_, line)| line.strip_prefix("# chunk-index "))
+            .ok_or_else(|| invalid(1, "expected a # chunk-index header"))?;
+        let mut fields = header.split_whitespace().map(|field| field.split_once('='));
+        let mut field = |name: &str| match fields.next() {
+            Some(Some((key, value))) if key == name => Ok(value),
+            _ => Err(invalid(1, &format!("expected {}=", name))),
+        };
+        let algorithm: Algorithm = field("algorithm")?.parse()?;
+        let mut size = |name: &str| {
+            field(name)?
+                .parse::<u32>()
+                .map_err(|_| invalid(1, &format!("bad {} size", name)))
+        };
+        let (min, avg, max) = (size("min")?, size("avg")?, size("max")?);
+        let chunker = Chunker::new(avg)?
+            .with_limits(min, max)?
+            .with_algorithm(algorithm);
+
+        let mut chunks: Vec<Chunk> = Vec::new();
+        for (number, line) in lines.filter(|(_, line)| !line.is_empty()) {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            let [digest, offset, len] = parts[..] else {
+                return Err(invalid(number, "expected <digest> <offset> <length>"));
+            };
+            let digest = Digest::parse_for(digest, algorithm)?;
+            let offset: u64 = offset.parse().map_err(|_| invalid(number, "bad offset"))?;
+            let len: u64 = len.parse().map_err(|_| invalid(number, "bad length"))?;
+            let expected = chunks.last().map_or(0, |chunk| chunk.offset + chunk.len);
+            if offset != expected {
+                return Err(invalid(
+                    number,
+                    &format!("chunk starts at {} instead of {}", offset, expected),
+                ));
+            }
+            chunks.push(Chunk {
+                offset,
+                len,
+                digest,
+            });
+        }
+        Ok(Self { chunker, chunks })
+    }
+}
@@ -0,0 +1,471 @@
+//! Objects in cloud storage: S3, Google Cloud Storage and Azure Blob
+//!
+//! [`ObjectUrl`] parses `s3://bucket/key`, `gs://bucket/object` and
+//! `az://account/container/blob`. With the `cloud` feature,
+//! [`open_object`] streams an object over HTTPS so it can be hashed like
+//! a local file, and [`hash_object`] also checks it against the checksums
+//! the provider stores with it: the `ETag` and `x-amz-checksum-*` headers
+//! of S3, the `x-goog-hash` header of GCS and `Content-MD5` on Azure.
+//!
+//! The providers' REST APIs are called directly through the HTTP client of
+//! the `http` feature, with S3 requests signed by [`crate::aws`], instead
+//! of through three SDKs that each bring an async runtime. Credentials come
+//! from the environment variables the SDKs read first:
+//!
+//! | Provider | Variables |
+//! |----------|-----------|
+//! | S3 | `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY`, `AWS_SESSION_TOKEN`, `AWS_REGION` (or `AWS_DEFAULT_REGION`), `AWS_ENDPOINT_URL` for S3-compatible stores |
+//! | GCS | `GOOGLE_OAUTH_ACCESS_TOKEN`, e.g. from `gcloud auth print-access-token` |
+//! | Azure | `AZURE_STORAGE_SAS_TOKEN` |
+//!
+//! Without them, objects are requested anonymously, which works for public
+//! buckets.
+//!
+//! ```
+//! use hashing::cloud::{ObjectUrl, Provider};
+//!
+//! let url: ObjectUrl = "s3://releases/v1.2/app.tar.gz".parse()?;
+//! assert_eq!(url.provider, Provider::S3);
+//! assert_eq!(url.bucket, "releases");
+//! assert_eq!(url.key, "v1.2/app.tar.gz");
+//! # Ok::<(), hashing::HashError>(())
+//! ```
+
+use crate::{Algorithm, HashError, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::fmt;
+use std::str::FromStr;
+
+/// A cloud storage service
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Provider {
+    /// Amazon S3 and S3-compatible stores (`s3://`)
+    S3,
+    /// Google Cloud Storage (`gs://`)
+    Gcs,
+    /// Azure Blob Storage (`az://`)
+    Azure,
+}
+
+impl Provider {
+    /// URL scheme naming the provider
+    pub fn scheme(&self) -> &'static str {
+        match self {
+            Provider::S3 => "s3",
+            Provider::Gcs => "gs",
+            Provider::Azure => "az",
+        }
+    }
+}
+
+/// Location of one object
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectUrl {
+    /// Service holding the object
+    pub provider: Provider,
+    /// Storage account, for Azure only
+    pub account: Option<String>,
+    /// Bucket, or container on Azure
+    pub bucket: String,
+    /// Object key, or blob name on Azure
+    pub key: String,
+}
+
+impl ObjectUrl {
+    /// HTTPS URL of the object, without credentials
+    ///
+    /// S3 objects use virtual-hosted URLs in `region`, or path-style URLs
+    /// below `AWS_ENDPOINT_URL` when that is set.
+    pub fn https_url(&self) -> String {
+        match self.provider {
+            Provider::S3 => match std::env::var("AWS_ENDPOINT_URL") {
+                Ok(endpoint) => format!(
+                    "{}/{}/{}",
+                    endpoint.trim_end_matches('/'),
+                    self.bucket,
+                    crate::aws::uri_encode(&self.key, true)
+                ),
+                Err(_) => format!(
+                    "https://{}.s3.{}.amazonaws.com/{}",
+                    self.bucket,
+                    s3_region(),
+                    crate::aws::uri_encode(&self.key, true)
+                ),
+            },
+            Provider::Gcs => format!(
+                "https://storage.googleapis.com/{}/{}",
+                self.bucket,
+                crate::aws::uri_encode(&self.key, true)
+            ),
+            Provider::Azure => format!(
+                "https://{}.blob.core.windows.net/{}/{}",
+                self.account.as_deref().unwrap_or_default(),
+                self.bucket,
+                crate::aws::uri_encode(&self.key, true)
+            ),
+        }
+    }
+}
+
+impl FromStr for ObjectUrl {
+    type Err = HashError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || {
+            HashError::InvalidInput(format!(
+                "invalid object URL {}: expected s3://bucket/key, gs://bucket/object \
+                 or az://account/container/blob",
+                s
+            ))
+        };
+        let (scheme, rest) = s.split_once("://").ok_or_else(invalid)?;
+        let provider = match scheme {
+            "s3" => Provider::S3,
+            "gs" => Provider::Gcs,
+            "az" => Provider::Azure,
+            _ => return Err(invalid()),
+        };
+        let (account, rest) = match provider {
+            Provider::Azure => {
+                let (account, rest) = rest.split_once('/').ok_or_else(invalid)?;
+                (Some(account.to_string()), rest)
+            }
+            _ => (None, rest),
+        };
+        let (bucket, key) = rest.split_once('/').ok_or_else(invalid)?;
+        if bucket.is_empty() || key.is_empty() || account.as_deref() == Some("") {
+            return Err(invalid());
+        }
+        Ok(Self {
+            provider,
+            account,
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for ObjectUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}://", self.provider.scheme())?;
+        if let Some(ref account) = self.account {
+            write!(f, "{}/", account)?;
+        }
+        write!(f, "{}/{}", self.bucket, self.key)
+    }
+}
+
+/// Whether `input` looks like an object URL rather than a path or string
+///
+/// # Examples
+///
+/// ```
+/// use hashing::cloud::is_object_url;
+///
+/// assert!(is_object_url("gs://bucket/data.csv"));
+/// assert!(!is_object_url("https://example.com/data.csv"));
+/// ```
+pub fn is_object_url(input: &str) -> bool {
+    ["s3://", "gs://", "az://"]
+        .iter()
+        .any(|scheme| input.starts_with(scheme))
+}
+
+/// A checksum the provider stores with an object
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredChecksum {
+    /// Algorithm of the checksum
+    pub algorithm: Algorithm,
+    /// The checksum's bytes
+    pub digest: Vec<u8>,
+    /// Response header it was read from
+    pub header: &'static str,
+}
+
+/// Stored checksums of an object, from the response headers of a GET
+///
+/// `header` looks up every value of a header by lowercase name. Only
+/// checksums over the whole object, in an algorithm the crate supports,
+/// are returned: a multipart `ETag` or a composite `x-amz-checksum-*`
+/// (ending in `-N`) is not a digest of the object's bytes, and neither is
+/// the `ETag` of an S3 object encrypted with KMS or a customer key.
+///
+/// # Examples
+///
+/// ```
+/// use hashing::cloud::{stored_checksums, Provider};
+/// use hashing::Algorithm;
+///
+/// let headers = [("x-goog-hash", "crc32c=n03x6A==,md5=XUFAKrxLKna5cZ2REBfFkg==")];
+/// let header = |name: &str| {
+///     headers.iter().filter(|(n, _)| *n == name).map(|(_, v)| v.to_string()).collect()
+/// };
+/// let stored = stored_checksums(Provider::Gcs, header);
+/// assert_eq!(stored[0].algorithm, Algorithm::Md5);
+/// assert_eq!(hex::encode(&stored[0].digest), "5d41402abc4b2a76b9719d911017c592");
+/// ```
+pub fn stored_checksums<F>(provider: Provider, header: F) -> Vec<StoredChecksum>
+where
+    F: Fn(&str) -> Vec<String>,
+{
+    let base64 = |value: &str| STANDARD.decode(value.trim()).ok();
+    let mut stored = Vec::new();
+    let mut push = |algorithm: Algorithm, digest: Option<Vec<u8>>, header: &'static str| {
+        if let Some(digest) = digest.filter(|digest| digest.len() == algorithm.output_size()) {
+            stored.push(StoredChecksum {
+                algorithm,
+                digest,
+                header,
+            });
+        }
+    };
+
+    match provider {
+        Provider::S3 => {
+            let encrypted = header("x-amz-server-side-encryption")
+                .iter()
+                .any(|value| value.starts_with("aws:kms"))
+                || !header("x-amz-server-side-encryption-customer-algorithm").is_empty();
+            if !encrypted {
+                for etag in header("etag") {
+                    push(
+                        Algorithm::Md5,
+                        hex::decode(etag.trim_matches('"')).ok(),
+                        "ETag",
+                    );
+                }
+            }
+            for (name, algorithm) in [
+                ("x-amz-checksum-sha256", Algorithm::Sha256),
+                ("x-amz-checksum-sha1", Algorithm::Sha1),
+            ] {
+                for value in header(name) {
+                    if !value.contains('-') {
+                        push(algorithm, base64(&value), name);
+                    }
+                }
+            }
+        }
+        Provider::Gcs => {
+            for value in header("x-goog-hash") {
+                for part in value.split(',') {
+                    if let Some(md5) = part.trim().strip_prefix("md5=") {
+                        push(Algorithm::Md5, base64(md5), "x-goog-hash");
+                    }
+                }
+            }
+        }
+        Provider::Azure => {
+            for value in header("content-md5") {
+                push(Algorithm::Md5, base64(&value), "Content-MD5");
+            }
+        }
+    }
+    stored
+}
+
+/// Region of S3 requests: `AWS_REGION`, `AWS_DEFAULT_REGION` or `us-east-1`
+fn s3_region() -> String {
+    std::env::var("AWS_REGION")
+        .or_else(|_| std::env::var("AWS_DEFAULT_REGION"))
+        .unwrap_or_else(|_| "us-east-1".to_string())
+}
+
+#[cfg(feature = "cloud")]
+pub use remote::*;
+
+#[cfg(feature = "cloud")]
+mod remote {
+    use super::{s3_region, stored_checksums, ObjectUrl, Provider, StoredChecksum};
+    use crate::aws::{amz_date, authorization, CanonicalRequest, Credentials, UNSIGNED_PAYLOAD};
+    use crate::{Algorithm, Digest, HashError, MultiHasher, Result};
+    use std::io::{self, Read};
+    use std::time::SystemTime;
+
+    /// Version of the Azure Blob REST API requested
+    const AZURE_API_VERSION: &str = "2021-08-06";
+
+    /// An object being downloaded, readable like a file
+    pub struct RemoteObject {
+        reader: Box<dyn Read + Send + Sync>,
+        /// Length from `Content-Length`, if the server sent one
+        pub size: Option<u64>,
+        /// Checksums the provider stores with the object
+        pub checksums: Vec<StoredChecksum>,
+    }
+
+    impl Read for RemoteObject {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.reader.read(buf)
+        }
+    }
+
+    /// Start downloading the object at `url`, with credentials from the
+    /// environment
+    ///
+    /// A missing object is an I/O error of kind `NotFound`; other HTTP
+    /// errors are invalid input.
+    pub fn open_object(url: &ObjectUrl) -> Result<RemoteObject> {
+        let https = url.https_url();
+        let mut request = ureq::get(&https);
+        match url.provider {
+            Provider::S3 => {
+                let date = amz_date(SystemTime::now());
+                request = request
+                    .set("x-amz-checksum-mode", "ENABLED")
+                    .set("x-amz-content-sha256", UNSIGNED_PAYLOAD)
+                    .set("x-amz-date", &date);
+                if let Some(credentials) = Credentials::from_env() {
+                    if let Some(ref token) = credentials.session_token {
+                        request = request.set("x-amz-security-token", token);
+                    }
+                    let signature = sign_s3(&https, &date, &credentials);
+                    request = request.set("Authorization", &signature);
+                }
+            }
+            Provider::Gcs => {
+                if let Ok(token) = std::env::var("GOOGLE_OAUTH_ACCESS_TOKEN") {
+                    request = request.set("Authorization", &format!("Bearer {}", token));
+                }
+            }
+            Provider::Azure => {
+                request = request.set("x-ms-version", AZURE_API_VERSION);
+                if let Ok(sas) = std::env::var("AZURE_STORAGE_SAS_TOKEN") {
+                    let sas = sas.trim_start_matches('?').to_string();
+                    let with_sas = format!("{}?{}", https, sas);
+                    request = ureq::get(&with_sas).set("x-ms-version", AZURE_API_VERSION);
+                }
+            }
+        }
+
+        let response = request.call().map_err(|e| match e {
+            ureq::Error::Status(404, _) => HashError::Io(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{}: no such object", url),
+            )),
+            ureq::Error::Status(status, response) => HashError::InvalidInput(format!(
+                "{}: HTTP {} {}",
+                url,
+                status,
+                response.status_text()
+            )),
+            e => HashError::InvalidInput(format!("{}: {}", url, e)),
+        })?;
+        let header = |name: &str| {
+            response
+                .all(name)
+                .into_iter()
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        };
+        let checksums = stored_checksums(url.provider, header);
+        let size = response
+            .header("content-length")
+            .and_then(|length| length.parse().ok());
+        Ok(RemoteObject {
+            reader: response.into_reader(),
+            size,
+            checksums,
+        })
+    }
+
+    /// What [`hash_object`] found
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct ObjectHash {
+        /// Digest of the object's contents
+        pub digest: Digest,
+        /// Bytes read
+        pub size: u64,
+        /// Each checksum the provider stores and whether the contents
+        /// match it
+        pub stored: Vec<(StoredChecksum, bool)>,
+    }
+
+    impl ObjectHash {
+        /// Whether every stored checksum matches; `None` if the provider
+        /// stores none the crate can check
+        pub fn stored_ok(&self) -> Option<bool> {
+            if self.stored.is_empty() {
+                None
+            } else {
+                Some(self.stored.iter().all(|(_, matches)| *matches))
+            }
+        }
+    }
+
+    /// Hash the object at `url` with `algorithm`, checking it against the
+    /// provider's stored checksums in the same read
+    pub fn hash_object(url: &ObjectUrl, algorithm: Algorithm) -> Result<ObjectHash> {
+        let object = open_object(url)?;
+        let mut algorithms = vec![algorithm];
+        for stored in &object.checksums {
+            if !algorithms.contains(&stored.algorithm) {
+                algorithms.push(stored.algorithm);
+            }
+        }
+        let checksums = object.checksums.clone();
+        let mut counted = Counted {
+            inner: object,
+            count: 0,
+        };
+        let digests = MultiHasher::new(&algorithms).hash_reader(&mut counted)?;
+        let stored = checksums
+            .into_iter()
+            .map(|stored| {
+                let matches = digests
+                    .iter()
+                    .any(|d| d.algorithm() == stored.algorithm && d.as_bytes() == stored.digest);
+                (stored, matches)
+            })
+            .collect();
+        Ok(ObjectHash {
+            digest: digests
+                .into_iter()
+                .next()
+                .expect("one digest per algorithm"),
+            size: counted.count,
+            stored,
+        })
+    }
+
+    /// `Authorization` header of an S3 GET of `https`
+    fn sign_s3(https: &str, date: &str, credentials: &Credentials) -> String {
+        let rest = https.split_once("://").map_or(https, |(_, rest)| rest);
+        let (host, path) = match rest.find('/') {
+            Some(slash) => rest.split_at(slash),
+            None => (rest, "/"),
+        };
+        let mut headers = vec![
+            ("host", host),
+            ("x-amz-checksum-mode", "ENABLED"),
+            ("x-amz-content-sha256", UNSIGNED_PAYLOAD),
+            ("x-amz-date", date),
+        ];
+        if let Some(ref token) = credentials.session_token {
+            headers.push(("x-amz-security-token", token));
+        }
+        let request = CanonicalRequest {
+            method: "GET",
+            path,
+            query: "",
+            headers: &headers,
+            payload_hash: UNSIGNED_PAYLOAD,
+        };
+        authorization(&request, credentials, &s3_region(), "s3", date)
+    }
+
+    /// Reader counting the bytes passing through it
+    struct Counted<R> {
+        inner: R,
+        count: u64,
+    }
+
+    impl<R: Read> Read for Counted<R> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let count = self.inner.read(buf)?;
+            self.count += count as u64;
+            Ok(count)
+        }
+    }
+}
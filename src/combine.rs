@@ -0,0 +1,106 @@
+//! Combining several digests into one
+//!
+//! These helpers build aggregate fingerprints (of a file collection, a
+//! batch of records, ...) from digests that have already been computed.
+//! Digests are taken as raw bytes; decode hex digests with `hex::decode`
+//! first.
+
+use crate::builder::digest_bytes;
+use crate::{Algorithm, HashError, Result};
+
+/// Combine digests whose order matters by hashing their concatenation
+///
+/// Each digest is prefixed with its length as a big-endian `u64`, and the
+/// sequence with the number of digests, so different splits of the same
+/// bytes never collide. Reordering the inputs changes the result.
+///
+/// # Examples
+///
+/// ```
+/// use hashing::combine::combine_ordered;
+/// use hashing::Algorithm;
+///
+/// let a = [1u8; 32];
+/// let b = [2u8; 32];
+/// let ab = combine_ordered(&[a, b], Algorithm::Sha256).unwrap();
+/// let ba = combine_ordered(&[b, a], Algorithm::Sha256).unwrap();
+/// assert_ne!(ab, ba);
+/// ```
+pub fn combine_ordered<D: AsRef<[u8]>>(digests: &[D], algorithm: Algorithm) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    data.extend_from_slice(&(digests.len() as u64).to_be_bytes());
+    for digest in digests {
+        let digest = digest.as_ref();
+        data.extend_from_slice(&(digest.len() as u64).to_be_bytes());
+        data.extend_from_slice(digest);
+    }
+    digest_bytes(&data, algorithm)
+}
+
+/// Combine digests whose order does not matter by XOR-folding them
+///
+/// All digests must have the same length. XOR is cheap and incremental,
+/// but identical digests cancel out in pairs, so a collection containing a
+/// duplicate pair fingerprints the same as one without it. Use [`hash_set`]
+/// when duplicates must count.
+///
+/// # Examples
+///
+/// ```
+/// use hashing::combine::combine_unordered;
+///
+/// let a = [0b1100u8; 4];
+/// let b = [0b1010u8; 4];
+/// assert_eq!(combine_unordered(&[a, b]).unwrap(), vec![0b0110; 4]);
+/// assert_eq!(combine_unordered(&[b, a]).unwrap(), vec![0b0110; 4]);
+/// ```
+pub fn combine_unordered<D: AsRef<[u8]>>(digests: &[D]) -> Result<Vec<u8>> {
+    let (first, rest) = digests
+        .split_first()
+        .ok_or_else(|| HashError::InvalidInput("no digests to combine".to_string()))?;
+
+    let mut folded = first.as_ref().to_vec();
+    for digest in rest {
+        let digest = digest.as_ref();
+        if digest.len() != folded.len() {
+            return Err(HashError::InvalidInput(format!(
+                "cannot combine digests of different lengths ({} and {} bytes)",
+                folded.len(),
+                digest.len()
+            )));
+        }
+        for (acc, byte) in folded.iter_mut().zip(digest) {
+            *acc ^= byte;
+        }
+    }
+    Ok(folded)
+}
+
+/// Hash a collection whose order is not meaningful (a multiset)
+///
+/// Every item is hashed on its own, the item digests are sorted, and the
+/// sorted list is combined with [`combine_ordered`]. Any permutation of the
+/// items gives the same digest, while duplicates still count: `{a, a}` and
+/// `{a}` differ. The result is lowercase hex, like [`crate::hash_bytes`].
+///
+/// # Examples
+///
+/// ```
+/// use hashing::{hash_set, Algorithm};
+///
+/// let tags = hash_set(["rust", "cli", "hash"], Algorithm::Sha256).unwrap();
+/// let shuffled = hash_set(["hash", "rust", "cli"], Algorithm::Sha256).unwrap();
+/// assert_eq!(tags, shuffled);
+/// ```
+pub fn hash_set<I, T>(items: I, algorithm: Algorithm) -> Result<String>
+where
+    I: IntoIterator<Item = T>,
+    T: AsRef<[u8]>,
+{
+    let mut digests = items
+        .into_iter()
+        .map(|item| digest_bytes(item.as_ref(), algorithm))
+        .collect::<Result<Vec<_>>>()?;
+    digests.sort_unstable();
+    combine_ordered(&digests, algorithm).map(hex::encode)
+}
@@ -0,0 +1,151 @@
+//! Hash-based commit/reveal
+//!
+//! A commitment binds a party to a value without revealing it: publish the
+//! [`Commitment`] first, later reveal the [`Opening`] and anyone can check
+//! it with [`verify_commitment`]. The random nonce is what hides the value;
+//! without it a commitment to a guessable value (a vote, a bid, a dice
+//! roll) is broken by hashing every candidate, so nonces shorter than
+//! [`MIN_NONCE_LEN`] bytes are rejected.
+//!
+//! The committed bytes are `hashing-commitment-v1`, the nonce length as a
+//! 64-bit big-endian integer, the nonce and the value, so a nonce/value
+//! boundary cannot be shifted to open the commitment differently.
+//!
+//! ```
+//! use hashing::commitment::{commit, generate_nonce, verify_commitment};
+//! use hashing::Algorithm;
+//!
+//! let (commitment, opening) = commit(b"heads", generate_nonce()?, Algorithm::Sha256)?;
+//! println!("publish: {}", commitment);
+//!
+//! // ... later, after revealing `opening`
+//! assert!(verify_commitment(&commitment, &opening));
+//! # Ok::<(), hashing::HashError>(())
+//! ```
+
+use crate::builder::digest_bytes;
+use crate::{Algorithm, HashError, Result};
+use std::fmt;
+
+/// Shortest nonce accepted, in bytes (128 bits)
+pub const MIN_NONCE_LEN: usize = 16;
+
+/// Domain separation prefix of the committed bytes
+const DOMAIN: &[u8] = b"hashing-commitment-v1";
+
+/// A published commitment to a hidden value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Commitment {
+    algorithm: Algorithm,
+    digest: Vec<u8>,
+}
+
+impl Commitment {
+    /// Parse a hex commitment made with `algorithm`
+    pub fn from_hex(hex_digest: &str, algorithm: Algorithm) -> Result<Self> {
+        let digest = hex::decode(hex_digest.trim())
+            .map_err(|e| HashError::InvalidInput(format!("invalid commitment: {}", e)))?;
+        if digest.len() != algorithm.output_size() {
+            return Err(HashError::InvalidInput(format!(
+                "commitment is {} bytes, {} digests are {}",
+                digest.len(),
+                algorithm.name(),
+                algorithm.output_size()
+            )));
+        }
+        Ok(Self { algorithm, digest })
+    }
+
+    /// Algorithm the commitment was made with
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    /// Raw digest bytes
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.digest
+    }
+}
+
+impl fmt::Display for Commitment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&hex::encode(&self.digest))
+    }
+}
+
+/// The secret needed to open a commitment; keep it private until the reveal
+#[derive(Clone, PartialEq, Eq)]
+pub struct Opening {
+    /// The committed value
+    pub value: Vec<u8>,
+    /// The random nonce hiding the value
+    pub nonce: Vec<u8>,
+}
+
+impl fmt::Debug for Opening {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Printing the opening would reveal the commitment early
+        f.debug_struct("Opening")
+            .field("value_len", &self.value.len())
+            .field("nonce_len", &self.nonce.len())
+            .finish()
+    }
+}
+
+/// A fresh 32-byte nonce from the operating system's random source
+pub fn generate_nonce() -> Result<Vec<u8>> {
+    let mut nonce = vec![0u8; 32];
+    getrandom::getrandom(&mut nonce).map_err(|e| {
+        HashError::Io(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            e.to_string(),
+        ))
+    })?;
+    Ok(nonce)
+}
+
+/// Commit to `value` with a random nonce of at least [`MIN_NONCE_LEN`] bytes
+pub fn commit(
+    value: impl AsRef<[u8]>,
+    nonce: impl AsRef<[u8]>,
+    algorithm: Algorithm,
+) -> Result<(Commitment, Opening)> {
+    let opening = Opening {
+        value: value.as_ref().to_vec(),
+        nonce: nonce.as_ref().to_vec(),
+    };
+    if opening.nonce.len() < MIN_NONCE_LEN {
+        return Err(HashError::InvalidInput(format!(
+            "nonce must be at least {} random bytes, got {}",
+            MIN_NONCE_LEN,
+            opening.nonce.len()
+        )));
+    }
+    let commitment = Commitment {
+        algorithm,
+        digest: commitment_digest(&opening, algorithm)?,
+    };
+    Ok((commitment, opening))
+}
+
+/// Whether `opening` reveals the value committed to by `commitment`
+pub fn verify_commitment(commitment: &Commitment, opening: &Opening) -> bool {
+    match commitment_digest(opening, commitment.algorithm) {
+        Ok(digest) => constant_time_eq(&digest, &commitment.digest),
+        Err(_) => false,
+    }
+}
+
+fn commitment_digest(opening: &Opening, algorithm: Algorithm) -> Result<Vec<u8>> {
+    let mut data = Vec::with_capacity(DOMAIN.len() + 8 + opening.nonce.len() + opening.value.len());
+    data.extend_from_slice(DOMAIN);
+    data.extend_from_slice(&(opening.nonce.len() as u64).to_be_bytes());
+    data.extend_from_slice(&opening.nonce);
+    data.extend_from_slice(&opening.value);
+    digest_bytes(&data, algorithm)
+}
+
+/// Compare without an early exit, so timing does not leak the matching prefix
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
@@ -0,0 +1,108 @@
+//! The user configuration file
+//!
+//! The file is TOML and is looked up at `$HASH_CONFIG`, falling back to
+//! `$XDG_CONFIG_HOME/hash/config.toml` (`~/.config/hash/config.toml`), or
+//! `%APPDATA%\hash\config.toml` on Windows. A missing file at the default
+//! location is not an error.
+//!
+//! ```toml
+//! [aliases]
+//! fast = "blake3"
+//! legacy = "md5"
+//!
+//! [formats]
+//! evidence = "/opt/acme/bin/evidence-format"
+//!
+//! [trust.keys]
+//! usb = "/media/usb/baseline.pub"
+//! ```
+
+use crate::alias::AliasRegistry;
+use crate::{Algorithm, HashError, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Environment variable naming an explicit config file
+pub const CONFIG_ENV: &str = "HASH_CONFIG";
+
+/// Contents of the configuration file
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+pub struct Config {
+    /// User-defined algorithm aliases: alias name to algorithm name
+    #[serde(default)]
+    pub aliases: BTreeMap<String, String>,
+    /// Export-format plugins: format name to program (see [`crate::plugin`])
+    #[serde(default)]
+    pub formats: BTreeMap<String, PathBuf>,
+    /// Keys trusted to sign manifests (see [`crate::trust`])
+    #[serde(default)]
+    pub trust: TrustConfig,
+}
+
+/// The `[trust]` table of the config file
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+pub struct TrustConfig {
+    /// Public key files by name; the only keys `--check --signature`
+    /// accepts besides those given with `--trusted-key`
+    #[serde(default)]
+    pub keys: BTreeMap<String, PathBuf>,
+}
+
+impl Config {
+    /// Parse a config from TOML text
+    pub fn from_toml(text: &str) -> Result<Self> {
+        toml::from_str(text).map_err(|e| HashError::InvalidInput(e.to_string()))
+    }
+
+    /// Read and parse the config file at `path`
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        Self::from_toml(&text).map_err(|e| {
+            HashError::InvalidInput(format!("invalid config {}: {}", path.display(), e))
+        })
+    }
+
+    /// Load the config from `$HASH_CONFIG` or the default location
+    ///
+    /// A file named by `$HASH_CONFIG` must exist; a missing file at the
+    /// default location yields an empty config.
+    pub fn load_default() -> Result<Self> {
+        if let Some(path) = std::env::var_os(CONFIG_ENV) {
+            return Self::load(path);
+        }
+        match Self::default_path() {
+            Some(path) if path.is_file() => Self::load(path),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    /// Default location of the config file, if a home directory is known
+    pub fn default_path() -> Option<PathBuf> {
+        let base = if cfg!(windows) {
+            std::env::var_os("APPDATA").map(PathBuf::from)
+        } else {
+            std::env::var_os("XDG_CONFIG_HOME")
+                .map(PathBuf::from)
+                .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+        };
+        base.map(|base| base.join("hash").join("config.toml"))
+    }
+
+    /// Built-in algorithm names extended with the `[aliases]` table
+    pub fn alias_registry(&self) -> Result<AliasRegistry> {
+        let mut registry = AliasRegistry::new();
+        for (alias, target) in &self.aliases {
+            let algorithm = Algorithm::from_str(target).map_err(|_| {
+                HashError::InvalidInput(format!(
+                    "alias {} points to unknown algorithm {}",
+                    alias, target
+                ))
+            })?;
+            registry.insert(alias, algorithm)?;
+        }
+        Ok(registry)
+    }
+}
@@ -0,0 +1,317 @@
+//! Re-encoding digests between textual forms
+//!
+//! The same digest turns up as bare hex or base64, prefixed with its
+//! algorithm (`sha256:<hex>`, as in OCI and Docker), in SRI form
+//! (`sha256-<base64>`), in Nix's base32, as a multihash (`1220...`, or
+//! base58 `Qm...` as in IPFS) or as an `ni:` URI. [`parse_digest`]
+//! recognizes all of them and checks the length against the algorithm;
+//! [`format_digest`] writes any of them.
+//!
+//! ```
+//! use hashing::convert::{format_digest, parse_digest, DigestFormat};
+//!
+//! let (algorithm, digest) =
+//!     parse_digest("sha256-47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=", None)?;
+//! assert_eq!(
+//!     format_digest(algorithm, &digest, DigestFormat::Prefixed)?,
+//!     "sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+//! );
+//! # Ok::<(), hashing::HashError>(())
+//! ```
+
+use crate::naming::{ni_uri, parse_ni_uri};
+use crate::nix::{self, decode_nix32, encode_nix32, NixHash};
+use crate::{Algorithm, HashError, Result};
+use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD};
+use base64::Engine;
+use std::fmt;
+use std::str::FromStr;
+
+/// A textual form of a digest
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigestFormat {
+    /// Lowercase hex
+    Hex,
+    /// Padded standard base64
+    Base64,
+    /// Unpadded URL-safe base64
+    Base64Url,
+    /// Nix's base32
+    Nix32,
+    /// `<algorithm>-<base64>` (Subresource Integrity, Nix)
+    Sri,
+    /// `<algorithm>:<hex>` (OCI, Docker, cosign)
+    Prefixed,
+    /// Hex multihash: varint code, varint length, digest
+    Multihash,
+    /// RFC 6920 `ni:///<algorithm>;<base64url>` URI
+    Ni,
+}
+
+impl fmt::Display for DigestFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DigestFormat::Hex => "hex",
+            DigestFormat::Base64 => "base64",
+            DigestFormat::Base64Url => "base64url",
+            DigestFormat::Nix32 => "nix32",
+            DigestFormat::Sri => "sri",
+            DigestFormat::Prefixed => "prefixed",
+            DigestFormat::Multihash => "multihash",
+            DigestFormat::Ni => "ni",
+        })
+    }
+}
+
+impl FromStr for DigestFormat {
+    type Err = HashError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "hex" | "base16" => Ok(DigestFormat::Hex),
+            "base64" => Ok(DigestFormat::Base64),
+            "base64url" => Ok(DigestFormat::Base64Url),
+            "nix32" | "base32" => Ok(DigestFormat::Nix32),
+            "sri" => Ok(DigestFormat::Sri),
+            "prefixed" => Ok(DigestFormat::Prefixed),
+            "multihash" => Ok(DigestFormat::Multihash),
+            "ni" => Ok(DigestFormat::Ni),
+            _ => Err(HashError::InvalidInput(format!(
+                "unknown digest format: {} (expected hex, base64, base64url, nix32, sri, \
+                 prefixed, multihash or ni)",
+                s
+            ))),
+        }
+    }
+}
+
+/// Multicodec code of `algorithm` in multihashes, if it has one
+pub fn multihash_code(algorithm: Algorithm) -> Option<u64> {
+    match algorithm {
+        Algorithm::Md5 => Some(0xd5),
+        Algorithm::Sha1 => Some(0x11),
+        Algorithm::Sha224 => Some(0x1013),
+        Algorithm::Sha256 => Some(0x12),
+        Algorithm::Sha384 => Some(0x20),
+        Algorithm::Sha512 => Some(0x13),
+        Algorithm::Sha512_224 => Some(0x1014),
+        Algorithm::Sha512_256 => Some(0x1015),
+        Algorithm::Sha3_224 => Some(0x17),
+        Algorithm::Sha3_256 => Some(0x16),
+        Algorithm::Sha3_384 => Some(0x15),
+        Algorithm::Sha3_512 => Some(0x14),
+        Algorithm::Shake128 => Some(0x18),
+        Algorithm::Shake256 => Some(0x19),
+        Algorithm::Blake2b512 => Some(0xb240),
+        Algorithm::Blake2s256 => Some(0xb260),
+        Algorithm::Blake3 => Some(0x1e),
+        Algorithm::Keccak224 => Some(0x1a),
+        Algorithm::Keccak256 => Some(0x1b),
+        Algorithm::Keccak384 => Some(0x1c),
+        Algorithm::Keccak512 => Some(0x1d),
+        Algorithm::Sha256d => Some(0x56),
+        Algorithm::Hash160 => None,
+        Algorithm::Xxh32 => Some(0xb3e1),
+        Algorithm::Xxh64 => Some(0xb3e2),
+        Algorithm::Xxh3 => Some(0xb3e3),
+        Algorithm::Xxh128 => Some(0xb3e4),
+        Algorithm::Crc32 => Some(0x0132),
+        Algorithm::Crc32c => None,
+    }
+}
+
+/// Parse a digest in any of the [`DigestFormat`]s
+///
+/// Bare hex, base64 and nix32 digests need `algorithm`, which tells them
+/// apart by length. A bare digest that does not fit is tried as a
+/// multihash, in hex or base58 (`Qm...`) or with a multibase prefix
+/// (`z`, `f`, `m`, `u`). When the text names an algorithm and `algorithm`
+/// is given as well, the two must agree.
+pub fn parse_digest(text: &str, algorithm: Option<Algorithm>) -> Result<(Algorithm, Vec<u8>)> {
+    let text = text.trim();
+    let bare = algorithm.and_then(|algorithm| {
+        let digest = decode_bare(text, algorithm).ok()?;
+        Some((algorithm, digest))
+    });
+    let (named, digest) = if text.starts_with("ni:") {
+        parse_ni_uri(text)?
+    } else if let Some((name, rest)) = text.split_once(':') {
+        let named: Algorithm = name.parse()?;
+        (named, decode_bare(rest, named)?)
+    } else if let Some(hash) = parse_sri(text)? {
+        (hash.algorithm, hash.digest)
+    } else if let Some(bare) = bare {
+        return Ok(bare);
+    } else if let Some(multihash) = parse_multihash(text) {
+        multihash
+    } else {
+        return Err(match algorithm {
+            Some(algorithm) => wrong_length(text, algorithm),
+            None => HashError::InvalidInput(format!("{} does not say which algorithm it is", text)),
+        });
+    };
+
+    if let Some(given) = algorithm.filter(|&given| given != named) {
+        return Err(HashError::InvalidInput(format!(
+            "{} is a {} digest, not {}",
+            text,
+            named.name(),
+            given.name()
+        )));
+    }
+    check_len(named, digest.len())?;
+    Ok((named, digest))
+}
+
+/// Write a digest in `format`
+///
+/// Fails if the digest length does not match `algorithm`, or if the
+/// format has no name for the algorithm.
+pub fn format_digest(algorithm: Algorithm, digest: &[u8], format: DigestFormat) -> Result<String> {
+    check_len(algorithm, digest.len())?;
+    Ok(match format {
+        DigestFormat::Hex => hex::encode(digest),
+        DigestFormat::Base64 => STANDARD.encode(digest),
+        DigestFormat::Base64Url => URL_SAFE_NO_PAD.encode(digest),
+        DigestFormat::Nix32 => encode_nix32(digest),
+        DigestFormat::Sri => NixHash::new(algorithm, digest.to_vec())?.to_sri(),
+        DigestFormat::Prefixed => format!("{}:{}", algorithm.name(), hex::encode(digest)),
+        DigestFormat::Multihash => hex::encode(multihash(algorithm, digest)?),
+        DigestFormat::Ni => ni_uri(algorithm, digest)?,
+    })
+}
+
+/// Parse `text` and write it in `format`
+pub fn convert_digest(
+    text: &str,
+    algorithm: Option<Algorithm>,
+    format: DigestFormat,
+) -> Result<String> {
+    let (algorithm, digest) = parse_digest(text, algorithm)?;
+    format_digest(algorithm, &digest, format)
+}
+
+/// The binary multihash of a digest
+pub fn multihash(algorithm: Algorithm, digest: &[u8]) -> Result<Vec<u8>> {
+    let code = multihash_code(algorithm).ok_or_else(|| {
+        HashError::UnsupportedAlgorithm(format!("{} has no multihash code", algorithm.name()))
+    })?;
+    let mut out = Vec::with_capacity(digest.len() + 4);
+    write_varint(&mut out, code);
+    write_varint(&mut out, digest.len() as u64);
+    out.extend_from_slice(digest);
+    Ok(out)
+}
+
+/// Decode a binary multihash
+pub fn decode_multihash(bytes: &[u8]) -> Result<(Algorithm, Vec<u8>)> {
+    let invalid = |reason: &str| HashError::InvalidInput(format!("invalid multihash: {}", reason));
+    let (code, rest) = read_varint(bytes).ok_or_else(|| invalid("truncated code"))?;
+    let (len, digest) = read_varint(rest).ok_or_else(|| invalid("truncated length"))?;
+    if digest.len() as u64 != len {
+        return Err(invalid("length does not match digest"));
+    }
+    let algorithm = Algorithm::all()
+        .into_iter()
+        .find(|&algorithm| multihash_code(algorithm) == Some(code))
+        .ok_or_else(|| {
+            HashError::UnsupportedAlgorithm(format!("unsupported multihash code: {:#x}", code))
+        })?;
+    Ok((algorithm, digest.to_vec()))
+}
+
+/// Hex, nix32 or base64 (either alphabet, padded or not), told apart by
+/// length
+fn decode_bare(text: &str, algorithm: Algorithm) -> Result<Vec<u8>> {
+    let size = algorithm.output_size();
+    let decoded = if text.len() == size * 2 {
+        hex::decode(text).ok()
+    } else if text.len() == nix::nix32_len(size) {
+        decode_nix32(text).ok()
+    } else if text.len() == (size + 2) / 3 * 4 || text.len() == (size * 8 + 5) / 6 {
+        [STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD]
+            .iter()
+            .find_map(|engine| engine.decode(text).ok())
+    } else {
+        return Err(wrong_length(text, algorithm));
+    };
+    decoded.ok_or_else(|| {
+        HashError::InvalidInput(format!(
+            "{} is not a valid {} digest",
+            text,
+            algorithm.name()
+        ))
+    })
+}
+
+/// An SRI hash, or `None` if `text` does not start with an SRI algorithm
+fn parse_sri(text: &str) -> Result<Option<NixHash>> {
+    match text.split_once('-') {
+        Some((name, _)) if nix::parse_algorithm(name).is_ok() => {
+            NixHash::parse(text, None).map(Some)
+        }
+        _ => Ok(None),
+    }
+}
+
+/// A multihash in hex, base58 or multibase, or `None` if `text` is none
+/// of these
+fn parse_multihash(text: &str) -> Option<(Algorithm, Vec<u8>)> {
+    let (prefix, rest) = text.split_at(text.chars().next().map_or(0, char::len_utf8));
+    let multibase = match prefix {
+        "z" => bs58::decode(rest).into_vec().ok(),
+        "f" => hex::decode(rest).ok(),
+        "m" => STANDARD_NO_PAD.decode(rest).ok(),
+        "M" => STANDARD.decode(rest).ok(),
+        "u" => URL_SAFE_NO_PAD.decode(rest).ok(),
+        _ => None,
+    };
+    [
+        hex::decode(text).ok(),
+        bs58::decode(text).into_vec().ok(),
+        multibase,
+    ]
+    .into_iter()
+    .flatten()
+    .filter_map(|bytes| decode_multihash(&bytes).ok())
+    .find(|(algorithm, digest)| digest.len() == algorithm.output_size())
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(bytes: &[u8]) -> Option<(u64, &[u8])> {
+    let mut value = 0u64;
+    for (i, &byte) in bytes.iter().enumerate().take(9) {
+        value |= u64::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Some((value, &bytes[i + 1..]));
+        }
+    }
+    None
+}
+
+fn wrong_length(text: &str, algorithm: Algorithm) -> HashError {
+    HashError::InvalidInput(format!(
+        "{} has the wrong length for a {} digest",
+        text,
+        algorithm.name()
+    ))
+}
+
+fn check_len(algorithm: Algorithm, len: usize) -> Result<()> {
+    if len != algorithm.output_size() {
+        return Err(HashError::InvalidInput(format!(
+            "{} digests are {} bytes, got {}",
+            algorithm.name(),
+            algorithm.output_size(),
+            len
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,635 @@
+//! rsync-style block signatures and deltas
+//!
+//! A [`Signature`] lists a weak rolling checksum and a strong BLAKE2b hash
+//! for each fixed-size block of a file, in the format of librsync's
+//! `rdiff signature`. Whoever holds a new version of the file can then find
+//! which of its bytes the old version already has, without seeing it:
+//! [`Signature::delta`] slides a window over the new data, finds blocks by
+//! their weak sum and confirms them with the strong one, and
+//! [`Signature::changed_blocks`] compares two signatures block by block for
+//! files edited in place, such as disk images.
+//!
+//! Turned around, a signature published next to a download lets a client
+//! work out what it must fetch, as zsync does: [`Signature::seed_plan`]
+//! looks for the published file's blocks in an older local copy, the seed,
+//! and [`SeedPlan::fetch_ranges`] lists the byte ranges it lacks.
+//!
+//! ```
+//! use hashing::delta::{DeltaOp, Signer};
+//!
+//! let old = b"The quick brown fox jumps over the lazy dog";
+//! let new = b"The quick brown cat jumps over the lazy dog";
+//! let signature = Signer::new(8)?.signature(&old[..])?;
+//! let delta = signature.delta(&new[..])?;
+//! assert_eq!(delta[0], DeltaOp::Copy { offset: 0, len: 16 });
+//! assert_eq!(delta[1], DeltaOp::Literal { offset: 16, len: 8 });
+//!
+//! let changed = signature.changed_blocks(&Signer::new(8)?.signature(&new[..])?)?;
+//! assert_eq!(changed, [2]);
+//! # Ok::<(), hashing::HashError>(())
+//! ```
+
+use crate::{HashError, Result};
+use blake2::digest::consts::U32;
+use blake2::{Blake2b, Digest};
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::ops::Range;
+
+/// Block length `rdiff` uses unless told otherwise
+pub const DEFAULT_BLOCK_LEN: u32 = 2048;
+
+/// Longest strong sum, and the length used unless told otherwise
+pub const MAX_STRONG_LEN: u32 = 32;
+
+/// Added to every byte by librsync's rollsum
+const CHAR_OFFSET: u16 = 31;
+
+/// Multiplier of librsync's Rabin-Karp sum, its inverse and `MULT - 1`
+const RK_MULT: u32 = 0x0810_4225;
+const RK_INVERSE: u32 = 0x98f0_09ad;
+const RK_ADJUST: u32 = 0x0810_4224;
+
+/// Signature formats, named after librsync's magic numbers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SignatureFormat {
+    /// Rabin-Karp weak sums with BLAKE2b, `rdiff`'s default since librsync 2.2
+    #[default]
+    RabinKarpBlake2,
+    /// The original Adler-style rollsum with BLAKE2b (librsync 1.0 to 2.1)
+    Blake2,
+}
+
+impl SignatureFormat {
+    /// Magic number at the start of a signature file
+    pub fn magic(self) -> u32 {
+        match self {
+            SignatureFormat::RabinKarpBlake2 => 0x7273_0147,
+            SignatureFormat::Blake2 => 0x7273_0137,
+        }
+    }
+
+    /// Format with `magic`; MD4 signatures are not supported
+    pub fn from_magic(magic: u32) -> Result<Self> {
+        match magic {
+            0x7273_0147 => Ok(SignatureFormat::RabinKarpBlake2),
+            0x7273_0137 => Ok(SignatureFormat::Blake2),
+            0x7273_0136 | 0x7273_0146 => Err(HashError::InvalidInput(
+                "MD4 signatures are not supported; regenerate with BLAKE2".to_string(),
+            )),
+            magic => Err(HashError::InvalidInput(format!(
+                "not an rdiff signature (magic {:#010x})",
+                magic
+            ))),
+        }
+    }
+}
+
+/// Computes [`Signature`]s with a given block length and format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Signer {
+    format: SignatureFormat,
+    block_len: u32,
+    strong_len: u32,
+}
+
+impl Signer {
+    /// Signatures of `block_len`-byte blocks, in the default format with
+    /// full-length strong sums
+    ///
+    /// Smaller blocks find more of an edited file again, at the price of
+    /// a larger signature; `rdiff` uses the square root of the file size,
+    /// and [`DEFAULT_BLOCK_LEN`] suits files of a few megabytes.
+    pub fn new(block_len: u32) -> Result<Self> {
+        if block_len == 0 {
+            return Err(HashError::InvalidInput(
+                "the block length must be at least 1".to_string(),
+            ));
+        }
+        Ok(Self {
+            format: SignatureFormat::default(),
+            block_len,
+            strong_len: MAX_STRONG_LEN,
+        })
+    }
+
+    /// Use `format` for the weak sums and the magic number
+    pub fn with_format(mut self, format: SignatureFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Keep the first `bytes` of each strong sum (1 to [`MAX_STRONG_LEN`])
+    ///
+    /// Shorter sums make smaller signatures and a match by accident more
+    /// likely; 8 bytes are plenty for files below a few gigabytes.
+    pub fn with_strong_len(mut self, bytes: u32) -> Result<Self> {
+        if !(1..=MAX_STRONG_LEN).contains(&bytes) {
+            return Err(HashError::InvalidInput(format!(
+                "the strong sum length must be between 1 and {}, not {}",
+                MAX_STRONG_LEN, bytes
+            )));
+        }
+        self.strong_len = bytes;
+        Ok(self)
+    }
+
+    /// Signature of everything `reader` yields
+    pub fn signature<R: Read>(&self, mut reader: R) -> Result<Signature> {
+        let mut signature = Signature {
+            format: self.format,
+            block_len: self.block_len,
+            strong_len: self.strong_len,
+            blocks: Vec::new(),
+        };
+        let mut block = vec![0u8; self.block_len as usize];
+        loop {
+            let count = read_full(&mut reader, &mut block)?;
+            if count == 0 {
+                break;
+            }
+            let data = &block[..count];
+            signature.blocks.push(BlockSum {
+                weak: WeakSum::of(self.format, data).digest(),
+                strong: signature.strong_sum(data),
+            });
+            if count < block.len() {
+                break;
+            }
+        }
+        Ok(signature)
+    }
+}
+
+/// Checksums of one block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockSum {
+    /// Rolling checksum, cheap to slide along the data a byte at a time
+    pub weak: u32,
+    /// Leading bytes of the block's BLAKE2b-256 hash
+    pub strong: Vec<u8>,
+}
+
+/// Block checksums of a file, as written by `rdiff signature`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    /// Format of the weak sums
+    pub format: SignatureFormat,
+    /// Length of every block but the last, which may be shorter
+    pub block_len: u32,
+    /// Length of each strong sum in bytes
+    pub strong_len: u32,
+    /// Checksums of each block, in file order
+    pub blocks: Vec<BlockSum>,
+}
+
+/// One step of rebuilding a new file from an old one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeltaOp {
+    /// `len` bytes the old file has at `offset`
+    Copy { offset: u64, len: u64 },
+    /// `len` bytes only the new file has, at `offset` in the new file
+    Literal { offset: u64, len: u64 },
+}
+
+/// Which blocks of a target file a local seed already holds
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeedPlan {
+    block_len: u32,
+    target_len: u64,
+    /// Offset in the seed of each block of the target, if the seed has it
+    pub sources: Vec<Option<u64>>,
+}
+
+impl SeedPlan {
+    /// Length of the target file
+    pub fn target_len(&self) -> u64 {
+        self.target_len
+    }
+
+    /// Byte ranges of the target the seed lacks, merged where adjacent,
+    /// e.g. for HTTP range requests
+    pub fn fetch_ranges(&self) -> Vec<Range<u64>> {
+        let block_len = u64::from(self.block_len);
+        let mut ranges: Vec<Range<u64>> = Vec::new();
+        for (i, _) in self.sources.iter().enumerate().filter(|(_, source)| source.is_none()) {
+            let start = i as u64 * block_len;
+            let end = (start + block_len).min(self.target_len);
+            match ranges.last_mut() {
+                Some(last) if last.end == start => last.end = end,
+                _ => ranges.push(start..end),
+            }
+        }
+        ranges
+    }
+
+    /// Bytes of the target the seed holds
+    pub fn reused_bytes(&self) -> u64 {
+        let fetched: u64 = self.fetch_ranges().iter().map(|range| range.end - range.start).sum();
+        self.target_len - fetched
+    }
+
+    /// Whether the seed holds the whole target
+    pub fn is_complete(&self) -> bool {
+        self.sources.iter().all(Option::is_some)
+    }
+}
+
+impl Signature {
+    /// Parse a signature written by `rdiff signature` or
+    /// [`Signature::write_to`]
+    pub fn read_from<R: Read>(mut reader: R) -> Result<Self> {
+        let mut header = [0u8; 12];
+        reader.read_exact(&mut header).map_err(|e| match e.kind() {
+            io::ErrorKind::UnexpectedEof => {
+                HashError::InvalidInput("signature header is truncated".to_string())
+            }
+            _ => HashError::Io(e),
+        })?;
+        let word = |i: usize| u32::from_be_bytes(header[i..i + 4].try_into().unwrap());
+        let format = SignatureFormat::from_magic(word(0))?;
+        let signer = Signer::new(word(4))?
+            .with_format(format)
+            .with_strong_len(word(8))?;
+
+        let mut blocks = Vec::new();
+        let mut entry = vec![0u8; 4 + signer.strong_len as usize];
+        loop {
+            match read_full(&mut reader, &mut entry)? {
+                0 => break,
+                count if count < entry.len() => {
+                    return Err(HashError::InvalidInput(format!(
+                        "signature is truncated in block {}",
+                        blocks.len()
+                    )));
+                }
+                _ => blocks.push(BlockSum {
+                    weak: u32::from_be_bytes(entry[..4].try_into().unwrap()),
+                    strong: entry[4..].to_vec(),
+                }),
+            }
+        }
+        Ok(Self {
+            format,
+            block_len: signer.block_len,
+            strong_len: signer.strong_len,
+            blocks,
+        })
+    }
+
+    /// Write the signature in `rdiff`'s format
+    pub fn write_to<W: Write>(&self, mut writer: W) -> Result<()> {
+        writer.write_all(&self.format.magic().to_be_bytes())?;
+        writer.write_all(&self.block_len.to_be_bytes())?;
+        writer.write_all(&self.strong_len.to_be_bytes())?;
+        for block in &self.blocks {
+            writer.write_all(&block.weak.to_be_bytes())?;
+            writer.write_all(&block.strong)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// The signature in `rdiff`'s format
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(12 + self.blocks.len() * (4 + self.strong_len as usize));
+        // Writing to a Vec cannot fail
+        self.write_to(&mut bytes).expect("signature to memory");
+        bytes
+    }
+
+    /// Indices of the blocks that differ between the file signed here and
+    /// the one signed by `other`, which only one of them has included
+    ///
+    /// Both signatures need the same format, block length and strong sum
+    /// length. A block that moved counts as changed; use
+    /// [`Signature::delta`] to find data at other offsets.
+    pub fn changed_blocks(&self, other: &Signature) -> Result<Vec<u64>> {
+        if (self.format, self.block_len, self.strong_len)
+            != (other.format, other.block_len, other.strong_len)
+        {
+            return Err(HashError::InvalidInput(format!(
+                "signatures differ in format or block size ({:?}, {}, {} vs {:?}, {}, {})",
+                self.format,
+                self.block_len,
+                self.strong_len,
+                other.format,
+                other.block_len,
+                other.strong_len
+            )));
+        }
+        let count = self.blocks.len().max(other.blocks.len());
+        Ok((0..count)
+            .filter(|&i| self.blocks.get(i) != other.blocks.get(i))
+            .map(|i| i as u64)
+            .collect())
+    }
+
+    /// How to rebuild what `reader` yields from the file signed here:
+    /// ranges of the old file to copy and ranges of the new data to send
+    ///
+    /// Adjacent copies and literals are merged, so the operations alternate
+    /// more or less; their lengths add up to the new data's length. The new
+    /// data is read once, keeping about two blocks of it in memory.
+    pub fn delta<R: Read>(&self, reader: R) -> Result<Vec<DeltaOp>> {
+        let mut index: HashMap<u32, Vec<usize>> = HashMap::new();
+        for (i, block) in self.blocks.iter().enumerate() {
+            index.entry(block.weak).or_default().push(i);
+        }
+        let mut window = Window::new(reader, self.block_len as usize);
+        let mut ops = Ops::default();
+        // Block that would continue the last copy, tried first among equals
+        let mut next_block = 0;
+        let mut sum: Option<WeakSum> = None;
+
+        while window.fill()? > 0 {
+            let data = window.data();
+            let weak = sum.get_or_insert_with(|| WeakSum::of(self.format, data));
+            let found = index.get(&weak.digest()).and_then(|candidates| {
+                let strong = self.strong_sum(data);
+                let preferred = candidates.iter().find(|&&i| i == next_block);
+                preferred
+                    .into_iter()
+                    .chain(candidates)
+                    .copied()
+                    .find(|&i| self.blocks[i].strong == strong)
+            });
+            if let Some(block) = found {
+                let len = data.len() as u64;
+                ops.copy(block as u64 * u64::from(self.block_len), len);
+                next_block = block + 1;
+                window.advance(data.len());
+                sum = None;
+                continue;
+            }
+            // Slide one byte; at the end of the data the window shrinks
+            // until it matches a short last block or runs out
+            let out = data[0];
+            ops.literal(window.offset(), 1);
+            match window.slide()? {
+                Some(byte_in) => weak.rotate(out, byte_in),
+                None => weak.roll_out(out),
+            }
+        }
+        Ok(ops.0)
+    }
+
+    /// Where `seed` holds the blocks of the `target_len`-byte file signed
+    /// here, found at any offset
+    ///
+    /// Blocks with the same content all come from the first place the seed
+    /// has it. The seed is read once, and only until every block is found;
+    /// a short last block is only found at the very end of the seed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hashing::delta::Signer;
+    ///
+    /// let target = b"The quick brown fox jumps over the lazy dog";
+    /// let seed = b"A quick brown fox jumps over the lazy cat";
+    /// let signature = Signer::new(8)?.signature(&target[..])?;
+    /// let plan = signature.seed_plan(&seed[..], target.len() as u64)?;
+    /// assert_eq!(plan.sources[1], Some(6)); // "k brown " is at 6 in the seed
+    /// assert_eq!(plan.fetch_ranges(), [0..8, 40..43]);
+    /// # Ok::<(), hashing::HashError>(())
+    /// ```
+    pub fn seed_plan<R: Read>(&self, seed: R, target_len: u64) -> Result<SeedPlan> {
+        let block_len = u64::from(self.block_len);
+        let expected = (target_len + block_len - 1) / block_len;
+        if expected != self.blocks.len() as u64 {
+            return Err(HashError::InvalidInput(format!(
+                "a {}-byte file has {} blocks of {} bytes, but the signature lists {}",
+                target_len,
+                expected,
+                block_len,
+                self.blocks.len()
+            )));
+        }
+        let mut index: HashMap<u32, Vec<usize>> = HashMap::new();
+        for (i, block) in self.blocks.iter().enumerate() {
+            index.entry(block.weak).or_default().push(i);
+        }
+        let mut sources = vec![None; self.blocks.len()];
+        let mut missing = sources.len();
+        let mut window = Window::new(seed, self.block_len as usize);
+        let mut sum: Option<WeakSum> = None;
+
+        while missing > 0 && window.fill()? > 0 {
+            let data = window.data();
+            let weak = sum.get_or_insert_with(|| WeakSum::of(self.format, data));
+            let mut found = false;
+            if let Some(candidates) = index.get(&weak.digest()) {
+                let strong = self.strong_sum(data);
+                for &i in candidates.iter().filter(|&&i| self.blocks[i].strong == strong) {
+                    found = true;
+                    if sources[i].is_none() {
+                        sources[i] = Some(window.offset());
+                        missing -= 1;
+                    }
+                }
+            }
+            if found {
+                window.advance(data.len());
+                sum = None;
+                continue;
+            }
+            let out = data[0];
+            match window.slide()? {
+                Some(byte_in) => weak.rotate(out, byte_in),
+                None => weak.roll_out(out),
+            }
+        }
+        Ok(SeedPlan {
+            block_len: self.block_len,
+            target_len,
+            sources,
+        })
+    }
+
+    /// Strong sum of one block, cut to this signature's length
+    fn strong_sum(&self, data: &[u8]) -> Vec<u8> {
+        let hash = Blake2b::<U32>::digest(data);
+        hash[..self.strong_len as usize].to_vec()
+    }
+}
+
+/// Delta operations being collected, merging each with the one before
+#[derive(Default)]
+struct Ops(Vec<DeltaOp>);
+
+impl Ops {
+    fn copy(&mut self, offset: u64, len: u64) {
+        if let Some(DeltaOp::Copy { offset: o, len: l }) = self.0.last_mut() {
+            if *o + *l == offset {
+                *l += len;
+                return;
+            }
+        }
+        self.0.push(DeltaOp::Copy { offset, len });
+    }
+
+    fn literal(&mut self, offset: u64, len: u64) {
+        if let Some(DeltaOp::Literal { len: l, .. }) = self.0.last_mut() {
+            *l += len;
+            return;
+        }
+        self.0.push(DeltaOp::Literal { offset, len });
+    }
+}
+
+/// A block-sized window sliding over a stream
+struct Window<R> {
+    reader: R,
+    block_len: usize,
+    buffer: Vec<u8>,
+    /// Start of the window in `buffer`
+    start: usize,
+    /// Offset of `buffer[0]` in the stream
+    base: u64,
+    eof: bool,
+}
+
+impl<R: Read> Window<R> {
+    fn new(reader: R, block_len: usize) -> Self {
+        Self {
+            reader,
+            block_len,
+            buffer: Vec::with_capacity(2 * block_len),
+            start: 0,
+            base: 0,
+            eof: false,
+        }
+    }
+
+    /// Offset of the window in the stream
+    fn offset(&self) -> u64 {
+        self.base + self.start as u64
+    }
+
+    /// Bytes in the window: a block, or less at the end of the stream
+    fn data(&self) -> &[u8] {
+        let end = self.buffer.len().min(self.start + self.block_len);
+        &self.buffer[self.start..end]
+    }
+
+    /// Read until the window holds a block or the stream ends
+    fn fill(&mut self) -> Result<usize> {
+        self.fill_to(self.block_len)?;
+        Ok(self.data().len())
+    }
+
+    fn advance(&mut self, count: usize) {
+        self.start += count;
+    }
+
+    /// Move the window one byte along, returning the byte that entered it
+    /// or `None` once there are no more
+    fn slide(&mut self) -> Result<Option<u8>> {
+        self.fill_to(self.block_len + 1)?;
+        self.start += 1;
+        Ok(self.buffer.get(self.start + self.block_len - 1).copied())
+    }
+
+    /// Read until `len` bytes from the start of the window are buffered
+    /// or the stream ends
+    fn fill_to(&mut self, len: usize) -> Result<()> {
+        if self.buffer.len() - self.start >= len || self.eof {
+            return Ok(());
+        }
+        // Drop what the window has left behind before the buffer grows
+        if self.start >= self.block_len {
+            self.buffer.drain(..self.start);
+            self.base += self.start as u64;
+            self.start = 0;
+        }
+        let filled = self.buffer.len();
+        let missing = (self.start + len - filled).max(self.block_len);
+        self.buffer.resize(filled + missing, 0);
+        let count = read_full(&mut self.reader, &mut self.buffer[filled..])?;
+        self.buffer.truncate(filled + count);
+        self.eof = count < missing;
+        Ok(())
+    }
+}
+
+/// librsync's weak checksums
+#[derive(Debug, Clone, Copy)]
+enum WeakSum {
+    Rollsum { count: u16, s1: u16, s2: u16 },
+    RabinKarp { hash: u32, mult: u32 },
+}
+
+impl WeakSum {
+    fn of(format: SignatureFormat, data: &[u8]) -> Self {
+        let mut sum = match format {
+            SignatureFormat::Blake2 => WeakSum::Rollsum {
+                count: 0,
+                s1: 0,
+                s2: 0,
+            },
+            SignatureFormat::RabinKarpBlake2 => WeakSum::RabinKarp { hash: 1, mult: 1 },
+        };
+        for &byte in data {
+            sum.roll_in(byte);
+        }
+        sum
+    }
+
+    fn digest(&self) -> u32 {
+        match *self {
+            WeakSum::Rollsum { s1, s2, .. } => u32::from(s2) << 16 | u32::from(s1),
+            WeakSum::RabinKarp { hash, .. } => hash,
+        }
+    }
+
+    fn roll_in(&mut self, byte: u8) {
+        match self {
+            WeakSum::Rollsum { count, s1, s2 } => {
+                *s1 = s1.wrapping_add(u16::from(byte) + CHAR_OFFSET);
+                *s2 = s2.wrapping_add(*s1);
+                *count = count.wrapping_add(1);
+            }
+            WeakSum::RabinKarp { hash, mult } => {
+                *hash = hash.wrapping_mul(RK_MULT).wrapping_add(u32::from(byte));
+                *mult = mult.wrapping_mul(RK_MULT);
+            }
+        }
+    }
+
+    fn roll_out(&mut self, byte: u8) {
+        match self {
+            WeakSum::Rollsum { count, s1, s2 } => {
+                let out = u16::from(byte) + CHAR_OFFSET;
+                *s1 = s1.wrapping_sub(out);
+                *s2 = s2.wrapping_sub(count.wrapping_mul(out));
+                *count = count.wrapping_sub(1);
+            }
+            WeakSum::RabinKarp { hash, mult } => {
+                *mult = mult.wrapping_mul(RK_INVERSE);
+                let out = u32::from(byte).wrapping_add(RK_ADJUST);
+                *hash = hash.wrapping_sub(mult.wrapping_mul(out));
+            }
+        }
+    }
+
+    /// Drop `out` from the front of the window and add `byte_in` at the end
+    fn rotate(&mut self, out: u8, byte_in: u8) {
+        self.roll_out(out);
+        self.roll_in(byte_in);
+    }
+}
+
+/// Read until `buf` is full or the stream ends, returning the count read
+pub(crate) fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(count) => filled += count,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
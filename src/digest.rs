@@ -0,0 +1,187 @@
+//! Raw digest bytes
+//!
+//! The free functions and [`crate::Hashing`] return digests as text. A
+//! [`Digest`] keeps the bytes themselves, tagged with the algorithm that
+//! produced them, for embedding in binary protocols and file formats
+//! without a round trip through hex. It converts to any [`Encoding`] when
+//! text is needed after all.
+//!
+//! ```
+//! use hashing::{hash_bytes_raw, hash_string, Algorithm};
+//!
+//! let digest = hash_bytes_raw(b"hello", Algorithm::Sha256)?;
+//! assert_eq!(digest.len(), 32);
+//! assert_eq!(digest.as_bytes()[0], 0x2c);
+//! assert_eq!(digest.to_hex(), hash_string("hello", Algorithm::Sha256)?);
+//! assert_eq!(digest.to_base64(), "LPJNul+wow4m6DsqxbninhsWHlwfp0JecwQzYpOLmCQ=");
+//! # Ok::<(), hashing::HashError>(())
+//! ```
+
+use crate::{Algorithm, Encoding, HashError, Result};
+use std::fmt;
+
+/// Digest bytes and the algorithm that produced them
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Digest {
+    algorithm: Algorithm,
+    bytes: Vec<u8>,
+}
+
+impl Digest {
+    /// Wrap `bytes` as a digest of `algorithm`, checking their length
+    /// against [`Algorithm::output_sizes`]
+    pub fn new(algorithm: Algorithm, bytes: impl Into<Vec<u8>>) -> Result<Self> {
+        let bytes = bytes.into();
+        check_length(algorithm, bytes.len())?;
+        Ok(Self { algorithm, bytes })
+    }
+
+    /// Parse a hex digest of `algorithm`, as users type or paste it
+    ///
+    /// Surrounding whitespace and a `0x` prefix are ignored, and either
+    /// case is accepted. A digest of the wrong length is an error that
+    /// suggests an algorithm it would fit, since the usual cause is picking
+    /// the wrong one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hashing::{Algorithm, Digest};
+    ///
+    /// let digest = Digest::parse_for("5D41402ABC4B2A76B9719D911017C592", Algorithm::Md5)?;
+    /// assert_eq!(digest.to_hex(), "5d41402abc4b2a76b9719d911017c592");
+    ///
+    /// let sha1 = "aaf4c61ddcc5e8a2dabede0f3b482cd9aea9434d";
+    /// let err = Digest::parse_for(sha1, Algorithm::Sha256).unwrap_err();
+    /// assert!(err.to_string().contains("did you mean sha1?"));
+    /// # Ok::<(), hashing::HashError>(())
+    /// ```
+    pub fn parse_for(text: &str, algorithm: Algorithm) -> Result<Self> {
+        let text = text.trim();
+        let hex = text
+            .strip_prefix("0x")
+            .or_else(|| text.strip_prefix("0X"))
+            .unwrap_or(text);
+        let expected = algorithm.output_size() * 2;
+        if hex.len() != expected {
+            let mut message = format!(
+                "expected {} hex characters for {}, got {}",
+                expected,
+                algorithm.name(),
+                hex.len()
+            );
+            let fits = Algorithm::all()
+                .into_iter()
+                .find(|other| other.output_size() * 2 == hex.len());
+            if let Some(other) = fits {
+                message.push_str(&format!(" — did you mean {}?", other.name()));
+            }
+            return Err(HashError::InvalidInput(message));
+        }
+        if let Some(bad) = hex.chars().find(|c| !c.is_ascii_hexdigit()) {
+            return Err(HashError::InvalidInput(format!(
+                "invalid character {:?} in {} digest {}",
+                bad,
+                algorithm.name(),
+                text
+            )));
+        }
+        // Every character is a hex digit and the length is even
+        let bytes = hex::decode(hex).expect("validated hex");
+        Ok(Self { algorithm, bytes })
+    }
+
+    /// A digest just computed, whose length is right by construction
+    pub(crate) fn computed(algorithm: Algorithm, bytes: Vec<u8>) -> Self {
+        debug_assert!(algorithm.output_sizes().contains(&bytes.len()));
+        Self { algorithm, bytes }
+    }
+
+    /// Algorithm that produced the digest
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    /// The digest bytes
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// The digest bytes, without copying
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    /// Length in bytes
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Whether the digest has no bytes, which no algorithm produces
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Lowercase hex, as the string-returning functions give it
+    pub fn to_hex(&self) -> String {
+        hex::encode(&self.bytes)
+    }
+
+    /// Padded standard base64 (RFC 4648)
+    pub fn to_base64(&self) -> String {
+        use base64::engine::general_purpose::STANDARD;
+        use base64::Engine;
+
+        STANDARD.encode(&self.bytes)
+    }
+
+    /// The digest in `encoding`
+    ///
+    /// # Errors
+    ///
+    /// [`HashError::InvalidInput`] for [`Encoding::Binary`]; use
+    /// [`Digest::as_bytes`].
+    pub fn encode(&self, encoding: Encoding) -> Result<String> {
+        encoding.encode(&self.bytes)
+    }
+}
+
+/// Fail unless `algorithm` can produce digests of `len` bytes
+pub(crate) fn check_length(algorithm: Algorithm, len: usize) -> Result<()> {
+    let sizes = algorithm.output_sizes();
+    if sizes.contains(&len) {
+        return Ok(());
+    }
+    let expected = if sizes.start() == sizes.end() {
+        format!("{} bytes long", sizes.start())
+    } else if *sizes.end() == usize::MAX {
+        format!("at least {} byte long", sizes.start())
+    } else {
+        format!("{} to {} bytes long", sizes.start(), sizes.end())
+    };
+    Err(HashError::InvalidInput(format!(
+        "{} digests are {}, got {}",
+        algorithm.name(),
+        expected,
+        len
+    )))
+}
+
+impl AsRef<[u8]> for Digest {
+    fn as_ref(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl From<Digest> for Vec<u8> {
+    fn from(digest: Digest) -> Self {
+        digest.bytes
+    }
+}
+
+/// Formats as lowercase hex
+impl fmt::Display for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
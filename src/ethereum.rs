@@ -0,0 +1,100 @@
+//! Ethereum addresses and EIP-55 checksums
+//!
+//! An address is the last 20 bytes of the Keccak-256 digest of an
+//! uncompressed secp256k1 public key. EIP-55 encodes a checksum in the
+//! case of the hex letters: a letter is uppercase when the matching nibble
+//! of the Keccak-256 digest of the lowercase hex address is 8 or more.
+//!
+//! ```
+//! use hashing::ethereum::to_checksum_address;
+//!
+//! let address = to_checksum_address("0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed").unwrap();
+//! assert_eq!(address, "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed");
+//! ```
+
+use crate::fixed::{hash_bytes_fixed, Keccak256};
+use crate::{HashError, Result};
+
+/// Derive the checksummed address of a secp256k1 public key
+///
+/// Accepts the 65-byte uncompressed SEC1 encoding (`0x04 || x || y`) or
+/// the bare 64-byte `x || y`. Compressed keys must be decompressed first.
+pub fn address_from_public_key(public_key: &[u8]) -> Result<String> {
+    let coordinates = match public_key {
+        [0x04, rest @ ..] if rest.len() == 64 => rest,
+        _ if public_key.len() == 64 => public_key,
+        [0x02 | 0x03, rest @ ..] if rest.len() == 32 => {
+            return Err(HashError::InvalidInput(
+                "compressed public keys are not supported; decompress the key first".to_string(),
+            ))
+        }
+        _ => {
+            return Err(HashError::InvalidInput(format!(
+                "public key must be 64 or 65 bytes, got {}",
+                public_key.len()
+            )))
+        }
+    };
+    let digest = hash_bytes_fixed::<Keccak256>(coordinates);
+    Ok(checksum_encode(&digest[12..]))
+}
+
+/// Parse an address, rejecting mixed-case input with a wrong EIP-55 checksum
+///
+/// All-lowercase and all-uppercase addresses carry no checksum and are
+/// accepted as is. The `0x` prefix is optional.
+pub fn parse_address(address: &str) -> Result<[u8; 20]> {
+    let hex_part = address
+        .strip_prefix("0x")
+        .or_else(|| address.strip_prefix("0X"))
+        .unwrap_or(address);
+    if hex_part.len() != 40 || !hex_part.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(HashError::InvalidInput(format!(
+            "not an Ethereum address (expected 40 hex digits): {}",
+            address
+        )));
+    }
+    let mut bytes = [0u8; 20];
+    hex::decode_to_slice(hex_part, &mut bytes)
+        .map_err(|e| HashError::InvalidInput(e.to_string()))?;
+
+    let mixed_case = hex_part.bytes().any(|b| b.is_ascii_lowercase())
+        && hex_part.bytes().any(|b| b.is_ascii_uppercase());
+    if mixed_case && checksum_encode(&bytes)[2..] != *hex_part {
+        return Err(HashError::InvalidInput(format!(
+            "invalid EIP-55 checksum: {}",
+            address
+        )));
+    }
+    Ok(bytes)
+}
+
+/// The EIP-55 form of an address given in any case
+pub fn to_checksum_address(address: &str) -> Result<String> {
+    parse_address(address).map(|bytes| checksum_encode(&bytes))
+}
+
+/// Whether `address` is exactly in EIP-55 checksummed form
+pub fn is_checksum_address(address: &str) -> bool {
+    match parse_address(address) {
+        Ok(bytes) => address.strip_prefix("0x") == Some(&checksum_encode(&bytes)[2..]),
+        Err(_) => false,
+    }
+}
+
+/// `0x`-prefixed EIP-55 encoding of 20 address bytes
+fn checksum_encode(address: &[u8]) -> String {
+    let lower = hex::encode(address);
+    let digest = hash_bytes_fixed::<Keccak256>(lower.as_bytes());
+    let mut out = String::with_capacity(42);
+    out.push_str("0x");
+    for (i, c) in lower.chars().enumerate() {
+        let nibble = (digest[i / 2] >> (if i % 2 == 0 { 4 } else { 0 })) & 0x0f;
+        out.push(if nibble >= 8 {
+            c.to_ascii_uppercase()
+        } else {
+            c
+        });
+    }
+    out
+}
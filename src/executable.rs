@@ -0,0 +1,321 @@
+//! Digests embedded in executables
+//!
+//! - [`authenticode_digest`] computes the Authenticode digest of a Windows
+//!   PE file: the hash of the file minus its checksum field, its
+//!   certificate table entry and the certificate table itself, which is
+//!   the value a code signature covers.
+//! - [`elf_build_id`] reads the GNU build-id note that linkers embed in
+//!   ELF binaries, which survives stripping and identifies a build's debug
+//!   information.
+//!
+//! Only available with the `executable` feature.
+//!
+//! ```no_run
+//! use hashing::executable::{authenticode_digest_file, elf_build_id_file};
+//! use hashing::Algorithm;
+//!
+//! let digest = authenticode_digest_file("setup.exe", Algorithm::Sha256)?;
+//! println!("{}", hex::encode(digest));
+//!
+//! if let Some(id) = elf_build_id_file("/usr/bin/ls")? {
+//!     println!("build-id {}", hex::encode(id));
+//! }
+//! # Ok::<(), hashing::HashError>(())
+//! ```
+
+use crate::builder::{digest_reader, DEFAULT_BUFFER_SIZE};
+use crate::{Algorithm, HashError, Result};
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+/// ELF note type of the GNU build-id
+const NT_GNU_BUILD_ID: u32 = 3;
+
+/// Executable formats recognized by [`detect_format`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecutableFormat {
+    /// Windows Portable Executable (`.exe`, `.dll`, `.sys`, ...)
+    Pe,
+    /// Executable and Linkable Format
+    Elf,
+}
+
+/// Format of an executable, judged by its magic bytes
+pub fn detect_format(data: &[u8]) -> Option<ExecutableFormat> {
+    if data.starts_with(b"\x7fELF") {
+        Some(ExecutableFormat::Elf)
+    } else if data.starts_with(b"MZ") {
+        Some(ExecutableFormat::Pe)
+    } else {
+        None
+    }
+}
+
+/// Authenticode digest of a PE image held in memory
+///
+/// Matches the digest recorded by signing tools for the usual layout, in
+/// which the certificate table, if any, is at the end of the file.
+pub fn authenticode_digest(data: &[u8], algorithm: Algorithm) -> Result<Vec<u8>> {
+    let layout = PeLayout::parse(data)?;
+    let end = layout
+        .certificate_table
+        .map_or(data.len(), |(offset, _)| offset);
+    let parts = [
+        &data[..layout.checksum],
+        &data[layout.checksum + 4..layout.certificate_entry],
+        &data[layout.certificate_entry + 8..end],
+    ];
+    let reader = parts[0].chain(parts[1]).chain(parts[2]);
+    digest_reader(reader, algorithm, DEFAULT_BUFFER_SIZE)
+}
+
+/// Authenticode digest of a PE file
+pub fn authenticode_digest_file<P: AsRef<Path>>(path: P, algorithm: Algorithm) -> Result<Vec<u8>> {
+    authenticode_digest(&fs::read(path)?, algorithm)
+}
+
+/// Whether a PE image carries an Authenticode signature
+pub fn is_authenticode_signed(data: &[u8]) -> Result<bool> {
+    Ok(PeLayout::parse(data)?.certificate_table.is_some())
+}
+
+/// GNU build-id of an ELF binary held in memory, if it has one
+pub fn elf_build_id(data: &[u8]) -> Result<Option<Vec<u8>>> {
+    let elf = Elf::parse(data)?;
+    for (offset, size) in elf.note_areas()? {
+        if let Some(id) = elf.find_build_id(offset, size) {
+            return Ok(Some(id.to_vec()));
+        }
+    }
+    Ok(None)
+}
+
+/// GNU build-id of an ELF file, if it has one
+pub fn elf_build_id_file<P: AsRef<Path>>(path: P) -> Result<Option<Vec<u8>>> {
+    elf_build_id(&fs::read(path)?)
+}
+
+/// Whether an ELF file's build-id equals `expected` (hex, case-insensitive)
+///
+/// A file without a build-id never matches.
+pub fn verify_elf_build_id<P: AsRef<Path>>(path: P, expected: &str) -> Result<bool> {
+    let expected = hex::decode(expected.trim())
+        .map_err(|e| HashError::InvalidInput(format!("invalid build-id: {}", e)))?;
+    Ok(elf_build_id_file(path)?.is_some_and(|id| id == expected))
+}
+
+fn malformed(what: &str) -> HashError {
+    HashError::InvalidInput(format!("malformed executable: {}", what))
+}
+
+fn read_u16_le(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32_le(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Offsets of the PE fields excluded from the Authenticode digest
+struct PeLayout {
+    checksum: usize,
+    certificate_entry: usize,
+    certificate_table: Option<(usize, usize)>,
+}
+
+impl PeLayout {
+    fn parse(data: &[u8]) -> Result<Self> {
+        if !data.starts_with(b"MZ") {
+            return Err(malformed("no MZ header"));
+        }
+        let pe = read_u32_le(data, 0x3c).ok_or_else(|| malformed("truncated DOS header"))? as usize;
+        if data.get(pe..pe + 4) != Some(&b"PE\0\0"[..]) {
+            return Err(malformed("no PE signature"));
+        }
+        let optional = pe + 24;
+        let (directories, directory_count) = match read_u16_le(data, optional) {
+            Some(0x10b) => (optional + 96, optional + 92),
+            Some(0x20b) => (optional + 112, optional + 108),
+            _ => return Err(malformed("unknown optional header magic")),
+        };
+        let directory_count =
+            read_u32_le(data, directory_count).ok_or_else(|| malformed("truncated header"))?;
+        if directory_count < 5 {
+            return Err(malformed("no certificate table directory"));
+        }
+
+        let certificate_entry = directories + 4 * 8;
+        let offset = read_u32_le(data, certificate_entry)
+            .ok_or_else(|| malformed("truncated data directories"))? as usize;
+        let size = read_u32_le(data, certificate_entry + 4)
+            .ok_or_else(|| malformed("truncated data directories"))? as usize;
+        let certificate_table = match (offset, size) {
+            (0, _) | (_, 0) => None,
+            _ if offset < certificate_entry + 8 || offset + size > data.len() => {
+                return Err(malformed("certificate table out of bounds"))
+            }
+            _ => Some((offset, size)),
+        };
+
+        Ok(Self {
+            checksum: optional + 64,
+            certificate_entry,
+            certificate_table,
+        })
+    }
+}
+
+/// Just enough of an ELF header to find its notes
+struct Elf<'a> {
+    data: &'a [u8],
+    is_64: bool,
+    little_endian: bool,
+}
+
+impl<'a> Elf<'a> {
+    fn parse(data: &'a [u8]) -> Result<Self> {
+        if !data.starts_with(b"\x7fELF") || data.len() < 0x34 {
+            return Err(malformed("no ELF header"));
+        }
+        let is_64 = match data[4] {
+            1 => false,
+            2 => true,
+            _ => return Err(malformed("unknown ELF class")),
+        };
+        let little_endian = match data[5] {
+            1 => true,
+            2 => false,
+            _ => return Err(malformed("unknown ELF byte order")),
+        };
+        Ok(Self {
+            data,
+            is_64,
+            little_endian,
+        })
+    }
+
+    fn u16_at(&self, offset: usize) -> Option<u64> {
+        let end = offset.checked_add(2)?;
+        let bytes: [u8; 2] = self.data.get(offset..end)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        } as u64)
+    }
+
+    fn u32_at(&self, offset: usize) -> Option<u64> {
+        let end = offset.checked_add(4)?;
+        let bytes: [u8; 4] = self.data.get(offset..end)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        } as u64)
+    }
+
+    /// A word-sized field: 8 bytes in ELF64, 4 in ELF32
+    fn word_at(&self, offset: usize) -> Option<u64> {
+        if !self.is_64 {
+            return self.u32_at(offset);
+        }
+        let end = offset.checked_add(8)?;
+        let bytes: [u8; 8] = self.data.get(offset..end)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u64::from_le_bytes(bytes)
+        } else {
+            u64::from_be_bytes(bytes)
+        })
+    }
+
+    /// File ranges of PT_NOTE segments, or of SHT_NOTE sections when the
+    /// file has no program headers (relocatable objects)
+    fn note_areas(&self) -> Result<Vec<(usize, usize)>> {
+        let truncated = || malformed("truncated ELF headers");
+        let (ph, sh) = if self.is_64 {
+            (0x20, 0x28)
+        } else {
+            (0x1c, 0x20)
+        };
+        let counts = if self.is_64 { 0x36 } else { 0x2a };
+        // Offsets come straight from the file, so a hostile header must not
+        // be able to wrap them around
+        let offset = |field: Option<u64>| {
+            usize::try_from(field.ok_or_else(truncated)?)
+                .map_err(|_| malformed("ELF offset out of range"))
+        };
+        let entry = |table: usize, index: usize, size: usize, field: usize| {
+            index
+                .checked_mul(size)
+                .and_then(|at| at.checked_add(table))
+                .and_then(|at| at.checked_add(field))
+                .ok_or_else(|| malformed("ELF header table out of range"))
+        };
+
+        let mut areas = Vec::new();
+        let phoff = offset(self.word_at(ph))?;
+        let phentsize = self.u16_at(counts).ok_or_else(truncated)? as usize;
+        let phnum = self.u16_at(counts + 2).ok_or_else(truncated)? as usize;
+        for i in 0..phnum {
+            let kind = self.u32_at(entry(phoff, i, phentsize, 0)?);
+            if kind.ok_or_else(truncated)? != 4 {
+                continue;
+            }
+            let (at, size) = if self.is_64 { (8, 0x20) } else { (4, 0x10) };
+            areas.push((
+                offset(self.word_at(entry(phoff, i, phentsize, at)?))?,
+                offset(self.word_at(entry(phoff, i, phentsize, size)?))?,
+            ));
+        }
+        if !areas.is_empty() {
+            return Ok(areas);
+        }
+
+        let shoff = offset(self.word_at(sh))?;
+        let shentsize = self.u16_at(counts + 4).ok_or_else(truncated)? as usize;
+        let shnum = self.u16_at(counts + 6).ok_or_else(truncated)? as usize;
+        for i in 0..shnum {
+            let kind = self.u32_at(entry(shoff, i, shentsize, 4)?);
+            if kind.ok_or_else(truncated)? != 7 {
+                continue;
+            }
+            let (at, size) = if self.is_64 {
+                (0x18, 0x20)
+            } else {
+                (0x10, 0x14)
+            };
+            areas.push((
+                offset(self.word_at(entry(shoff, i, shentsize, at)?))?,
+                offset(self.word_at(entry(shoff, i, shentsize, size)?))?,
+            ));
+        }
+        Ok(areas)
+    }
+
+    /// Descriptor of the `GNU` build-id note within a note area
+    fn find_build_id(&self, offset: usize, size: usize) -> Option<&'a [u8]> {
+        let align4 = |n: usize| (n + 3) & !3;
+        let end = offset.checked_add(size)?.min(self.data.len());
+        let mut note = offset;
+        while note.checked_add(12)? <= end {
+            let name_size = self.u32_at(note)? as usize;
+            let desc_size = self.u32_at(note + 4)? as usize;
+            let kind = self.u32_at(note + 8)?;
+            let name = note + 12;
+            let desc = name.checked_add(align4(name_size))?;
+            let next = desc.checked_add(align4(desc_size))?;
+            if desc.checked_add(desc_size)? > end {
+                return None;
+            }
+            if kind == NT_GNU_BUILD_ID as u64 && self.data.get(name..name + name_size)? == b"GNU\0"
+            {
+                return self.data.get(desc..desc + desc_size);
+            }
+            note = next;
+        }
+        None
+    }
+}
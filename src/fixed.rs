@@ -0,0 +1,144 @@
+//! Digests as fixed-size arrays, typed by algorithm
+//!
+//! Each algorithm has a marker type whose [`FixedDigest::Output`] is an
+//! array of exactly the digest length, so sizes are checked at compile time
+//! and no hex string is allocated.
+//!
+//! ```
+//! use hashing::fixed::{hash_bytes_fixed, Sha256};
+//!
+//! let digest: [u8; 32] = hash_bytes_fixed::<Sha256>(b"hello");
+//! assert_eq!(digest[0], 0x2c);
+//! ```
+
+use crate::Algorithm;
+use sha2::Digest;
+
+/// An algorithm whose digest is returned as a fixed-size array
+pub trait FixedDigest {
+    /// The digest array, e.g. `[u8; 32]` for SHA-256
+    type Output: AsRef<[u8]> + Copy;
+
+    /// The runtime [`Algorithm`] this marker corresponds to
+    const ALGORITHM: Algorithm;
+
+    /// Hash `data` in one shot
+    fn digest(data: &[u8]) -> Self::Output;
+}
+
+/// Hash a byte slice into the algorithm's fixed-size digest array
+pub fn hash_bytes_fixed<A: FixedDigest>(data: &[u8]) -> A::Output {
+    A::digest(data)
+}
+
+macro_rules! fixed_digest {
+    ($($(#[$doc:meta])* $name:ident => $algorithm:ident, $len:literal, $hasher:ty;)*) => {
+        $(
+            $(#[$doc])*
+            #[derive(Debug, Clone, Copy)]
+            pub enum $name {}
+
+            impl FixedDigest for $name {
+                type Output = [u8; $len];
+                const ALGORITHM: Algorithm = Algorithm::$algorithm;
+
+                fn digest(data: &[u8]) -> Self::Output {
+                    let mut out = [0u8; $len];
+                    out.copy_from_slice(&<$hasher as Digest>::digest(data));
+                    out
+                }
+            }
+        )*
+    };
+}
+
+fixed_digest! {
+    /// MD5 (16 bytes)
+    Md5 => Md5, 16, md5::Md5;
+    /// SHA-224 (28 bytes)
+    Sha224 => Sha224, 28, sha2::Sha224;
+    /// SHA-256 (32 bytes)
+    Sha256 => Sha256, 32, sha2::Sha256;
+    /// SHA-384 (48 bytes)
+    Sha384 => Sha384, 48, sha2::Sha384;
+    /// SHA-512 (64 bytes)
+    Sha512 => Sha512, 64, sha2::Sha512;
+    /// SHA-512/224 (28 bytes)
+    Sha512_224 => Sha512_224, 28, sha2::Sha512_224;
+    /// SHA-512/256 (32 bytes)
+    Sha512_256 => Sha512_256, 32, sha2::Sha512_256;
+    /// SHA3-224 (28 bytes)
+    Sha3_224 => Sha3_224, 28, sha3::Sha3_224;
+    /// SHA3-256 (32 bytes)
+    Sha3_256 => Sha3_256, 32, sha3::Sha3_256;
+    /// SHA3-384 (48 bytes)
+    Sha3_384 => Sha3_384, 48, sha3::Sha3_384;
+    /// SHA3-512 (64 bytes)
+    Sha3_512 => Sha3_512, 64, sha3::Sha3_512;
+    /// BLAKE2b-512 (64 bytes)
+    Blake2b512 => Blake2b512, 64, blake2::Blake2b512;
+    /// BLAKE2s-256 (32 bytes)
+    Blake2s256 => Blake2s256, 32, blake2::Blake2s256;
+    /// Keccak-224 (28 bytes)
+    Keccak224 => Keccak224, 28, sha3::Keccak224;
+    /// Keccak-256 (32 bytes)
+    Keccak256 => Keccak256, 32, sha3::Keccak256;
+    /// Keccak-384 (48 bytes)
+    Keccak384 => Keccak384, 48, sha3::Keccak384;
+    /// Keccak-512 (64 bytes)
+    Keccak512 => Keccak512, 64, sha3::Keccak512;
+}
+
+/// SHA-1 (20 bytes)
+#[derive(Debug, Clone, Copy)]
+pub enum Sha1 {}
+
+impl FixedDigest for Sha1 {
+    type Output = [u8; 20];
+    const ALGORITHM: Algorithm = Algorithm::Sha1;
+
+    fn digest(data: &[u8]) -> Self::Output {
+        let mut hasher = crate::sha1_smol::Sha1::new();
+        hasher.update(data);
+        hasher.digest().bytes()
+    }
+}
+
+/// BLAKE3 (32 bytes)
+#[derive(Debug, Clone, Copy)]
+pub enum Blake3 {}
+
+impl FixedDigest for Blake3 {
+    type Output = [u8; 32];
+    const ALGORITHM: Algorithm = Algorithm::Blake3;
+
+    fn digest(data: &[u8]) -> Self::Output {
+        *blake3::hash(data).as_bytes()
+    }
+}
+
+/// SHA-256d, SHA-256 applied twice (32 bytes)
+#[derive(Debug, Clone, Copy)]
+pub enum Sha256d {}
+
+impl FixedDigest for Sha256d {
+    type Output = [u8; 32];
+    const ALGORITHM: Algorithm = Algorithm::Sha256d;
+
+    fn digest(data: &[u8]) -> Self::Output {
+        sha2::Sha256::digest(sha2::Sha256::digest(data)).into()
+    }
+}
+
+/// HASH160, RIPEMD-160 of SHA-256 (20 bytes)
+#[derive(Debug, Clone, Copy)]
+pub enum Hash160 {}
+
+impl FixedDigest for Hash160 {
+    type Output = [u8; 20];
+    const ALGORITHM: Algorithm = Algorithm::Hash160;
+
+    fn digest(data: &[u8]) -> Self::Output {
+        ripemd::Ripemd160::digest(sha2::Sha256::digest(data)).into()
+    }
+}
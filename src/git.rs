@@ -0,0 +1,100 @@
+//! File selection from Git
+//!
+//! [`tracked_files`] lists the files Git tracks below a directory and
+//! [`changed_files`] the ones that differ from a commit, including
+//! untracked files that are not ignored. Both run the `git` executable and
+//! parse its plumbing output, so build artifacts and anything else in
+//! `.gitignore` are left out without extra configuration. The paths are
+//! joined to the given directory, as [`crate::walk::walk_files`] yields
+//! them, and can be passed to [`crate::walk::WalkOptions::with_only_paths`].
+//!
+//! Only available with the `git` feature.
+//!
+//! ```no_run
+//! use hashing::git::tracked_files;
+//! use hashing::walk::{walk_files, WalkOptions};
+//!
+//! let options = WalkOptions::new().with_only_paths(tracked_files(".")?);
+//! for path in walk_files(".", &options) {
+//!     println!("{}", path?.display());
+//! }
+//! # Ok::<(), hashing::HashError>(())
+//! ```
+
+use crate::{HashError, Result};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Files tracked by Git below `dir`
+pub fn tracked_files<P: AsRef<Path>>(dir: P) -> Result<Vec<PathBuf>> {
+    let dir = dir.as_ref();
+    let listed = git(dir, &["ls-files", "-z", "--cached"])?;
+    Ok(join_paths(dir, &listed))
+}
+
+/// Files below `dir` that differ from `base` (default `HEAD`), staged or
+/// not, plus untracked files that are not ignored
+///
+/// Deleted files are not included. In a repository without commits every
+/// file counts as changed.
+pub fn changed_files<P: AsRef<Path>>(dir: P, base: Option<&str>) -> Result<Vec<PathBuf>> {
+    let dir = dir.as_ref();
+    let has_head = base.is_some() || git(dir, &["rev-parse", "--verify", "-q", "HEAD"]).is_ok();
+    let mut listed = if has_head {
+        let base = base.unwrap_or("HEAD");
+        let diff = ["diff", "--name-only", "-z", "--relative", base, "--"];
+        git(dir, &diff)?
+    } else {
+        git(dir, &["ls-files", "-z", "--cached"])?
+    };
+    let untracked = ["ls-files", "-z", "--others", "--exclude-standard"];
+    listed.extend(git(dir, &untracked)?);
+
+    let mut files: Vec<PathBuf> = join_paths(dir, &listed)
+        .into_iter()
+        .filter(|path| path.is_file())
+        .collect();
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+/// Run `git -C dir args...` and return its standard output
+fn git(dir: &Path, args: &[&str]) -> Result<Vec<u8>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .map_err(|e| HashError::InvalidInput(format!("failed to run git: {}", e)))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(HashError::InvalidInput(format!(
+            "git {} failed in {}: {}",
+            args[0],
+            dir.display(),
+            stderr.trim()
+        )));
+    }
+    Ok(output.stdout)
+}
+
+/// Split NUL-separated output into paths below `dir`
+fn join_paths(dir: &Path, listed: &[u8]) -> Vec<PathBuf> {
+    listed
+        .split(|&byte| byte == 0)
+        .filter(|name| !name.is_empty())
+        .map(|name| dir.join(path_from_bytes(name)))
+        .collect()
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    use std::os::unix::ffi::OsStrExt;
+    PathBuf::from(std::ffi::OsStr::from_bytes(bytes))
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+}
@@ -0,0 +1,245 @@
+//! Keyed-hash message authentication codes (HMAC, RFC 2104)
+//!
+//! HMAC is defined for every Merkle–Damgård and sponge hash the crate
+//! supports. BLAKE3 has its own keyed mode instead, and HMAC over the
+//! composed SHA-256d and HASH160 is not standardized; both are rejected here.
+//!
+//! [`hmac_string`], [`hmac_bytes`] and [`hmac_file`] cover whole inputs;
+//! [`HmacHasher`] takes the message in pieces, as it arrives.
+//!
+//! ```
+//! use hashing::hmac::{hmac_string, HmacHasher};
+//! use hashing::Algorithm;
+//!
+//! // A webhook signature over a body received in two parts
+//! let expected = hmac_string(b"secret", r#"{"event":"push"}"#, Algorithm::Sha256)?;
+//! let mut mac = HmacHasher::new(b"secret", Algorithm::Sha256)?;
+//! mac.update(br#"{"event":"#);
+//! mac.update(br#""push"}"#);
+//! assert_eq!(mac.finalize().to_hex(), expected);
+//! # Ok::<(), hashing::HashError>(())
+//! ```
+
+use crate::{Algorithm, Digest as RawDigest, HashError, Result};
+use ::hmac::{Mac, SimpleHmac};
+use sha2::digest::core_api::BlockSizeUser;
+use sha2::Digest;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+/// Whether HMAC is available for `algorithm`
+pub fn supports(algorithm: Algorithm) -> bool {
+    !matches!(
+        algorithm,
+        Algorithm::Blake3
+            | Algorithm::Shake128
+            | Algorithm::Shake256
+            | Algorithm::Sha256d
+            | Algorithm::Hash160
+    ) && algorithm.is_cryptographic()
+}
+
+/// Compute the HMAC of a string's UTF-8 bytes, returned as lowercase hex
+pub fn hmac_string(key: &[u8], data: &str, algorithm: Algorithm) -> Result<String> {
+    hmac_bytes(key, data.as_bytes(), algorithm)
+}
+
+/// Compute the HMAC of a byte slice, returned as lowercase hex
+///
+/// # Examples
+///
+/// ```
+/// use hashing::{hmac, Algorithm};
+///
+/// let data = b"The quick brown fox jumps over the lazy dog";
+/// let mac = hmac::hmac_bytes(b"key", data, Algorithm::Sha256).unwrap();
+/// assert_eq!(mac, "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8");
+/// ```
+pub fn hmac_bytes(key: &[u8], data: &[u8], algorithm: Algorithm) -> Result<String> {
+    mac_reader(key, data, algorithm).map(hex::encode)
+}
+
+/// Compute the HMAC of a file's contents with streaming
+pub fn hmac_file<P: AsRef<Path>>(key: &[u8], path: P, algorithm: Algorithm) -> Result<String> {
+    mac_reader(key, File::open(path)?, algorithm).map(hex::encode)
+}
+
+/// Compute the raw HMAC bytes of everything `reader` yields
+pub(crate) fn mac_reader<R: Read>(
+    key: &[u8],
+    mut reader: R,
+    algorithm: Algorithm,
+) -> Result<Vec<u8>> {
+    let mut mac = HmacHasher::new(key, algorithm)?;
+    io::copy(&mut reader, &mut mac)?;
+    Ok(mac.finalize().into_bytes())
+}
+
+/// An HMAC computed incrementally
+///
+/// Feed the message with [`HmacHasher::update`] (or write it, as the
+/// hasher is an [`io::Write`]), then call [`HmacHasher::finalize`] or
+/// [`HmacHasher::verify`].
+pub struct HmacHasher {
+    algorithm: Algorithm,
+    state: Box<dyn MacState>,
+}
+
+impl HmacHasher {
+    /// Start an HMAC of `algorithm` under `key`
+    pub fn new(key: &[u8], algorithm: Algorithm) -> Result<Self> {
+        use blake2::{Blake2b512, Blake2s256};
+        use md5::Md5;
+        use sha2::{Sha224, Sha256, Sha384, Sha512, Sha512_224, Sha512_256};
+        use sha3::{
+            Keccak224, Keccak256, Keccak384, Keccak512, Sha3_224, Sha3_256, Sha3_384, Sha3_512,
+        };
+
+        let state = match algorithm {
+            Algorithm::Md5 => mac_state::<Md5>(key)?,
+            Algorithm::Sha1 => Box::new(Sha1Hmac::new(key)),
+            Algorithm::Sha224 => mac_state::<Sha224>(key)?,
+            Algorithm::Sha256 => mac_state::<Sha256>(key)?,
+            Algorithm::Sha384 => mac_state::<Sha384>(key)?,
+            Algorithm::Sha512 => mac_state::<Sha512>(key)?,
+            Algorithm::Sha512_224 => mac_state::<Sha512_224>(key)?,
+            Algorithm::Sha512_256 => mac_state::<Sha512_256>(key)?,
+            Algorithm::Sha3_224 => mac_state::<Sha3_224>(key)?,
+            Algorithm::Sha3_256 => mac_state::<Sha3_256>(key)?,
+            Algorithm::Sha3_384 => mac_state::<Sha3_384>(key)?,
+            Algorithm::Sha3_512 => mac_state::<Sha3_512>(key)?,
+            Algorithm::Blake2b512 => mac_state::<Blake2b512>(key)?,
+            Algorithm::Blake2s256 => mac_state::<Blake2s256>(key)?,
+            Algorithm::Keccak224 => mac_state::<Keccak224>(key)?,
+            Algorithm::Keccak256 => mac_state::<Keccak256>(key)?,
+            Algorithm::Keccak384 => mac_state::<Keccak384>(key)?,
+            Algorithm::Keccak512 => mac_state::<Keccak512>(key)?,
+            Algorithm::Blake3
+            | Algorithm::Shake128
+            | Algorithm::Shake256
+            | Algorithm::Sha256d
+            | Algorithm::Hash160
+            | Algorithm::Xxh32
+            | Algorithm::Xxh64
+            | Algorithm::Xxh3
+            | Algorithm::Xxh128
+            | Algorithm::Crc32
+            | Algorithm::Crc32c => {
+                return Err(HashError::UnsupportedAlgorithm(format!(
+                    "HMAC is not defined for {}",
+                    algorithm.name()
+                )))
+            }
+        };
+        Ok(Self { algorithm, state })
+    }
+
+    /// Algorithm the HMAC is built on
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    /// Add the next part of the message
+    pub fn update(&mut self, data: &[u8]) {
+        self.state.update(data);
+    }
+
+    /// The HMAC of everything added
+    pub fn finalize(self) -> RawDigest {
+        RawDigest::computed(self.algorithm, self.state.finalize())
+    }
+
+    /// Whether the HMAC of everything added equals `expected`, compared in
+    /// constant time so a forger learns nothing from how long it takes
+    pub fn verify(self, expected: &[u8]) -> bool {
+        let mac = self.finalize();
+        mac.len() == expected.len()
+            && mac
+                .as_bytes()
+                .iter()
+                .zip(expected)
+                .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+                == 0
+    }
+}
+
+impl Write for HmacHasher {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Running state of one HMAC construction
+trait MacState: Send {
+    fn update(&mut self, data: &[u8]);
+    fn finalize(self: Box<Self>) -> Vec<u8>;
+}
+
+impl<D> MacState for SimpleHmac<D>
+where
+    D: Digest + BlockSizeUser + Send,
+{
+    fn update(&mut self, data: &[u8]) {
+        Mac::update(self, data);
+    }
+
+    fn finalize(self: Box<Self>) -> Vec<u8> {
+        Mac::finalize(*self).into_bytes().to_vec()
+    }
+}
+
+fn mac_state<D>(key: &[u8]) -> Result<Box<dyn MacState>>
+where
+    D: Digest + BlockSizeUser + Send + 'static,
+{
+    // HMAC accepts keys of any length, so this cannot fail
+    let mac = <SimpleHmac<D> as Mac>::new_from_slice(key)
+        .map_err(|e| HashError::InvalidInput(e.to_string()))?;
+    Ok(Box::new(mac))
+}
+
+/// HMAC-SHA1 on top of the crate's own SHA-1 implementation
+struct Sha1Hmac {
+    inner: crate::sha1_smol::Sha1,
+    block_key: [u8; SHA1_BLOCK_SIZE],
+}
+
+const SHA1_BLOCK_SIZE: usize = 64;
+
+impl Sha1Hmac {
+    fn new(key: &[u8]) -> Self {
+        use crate::sha1_smol::Sha1;
+
+        let mut block_key = [0u8; SHA1_BLOCK_SIZE];
+        if key.len() > SHA1_BLOCK_SIZE {
+            let mut hasher = Sha1::new();
+            hasher.update(key);
+            block_key[..20].copy_from_slice(&hasher.digest().bytes());
+        } else {
+            block_key[..key.len()].copy_from_slice(key);
+        }
+
+        let mut inner = Sha1::new();
+        inner.update(&block_key.map(|b| b ^ 0x36));
+        Self { inner, block_key }
+    }
+}
+
+impl MacState for Sha1Hmac {
+    fn update(&mut self, data: &[u8]) {
+        self.inner.update(data);
+    }
+
+    fn finalize(self: Box<Self>) -> Vec<u8> {
+        let mut outer = crate::sha1_smol::Sha1::new();
+        outer.update(&self.block_key.map(|b| b ^ 0x5c));
+        outer.update(&self.inner.digest().bytes());
+        outer.digest().bytes().to_vec()
+    }
+}
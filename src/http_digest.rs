@@ -0,0 +1,199 @@
+//! HTTP message digest fields
+//!
+//! Generates and checks the values of the `Content-Digest` and
+//! `Repr-Digest` fields of RFC 9530, e.g. `sha-256=:X48E9q...=:`, and of
+//! the older RFC 3230 `Digest` field, e.g. `SHA-256=X48E9q...=`.
+//!
+//! RFC 9530 only registers SHA-256 and SHA-512 as active algorithms, so
+//! only those can be generated for the new fields; the legacy field also
+//! allows MD5 and SHA-1. Parsing accepts all four and skips any other
+//! algorithm (`crc32c`, `unixsum`, ...).
+//!
+//! ```
+//! use hashing::http_digest::{digest_header, verify_digest_header, DigestHeader};
+//! use hashing::Algorithm;
+//!
+//! let body = br#"{"hello": "world"}"#;
+//! let value = digest_header(DigestHeader::ContentDigest, Algorithm::Sha256, body).unwrap();
+//! assert_eq!(value, "sha-256=:X48E9qOokqqrvdts8nOJRJN3OWDUoyWxBf7kbu9DBPE=:");
+//! assert!(verify_digest_header(&value, body).unwrap());
+//! ```
+
+use crate::builder::{digest_bytes, digest_reader, DEFAULT_BUFFER_SIZE};
+use crate::{Algorithm, HashError, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// The HTTP fields carrying message digests
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigestHeader {
+    /// `Content-Digest` (RFC 9530): digest of the message content
+    ContentDigest,
+    /// `Repr-Digest` (RFC 9530): digest of the selected representation
+    ReprDigest,
+    /// `Digest` (RFC 3230, obsoleted by RFC 9530)
+    Digest,
+}
+
+impl DigestHeader {
+    /// Field name as sent on the wire
+    pub fn name(&self) -> &'static str {
+        match self {
+            DigestHeader::ContentDigest => "Content-Digest",
+            DigestHeader::ReprDigest => "Repr-Digest",
+            DigestHeader::Digest => "Digest",
+        }
+    }
+
+    /// Token of `algorithm` in this field, if it may be generated
+    fn token(&self, algorithm: Algorithm) -> Option<&'static str> {
+        match (self, algorithm) {
+            (DigestHeader::Digest, Algorithm::Sha256) => Some("SHA-256"),
+            (DigestHeader::Digest, Algorithm::Sha512) => Some("SHA-512"),
+            (DigestHeader::Digest, Algorithm::Md5) => Some("MD5"),
+            (DigestHeader::Digest, Algorithm::Sha1) => Some("SHA"),
+            (_, Algorithm::Sha256) => Some("sha-256"),
+            (_, Algorithm::Sha512) => Some("sha-512"),
+            _ => None,
+        }
+    }
+}
+
+/// Field value for an already computed digest
+pub fn header_value(header: DigestHeader, algorithm: Algorithm, digest: &[u8]) -> Result<String> {
+    let token = checked_token(header, algorithm)?;
+    if digest.len() != algorithm.output_size() {
+        return Err(HashError::InvalidInput(format!(
+            "{} digests are {} bytes, got {}",
+            algorithm.name(),
+            algorithm.output_size(),
+            digest.len()
+        )));
+    }
+    let encoded = STANDARD.encode(digest);
+    Ok(match header {
+        DigestHeader::Digest => format!("{}={}", token, encoded),
+        _ => format!("{}=:{}:", token, encoded),
+    })
+}
+
+/// Field value for a message body held in memory
+pub fn digest_header(header: DigestHeader, algorithm: Algorithm, body: &[u8]) -> Result<String> {
+    // Check the algorithm before hashing a possibly large body
+    checked_token(header, algorithm)?;
+    header_value(header, algorithm, &digest_bytes(body, algorithm)?)
+}
+
+/// Field value for a body streamed from `reader`
+pub fn digest_header_reader<R: Read>(
+    header: DigestHeader,
+    algorithm: Algorithm,
+    reader: R,
+) -> Result<String> {
+    checked_token(header, algorithm)?;
+    let digest = digest_reader(reader, algorithm, DEFAULT_BUFFER_SIZE)?;
+    header_value(header, algorithm, &digest)
+}
+
+/// Field value for a body stored in a file
+pub fn digest_header_file<P: AsRef<Path>>(
+    header: DigestHeader,
+    algorithm: Algorithm,
+    path: P,
+) -> Result<String> {
+    digest_header_reader(header, algorithm, File::open(path)?)
+}
+
+/// The digests in a field value that this crate can check, in field order
+///
+/// Both the RFC 9530 dictionary syntax and the RFC 3230 list syntax are
+/// accepted. Unknown algorithms are skipped.
+pub fn parse_digest_header(value: &str) -> Result<Vec<(Algorithm, Vec<u8>)>> {
+    let mut digests = Vec::new();
+    for member in value.split(',') {
+        let member = member.trim();
+        if member.is_empty() {
+            continue;
+        }
+        let (token, encoded) = member.split_once('=').ok_or_else(|| {
+            HashError::InvalidInput(format!("invalid digest field member: {}", member))
+        })?;
+        let algorithm = match token.trim().to_ascii_lowercase().as_str() {
+            "sha-256" => Algorithm::Sha256,
+            "sha-512" => Algorithm::Sha512,
+            "md5" => Algorithm::Md5,
+            "sha" => Algorithm::Sha1,
+            _ => continue,
+        };
+        // Drop structured field parameters, then the byte sequence colons
+        let encoded = encoded.split(';').next().unwrap_or(encoded).trim();
+        let encoded = encoded
+            .strip_prefix(':')
+            .and_then(|rest| rest.strip_suffix(':'))
+            .unwrap_or(encoded);
+        let digest = STANDARD.decode(encoded).map_err(|e| {
+            HashError::InvalidInput(format!("invalid {} digest: {}", algorithm.name(), e))
+        })?;
+        if digest.len() != algorithm.output_size() {
+            return Err(HashError::InvalidInput(format!(
+                "{} digest is {} bytes, expected {}",
+                algorithm.name(),
+                digest.len(),
+                algorithm.output_size()
+            )));
+        }
+        digests.push((algorithm, digest));
+    }
+    Ok(digests)
+}
+
+/// Whether `body` matches the strongest digest in a field value
+///
+/// Fails if the field names no algorithm this crate implements.
+pub fn verify_digest_header(value: &str, body: &[u8]) -> Result<bool> {
+    verify_digest_header_reader(value, body)
+}
+
+/// Whether the body streamed from `reader` matches the strongest digest
+/// in a field value
+pub fn verify_digest_header_reader<R: Read>(value: &str, reader: R) -> Result<bool> {
+    let (algorithm, expected) = strongest(parse_digest_header(value)?).ok_or_else(|| {
+        HashError::UnsupportedAlgorithm(format!(
+            "no supported algorithm in digest field: {}",
+            value
+        ))
+    })?;
+    let actual = digest_reader(reader, algorithm, DEFAULT_BUFFER_SIZE)?;
+    Ok(actual == expected)
+}
+
+/// Whether the file at `path` matches the strongest digest in a field value
+pub fn verify_digest_header_file<P: AsRef<Path>>(value: &str, path: P) -> Result<bool> {
+    verify_digest_header_reader(value, File::open(path)?)
+}
+
+fn checked_token(header: DigestHeader, algorithm: Algorithm) -> Result<&'static str> {
+    header.token(algorithm).ok_or_else(|| {
+        HashError::UnsupportedAlgorithm(format!(
+            "{} cannot be used in the {} field",
+            algorithm.name(),
+            header.name()
+        ))
+    })
+}
+
+fn strongest(digests: Vec<(Algorithm, Vec<u8>)>) -> Option<(Algorithm, Vec<u8>)> {
+    let rank = |algorithm: Algorithm| match algorithm {
+        Algorithm::Sha512 => 3,
+        Algorithm::Sha256 => 2,
+        Algorithm::Sha1 => 1,
+        _ => 0,
+    };
+    digests
+        .into_iter()
+        .rev()
+        .max_by_key(|(algorithm, _)| rank(*algorithm))
+}
@@ -0,0 +1,240 @@
+//! One-read forensic overview of a file
+//!
+//! [`inspect_file`] reads a file once and returns what a quick triage
+//! looks at first: its size, a preview of the bytes at either end, the
+//! type its magic bytes suggest and its digests under several algorithms,
+//! computed by a [`MultiHasher`] in the same pass. [`hex_dump`] formats the
+//! previews the way `xxd` does.
+//!
+//! ```no_run
+//! use hashing::inspect::{hex_dump, inspect_file};
+//! use hashing::Algorithm;
+//!
+//! let report = inspect_file("sample.bin", &[Algorithm::Md5, Algorithm::Sha256], 64)?;
+//! println!("{} bytes, {}", report.size, report.file_type);
+//! print!("{}", hex_dump(&report.head, 0));
+//! for digest in &report.digests {
+//!     println!("{}: {}", digest.algorithm().name(), digest);
+//! }
+//! # Ok::<(), hashing::HashError>(())
+//! ```
+
+use crate::{Algorithm, Digest, MultiHasher, Result};
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Bytes read from the start of a file for type detection: enough to reach
+/// the `ustar` marker of a tar header at offset 257
+pub const SNIFF_LEN: usize = 512;
+
+/// Bytes per line of [`hex_dump`]
+const DUMP_WIDTH: usize = 16;
+
+/// Magic bytes at the start of a file and the type they announce, most
+/// specific first
+const MAGIC: &[(&[u8], &str)] = &[
+    (b"\x7fELF", "ELF executable"),
+    (b"MZ", "PE/DOS executable"),
+    (b"\xfe\xed\xfa\xce", "Mach-O binary"),
+    (b"\xfe\xed\xfa\xcf", "Mach-O binary"),
+    (b"\xce\xfa\xed\xfe", "Mach-O binary"),
+    (b"\xcf\xfa\xed\xfe", "Mach-O binary"),
+    (b"\x00asm", "WebAssembly module"),
+    (b"!<arch>\ndebian-binary", "Debian package"),
+    (b"!<arch>\n", "ar archive"),
+    (b"\xed\xab\xee\xdb", "RPM package"),
+    (b"PK\x03\x04", "ZIP archive"),
+    (b"PK\x05\x06", "ZIP archive (empty)"),
+    (b"\x1f\x8b", "gzip compressed data"),
+    (b"\xfd7zXZ\x00", "xz compressed data"),
+    (b"\x28\xb5\x2f\xfd", "Zstandard compressed data"),
+    (b"BZh", "bzip2 compressed data"),
+    (b"7z\xbc\xaf\x27\x1c", "7-Zip archive"),
+    (b"Rar!\x1a\x07", "RAR archive"),
+    (b"%PDF-", "PDF document"),
+    (b"\x89PNG\r\n\x1a\n", "PNG image"),
+    (b"\xff\xd8\xff", "JPEG image"),
+    (b"GIF87a", "GIF image"),
+    (b"GIF89a", "GIF image"),
+    (b"SQLite format 3\x00", "SQLite database"),
+    (b"-----BEGIN ", "PEM data"),
+    (b"#!", "script"),
+];
+
+/// What [`inspect_file`] found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Inspection {
+    /// Size in bytes, as read
+    pub size: u64,
+    /// Up to the requested number of bytes from the start
+    pub head: Vec<u8>,
+    /// Up to the requested number of bytes from the end; the same bytes as
+    /// `head` when the file is no longer than the preview
+    pub tail: Vec<u8>,
+    /// Type guessed by [`detect_type`]
+    pub file_type: &'static str,
+    /// One digest per requested algorithm, in order
+    pub digests: Vec<Digest>,
+}
+
+impl Inspection {
+    /// Offset of the first byte of [`Inspection::tail`]
+    pub fn tail_offset(&self) -> u64 {
+        self.size - self.tail.len() as u64
+    }
+}
+
+/// Inspect a file, reading it once
+///
+/// `preview` bytes of each end are kept; the digests come from a
+/// [`MultiHasher`] over the whole file.
+pub fn inspect_file<P: AsRef<Path>>(
+    path: P,
+    algorithms: &[Algorithm],
+    preview: usize,
+) -> Result<Inspection> {
+    inspect_reader(File::open(path)?, algorithms, preview)
+}
+
+/// Inspect everything `reader` yields, as [`inspect_file`] does
+pub fn inspect_reader<R: Read>(
+    reader: R,
+    algorithms: &[Algorithm],
+    preview: usize,
+) -> Result<Inspection> {
+    let mut recorder = Recorder {
+        inner: reader,
+        head: Vec::new(),
+        head_len: preview.max(SNIFF_LEN),
+        tail: Vec::new(),
+        tail_len: preview,
+        size: 0,
+    };
+    let digests = MultiHasher::new(algorithms).hash_reader(&mut recorder)?;
+    let file_type = detect_type(&recorder.head);
+    let mut head = recorder.head;
+    head.truncate(preview);
+    Ok(Inspection {
+        size: recorder.size,
+        head,
+        tail: recorder.tail,
+        file_type,
+        digests,
+    })
+}
+
+/// Type of a file judged by its first bytes, at least [`SNIFF_LEN`] of them
+/// where the file has that many
+///
+/// Files without known magic bytes are `UTF-8 text` if the bytes decode
+/// and hold no NUL, otherwise `data`.
+///
+/// # Examples
+///
+/// ```
+/// use hashing::inspect::detect_type;
+///
+/// assert_eq!(detect_type(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"), "PNG image");
+/// assert_eq!(detect_type("héllo\n".as_bytes()), "UTF-8 text");
+/// assert_eq!(detect_type(&[0, 1, 2]), "data");
+/// assert_eq!(detect_type(b""), "empty");
+/// ```
+pub fn detect_type(head: &[u8]) -> &'static str {
+    if head.is_empty() {
+        return "empty";
+    }
+    if let Some((_, name)) = MAGIC.iter().find(|(magic, _)| head.starts_with(magic)) {
+        return name;
+    }
+    if head.len() >= 12 && head.starts_with(b"RIFF") && &head[8..12] == b"WEBP" {
+        return "WebP image";
+    }
+    if head.get(257..262) == Some(b"ustar") {
+        return "tar archive";
+    }
+    let text = match std::str::from_utf8(head) {
+        Ok(_) => true,
+        // A character cut off at the end of the sniffed bytes
+        Err(e) => e.error_len().is_none(),
+    };
+    if text && !head.contains(&0) {
+        "UTF-8 text"
+    } else {
+        "data"
+    }
+}
+
+/// `data` as `xxd` prints it: offset, 16 bytes in hex and their ASCII,
+/// offsets counted from `offset`
+///
+/// # Examples
+///
+/// ```
+/// use hashing::inspect::hex_dump;
+///
+/// assert_eq!(
+///     hex_dump(b"hello, world\n", 0x20),
+///     "00000020: 6865 6c6c 6f2c 2077 6f72 6c64 0a         hello, world.\n"
+/// );
+/// ```
+pub fn hex_dump(data: &[u8], offset: u64) -> String {
+    let mut out = String::new();
+    for (line, bytes) in data.chunks(DUMP_WIDTH).enumerate() {
+        let _ = write!(out, "{:08x}:", offset + (line * DUMP_WIDTH) as u64);
+        for i in 0..DUMP_WIDTH {
+            if i % 2 == 0 {
+                out.push(' ');
+            }
+            match bytes.get(i) {
+                Some(byte) => {
+                    let _ = write!(out, "{:02x}", byte);
+                }
+                None => out.push_str("  "),
+            }
+        }
+        out.push_str("  ");
+        for &byte in bytes {
+            let shown = if byte.is_ascii_graphic() || byte == b' ' {
+                byte as char
+            } else {
+                '.'
+            };
+            out.push(shown);
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Reader that keeps the first and last bytes passing through it
+struct Recorder<R> {
+    inner: R,
+    head: Vec<u8>,
+    head_len: usize,
+    tail: Vec<u8>,
+    tail_len: usize,
+    size: u64,
+}
+
+impl<R: Read> Read for Recorder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let count = self.inner.read(buf)?;
+        let data = &buf[..count];
+        self.size += count as u64;
+
+        let wanted = self.head_len.saturating_sub(self.head.len());
+        self.head.extend_from_slice(&data[..wanted.min(count)]);
+
+        if count >= self.tail_len {
+            self.tail.clear();
+            self.tail.extend_from_slice(&data[count - self.tail_len..]);
+        } else {
+            self.tail.extend_from_slice(data);
+            let excess = self.tail.len().saturating_sub(self.tail_len);
+            self.tail.drain(..excess);
+        }
+        Ok(count)
+    }
+}
@@ -0,0 +1,311 @@
+//! Digest checks for signed JAR and APK archives
+//!
+//! A signed JAR (and an APK signed with the v1 scheme) records a digest of
+//! every entry in `META-INF/MANIFEST.MF`, and each signature file
+//! (`META-INF/*.SF`) records digests of the manifest and of its sections.
+//! [`verify_jar`] recomputes all of them from the archive. It does not
+//! check the cryptographic signature in the signature block files
+//! (`.RSA`, `.DSA`, `.EC`) nor APK v2+ signing blocks.
+//!
+//! Only available with the `archive` feature.
+//!
+//! ```no_run
+//! use hashing::jar::{verify_jar_file, EntryStatus};
+//!
+//! let report = verify_jar_file("app.apk")?;
+//! for entry in &report.entries {
+//!     if entry.status != EntryStatus::Verified {
+//!         println!("{}: {:?}", entry.name, entry.status);
+//!     }
+//! }
+//! assert!(report.is_ok());
+//! # Ok::<(), hashing::HashError>(())
+//! ```
+
+use crate::builder::digest_bytes;
+use crate::{Algorithm, HashError, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{Read, Seek};
+use std::path::Path;
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+const MANIFEST: &str = "META-INF/MANIFEST.MF";
+
+/// Outcome of checking one entry or signature file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryStatus {
+    /// Every recorded digest matches
+    Verified,
+    /// At least one recorded digest differs
+    Mismatch,
+    /// The manifest names an entry the archive does not contain
+    Missing,
+    /// The archive contains an entry the manifest does not cover
+    Unlisted,
+}
+
+/// Result for one archive entry or signature file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryCheck {
+    /// Entry path within the archive
+    pub name: String,
+    /// What the check found
+    pub status: EntryStatus,
+}
+
+/// Everything [`verify_jar`] checked
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JarVerification {
+    /// Entries with digests in the manifest, then unlisted entries
+    pub entries: Vec<EntryCheck>,
+    /// Signature files checked against the manifest
+    pub signature_files: Vec<EntryCheck>,
+}
+
+impl JarVerification {
+    /// Whether nothing mismatched or went missing
+    ///
+    /// Unlisted entries are not failures on their own, but they are not
+    /// protected by the signature either.
+    pub fn is_ok(&self) -> bool {
+        self.entries
+            .iter()
+            .chain(&self.signature_files)
+            .all(|check| matches!(check.status, EntryStatus::Verified | EntryStatus::Unlisted))
+    }
+}
+
+/// Check a JAR or APK read from `reader`
+pub fn verify_jar<R: Read + Seek>(reader: R) -> Result<JarVerification> {
+    let mut archive = ZipArchive::new(reader).map_err(zip_error)?;
+    let manifest = read_entry(&mut archive, MANIFEST)?
+        .ok_or_else(|| HashError::InvalidInput(format!("archive has no {}", MANIFEST)))?;
+    let sections = parse_sections(&manifest);
+
+    let mut report = JarVerification::default();
+    let mut listed = BTreeSet::new();
+    for section in sections.iter().skip(1) {
+        let Some(name) = section.attribute("Name") else {
+            continue;
+        };
+        let digests = section.digests("-Digest");
+        if digests.is_empty() {
+            continue;
+        }
+        listed.insert(name.to_string());
+        let status = match read_entry(&mut archive, name)? {
+            None => EntryStatus::Missing,
+            Some(contents) => check_digests(&contents, &digests)?,
+        };
+        report.entries.push(EntryCheck {
+            name: name.to_string(),
+            status,
+        });
+    }
+
+    let names: Vec<String> = archive.file_names().map(str::to_string).collect();
+    if !listed.is_empty() {
+        for name in &names {
+            if !name.ends_with('/') && !listed.contains(name) && !is_meta_inf_file(name) {
+                report.entries.push(EntryCheck {
+                    name: name.clone(),
+                    status: EntryStatus::Unlisted,
+                });
+            }
+        }
+    }
+
+    for name in names.iter().filter(|name| is_signature_file(name)) {
+        let signature = read_entry(&mut archive, name)?.unwrap_or_default();
+        if let Some(status) = check_signature_file(&signature, &manifest, &sections)? {
+            report.signature_files.push(EntryCheck {
+                name: name.clone(),
+                status,
+            });
+        }
+    }
+    Ok(report)
+}
+
+/// Check a JAR or APK file
+pub fn verify_jar_file<P: AsRef<Path>>(path: P) -> Result<JarVerification> {
+    verify_jar(File::open(path)?)
+}
+
+/// Check a signature file: the whole-manifest digest if it matches,
+/// otherwise the main attributes and every section it lists
+fn check_signature_file(
+    signature: &[u8],
+    manifest: &[u8],
+    sections: &[Section<'_>],
+) -> Result<Option<EntryStatus>> {
+    let signature_sections = parse_sections(signature);
+    let Some(main) = signature_sections.first() else {
+        return Ok(None);
+    };
+
+    let whole = main.digests("-Digest-Manifest");
+    if !whole.is_empty() && check_digests(manifest, &whole)? == EntryStatus::Verified {
+        return Ok(Some(EntryStatus::Verified));
+    }
+
+    let mut checked = !whole.is_empty();
+    let main_attributes = main.digests("-Digest-Manifest-Main-Attributes");
+    if let Some(manifest_main) = sections.first() {
+        if !main_attributes.is_empty() {
+            checked = true;
+            if check_digests(manifest_main.raw, &main_attributes)? != EntryStatus::Verified {
+                return Ok(Some(EntryStatus::Mismatch));
+            }
+        }
+    }
+    for signature_section in signature_sections.iter().skip(1) {
+        let Some(name) = signature_section.attribute("Name") else {
+            continue;
+        };
+        let digests = signature_section.digests("-Digest");
+        if digests.is_empty() {
+            continue;
+        }
+        checked = true;
+        let section = sections
+            .iter()
+            .skip(1)
+            .find(|section| section.attribute("Name") == Some(name));
+        match section {
+            Some(section) if check_digests(section.raw, &digests)? == EntryStatus::Verified => {}
+            _ => return Ok(Some(EntryStatus::Mismatch)),
+        }
+    }
+    // A stale whole-manifest digest is fine when entries were added to the
+    // manifest after signing, as long as every signed section still matches
+    Ok(checked.then_some(EntryStatus::Verified))
+}
+
+fn check_digests(contents: &[u8], digests: &[(Algorithm, Vec<u8>)]) -> Result<EntryStatus> {
+    for (algorithm, expected) in digests {
+        if digest_bytes(contents, *algorithm)? != *expected {
+            return Ok(EntryStatus::Mismatch);
+        }
+    }
+    Ok(EntryStatus::Verified)
+}
+
+fn read_entry<R: Read + Seek>(archive: &mut ZipArchive<R>, name: &str) -> Result<Option<Vec<u8>>> {
+    let mut entry = match archive.by_name(name) {
+        Ok(entry) => entry,
+        Err(ZipError::FileNotFound) => return Ok(None),
+        Err(e) => return Err(zip_error(e)),
+    };
+    let mut contents = Vec::with_capacity(entry.size() as usize);
+    entry.read_to_end(&mut contents)?;
+    Ok(Some(contents))
+}
+
+fn zip_error(error: ZipError) -> HashError {
+    match error {
+        ZipError::Io(e) => HashError::Io(e),
+        e => HashError::InvalidInput(format!("invalid archive: {}", e)),
+    }
+}
+
+/// Files directly in `META-INF/`, which the manifest does not cover
+fn is_meta_inf_file(name: &str) -> bool {
+    name.strip_prefix("META-INF/")
+        .is_some_and(|rest| !rest.contains('/'))
+}
+
+fn is_signature_file(name: &str) -> bool {
+    is_meta_inf_file(name) && name.to_ascii_uppercase().ends_with(".SF")
+}
+
+/// Algorithm named by the prefix of a digest attribute (`SHA-256-Digest`)
+fn digest_algorithm(name: &str) -> Option<Algorithm> {
+    match name.to_ascii_uppercase().as_str() {
+        "SHA-256" | "SHA256" => Some(Algorithm::Sha256),
+        "SHA-384" | "SHA384" => Some(Algorithm::Sha384),
+        "SHA-512" | "SHA512" => Some(Algorithm::Sha512),
+        "SHA1" | "SHA-1" | "SHA" => Some(Algorithm::Sha1),
+        "MD5" => Some(Algorithm::Md5),
+        _ => None,
+    }
+}
+
+/// A manifest section: its raw bytes, including the blank line that ends
+/// it, and its attributes with continuation lines joined
+struct Section<'a> {
+    raw: &'a [u8],
+    attributes: Vec<(String, String)>,
+}
+
+impl Section<'_> {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Decoded `<algorithm><suffix>` attributes of known algorithms
+    fn digests(&self, suffix: &str) -> Vec<(Algorithm, Vec<u8>)> {
+        self.attributes
+            .iter()
+            .filter_map(|(key, value)| {
+                let split = key.len().checked_sub(suffix.len())?;
+                if !key.is_char_boundary(split) || !key[split..].eq_ignore_ascii_case(suffix) {
+                    return None;
+                }
+                let algorithm = digest_algorithm(&key[..split])?;
+                Some((algorithm, STANDARD.decode(value.trim()).ok()?))
+            })
+            .collect()
+    }
+}
+
+fn parse_sections(data: &[u8]) -> Vec<Section<'_>> {
+    let mut sections = Vec::new();
+    let mut start = 0;
+    let mut attributes: Vec<(String, String)> = Vec::new();
+    let mut position = 0;
+    while position < data.len() {
+        let line_start = position;
+        while position < data.len() && data[position] != b'\n' && data[position] != b'\r' {
+            position += 1;
+        }
+        let line = &data[line_start..position];
+        if data[position..].starts_with(b"\r\n") {
+            position += 2;
+        } else if position < data.len() {
+            position += 1;
+        }
+
+        if line.is_empty() {
+            sections.push(Section {
+                raw: &data[start..position],
+                attributes: std::mem::take(&mut attributes),
+            });
+            start = position;
+        } else if let Some(continuation) = line.strip_prefix(b" ") {
+            if let Some((_, value)) = attributes.last_mut() {
+                value.push_str(&String::from_utf8_lossy(continuation));
+            }
+        } else {
+            let line = String::from_utf8_lossy(line);
+            if let Some((key, value)) = line.split_once(':') {
+                let value = value.strip_prefix(' ').unwrap_or(value);
+                attributes.push((key.to_string(), value.to_string()));
+            }
+        }
+    }
+    if start < data.len() {
+        sections.push(Section {
+            raw: &data[start..],
+            attributes,
+        });
+    }
+    sections
+}
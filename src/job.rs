@@ -0,0 +1,685 @@
+//! Long-running hashing and verification jobs
+//!
+//! Desktop applications cannot block their UI thread on a directory scan.
+//! A [`Job`] runs the work on a background thread and exposes it three
+//! ways: per-file [`JobEvent`]s over a channel, a [`JobStatus`] snapshot
+//! that can be polled at any time, and [`Job::cancel`], which stops the job
+//! between files (and, for hash jobs, in the middle of a file).
+//!
+//! ```no_run
+//! use hashing::job::{HashJob, JobEvent};
+//! use hashing::Algorithm;
+//!
+//! let job = HashJob::new("photos").with_algorithm(Algorithm::Blake3).spawn();
+//! for event in job.events() {
+//!     match event {
+//!         JobEvent::Hashed { path, digest, .. } => println!("{}  {}", digest, path.display()),
+//!         JobEvent::Failed { path, message } => eprintln!("{:?}: {}", path, message),
+//!         _ => {}
+//!     }
+//! }
+//! let status = job.wait()?;
+//! println!("{} files hashed", status.files_done);
+//! # Ok::<(), hashing::HashError>(())
+//! ```
+//!
+//! Events are buffered without limit, so a consumer that falls behind
+//! delays nothing but its own view; the last event is always
+//! [`JobEvent::Finished`].
+
+use crate::checksum::{self, CheckStatus, ChecksumEntry};
+use crate::statcache::StatCache;
+use crate::walk::{walk_files, HardLinks, WalkOptions};
+use crate::builder::{check_reread, FileReads};
+use crate::{hash_file_with_timeout, Algorithm, HashError, Hashing, Result};
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Something that happened while a job ran, in the order it happened
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobEvent {
+    /// A file is about to be read
+    Started { path: PathBuf },
+    /// The current file has been read up to `bytes` bytes; sent at most
+    /// once per [`HashJob::with_progress_interval`], and once more with the
+    /// final count
+    Progress { path: PathBuf, bytes: u64 },
+    /// A file of `size` bytes was hashed; `linked_to` names an earlier hard
+    /// link to the same content whose digest was reused
+    Hashed {
+        path: PathBuf,
+        digest: String,
+        size: u64,
+        linked_to: Option<PathBuf>,
+    },
+    /// A checksum entry was verified; `line` is its line in the checksum
+    /// file, or its 1-based position in the list given to [`VerifyJob::new`]
+    Checked {
+        entry: ChecksumEntry,
+        line: usize,
+        status: CheckStatus,
+    },
+    /// A file (or, without `path`, a directory entry) could not be read
+    Failed {
+        path: Option<PathBuf>,
+        message: String,
+    },
+    /// The job is over; always the last event
+    Finished(JobStatus),
+}
+
+/// Where a job stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobState {
+    /// Still working
+    Running,
+    /// Every file was processed
+    Completed,
+    /// Stopped early by [`Job::cancel`]
+    Cancelled,
+}
+
+/// Snapshot of a job's progress
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JobStatus {
+    /// Where the job stands
+    pub state: JobState,
+    /// Files hashed, or checksum entries that verified
+    pub files_done: u64,
+    /// Files that could not be read, or entries that did not verify
+    /// (improperly formatted lines count as neither)
+    pub files_failed: u64,
+    /// Bytes hashed so far, including the current file's (hash jobs only)
+    pub bytes_done: u64,
+    /// File being hashed (hash jobs only)
+    pub current: Option<PathBuf>,
+    /// Time since the job was spawned, or that it ran for once over
+    pub elapsed: Duration,
+}
+
+/// A running (or finished) job
+///
+/// Dropping a job cancels it without waiting for the thread to stop.
+pub struct Job {
+    events: Receiver<JobEvent>,
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Job {
+    fn spawn<F>(work: F) -> Self
+    where
+        F: FnOnce(&Context) + Send + 'static,
+    {
+        let (sender, events) = mpsc::channel();
+        let shared = Arc::new(Shared {
+            cancel: AtomicBool::new(false),
+            status: Mutex::new(Progress::default()),
+            start: Instant::now(),
+        });
+        let context = Context {
+            sender,
+            shared: Arc::clone(&shared),
+        };
+        let thread = thread::spawn(move || {
+            work(&context);
+            let state = if context.cancelled() {
+                JobState::Cancelled
+            } else {
+                JobState::Completed
+            };
+            let status = {
+                let mut progress = context.shared.lock();
+                progress.state = Some(state);
+                progress.current = None;
+                progress.elapsed = Some(context.shared.start.elapsed());
+                progress.snapshot(context.shared.start)
+            };
+            context.send(JobEvent::Finished(status));
+        });
+        Self {
+            events,
+            shared,
+            thread: Some(thread),
+        }
+    }
+
+    /// Receiver of the job's events; iterating it ends after
+    /// [`JobEvent::Finished`]
+    pub fn events(&self) -> &Receiver<JobEvent> {
+        &self.events
+    }
+
+    /// Current progress, without waiting
+    pub fn status(&self) -> JobStatus {
+        self.shared.lock().snapshot(self.shared.start)
+    }
+
+    /// True once the job has stopped, whether completed or cancelled
+    pub fn is_finished(&self) -> bool {
+        self.thread.as_ref().map_or(true, JoinHandle::is_finished)
+    }
+
+    /// Ask the job to stop; it finishes as [`JobState::Cancelled`]
+    pub fn cancel(&self) {
+        self.shared.cancel.store(true, Ordering::Relaxed);
+    }
+
+    /// Wait for the job to stop and return its final status
+    ///
+    /// Events not yet received are discarded.
+    pub fn wait(mut self) -> Result<JobStatus> {
+        if let Some(thread) = self.thread.take() {
+            thread.join().map_err(|_| {
+                HashError::Io(io::Error::new(io::ErrorKind::Other, "job thread panicked"))
+            })?;
+        }
+        Ok(self.status())
+    }
+}
+
+impl Drop for Job {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+/// Hash every file below a directory, in walk order
+#[derive(Debug, Clone)]
+pub struct HashJob {
+    root: PathBuf,
+    walk: WalkOptions,
+    algorithm: Algorithm,
+    key: Option<Vec<u8>>,
+    timeout: Option<Duration>,
+    hard_link_dedup: bool,
+    skip: HashSet<PathBuf>,
+    progress_interval: Option<Duration>,
+    paranoid: bool,
+    double_read: bool,
+    reads: FileReads,
+}
+
+impl HashJob {
+    /// SHA-256 of every file below `root`, reusing digests of hard links
+    pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+            walk: WalkOptions::new(),
+            algorithm: Algorithm::Sha256,
+            key: None,
+            timeout: None,
+            hard_link_dedup: true,
+            skip: HashSet::new(),
+            progress_interval: None,
+            paranoid: false,
+            double_read: false,
+            reads: FileReads::default(),
+        }
+    }
+
+    /// Hash algorithm to use
+    pub fn with_algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Which files to visit, and in what order
+    pub fn with_walk_options(mut self, options: WalkOptions) -> Self {
+        self.walk = options;
+        self
+    }
+
+    /// Compute HMACs with this key instead of plain hashes
+    pub fn with_key(mut self, key: impl Into<Vec<u8>>) -> Self {
+        self.key = Some(key.into());
+        self
+    }
+
+    /// Give up on a file that takes longer than `timeout`, e.g. on a hung
+    /// network mount
+    ///
+    /// A timed-out file is reported as failed; its read cannot be
+    /// interrupted, so no [`JobEvent::Progress`] is sent for it and
+    /// cancelling waits for it. Ignored with a key, in paranoid mode, with
+    /// double reads, with uncached reads and with a read-ahead window.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Whether to hash each set of hard links once (default: true)
+    pub fn with_hard_link_dedup(mut self, dedup: bool) -> Self {
+        self.hard_link_dedup = dedup;
+        self
+    }
+
+    /// Leave out these files, e.g. those a resumed scan already finished
+    pub fn with_skip<I, P>(mut self, paths: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        self.skip.extend(paths.into_iter().map(Into::into));
+        self
+    }
+
+    /// Compute every digest twice and report a file whose two digests
+    /// disagree as failed (see [`Hashing::paranoid`])
+    pub fn with_paranoid(mut self, paranoid: bool) -> Self {
+        self.paranoid = paranoid;
+        self
+    }
+
+    /// Read every file twice and report a file whose two digests differ as
+    /// failed (see [`Hashing::double_read`]); progress covers the first read
+    pub fn with_double_read(mut self, double_read: bool) -> Self {
+        self.double_read = double_read;
+        self
+    }
+
+    /// Read files past the page cache (see [`Hashing::uncached`])
+    pub fn with_uncached(mut self, uncached: bool) -> Self {
+        self.reads.uncached = uncached;
+        self
+    }
+
+    /// Keep the kernel reading `bytes` ahead of each file (see
+    /// [`Hashing::read_ahead`])
+    pub fn with_read_ahead(mut self, bytes: u64) -> Self {
+        self.reads.read_ahead = bytes;
+        self
+    }
+
+    /// Send [`JobEvent::Progress`] at most once per `interval`
+    pub fn with_progress_interval(mut self, interval: Duration) -> Self {
+        self.progress_interval = Some(interval);
+        self
+    }
+
+    /// Start hashing on a background thread
+    pub fn spawn(self) -> Job {
+        Job::spawn(move |context| self.run(context))
+    }
+
+    fn run(self, context: &Context) {
+        let mut hard_links = HardLinks::new();
+        for entry in walk_files(&self.root, &self.walk) {
+            if context.cancelled() {
+                return;
+            }
+            let path = match entry {
+                Ok(path) => path,
+                Err(e) => {
+                    context.fail(None, e.to_string());
+                    continue;
+                }
+            };
+            if self.skip.contains(&path) {
+                continue;
+            }
+
+            let metadata = if self.hard_link_dedup {
+                fs::metadata(&path).ok()
+            } else {
+                None
+            };
+            context.start(&path);
+            if let Some((first, digest)) = metadata.as_ref().and_then(|m| hard_links.lookup(m)) {
+                let event = JobEvent::Hashed {
+                    path: path.clone(),
+                    digest: digest.to_string(),
+                    size: metadata.as_ref().map_or(0, |m| m.len()),
+                    linked_to: Some(first.to_path_buf()),
+                };
+                context.finish(0, event);
+                continue;
+            }
+
+            match self.hash(context, &path) {
+                Ok((digest, bytes)) => {
+                    if let Some(ref m) = metadata {
+                        hard_links.insert(m, &path, &digest);
+                    }
+                    let event = JobEvent::Hashed {
+                        path,
+                        digest,
+                        size: bytes,
+                        linked_to: None,
+                    };
+                    context.finish(bytes, event);
+                }
+                // The interrupted file is neither done nor failed
+                Err(_) if context.cancelled() => return,
+                Err(e) => context.fail(Some(path), e.to_string()),
+            }
+        }
+    }
+
+    /// Digest of one file and the number of bytes read
+    fn hash(&self, context: &Context, path: &Path) -> Result<(String, u64)> {
+        let plain = !(self.paranoid || self.double_read || self.reads.uncached)
+            && self.reads.read_ahead == 0;
+        if let (Some(timeout), None, true) = (self.timeout, &self.key, plain) {
+            let digest = hash_file_with_timeout(path, self.algorithm, timeout)?;
+            let bytes = fs::metadata(path).map_or(0, |m| m.len());
+            return Ok((digest, bytes));
+        }
+
+        let hashing = || {
+            let hashing = Hashing::new()
+                .algorithm(self.algorithm)
+                .paranoid(self.paranoid);
+            match &self.key {
+                Some(key) => hashing.key(key.as_slice()),
+                None => hashing,
+            }
+        };
+        let (mut read, mut sent) = (0, 0);
+        let mut last_event: Option<Instant> = None;
+        let digest = hashing()
+            .progress(|bytes| {
+                read = bytes;
+                context.shared.lock().current_bytes = bytes;
+                let Some(interval) = self.progress_interval else {
+                    return;
+                };
+                let now = Instant::now();
+                if last_event.is_some_and(|last| now.duration_since(last) < interval) {
+                    return;
+                }
+                last_event = Some(now);
+                sent = bytes;
+                context.send(JobEvent::Progress {
+                    path: path.to_path_buf(),
+                    bytes,
+                });
+            })
+            .hash_reader(Cancellable {
+                inner: self.reads.open(path, false)?,
+                cancel: &context.shared.cancel,
+            })?;
+        if self.double_read {
+            let again = hashing().hash_reader(Cancellable {
+                inner: self.reads.open(path, true)?,
+                cancel: &context.shared.cancel,
+            })?;
+            check_reread(path, &digest, &again)?;
+        }
+        // Whoever follows progress sees the file's full size before it is done
+        if self.progress_interval.is_some() && sent != read {
+            context.send(JobEvent::Progress {
+                path: path.to_path_buf(),
+                bytes: read,
+            });
+        }
+        Ok((digest, read))
+    }
+}
+
+/// Verify checksum entries, several files at a time, reporting in list order
+#[derive(Debug)]
+pub struct VerifyJob {
+    source: VerifySource,
+    algorithm: Algorithm,
+    base: PathBuf,
+    jobs: usize,
+    cache: Option<PathBuf>,
+}
+
+/// Where a [`VerifyJob`] gets its entries
+enum VerifySource {
+    Entries(Vec<ChecksumEntry>),
+    Reader(Box<dyn BufRead + Send>),
+}
+
+impl fmt::Debug for VerifySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifySource::Entries(entries) => f.debug_tuple("Entries").field(entries).finish(),
+            VerifySource::Reader(_) => f.write_str("Reader"),
+        }
+    }
+}
+
+impl VerifyJob {
+    /// Check `entries` with SHA-256, relative paths resolved against the
+    /// current directory, on [`checksum::default_jobs`] threads
+    pub fn new(entries: Vec<ChecksumEntry>) -> Self {
+        Self::from_source(VerifySource::Entries(entries))
+    }
+
+    /// Check the entries of a checksum file as the job reads them from
+    /// `reader`, never holding the whole list (see
+    /// [`checksum::verify_reader`])
+    ///
+    /// Improperly formatted lines are reported as [`CheckStatus::Malformed`];
+    /// a read error ends the job with a [`JobEvent::Failed`] without a path.
+    pub fn from_reader<R: BufRead + Send + 'static>(reader: R) -> Self {
+        Self::from_source(VerifySource::Reader(Box::new(reader)))
+    }
+
+    fn from_source(source: VerifySource) -> Self {
+        Self {
+            source,
+            algorithm: Algorithm::Sha256,
+            base: PathBuf::from("."),
+            jobs: checksum::default_jobs(),
+            cache: None,
+        }
+    }
+
+    /// Hash algorithm the digests were made with
+    pub fn with_algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Directory that relative entry paths are resolved against
+    pub fn with_base<P: AsRef<Path>>(mut self, base: P) -> Self {
+        self.base = base.as_ref().to_path_buf();
+        self
+    }
+
+    /// Number of files hashed at a time (minimum 1)
+    pub fn with_jobs(mut self, jobs: usize) -> Self {
+        self.jobs = jobs.max(1);
+        self
+    }
+
+    /// Skip files that the [`StatCache`] file at `path` says are unchanged
+    /// since they last verified, reporting them as [`CheckStatus::Cached`]
+    ///
+    /// The file is loaded when the job starts and saved with the files it
+    /// hashed when it ends, even if cancelled; a cache that cannot be read
+    /// or written is reported as a [`JobEvent::Failed`] for its path.
+    pub fn with_cache<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.cache = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Start verifying on background threads
+    pub fn spawn(self) -> Job {
+        Job::spawn(move |context| self.run(context))
+    }
+
+    fn run(self, context: &Context) {
+        let mut cache = self.cache.as_ref().map(|path| {
+            StatCache::load(path).unwrap_or_else(|e| {
+                context.fail(Some(path.clone()), e.to_string());
+                StatCache::new()
+            })
+        });
+        let cancel = Some(&context.shared.cancel);
+        let report = |entry: ChecksumEntry, line: usize, status: &CheckStatus| {
+            {
+                let mut progress = context.shared.lock();
+                match status {
+                    CheckStatus::Ok | CheckStatus::Cached => progress.files_done += 1,
+                    CheckStatus::Malformed => {}
+                    _ => progress.files_failed += 1,
+                }
+            }
+            context.send(JobEvent::Checked {
+                entry,
+                line,
+                status: status.clone(),
+            });
+        };
+        match self.source {
+            VerifySource::Entries(entries) => {
+                let mut line = 0;
+                checksum::verify_entries_until(
+                    &entries,
+                    self.algorithm,
+                    &self.base,
+                    self.jobs,
+                    cancel,
+                    cache.as_mut(),
+                    |entry, status| {
+                        line += 1;
+                        report(entry.clone(), line, status);
+                    },
+                );
+            }
+            VerifySource::Reader(reader) => {
+                let checked = checksum::verify_reader_until(
+                    reader,
+                    self.algorithm,
+                    &self.base,
+                    self.jobs,
+                    cancel,
+                    cache.as_mut(),
+                    |entry, status| {
+                        let owned = ChecksumEntry {
+                            digest: entry.digest.to_string(),
+                            path: entry.path.to_string(),
+                        };
+                        report(owned, entry.line, status);
+                    },
+                );
+                if let Err(e) = checked {
+                    context.fail(None, e.to_string());
+                }
+            }
+        }
+        if let (Some(cache), Some(path)) = (cache, &self.cache) {
+            if let Err(e) = cache.save(path) {
+                context.fail(Some(path.clone()), e.to_string());
+            }
+        }
+    }
+}
+
+/// State shared between a [`Job`] and its thread
+struct Shared {
+    cancel: AtomicBool,
+    status: Mutex<Progress>,
+    start: Instant,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, Progress> {
+        // Counters stay consistent even if a holder panicked
+        self.status.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[derive(Debug, Default)]
+struct Progress {
+    /// `None` while running
+    state: Option<JobState>,
+    files_done: u64,
+    files_failed: u64,
+    bytes_done: u64,
+    current_bytes: u64,
+    current: Option<PathBuf>,
+    /// Frozen once the job is over
+    elapsed: Option<Duration>,
+}
+
+impl Progress {
+    fn snapshot(&self, start: Instant) -> JobStatus {
+        JobStatus {
+            state: self.state.unwrap_or(JobState::Running),
+            files_done: self.files_done,
+            files_failed: self.files_failed,
+            bytes_done: self.bytes_done + self.current_bytes,
+            current: self.current.clone(),
+            elapsed: self.elapsed.unwrap_or_else(|| start.elapsed()),
+        }
+    }
+}
+
+/// The job thread's side: updates the status and sends events
+struct Context {
+    sender: Sender<JobEvent>,
+    shared: Arc<Shared>,
+}
+
+impl Context {
+    fn cancelled(&self) -> bool {
+        self.shared.cancel.load(Ordering::Relaxed)
+    }
+
+    fn send(&self, event: JobEvent) {
+        // Nobody is listening once the job has been dropped
+        let _ = self.sender.send(event);
+    }
+
+    fn start(&self, path: &Path) {
+        {
+            let mut progress = self.shared.lock();
+            progress.current = Some(path.to_path_buf());
+            progress.current_bytes = 0;
+        }
+        self.send(JobEvent::Started {
+            path: path.to_path_buf(),
+        });
+    }
+
+    fn finish(&self, bytes: u64, event: JobEvent) {
+        {
+            let mut progress = self.shared.lock();
+            progress.files_done += 1;
+            progress.bytes_done += bytes;
+            progress.current_bytes = 0;
+            progress.current = None;
+        }
+        self.send(event);
+    }
+
+    fn fail(&self, path: Option<PathBuf>, message: String) {
+        {
+            let mut progress = self.shared.lock();
+            progress.files_failed += 1;
+            progress.current_bytes = 0;
+            progress.current = None;
+        }
+        self.send(JobEvent::Failed { path, message });
+    }
+}
+
+/// Reader that fails once `cancel` is set, so a large file does not
+/// delay cancellation
+pub(crate) struct Cancellable<'a, R> {
+    pub(crate) inner: R,
+    pub(crate) cancel: &'a AtomicBool,
+}
+
+impl<R: Read> Read for Cancellable<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.cancel.load(Ordering::Relaxed) {
+            return Err(io::Error::new(io::ErrorKind::Other, "cancelled"));
+        }
+        self.inner.read(buf)
+    }
+}
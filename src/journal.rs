@@ -0,0 +1,157 @@
+//! Journal of completed paths for resumable recursive scans
+//!
+//! A journal is a plain text file with one completed path per line. Each
+//! entry is flushed as soon as it is recorded, so an interrupted scan can be
+//! restarted and skip everything that was already processed.
+//!
+//! Paths are stored losslessly: backslashes, newlines and carriage returns
+//! are written as `\\`, `\n` and `\r`, and on Unix bytes that are not
+//! UTF-8 as `\xHH`.
+
+use crate::{HashError, Result};
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+/// Append-only record of paths that have been fully processed
+pub struct Journal {
+    file: File,
+    completed: HashSet<PathBuf>,
+}
+
+impl Journal {
+    /// Open a journal, creating it if it does not exist yet
+    ///
+    /// Existing entries are loaded so that [`Journal::is_completed`] reports
+    /// paths finished by earlier runs.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let mut completed = HashSet::new();
+
+        if path.exists() {
+            let reader = BufReader::new(File::open(path)?);
+            for line in reader.lines() {
+                let line = line?;
+                if !line.is_empty() {
+                    completed.insert(unescape(&line)?);
+                }
+            }
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self { file, completed })
+    }
+
+    /// Whether the path was recorded as completed
+    pub fn is_completed(&self, path: &Path) -> bool {
+        self.completed.contains(path)
+    }
+
+    /// Every path recorded as completed, in no particular order
+    pub fn completed(&self) -> impl Iterator<Item = &Path> {
+        self.completed.iter().map(PathBuf::as_path)
+    }
+
+    /// Number of completed paths in the journal
+    pub fn len(&self) -> usize {
+        self.completed.len()
+    }
+
+    /// Whether the journal has no completed paths
+    pub fn is_empty(&self) -> bool {
+        self.completed.is_empty()
+    }
+
+    /// Record a path as completed and flush it to disk immediately
+    pub fn record(&mut self, path: &Path) -> Result<()> {
+        writeln!(self.file, "{}", escape(path))?;
+        self.file.flush()?;
+        self.completed.insert(path.to_path_buf());
+        Ok(())
+    }
+}
+
+/// `path` as one journal line
+fn escape(path: &Path) -> String {
+    let bytes = path_bytes(path);
+    let mut bytes: &[u8] = &bytes;
+    let mut out = String::with_capacity(bytes.len());
+    loop {
+        let (text, invalid) = match std::str::from_utf8(bytes) {
+            Ok(text) => (text, &[][..]),
+            Err(err) => {
+                let (valid, invalid) = bytes.split_at(err.valid_up_to());
+                (std::str::from_utf8(valid).expect("valid prefix"), invalid)
+            }
+        };
+        for c in text.chars() {
+            match c {
+                '\\' => out.push_str("\\\\"),
+                '\n' => out.push_str("\\n"),
+                '\r' => out.push_str("\\r"),
+                _ => out.push(c),
+            }
+        }
+        match invalid.split_first() {
+            Some((byte, rest)) => {
+                let _ = write!(out, "\\x{:02x}", byte);
+                bytes = rest;
+            }
+            None => return out,
+        }
+    }
+}
+
+/// The path an [`escape`]d journal line stands for
+fn unescape(line: &str) -> Result<PathBuf> {
+    let malformed = || HashError::InvalidInput(format!("malformed journal line: {}", line));
+    let mut bytes = Vec::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next().ok_or_else(malformed)? {
+                '\\' => bytes.push(b'\\'),
+                'n' => bytes.push(b'\n'),
+                'r' => bytes.push(b'\r'),
+                'x' => {
+                    let hex: String = chars.by_ref().take(2).collect();
+                    if hex.len() != 2 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                        return Err(malformed());
+                    }
+                    bytes.push(u8::from_str_radix(&hex, 16).expect("two hex digits"));
+                }
+                _ => return Err(malformed()),
+            },
+            _ => bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+        }
+    }
+    Ok(path_from_bytes(&bytes))
+}
+
+#[cfg(unix)]
+fn path_bytes(path: &Path) -> Cow<'_, [u8]> {
+    use std::os::unix::ffi::OsStrExt;
+    Cow::Borrowed(path.as_os_str().as_bytes())
+}
+
+#[cfg(not(unix))]
+fn path_bytes(path: &Path) -> Cow<'_, [u8]> {
+    match path.to_string_lossy() {
+        Cow::Borrowed(text) => Cow::Borrowed(text.as_bytes()),
+        Cow::Owned(text) => Cow::Owned(text.into_bytes()),
+    }
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    use std::os::unix::ffi::OsStrExt;
+    PathBuf::from(std::ffi::OsStr::from_bytes(bytes))
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+}
@@ -0,0 +1,81 @@
+//! Keys derived from passphrases
+//!
+//! [`pbkdf2`] derives keys with PBKDF2 (RFC 8018) over HMAC-SHA-256 or
+//! HMAC-SHA-512, as implemented by the `pbkdf2` crate. The iteration count
+//! is what makes guessing a passphrase expensive; [`DEFAULT_ITERATIONS`]
+//! follows the current OWASP advice for PBKDF2-HMAC-SHA-256. A random salt,
+//! stored next to whatever the key protects, keeps one precomputed guess
+//! list from serving every file.
+//!
+//! ```
+//! use hashing::kdf::{generate_salt, pbkdf2};
+//! use hashing::Algorithm;
+//!
+//! let salt = generate_salt()?;
+//! let key = pbkdf2(b"correct horse", &salt, 10_000, Algorithm::Sha256, 32)?;
+//! assert_eq!(key.len(), 32);
+//! assert_eq!(key, pbkdf2(b"correct horse", &salt, 10_000, Algorithm::Sha256, 32)?);
+//! # Ok::<(), hashing::HashError>(())
+//! ```
+
+use crate::{Algorithm, HashError, Result};
+use pbkdf2::pbkdf2_hmac;
+use sha2::{Sha256, Sha512};
+
+/// Iterations for PBKDF2-HMAC-SHA-256 keys protecting stored files
+pub const DEFAULT_ITERATIONS: u32 = 600_000;
+
+/// Length of the salts [`generate_salt`] returns, in bytes (128 bits)
+pub const SALT_LEN: usize = 16;
+
+/// Derive `len` bytes from `passphrase` and `salt` with PBKDF2-HMAC over
+/// `algorithm`, which must be SHA-256 or SHA-512
+///
+/// # Examples
+///
+/// ```
+/// use hashing::kdf::pbkdf2;
+/// use hashing::Algorithm;
+///
+/// // RFC 7914, section 11
+/// let key = pbkdf2(b"passwd", b"salt", 1, Algorithm::Sha256, 16)?;
+/// assert_eq!(hex::encode(key), "55ac046e56e3089fec1691c22544b605");
+/// # Ok::<(), hashing::HashError>(())
+/// ```
+pub fn pbkdf2(
+    passphrase: &[u8],
+    salt: &[u8],
+    iterations: u32,
+    algorithm: Algorithm,
+    len: usize,
+) -> Result<Vec<u8>> {
+    if iterations == 0 {
+        return Err(HashError::InvalidInput(
+            "PBKDF2 needs at least one iteration".to_string(),
+        ));
+    }
+    let mut key = vec![0u8; len];
+    match algorithm {
+        Algorithm::Sha256 => pbkdf2_hmac::<Sha256>(passphrase, salt, iterations, &mut key),
+        Algorithm::Sha512 => pbkdf2_hmac::<Sha512>(passphrase, salt, iterations, &mut key),
+        _ => {
+            return Err(HashError::UnsupportedAlgorithm(format!(
+                "PBKDF2 is only offered with sha256 or sha512, not {}",
+                algorithm.name()
+            )))
+        }
+    }
+    Ok(key)
+}
+
+/// A fresh [`SALT_LEN`]-byte salt from the operating system's random source
+pub fn generate_salt() -> Result<[u8; SALT_LEN]> {
+    let mut salt = [0u8; SALT_LEN];
+    getrandom::getrandom(&mut salt).map_err(|e| {
+        HashError::Io(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            e.to_string(),
+        ))
+    })?;
+    Ok(salt)
+}
@@ -0,0 +1,98 @@
+//! Loading key material for keyed hashing (HMAC and friends)
+//!
+//! Keys can come from a hex literal, a file, an environment variable or
+//! stdin, so that secrets do not have to be typed on the command line.
+
+use crate::{HashError, Result};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Where to read key material from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeySource {
+    /// Hex-encoded key bytes
+    Hex(String),
+    /// Raw key bytes stored in a file (used verbatim, no newline trimming)
+    File(PathBuf),
+    /// The UTF-8 bytes of an environment variable
+    Env(String),
+    /// Raw key bytes read from standard input until EOF
+    Stdin,
+    /// The UTF-8 bytes of a literal string, such as a webhook secret
+    Text(String),
+}
+
+impl KeySource {
+    /// Read the key bytes from this source
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hashing::key::KeySource;
+    ///
+    /// let key = KeySource::Hex("00ff".to_string()).load().unwrap();
+    /// assert_eq!(key, vec![0x00, 0xff]);
+    /// ```
+    pub fn load(&self) -> Result<Vec<u8>> {
+        let key = match self {
+            KeySource::Hex(hex_key) => hex::decode(hex_key.trim())
+                .map_err(|e| HashError::InvalidInput(format!("invalid hex key: {}", e)))?,
+            KeySource::File(path) => fs::read(path)?,
+            KeySource::Env(var) => std::env::var(var)
+                .map_err(|_| {
+                    HashError::InvalidInput(format!("environment variable {} is not set", var))
+                })?
+                .into_bytes(),
+            KeySource::Stdin => {
+                let mut key = Vec::new();
+                std::io::stdin().read_to_end(&mut key)?;
+                key
+            }
+            KeySource::Text(text) => text.clone().into_bytes(),
+        };
+
+        if key.is_empty() {
+            return Err(HashError::InvalidInput("key is empty".to_string()));
+        }
+        Ok(key)
+    }
+
+    /// A warning if the key is stored somewhere other users can read it
+    ///
+    /// Only key files are checked: on Unix, a file readable by group or
+    /// others is reported. Other sources never produce a warning.
+    pub fn permission_warning(&self) -> Option<String> {
+        match self {
+            KeySource::File(path) => file_permission_warning(path),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(unix)]
+fn file_permission_warning(path: &Path) -> Option<String> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = fs::metadata(path).ok()?.permissions().mode();
+    if mode & 0o004 != 0 {
+        Some(format!(
+            "key file {} is world-readable (mode {:o}); run chmod 600 on it",
+            path.display(),
+            mode & 0o777
+        ))
+    } else if mode & 0o040 != 0 {
+        Some(format!(
+            "key file {} is group-readable (mode {:o})",
+            path.display(),
+            mode & 0o777
+        ))
+    } else {
+        None
+    }
+}
+
+#[cfg(not(unix))]
+fn file_permission_warning(_path: &Path) -> Option<String> {
+    None
+}
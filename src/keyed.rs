@@ -0,0 +1,149 @@
+//! The built-in keyed modes of BLAKE2 and BLAKE3
+//!
+//! BLAKE2 takes a key as a parameter of the hash (RFC 7693, section 2.5)
+//! and BLAKE3 has a `keyed_hash` mode. Either is a MAC in a single pass,
+//! where [`crate::hmac`] hashes twice; the digests differ from HMAC-BLAKE2
+//! and from the plain hashes. [`crate::Hashing::blake_key`] selects them.
+//! BLAKE3 also derives keys from a context string and key material in its
+//! `derive_key` mode, selected with [`crate::Hashing::derive_key`].
+//!
+//! ```
+//! use hashing::{keyed, Algorithm, Hashing};
+//!
+//! assert!(keyed::supports(Algorithm::Blake3));
+//! assert_eq!(keyed::key_sizes(Algorithm::Blake3), Some(32..=32));
+//!
+//! let mac = Hashing::new()
+//!     .algorithm(Algorithm::Blake3)
+//!     .blake_key([7u8; 32])
+//!     .hash_string("message")?;
+//! assert_eq!(mac, blake3::keyed_hash(&[7u8; 32], b"message").to_hex().as_str());
+//! # Ok::<(), hashing::HashError>(())
+//! ```
+
+use crate::builder::for_each_chunk;
+use crate::{Algorithm, HashError, Result};
+use blake2::digest::core_api::{Block, Buffer, UpdateCore, VariableOutputCore};
+use blake2::{Blake2bVarCore, Blake2sVarCore};
+use std::io::Read;
+use std::ops::RangeInclusive;
+
+/// Whether `algorithm` has a keyed mode of its own
+pub fn supports(algorithm: Algorithm) -> bool {
+    key_sizes(algorithm).is_some()
+}
+
+/// Key lengths in bytes that `algorithm`'s keyed mode accepts, or `None`
+/// without one
+///
+/// BLAKE2b takes up to 64 bytes and BLAKE2s up to 32; BLAKE3 keys are
+/// exactly 32 bytes, so derive one (see [`crate::kdf`]) from anything else.
+pub fn key_sizes(algorithm: Algorithm) -> Option<RangeInclusive<usize>> {
+    match algorithm {
+        Algorithm::Blake2b512 => Some(1..=64),
+        Algorithm::Blake2s256 => Some(1..=32),
+        Algorithm::Blake3 => Some(32..=32),
+        _ => None,
+    }
+}
+
+/// Fail unless `key` suits `algorithm`'s keyed mode
+pub(crate) fn check_key(algorithm: Algorithm, key: &[u8]) -> Result<()> {
+    let Some(sizes) = key_sizes(algorithm) else {
+        return Err(HashError::UnsupportedAlgorithm(format!(
+            "{} has no keyed mode; use an HMAC key instead",
+            algorithm.name()
+        )));
+    };
+    if sizes.contains(&key.len()) {
+        return Ok(());
+    }
+    let expected = if sizes.start() == sizes.end() {
+        format!("exactly {}", sizes.start())
+    } else {
+        format!("{} to {}", sizes.start(), sizes.end())
+    };
+    Err(HashError::InvalidInput(format!(
+        "{} keys are {} bytes long, got {}",
+        algorithm.name(),
+        expected,
+        key.len()
+    )))
+}
+
+/// Fail unless `algorithm` has a key derivation mode, which only BLAKE3 has
+pub(crate) fn check_derive(algorithm: Algorithm) -> Result<()> {
+    match algorithm {
+        Algorithm::Blake3 => Ok(()),
+        _ => Err(HashError::UnsupportedAlgorithm(format!(
+            "{} has no key derivation mode; only blake3 does",
+            algorithm.name()
+        ))),
+    }
+}
+
+/// Keyed BLAKE2 of everything `$reader` yields into `$digest`, at its length
+///
+/// `blake2::Blake2bMac` fixes the length at compile time, so this drives
+/// the core directly the way it does: the key, zero-padded to a block, is
+/// the first block, and the buffer holds back the last block for the
+/// finalization flag.
+macro_rules! keyed_blake2 {
+    ($core:ty, $key:expr, $reader:expr, $buffer_size:expr, $digest:expr) => {{
+        let len = $digest.len();
+        let mut core = <$core>::new_with_params(&[], &[], $key.len(), len);
+        let mut buffer = Buffer::<$core>::default();
+        let mut block = Block::<$core>::default();
+        block[..$key.len()].copy_from_slice($key);
+        buffer.digest_blocks(&block, |blocks| core.update_blocks(blocks));
+        for_each_chunk($reader, $buffer_size, |chunk| {
+            buffer.digest_blocks(chunk, |blocks| core.update_blocks(blocks))
+        })?;
+        let mut full = Default::default();
+        core.finalize_variable_core(&mut buffer, &mut full);
+        $digest.copy_from_slice(&full[..len]);
+    }};
+}
+
+/// Raw `len`-byte keyed digest of everything `reader` yields, for a key
+/// that passed [`check_key`] and a length in [`Algorithm::output_sizes`]
+pub(crate) fn digest_reader<R: Read>(
+    key: &[u8],
+    reader: R,
+    algorithm: Algorithm,
+    len: usize,
+    buffer_size: usize,
+) -> Result<Vec<u8>> {
+    let mut digest = vec![0u8; len];
+    match algorithm {
+        Algorithm::Blake2b512 => keyed_blake2!(Blake2bVarCore, key, reader, buffer_size, digest),
+        Algorithm::Blake2s256 => keyed_blake2!(Blake2sVarCore, key, reader, buffer_size, digest),
+        Algorithm::Blake3 => {
+            let key: &[u8; 32] = key.try_into().expect("checked BLAKE3 key");
+            let mut hasher = blake3::Hasher::new_keyed(key);
+            for_each_chunk(reader, buffer_size, |chunk| {
+                hasher.update(chunk);
+            })?;
+            hasher.finalize_xof().fill(&mut digest);
+        }
+        _ => unreachable!("{} has no keyed mode", algorithm.name()),
+    }
+    Ok(digest)
+}
+
+/// Raw `len`-byte BLAKE3 key derived under `context` from everything
+/// `reader` yields as key material
+pub(crate) fn derive_reader<R: Read>(
+    context: &str,
+    reader: R,
+    len: usize,
+    buffer_size: usize,
+) -> Result<Vec<u8>> {
+    let mut hasher = blake3::Hasher::new_derive_key(context);
+    for_each_chunk(reader, buffer_size, |chunk| {
+        hasher.update(chunk);
+    })?;
+    let mut key = vec![0u8; len];
+    hasher.finalize_xof().fill(&mut key);
+    Ok(key)
+}
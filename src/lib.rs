@@ -19,10 +19,17 @@
 //! println!("SHA-256: {}", digest);
 //! ```
 
+use std::collections::HashMap;
+use std::fmt;
 use std::fs::File;
-use std::io::{self, BufReader, Read};
-use std::path::Path;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::ops::RangeInclusive;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
 use thiserror::Error;
 
 // Re-export digest traits for library users
@@ -30,6 +37,86 @@ pub use blake2::Digest as Blake2Digest;
 pub use md5::Digest as Md5Digest;
 pub use sha2::Digest as Sha2Digest;
 pub use sha3::Digest as Sha3Digest;
+pub use builder::Hashing;
+pub use combine::hash_set;
+pub use digest::Digest;
+pub use multi::MultiHasher;
+pub use tree::hash_dir;
+#[cfg(feature = "async")]
+pub use async_hash::{hash_file_async, hash_reader_async, AsyncHasher};
+
+pub mod alias;
+#[cfg(feature = "async")]
+pub mod async_hash;
+pub mod attest;
+pub mod auth;
+pub mod aws;
+pub mod builder;
+pub mod buildinfo;
+pub mod cachekey;
+pub mod chain;
+pub mod cloud;
+#[cfg(feature = "serde-hash")]
+pub mod canonical;
+pub mod checksum;
+pub mod chunk;
+pub mod combine;
+pub mod commitment;
+pub mod config;
+pub mod convert;
+pub mod delta;
+pub mod digest;
+pub mod dkim;
+pub mod ethereum;
+#[cfg(feature = "executable")]
+pub mod executable;
+pub mod fixed;
+#[cfg(feature = "git")]
+pub mod git;
+pub mod hmac;
+pub mod http_digest;
+pub mod inspect;
+#[cfg(feature = "archive")]
+pub mod jar;
+pub mod job;
+pub mod journal;
+pub mod kdf;
+pub mod key;
+pub mod keyed;
+pub mod manifest;
+#[cfg(feature = "mmap")]
+pub mod mmap;
+pub mod multi;
+pub mod naming;
+pub mod newline;
+pub mod nix;
+mod noncrypto;
+#[cfg(feature = "archive")]
+pub mod package;
+pub mod pagecache;
+#[cfg(feature = "perf")]
+pub mod perf;
+pub mod pins;
+pub mod pkgmanifest;
+pub mod plugin;
+pub mod probabilistic;
+pub mod progress;
+pub mod pseudonym;
+pub mod release;
+pub mod ring;
+pub mod sbom;
+pub mod seal;
+pub mod sigstore;
+pub mod similarity;
+pub mod ssh;
+pub mod stamp;
+pub mod statcache;
+pub mod tabular;
+pub mod template;
+pub mod tree;
+pub mod trust;
+pub mod units;
+pub mod walk;
 
 /// Errors that can occur during hashing operations
 #[derive(Error, Debug)]
@@ -45,13 +132,19 @@ pub enum HashError {
     
     #[error("Export error: {0}")]
     ExportError(String),
+    
+    #[error("Timed out after {0:?}")]
+    Timeout(Duration),
+
+    #[error("Self-check failed: {0}")]
+    SelfCheck(String),
 }
 
 /// Result type for hashing operations
 pub type Result<T> = std::result::Result<T, HashError>;
 
 /// Supported hashing algorithms
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
 pub enum Algorithm {
     // MD5
     Md5,
@@ -61,6 +154,7 @@ pub enum Algorithm {
     
     // SHA-2 family
     Sha224,
+    #[default]
     Sha256,
     Sha384,
     Sha512,
@@ -72,6 +166,8 @@ pub enum Algorithm {
     Sha3_256,
     Sha3_384,
     Sha3_512,
+    Shake128,
+    Shake256,
     
     // BLAKE2
     Blake2b512,
@@ -85,32 +181,57 @@ pub enum Algorithm {
     Keccak256,
     Keccak384,
     Keccak512,
+    
+    // Composites used by Bitcoin
+    Sha256d,
+    Hash160,
+
+    // Non-cryptographic checksums, for integrity checks and cache keys
+    Xxh32,
+    Xxh64,
+    Xxh3,
+    Xxh128,
+    Crc32,
+    Crc32c,
 }
 
+/// Every algorithm, in the order used for listings and `--all-algorithms`
+const ALL_ALGORITHMS: &[Algorithm] = &[
+    Algorithm::Md5,
+    Algorithm::Sha1,
+    Algorithm::Sha224,
+    Algorithm::Sha256,
+    Algorithm::Sha384,
+    Algorithm::Sha512,
+    Algorithm::Sha512_224,
+    Algorithm::Sha512_256,
+    Algorithm::Sha3_224,
+    Algorithm::Sha3_256,
+    Algorithm::Sha3_384,
+    Algorithm::Sha3_512,
+    Algorithm::Shake128,
+    Algorithm::Shake256,
+    Algorithm::Blake2b512,
+    Algorithm::Blake2s256,
+    Algorithm::Blake3,
+    Algorithm::Keccak224,
+    Algorithm::Keccak256,
+    Algorithm::Keccak384,
+    Algorithm::Keccak512,
+    Algorithm::Sha256d,
+    Algorithm::Hash160,
+    Algorithm::Xxh32,
+    Algorithm::Xxh64,
+    Algorithm::Xxh3,
+    Algorithm::Xxh128,
+    Algorithm::Crc32,
+    Algorithm::Crc32c,
+];
+
 impl Algorithm {
     /// Get all available algorithms
     pub fn all() -> Vec<Algorithm> {
-        vec![
-            Algorithm::Md5,
-            Algorithm::Sha1,
-            Algorithm::Sha224,
-            Algorithm::Sha256,
-            Algorithm::Sha384,
-            Algorithm::Sha512,
-            Algorithm::Sha512_224,
-            Algorithm::Sha512_256,
-            Algorithm::Sha3_224,
-            Algorithm::Sha3_256,
-            Algorithm::Sha3_384,
-            Algorithm::Sha3_512,
-            Algorithm::Blake2b512,
-            Algorithm::Blake2s256,
-            Algorithm::Blake3,
-            Algorithm::Keccak224,
-            Algorithm::Keccak256,
-            Algorithm::Keccak384,
-            Algorithm::Keccak512,
-        ]
+        ALL_ALGORITHMS.to_vec()
     }
     
     /// Get algorithm name as string
@@ -128,6 +249,8 @@ impl Algorithm {
             Algorithm::Sha3_256 => "sha3-256",
             Algorithm::Sha3_384 => "sha3-384",
             Algorithm::Sha3_512 => "sha3-512",
+            Algorithm::Shake128 => "shake128",
+            Algorithm::Shake256 => "shake256",
             Algorithm::Blake2b512 => "blake2b",
             Algorithm::Blake2s256 => "blake2s",
             Algorithm::Blake3 => "blake3",
@@ -135,8 +258,218 @@ impl Algorithm {
             Algorithm::Keccak256 => "keccak256",
             Algorithm::Keccak384 => "keccak384",
             Algorithm::Keccak512 => "keccak512",
+            Algorithm::Sha256d => "sha256d",
+            Algorithm::Hash160 => "hash160",
+            Algorithm::Xxh32 => "xxh32",
+            Algorithm::Xxh64 => "xxh64",
+            Algorithm::Xxh3 => "xxh3",
+            Algorithm::Xxh128 => "xxh128",
+            Algorithm::Crc32 => "crc32",
+            Algorithm::Crc32c => "crc32c",
+        }
+    }
+}
+
+impl Algorithm {
+    /// Human-readable name, e.g. `SHA-256`
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Algorithm::Md5 => "MD5",
+            Algorithm::Sha1 => "SHA-1",
+            Algorithm::Sha224 => "SHA-224",
+            Algorithm::Sha256 => "SHA-256",
+            Algorithm::Sha384 => "SHA-384",
+            Algorithm::Sha512 => "SHA-512",
+            Algorithm::Sha512_224 => "SHA-512/224",
+            Algorithm::Sha512_256 => "SHA-512/256",
+            Algorithm::Sha3_224 => "SHA3-224",
+            Algorithm::Sha3_256 => "SHA3-256",
+            Algorithm::Sha3_384 => "SHA3-384",
+            Algorithm::Sha3_512 => "SHA3-512",
+            Algorithm::Shake128 => "SHAKE128",
+            Algorithm::Shake256 => "SHAKE256",
+            Algorithm::Blake2b512 => "BLAKE2b",
+            Algorithm::Blake2s256 => "BLAKE2s",
+            Algorithm::Blake3 => "BLAKE3",
+            Algorithm::Keccak224 => "Keccak-224",
+            Algorithm::Keccak256 => "Keccak-256",
+            Algorithm::Keccak384 => "Keccak-384",
+            Algorithm::Keccak512 => "Keccak-512",
+            Algorithm::Sha256d => "SHA-256d",
+            Algorithm::Hash160 => "HASH160",
+            Algorithm::Xxh32 => "XXH32",
+            Algorithm::Xxh64 => "XXH64",
+            Algorithm::Xxh3 => "XXH3-64",
+            Algorithm::Xxh128 => "XXH3-128",
+            Algorithm::Crc32 => "CRC-32",
+            Algorithm::Crc32c => "CRC-32C",
+        }
+    }
+
+    /// Digest length in bytes
+    pub fn output_size(&self) -> usize {
+        match self {
+            Algorithm::Md5 => 16,
+            Algorithm::Sha1 | Algorithm::Hash160 => 20,
+            Algorithm::Sha224 | Algorithm::Sha512_224 | Algorithm::Sha3_224 => 28,
+            Algorithm::Keccak224 => 28,
+            Algorithm::Sha256 | Algorithm::Sha512_256 | Algorithm::Sha3_256 => 32,
+            Algorithm::Blake2s256 | Algorithm::Blake3 | Algorithm::Keccak256 => 32,
+            Algorithm::Sha256d | Algorithm::Shake128 => 32,
+            Algorithm::Sha384 | Algorithm::Sha3_384 | Algorithm::Keccak384 => 48,
+            Algorithm::Sha512 | Algorithm::Sha3_512 | Algorithm::Keccak512 => 64,
+            Algorithm::Blake2b512 | Algorithm::Shake256 => 64,
+            Algorithm::Xxh32 | Algorithm::Crc32 | Algorithm::Crc32c => 4,
+            Algorithm::Xxh64 | Algorithm::Xxh3 => 8,
+            Algorithm::Xxh128 => 16,
+        }
+    }
+
+    /// Digest lengths in bytes the algorithm can produce
+    ///
+    /// A single length for most algorithms. BLAKE2 takes any length up to
+    /// its full size, and BLAKE3 and the SHAKE functions are extendable
+    /// output functions with no upper limit; [`crate::Hashing::output_size`]
+    /// picks one. [`Algorithm::output_size`] is the length used otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hashing::Algorithm;
+    ///
+    /// assert_eq!(Algorithm::Sha256.output_sizes(), 32..=32);
+    /// assert_eq!(Algorithm::Blake2b512.output_sizes(), 1..=64);
+    /// assert!(Algorithm::Blake3.output_sizes().contains(&64));
+    /// ```
+    pub fn output_sizes(&self) -> RangeInclusive<usize> {
+        match self {
+            Algorithm::Blake2b512 | Algorithm::Blake2s256 => 1..=self.output_size(),
+            Algorithm::Blake3 | Algorithm::Shake128 | Algorithm::Shake256 => 1..=usize::MAX,
+            _ => self.output_size()..=self.output_size(),
+        }
+    }
+
+    /// One-line description of digest size and intended use
+    pub fn description(&self) -> &'static str {
+        match self {
+            Algorithm::Md5 => "128-bit (insecure, legacy use only)",
+            Algorithm::Sha1 => "160-bit (insecure, legacy use only)",
+            Algorithm::Sha224 => "224-bit SHA-2",
+            Algorithm::Sha256 => "256-bit SHA-2 (recommended)",
+            Algorithm::Sha384 => "384-bit SHA-2",
+            Algorithm::Sha512 => "512-bit SHA-2",
+            Algorithm::Sha512_224 => "224-bit SHA-2 variant",
+            Algorithm::Sha512_256 => "256-bit SHA-2 variant",
+            Algorithm::Sha3_224 => "224-bit SHA-3",
+            Algorithm::Sha3_256 => "256-bit SHA-3",
+            Algorithm::Sha3_384 => "384-bit SHA-3",
+            Algorithm::Sha3_512 => "512-bit SHA-3",
+            Algorithm::Shake128 => "SHA-3 extendable output, 256 bits by default",
+            Algorithm::Shake256 => "SHA-3 extendable output, 512 bits by default",
+            Algorithm::Blake2b512 => "512-bit BLAKE2b",
+            Algorithm::Blake2s256 => "256-bit BLAKE2s",
+            Algorithm::Blake3 => "256-bit BLAKE3 (fast, modern)",
+            Algorithm::Keccak224 => "224-bit Keccak",
+            Algorithm::Keccak256 => "256-bit Keccak",
+            Algorithm::Keccak384 => "384-bit Keccak",
+            Algorithm::Keccak512 => "512-bit Keccak",
+            Algorithm::Sha256d => "256-bit SHA-256 applied twice (Bitcoin)",
+            Algorithm::Hash160 => "160-bit RIPEMD-160 of SHA-256 (Bitcoin)",
+            Algorithm::Xxh32 => "32-bit xxHash (non-cryptographic)",
+            Algorithm::Xxh64 => "64-bit xxHash (non-cryptographic)",
+            Algorithm::Xxh3 => "64-bit XXH3 (non-cryptographic, fastest)",
+            Algorithm::Xxh128 => "128-bit XXH3 (non-cryptographic)",
+            Algorithm::Crc32 => "32-bit CRC (zip, gzip, SFV; non-cryptographic)",
+            Algorithm::Crc32c => "32-bit Castagnoli CRC (iSCSI, ext4; non-cryptographic)",
+        }
+    }
+
+    /// Alternative spellings accepted besides [`Algorithm::name`]
+    ///
+    /// Parsing also ignores case, `-`, `_` and `/`, so these only list the
+    /// forms worth showing in help output and shell completions.
+    pub fn aliases(&self) -> &'static [&'static str] {
+        alias::builtin_aliases(*self)
+    }
+
+    /// The faster of BLAKE3 and SHA-256 on the running machine
+    ///
+    /// SHA-256 wins where the CPU computes it in hardware (SHA-NI, the ARMv8
+    /// SHA-2 instructions) and BLAKE3 has no AVX2 to answer with; BLAKE3
+    /// wins everywhere else. Another machine may pick the other one, so
+    /// store the algorithm along with the digest.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hashing::Algorithm;
+    ///
+    /// let algorithm = Algorithm::fastest_secure();
+    /// assert!(matches!(algorithm, Algorithm::Blake3 | Algorithm::Sha256));
+    /// ```
+    pub fn fastest_secure() -> Algorithm {
+        let cpu = buildinfo::cpu_features();
+        let has = |feature: &str| cpu.contains(&feature);
+        let sha256_in_hardware = (has("sha") && has("sse4.1")) || has("sha2");
+        if sha256_in_hardware && !has("avx2") {
+            Algorithm::Sha256
+        } else {
+            Algorithm::Blake3
+        }
+    }
+
+    /// Family the algorithm belongs to
+    pub fn family(&self) -> AlgorithmFamily {
+        match self {
+            Algorithm::Md5 => AlgorithmFamily::Md5,
+            Algorithm::Sha1 => AlgorithmFamily::Sha1,
+            Algorithm::Sha224
+            | Algorithm::Sha256
+            | Algorithm::Sha384
+            | Algorithm::Sha512
+            | Algorithm::Sha512_224
+            | Algorithm::Sha512_256 => AlgorithmFamily::Sha2,
+            Algorithm::Sha3_224
+            | Algorithm::Sha3_256
+            | Algorithm::Sha3_384
+            | Algorithm::Sha3_512
+            | Algorithm::Shake128
+            | Algorithm::Shake256 => AlgorithmFamily::Sha3,
+            Algorithm::Blake2b512 | Algorithm::Blake2s256 | Algorithm::Blake3 => {
+                AlgorithmFamily::Blake
+            }
+            Algorithm::Keccak224
+            | Algorithm::Keccak256
+            | Algorithm::Keccak384
+            | Algorithm::Keccak512 => AlgorithmFamily::Keccak,
+            Algorithm::Sha256d | Algorithm::Hash160 => AlgorithmFamily::Bitcoin,
+            Algorithm::Xxh32
+            | Algorithm::Xxh64
+            | Algorithm::Xxh3
+            | Algorithm::Xxh128
+            | Algorithm::Crc32
+            | Algorithm::Crc32c => AlgorithmFamily::NonCryptographic,
         }
     }
+
+    /// Whether the algorithm is meant to resist deliberate collisions
+    ///
+    /// xxHash and the CRCs catch accidental corruption and make good cache
+    /// and deduplication keys for trusted data, but anyone can craft two
+    /// inputs with the same checksum. MD5 and SHA-1 are broken yet still
+    /// count as cryptographic here.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hashing::Algorithm;
+    ///
+    /// assert!(Algorithm::Sha256.is_cryptographic());
+    /// assert!(!Algorithm::Xxh3.is_cryptographic());
+    /// ```
+    pub fn is_cryptographic(&self) -> bool {
+        self.family() != AlgorithmFamily::NonCryptographic
+    }
 }
 
 /// Implement FromStr trait for Algorithm
@@ -144,29 +477,331 @@ impl FromStr for Algorithm {
     type Err = HashError;
 
     fn from_str(s: &str) -> Result<Self> {
-        match s.to_lowercase().as_str() {
-            "md5" => Ok(Algorithm::Md5),
-            "sha1" => Ok(Algorithm::Sha1),
-            "sha224" => Ok(Algorithm::Sha224),
-            "sha256" => Ok(Algorithm::Sha256),
-            "sha384" => Ok(Algorithm::Sha384),
-            "sha512" => Ok(Algorithm::Sha512),
-            "sha512-224" | "sha512_224" => Ok(Algorithm::Sha512_224),
-            "sha512-256" | "sha512_256" => Ok(Algorithm::Sha512_256),
-            "sha3-224" | "sha3_224" => Ok(Algorithm::Sha3_224),
-            "sha3-256" | "sha3_256" => Ok(Algorithm::Sha3_256),
-            "sha3-384" | "sha3_384" => Ok(Algorithm::Sha3_384),
-            "sha3-512" | "sha3_512" => Ok(Algorithm::Sha3_512),
-            "blake2b" | "blake2b512" => Ok(Algorithm::Blake2b512),
-            "blake2s" | "blake2s256" => Ok(Algorithm::Blake2s256),
-            "blake3" => Ok(Algorithm::Blake3),
-            "keccak224" => Ok(Algorithm::Keccak224),
-            "keccak256" => Ok(Algorithm::Keccak256),
-            "keccak384" => Ok(Algorithm::Keccak384),
-            "keccak512" => Ok(Algorithm::Keccak512),
-            _ => Err(HashError::UnsupportedAlgorithm(s.to_string())),
+        alias::lookup_builtin(s).ok_or_else(|| HashError::UnsupportedAlgorithm(s.to_string()))
+    }
+}
+
+impl TryFrom<&str> for Algorithm {
+    type Error = HashError;
+
+    fn try_from(s: &str) -> Result<Self> {
+        s.parse()
+    }
+}
+
+/// Lets CLIs take an `Algorithm` argument directly, with possible values,
+/// help text and shell completions derived from [`Algorithm::all`]
+impl clap::ValueEnum for Algorithm {
+    fn value_variants<'a>() -> &'a [Self] {
+        ALL_ALGORITHMS
+    }
+
+    fn to_possible_value(&self) -> Option<clap::builder::PossibleValue> {
+        Some(
+            clap::builder::PossibleValue::new(self.name())
+                .help(self.description())
+                .aliases(self.aliases()),
+        )
+    }
+}
+
+/// Groups of related algorithms, in listing order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum AlgorithmFamily {
+    Md5,
+    Sha1,
+    Sha2,
+    Sha3,
+    /// BLAKE2b, BLAKE2s and BLAKE3
+    Blake,
+    Keccak,
+    /// SHA-256d and HASH160
+    Bitcoin,
+    /// xxHash and the CRC-32s
+    NonCryptographic,
+}
+
+const ALL_FAMILIES: &[AlgorithmFamily] = &[
+    AlgorithmFamily::Md5,
+    AlgorithmFamily::Sha1,
+    AlgorithmFamily::Sha2,
+    AlgorithmFamily::Sha3,
+    AlgorithmFamily::Blake,
+    AlgorithmFamily::Keccak,
+    AlgorithmFamily::Bitcoin,
+    AlgorithmFamily::NonCryptographic,
+];
+
+impl AlgorithmFamily {
+    /// Every family, in listing order
+    pub fn all() -> Vec<AlgorithmFamily> {
+        ALL_FAMILIES.to_vec()
+    }
+
+    /// Name used on the command line, e.g. `sha2`
+    pub fn name(&self) -> &'static str {
+        match self {
+            AlgorithmFamily::Md5 => "md5",
+            AlgorithmFamily::Sha1 => "sha1",
+            AlgorithmFamily::Sha2 => "sha2",
+            AlgorithmFamily::Sha3 => "sha3",
+            AlgorithmFamily::Blake => "blake",
+            AlgorithmFamily::Keccak => "keccak",
+            AlgorithmFamily::Bitcoin => "bitcoin",
+            AlgorithmFamily::NonCryptographic => "noncryptographic",
+        }
+    }
+
+    /// Human-readable name, e.g. `SHA-2`
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            AlgorithmFamily::Md5 => "MD5",
+            AlgorithmFamily::Sha1 => "SHA-1",
+            AlgorithmFamily::Sha2 => "SHA-2",
+            AlgorithmFamily::Sha3 => "SHA-3",
+            AlgorithmFamily::Blake => "BLAKE",
+            AlgorithmFamily::Keccak => "Keccak",
+            AlgorithmFamily::Bitcoin => "Bitcoin",
+            AlgorithmFamily::NonCryptographic => "Non-cryptographic",
         }
     }
+
+    /// The family's algorithms, in the order of [`Algorithm::all`]
+    pub fn algorithms(&self) -> Vec<Algorithm> {
+        ALL_ALGORITHMS
+            .iter()
+            .copied()
+            .filter(|algorithm| algorithm.family() == *self)
+            .collect()
+    }
+}
+
+impl fmt::Display for AlgorithmFamily {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for AlgorithmFamily {
+    type Err = HashError;
+
+    /// Parse a family name, ignoring case and `-` (`SHA-2` is `sha2`)
+    fn from_str(s: &str) -> Result<Self> {
+        let wanted = s.to_ascii_lowercase().replace('-', "");
+        ALL_FAMILIES
+            .iter()
+            .copied()
+            .find(|family| family.name() == wanted)
+            .ok_or_else(|| {
+                let names: Vec<&str> = ALL_FAMILIES.iter().map(|family| family.name()).collect();
+                HashError::InvalidInput(format!(
+                    "unknown algorithm family: {} (expected {})",
+                    s,
+                    names.join(", ")
+                ))
+            })
+    }
+}
+
+/// Lets CLIs take an `AlgorithmFamily` argument directly
+impl clap::ValueEnum for AlgorithmFamily {
+    fn value_variants<'a>() -> &'a [Self] {
+        ALL_FAMILIES
+    }
+
+    fn to_possible_value(&self) -> Option<clap::builder::PossibleValue> {
+        let value = clap::builder::PossibleValue::new(self.name()).help(self.display_name());
+        // Accept the hyphenated spellings `FromStr` does, e.g. `sha-2`
+        Some(match self {
+            AlgorithmFamily::Sha1 => value.alias("sha-1"),
+            AlgorithmFamily::Sha2 => value.alias("sha-2"),
+            AlgorithmFamily::Sha3 => value.alias("sha-3"),
+            AlgorithmFamily::NonCryptographic => value.alias("non-cryptographic"),
+            _ => value,
+        })
+    }
+}
+
+/// Text encoding applied to raw digest bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Encoding {
+    /// Lowercase hexadecimal (the default everywhere)
+    #[default]
+    Hex,
+    /// Uppercase hexadecimal
+    HexUpper,
+    /// Standard Base64 with padding (RFC 4648)
+    Base64,
+    /// URL- and filename-safe Base64 without padding (RFC 4648 §5), as in
+    /// JWTs and `ni:` URIs
+    Base64Url,
+    /// Uppercase Base32 with padding (RFC 4648), as `base32` prints it
+    Base32,
+    /// Base58 with the Bitcoin alphabet
+    Base58,
+    /// Base58 with a 4-byte double-SHA-256 checksum appended, as in
+    /// Bitcoin addresses and WIF keys
+    Base58Check,
+    /// The digest bytes themselves, for files and pipes; not text, so only
+    /// [`Encoding::encode_bytes`] and [`hash_bytes_with_encoding`] produce it
+    /// and [`Encoding::encode`] refuses it
+    Binary,
+}
+
+impl Encoding {
+    /// Encode raw digest bytes
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hashing::Encoding;
+    ///
+    /// assert_eq!(Encoding::Hex.encode(&[0xab, 0xcd])?, "abcd");
+    /// assert_eq!(Encoding::Base64.encode(&[0xab, 0xcd])?, "q80=");
+    /// assert_eq!(Encoding::Base58.encode(&[0x00, 0xab, 0xcd])?, "1E5J");
+    /// assert_eq!(Encoding::Base32.encode(&[0xab, 0xcd])?, "VPGQ====");
+    /// assert!(Encoding::Binary.encode(&[0xab, 0xcd]).is_err());
+    /// # Ok::<(), hashing::HashError>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// [`HashError::InvalidInput`] for [`Encoding::Binary`], which has no
+    /// text form; see [`Encoding::is_text`] and [`Encoding::encode_bytes`].
+    pub fn encode(&self, bytes: &[u8]) -> Result<String> {
+        self.encode_text(bytes).ok_or_else(|| {
+            HashError::InvalidInput("binary digests have no text encoding".to_string())
+        })
+    }
+
+    /// The text form of `bytes`, or `None` for [`Encoding::Binary`]
+    fn encode_text(&self, bytes: &[u8]) -> Option<String> {
+        use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+        use base64::Engine;
+
+        Some(match self {
+            Encoding::Hex => hex::encode(bytes),
+            Encoding::HexUpper => hex::encode_upper(bytes),
+            Encoding::Base64 => STANDARD.encode(bytes),
+            Encoding::Base64Url => URL_SAFE_NO_PAD.encode(bytes),
+            Encoding::Base32 => {
+                let mut text = naming::base32_encode(bytes).to_ascii_uppercase();
+                while text.len() % 8 != 0 {
+                    text.push('=');
+                }
+                text
+            }
+            Encoding::Base58 => bs58::encode(bytes).into_string(),
+            Encoding::Base58Check => {
+                let checksum = fixed::hash_bytes_fixed::<fixed::Sha256d>(bytes);
+                let mut payload = Vec::with_capacity(bytes.len() + 4);
+                payload.extend_from_slice(bytes);
+                payload.extend_from_slice(&checksum[..4]);
+                bs58::encode(payload).into_string()
+            }
+            Encoding::Binary => return None,
+        })
+    }
+
+    /// Encode raw digest bytes as the bytes to write out: the text of
+    /// [`Encoding::encode`], or the bytes unchanged for [`Encoding::Binary`]
+    pub fn encode_bytes(&self, bytes: &[u8]) -> Vec<u8> {
+        match self.encode_text(bytes) {
+            Some(text) => text.into_bytes(),
+            None => bytes.to_vec(),
+        }
+    }
+
+    /// Whether the encoding produces text, which all but
+    /// [`Encoding::Binary`] do
+    pub fn is_text(&self) -> bool {
+        *self != Encoding::Binary
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = HashError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "hex" => Ok(Encoding::Hex),
+            "HEX" | "hex-upper" => Ok(Encoding::HexUpper),
+            "base64" => Ok(Encoding::Base64),
+            "base64url" => Ok(Encoding::Base64Url),
+            "base32" => Ok(Encoding::Base32),
+            "base58" => Ok(Encoding::Base58),
+            "base58check" => Ok(Encoding::Base58Check),
+            "binary" | "raw" => Ok(Encoding::Binary),
+            _ => Err(HashError::InvalidInput(format!("unknown encoding: {}", s))),
+        }
+    }
+}
+
+/// What a textual input refers to once disambiguated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputKind {
+    /// The input is a path whose contents should be hashed
+    File,
+    /// The input is literal text to hash
+    String,
+    /// The input is `-`: the data to hash arrives on standard input
+    Stdin,
+}
+
+/// Policy for deciding whether a textual input names a file or is a string
+///
+/// `Auto` reproduces the CLI's historical heuristic: if a path with that
+/// name exists, its contents are hashed. This is convenient but surprising
+/// (hashing the string `"README.md"` in a checkout hashes the file), so
+/// callers that know what they want should pick `File` or `String`.
+///
+/// Following `sha256sum` and `tar`, the input `-` stands for standard
+/// input unless the policy is `String`; a file named `-` can still be
+/// reached as `./-`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InputPolicy {
+    /// Treat the input as a file if such a path exists, otherwise as a string
+    #[default]
+    Auto,
+    /// Always treat the input as a file path
+    File,
+    /// Always treat the input as a literal string
+    String,
+}
+
+impl InputPolicy {
+    /// Decide how `input` should be interpreted under this policy
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hashing::{InputKind, InputPolicy};
+    ///
+    /// assert_eq!(InputPolicy::String.resolve("Cargo.toml"), InputKind::String);
+    /// assert_eq!(InputPolicy::File.resolve("missing.bin"), InputKind::File);
+    /// assert_eq!(InputPolicy::Auto.resolve("-"), InputKind::Stdin);
+    /// assert_eq!(InputPolicy::String.resolve("-"), InputKind::String);
+    /// ```
+    pub fn resolve(&self, input: &str) -> InputKind {
+        match self {
+            InputPolicy::File | InputPolicy::Auto if input == "-" => InputKind::Stdin,
+            InputPolicy::File => InputKind::File,
+            InputPolicy::String => InputKind::String,
+            InputPolicy::Auto => {
+                if Path::new(input).exists() {
+                    InputKind::File
+                } else {
+                    InputKind::String
+                }
+            }
+        }
+    }
+
+    /// Whether resolving `input` relies on the file-existence heuristic
+    /// and picks the file, i.e. the case that is worth warning about
+    pub fn is_ambiguous(&self, input: &str) -> bool {
+        *self == InputPolicy::Auto && self.resolve(input) == InputKind::File
+    }
 }
 
 /// Hash a string using the specified algorithm
@@ -285,15 +920,94 @@ pub fn hash_bytes(data: &[u8], algorithm: Algorithm) -> Result<String> {
             hasher.update(data);
             hex::encode(hasher.finalize())
         }
+        Algorithm::Sha256d | Algorithm::Hash160 => {
+            hex::encode(finish_sha256_composite(algorithm, &Sha256::digest(data)))
+        }
+        Algorithm::Xxh32
+        | Algorithm::Xxh64
+        | Algorithm::Xxh3
+        | Algorithm::Xxh128
+        | Algorithm::Crc32
+        | Algorithm::Crc32c => {
+            hex::encode(noncrypto::digest(algorithm, data).expect("non-cryptographic"))
+        }
+        Algorithm::Shake128 | Algorithm::Shake256 => {
+            hex::encode(builder::digest_bytes(data, algorithm)?)
+        }
     };
     
     Ok(digest)
 }
 
+/// Hash a byte slice, returning the raw digest bytes
+///
+/// # Examples
+///
+/// ```
+/// use hashing::{hash_bytes_raw, Algorithm};
+///
+/// let digest = hash_bytes_raw(b"hello", Algorithm::Md5).unwrap();
+/// assert_eq!(digest.as_bytes().len(), 16);
+/// assert_eq!(digest.to_hex(), "5d41402abc4b2a76b9719d911017c592");
+/// ```
+pub fn hash_bytes_raw(data: &[u8], algorithm: Algorithm) -> Result<Digest> {
+    hash_reader_raw(data, algorithm)
+}
+
+/// Hash a byte slice and encode the digest with `encoding`
+///
+/// Returns bytes so that [`Encoding::Binary`] fits; the text encodings
+/// give ASCII, which `String::from_utf8` always accepts.
+///
+/// # Examples
+///
+/// ```
+/// use hashing::{hash_bytes_with_encoding, Algorithm, Encoding};
+///
+/// let base32 = hash_bytes_with_encoding(b"hello", Algorithm::Md5, Encoding::Base32)?;
+/// assert_eq!(base32, b"LVAUAKV4JMVHNOLRTWIRAF6FSI======");
+/// let binary = hash_bytes_with_encoding(b"hello", Algorithm::Md5, Encoding::Binary)?;
+/// assert_eq!(binary[..2], [0x5d, 0x41]);
+/// # Ok::<(), hashing::HashError>(())
+/// ```
+pub fn hash_bytes_with_encoding(
+    data: &[u8],
+    algorithm: Algorithm,
+    encoding: Encoding,
+) -> Result<Vec<u8>> {
+    Ok(encoding.encode_bytes(hash_bytes_raw(data, algorithm)?.as_bytes()))
+}
+
+/// Hash everything a reader yields using the specified algorithm
+///
+/// The input is streamed through a fixed-size buffer, so stdin, sockets
+/// and decompression readers can be hashed without a temporary file.
+///
+/// # Examples
+///
+/// ```
+/// use hashing::{hash_reader, hash_string, Algorithm};
+/// use std::io::Cursor;
+///
+/// let digest = hash_reader(Cursor::new("hello"), Algorithm::Sha256).unwrap();
+/// assert_eq!(digest, hash_string("hello", Algorithm::Sha256).unwrap());
+/// ```
+pub fn hash_reader<R: Read>(reader: R, algorithm: Algorithm) -> Result<String> {
+    Ok(hash_reader_raw(reader, algorithm)?.to_hex())
+}
+
+/// Hash everything a reader yields, returning the raw digest bytes
+pub fn hash_reader_raw<R: Read>(reader: R, algorithm: Algorithm) -> Result<Digest> {
+    let bytes = builder::digest_reader(reader, algorithm, builder::DEFAULT_BUFFER_SIZE)?;
+    Ok(Digest::computed(algorithm, bytes))
+}
+
 /// Hash a file using the specified algorithm with streaming
 ///
 /// This uses buffered I/O to efficiently hash large files without loading
-/// them entirely into memory.
+/// them entirely into memory. With the `mmap` feature, large files hashed
+/// with BLAKE3 are memory-mapped instead, and with `rayon` hashed on every
+/// core (see the `mmap` module).
 ///
 /// # Examples
 ///
@@ -304,73 +1018,153 @@ pub fn hash_bytes(data: &[u8], algorithm: Algorithm) -> Result<String> {
 /// println!("File hash: {}", digest);
 /// ```
 pub fn hash_file<P: AsRef<Path>>(path: P, algorithm: Algorithm) -> Result<String> {
-    use blake2::Blake2b512;
-    use blake2::Blake2s256;
-    use md5::Md5;
-    use sha2::{Sha224, Sha256, Sha384, Sha512, Sha512_224, Sha512_256, Digest};
-    use sha3::{Sha3_224, Sha3_256, Sha3_384, Sha3_512, Keccak224, Keccak256, Keccak384, Keccak512};
-    
+    Ok(hash_file_raw(path, algorithm)?.to_hex())
+}
+
+/// Hash a file with streaming, returning the raw digest bytes
+pub fn hash_file_raw<P: AsRef<Path>>(path: P, algorithm: Algorithm) -> Result<Digest> {
+    #[cfg(feature = "mmap")]
+    if algorithm == Algorithm::Blake3 && mmap::worth_mapping(path.as_ref()) {
+        return mmap::blake3_file(path);
+    }
     let file = File::open(path)?;
-    let mut reader = BufReader::with_capacity(8192, file);
-    let mut buffer = [0u8; 8192];
-    
-    macro_rules! hash_with_reader {
-        ($hasher:expr) => {{
-            let mut hasher = $hasher;
-            loop {
-                let count = reader.read(&mut buffer)?;
-                if count == 0 {
-                    break;
-                }
-                hasher.update(&buffer[..count]);
-            }
-            hex::encode(hasher.finalize())
-        }};
+    // Only a hint, which pipes and some platforms do not take
+    let _ = pagecache::advise_sequential(&file);
+    hash_reader_raw(file, algorithm)
+}
+
+/// Hash a file with streaming, calling `callback(done, total)` after each
+/// read with the bytes read so far and the file's size
+///
+/// `total` is 0 for files whose size is not known up front, such as pipes
+/// and, on some platforms, block devices. An empty file is hashed without
+/// a call.
+///
+/// # Examples
+///
+/// ```no_run
+/// use hashing::{hash_file_with_progress, Algorithm};
+///
+/// let digest = hash_file_with_progress("disk.img", Algorithm::Sha256, |done, total| {
+///     eprint!("\r{} of {} bytes", done, total);
+/// })
+/// .unwrap();
+/// eprintln!();
+/// println!("{}", digest);
+/// ```
+pub fn hash_file_with_progress<P: AsRef<Path>>(
+    path: P,
+    algorithm: Algorithm,
+    mut callback: impl FnMut(u64, u64),
+) -> Result<String> {
+    let path = path.as_ref();
+    let total = std::fs::metadata(path)?.len();
+    let mut hashing = Hashing::new()
+        .algorithm(algorithm)
+        .progress(move |done| callback(done, total));
+    hashing.hash_file(path)
+}
+
+/// Hash many files on a pool of `num_threads` threads, 0 for one per core
+///
+/// Returns one result per path, in the order of `paths` whatever order the
+/// files finish in; a file that cannot be read gives an error in its
+/// place without stopping the others.
+///
+/// # Examples
+///
+/// ```no_run
+/// use hashing::{hash_files_parallel, Algorithm};
+/// use std::path::PathBuf;
+///
+/// let paths = vec![PathBuf::from("a.iso"), PathBuf::from("b.iso")];
+/// let results = hash_files_parallel(&paths, Algorithm::Sha256, 4);
+/// for (path, result) in paths.iter().zip(results) {
+///     match result {
+///         Ok(result) => println!("{}  {}", result.digest, path.display()),
+///         Err(e) => eprintln!("{}: {}", path.display(), e),
+///     }
+/// }
+/// ```
+pub fn hash_files_parallel(
+    paths: &[PathBuf],
+    algorithm: Algorithm,
+    num_threads: usize,
+) -> Vec<Result<HashResult>> {
+    let jobs = match num_threads {
+        0 => checksum::default_jobs(),
+        threads => threads,
+    };
+    let mut results = Vec::with_capacity(paths.len());
+    let hash = |path: &PathBuf| {
+        let digest = hash_file(path, algorithm)?;
+        Ok(HashResult::new(algorithm, digest, "file").with_path(path))
+    };
+    checksum::in_order(paths, jobs, None, hash, |_, result| results.push(result));
+    results
+}
+
+/// Second stage of the composed digests, applied to a SHA-256 digest
+///
+/// SHA-256d hashes it with SHA-256 again; HASH160 with RIPEMD-160.
+pub(crate) fn finish_sha256_composite(algorithm: Algorithm, sha256: &[u8]) -> Vec<u8> {
+    use sha2::Digest;
+
+    match algorithm {
+        Algorithm::Sha256d => sha2::Sha256::digest(sha256).to_vec(),
+        Algorithm::Hash160 => ripemd::Ripemd160::digest(sha256).to_vec(),
+        _ => unreachable!("{} is not a SHA-256 composite", algorithm.name()),
     }
-    
-    let digest = match algorithm {
-        Algorithm::Md5 => hash_with_reader!(Md5::new()),
-        Algorithm::Sha1 => {
-            let mut hasher = sha1_smol::Sha1::new();
-            loop {
-                let count = reader.read(&mut buffer)?;
-                if count == 0 {
-                    break;
-                }
-                hasher.update(&buffer[..count]);
-            }
-            hex::encode(hasher.digest().bytes())
-        }
-        Algorithm::Sha224 => hash_with_reader!(Sha224::new()),
-        Algorithm::Sha256 => hash_with_reader!(Sha256::new()),
-        Algorithm::Sha384 => hash_with_reader!(Sha384::new()),
-        Algorithm::Sha512 => hash_with_reader!(Sha512::new()),
-        Algorithm::Sha512_224 => hash_with_reader!(Sha512_224::new()),
-        Algorithm::Sha512_256 => hash_with_reader!(Sha512_256::new()),
-        Algorithm::Sha3_224 => hash_with_reader!(Sha3_224::new()),
-        Algorithm::Sha3_256 => hash_with_reader!(Sha3_256::new()),
-        Algorithm::Sha3_384 => hash_with_reader!(Sha3_384::new()),
-        Algorithm::Sha3_512 => hash_with_reader!(Sha3_512::new()),
-        Algorithm::Blake2b512 => hash_with_reader!(Blake2b512::new()),
-        Algorithm::Blake2s256 => hash_with_reader!(Blake2s256::new()),
-        Algorithm::Blake3 => {
-            let mut hasher = blake3::Hasher::new();
-            loop {
-                let count = reader.read(&mut buffer)?;
-                if count == 0 {
-                    break;
-                }
-                hasher.update(&buffer[..count]);
-            }
-            hex::encode(hasher.finalize().as_bytes())
+}
+
+/// Hash a file, giving up if it takes longer than `timeout`
+///
+/// The file is hashed on a helper thread so that a hung network mount or a
+/// FIFO with no writer cannot stall the caller. On timeout the helper thread
+/// is told to stop and gives up at its next read; one blocked inside a
+/// single `open` or `read` call exits once that call returns.
+///
+/// # Examples
+///
+/// ```no_run
+/// use hashing::{hash_file_with_timeout, Algorithm};
+/// use std::time::Duration;
+///
+/// let digest = hash_file_with_timeout("file.bin", Algorithm::Sha256, Duration::from_secs(30));
+/// ```
+pub fn hash_file_with_timeout<P: AsRef<Path>>(
+    path: P,
+    algorithm: Algorithm,
+    timeout: Duration,
+) -> Result<String> {
+    let path = path.as_ref().to_path_buf();
+    let cancel = Arc::new(AtomicBool::new(false));
+    let (tx, rx) = mpsc::channel();
+    let worker_cancel = Arc::clone(&cancel);
+    thread::spawn(move || {
+        let digest = File::open(path).map_err(HashError::from).and_then(|file| {
+            let _ = pagecache::advise_sequential(&file);
+            let reader = job::Cancellable {
+                inner: file,
+                cancel: &worker_cancel,
+            };
+            hash_reader(reader, algorithm)
+        });
+        // The receiver is gone if we already timed out; nothing to report then
+        let _ = tx.send(digest);
+    });
+
+    match rx.recv_timeout(timeout) {
+        Ok(result) => result,
+        Err(mpsc::RecvTimeoutError::Timeout) => {
+            cancel.store(true, Ordering::Relaxed);
+            Err(HashError::Timeout(timeout))
         }
-        Algorithm::Keccak224 => hash_with_reader!(Keccak224::new()),
-        Algorithm::Keccak256 => hash_with_reader!(Keccak256::new()),
-        Algorithm::Keccak384 => hash_with_reader!(Keccak384::new()),
-        Algorithm::Keccak512 => hash_with_reader!(Keccak512::new()),
-    };
-    
-    Ok(digest)
+        Err(mpsc::RecvTimeoutError::Disconnected) => Err(HashError::Io(io::Error::new(
+            io::ErrorKind::Other,
+            "hashing thread terminated unexpectedly",
+        ))),
+    }
 }
 
 /// Hash result with metadata
@@ -381,6 +1175,9 @@ pub struct HashResult {
     pub input_type: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub input_path: Option<String>,
+    /// Path of an earlier hard link to the same content, whose digest was reused
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub linked_to: Option<String>,
 }
 
 impl HashResult {
@@ -391,6 +1188,7 @@ impl HashResult {
             digest,
             input_type: input_type.to_string(),
             input_path: None,
+            linked_to: None,
         }
     }
     
@@ -400,18 +1198,91 @@ impl HashResult {
         self
     }
     
+    /// Mark this result as a hard link of an already hashed path
+    pub fn with_linked_to<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.linked_to = Some(path.as_ref().display().to_string());
+        self
+    }
+    
     /// Export to JSON
     pub fn to_json(&self) -> Result<String> {
         serde_json::to_string_pretty(self)
             .map_err(|e| HashError::ExportError(e.to_string()))
     }
     
+    /// Export to a single-line JSON object (one record of a JSON Lines stream)
+    pub fn to_json_line(&self) -> Result<String> {
+        serde_json::to_string(self)
+            .map_err(|e| HashError::ExportError(e.to_string()))
+    }
+    
     /// Export to simple text format
     pub fn to_text(&self) -> String {
         format!("{} ({})", self.digest, self.algorithm)
     }
 }
 
+/// A set of files that share the same digest
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct DigestGroup {
+    pub algorithm: String,
+    pub digest: String,
+    pub paths: Vec<String>,
+}
+
+impl DigestGroup {
+    /// Whether more than one path shares this digest
+    pub fn is_duplicate(&self) -> bool {
+        self.paths.len() > 1
+    }
+}
+
+/// Cluster results by digest
+///
+/// Groups appear in the order their digest was first seen and paths keep
+/// their original order within each group. Results without an input path
+/// (e.g. hashed strings) are ignored.
+///
+/// # Examples
+///
+/// ```
+/// use hashing::{group_by_digest, Algorithm, HashResult};
+///
+/// let results = vec![
+///     HashResult::new(Algorithm::Sha256, "aa".into(), "file").with_path("a.txt"),
+///     HashResult::new(Algorithm::Sha256, "bb".into(), "file").with_path("b.txt"),
+///     HashResult::new(Algorithm::Sha256, "aa".into(), "file").with_path("copy-of-a.txt"),
+/// ];
+///
+/// let groups = group_by_digest(&results);
+/// assert_eq!(groups.len(), 2);
+/// assert_eq!(groups[0].paths, vec!["a.txt", "copy-of-a.txt"]);
+/// ```
+pub fn group_by_digest(results: &[HashResult]) -> Vec<DigestGroup> {
+    let mut groups: Vec<DigestGroup> = Vec::new();
+    let mut index: HashMap<(&str, &str), usize> = HashMap::new();
+
+    for result in results {
+        let Some(ref path) = result.input_path else {
+            continue;
+        };
+        let key = (result.algorithm.as_str(), result.digest.as_str());
+        match index.get(&key) {
+            Some(&i) => groups[i].paths.push(path.clone()),
+            None => {
+                index.insert(key, groups.len());
+                groups.push(DigestGroup {
+                    algorithm: result.algorithm.clone(),
+                    digest: result.digest.clone(),
+                    paths: vec![path.clone()],
+                });
+            }
+        }
+    }
+
+    groups
+}
+
 /// Mini SHA-1 implementation to avoid extra dependencies
 mod sha1_smol {
     pub struct Sha1 {
@@ -565,6 +1436,40 @@ mod tests {
         );
         assert_eq!(Algorithm::from_str("blake3").unwrap(), Algorithm::Blake3);
         assert!(Algorithm::from_str("invalid").is_err());
+        assert_eq!(Algorithm::from_str("SHA-512/256").unwrap(), Algorithm::Sha512_256);
+        assert_eq!(Algorithm::from_str("Keccak_256").unwrap(), Algorithm::Keccak256);
+    }
+
+    #[test]
+    fn test_algorithm_value_enum_matches_from_str() {
+        use clap::ValueEnum;
+
+        assert_eq!(Algorithm::value_variants().to_vec(), Algorithm::all());
+        for algo in Algorithm::all() {
+            assert_eq!(Algorithm::try_from(algo.name()).unwrap(), algo);
+            assert_eq!(<Algorithm as ValueEnum>::from_str(algo.name(), false).unwrap(), algo);
+            for alias in algo.aliases() {
+                assert_eq!(<Algorithm as ValueEnum>::from_str(alias, false).unwrap(), algo);
+                assert_eq!(<Algorithm as FromStr>::from_str(alias).unwrap(), algo);
+            }
+        }
+    }
+
+    #[test]
+    fn test_input_policy() {
+        let dir = std::env::temp_dir();
+        let existing = dir.to_str().unwrap();
+
+        assert_eq!(InputPolicy::Auto.resolve(existing), InputKind::File);
+        assert!(InputPolicy::Auto.is_ambiguous(existing));
+        assert!(!InputPolicy::File.is_ambiguous(existing));
+        assert_eq!(
+            InputPolicy::Auto.resolve("definitely not a path \u{1F600}"),
+            InputKind::String
+        );
+        assert_eq!(InputPolicy::File.resolve("-"), InputKind::Stdin);
+        assert_eq!(InputPolicy::File.resolve("./-"), InputKind::File);
+        assert!(!InputPolicy::Auto.is_ambiguous("-"));
     }
 
     #[test]
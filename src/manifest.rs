@@ -0,0 +1,650 @@
+//! Whole checksum files: loading, normalizing, merging and converting
+//!
+//! A [`Manifest`] is the list of entries of a coreutils-style checksum file
+//! (`<digest>  <path>`). [`merge`] combines several of them into one clean
+//! manifest: digests become lowercase hex, path separators become `/`, and
+//! the same path listed with two different digests is an error rather than
+//! something to pick a winner for.
+//!
+//! [`read_records`] and [`write_records`] translate between the checksum
+//! list formats of [`ChecksumFormat`] without hashing anything. A
+//! [`ManifestRecord`] carries everything any of them stores (sizes,
+//! several digests per file, hard links), and each format keeps what it
+//! can.
+//!
+//! A [`ManifestSplit`] names the part of a split manifest each file of a
+//! scan belongs to, for archives whose datasets are verified separately.
+//!
+//! ```
+//! use hashing::manifest::{merge, Manifest, MergeOptions};
+//!
+//! let a = Manifest::parse("BBBB  .\\docs\\b.txt\naaaa  a.txt\n")?;
+//! let b = Manifest::parse("aaaa  ./a.txt\n")?;
+//! let merged = merge(&[a, b], MergeOptions::new().with_dedupe(true).with_sort(true))?;
+//! assert_eq!(merged.to_text(), "aaaa  a.txt\nbbbb  docs/b.txt\n");
+//! # Ok::<(), hashing::HashError>(())
+//! ```
+
+use crate::checksum::{normalize_expected, parse_line, ChecksumEntry};
+use crate::{Algorithm, HashError, HashResult, Result};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+
+/// Header line every hashdeep file starts with
+const HASHDEEP_HEADER: &str = "%%%% HASHDEEP-1.0";
+
+/// Algorithm name used for SFV checksums
+pub const CRC32: &str = "crc32";
+
+/// Part of a [`ManifestSplit::TopDirectory`] split holding the files
+/// directly inside the scanned directory
+pub const ROOT_PART: &str = "_root";
+
+/// The entries of one checksum file, in file order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Manifest {
+    pub entries: Vec<ChecksumEntry>,
+}
+
+impl Manifest {
+    /// Parse checksum file contents; blank lines and `#` comments are
+    /// skipped, and every other line must name a file
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut entries = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let Some(entry) = parse_line(line) else {
+                continue;
+            };
+            if entry.path.is_empty() {
+                return Err(HashError::InvalidInput(format!(
+                    "line {}: digest without a file name",
+                    index + 1
+                )));
+            }
+            entries.push(entry);
+        }
+        Ok(Self { entries })
+    }
+
+    /// Read and parse a checksum file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        Self::parse(&text).map_err(|e| match e {
+            HashError::InvalidInput(message) => {
+                HashError::InvalidInput(format!("{}: {}", path.display(), message))
+            }
+            e => e,
+        })
+    }
+
+    /// Number of entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// True if the manifest has no entries
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The manifest as checksum file text, one `<digest>  <path>` line per
+    /// entry
+    pub fn to_text(&self) -> String {
+        self.entries
+            .iter()
+            .map(|entry| format!("{}  {}\n", entry.digest, entry.path))
+            .collect()
+    }
+}
+
+/// How [`merge`] treats repeated paths and entry order
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MergeOptions {
+    /// Keep only the first entry for each path
+    pub dedupe: bool,
+    /// Order entries by path instead of by input order
+    pub sort: bool,
+}
+
+impl MergeOptions {
+    /// Keep every entry, in input order
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep only the first entry for each path
+    pub fn with_dedupe(mut self, dedupe: bool) -> Self {
+        self.dedupe = dedupe;
+        self
+    }
+
+    /// Order entries by path
+    pub fn with_sort(mut self, sort: bool) -> Self {
+        self.sort = sort;
+        self
+    }
+}
+
+/// Combine `manifests` into one, normalizing digests (lowercase hex) and
+/// paths (see [`normalize_path`])
+///
+/// Fails if a path is listed with two different digests, or if the digests
+/// are not all the same length (checksum files of different algorithms).
+pub fn merge(manifests: &[Manifest], options: MergeOptions) -> Result<Manifest> {
+    let mut entries: Vec<ChecksumEntry> = Vec::new();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for entry in manifests.iter().flat_map(|manifest| &manifest.entries) {
+        let entry = ChecksumEntry {
+            digest: normalize_expected(&entry.digest).hex,
+            path: normalize_path(&entry.path),
+        };
+        if let Some(first) = entries.first() {
+            if first.digest.len() != entry.digest.len() {
+                return Err(HashError::InvalidInput(format!(
+                    "{} and {} have digests of different lengths; \
+                     were they made with different algorithms?",
+                    first.path, entry.path
+                )));
+            }
+        }
+        if let Some(&index) = seen.get(&entry.path) {
+            let earlier = &entries[index];
+            if earlier.digest != entry.digest {
+                return Err(HashError::InvalidInput(format!(
+                    "conflicting digests for {}: {} and {}",
+                    entry.path, earlier.digest, entry.digest
+                )));
+            }
+            if options.dedupe {
+                continue;
+            }
+        } else {
+            seen.insert(entry.path.clone(), entries.len());
+        }
+        entries.push(entry);
+    }
+    if options.sort {
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+    }
+    Ok(Manifest { entries })
+}
+
+/// Normalize a path as listed in a checksum file: `\` becomes `/`, repeated
+/// separators collapse and `.` components are dropped
+///
+/// `..` components are kept, since resolving them depends on the file
+/// system.
+///
+/// # Examples
+///
+/// ```
+/// use hashing::manifest::normalize_path;
+///
+/// assert_eq!(normalize_path(".\\data\\\\set/./a.bin"), "data/set/a.bin");
+/// assert_eq!(normalize_path("/srv//x"), "/srv/x");
+/// ```
+pub fn normalize_path(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let absolute = path.starts_with('/');
+    let parts: Vec<&str> = path
+        .split('/')
+        .filter(|part| !part.is_empty() && *part != ".")
+        .collect();
+    let joined = parts.join("/");
+    if absolute {
+        format!("/{}", joined)
+    } else if joined.is_empty() {
+        ".".to_string()
+    } else {
+        joined
+    }
+}
+
+/// Checksum list formats understood by [`read_records`] and [`write_records`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumFormat {
+    /// `<digest>  <path>` lines, as written by `sha256sum` and friends
+    Checksum,
+    /// JSON array of results, as written by `hash -r --format json`
+    Json,
+    /// One JSON result per line, as written by `hash -r --format jsonl`
+    Jsonl,
+    /// hashdeep: size and several digests per file
+    Hashdeep,
+    /// Simple File Verification: `<path> <CRC32>` lines
+    Sfv,
+}
+
+impl ChecksumFormat {
+    /// Format implied by a file's extension; anything unrecognized (such
+    /// as `.sha256` or `SHA256SUMS`) is a checksum file
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        let extension = path
+            .as_ref()
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("json") => ChecksumFormat::Json,
+            Some("jsonl" | "ndjson") => ChecksumFormat::Jsonl,
+            Some("hashdeep" | "hsh") => ChecksumFormat::Hashdeep,
+            Some("sfv") => ChecksumFormat::Sfv,
+            _ => ChecksumFormat::Checksum,
+        }
+    }
+}
+
+impl fmt::Display for ChecksumFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ChecksumFormat::Checksum => "checksum",
+            ChecksumFormat::Json => "json",
+            ChecksumFormat::Jsonl => "jsonl",
+            ChecksumFormat::Hashdeep => "hashdeep",
+            ChecksumFormat::Sfv => "sfv",
+        })
+    }
+}
+
+impl FromStr for ChecksumFormat {
+    type Err = HashError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "checksum" | "sums" => Ok(ChecksumFormat::Checksum),
+            "json" => Ok(ChecksumFormat::Json),
+            "jsonl" | "ndjson" => Ok(ChecksumFormat::Jsonl),
+            "hashdeep" => Ok(ChecksumFormat::Hashdeep),
+            "sfv" => Ok(ChecksumFormat::Sfv),
+            _ => Err(HashError::InvalidInput(format!(
+                "unknown checksum format: {} (expected checksum, json, jsonl, hashdeep or sfv)",
+                s
+            ))),
+        }
+    }
+}
+
+/// One file of a checksum list, with whatever its format recorded
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ManifestRecord {
+    /// Path as listed
+    pub path: String,
+    /// Size in bytes (hashdeep)
+    pub size: Option<u64>,
+    /// Lowercase hex digests by algorithm name (`sha256`, `crc32`, ...)
+    pub digests: Vec<(String, String)>,
+    /// Earlier hard link whose digest was reused (JSON)
+    pub linked_to: Option<String>,
+}
+
+impl ManifestRecord {
+    /// The digest made with `algorithm`, if recorded
+    pub fn digest(&self, algorithm: &str) -> Option<&str> {
+        self.digests
+            .iter()
+            .find(|(name, _)| name == algorithm)
+            .map(|(_, digest)| digest.as_str())
+    }
+}
+
+/// Algorithm a checksum file's name implies: `app.sha256`, `SHA256SUMS`,
+/// `md5sums.txt`
+pub fn algorithm_from_file_name<P: AsRef<Path>>(path: P) -> Option<Algorithm> {
+    let path = path.as_ref();
+    let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+    let stem = name.split('.').next().unwrap_or(&name);
+    let from_sums = stem.strip_suffix("sums").and_then(|name| name.parse().ok());
+    let from_extension = || path.extension()?.to_str()?.parse().ok();
+    from_sums.or_else(from_extension)
+}
+
+/// Parse a checksum list in `format`
+///
+/// A checksum file does not say which algorithm made its digests, so
+/// `algorithm` must name it; the other formats ignore it.
+pub fn read_records(
+    text: &str,
+    format: ChecksumFormat,
+    algorithm: Option<&str>,
+) -> Result<Vec<ManifestRecord>> {
+    match format {
+        ChecksumFormat::Checksum => {
+            let algorithm = algorithm.ok_or_else(|| {
+                HashError::InvalidInput(
+                    "the algorithm of a checksum file's digests must be given".to_string(),
+                )
+            })?;
+            let records = Manifest::parse(text)?
+                .entries
+                .into_iter()
+                .map(|entry| ManifestRecord {
+                    path: entry.path,
+                    digests: vec![(algorithm.to_string(), normalize_expected(&entry.digest).hex)],
+                    ..ManifestRecord::default()
+                })
+                .collect();
+            Ok(records)
+        }
+        ChecksumFormat::Json => {
+            let results: Vec<HashResult> = serde_json::from_str(text)
+                .map_err(|e| HashError::InvalidInput(format!("invalid JSON results: {}", e)))?;
+            from_results(results)
+        }
+        ChecksumFormat::Jsonl => {
+            let results = text
+                .lines()
+                .enumerate()
+                .filter(|(_, line)| !line.trim().is_empty())
+                .map(|(index, line)| {
+                    serde_json::from_str(line)
+                        .map_err(|e| HashError::InvalidInput(format!("line {}: {}", index + 1, e)))
+                })
+                .collect::<Result<Vec<HashResult>>>()?;
+            from_results(results)
+        }
+        ChecksumFormat::Hashdeep => read_hashdeep(text),
+        ChecksumFormat::Sfv => read_sfv(text),
+    }
+}
+
+/// Group results by path, keeping their first appearance's order
+fn from_results(results: Vec<HashResult>) -> Result<Vec<ManifestRecord>> {
+    let mut records: Vec<ManifestRecord> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for result in results {
+        let path = result
+            .input_path
+            .ok_or_else(|| HashError::InvalidInput("result without an input_path".to_string()))?;
+        let position = *index.entry(path.clone()).or_insert_with(|| {
+            records.push(ManifestRecord {
+                path,
+                ..ManifestRecord::default()
+            });
+            records.len() - 1
+        });
+        let record = &mut records[position];
+        let digest = normalize_expected(&result.digest).hex;
+        record.digests.push((result.algorithm, digest));
+        if result.linked_to.is_some() {
+            record.linked_to = result.linked_to;
+        }
+    }
+    Ok(records)
+}
+
+fn read_hashdeep(text: &str) -> Result<Vec<ManifestRecord>> {
+    let invalid = |line: usize, message: &str| {
+        HashError::InvalidInput(format!("hashdeep line {}: {}", line, message))
+    };
+    let mut lines = text.lines().enumerate();
+    match lines.next() {
+        Some((_, line)) if line.trim_end() == HASHDEEP_HEADER => {}
+        _ => return Err(invalid(1, "missing HASHDEEP-1.0 header")),
+    }
+
+    let mut columns: Option<Vec<String>> = None;
+    let mut records = Vec::new();
+    for (index, line) in lines {
+        let line = line.trim_end_matches('\r');
+        if let Some(header) = line.strip_prefix("%%%% ") {
+            let names: Vec<String> = header.split(',').map(str::to_ascii_lowercase).collect();
+            if names.first().map(String::as_str) != Some("size")
+                || names.last().map(String::as_str) != Some("filename")
+            {
+                return Err(invalid(index + 1, "columns must run from size to filename"));
+            }
+            columns = Some(names);
+            continue;
+        }
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let columns = columns
+            .as_ref()
+            .ok_or_else(|| invalid(index + 1, "file listed before the column header"))?;
+        // The file name comes last and may itself contain commas
+        let fields: Vec<&str> = line.splitn(columns.len(), ',').collect();
+        if fields.len() != columns.len() {
+            return Err(invalid(index + 1, "too few fields"));
+        }
+        let size = fields[0]
+            .parse()
+            .map_err(|_| invalid(index + 1, "size is not a number"))?;
+        let digests = columns[1..columns.len() - 1]
+            .iter()
+            .zip(&fields[1..])
+            .map(|(name, digest)| (name.clone(), digest.to_ascii_lowercase()))
+            .collect();
+        records.push(ManifestRecord {
+            path: fields[columns.len() - 1].to_string(),
+            size: Some(size),
+            digests,
+            linked_to: None,
+        });
+    }
+    Ok(records)
+}
+
+fn read_sfv(text: &str) -> Result<Vec<ManifestRecord>> {
+    let mut records = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with(';') {
+            continue;
+        }
+        let crc = line
+            .rsplit_once([' ', '\t'])
+            .map(|(path, crc)| (path.trim_end(), crc))
+            .filter(|(path, crc)| {
+                !path.is_empty() && crc.len() == 8 && crc.bytes().all(|b| b.is_ascii_hexdigit())
+            });
+        let Some((path, crc)) = crc else {
+            return Err(HashError::InvalidInput(format!(
+                "SFV line {}: expected <path> <CRC32>",
+                index + 1
+            )));
+        };
+        records.push(ManifestRecord {
+            path: path.to_string(),
+            digests: vec![(CRC32.to_string(), crc.to_ascii_lowercase())],
+            ..ManifestRecord::default()
+        });
+    }
+    Ok(records)
+}
+
+/// Write `records` in `format`
+///
+/// Formats with one digest per file (checksum, SFV) write `algorithm`'s,
+/// which may be left out when every record has a single digest of the same
+/// algorithm; SFV only holds CRC32. The JSON formats and hashdeep write
+/// every digest unless `algorithm` picks one. hashdeep needs file sizes
+/// (see [`fill_sizes`]). Fails, rather than write a partial list, when a
+/// record lacks something the format needs.
+pub fn write_records(
+    records: &[ManifestRecord],
+    format: ChecksumFormat,
+    algorithm: Option<&str>,
+) -> Result<String> {
+    let algorithms = match (format, algorithm) {
+        (ChecksumFormat::Sfv, Some(name)) if name != CRC32 => {
+            return Err(HashError::InvalidInput(format!(
+                "SFV files hold CRC32 checksums, not {}",
+                name
+            )));
+        }
+        (ChecksumFormat::Sfv, _) => vec![CRC32.to_string()],
+        (_, Some(name)) => vec![name.to_string()],
+        (_, None) => record_algorithms(records),
+    };
+    let single = |format: ChecksumFormat| -> Result<&str> {
+        match algorithms.as_slice() {
+            [name] => Ok(name),
+            names => Err(HashError::InvalidInput(format!(
+                "{} files hold one digest per file; choose one of {}",
+                format,
+                names.join(", ")
+            ))),
+        }
+    };
+    let digest = |record: &'_ ManifestRecord, name: &str| -> Result<String> {
+        record.digest(name).map(str::to_string).ok_or_else(|| {
+            HashError::InvalidInput(format!("{} has no {} digest", record.path, name))
+        })
+    };
+
+    let mut out = String::new();
+    match format {
+        ChecksumFormat::Checksum => {
+            let name = single(format)?;
+            for record in records {
+                out.push_str(&format!("{}  {}\n", digest(record, name)?, record.path));
+            }
+        }
+        ChecksumFormat::Sfv => {
+            for record in records {
+                let crc = digest(record, CRC32)?.to_ascii_uppercase();
+                out.push_str(&format!("{} {}\n", record.path, crc));
+            }
+        }
+        ChecksumFormat::Json | ChecksumFormat::Jsonl => {
+            let mut results = Vec::new();
+            for record in records {
+                for name in &algorithms {
+                    let Some(digest) = record.digest(name) else {
+                        continue;
+                    };
+                    results.push(HashResult {
+                        algorithm: name.clone(),
+                        digest: digest.to_string(),
+                        input_type: "file".to_string(),
+                        input_path: Some(record.path.clone()),
+                        linked_to: record.linked_to.clone(),
+                    });
+                }
+            }
+            if format == ChecksumFormat::Json {
+                // Serializing plain strings cannot fail
+                out = serde_json::to_string_pretty(&results).expect("hash results");
+                out.push('\n');
+            } else {
+                for result in results {
+                    out.push_str(&result.to_json_line()?);
+                    out.push('\n');
+                }
+            }
+        }
+        ChecksumFormat::Hashdeep => {
+            out.push_str(HASHDEEP_HEADER);
+            out.push('\n');
+            out.push_str(&format!(
+                "%%%% size,{},filename\n##\n",
+                algorithms.join(",")
+            ));
+            for record in records {
+                let size = record.size.ok_or_else(|| {
+                    HashError::InvalidInput(format!(
+                        "{} has no size, which hashdeep needs",
+                        record.path
+                    ))
+                })?;
+                let digests = algorithms
+                    .iter()
+                    .map(|name| digest(record, name))
+                    .collect::<Result<Vec<_>>>()?;
+                out.push_str(&format!("{},{},{}\n", size, digests.join(","), record.path));
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Every algorithm some record has a digest for, in order of appearance
+fn record_algorithms(records: &[ManifestRecord]) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for (name, _) in records.iter().flat_map(|record| &record.digests) {
+        if !names.contains(name) {
+            names.push(name.clone());
+        }
+    }
+    names
+}
+
+/// Look up the size of each record that has none, resolving relative
+/// paths against `base`; the files are not read
+pub fn fill_sizes(records: &mut [ManifestRecord], base: &Path) -> Result<()> {
+    for record in records.iter_mut().filter(|record| record.size.is_none()) {
+        let metadata = fs::metadata(base.join(&record.path)).map_err(|e| {
+            HashError::Io(std::io::Error::new(
+                e.kind(),
+                format!("{}: {}", record.path, e),
+            ))
+        })?;
+        record.size = Some(metadata.len());
+    }
+    Ok(())
+}
+
+/// How the manifest of a recursive scan is split into several files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ManifestSplit {
+    /// One manifest per top-level subdirectory of the scanned directory;
+    /// files directly inside it go to [`ROOT_PART`]
+    TopDirectory,
+    /// A new manifest after every this many entries: `0001`, `0002`, ...
+    Every(usize),
+}
+
+impl ManifestSplit {
+    /// Name of the part holding `path`, found below `root` as the scan's
+    /// `index`-th entry (counting from 0)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hashing::manifest::ManifestSplit;
+    ///
+    /// let split = ManifestSplit::TopDirectory;
+    /// assert_eq!(split.part("data", "data/set-a/x/1.bin", 0), "set-a");
+    /// assert_eq!(split.part("data", "data/README", 1), "_root");
+    /// assert_eq!(ManifestSplit::Every(1000).part("data", "data/README", 1500), "0002");
+    /// ```
+    pub fn part<R: AsRef<Path>, P: AsRef<Path>>(&self, root: R, path: P, index: usize) -> String {
+        match *self {
+            ManifestSplit::TopDirectory => {
+                let path = path.as_ref();
+                let relative = path.strip_prefix(root.as_ref()).unwrap_or(path);
+                let mut components = relative
+                    .components()
+                    .filter(|component| matches!(component, Component::Normal(_)));
+                match (components.next(), components.next()) {
+                    (Some(directory), Some(_)) => {
+                        directory.as_os_str().to_string_lossy().into_owned()
+                    }
+                    _ => ROOT_PART.to_string(),
+                }
+            }
+            ManifestSplit::Every(entries) => format!("{:04}", index / entries.max(1) + 1),
+        }
+    }
+}
+
+/// File a part of a split manifest is written to: `part` joins the file
+/// stem of `export`, so `out/hashes.sha256` gives `out/hashes-set-a.sha256`
+pub fn part_path<P: AsRef<Path>>(export: P, part: &str) -> PathBuf {
+    let export = export.as_ref();
+    let stem = export
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut name = format!("{}-{}", stem, part);
+    if let Some(extension) = export.extension() {
+        name.push('.');
+        name.push_str(&extension.to_string_lossy());
+    }
+    export.with_file_name(name)
+}
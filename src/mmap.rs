@@ -0,0 +1,73 @@
+//! Memory-mapped and multithreaded BLAKE3 for large files
+//!
+//! BLAKE3 is a tree hash: separate parts of the input can be hashed on
+//! separate cores and combined, which a stream read 8 KiB at a time never
+//! takes advantage of. [`blake3_file`] maps the file into memory instead
+//! of copying it through a buffer and, with the `rayon` feature, spreads
+//! the hashing over every core. The digest is the same as a streamed one.
+//!
+//! With the `mmap` feature, [`crate::hash_file`] and [`crate::Hashing`]
+//! take this path by themselves for BLAKE3 files of at least
+//! [`MMAP_THRESHOLD`] bytes; below that, setting up the mapping and the
+//! threads costs more than it saves.
+//!
+//! ```no_run
+//! use hashing::mmap::blake3_file;
+//!
+//! let digest = blake3_file("disk.img")?;
+//! println!("{}", digest);
+//! # Ok::<(), hashing::HashError>(())
+//! ```
+
+use crate::{Algorithm, Digest, Result};
+use std::fs;
+use std::path::Path;
+
+/// Smallest file hashed through a memory map (128 KiB)
+pub const MMAP_THRESHOLD: u64 = 128 * 1024;
+
+/// BLAKE3 digest of the file at `path`, memory-mapped, on every core with
+/// the `rayon` feature and on the calling thread without it
+///
+/// Files that cannot be mapped, such as pipes, are read as usual.
+pub fn blake3_file<P: AsRef<Path>>(path: P) -> Result<Digest> {
+    let mut hasher = blake3::Hasher::new();
+    #[cfg(feature = "rayon")]
+    hasher.update_mmap_rayon(path)?;
+    #[cfg(not(feature = "rayon"))]
+    hasher.update_mmap(path)?;
+    Ok(finish(hasher))
+}
+
+/// [`blake3_file`] on `threads` threads, or one per core for 0
+///
+/// The threads are started for this call and stopped after it.
+///
+/// ```no_run
+/// use hashing::mmap::blake3_file_threads;
+///
+/// // Leave the other cores to the rest of the machine
+/// let digest = blake3_file_threads("disk.img", 4)?;
+/// # Ok::<(), hashing::HashError>(())
+/// ```
+#[cfg(feature = "rayon")]
+pub fn blake3_file_threads<P: AsRef<Path>>(path: P, threads: usize) -> Result<Digest> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+    let path = path.as_ref();
+    let mut hasher = blake3::Hasher::new();
+    pool.install(|| hasher.update_mmap_rayon(path))?;
+    Ok(finish(hasher))
+}
+
+/// Whether `path` is a regular file large enough for [`blake3_file`] to
+/// beat a streamed read
+pub(crate) fn worth_mapping(path: &Path) -> bool {
+    fs::metadata(path).is_ok_and(|metadata| metadata.is_file() && metadata.len() >= MMAP_THRESHOLD)
+}
+
+fn finish(hasher: blake3::Hasher) -> Digest {
+    Digest::computed(Algorithm::Blake3, hasher.finalize().as_bytes().to_vec())
+}
@@ -0,0 +1,373 @@
+//! Several digests from a single read
+//!
+//! Computing a file's digest under many algorithms one [`crate::Hashing`]
+//! at a time reads the file once per algorithm. [`MultiHasher`] reads it
+//! once and feeds every chunk to all the algorithms, optionally each on
+//! its own thread, so the slowest algorithm rather than the sum of them
+//! sets the pace.
+//!
+//! ```
+//! use hashing::{hash_string, Algorithm, MultiHasher};
+//!
+//! let algorithms = [Algorithm::Md5, Algorithm::Sha256, Algorithm::Blake3];
+//! let digests = MultiHasher::new(&algorithms)
+//!     .parallel(true)
+//!     .hash_bytes(b"hello")?;
+//! for (digest, algorithm) in digests.iter().zip(algorithms) {
+//!     assert_eq!(digest.algorithm(), algorithm);
+//!     assert_eq!(digest.to_hex(), hash_string("hello", algorithm)?);
+//! }
+//! # Ok::<(), hashing::HashError>(())
+//! ```
+
+use crate::builder::{FileReads, ProgressReader};
+use crate::hmac::HmacHasher;
+use crate::noncrypto::Checksum;
+use crate::{Algorithm, Digest as RawDigest, Result};
+use sha2::Digest;
+use std::io::{self, Read};
+use std::path::Path;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+
+/// Bytes read from the input at a time
+const CHUNK_SIZE: usize = 256 * 1024;
+
+/// Chunks queued for each hashing thread before the reader waits for it
+const QUEUE_LEN: usize = 8;
+
+/// Computes the digests of one input under several algorithms
+///
+/// Digests come back in the order the algorithms were given. With a key,
+/// every digest is an HMAC, and an algorithm without HMAC fails the hash.
+#[derive(Clone)]
+pub struct MultiHasher {
+    algorithms: Vec<Algorithm>,
+    key: Option<Vec<u8>>,
+    parallel: bool,
+    reads: FileReads,
+}
+
+impl MultiHasher {
+    /// Hash with each of `algorithms`, on the calling thread
+    pub fn new(algorithms: &[Algorithm]) -> Self {
+        Self {
+            algorithms: algorithms.to_vec(),
+            key: None,
+            parallel: false,
+            reads: FileReads::default(),
+        }
+    }
+
+    /// Compute HMACs with this key instead of plain hashes
+    pub fn key(mut self, key: impl Into<Vec<u8>>) -> Self {
+        self.key = Some(key.into());
+        self
+    }
+
+    /// Run each algorithm on its own thread while the calling thread reads
+    ///
+    /// Pays off when there are several algorithms and the input comes in
+    /// faster than the slowest of them hashes it on one core.
+    pub fn parallel(mut self, enabled: bool) -> Self {
+        self.parallel = enabled;
+        self
+    }
+
+    /// Have [`MultiHasher::hash_file`] read past the page cache, as
+    /// [`crate::Hashing::uncached`] does
+    pub fn uncached(mut self, enabled: bool) -> Self {
+        self.reads.uncached = enabled;
+        self
+    }
+
+    /// Have [`MultiHasher::hash_file`] keep the kernel reading `bytes`
+    /// ahead, as [`crate::Hashing::read_ahead`] does
+    pub fn read_ahead(mut self, bytes: u64) -> Self {
+        self.reads.read_ahead = bytes;
+        self
+    }
+
+    /// Algorithms the digests are computed with, in order
+    pub fn algorithms(&self) -> &[Algorithm] {
+        &self.algorithms
+    }
+
+    /// Digests of a byte slice
+    pub fn hash_bytes(&self, data: &[u8]) -> Result<Vec<RawDigest>> {
+        self.hash_reader(data)
+    }
+
+    /// Digests of a file's contents, read once
+    pub fn hash_file<P: AsRef<Path>>(&self, path: P) -> Result<Vec<RawDigest>> {
+        self.hash_reader(self.reads.open(path.as_ref(), false)?)
+    }
+
+    /// [`MultiHasher::hash_file`], calling `callback` with the total number
+    /// of bytes read after each read
+    pub fn hash_file_with_progress<P: AsRef<Path>>(
+        &self,
+        path: P,
+        callback: impl FnMut(u64),
+    ) -> Result<Vec<RawDigest>> {
+        self.hash_reader_with_progress(self.reads.open(path.as_ref(), false)?, callback)
+    }
+
+    /// [`MultiHasher::hash_reader`], calling `callback` with the total
+    /// number of bytes read after each read
+    pub fn hash_reader_with_progress<R: Read>(
+        &self,
+        reader: R,
+        mut callback: impl FnMut(u64),
+    ) -> Result<Vec<RawDigest>> {
+        self.hash_reader(ProgressReader::new(reader, Some(&mut callback)))
+    }
+
+    /// Digests of everything `reader` yields, read once
+    pub fn hash_reader<R: Read>(&self, reader: R) -> Result<Vec<RawDigest>> {
+        let states = self
+            .algorithms
+            .iter()
+            .map(|&algorithm| state(algorithm, self.key.as_deref()))
+            .collect::<Result<Vec<_>>>()?;
+        let digests = if self.parallel && states.len() > 1 {
+            hash_parallel(reader, states)?
+        } else {
+            hash_sequential(reader, states)?
+        };
+        Ok(self
+            .algorithms
+            .iter()
+            .zip(digests)
+            .map(|(&algorithm, bytes)| RawDigest::computed(algorithm, bytes))
+            .collect())
+    }
+}
+
+/// Feed every chunk to each state in turn
+fn hash_sequential<R: Read>(
+    mut reader: R,
+    mut states: Vec<Box<dyn State>>,
+) -> Result<Vec<Vec<u8>>> {
+    let mut buffer = vec![0u8; CHUNK_SIZE];
+    loop {
+        let count = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(count) => count,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+        for state in &mut states {
+            state.update(&buffer[..count]);
+        }
+    }
+    Ok(states.into_iter().map(|state| state.finalize()).collect())
+}
+
+/// Read on this thread and hand each chunk to one thread per state
+fn hash_parallel<R: Read>(mut reader: R, states: Vec<Box<dyn State>>) -> Result<Vec<Vec<u8>>> {
+    thread::scope(|scope| {
+        let mut senders = Vec::with_capacity(states.len());
+        let mut workers = Vec::with_capacity(states.len());
+        for mut state in states {
+            let (sender, receiver) = mpsc::sync_channel::<Arc<Vec<u8>>>(QUEUE_LEN);
+            senders.push(sender);
+            workers.push(scope.spawn(move || {
+                for chunk in receiver {
+                    state.update(&chunk);
+                }
+                state.finalize()
+            }));
+        }
+
+        let mut failed = None;
+        loop {
+            let mut buffer = vec![0u8; CHUNK_SIZE];
+            let count = match reader.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(count) => count,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    failed = Some(e);
+                    break;
+                }
+            };
+            buffer.truncate(count);
+            let chunk = Arc::new(buffer);
+            for sender in &senders {
+                // A worker only hangs up by panicking, which join reports
+                let _ = sender.send(Arc::clone(&chunk));
+            }
+        }
+        // Closing the channels lets the workers finish
+        drop(senders);
+
+        let digests: Vec<Vec<u8>> = workers
+            .into_iter()
+            .map(|worker| {
+                worker
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            })
+            .collect();
+        match failed {
+            Some(e) => Err(e.into()),
+            None => Ok(digests),
+        }
+    })
+}
+
+/// Running state of one digest
+pub(crate) trait State: Send {
+    fn update(&mut self, data: &[u8]);
+    fn finalize(self: Box<Self>) -> Vec<u8>;
+}
+
+/// A RustCrypto hasher
+struct Plain<D>(D);
+
+impl<D: Digest + Send> State for Plain<D> {
+    fn update(&mut self, data: &[u8]) {
+        Digest::update(&mut self.0, data);
+    }
+
+    fn finalize(self: Box<Self>) -> Vec<u8> {
+        self.0.finalize().to_vec()
+    }
+}
+
+struct Sha1(crate::sha1_smol::Sha1);
+
+impl State for Sha1 {
+    fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    fn finalize(self: Box<Self>) -> Vec<u8> {
+        self.0.digest().bytes().to_vec()
+    }
+}
+
+struct Blake3(blake3::Hasher);
+
+impl State for Blake3 {
+    fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    fn finalize(self: Box<Self>) -> Vec<u8> {
+        self.0.finalize().as_bytes().to_vec()
+    }
+}
+
+/// SHAKE128 or SHAKE256, read out to the algorithm's default length
+struct Shake<D> {
+    len: usize,
+    hasher: D,
+}
+
+impl<D> State for Shake<D>
+where
+    D: sha3::digest::Update + sha3::digest::ExtendableOutput + Send,
+{
+    fn update(&mut self, data: &[u8]) {
+        self.hasher.update(data);
+    }
+
+    fn finalize(self: Box<Self>) -> Vec<u8> {
+        let mut digest = vec![0u8; self.len];
+        self.hasher.finalize_xof_into(&mut digest);
+        digest
+    }
+}
+
+/// SHA-256d or HASH160: SHA-256 first, the second stage at the end
+struct Sha256Composite {
+    algorithm: Algorithm,
+    sha256: sha2::Sha256,
+}
+
+impl State for Sha256Composite {
+    fn update(&mut self, data: &[u8]) {
+        Digest::update(&mut self.sha256, data);
+    }
+
+    fn finalize(self: Box<Self>) -> Vec<u8> {
+        crate::finish_sha256_composite(self.algorithm, &self.sha256.finalize())
+    }
+}
+
+impl State for Checksum {
+    fn update(&mut self, data: &[u8]) {
+        Checksum::update(self, data);
+    }
+
+    fn finalize(self: Box<Self>) -> Vec<u8> {
+        Checksum::finalize(*self)
+    }
+}
+
+impl State for HmacHasher {
+    fn update(&mut self, data: &[u8]) {
+        HmacHasher::update(self, data);
+    }
+
+    fn finalize(self: Box<Self>) -> Vec<u8> {
+        HmacHasher::finalize(*self).into_bytes()
+    }
+}
+
+/// A fresh state for `algorithm`, an HMAC if there is a `key`
+pub(crate) fn state(algorithm: Algorithm, key: Option<&[u8]>) -> Result<Box<dyn State>> {
+    use blake2::{Blake2b512, Blake2s256};
+    use md5::Md5;
+    use sha2::{Sha224, Sha256, Sha384, Sha512, Sha512_224, Sha512_256};
+    use sha3::{
+        Keccak224, Keccak256, Keccak384, Keccak512, Sha3_224, Sha3_256, Sha3_384, Sha3_512,
+        Shake128, Shake256,
+    };
+
+    if let Some(key) = key {
+        return Ok(Box::new(HmacHasher::new(key, algorithm)?));
+    }
+    Ok(match algorithm {
+        Algorithm::Md5 => Box::new(Plain(Md5::new())),
+        Algorithm::Sha1 => Box::new(Sha1(crate::sha1_smol::Sha1::new())),
+        Algorithm::Sha224 => Box::new(Plain(Sha224::new())),
+        Algorithm::Sha256 => Box::new(Plain(Sha256::new())),
+        Algorithm::Sha384 => Box::new(Plain(Sha384::new())),
+        Algorithm::Sha512 => Box::new(Plain(Sha512::new())),
+        Algorithm::Sha512_224 => Box::new(Plain(Sha512_224::new())),
+        Algorithm::Sha512_256 => Box::new(Plain(Sha512_256::new())),
+        Algorithm::Sha3_224 => Box::new(Plain(Sha3_224::new())),
+        Algorithm::Sha3_256 => Box::new(Plain(Sha3_256::new())),
+        Algorithm::Sha3_384 => Box::new(Plain(Sha3_384::new())),
+        Algorithm::Sha3_512 => Box::new(Plain(Sha3_512::new())),
+        Algorithm::Shake128 => Box::new(Shake {
+            len: algorithm.output_size(),
+            hasher: Shake128::default(),
+        }),
+        Algorithm::Shake256 => Box::new(Shake {
+            len: algorithm.output_size(),
+            hasher: Shake256::default(),
+        }),
+        Algorithm::Blake2b512 => Box::new(Plain(Blake2b512::new())),
+        Algorithm::Blake2s256 => Box::new(Plain(Blake2s256::new())),
+        Algorithm::Blake3 => Box::new(Blake3(blake3::Hasher::new())),
+        Algorithm::Keccak224 => Box::new(Plain(Keccak224::new())),
+        Algorithm::Keccak256 => Box::new(Plain(Keccak256::new())),
+        Algorithm::Keccak384 => Box::new(Plain(Keccak384::new())),
+        Algorithm::Keccak512 => Box::new(Plain(Keccak512::new())),
+        Algorithm::Sha256d | Algorithm::Hash160 => Box::new(Sha256Composite {
+            algorithm,
+            sha256: Sha256::new(),
+        }),
+        Algorithm::Xxh32
+        | Algorithm::Xxh64
+        | Algorithm::Xxh3
+        | Algorithm::Xxh128
+        | Algorithm::Crc32
+        | Algorithm::Crc32c => Box::new(Checksum::new(algorithm).expect("non-cryptographic")),
+    })
+}
@@ -0,0 +1,185 @@
+//! Content-address names derived from digests and keys
+//!
+//! - [`ni_uri`] builds RFC 6920 named-information URIs such as
+//!   `ni:///sha-256;f4OxZX_x_FO5LcGBSKHWXfwtSx-j1ncoSt3SABJtkGk`, and
+//!   [`parse_ni_uri`] reads them back.
+//! - [`onion_v3_address`] derives the `.onion` name of a Tor v3 onion
+//!   service from its ed25519 public key, and [`parse_onion_v3_address`]
+//!   checks one.
+//!
+//! ```
+//! use hashing::naming::ni_uri;
+//! use hashing::{hash_string, Algorithm};
+//!
+//! let digest = hex::decode(hash_string("Hello World!", Algorithm::Sha256).unwrap()).unwrap();
+//! assert_eq!(
+//!     ni_uri(Algorithm::Sha256, &digest).unwrap(),
+//!     "ni:///sha-256;f4OxZX_x_FO5LcGBSKHWXfwtSx-j1ncoSt3SABJtkGk"
+//! );
+//! ```
+
+use crate::fixed::{hash_bytes_fixed, Sha3_256};
+use crate::{Algorithm, HashError, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+
+/// Version byte of v3 onion addresses
+const ONION_VERSION: u8 = 0x03;
+
+/// Lowercase RFC 4648 base32 alphabet, as used in onion addresses
+const BASE32_ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+/// Name of `algorithm` in the IANA Named Information Hash Algorithm
+/// Registry, if it has one
+pub fn ni_algorithm_name(algorithm: Algorithm) -> Option<&'static str> {
+    match algorithm {
+        Algorithm::Sha256 => Some("sha-256"),
+        Algorithm::Sha384 => Some("sha-384"),
+        Algorithm::Sha512 => Some("sha-512"),
+        Algorithm::Sha3_224 => Some("sha3-224"),
+        Algorithm::Sha3_256 => Some("sha3-256"),
+        Algorithm::Sha3_384 => Some("sha3-384"),
+        Algorithm::Sha3_512 => Some("sha3-512"),
+        _ => None,
+    }
+}
+
+/// The `ni:///` URI (RFC 6920) naming content with the given digest
+pub fn ni_uri(algorithm: Algorithm, digest: &[u8]) -> Result<String> {
+    let name = ni_algorithm_name(algorithm).ok_or_else(|| {
+        HashError::UnsupportedAlgorithm(format!(
+            "{} has no named-information (RFC 6920) identifier",
+            algorithm.name()
+        ))
+    })?;
+    check_digest_len(algorithm, digest.len())?;
+    Ok(format!("ni:///{};{}", name, URL_SAFE_NO_PAD.encode(digest)))
+}
+
+/// Split an `ni:` URI into its algorithm and digest
+///
+/// The authority and any query (such as `?ct=text/plain`) are ignored.
+/// Truncated algorithms like `sha-256-128` are not supported.
+pub fn parse_ni_uri(uri: &str) -> Result<(Algorithm, Vec<u8>)> {
+    let invalid = |reason: &str| HashError::InvalidInput(format!("{}: {}", reason, uri));
+
+    let rest = uri
+        .strip_prefix("ni://")
+        .ok_or_else(|| invalid("not an ni URI"))?;
+    let path = match rest.find('/') {
+        Some(slash) => &rest[slash + 1..],
+        None => return Err(invalid("ni URI has no path")),
+    };
+    let path = path.split('?').next().unwrap_or(path);
+    let (name, value) = path
+        .split_once(';')
+        .ok_or_else(|| invalid("ni URI has no digest"))?;
+
+    let algorithm = Algorithm::all()
+        .into_iter()
+        .find(|&algorithm| ni_algorithm_name(algorithm) == Some(name))
+        .ok_or_else(|| {
+            HashError::UnsupportedAlgorithm(format!("unsupported ni algorithm: {}", name))
+        })?;
+    let digest = URL_SAFE_NO_PAD
+        .decode(value)
+        .map_err(|_| invalid("ni digest is not base64url"))?;
+    check_digest_len(algorithm, digest.len())?;
+    Ok((algorithm, digest))
+}
+
+/// The `.onion` address of a Tor v3 onion service
+///
+/// The address encodes the key, a two-byte SHA3-256 checksum and the
+/// version byte in lowercase base32.
+pub fn onion_v3_address(public_key: &[u8; 32]) -> String {
+    let checksum = onion_checksum(public_key);
+    let mut bytes = Vec::with_capacity(35);
+    bytes.extend_from_slice(public_key);
+    bytes.extend_from_slice(&checksum);
+    bytes.push(ONION_VERSION);
+    format!("{}.onion", base32_encode(&bytes))
+}
+
+/// The ed25519 public key of a v3 onion address, after checking its
+/// version and checksum
+///
+/// The `.onion` suffix is optional and the address is case-insensitive.
+pub fn parse_onion_v3_address(address: &str) -> Result<[u8; 32]> {
+    let invalid = |reason: &str| HashError::InvalidInput(format!("{}: {}", reason, address));
+
+    let lower = address.trim().to_ascii_lowercase();
+    let name = lower.strip_suffix(".onion").unwrap_or(&lower);
+    // Subdomains are allowed in front of the service name
+    let name = name.rsplit('.').next().unwrap_or(name);
+    if name.len() != 56 {
+        return Err(invalid("v3 onion addresses are 56 characters"));
+    }
+    let bytes = base32_decode(name).ok_or_else(|| invalid("onion address is not base32"))?;
+
+    let mut public_key = [0u8; 32];
+    public_key.copy_from_slice(&bytes[..32]);
+    if bytes[34] != ONION_VERSION {
+        return Err(invalid("not a version 3 onion address"));
+    }
+    if bytes[32..34] != onion_checksum(&public_key) {
+        return Err(invalid("onion address checksum does not match"));
+    }
+    Ok(public_key)
+}
+
+fn onion_checksum(public_key: &[u8; 32]) -> [u8; 2] {
+    let mut data = Vec::with_capacity(48);
+    data.extend_from_slice(b".onion checksum");
+    data.extend_from_slice(public_key);
+    data.push(ONION_VERSION);
+    let digest = hash_bytes_fixed::<Sha3_256>(&data);
+    [digest[0], digest[1]]
+}
+
+fn check_digest_len(algorithm: Algorithm, len: usize) -> Result<()> {
+    if len != algorithm.output_size() {
+        return Err(HashError::InvalidInput(format!(
+            "{} digests are {} bytes, got {}",
+            algorithm.name(),
+            algorithm.output_size(),
+            len
+        )));
+    }
+    Ok(())
+}
+
+/// Lowercase RFC 4648 base32 without padding
+pub(crate) fn base32_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity((bytes.len() * 8 + 4) / 5);
+    let mut buffer = 0u16;
+    let mut bits = 0;
+    for &byte in bytes {
+        buffer = (buffer << 8) | byte as u16;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    out
+}
+
+fn base32_decode(text: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(text.len() * 5 / 8);
+    let mut buffer = 0u16;
+    let mut bits = 0;
+    for c in text.bytes() {
+        let value = BASE32_ALPHABET.iter().position(|&a| a == c)? as u16;
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    Some(out)
+}
@@ -0,0 +1,133 @@
+//! Dropping the line ending text picks up on its way in
+//!
+//! `echo hello`, a here-string, a file saved by most editors and many
+//! `.env` loaders all end text with a newline, or `\r\n` on Windows, that
+//! the user never meant to hash. The digest then matches neither
+//! `printf hello | sha256sum` nor the value published upstream. These
+//! helpers remove exactly one trailing line ending, the one a shell or
+//! editor adds: `\n`, `\r\n`, or a lone `\r`, which is what is left of a
+//! Windows `\r\n` once command substitution (`"$(cat note.txt)"`) has
+//! removed the `\n`. Line endings inside the text and further blank lines
+//! are kept, since they are content.
+//!
+//! ```
+//! use hashing::newline::strip_trailing;
+//! use hashing::{hash_string, Algorithm};
+//!
+//! let typed = "hello\r\n";
+//! assert_eq!(
+//!     hash_string(strip_trailing(typed), Algorithm::Sha256)?,
+//!     hash_string("hello", Algorithm::Sha256)?
+//! );
+//! # Ok::<(), hashing::HashError>(())
+//! ```
+
+use std::io::{self, Read};
+
+/// Bytes read from the inner reader at a time
+const CHUNK_SIZE: usize = 8192;
+
+/// `text` without one trailing `\n`, `\r\n` or `\r`
+pub fn strip_trailing(text: &str) -> &str {
+    let kept = strip_trailing_bytes(text.as_bytes()).len();
+    // Only ASCII was removed, so `kept` is a character boundary
+    &text[..kept]
+}
+
+/// `data` without one trailing `\n`, `\r\n` or `\r`
+pub fn strip_trailing_bytes(data: &[u8]) -> &[u8] {
+    let kept = data.len() - held_back(data);
+    &data[..kept]
+}
+
+/// Length of the line ending at the end of `data`, which a stream holds
+/// back until it knows whether it is the final one
+fn held_back(data: &[u8]) -> usize {
+    if data.ends_with(b"\r\n") {
+        2
+    } else if data.ends_with(b"\n") || data.ends_with(b"\r") {
+        1
+    } else {
+        0
+    }
+}
+
+/// Reader that leaves out one line ending at the very end of `inner`
+///
+/// The streaming form of [`strip_trailing_bytes`], for input such as
+/// stdin that is hashed as it arrives. Up to two bytes are held back until
+/// more data or the end of the stream shows whether they are the final
+/// line ending.
+///
+/// # Examples
+///
+/// ```
+/// use hashing::newline::StripTrailingNewline;
+/// use hashing::{hash_reader, hash_string, Algorithm};
+///
+/// let piped: &[u8] = b"hello\n";
+/// let digest = hash_reader(StripTrailingNewline::new(piped), Algorithm::Sha256)?;
+/// assert_eq!(digest, hash_string("hello", Algorithm::Sha256)?);
+/// # Ok::<(), hashing::HashError>(())
+/// ```
+pub struct StripTrailingNewline<R> {
+    inner: R,
+    buffer: Vec<u8>,
+    /// Unread data is `buffer[pos..]`
+    pos: usize,
+    eof: bool,
+}
+
+impl<R: Read> StripTrailingNewline<R> {
+    /// Read `inner` without its final line ending
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            buffer: Vec::new(),
+            pos: 0,
+            eof: false,
+        }
+    }
+
+    /// The wrapped reader
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for StripTrailingNewline<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let end = if self.eof {
+                self.buffer.len()
+            } else {
+                self.buffer.len() - held_back(&self.buffer[self.pos..])
+            };
+            if self.pos < end || self.eof {
+                let count = buf.len().min(end - self.pos);
+                buf[..count].copy_from_slice(&self.buffer[self.pos..self.pos + count]);
+                self.pos += count;
+                return Ok(count);
+            }
+
+            // Only a possible final line ending is left: keep it, read on
+            self.buffer.drain(..self.pos);
+            self.pos = 0;
+            let held = self.buffer.len();
+            self.buffer.resize(held + CHUNK_SIZE, 0);
+            let count = match self.inner.read(&mut self.buffer[held..]) {
+                Ok(count) => count,
+                Err(e) => {
+                    self.buffer.truncate(held);
+                    return Err(e);
+                }
+            };
+            self.buffer.truncate(held + count);
+            if count == 0 {
+                self.eof = true;
+                let kept = strip_trailing_bytes(&self.buffer).len();
+                self.buffer.truncate(kept);
+            }
+        }
+    }
+}
@@ -0,0 +1,264 @@
+//! Nix hash encodings
+//!
+//! Nix writes the same digest several ways: base16 (hex), base64, its own
+//! base32 variant ("nix32", as in store paths and `fetchurl` hashes), and
+//! SRI (`sha256-<base64>`, the W3C Subresource Integrity form that newer
+//! Nix prefers). [`NixHash::parse`] reads any of them, with or without an
+//! `algorithm:` prefix, and [`NixHash::format`] writes them back.
+//!
+//! ```
+//! use hashing::nix::{HashFormat, NixHash};
+//!
+//! let hash = NixHash::parse("sha256-47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=", None)?;
+//! assert_eq!(
+//!     hash.format(HashFormat::Nix32),
+//!     "0mdqa9w1p6cmli6976v4wi0sw9r4p5prkj7lzfd1877wk11c9c73"
+//! );
+//! # Ok::<(), hashing::HashError>(())
+//! ```
+
+use crate::{Algorithm, HashError, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::fmt;
+use std::str::FromStr;
+
+/// Nix's base32 alphabet: digits and lowercase letters without e, o, t, u
+const NIX32_ALPHABET: &[u8; 32] = b"0123456789abcdfghijklmnpqrsvwxyz";
+
+/// A way of writing a digest
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashFormat {
+    /// Lowercase hex (Nix's base16)
+    Hex,
+    /// Padded standard base64
+    Base64,
+    /// Nix's base32
+    Nix32,
+    /// `<algorithm>-<base64>`
+    Sri,
+}
+
+impl fmt::Display for HashFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            HashFormat::Hex => "hex",
+            HashFormat::Base64 => "base64",
+            HashFormat::Nix32 => "nix32",
+            HashFormat::Sri => "sri",
+        })
+    }
+}
+
+impl FromStr for HashFormat {
+    type Err = HashError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "hex" | "base16" => Ok(HashFormat::Hex),
+            "base64" => Ok(HashFormat::Base64),
+            "nix32" | "base32" => Ok(HashFormat::Nix32),
+            "sri" => Ok(HashFormat::Sri),
+            _ => Err(HashError::InvalidInput(format!(
+                "unknown hash format: {} (expected hex, base64, nix32 or sri)",
+                s
+            ))),
+        }
+    }
+}
+
+/// Name of `algorithm` in Nix and SRI hashes, if it has one
+pub fn algorithm_name(algorithm: Algorithm) -> Option<&'static str> {
+    match algorithm {
+        Algorithm::Md5 => Some("md5"),
+        Algorithm::Sha1 => Some("sha1"),
+        Algorithm::Sha256 => Some("sha256"),
+        Algorithm::Sha384 => Some("sha384"),
+        Algorithm::Sha512 => Some("sha512"),
+        _ => None,
+    }
+}
+
+pub(crate) fn parse_algorithm(name: &str) -> Result<Algorithm> {
+    [
+        Algorithm::Md5,
+        Algorithm::Sha1,
+        Algorithm::Sha256,
+        Algorithm::Sha384,
+        Algorithm::Sha512,
+    ]
+    .into_iter()
+    .find(|&algorithm| algorithm_name(algorithm) == Some(name))
+    .ok_or_else(|| HashError::UnsupportedAlgorithm(format!("unsupported Nix hash type: {}", name)))
+}
+
+/// A digest together with its algorithm
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NixHash {
+    /// Algorithm that produced the digest
+    pub algorithm: Algorithm,
+    /// Raw digest bytes
+    pub digest: Vec<u8>,
+}
+
+impl NixHash {
+    /// Wrap a raw digest, checking its length
+    pub fn new(algorithm: Algorithm, digest: Vec<u8>) -> Result<Self> {
+        algorithm_name(algorithm).ok_or_else(|| {
+            HashError::UnsupportedAlgorithm(format!("{} has no Nix hash type", algorithm.name()))
+        })?;
+        check_len(algorithm, digest.len())?;
+        Ok(Self { algorithm, digest })
+    }
+
+    /// Parse an SRI hash, an `<algorithm>:<digest>` hash, or a bare digest
+    ///
+    /// The encoding of a prefixed or bare digest is told apart by its
+    /// length, as Nix does. A bare digest needs `algorithm`; when the text
+    /// names one too, the two must agree.
+    pub fn parse(text: &str, algorithm: Option<Algorithm>) -> Result<Self> {
+        let text = text.trim();
+        let (named, encoded, sri) = match text.split_once(':') {
+            Some((name, rest)) => (Some(parse_algorithm(name)?), rest, false),
+            None => match text.split_once('-') {
+                Some((name, rest)) => (Some(parse_algorithm(name)?), rest, true),
+                None => (None, text, false),
+            },
+        };
+        let algorithm = match (named, algorithm) {
+            (Some(named), Some(given)) if named != given => {
+                return Err(HashError::InvalidInput(format!(
+                    "{} is a {} hash, not {}",
+                    text,
+                    named.name(),
+                    given.name()
+                )))
+            }
+            (Some(algorithm), _) | (None, Some(algorithm)) => algorithm,
+            (None, None) => {
+                return Err(HashError::InvalidInput(format!(
+                    "{} does not say which algorithm it is",
+                    text
+                )))
+            }
+        };
+
+        let invalid = |encoding: &str| {
+            HashError::InvalidInput(format!(
+                "invalid {} {} hash: {}",
+                encoding,
+                algorithm.name(),
+                text
+            ))
+        };
+        let size = algorithm.output_size();
+        let digest = if sri {
+            STANDARD.decode(encoded).map_err(|_| invalid("SRI"))?
+        } else if encoded.len() == size * 2 {
+            hex::decode(encoded).map_err(|_| invalid("hex"))?
+        } else if encoded.len() == nix32_len(size) {
+            decode_nix32(encoded).map_err(|_| invalid("nix32"))?
+        } else if encoded.len() == (size + 2) / 3 * 4 {
+            STANDARD.decode(encoded).map_err(|_| invalid("base64"))?
+        } else {
+            return Err(HashError::InvalidInput(format!(
+                "{} has the wrong length for a {} hash",
+                text,
+                algorithm.name()
+            )));
+        };
+        Self::new(algorithm, digest)
+    }
+
+    /// Write the digest in `format`
+    pub fn format(&self, format: HashFormat) -> String {
+        match format {
+            HashFormat::Hex => hex::encode(&self.digest),
+            HashFormat::Base64 => STANDARD.encode(&self.digest),
+            HashFormat::Nix32 => encode_nix32(&self.digest),
+            HashFormat::Sri => self.to_sri(),
+        }
+    }
+
+    /// The SRI form, `<algorithm>-<base64>`
+    pub fn to_sri(&self) -> String {
+        // `new` and `parse` only accept algorithms with a name
+        let name = algorithm_name(self.algorithm).unwrap_or_default();
+        format!("{}-{}", name, STANDARD.encode(&self.digest))
+    }
+}
+
+impl fmt::Display for NixHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_sri())
+    }
+}
+
+/// Length of the nix32 encoding of `size` bytes
+pub(crate) fn nix32_len(size: usize) -> usize {
+    if size == 0 {
+        0
+    } else {
+        (size * 8 - 1) / 5 + 1
+    }
+}
+
+/// Encode bytes in Nix's base32
+///
+/// Unlike RFC 4648, Nix reads the bytes from the last bit backwards, so
+/// the first character holds the most significant bits of the last byte.
+pub fn encode_nix32(bytes: &[u8]) -> String {
+    let len = nix32_len(bytes.len());
+    let mut out = String::with_capacity(len);
+    for n in (0..len).rev() {
+        let bit = n * 5;
+        let (i, j) = (bit / 8, bit % 8);
+        let low = bytes[i] >> j;
+        let high = match bytes.get(i + 1) {
+            Some(&next) if j > 0 => next << (8 - j),
+            _ => 0,
+        };
+        out.push(NIX32_ALPHABET[((low | high) & 0x1f) as usize] as char);
+    }
+    out
+}
+
+/// Decode Nix's base32
+pub fn decode_nix32(text: &str) -> Result<Vec<u8>> {
+    let invalid = || HashError::InvalidInput(format!("invalid nix32 string: {}", text));
+    let size = text.len() * 5 / 8;
+    if nix32_len(size) != text.len() {
+        return Err(invalid());
+    }
+    let mut bytes = vec![0u8; size];
+    for (n, c) in text.bytes().rev().enumerate() {
+        let digit = NIX32_ALPHABET
+            .iter()
+            .position(|&a| a == c)
+            .ok_or_else(invalid)? as u16;
+        let bit = n * 5;
+        let (i, j) = (bit / 8, bit % 8);
+        let shifted = digit << j;
+        bytes[i] |= shifted as u8;
+        let carry = (shifted >> 8) as u8;
+        match bytes.get_mut(i + 1) {
+            Some(next) => *next |= carry,
+            // Bits past the last byte must be zero
+            None if carry != 0 => return Err(invalid()),
+            None => {}
+        }
+    }
+    Ok(bytes)
+}
+
+fn check_len(algorithm: Algorithm, len: usize) -> Result<()> {
+    if len != algorithm.output_size() {
+        return Err(HashError::InvalidInput(format!(
+            "{} digests are {} bytes, got {}",
+            algorithm.name(),
+            algorithm.output_size(),
+            len
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,63 @@
+//! Streaming states of the non-cryptographic algorithms
+//!
+//! Digests are the canonical big-endian bytes of the checksum, so their hex
+//! matches `xxhsum`, `cksfv`, zip listings and the other tools that print
+//! these values as numbers.
+
+use crate::Algorithm;
+use xxhash_rust::xxh3::Xxh3Default;
+use xxhash_rust::xxh32::Xxh32;
+use xxhash_rust::xxh64::Xxh64;
+
+/// An xxHash or CRC-32 in progress
+pub(crate) enum Checksum {
+    Xxh32(Xxh32),
+    Xxh64(Xxh64),
+    Xxh3(Box<Xxh3Default>),
+    Xxh128(Box<Xxh3Default>),
+    Crc32(crc32fast::Hasher),
+    Crc32c(u32),
+}
+
+impl Checksum {
+    /// A fresh state, or `None` if `algorithm` is cryptographic
+    pub(crate) fn new(algorithm: Algorithm) -> Option<Self> {
+        Some(match algorithm {
+            Algorithm::Xxh32 => Checksum::Xxh32(Xxh32::new(0)),
+            Algorithm::Xxh64 => Checksum::Xxh64(Xxh64::new(0)),
+            Algorithm::Xxh3 => Checksum::Xxh3(Box::new(Xxh3Default::new())),
+            Algorithm::Xxh128 => Checksum::Xxh128(Box::new(Xxh3Default::new())),
+            Algorithm::Crc32 => Checksum::Crc32(crc32fast::Hasher::new()),
+            Algorithm::Crc32c => Checksum::Crc32c(0),
+            _ => return None,
+        })
+    }
+
+    pub(crate) fn update(&mut self, data: &[u8]) {
+        match self {
+            Checksum::Xxh32(state) => state.update(data),
+            Checksum::Xxh64(state) => state.update(data),
+            Checksum::Xxh3(state) | Checksum::Xxh128(state) => state.update(data),
+            Checksum::Crc32(state) => state.update(data),
+            Checksum::Crc32c(crc) => *crc = crc32c::crc32c_append(*crc, data),
+        }
+    }
+
+    pub(crate) fn finalize(self) -> Vec<u8> {
+        match self {
+            Checksum::Xxh32(state) => state.digest().to_be_bytes().to_vec(),
+            Checksum::Xxh64(state) => state.digest().to_be_bytes().to_vec(),
+            Checksum::Xxh3(state) => state.digest().to_be_bytes().to_vec(),
+            Checksum::Xxh128(state) => state.digest128().to_be_bytes().to_vec(),
+            Checksum::Crc32(state) => state.finalize().to_be_bytes().to_vec(),
+            Checksum::Crc32c(crc) => crc.to_be_bytes().to_vec(),
+        }
+    }
+}
+
+/// Checksum of `data`, or `None` if `algorithm` is cryptographic
+pub(crate) fn digest(algorithm: Algorithm, data: &[u8]) -> Option<Vec<u8>> {
+    let mut state = Checksum::new(algorithm)?;
+    state.update(data);
+    Some(state.finalize())
+}
@@ -0,0 +1,391 @@
+//! Digest checks for Debian and RPM packages
+//!
+//! - A `.deb` lists the MD5 of each installed file in the `md5sums` file of
+//!   its control archive; [`verify_deb`] checks it against the data archive.
+//! - An `.rpm` carries digests of its header and payload in its signature
+//!   header, and of its payload in the main header; [`verify_rpm`] checks
+//!   all of them. Per-file digests inside the payload are not checked, as
+//!   the payload digest already covers them.
+//!
+//! Neither checks a GPG signature. Compressed members may use gzip, xz or
+//! zstd. Results use the [`EntryCheck`] type of the [`jar`](crate::jar)
+//! module. Only available with the `archive` feature.
+//!
+//! ```no_run
+//! use hashing::package::verify_package_file;
+//!
+//! let report = verify_package_file("tool_1.0_amd64.deb")?;
+//! assert!(report.is_ok());
+//! # Ok::<(), hashing::HashError>(())
+//! ```
+
+use crate::builder::{digest_bytes, digest_reader, DEFAULT_BUFFER_SIZE};
+use crate::jar::{EntryCheck, EntryStatus};
+use crate::{Algorithm, HashError, Result};
+use flate2::read::GzDecoder;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{Cursor, Read};
+use std::path::Path;
+
+const AR_MAGIC: &[u8] = b"!<arch>\n";
+const RPM_LEAD_MAGIC: &[u8] = &[0xed, 0xab, 0xee, 0xdb];
+const RPM_HEADER_MAGIC: &[u8] = &[0x8e, 0xad, 0xe8, 0x01];
+const RPM_LEAD_SIZE: usize = 96;
+
+// Signature header tags
+const RPMSIGTAG_SHA1: u32 = 269;
+const RPMSIGTAG_SHA256: u32 = 273;
+const RPMSIGTAG_MD5: u32 = 1004;
+
+// Main header tags
+const RPMTAG_PAYLOADCOMPRESSOR: u32 = 1125;
+const RPMTAG_PAYLOADDIGEST: u32 = 5092;
+const RPMTAG_PAYLOADDIGESTALGO: u32 = 5093;
+const RPMTAG_PAYLOADDIGESTALT: u32 = 5097;
+
+/// Package formats recognized by [`detect_format`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackageFormat {
+    /// Debian binary package (`.deb`, `.udeb`)
+    Deb,
+    /// RPM package (`.rpm`)
+    Rpm,
+}
+
+/// Format of a package, judged by its magic bytes
+pub fn detect_format(data: &[u8]) -> Option<PackageFormat> {
+    if data.starts_with(AR_MAGIC) {
+        Some(PackageFormat::Deb)
+    } else if data.starts_with(RPM_LEAD_MAGIC) {
+        Some(PackageFormat::Rpm)
+    } else {
+        None
+    }
+}
+
+/// Everything checked in a package
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageVerification {
+    /// Format of the package
+    pub format: PackageFormat,
+    /// One check per file (`.deb`) or per embedded digest (`.rpm`)
+    pub checks: Vec<EntryCheck>,
+}
+
+impl PackageVerification {
+    /// Whether nothing mismatched or went missing
+    pub fn is_ok(&self) -> bool {
+        self.checks
+            .iter()
+            .all(|check| matches!(check.status, EntryStatus::Verified | EntryStatus::Unlisted))
+    }
+}
+
+/// Check a `.deb` or `.rpm` held in memory
+pub fn verify_package(data: &[u8]) -> Result<PackageVerification> {
+    match detect_format(data) {
+        Some(PackageFormat::Deb) => verify_deb(data),
+        Some(PackageFormat::Rpm) => verify_rpm(data),
+        None => Err(HashError::InvalidInput(
+            "not a .deb or .rpm package".to_string(),
+        )),
+    }
+}
+
+/// Check a `.deb` or `.rpm` file
+pub fn verify_package_file<P: AsRef<Path>>(path: P) -> Result<PackageVerification> {
+    verify_package(&fs::read(path)?)
+}
+
+/// Check the files of a `.deb` against its `md5sums`
+///
+/// Files without an `md5sums` line (usually conffiles) are reported as
+/// [`EntryStatus::Unlisted`]. A package without `md5sums` has no checks.
+pub fn verify_deb(data: &[u8]) -> Result<PackageVerification> {
+    let members = ar_members(data)?;
+    let member = |prefix: &str| {
+        members
+            .iter()
+            .find(|(name, _)| name.starts_with(prefix))
+            .ok_or_else(|| malformed(&format!("no {} member", prefix)))
+    };
+    let (control_name, control) = member("control.tar")?;
+    let (data_name, payload) = member("data.tar")?;
+
+    let mut expected = BTreeMap::new();
+    let mut control_tar = tar::Archive::new(decompress(control_name, control)?);
+    for entry in control_tar.entries()? {
+        let mut entry = entry?;
+        if normalize_path(&entry.path_bytes()) != "md5sums" {
+            continue;
+        }
+        let mut md5sums = String::new();
+        entry.read_to_string(&mut md5sums)?;
+        for line in md5sums.lines() {
+            let Some((digest, path)) = line.split_once(char::is_whitespace) else {
+                continue;
+            };
+            let digest = hex::decode(digest)
+                .map_err(|_| malformed(&format!("invalid md5sums line: {}", line)))?;
+            expected.insert(normalize_path(path.trim_start().as_bytes()), digest);
+        }
+    }
+
+    let mut checks = Vec::new();
+    if !expected.is_empty() {
+        let mut data_tar = tar::Archive::new(decompress(data_name, payload)?);
+        for entry in data_tar.entries()? {
+            let entry = entry?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let name = normalize_path(&entry.path_bytes());
+            let status = match expected.remove(&name) {
+                Some(digest) => {
+                    let actual = digest_reader(entry, Algorithm::Md5, DEFAULT_BUFFER_SIZE)?;
+                    if actual == digest {
+                        EntryStatus::Verified
+                    } else {
+                        EntryStatus::Mismatch
+                    }
+                }
+                None => EntryStatus::Unlisted,
+            };
+            checks.push(EntryCheck { name, status });
+        }
+        checks.extend(expected.into_keys().map(|name| EntryCheck {
+            name,
+            status: EntryStatus::Missing,
+        }));
+    }
+    Ok(PackageVerification {
+        format: PackageFormat::Deb,
+        checks,
+    })
+}
+
+/// Check the header and payload digests of an `.rpm`
+///
+/// The uncompressed payload digest is only checked when the payload
+/// compressor is supported.
+pub fn verify_rpm(data: &[u8]) -> Result<PackageVerification> {
+    if !data.starts_with(RPM_LEAD_MAGIC) || data.len() < RPM_LEAD_SIZE {
+        return Err(malformed("no RPM lead"));
+    }
+    let signature = RpmHeader::parse(&data[RPM_LEAD_SIZE..])?;
+    let main_start = RPM_LEAD_SIZE + (signature.len + 7) / 8 * 8;
+    let header = RpmHeader::parse(data.get(main_start..).unwrap_or_default())?;
+    let header_bytes = &data[main_start..main_start + header.len];
+    let payload = &data[main_start + header.len..];
+
+    let mut checks = Vec::new();
+    let mut check = |name: String, contents: &[u8], algorithm: Algorithm, expected: &[u8]| {
+        let status = if digest_bytes(contents, algorithm)? == expected {
+            EntryStatus::Verified
+        } else {
+            EntryStatus::Mismatch
+        };
+        checks.push(EntryCheck { name, status });
+        Ok::<_, HashError>(())
+    };
+
+    for (tag, algorithm) in [
+        (RPMSIGTAG_SHA1, Algorithm::Sha1),
+        (RPMSIGTAG_SHA256, Algorithm::Sha256),
+    ] {
+        if let Some(expected) = signature.string(tag) {
+            let expected = decode_hex(expected)?;
+            let name = format!("header {}", algorithm.display_name());
+            check(name, header_bytes, algorithm, &expected)?;
+        }
+    }
+    if let Some(expected) = signature.binary(RPMSIGTAG_MD5) {
+        let name = "header+payload MD5".to_string();
+        check(name, &data[main_start..], Algorithm::Md5, expected)?;
+    }
+
+    let algorithm = match header.int32(RPMTAG_PAYLOADDIGESTALGO) {
+        Some(code) => Some(pgp_hash_algorithm(code)?),
+        None => None,
+    };
+    if let (Some(algorithm), Some(expected)) = (algorithm, header.string(RPMTAG_PAYLOADDIGEST)) {
+        let name = format!("payload {}", algorithm.display_name());
+        check(name, payload, algorithm, &decode_hex(expected)?)?;
+    }
+    if let (Some(algorithm), Some(expected)) = (algorithm, header.string(RPMTAG_PAYLOADDIGESTALT)) {
+        let compressor = header.string(RPMTAG_PAYLOADCOMPRESSOR).unwrap_or("gzip");
+        if let Ok(mut reader) = decompress(compressor, payload) {
+            let mut uncompressed = Vec::new();
+            reader.read_to_end(&mut uncompressed)?;
+            let name = format!("uncompressed payload {}", algorithm.display_name());
+            check(name, &uncompressed, algorithm, &decode_hex(expected)?)?;
+        }
+    }
+
+    Ok(PackageVerification {
+        format: PackageFormat::Rpm,
+        checks,
+    })
+}
+
+fn malformed(what: &str) -> HashError {
+    HashError::InvalidInput(format!("malformed package: {}", what))
+}
+
+fn decode_hex(digest: &str) -> Result<Vec<u8>> {
+    hex::decode(digest.trim()).map_err(|_| malformed(&format!("invalid digest {}", digest)))
+}
+
+/// Path of a tar or `md5sums` entry without its leading `./` or `/`
+fn normalize_path(path: &[u8]) -> String {
+    let path = String::from_utf8_lossy(path);
+    let path = path.strip_prefix("./").unwrap_or(&path);
+    path.trim_start_matches('/').to_string()
+}
+
+/// Algorithm of an OpenPGP hash algorithm number, as RPM records it
+fn pgp_hash_algorithm(code: u32) -> Result<Algorithm> {
+    match code {
+        1 => Ok(Algorithm::Md5),
+        2 => Ok(Algorithm::Sha1),
+        8 => Ok(Algorithm::Sha256),
+        9 => Ok(Algorithm::Sha384),
+        10 => Ok(Algorithm::Sha512),
+        11 => Ok(Algorithm::Sha224),
+        _ => Err(HashError::UnsupportedAlgorithm(format!(
+            "RPM payload digest algorithm {}",
+            code
+        ))),
+    }
+}
+
+/// Reader over a member decompressed according to its name or compressor
+/// (`data.tar.xz`, `zstd`, ...)
+fn decompress<'a>(name: &str, data: &'a [u8]) -> Result<Box<dyn Read + 'a>> {
+    let extension = name.rsplit('.').next().unwrap_or_default();
+    match extension {
+        "tar" | "cpio" => Ok(Box::new(data)),
+        "gz" | "gzip" => Ok(Box::new(GzDecoder::new(data))),
+        "xz" | "lzma" => {
+            let mut input = data;
+            let mut output = Vec::new();
+            let result = if extension == "xz" {
+                lzma_rs::xz_decompress(&mut input, &mut output)
+            } else {
+                lzma_rs::lzma_decompress(&mut input, &mut output)
+            };
+            result.map_err(|e| malformed(&format!("{}: {}", name, e)))?;
+            Ok(Box::new(Cursor::new(output)))
+        }
+        "zst" | "zstd" => {
+            let decoder = ruzstd::StreamingDecoder::new(data)
+                .map_err(|e| malformed(&format!("{}: {}", name, e)))?;
+            Ok(Box::new(decoder))
+        }
+        _ => Err(HashError::UnsupportedAlgorithm(format!(
+            "compression of {}",
+            name
+        ))),
+    }
+}
+
+/// Members of an ar archive as (name, contents)
+fn ar_members(data: &[u8]) -> Result<Vec<(String, &[u8])>> {
+    let mut rest = data
+        .strip_prefix(AR_MAGIC)
+        .ok_or_else(|| malformed("no ar header"))?;
+    let mut members = Vec::new();
+    while rest.len() >= 60 {
+        let header = &rest[..60];
+        if &header[58..60] != b"`\n" {
+            return Err(malformed("bad ar member header"));
+        }
+        let name = String::from_utf8_lossy(&header[..16]);
+        let name = name.trim_end().trim_end_matches('/').to_string();
+        let size = std::str::from_utf8(&header[48..58])
+            .ok()
+            .and_then(|size| size.trim().parse::<usize>().ok())
+            .ok_or_else(|| malformed("bad ar member size"))?;
+        let contents = rest
+            .get(60..60 + size)
+            .ok_or_else(|| malformed("truncated ar member"))?;
+        members.push((name, contents));
+        rest = rest.get(60 + size + size % 2..).unwrap_or_default();
+    }
+    Ok(members)
+}
+
+/// An RPM header structure: index entries and the data store they point into
+struct RpmHeader<'a> {
+    index: &'a [u8],
+    store: &'a [u8],
+    /// Length of the whole header structure, from its magic to the end of
+    /// the store
+    len: usize,
+}
+
+impl<'a> RpmHeader<'a> {
+    fn parse(data: &'a [u8]) -> Result<Self> {
+        if !data.starts_with(RPM_HEADER_MAGIC) || data.len() < 16 {
+            return Err(malformed("bad RPM header"));
+        }
+        let count = read_u32_be(data, 8).unwrap_or_default() as usize;
+        let store_len = read_u32_be(data, 12).unwrap_or_default() as usize;
+        let store_start = count
+            .checked_mul(16)
+            .and_then(|n| n.checked_add(16))
+            .ok_or_else(|| malformed("bad RPM header"))?;
+        let len = store_start
+            .checked_add(store_len)
+            .filter(|&len| len <= data.len())
+            .ok_or_else(|| malformed("truncated RPM header"))?;
+        Ok(Self {
+            index: &data[16..store_start],
+            store: &data[store_start..len],
+            len,
+        })
+    }
+
+    /// Type, offset and count of a tag's entry
+    fn entry(&self, tag: u32) -> Option<(u32, usize, usize)> {
+        self.index.chunks_exact(16).find_map(|entry| {
+            (read_u32_be(entry, 0)? == tag).then_some((
+                read_u32_be(entry, 4)?,
+                read_u32_be(entry, 8)? as usize,
+                read_u32_be(entry, 12)? as usize,
+            ))
+        })
+    }
+
+    fn int32(&self, tag: u32) -> Option<u32> {
+        match self.entry(tag)? {
+            (4, offset, _) => read_u32_be(self.store, offset),
+            _ => None,
+        }
+    }
+
+    /// A STRING value, or the first element of a STRING_ARRAY
+    fn string(&self, tag: u32) -> Option<&'a str> {
+        match self.entry(tag)? {
+            (6 | 8 | 9, offset, _) => {
+                let bytes = self.store.get(offset..)?;
+                let end = bytes.iter().position(|&b| b == 0)?;
+                std::str::from_utf8(&bytes[..end]).ok()
+            }
+            _ => None,
+        }
+    }
+
+    fn binary(&self, tag: u32) -> Option<&'a [u8]> {
+        match self.entry(tag)? {
+            (7, offset, count) => self.store.get(offset..offset.checked_add(count)?),
+            _ => None,
+        }
+    }
+}
+
+fn read_u32_be(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
@@ -0,0 +1,261 @@
+//! Keeping file reads honest about the page cache
+//!
+//! Reading a file that was read recently usually returns the copy the
+//! operating system keeps in memory, not the bytes on disk. Checks meant to
+//! catch bit rot have to get past that copy: [`drop_cached`] asks the kernel
+//! to discard it so the next read goes to the device, and [`UncachedReader`]
+//! reads without using the cache at all. The other way round,
+//! [`ReadAheadReader`] keeps the cache filled ahead of a sequential read.
+//!
+//! ```no_run
+//! use hashing::pagecache::{self, UncachedReader};
+//! use std::fs::File;
+//! use std::io::Read;
+//!
+//! let file = File::open("archive.tar")?;
+//! if !pagecache::drop_cached(&file)? {
+//!     eprintln!("the next read may come from the page cache");
+//! }
+//!
+//! let mut reader = UncachedReader::open("archive.tar")?;
+//! let mut contents = Vec::new();
+//! reader.read_to_end(&mut contents)?;
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Alignment of buffers, offsets and lengths for `O_DIRECT` reads; a
+/// multiple of every common logical block size
+const DIRECT_ALIGN: usize = 4096;
+
+/// Bytes requested from the file per uncached read
+const DIRECT_READ_SIZE: usize = 1 << 20;
+
+/// Evict `file`'s pages from the page cache, returning whether the
+/// platform supports it
+///
+/// On Linux and Android this is `posix_fadvise(POSIX_FADV_DONTNEED)` over
+/// the whole file. Pages another process has modified but not yet written
+/// back stay cached. Elsewhere nothing is done and `false` is returned.
+pub fn drop_cached(file: &File) -> io::Result<bool> {
+    fadvise(file, 0, 0, Advice::DontNeed)
+}
+
+/// Tell the kernel `file` will be read from start to end, returning whether
+/// the platform supports it
+///
+/// On Linux this doubles the read-ahead window for the file. The hint only
+/// changes speed, never what is read.
+pub fn advise_sequential(file: &File) -> io::Result<bool> {
+    fadvise(file, 0, 0, Advice::Sequential)
+}
+
+/// Ask the kernel to start reading `len` bytes of `file` at `offset` into
+/// the page cache in the background, returning whether the platform
+/// supports it
+pub fn prefetch(file: &File, offset: u64, len: u64) -> io::Result<bool> {
+    fadvise(file, offset, len, Advice::WillNeed)
+}
+
+/// Reader over a file that keeps the kernel fetching `window` bytes ahead
+/// of it
+///
+/// Every time less than half the window is left in flight, the next part
+/// is requested with [`prefetch`]. Storage with high latency but plenty of
+/// bandwidth, like a NAS, then streams instead of waiting on each read. If
+/// the platform or file (a pipe, say) does not take the hint, it reads
+/// like the plain file.
+pub struct ReadAheadReader {
+    file: File,
+    window: u64,
+    /// Bytes read so far
+    offset: u64,
+    /// End of the range requested so far
+    requested: u64,
+}
+
+impl ReadAheadReader {
+    /// Read `file` from its start with a read-ahead `window` in bytes
+    pub fn new(file: File, window: u64) -> Self {
+        // A file that cannot take the first hint will not take the others
+        let window = match advise_sequential(&file) {
+            Ok(true) => window,
+            _ => 0,
+        };
+        Self {
+            file,
+            window,
+            offset: 0,
+            requested: 0,
+        }
+    }
+}
+
+impl Read for ReadAheadReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.window > 0 && self.requested.saturating_sub(self.offset) < self.window / 2 {
+            let start = self.requested.max(self.offset);
+            let end = self.offset.saturating_add(self.window);
+            if prefetch(&self.file, start, end - start).is_err() {
+                self.window = 0;
+            }
+            self.requested = end;
+        }
+        let count = self.file.read(buf)?;
+        self.offset += count as u64;
+        Ok(count)
+    }
+}
+
+/// Access patterns `posix_fadvise` is told about
+#[derive(Debug, Clone, Copy)]
+enum Advice {
+    Sequential,
+    WillNeed,
+    DontNeed,
+}
+
+/// `posix_fadvise` over `len` bytes (0 for "to the end") at `offset`
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn fadvise(file: &File, offset: u64, len: u64, advice: Advice) -> io::Result<bool> {
+    use std::os::unix::io::AsRawFd;
+
+    let advice = match advice {
+        Advice::Sequential => libc::POSIX_FADV_SEQUENTIAL,
+        Advice::WillNeed => libc::POSIX_FADV_WILLNEED,
+        Advice::DontNeed => libc::POSIX_FADV_DONTNEED,
+    };
+    let offset = libc::off_t::try_from(offset).unwrap_or(libc::off_t::MAX);
+    let len = libc::off_t::try_from(len).unwrap_or(libc::off_t::MAX);
+    // SAFETY: the descriptor is owned by `file` and valid for this call;
+    // posix_fadvise only reads its arguments
+    let result = unsafe { libc::posix_fadvise(file.as_raw_fd(), offset, len, advice) };
+    if result != 0 {
+        // posix_fadvise returns the error number instead of setting errno
+        return Err(io::Error::from_raw_os_error(result));
+    }
+    Ok(true)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn fadvise(_file: &File, _offset: u64, _len: u64, _advice: Advice) -> io::Result<bool> {
+    Ok(false)
+}
+
+/// Reader over a file that bypasses the page cache where the platform can
+///
+/// - Linux and Android open the file with `O_DIRECT` and read it in aligned
+///   1 MiB blocks. File systems without `O_DIRECT` support (tmpfs, some
+///   network and FUSE mounts) fall back to a normal read after dropping the
+///   file's cached pages.
+/// - macOS sets `F_NOCACHE` on the file.
+/// - Elsewhere the file is read normally.
+///
+/// [`UncachedReader::bypasses_cache`] tells which happened.
+pub struct UncachedReader {
+    file: File,
+    bypasses_cache: bool,
+    direct: bool,
+    buffer: Vec<u8>,
+    /// Offset of the first aligned byte in `buffer`
+    aligned: usize,
+    /// Unread data is `buffer[pos..end]`
+    pos: usize,
+    end: usize,
+}
+
+impl UncachedReader {
+    /// Open `path` for reading past the page cache
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let (file, direct) = open_direct(path.as_ref())?;
+        let bypasses_cache = direct || set_nocache(&file)?;
+        let (buffer, aligned) = if direct {
+            let buffer = vec![0u8; DIRECT_READ_SIZE + DIRECT_ALIGN];
+            let aligned = buffer.as_ptr().align_offset(DIRECT_ALIGN);
+            (buffer, aligned)
+        } else {
+            (Vec::new(), 0)
+        };
+        Ok(Self {
+            file,
+            bypasses_cache,
+            direct,
+            buffer,
+            aligned,
+            pos: 0,
+            end: 0,
+        })
+    }
+
+    /// Whether reads really bypass the page cache
+    pub fn bypasses_cache(&self) -> bool {
+        self.bypasses_cache
+    }
+}
+
+impl Read for UncachedReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if !self.direct {
+            return self.file.read(buf);
+        }
+        if self.pos == self.end {
+            // O_DIRECT needs an aligned buffer and length, so read into our
+            // own block and copy out of it
+            let block = &mut self.buffer[self.aligned..self.aligned + DIRECT_READ_SIZE];
+            let count = self.file.read(block)?;
+            self.pos = self.aligned;
+            self.end = self.aligned + count;
+        }
+        let count = buf.len().min(self.end - self.pos);
+        buf[..count].copy_from_slice(&self.buffer[self.pos..self.pos + count]);
+        self.pos += count;
+        Ok(count)
+    }
+}
+
+/// Open `path` with `O_DIRECT`, or normally with its cache dropped where the
+/// file system refuses; the flag says whether `O_DIRECT` is in effect
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn open_direct(path: &Path) -> io::Result<(File, bool)> {
+    use std::fs::OpenOptions;
+    use std::os::unix::fs::OpenOptionsExt;
+
+    match OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_DIRECT)
+        .open(path)
+    {
+        Ok(file) => Ok((file, true)),
+        Err(e) if e.raw_os_error() == Some(libc::EINVAL) => {
+            let file = File::open(path)?;
+            drop_cached(&file)?;
+            Ok((file, false))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn open_direct(path: &Path) -> io::Result<(File, bool)> {
+    Ok((File::open(path)?, false))
+}
+
+/// Turn off caching for `file` with `F_NOCACHE`
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+fn set_nocache(file: &File) -> io::Result<bool> {
+    use std::os::unix::io::AsRawFd;
+
+    // SAFETY: the descriptor is owned by `file` and valid for this call
+    if unsafe { libc::fcntl(file.as_raw_fd(), libc::F_NOCACHE, 1) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(true)
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "ios")))]
+fn set_nocache(_file: &File) -> io::Result<bool> {
+    Ok(false)
+}
@@ -0,0 +1,91 @@
+//! Throughput calibration (feature `perf`)
+//!
+//! How fast each algorithm runs depends on the CPU it lands on: SHA-256 is
+//! several times faster with SHA extensions, BLAKE3 with AVX-512. An
+//! application that only needs "a secure digest, as fast as possible" can
+//! measure its candidates once at startup with [`measure_throughput`] and
+//! pick with [`fastest`], instead of guessing at build time.
+//!
+//! ```
+//! use hashing::perf::{fastest, measure_throughput};
+//! use hashing::Algorithm;
+//!
+//! let sha256 = measure_throughput(Algorithm::Sha256, 1 << 20);
+//! assert_eq!(sha256.bytes, 1 << 20);
+//! assert!(sha256.bytes_per_second() > 0.0);
+//!
+//! let pick = fastest(&[Algorithm::Sha256, Algorithm::Blake3], 1 << 20).unwrap();
+//! println!("using {} at {:.0} MB/s", pick.algorithm.name(), pick.bytes_per_second() / 1e6);
+//! ```
+
+use crate::{hash_bytes, Algorithm};
+use std::time::{Duration, Instant};
+
+/// Bytes hashed before timing starts, so lazy initialization and cold
+/// caches do not count against an algorithm
+const WARM_UP_SIZE: usize = 64 * 1024;
+
+/// Byte the measured buffer is filled with
+const FILL: u8 = 0xA5;
+
+/// One timed run of an algorithm over an in-memory buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Throughput {
+    /// Algorithm measured
+    pub algorithm: Algorithm,
+    /// Bytes hashed
+    pub bytes: u64,
+    /// Time the hashing took
+    pub elapsed: Duration,
+}
+
+impl Throughput {
+    /// Bytes hashed per second; 0 for an empty run
+    pub fn bytes_per_second(&self) -> f64 {
+        if self.bytes == 0 {
+            return 0.0;
+        }
+        // A clock too coarse to see the run still must not divide by zero
+        let seconds = self.elapsed.as_secs_f64().max(1e-9);
+        self.bytes as f64 / seconds
+    }
+
+    /// Whether the run reached `bytes_per_second`, for regression guards
+    pub fn at_least(&self, bytes_per_second: f64) -> bool {
+        self.bytes_per_second() >= bytes_per_second
+    }
+}
+
+/// Time `algorithm` hashing `size` bytes held in memory
+///
+/// No I/O is involved, so this is the ceiling the algorithm can reach on
+/// this machine. A few megabytes give a stable figure in milliseconds.
+pub fn measure_throughput(algorithm: Algorithm, size: usize) -> Throughput {
+    let data = vec![FILL; size];
+    let warm_up = &data[..size.min(WARM_UP_SIZE)];
+    // Hashing an in-memory slice performs no I/O and cannot fail
+    hash_bytes(warm_up, algorithm).expect("in-memory digest");
+
+    let start = Instant::now();
+    hash_bytes(&data, algorithm).expect("in-memory digest");
+    Throughput {
+        algorithm,
+        bytes: size as u64,
+        elapsed: start.elapsed(),
+    }
+}
+
+/// Measure every candidate over `size` bytes, fastest first
+pub fn rank(candidates: &[Algorithm], size: usize) -> Vec<Throughput> {
+    let mut results: Vec<Throughput> = candidates
+        .iter()
+        .map(|&algorithm| measure_throughput(algorithm, size))
+        .collect();
+    results.sort_by(|a, b| b.bytes_per_second().total_cmp(&a.bytes_per_second()));
+    results
+}
+
+/// The fastest of `candidates` over `size` bytes, or `None` if there are none
+pub fn fastest(candidates: &[Algorithm], size: usize) -> Option<Throughput> {
+    rank(candidates, size).into_iter().next()
+}
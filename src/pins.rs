@@ -0,0 +1,131 @@
+//! Digests pinned in a repository file
+//!
+//! A pins file lists files whose contents must not change unnoticed
+//! (vendored binaries, lock files, downloaded toolchains) together with
+//! their expected digests. Paths are relative to the directory holding the
+//! pins file, normally the repository root, and digests take any form
+//! [`crate::convert::parse_digest`] reads; bare digests use the file's
+//! `algorithm` (default SHA-256).
+//!
+//! ```toml
+//! algorithm = "sha256"
+//!
+//! [pins]
+//! "vendor/protoc" = "sha256:9f2c0e8d..."
+//! "Cargo.lock" = "blake3:4be1a7c2..."
+//! ```
+//!
+//! [`Pins::check`] recomputes every pinned digest; `hash hook pre-commit`
+//! runs it from a Git pre-commit hook.
+
+use crate::convert::{format_digest, parse_digest, DigestFormat};
+use crate::{builder::digest_reader, builder::DEFAULT_BUFFER_SIZE, Algorithm, HashError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+
+/// Conventional name of the pins file at the repository root
+pub const PINS_FILE: &str = ".hashpins.toml";
+
+/// Contents of a pins file
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Pins {
+    /// Algorithm of bare digests
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub algorithm: Option<String>,
+    /// Expected digest by path
+    #[serde(default)]
+    pub pins: BTreeMap<String, String>,
+}
+
+/// Outcome of checking one pinned file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PinStatus {
+    /// The file has the pinned digest
+    Verified,
+    /// The file's digest differs
+    Mismatch,
+    /// The file does not exist
+    Missing,
+}
+
+/// Result for one pinned file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PinCheck {
+    /// Path as written in the pins file
+    pub path: String,
+    /// What the check found
+    pub status: PinStatus,
+}
+
+impl Pins {
+    /// Parse a pins file from TOML text
+    pub fn from_toml(text: &str) -> Result<Self> {
+        toml::from_str(text).map_err(|e| HashError::InvalidInput(e.to_string()))
+    }
+
+    /// Read and parse the pins file at `path`
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        Self::from_toml(&text).map_err(|e| {
+            HashError::InvalidInput(format!("invalid pins file {}: {}", path.display(), e))
+        })
+    }
+
+    /// Write the pins file to `path`
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let text = toml::to_string(self).map_err(|e| HashError::ExportError(e.to_string()))?;
+        fs::write(path, text)?;
+        Ok(())
+    }
+
+    /// Algorithm of bare digests
+    pub fn default_algorithm(&self) -> Result<Algorithm> {
+        match &self.algorithm {
+            Some(name) => name.parse(),
+            None => Ok(Algorithm::Sha256),
+        }
+    }
+
+    /// Pin `path` (relative to `root`) to its current digest
+    pub fn pin<P: AsRef<Path>>(&mut self, root: P, path: &str, algorithm: Algorithm) -> Result<()> {
+        let digest = file_digest(&root.as_ref().join(path), algorithm)?;
+        let value = format_digest(algorithm, &digest, DigestFormat::Prefixed)?;
+        self.pins.insert(path.to_string(), value);
+        Ok(())
+    }
+
+    /// Check every pinned file below `root`, in path order
+    ///
+    /// Fails on a pin whose digest cannot be parsed, so a typo in the file
+    /// does not pass silently.
+    pub fn check<P: AsRef<Path>>(&self, root: P) -> Result<Vec<PinCheck>> {
+        let root = root.as_ref();
+        let default = self.default_algorithm()?;
+        let mut checks = Vec::with_capacity(self.pins.len());
+        for (path, expected) in &self.pins {
+            // Digests that name their algorithm may use any, bare ones the default
+            let (algorithm, expected) = parse_digest(expected, Some(default))
+                .or_else(|e| parse_digest(expected, None).map_err(|_| e))
+                .map_err(|e| HashError::InvalidInput(format!("pin for {}: {}", path, e)))?;
+            let status = match file_digest(&root.join(path), algorithm) {
+                Ok(actual) if actual == expected => PinStatus::Verified,
+                Ok(_) => PinStatus::Mismatch,
+                Err(HashError::Io(e)) if e.kind() == io::ErrorKind::NotFound => PinStatus::Missing,
+                Err(e) => return Err(e),
+            };
+            checks.push(PinCheck {
+                path: path.clone(),
+                status,
+            });
+        }
+        Ok(checks)
+    }
+}
+
+fn file_digest(path: &Path, algorithm: Algorithm) -> Result<Vec<u8>> {
+    digest_reader(File::open(path)?, algorithm, DEFAULT_BUFFER_SIZE)
+}
@@ -0,0 +1,343 @@
+//! Filling in digests of package-manager manifests
+//!
+//! Homebrew formulae, Scoop manifests and winget installer manifests name
+//! each download by URL with its SHA-256 next to it:
+//!
+//! | Format | URL | Digest |
+//! |--------|-----|--------|
+//! | Homebrew (`.rb`) | `url "..."` | `sha256 "..."` |
+//! | Scoop (`.json`) | `"url": "..."` | `"hash": "..."` |
+//! | winget (`.yaml`) | `InstallerUrl: ...` | `InstallerSha256: ...` |
+//!
+//! [`fill_manifest`] edits those fields in place, leaving the rest of the
+//! text untouched. Each digest belongs to the nearest URL in the file, and
+//! an artifact fills the digests whose URL ends in its file name; a single
+//! artifact also fills a lone digest field whatever its URL. Empty and
+//! placeholder values are replaced, real ones are checked.
+//!
+//! ```no_run
+//! use hashing::pkgmanifest::{fill_manifest, ManifestFormat};
+//!
+//! let text = std::fs::read_to_string("Formula/tool.rb")?;
+//! let fill = fill_manifest(&text, ManifestFormat::Homebrew, &["tool-1.2.tar.gz"], false)?;
+//! assert!(fill.is_ok());
+//! std::fs::write("Formula/tool.rb", &fill.text)?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use crate::{hash_file, Algorithm, HashError, Result};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Package-manager manifest formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ManifestFormat {
+    /// Homebrew formula or cask (Ruby)
+    Homebrew,
+    /// Scoop app manifest (JSON)
+    Scoop,
+    /// winget installer manifest (YAML)
+    Winget,
+}
+
+impl ManifestFormat {
+    /// Format implied by a manifest's file extension
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "rb" => Some(ManifestFormat::Homebrew),
+            "json" => Some(ManifestFormat::Scoop),
+            "yaml" | "yml" => Some(ManifestFormat::Winget),
+            _ => None,
+        }
+    }
+
+    fn url_key(self) -> &'static str {
+        match self {
+            ManifestFormat::Homebrew => "url",
+            ManifestFormat::Scoop => "url",
+            ManifestFormat::Winget => "InstallerUrl",
+        }
+    }
+
+    fn digest_key(self) -> &'static str {
+        match self {
+            ManifestFormat::Homebrew => "sha256",
+            ManifestFormat::Scoop => "hash",
+            ManifestFormat::Winget => "InstallerSha256",
+        }
+    }
+
+    /// Byte range of the quoted or bare value of `key` on `line`
+    fn value_range(self, line: &str, key: &str) -> Option<(usize, usize)> {
+        let line = line.trim_end_matches(['\n', '\r']);
+        let indent = line.len() - line.trim_start().len();
+        let rest = match self {
+            ManifestFormat::Homebrew => {
+                let rest = line[indent..].strip_prefix(key)?;
+                let value = rest.trim_start();
+                if value.len() == rest.len() {
+                    return None;
+                }
+                value
+            }
+            ManifestFormat::Scoop => {
+                let quoted = format!("\"{}\"", key);
+                let start = line.find(&quoted)? + quoted.len();
+                line[start..].trim_start().strip_prefix(':')?.trim_start()
+            }
+            ManifestFormat::Winget => {
+                let item = line[indent..].strip_prefix("- ").unwrap_or(&line[indent..]);
+                item.trim_start()
+                    .strip_prefix(key)?
+                    .strip_prefix(':')?
+                    .trim_start()
+            }
+        };
+        let start = line.len() - rest.len();
+        match rest.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                let len = rest[1..].find(quote)?;
+                Some((start + 1, start + 1 + len))
+            }
+            // Homebrew and Scoop values are always quoted
+            _ if self != ManifestFormat::Winget => None,
+            _ => {
+                let value = rest.split(" #").next().unwrap_or(rest).trim_end();
+                Some((start, start + value.len()))
+            }
+        }
+    }
+}
+
+impl fmt::Display for ManifestFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ManifestFormat::Homebrew => "homebrew",
+            ManifestFormat::Scoop => "scoop",
+            ManifestFormat::Winget => "winget",
+        })
+    }
+}
+
+impl FromStr for ManifestFormat {
+    type Err = HashError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "homebrew" | "brew" => Ok(ManifestFormat::Homebrew),
+            "scoop" => Ok(ManifestFormat::Scoop),
+            "winget" => Ok(ManifestFormat::Winget),
+            _ => Err(HashError::InvalidInput(format!(
+                "unknown manifest format: {} (expected homebrew, scoop or winget)",
+                s
+            ))),
+        }
+    }
+}
+
+/// What happened to one digest field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldStatus {
+    /// An empty or placeholder value was filled in
+    Filled,
+    /// The existing value matches the artifact
+    Verified,
+    /// The existing value differs and was left alone
+    Mismatch,
+    /// The existing value differed and was replaced (forced)
+    Updated,
+    /// No artifact belongs to this field's URL
+    Unmatched,
+}
+
+/// One digest field of the manifest
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldFill {
+    /// 1-based line number
+    pub line: usize,
+    /// The URL the field belongs to, if any
+    pub url: Option<String>,
+    /// The artifact used, if any
+    pub artifact: Option<PathBuf>,
+    /// The value before filling
+    pub previous: String,
+    /// What happened
+    pub status: FieldStatus,
+}
+
+/// Result of [`fill_manifest`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestFill {
+    /// The manifest text with digests filled in
+    pub text: String,
+    /// Every digest field, in file order
+    pub fields: Vec<FieldFill>,
+}
+
+impl ManifestFill {
+    /// True when no existing value contradicted an artifact
+    pub fn is_ok(&self) -> bool {
+        self.fields
+            .iter()
+            .all(|field| field.status != FieldStatus::Mismatch)
+    }
+
+    /// True when the text differs from the input
+    pub fn changed(&self) -> bool {
+        self.fields
+            .iter()
+            .any(|field| matches!(field.status, FieldStatus::Filled | FieldStatus::Updated))
+    }
+}
+
+/// Fill the digest fields of manifest `text` from `artifacts`
+///
+/// A mismatching value is reported as [`FieldStatus::Mismatch`] and kept,
+/// unless `force` is set. Fails when the manifest has no digest field or an
+/// artifact belongs to none of them.
+pub fn fill_manifest<P: AsRef<Path>>(
+    text: &str,
+    format: ManifestFormat,
+    artifacts: &[P],
+    force: bool,
+) -> Result<ManifestFill> {
+    let mut lines: Vec<String> = text.split_inclusive('\n').map(str::to_string).collect();
+    let url_key = format.url_key();
+    let urls: Vec<(usize, String)> = lines
+        .iter()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let (start, end) = format.value_range(line, url_key)?;
+            Some((index, line[start..end].to_string()))
+        })
+        .collect();
+    let digest_key = format.digest_key();
+    let fields: Vec<(usize, (usize, usize))> = lines
+        .iter()
+        .enumerate()
+        .filter_map(|(index, line)| Some((index, format.value_range(line, digest_key)?)))
+        .collect();
+    if fields.is_empty() {
+        return Err(HashError::InvalidInput(format!(
+            "no {} fields in the {} manifest",
+            digest_key, format
+        )));
+    }
+
+    let mut used = vec![false; artifacts.len()];
+    let mut digests: HashMap<(usize, Algorithm), String> = HashMap::new();
+    let mut fills = Vec::with_capacity(fields.len());
+    for &(index, (start, end)) in &fields {
+        let url = nearest_url(&urls, index);
+        let matched = artifacts.iter().position(|artifact| {
+            url.is_some_and(|url| url_file_name(url) == file_name(artifact.as_ref()))
+        });
+        let matched = matched.or_else(|| (artifacts.len() == 1 && fields.len() == 1).then_some(0));
+        let previous = lines[index][start..end].to_string();
+        let Some(artifact) = matched else {
+            fills.push(FieldFill {
+                line: index + 1,
+                url: url.map(str::to_string),
+                artifact: None,
+                previous,
+                status: FieldStatus::Unmatched,
+            });
+            continue;
+        };
+        used[artifact] = true;
+
+        // Scoop allows other algorithms as `sha512:<hex>`
+        let (prefix, current) = match previous.split_once(':') {
+            Some((name, rest)) if format == ManifestFormat::Scoop => (Some(name), rest),
+            _ => (None, previous.as_str()),
+        };
+        let algorithm = match prefix {
+            Some(name) => name.parse()?,
+            None => Algorithm::Sha256,
+        };
+        let digest = match digests.get(&(artifact, algorithm)) {
+            Some(digest) => digest.clone(),
+            None => {
+                let digest = hash_file(artifacts[artifact].as_ref(), algorithm)?;
+                digests.insert((artifact, algorithm), digest.clone());
+                digest
+            }
+        };
+
+        let status = if is_placeholder(current, algorithm) {
+            FieldStatus::Filled
+        } else if current.eq_ignore_ascii_case(&digest) {
+            FieldStatus::Verified
+        } else if force {
+            FieldStatus::Updated
+        } else {
+            FieldStatus::Mismatch
+        };
+        if matches!(status, FieldStatus::Filled | FieldStatus::Updated) {
+            // winget writes digests in upper case
+            let digest = match format {
+                ManifestFormat::Winget => digest.to_ascii_uppercase(),
+                _ => digest,
+            };
+            let value = match prefix {
+                Some(name) => format!("{}:{}", name, digest),
+                None => digest,
+            };
+            // A bare YAML key (`InstallerSha256:`) needs a space before the value
+            let value = if lines[index][..start].ends_with(':') {
+                format!(" {}", value)
+            } else {
+                value
+            };
+            lines[index].replace_range(start..end, &value);
+        }
+        fills.push(FieldFill {
+            line: index + 1,
+            url: url.map(str::to_string),
+            artifact: Some(artifacts[artifact].as_ref().to_path_buf()),
+            previous,
+            status,
+        });
+    }
+
+    if let Some(unused) = used.iter().position(|used| !used) {
+        return Err(HashError::InvalidInput(format!(
+            "no {} field belongs to {}",
+            digest_key,
+            artifacts[unused].as_ref().display()
+        )));
+    }
+    Ok(ManifestFill {
+        text: lines.concat(),
+        fields: fills,
+    })
+}
+
+/// The URL on the line closest to `index`, preferring the one before it
+fn nearest_url(urls: &[(usize, String)], index: usize) -> Option<&str> {
+    urls.iter()
+        .min_by_key(|(line, _)| (line.abs_diff(index), *line > index))
+        .map(|(_, url)| url.as_str())
+}
+
+fn url_file_name(url: &str) -> &str {
+    let url = url.split(['?', '#']).next().unwrap_or(url);
+    url.rsplit('/').next().unwrap_or(url)
+}
+
+fn file_name(path: &Path) -> &str {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default()
+}
+
+/// Anything that is not a digest of the right length (empty, `TODO`,
+/// `<sha256>`, ...) or is all zeros
+fn is_placeholder(value: &str, algorithm: Algorithm) -> bool {
+    let is_digest = value.len() == algorithm.output_size() * 2
+        && value.bytes().all(|byte| byte.is_ascii_hexdigit());
+    !is_digest || value.bytes().all(|byte| byte == b'0')
+}
@@ -0,0 +1,257 @@
+//! External export-format plugins
+//!
+//! A format plugin is an executable that turns hash results into a file
+//! format the crate does not know, such as an internal evidence record or
+//! a ticketing payload, so organizations can add one without forking the
+//! CLI. The protocol is deliberately small:
+//!
+//! - The plugin for format `NAME` is the program registered under `NAME`
+//!   in the `[formats]` table of the config file, or else an executable
+//!   named `hash-format-NAME` on the `PATH`.
+//! - It runs with `HASH_PLUGIN_PROTOCOL` set to [`PROTOCOL_VERSION`] and
+//!   `HASH_PLUGIN_FORMAT` set to `NAME`.
+//! - Its standard input receives one JSON object per result, as written by
+//!   [`crate::HashResult::to_json_line`], and is closed after the last one.
+//! - Whatever it writes to standard output is the export; its standard
+//!   error is passed through, and a non-zero exit status is an error.
+//!
+//! ```no_run
+//! use hashing::plugin::FormatPlugin;
+//! use hashing::{hash_string, Algorithm, HashResult};
+//! use std::collections::BTreeMap;
+//! use std::fs::File;
+//!
+//! let plugin = FormatPlugin::find("evidence", &BTreeMap::new())?;
+//! let digest = hash_string("hello", Algorithm::Sha256)?;
+//! let result = HashResult::new(Algorithm::Sha256, digest, "string");
+//! plugin.render(&[result], File::create("evidence.xml")?)?;
+//! # Ok::<(), hashing::HashError>(())
+//! ```
+
+use crate::{HashError, HashResult, Result};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+
+/// Version of the plugin protocol, passed in [`PROTOCOL_ENV`]
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Environment variable carrying the protocol version
+pub const PROTOCOL_ENV: &str = "HASH_PLUGIN_PROTOCOL";
+
+/// Environment variable carrying the requested format name
+pub const FORMAT_ENV: &str = "HASH_PLUGIN_FORMAT";
+
+/// Prefix of plugin executables found on the `PATH`
+pub const PLUGIN_PREFIX: &str = "hash-format-";
+
+/// An export format implemented by an external program
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatPlugin {
+    name: String,
+    program: PathBuf,
+}
+
+impl FormatPlugin {
+    /// A plugin for format `name` run as `program`
+    pub fn new<P: Into<PathBuf>>(name: &str, program: P) -> Result<Self> {
+        check_name(name)?;
+        Ok(Self {
+            name: name.to_string(),
+            program: program.into(),
+        })
+    }
+
+    /// The plugin for `name`: the program `configured` maps it to (the
+    /// config file's `[formats]` table), or `hash-format-NAME` on the `PATH`
+    pub fn find(name: &str, configured: &BTreeMap<String, PathBuf>) -> Result<Self> {
+        check_name(name)?;
+        if let Some(program) = configured.get(name) {
+            return Self::new(name, program);
+        }
+        let file_name = format!("{}{}{}", PLUGIN_PREFIX, name, std::env::consts::EXE_SUFFIX);
+        path_dirs()
+            .map(|dir| dir.join(&file_name))
+            .find(|candidate| is_executable(candidate))
+            .map(|program| Self {
+                name: name.to_string(),
+                program,
+            })
+            .ok_or_else(|| {
+                HashError::InvalidInput(format!(
+                    "no format plugin {}: add it to [formats] in the config file \
+                     or put {} on the PATH",
+                    name, file_name
+                ))
+            })
+    }
+
+    /// Format name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Program run for the format
+    pub fn program(&self) -> &Path {
+        &self.program
+    }
+
+    /// Start the plugin with its standard output going to `output`
+    pub fn spawn(&self, output: File) -> Result<PluginSession> {
+        let mut child = Command::new(&self.program)
+            .env(PROTOCOL_ENV, PROTOCOL_VERSION.to_string())
+            .env(FORMAT_ENV, &self.name)
+            .stdin(Stdio::piped())
+            .stdout(output)
+            .spawn()
+            .map_err(|e| {
+                HashError::ExportError(format!(
+                    "failed to run format plugin {} ({}): {}",
+                    self.name,
+                    self.program.display(),
+                    e
+                ))
+            })?;
+        let stdin = child.stdin.take().map(BufWriter::new);
+        Ok(PluginSession {
+            name: self.name.clone(),
+            child,
+            stdin,
+        })
+    }
+
+    /// Run the plugin over `results`, writing the export to `output`
+    pub fn render(&self, results: &[HashResult], output: File) -> Result<()> {
+        let mut session = self.spawn(output)?;
+        for result in results {
+            session.write_result(result)?;
+        }
+        session.finish()
+    }
+}
+
+/// A running plugin, fed one result at a time
+pub struct PluginSession {
+    name: String,
+    child: Child,
+    stdin: Option<BufWriter<ChildStdin>>,
+}
+
+impl PluginSession {
+    /// Send one result to the plugin
+    pub fn write_result(&mut self, result: &HashResult) -> Result<()> {
+        let line = result.to_json_line()?;
+        let stdin = self.stdin.as_mut().expect("stdin is open until finish");
+        if let Err(e) = writeln!(stdin, "{}", line) {
+            return Err(self.failed(e));
+        }
+        Ok(())
+    }
+
+    /// Close the plugin's input and wait for it to finish writing
+    pub fn finish(mut self) -> Result<()> {
+        let mut stdin = self.stdin.take().expect("stdin is open until finish");
+        if let Err(e) = stdin.flush() {
+            return Err(self.failed(e));
+        }
+        drop(stdin);
+        let status = self.child.wait()?;
+        if !status.success() {
+            return Err(HashError::ExportError(format!(
+                "format plugin {} failed: {}",
+                self.name, status
+            )));
+        }
+        Ok(())
+    }
+
+    /// The error for a failed write: the plugin's exit status if it has
+    /// stopped reading, otherwise the write error itself
+    fn failed(&mut self, e: io::Error) -> HashError {
+        self.stdin = None;
+        if e.kind() == io::ErrorKind::BrokenPipe {
+            if let Ok(status) = self.child.wait() {
+                return HashError::ExportError(format!(
+                    "format plugin {} stopped reading its input: {}",
+                    self.name, status
+                ));
+            }
+        }
+        e.into()
+    }
+}
+
+/// Plugins available on the `PATH`, by format name, plus the configured ones
+pub fn list_plugins(configured: &BTreeMap<String, PathBuf>) -> Vec<FormatPlugin> {
+    let mut found: BTreeMap<String, PathBuf> = BTreeMap::new();
+    for dir in path_dirs() {
+        let Ok(entries) = dir.read_dir() else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let file_name = entry.file_name();
+            let Some(name) = file_name.to_str().and_then(plugin_name) else {
+                continue;
+            };
+            // The first match on the PATH is the one `find` runs
+            if !found.contains_key(name) && is_executable(&entry.path()) {
+                found.insert(name.to_string(), entry.path());
+            }
+        }
+    }
+    found.extend(
+        configured
+            .iter()
+            .map(|(name, program)| (name.clone(), program.clone())),
+    );
+    found
+        .into_iter()
+        .filter(|(name, _)| check_name(name).is_ok())
+        .map(|(name, program)| FormatPlugin { name, program })
+        .collect()
+}
+
+/// Format name of a plugin executable's file name
+fn plugin_name(file_name: &str) -> Option<&str> {
+    let name = file_name.strip_prefix(PLUGIN_PREFIX)?;
+    let suffix = std::env::consts::EXE_SUFFIX;
+    name.strip_suffix(suffix)
+        .or(Some(name))
+        .filter(|name| !name.is_empty())
+}
+
+/// Format names are limited to ASCII letters, digits, `-` and `_`, so they
+/// cannot point outside the plugin naming scheme
+fn check_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_');
+    if valid {
+        Ok(())
+    } else {
+        Err(HashError::InvalidInput(format!(
+            "invalid format plugin name: {}",
+            name
+        )))
+    }
+}
+
+fn path_dirs() -> impl Iterator<Item = PathBuf> {
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    std::env::split_paths(&path).collect::<Vec<_>>().into_iter()
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
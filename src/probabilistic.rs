@@ -0,0 +1,226 @@
+//! Bloom filters and HyperLogLog sketches over the crate's algorithms
+//!
+//! Both structures only need well-mixed hash bits, so the fastest
+//! algorithm is the natural choice; [`Algorithm::Blake3`] is the default.
+//! Two filters or sketches can only be combined when they use the same
+//! algorithm and size.
+//!
+//! ```
+//! use hashing::probabilistic::{BloomFilter, HyperLogLog};
+//!
+//! let mut seen = BloomFilter::new(1_000, 0.01).unwrap();
+//! seen.insert("alice");
+//! assert!(seen.contains("alice"));
+//!
+//! let mut visitors = HyperLogLog::new(12).unwrap();
+//! for id in 0..10_000 {
+//!     visitors.insert(id.to_string());
+//! }
+//! let estimate = visitors.count();
+//! assert!((9_000..11_000).contains(&estimate));
+//! ```
+
+use crate::builder::digest_words;
+use crate::{Algorithm, HashError, Result};
+
+/// Default algorithm for new filters and sketches
+pub const DEFAULT_ALGORITHM: Algorithm = Algorithm::Blake3;
+
+/// Set membership with false positives but no false negatives
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BloomFilter {
+    algorithm: Algorithm,
+    bits: Vec<u64>,
+    bit_count: u64,
+    hash_count: u32,
+}
+
+impl BloomFilter {
+    /// A filter sized for `expected_items` at the given false positive rate
+    pub fn new(expected_items: usize, false_positive_rate: f64) -> Result<Self> {
+        if expected_items == 0 {
+            return Err(HashError::InvalidInput(
+                "expected item count must be positive".to_string(),
+            ));
+        }
+        if !(false_positive_rate > 0.0 && false_positive_rate < 1.0) {
+            return Err(HashError::InvalidInput(format!(
+                "false positive rate must be between 0 and 1, got {}",
+                false_positive_rate
+            )));
+        }
+        let n = expected_items as f64;
+        let ln2 = std::f64::consts::LN_2;
+        let bit_count = (-n * false_positive_rate.ln() / (ln2 * ln2)).ceil();
+        let hash_count = (bit_count / n * ln2).round().max(1.0);
+        Self::with_params(bit_count as u64, hash_count as u32)
+    }
+
+    /// A filter with exactly `bit_count` bits and `hash_count` probes per item
+    pub fn with_params(bit_count: u64, hash_count: u32) -> Result<Self> {
+        if bit_count == 0 || hash_count == 0 {
+            return Err(HashError::InvalidInput(
+                "bit and hash counts must be positive".to_string(),
+            ));
+        }
+        Ok(Self {
+            algorithm: DEFAULT_ALGORITHM,
+            bits: vec![0; ((bit_count + 63) / 64) as usize],
+            bit_count,
+            hash_count,
+        })
+    }
+
+    /// Hash items with `algorithm` instead of the default
+    pub fn with_algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.algorithm = algorithm;
+        self.clear();
+        self
+    }
+
+    /// Add an item
+    pub fn insert(&mut self, item: impl AsRef<[u8]>) {
+        for index in self.indices(item.as_ref()) {
+            self.bits[(index / 64) as usize] |= 1 << (index % 64);
+        }
+    }
+
+    /// Whether the item may have been added (false means definitely not)
+    pub fn contains(&self, item: impl AsRef<[u8]>) -> bool {
+        self.indices(item.as_ref())
+            .all(|index| self.bits[(index / 64) as usize] & (1 << (index % 64)) != 0)
+    }
+
+    /// Add every item of `other`, which must have the same shape
+    pub fn union(&mut self, other: &BloomFilter) -> Result<()> {
+        if (self.algorithm, self.bit_count, self.hash_count)
+            != (other.algorithm, other.bit_count, other.hash_count)
+        {
+            return Err(HashError::InvalidInput(
+                "cannot combine Bloom filters with different parameters".to_string(),
+            ));
+        }
+        for (word, other) in self.bits.iter_mut().zip(&other.bits) {
+            *word |= other;
+        }
+        Ok(())
+    }
+
+    /// Remove all items
+    pub fn clear(&mut self) {
+        self.bits.fill(0);
+    }
+
+    /// Number of bits in the filter
+    pub fn bit_count(&self) -> u64 {
+        self.bit_count
+    }
+
+    /// Number of bits probed per item
+    pub fn hash_count(&self) -> u32 {
+        self.hash_count
+    }
+
+    /// Expected false positive rate given the bits set so far
+    pub fn false_positive_rate(&self) -> f64 {
+        let set: u64 = self.bits.iter().map(|word| word.count_ones() as u64).sum();
+        (set as f64 / self.bit_count as f64).powi(self.hash_count as i32)
+    }
+
+    /// Bit positions via double hashing: `h1 + i * h2`
+    fn indices(&self, item: &[u8]) -> impl Iterator<Item = u64> {
+        let (h1, h2) = digest_words(item, self.algorithm);
+        let h2 = h2 | 1;
+        let bit_count = self.bit_count;
+        (0..self.hash_count as u64).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % bit_count)
+    }
+}
+
+/// Cardinality estimation in a fixed amount of memory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HyperLogLog {
+    algorithm: Algorithm,
+    precision: u8,
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    /// A sketch with `2^precision` registers (`precision` from 4 to 18)
+    ///
+    /// The standard error is about `1.04 / sqrt(2^precision)`, e.g. 1.6%
+    /// at precision 12 (4 KiB).
+    pub fn new(precision: u8) -> Result<Self> {
+        if !(4..=18).contains(&precision) {
+            return Err(HashError::InvalidInput(format!(
+                "HyperLogLog precision must be between 4 and 18, got {}",
+                precision
+            )));
+        }
+        Ok(Self {
+            algorithm: DEFAULT_ALGORITHM,
+            precision,
+            registers: vec![0; 1 << precision],
+        })
+    }
+
+    /// Hash items with `algorithm` instead of the default
+    pub fn with_algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.algorithm = algorithm;
+        self.registers.fill(0);
+        self
+    }
+
+    /// Add an item
+    pub fn insert(&mut self, item: impl AsRef<[u8]>) {
+        let (hash, _) = digest_words(item.as_ref(), self.algorithm);
+        let index = (hash >> (64 - self.precision)) as usize;
+        let rest = hash << self.precision;
+        let rank = (rest.leading_zeros() + 1).min(64 - self.precision as u32 + 1) as u8;
+        if rank > self.registers[index] {
+            self.registers[index] = rank;
+        }
+    }
+
+    /// Estimated number of distinct items added
+    pub fn count(&self) -> u64 {
+        let m = self.registers.len() as f64;
+        let alpha = match self.registers.len() {
+            16 => 0.673,
+            32 => 0.697,
+            64 => 0.709,
+            _ => 0.7213 / (1.0 + 1.079 / m),
+        };
+        let sum: f64 = self
+            .registers
+            .iter()
+            .map(|&rank| 2f64.powi(-(rank as i32)))
+            .sum();
+        let estimate = alpha * m * m / sum;
+
+        // Small cardinalities: linear counting over the empty registers
+        let zeros = self.registers.iter().filter(|&&rank| rank == 0).count();
+        if estimate <= 2.5 * m && zeros > 0 {
+            (m * (m / zeros as f64).ln()).round() as u64
+        } else {
+            estimate.round() as u64
+        }
+    }
+
+    /// Add every item of `other`, which must have the same precision and algorithm
+    pub fn merge(&mut self, other: &HyperLogLog) -> Result<()> {
+        if (self.algorithm, self.precision) != (other.algorithm, other.precision) {
+            return Err(HashError::InvalidInput(
+                "cannot merge HyperLogLog sketches with different parameters".to_string(),
+            ));
+        }
+        for (rank, other) in self.registers.iter_mut().zip(&other.registers) {
+            *rank = (*rank).max(*other);
+        }
+        Ok(())
+    }
+
+    /// Number of index bits
+    pub fn precision(&self) -> u8 {
+        self.precision
+    }
+}
@@ -0,0 +1,352 @@
+//! Progress records and bars
+//!
+//! [`ProgressTracker`] follows a scan file by file and, at most once per
+//! interval, writes a [`ProgressRecord`] as one line of JSON, so GUIs and
+//! wrappers can show progress by reading lines instead of scraping a
+//! terminal; [`ProgressBar`] draws one line for people instead. Byte counts
+//! come from [`crate::Hashing::progress`]:
+//!
+//! ```
+//! use hashing::progress::ProgressTracker;
+//! use hashing::Hashing;
+//! use std::time::Duration;
+//!
+//! let mut records = Vec::new();
+//! let mut tracker = ProgressTracker::new(&mut records, Duration::from_millis(500));
+//! tracker.start_file("hello.txt");
+//! Hashing::new()
+//!     .progress(|bytes| tracker.bytes(bytes))
+//!     .hash_string("hello")
+//!     .unwrap();
+//! tracker.finish_file();
+//! tracker.finish().unwrap();
+//! let last = String::from_utf8(records).unwrap();
+//! assert!(last.lines().last().unwrap().contains(r#""event":"done""#));
+//! ```
+
+use crate::units::SizeFormat;
+use crate::{HashError, Result};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Kind of a [`ProgressRecord`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProgressEvent {
+    /// Periodic update
+    Progress,
+    /// A file could not be hashed
+    Error,
+    /// The scan is over; always the last record
+    Done,
+}
+
+/// One line of progress output
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProgressRecord {
+    /// Kind of record
+    pub event: ProgressEvent,
+    /// Milliseconds since the tracker was created
+    pub elapsed_ms: u64,
+    /// Files hashed so far
+    pub files_done: u64,
+    /// Files that failed so far
+    pub files_failed: u64,
+    /// Bytes hashed so far, including the current file's
+    pub bytes_done: u64,
+    /// Average throughput since the start
+    pub bytes_per_sec: u64,
+    /// File being hashed (or that failed, for `error`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current: Option<String>,
+    /// Why the file failed, for `error`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// Counts files and bytes and writes throttled JSON Lines records
+pub struct ProgressTracker<W: Write> {
+    writer: W,
+    interval: Duration,
+    start: Instant,
+    last: Option<Instant>,
+    files_done: u64,
+    files_failed: u64,
+    bytes_done: u64,
+    current_bytes: u64,
+    current: Option<String>,
+    /// First write error, reported by [`ProgressTracker::finish`]
+    failed: Option<std::io::Error>,
+}
+
+impl<W: Write> ProgressTracker<W> {
+    /// Write records to `writer`, periodic ones at most once per `interval`
+    pub fn new(writer: W, interval: Duration) -> Self {
+        Self {
+            writer,
+            interval,
+            start: Instant::now(),
+            last: None,
+            files_done: 0,
+            files_failed: 0,
+            bytes_done: 0,
+            current_bytes: 0,
+            current: None,
+            failed: None,
+        }
+    }
+
+    /// A file is about to be hashed
+    pub fn start_file<P: AsRef<Path>>(&mut self, path: P) {
+        self.current = Some(path.as_ref().display().to_string());
+        self.current_bytes = 0;
+        self.tick();
+    }
+
+    /// The current file has been read up to `consumed` bytes
+    pub fn bytes(&mut self, consumed: u64) {
+        self.current_bytes = consumed;
+        self.tick();
+    }
+
+    /// The current file is done
+    pub fn finish_file(&mut self) {
+        self.files_done += 1;
+        self.bytes_done += self.current_bytes;
+        self.current_bytes = 0;
+        self.tick();
+    }
+
+    /// A file (or, without `path`, a directory entry) could not be read;
+    /// written immediately
+    pub fn fail_file(&mut self, path: Option<&Path>, message: impl Into<String>) {
+        self.files_failed += 1;
+        self.current_bytes = 0;
+        let mut record = self.record(ProgressEvent::Error);
+        record.current = path.map(|path| path.display().to_string());
+        record.message = Some(message.into());
+        self.write(&record);
+    }
+
+    /// Write the final `done` record and report any earlier write error
+    pub fn finish(mut self) -> Result<()> {
+        self.current = None;
+        let record = self.record(ProgressEvent::Done);
+        self.write(&record);
+        match self.failed {
+            Some(e) => Err(HashError::Io(e)),
+            None => Ok(()),
+        }
+    }
+
+    /// The record that would be written now
+    pub fn record(&self, event: ProgressEvent) -> ProgressRecord {
+        let elapsed = self.start.elapsed();
+        let bytes_done = self.bytes_done + self.current_bytes;
+        let bytes_per_sec = match elapsed.as_millis() {
+            0 => 0,
+            millis => (u128::from(bytes_done) * 1000 / millis) as u64,
+        };
+        ProgressRecord {
+            event,
+            elapsed_ms: elapsed.as_millis() as u64,
+            files_done: self.files_done,
+            files_failed: self.files_failed,
+            bytes_done,
+            bytes_per_sec,
+            current: self.current.clone(),
+            message: None,
+        }
+    }
+
+    fn tick(&mut self) {
+        let now = Instant::now();
+        if self
+            .last
+            .is_some_and(|last| now.duration_since(last) < self.interval)
+        {
+            return;
+        }
+        let record = self.record(ProgressEvent::Progress);
+        self.write(&record);
+    }
+
+    fn write(&mut self, record: &ProgressRecord) {
+        self.last = Some(Instant::now());
+        if self.failed.is_some() {
+            return;
+        }
+        // Serializing plain numbers and strings cannot fail
+        let line = serde_json::to_string(record).expect("progress record");
+        let written = writeln!(self.writer, "{}", line).and_then(|_| self.writer.flush());
+        if let Err(e) = written {
+            self.failed = Some(e);
+        }
+    }
+}
+
+/// Least time between two redraws of a [`ProgressBar`]
+const BAR_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Width of the bar itself, in characters
+const BAR_WIDTH: usize = 24;
+
+/// A one-line progress bar with throughput and time left, redrawn in place
+///
+/// Each redraw starts with a carriage return, so the bar belongs on a
+/// terminal, usually standard error. Dropping the bar draws it one last
+/// time and ends the line.
+///
+/// ```
+/// use hashing::progress::ProgressBar;
+/// use hashing::Hashing;
+///
+/// let mut bar = ProgressBar::new(std::io::stderr(), Some(5));
+/// Hashing::new()
+///     .progress(|bytes| bar.update(bytes))
+///     .hash_string("hello")
+///     .unwrap();
+/// bar.finish();
+/// ```
+pub struct ProgressBar<W: Write> {
+    writer: W,
+    total: Option<u64>,
+    format: SizeFormat,
+    start: Instant,
+    last: Option<Instant>,
+    done: u64,
+    /// Length of the last line drawn, to blank out what a shorter one leaves
+    width: usize,
+    finished: bool,
+}
+
+impl<W: Write> ProgressBar<W> {
+    /// Draw on `writer` for an input of `total` bytes, or of unknown size
+    ///
+    /// With no total (or a total of 0), the bar shows the bytes read so
+    /// far and the time spent instead of a percentage and time left.
+    pub fn new(writer: W, total: Option<u64>) -> Self {
+        Self {
+            writer,
+            total: total.filter(|&total| total > 0),
+            format: SizeFormat::new(),
+            start: Instant::now(),
+            last: None,
+            done: 0,
+            width: 0,
+            finished: false,
+        }
+    }
+
+    /// Print sizes and throughput with `format`
+    pub fn with_size_format(mut self, format: SizeFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// `done` bytes have been read; redraws at most ten times a second
+    pub fn update(&mut self, done: u64) {
+        self.done = done;
+        let now = Instant::now();
+        if self
+            .last
+            .is_some_and(|last| now.duration_since(last) < BAR_INTERVAL)
+        {
+            return;
+        }
+        self.draw();
+    }
+
+    /// Draw the bar as it ends and move to the next line
+    pub fn finish(mut self) {
+        self.end();
+    }
+
+    /// The line drawn after `done` bytes in `elapsed`, e.g.
+    /// `[=========>              ]  40%  2.0 GiB / 5.0 GiB  310.2 MiB/s  ETA 0:09`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hashing::progress::ProgressBar;
+    /// use std::time::Duration;
+    ///
+    /// let bar = ProgressBar::new(std::io::sink(), Some(4 << 20));
+    /// assert_eq!(
+    ///     bar.line(1 << 20, Duration::from_secs(2)),
+    ///     "[======>                 ]  25%  1.0 MiB / 4.0 MiB  512.0 KiB/s  ETA 0:06"
+    /// );
+    /// ```
+    pub fn line(&self, done: u64, elapsed: Duration) -> String {
+        let rate = self.format.rate(done, elapsed);
+        let total = match self.total {
+            Some(total) => total,
+            None => {
+                let size = self.format.size(done);
+                return format!("{}  {}  {}", size, rate, clock(elapsed));
+            }
+        };
+        let done = done.min(total);
+        let filled = (u128::from(done) * BAR_WIDTH as u128 / u128::from(total)) as usize;
+        let bar = match filled {
+            BAR_WIDTH => "=".repeat(BAR_WIDTH),
+            filled => format!(
+                "{}>{}",
+                "=".repeat(filled),
+                " ".repeat(BAR_WIDTH - filled - 1)
+            ),
+        };
+        let percent = u128::from(done) * 100 / u128::from(total);
+        let secs = elapsed.as_secs_f64();
+        let time = if done < total && done > 0 && secs > 0.0 {
+            let left = (total - done) as f64 * secs / done as f64;
+            format!("ETA {}", clock(Duration::from_secs_f64(left)))
+        } else {
+            clock(elapsed)
+        };
+        format!(
+            "[{}] {:>3}%  {} / {}  {}  {}",
+            bar,
+            percent,
+            self.format.size(done),
+            self.format.size(total),
+            rate,
+            time
+        )
+    }
+
+    fn draw(&mut self) {
+        self.last = Some(Instant::now());
+        let line = self.line(self.done, self.start.elapsed());
+        let blank = self.width.saturating_sub(line.chars().count());
+        self.width = line.chars().count();
+        // A bar that cannot be drawn is not worth failing the hash over
+        let _ = write!(self.writer, "\r{}{}", line, " ".repeat(blank))
+            .and_then(|_| self.writer.flush());
+    }
+
+    fn end(&mut self) {
+        if !self.finished {
+            self.finished = true;
+            self.draw();
+            let _ = writeln!(self.writer).and_then(|_| self.writer.flush());
+        }
+    }
+}
+
+impl<W: Write> Drop for ProgressBar<W> {
+    fn drop(&mut self) {
+        self.end();
+    }
+}
+
+/// `elapsed` as `M:SS`, or `H:MM:SS` from an hour on
+fn clock(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    match secs / 3600 {
+        0 => format!("{}:{:02}", secs / 60, secs % 60),
+        hours => format!("{}:{:02}:{:02}", hours, secs / 60 % 60, secs % 60),
+    }
+}
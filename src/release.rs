@@ -0,0 +1,193 @@
+//! Release checksum files from one pass over the artifacts
+//!
+//! Release pipelines publish the same digests in several shapes: coreutils
+//! `SHA256SUMS` and `SHA512SUMS` files for `sha256sum -c`, Subresource
+//! Integrity values for the web assets, and a JSON manifest for tooling.
+//! [`scan_dist`] reads every artifact once, feeding SHA-256, SHA-384 and
+//! SHA-512 together, and the other functions render the results.
+//!
+//! ```no_run
+//! use hashing::release::{scan_dist, write_release_files};
+//!
+//! let artifacts = scan_dist("dist")?;
+//! for path in write_release_files("dist", &artifacts)? {
+//!     println!("wrote {}", path.display());
+//! }
+//! # Ok::<(), hashing::HashError>(())
+//! ```
+
+use crate::builder::DEFAULT_BUFFER_SIZE;
+use crate::convert::{format_digest, DigestFormat};
+use crate::walk::{walk_files, SortOrder, WalkOptions};
+use crate::{Algorithm, HashError, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Coreutils-style SHA-256 checksum file
+pub const SHA256SUMS: &str = "SHA256SUMS";
+/// Coreutils-style SHA-512 checksum file
+pub const SHA512SUMS: &str = "SHA512SUMS";
+/// JSON map from web asset name to SRI value
+pub const SRI_FILE: &str = "sri.json";
+/// JSON manifest of every artifact
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// File names [`write_release_files`] writes, skipped by [`scan_dist`]
+pub const RELEASE_FILES: [&str; 4] = [SHA256SUMS, SHA512SUMS, SRI_FILE, MANIFEST_FILE];
+
+/// Extensions of files browsers load with an `integrity` attribute
+const WEB_ASSET_EXTENSIONS: [&str; 5] = ["js", "mjs", "cjs", "css", "wasm"];
+
+/// One release artifact, as listed in the JSON manifest
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReleaseArtifact {
+    /// Path below the dist directory, with `/` separators
+    pub name: String,
+    /// Size in bytes
+    pub size: u64,
+    /// Lowercase hex SHA-256
+    pub sha256: String,
+    /// Lowercase hex SHA-512
+    pub sha512: String,
+    /// Subresource Integrity value (`sha384-<base64>`)
+    pub sri: String,
+}
+
+/// The JSON manifest
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReleaseManifest {
+    /// Artifacts in name order
+    pub artifacts: Vec<ReleaseArtifact>,
+}
+
+impl ReleaseArtifact {
+    /// Hash one file, reading it once
+    pub fn from_file<P: AsRef<Path>>(path: P, name: impl Into<String>) -> Result<Self> {
+        let mut file = File::open(path)?;
+        let mut sha256 = Sha256::new();
+        let mut sha384 = Sha384::new();
+        let mut sha512 = Sha512::new();
+        let mut size = 0u64;
+        let mut buffer = vec![0u8; DEFAULT_BUFFER_SIZE];
+        loop {
+            let count = file.read(&mut buffer)?;
+            if count == 0 {
+                break;
+            }
+            let chunk = &buffer[..count];
+            sha256.update(chunk);
+            sha384.update(chunk);
+            sha512.update(chunk);
+            size += count as u64;
+        }
+        let sha384 = sha384.finalize();
+        Ok(Self {
+            name: name.into(),
+            size,
+            sha256: hex::encode(sha256.finalize()),
+            sha512: hex::encode(sha512.finalize()),
+            sri: format_digest(Algorithm::Sha384, &sha384, DigestFormat::Sri)?,
+        })
+    }
+
+    /// Whether browsers load this artifact with an `integrity` attribute
+    pub fn is_web_asset(&self) -> bool {
+        Path::new(&self.name)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| WEB_ASSET_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+    }
+}
+
+/// Every file below `dir` in name order, except earlier release files at
+/// its top level
+pub fn scan_dist<P: AsRef<Path>>(dir: P) -> Result<Vec<ReleaseArtifact>> {
+    let dir = dir.as_ref();
+    if !dir.is_dir() {
+        return Err(HashError::InvalidInput(format!(
+            "not a directory: {}",
+            dir.display()
+        )));
+    }
+    let options = WalkOptions::new().with_sort(SortOrder::Name);
+    let mut artifacts = Vec::new();
+    for file in walk_files(dir, &options) {
+        let file = file?;
+        let name = artifact_name(file.strip_prefix(dir).unwrap_or(&file));
+        if RELEASE_FILES.contains(&name.as_str()) {
+            continue;
+        }
+        artifacts.push(ReleaseArtifact::from_file(&file, name)?);
+    }
+    Ok(artifacts)
+}
+
+/// `SHA256SUMS` contents, as `sha256sum` writes them
+pub fn sha256sums(artifacts: &[ReleaseArtifact]) -> String {
+    sums(artifacts, |artifact| &artifact.sha256)
+}
+
+/// `SHA512SUMS` contents, as `sha512sum` writes them
+pub fn sha512sums(artifacts: &[ReleaseArtifact]) -> String {
+    sums(artifacts, |artifact| &artifact.sha512)
+}
+
+/// SRI values of the web assets, keyed by name
+pub fn sri_map(artifacts: &[ReleaseArtifact]) -> BTreeMap<String, String> {
+    artifacts
+        .iter()
+        .filter(|artifact| artifact.is_web_asset())
+        .map(|artifact| (artifact.name.clone(), artifact.sri.clone()))
+        .collect()
+}
+
+/// Write [`RELEASE_FILES`] to `dir` and return their paths
+pub fn write_release_files<P: AsRef<Path>>(
+    dir: P,
+    artifacts: &[ReleaseArtifact],
+) -> Result<Vec<PathBuf>> {
+    let dir = dir.as_ref();
+    let manifest = ReleaseManifest {
+        artifacts: artifacts.to_vec(),
+    };
+    let contents = [
+        sha256sums(artifacts),
+        sha512sums(artifacts),
+        to_json(&sri_map(artifacts))?,
+        to_json(&manifest)?,
+    ];
+    let mut written = Vec::new();
+    for (name, contents) in RELEASE_FILES.iter().zip(contents) {
+        let path = dir.join(name);
+        fs::write(&path, contents)?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+fn sums(artifacts: &[ReleaseArtifact], digest: impl Fn(&ReleaseArtifact) -> &str) -> String {
+    artifacts
+        .iter()
+        .map(|artifact| format!("{}  {}\n", digest(artifact), artifact.name))
+        .collect()
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String> {
+    let mut json =
+        serde_json::to_string_pretty(value).map_err(|e| HashError::ExportError(e.to_string()))?;
+    json.push('\n');
+    Ok(json)
+}
+
+fn artifact_name(path: &Path) -> String {
+    let name = path.to_string_lossy();
+    if std::path::MAIN_SEPARATOR == '\\' {
+        name.replace('\\', "/")
+    } else {
+        name.into_owned()
+    }
+}
@@ -0,0 +1,200 @@
+//! Consistent hashing over any supported algorithm
+//!
+//! Each node is placed on a 64-bit ring at several virtual points; a key
+//! belongs to the node owning the first point at or after the key's own
+//! position, wrapping around. Adding or removing a node therefore only
+//! moves the keys on the arcs next to its points.
+//!
+//! Positions are the first 8 bytes (big-endian) of the digest. Virtual
+//! point `i` of a node is the position of the string `"{node}-{i}"`, so
+//! rings built in other languages with the same scheme agree.
+//!
+//! ```
+//! use hashing::ring::HashRing;
+//! use hashing::Algorithm;
+//!
+//! let mut ring = HashRing::new(Algorithm::Sha256);
+//! ring.add("cache-a");
+//! ring.add("cache-b");
+//! let node = ring.node_for("user:42").unwrap();
+//! assert!(*node == "cache-a" || *node == "cache-b");
+//! ```
+
+use crate::builder::digest_words;
+use crate::Algorithm;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Display;
+
+/// Virtual points per node unless configured otherwise
+pub const DEFAULT_VIRTUAL_NODES: usize = 160;
+
+/// Number of positions on the ring (2^64), as a float for ratios
+const RING_SIZE: f64 = 18_446_744_073_709_551_616.0;
+
+/// A consistent-hash ring of nodes
+#[derive(Debug, Clone)]
+pub struct HashRing<N> {
+    algorithm: Algorithm,
+    virtual_nodes: usize,
+    nodes: BTreeSet<N>,
+    points: BTreeMap<u64, N>,
+}
+
+/// How ownership of the keyspace differs between two rings
+#[derive(Debug, Clone, PartialEq)]
+pub struct RebalanceStats<N: Ord> {
+    /// Fraction of the keyspace (0.0 to 1.0) owned by a different node
+    pub moved: f64,
+    /// Fraction of the keyspace each node owns before the change
+    pub before: BTreeMap<N, f64>,
+    /// Fraction of the keyspace each node owns after the change
+    pub after: BTreeMap<N, f64>,
+}
+
+impl<N: Clone + Ord + Display> HashRing<N> {
+    /// An empty ring with [`DEFAULT_VIRTUAL_NODES`] points per node
+    pub fn new(algorithm: Algorithm) -> Self {
+        Self {
+            algorithm,
+            virtual_nodes: DEFAULT_VIRTUAL_NODES,
+            nodes: BTreeSet::new(),
+            points: BTreeMap::new(),
+        }
+    }
+
+    /// Use `count` virtual points per node (minimum 1)
+    ///
+    /// More points spread keys more evenly at the cost of memory. Nodes
+    /// already on the ring are re-placed.
+    pub fn with_virtual_nodes(mut self, count: usize) -> Self {
+        self.virtual_nodes = count.max(1);
+        self.rebuild();
+        self
+    }
+
+    /// Algorithm used to place nodes and keys
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    /// Add a node; returns false if it was already present
+    pub fn add(&mut self, node: N) -> bool {
+        if !self.nodes.insert(node.clone()) {
+            return false;
+        }
+        self.place(&node);
+        true
+    }
+
+    /// Remove a node; returns false if it was not present
+    pub fn remove(&mut self, node: &N) -> bool {
+        if !self.nodes.remove(node) {
+            return false;
+        }
+        self.rebuild();
+        true
+    }
+
+    /// The node responsible for `key`, or `None` if the ring is empty
+    pub fn node_for(&self, key: impl AsRef<[u8]>) -> Option<&N> {
+        self.owner_at(self.position(key.as_ref()))
+    }
+
+    /// Nodes on the ring, in sorted order
+    pub fn nodes(&self) -> impl Iterator<Item = &N> {
+        self.nodes.iter()
+    }
+
+    /// Number of nodes
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Whether the ring has no nodes
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Fraction of the keyspace (0.0 to 1.0) each node owns
+    pub fn ownership(&self) -> BTreeMap<N, f64> {
+        let mut shares: BTreeMap<N, f64> =
+            self.nodes.iter().map(|node| (node.clone(), 0.0)).collect();
+        let mut previous = self.points.keys().next_back().copied();
+        for (&point, node) in &self.points {
+            *shares.get_mut(node).expect("every point belongs to a node") +=
+                arc_fraction(previous.unwrap_or(point), point, self.points.len());
+            previous = Some(point);
+        }
+        shares
+    }
+
+    /// Compare this ring with `after`, e.g. a copy with a node added
+    ///
+    /// Both rings should use the same algorithm and virtual node count,
+    /// otherwise nearly every key moves.
+    pub fn rebalance_stats(&self, after: &HashRing<N>) -> RebalanceStats<N> {
+        // Between two consecutive points of either ring, every position has
+        // the same owner in each ring: the owner of the arc's end point
+        let boundaries: BTreeSet<u64> = self
+            .points
+            .keys()
+            .chain(after.points.keys())
+            .copied()
+            .collect();
+        let mut moved = 0.0;
+        let mut previous = boundaries.iter().next_back().copied();
+        for &point in &boundaries {
+            if self.owner_at(point) != after.owner_at(point) {
+                moved += arc_fraction(previous.unwrap_or(point), point, boundaries.len());
+            }
+            previous = Some(point);
+        }
+        RebalanceStats {
+            moved,
+            before: self.ownership(),
+            after: after.ownership(),
+        }
+    }
+
+    /// Owner of the first point at or after `position`, wrapping around
+    fn owner_at(&self, position: u64) -> Option<&N> {
+        self.points
+            .range(position..)
+            .next()
+            .or_else(|| self.points.iter().next())
+            .map(|(_, node)| node)
+    }
+
+    fn place(&mut self, node: &N) {
+        for i in 0..self.virtual_nodes {
+            let point = self.position(format!("{}-{}", node, i).as_bytes());
+            // On a collision the smaller node wins, independent of insertion order
+            match self.points.get(&point) {
+                Some(existing) if existing <= node => {}
+                _ => {
+                    self.points.insert(point, node.clone());
+                }
+            }
+        }
+    }
+
+    fn rebuild(&mut self) {
+        self.points.clear();
+        for node in self.nodes.clone() {
+            self.place(&node);
+        }
+    }
+
+    fn position(&self, data: &[u8]) -> u64 {
+        digest_words(data, self.algorithm).0
+    }
+}
+
+/// Share of the ring on the arc `(start, end]`; a single point owns it all
+fn arc_fraction(start: u64, end: u64, points: usize) -> f64 {
+    if points == 1 {
+        1.0
+    } else {
+        end.wrapping_sub(start) as f64 / RING_SIZE
+    }
+}
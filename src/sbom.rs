@@ -0,0 +1,372 @@
+//! File checksums for SPDX and CycloneDX SBOMs
+//!
+//! [`scan_tree`] hashes every file below a directory with one or more
+//! algorithms, [`fragment`] turns the result into the `files` array of an
+//! SPDX 2.3 document or the `components` array of a CycloneDX 1.5 BOM, and
+//! [`verify_sbom`] checks the file checksums of an existing document (JSON
+//! in either format) against disk.
+//!
+//! SPDX 2.3 requires a SHA-1 checksum for every file; include
+//! [`Algorithm::Sha1`] when generating documents that must validate.
+//!
+//! ```no_run
+//! use hashing::sbom::{fragment, scan_tree, SbomFormat};
+//! use hashing::walk::WalkOptions;
+//! use hashing::Algorithm;
+//!
+//! let files = scan_tree("dist", &WalkOptions::new(), &[Algorithm::Sha1, Algorithm::Sha256])?;
+//! let json = fragment(SbomFormat::Spdx, &files)?;
+//! println!("{}", serde_json::to_string_pretty(&json).unwrap());
+//! # Ok::<(), hashing::HashError>(())
+//! ```
+
+use crate::walk::{walk_files, SortOrder, WalkOptions};
+use crate::{hash_file, Algorithm, HashError, Result};
+use serde_json::{json, Value};
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+
+/// SBOM document formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SbomFormat {
+    /// SPDX 2.3 JSON
+    #[default]
+    Spdx,
+    /// CycloneDX 1.5 JSON
+    CycloneDx,
+}
+
+impl SbomFormat {
+    /// Name of an algorithm in this format's checksum entries, if the
+    /// format defines one
+    pub fn algorithm_label(&self, algorithm: Algorithm) -> Option<&'static str> {
+        let labels = match self {
+            SbomFormat::Spdx => SPDX_ALGORITHMS,
+            SbomFormat::CycloneDx => CYCLONEDX_ALGORITHMS,
+        };
+        labels
+            .iter()
+            .find(|(candidate, _)| *candidate == algorithm)
+            .map(|(_, label)| *label)
+    }
+
+    /// Algorithm named by a checksum entry label (case-insensitive)
+    pub fn parse_algorithm(&self, label: &str) -> Option<Algorithm> {
+        let labels = match self {
+            SbomFormat::Spdx => SPDX_ALGORITHMS,
+            SbomFormat::CycloneDx => CYCLONEDX_ALGORITHMS,
+        };
+        labels
+            .iter()
+            .find(|(_, candidate)| candidate.eq_ignore_ascii_case(label))
+            .map(|(algorithm, _)| *algorithm)
+    }
+}
+
+impl fmt::Display for SbomFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SbomFormat::Spdx => "spdx",
+            SbomFormat::CycloneDx => "cyclonedx",
+        })
+    }
+}
+
+impl FromStr for SbomFormat {
+    type Err = HashError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "spdx" => Ok(SbomFormat::Spdx),
+            "cyclonedx" | "cdx" => Ok(SbomFormat::CycloneDx),
+            _ => Err(HashError::InvalidInput(format!(
+                "unknown SBOM format: {} (expected spdx or cyclonedx)",
+                s
+            ))),
+        }
+    }
+}
+
+/// SPDX 2.3 `ChecksumAlgorithm` values
+const SPDX_ALGORITHMS: &[(Algorithm, &str)] = &[
+    (Algorithm::Md5, "MD5"),
+    (Algorithm::Sha1, "SHA1"),
+    (Algorithm::Sha224, "SHA224"),
+    (Algorithm::Sha256, "SHA256"),
+    (Algorithm::Sha384, "SHA384"),
+    (Algorithm::Sha512, "SHA512"),
+    (Algorithm::Sha3_256, "SHA3-256"),
+    (Algorithm::Sha3_384, "SHA3-384"),
+    (Algorithm::Sha3_512, "SHA3-512"),
+    (Algorithm::Blake2b512, "BLAKE2b-512"),
+    (Algorithm::Blake3, "BLAKE3"),
+];
+
+/// CycloneDX 1.5 `hash-alg` values
+const CYCLONEDX_ALGORITHMS: &[(Algorithm, &str)] = &[
+    (Algorithm::Md5, "MD5"),
+    (Algorithm::Sha1, "SHA-1"),
+    (Algorithm::Sha256, "SHA-256"),
+    (Algorithm::Sha384, "SHA-384"),
+    (Algorithm::Sha512, "SHA-512"),
+    (Algorithm::Sha3_256, "SHA3-256"),
+    (Algorithm::Sha3_384, "SHA3-384"),
+    (Algorithm::Sha3_512, "SHA3-512"),
+    (Algorithm::Blake2b512, "BLAKE2b-512"),
+    (Algorithm::Blake3, "BLAKE3"),
+];
+
+/// A file and its checksums, ready to be written into an SBOM
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SbomFile {
+    /// Path relative to the scanned root, with `/` separators
+    pub path: String,
+    /// Lowercase hex digest per algorithm
+    pub checksums: Vec<(Algorithm, String)>,
+}
+
+/// Hash every file below `root` with each of `algorithms`
+///
+/// Files are visited in name order unless `options` asks for another
+/// order, so repeated scans produce identical documents.
+pub fn scan_tree<P: AsRef<Path>>(
+    root: P,
+    options: &WalkOptions,
+    algorithms: &[Algorithm],
+) -> Result<Vec<SbomFile>> {
+    let root = root.as_ref();
+    let mut options = options.clone();
+    if options.sort == SortOrder::None {
+        options.sort = SortOrder::Name;
+    }
+
+    let mut files = Vec::new();
+    for path in walk_files(root, &options) {
+        let path = path?;
+        let checksums = algorithms
+            .iter()
+            .map(|&algorithm| Ok((algorithm, hash_file(&path, algorithm)?)))
+            .collect::<Result<_>>()?;
+        files.push(SbomFile {
+            path: relative_path(root, &path),
+            checksums,
+        });
+    }
+    Ok(files)
+}
+
+/// The SPDX `files` or CycloneDX `components` fragment for `files`
+///
+/// Fails if an algorithm has no name in the chosen format (e.g. SHA-224 in
+/// CycloneDX).
+pub fn fragment(format: SbomFormat, files: &[SbomFile]) -> Result<Value> {
+    let label = |algorithm: Algorithm| {
+        format.algorithm_label(algorithm).ok_or_else(|| {
+            HashError::UnsupportedAlgorithm(format!(
+                "{} has no {} checksum name",
+                algorithm.name(),
+                format
+            ))
+        })
+    };
+
+    let mut entries = Vec::with_capacity(files.len());
+    for (index, file) in files.iter().enumerate() {
+        let entry = match format {
+            SbomFormat::Spdx => {
+                let checksums = file
+                    .checksums
+                    .iter()
+                    .map(|(algorithm, digest)| {
+                        Ok(json!({ "algorithm": label(*algorithm)?, "checksumValue": digest }))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                json!({
+                    "SPDXID": format!("SPDXRef-File-{}", index + 1),
+                    "fileName": format!("./{}", file.path),
+                    "checksums": checksums,
+                })
+            }
+            SbomFormat::CycloneDx => {
+                let hashes = file
+                    .checksums
+                    .iter()
+                    .map(|(algorithm, digest)| {
+                        Ok(json!({ "alg": label(*algorithm)?, "content": digest }))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                json!({
+                    "type": "file",
+                    "bom-ref": format!("file:{}", file.path),
+                    "name": file.path,
+                    "hashes": hashes,
+                })
+            }
+        };
+        entries.push(entry);
+    }
+
+    Ok(match format {
+        SbomFormat::Spdx => json!({ "files": entries }),
+        SbomFormat::CycloneDx => json!({ "components": entries }),
+    })
+}
+
+/// Outcome of checking one file listed in an SBOM
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SbomStatus {
+    /// Every checksum in a supported algorithm matches
+    Verified,
+    /// At least one checksum differs
+    Mismatch,
+    /// The file does not exist on disk
+    Missing,
+    /// None of the file's checksums use a supported algorithm
+    Unchecked,
+}
+
+/// Result for one file listed in an SBOM
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SbomCheck {
+    /// Path as written in the document
+    pub path: String,
+    /// What the check found
+    pub status: SbomStatus,
+}
+
+/// Everything [`verify_sbom`] checked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SbomVerification {
+    /// Format the document was recognized as
+    pub format: SbomFormat,
+    /// One check per listed file, in document order
+    pub checks: Vec<SbomCheck>,
+}
+
+impl SbomVerification {
+    /// Whether nothing mismatched or went missing
+    pub fn is_ok(&self) -> bool {
+        self.checks
+            .iter()
+            .all(|check| matches!(check.status, SbomStatus::Verified | SbomStatus::Unchecked))
+    }
+}
+
+/// Check the file checksums of an SPDX or CycloneDX JSON document against
+/// the files below `root`
+///
+/// SPDX `files` entries are checked by `fileName`; CycloneDX components of
+/// type `file`, including nested ones, by `name`.
+pub fn verify_sbom<P: AsRef<Path>>(document: &str, root: P) -> Result<SbomVerification> {
+    let root = root.as_ref();
+    let document: Value = serde_json::from_str(document)
+        .map_err(|e| HashError::InvalidInput(format!("invalid SBOM JSON: {}", e)))?;
+
+    let (format, entries) =
+        if document.get("spdxVersion").is_some() || document.get("files").is_some() {
+            (SbomFormat::Spdx, spdx_entries(&document))
+        } else if document.get("bomFormat").is_some() || document.get("components").is_some() {
+            let mut entries = Vec::new();
+            cyclonedx_entries(&document, &mut entries);
+            (SbomFormat::CycloneDx, entries)
+        } else {
+            return Err(HashError::InvalidInput(
+                "not an SPDX or CycloneDX JSON document".to_string(),
+            ));
+        };
+
+    let mut checks = Vec::with_capacity(entries.len());
+    for (name, checksums) in entries {
+        let path = resolve(root, &name);
+        let known: Vec<(Algorithm, &str)> = checksums
+            .iter()
+            .filter_map(|(label, digest)| Some((format.parse_algorithm(label)?, *digest)))
+            .collect();
+
+        let status = if !path.is_file() {
+            SbomStatus::Missing
+        } else if known.is_empty() {
+            SbomStatus::Unchecked
+        } else {
+            let mut status = SbomStatus::Verified;
+            for (algorithm, expected) in known {
+                if !hash_file(&path, algorithm)?.eq_ignore_ascii_case(expected.trim()) {
+                    status = SbomStatus::Mismatch;
+                    break;
+                }
+            }
+            status
+        };
+        checks.push(SbomCheck { path: name, status });
+    }
+    Ok(SbomVerification { format, checks })
+}
+
+/// `(fileName, [(algorithm, checksumValue)])` for each SPDX file
+fn spdx_entries(document: &Value) -> Vec<(String, Vec<(&str, &str)>)> {
+    let files = document.get("files").and_then(Value::as_array);
+    files
+        .into_iter()
+        .flatten()
+        .filter_map(|file| {
+            let name = file.get("fileName")?.as_str()?.to_string();
+            let checksums = file
+                .get("checksums")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(|checksum| {
+                    Some((
+                        checksum.get("algorithm")?.as_str()?,
+                        checksum.get("checksumValue")?.as_str()?,
+                    ))
+                })
+                .collect();
+            Some((name, checksums))
+        })
+        .collect()
+}
+
+/// `(name, [(alg, content)])` for each CycloneDX file component, depth first
+fn cyclonedx_entries<'a>(parent: &'a Value, entries: &mut Vec<(String, Vec<(&'a str, &'a str)>)>) {
+    let components = parent.get("components").and_then(Value::as_array);
+    for component in components.into_iter().flatten() {
+        let is_file = component.get("type").and_then(Value::as_str) == Some("file");
+        if let (true, Some(name)) = (is_file, component.get("name").and_then(Value::as_str)) {
+            let hashes = component
+                .get("hashes")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(|hash| {
+                    Some((hash.get("alg")?.as_str()?, hash.get("content")?.as_str()?))
+                })
+                .collect();
+            entries.push((name.to_string(), hashes));
+        }
+        cyclonedx_entries(component, entries);
+    }
+}
+
+/// `path` relative to `root`, with `/` separators
+fn relative_path(root: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Location on disk of a path from an SBOM, which is always relative to
+/// the root even when written as `./a` or `/a`
+fn resolve(root: &Path, name: &str) -> PathBuf {
+    let mut path = root.to_path_buf();
+    for component in Path::new(name).components() {
+        if let Component::Normal(part) = component {
+            path.push(part);
+        }
+    }
+    path
+}
@@ -0,0 +1,238 @@
+//! Passphrase-encrypted files
+//!
+//! A manifest kept next to the data it describes protects little on its
+//! own: whoever can change the files can usually rewrite the manifest to
+//! match. [`SealWriter`] encrypts and authenticates a manifest as it is
+//! written, under a key derived from a passphrase, and [`open`] refuses a
+//! sealed file that was modified or a wrong passphrase.
+//!
+//! ```
+//! use hashing::seal::{is_sealed, open, SealWriter};
+//! use std::io::Write;
+//!
+//! let mut writer = SealWriter::with_iterations(Vec::new(), b"passphrase", 1_000)?;
+//! writeln!(writer, "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824  a.txt")?;
+//! let sealed = writer.finish()?;
+//!
+//! assert!(is_sealed(&sealed));
+//! assert!(open(&sealed, b"passphrase")?.ends_with(b"  a.txt\n"));
+//! assert!(open(&sealed, b"wrong").is_err());
+//! # Ok::<(), hashing::HashError>(())
+//! ```
+//!
+//! # Format
+//!
+//! A sealed file is the header followed by the ciphertext. The header is
+//! the magic `hashseal`, a version byte (1), the PBKDF2 iteration count as
+//! a big-endian `u32`, a 16-byte random salt and a 7-byte random nonce.
+//! PBKDF2-HMAC-SHA-256 ([`crate::kdf`]) turns passphrase and salt into a
+//! 32-byte AES-256 key.
+//!
+//! The plaintext is cut into chunks of 64 KiB, the last one shorter and
+//! possibly empty, and each chunk is encrypted with AES-256-GCM under the
+//! STREAM construction: the nonce is completed with a big-endian chunk
+//! counter and a flag marking the last chunk, and the header is the
+//! associated data of every chunk. Reordered, dropped or truncated chunks
+//! therefore fail to authenticate, as does any change to the header.
+
+use crate::kdf::{self, DEFAULT_ITERATIONS, SALT_LEN};
+use crate::{Algorithm, HashError, Result};
+use aes_gcm::aead::stream::{DecryptorBE32, EncryptorBE32};
+use aes_gcm::aead::{KeyInit, Payload};
+use aes_gcm::Aes256Gcm;
+use std::io::{self, Write};
+
+/// First bytes of every sealed file
+pub const MAGIC: &[u8; 8] = b"hashseal";
+
+/// Format version written after [`MAGIC`]
+const VERSION: u8 = 1;
+
+/// Length of the nonce prefix; STREAM fills the rest of the 12-byte
+/// AES-GCM nonce with a chunk counter and a last-chunk flag
+const NONCE_LEN: usize = 7;
+
+/// Length of the header: magic, version, iterations, salt and nonce
+const HEADER_LEN: usize = MAGIC.len() + 1 + 4 + SALT_LEN + NONCE_LEN;
+
+/// Plaintext bytes per chunk
+const CHUNK_LEN: usize = 64 * 1024;
+
+/// Length of the authentication tag closing every chunk
+const TAG_LEN: usize = 16;
+
+/// Most iterations [`open`] accepts, so that a crafted header cannot keep
+/// it busy for hours
+const MAX_ITERATIONS: u32 = 10_000_000;
+
+/// Writer that seals everything written to it; call
+/// [`SealWriter::finish`] to seal the last chunk
+pub struct SealWriter<W: Write> {
+    inner: W,
+    encryptor: EncryptorBE32<Aes256Gcm>,
+    header: Vec<u8>,
+    /// Plaintext not yet encrypted; a full chunk is only sealed once more
+    /// follows, as the last chunk is marked differently
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> SealWriter<W> {
+    /// Seal to `inner` with a key derived from `passphrase` with
+    /// [`DEFAULT_ITERATIONS`] of PBKDF2
+    pub fn new(inner: W, passphrase: &[u8]) -> Result<Self> {
+        Self::with_iterations(inner, passphrase, DEFAULT_ITERATIONS)
+    }
+
+    /// Seal with a chosen PBKDF2 iteration count, which is stored in the
+    /// header; fewer than the default make the passphrase easier to guess
+    pub fn with_iterations(mut inner: W, passphrase: &[u8], iterations: u32) -> Result<Self> {
+        if passphrase.is_empty() {
+            return Err(HashError::InvalidInput("passphrase is empty".to_string()));
+        }
+        let salt = kdf::generate_salt()?;
+        let mut nonce = [0u8; NONCE_LEN];
+        getrandom::getrandom(&mut nonce)
+            .map_err(|e| HashError::Io(io::Error::new(io::ErrorKind::Other, e.to_string())))?;
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        header.extend_from_slice(MAGIC);
+        header.push(VERSION);
+        header.extend_from_slice(&iterations.to_be_bytes());
+        header.extend_from_slice(&salt);
+        header.extend_from_slice(&nonce);
+
+        let cipher = cipher(passphrase, &salt, iterations)?;
+        inner.write_all(&header)?;
+        Ok(Self {
+            inner,
+            encryptor: EncryptorBE32::from_aead(cipher, nonce.as_slice().into()),
+            header,
+            buffer: Vec::with_capacity(CHUNK_LEN),
+        })
+    }
+
+    /// Seal the last chunk and return the inner writer
+    pub fn finish(mut self) -> Result<W> {
+        let chunk = self
+            .encryptor
+            .encrypt_last(Payload {
+                msg: &self.buffer,
+                aad: &self.header,
+            })
+            .map_err(|_| HashError::InvalidInput("sealed file is too large".to_string()))?;
+        self.inner.write_all(&chunk)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for SealWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut rest = buf;
+        while !rest.is_empty() {
+            if self.buffer.len() == CHUNK_LEN {
+                let chunk = self
+                    .encryptor
+                    .encrypt_next(Payload {
+                        msg: &self.buffer,
+                        aad: &self.header,
+                    })
+                    .map_err(|_| {
+                        io::Error::new(io::ErrorKind::Other, "sealed file is too large")
+                    })?;
+                self.inner.write_all(&chunk)?;
+                self.buffer.clear();
+            }
+            let take = (CHUNK_LEN - self.buffer.len()).min(rest.len());
+            self.buffer.extend_from_slice(&rest[..take]);
+            rest = &rest[take..];
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Seal `data` in memory
+pub fn seal(data: &[u8], passphrase: &[u8]) -> Result<Vec<u8>> {
+    let chunks = data.len() / CHUNK_LEN + 1;
+    let mut writer = SealWriter::new(
+        Vec::with_capacity(HEADER_LEN + data.len() + chunks * TAG_LEN),
+        passphrase,
+    )?;
+    writer.write_all(data)?;
+    writer.finish()
+}
+
+/// Whether `data` starts like a sealed file
+pub fn is_sealed(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// The plaintext of a sealed file
+///
+/// Fails without returning any plaintext if the passphrase is wrong or a
+/// single byte of the file was changed, the two being indistinguishable.
+pub fn open(sealed: &[u8], passphrase: &[u8]) -> Result<Vec<u8>> {
+    if !is_sealed(sealed) {
+        return Err(HashError::InvalidInput("not a sealed file".to_string()));
+    }
+    if sealed.len() < HEADER_LEN + TAG_LEN {
+        return Err(HashError::InvalidInput(
+            "sealed file is truncated".to_string(),
+        ));
+    }
+    let version = sealed[MAGIC.len()];
+    if version != VERSION {
+        return Err(HashError::InvalidInput(format!(
+            "unsupported sealed file version {}",
+            version
+        )));
+    }
+    let counts = &sealed[MAGIC.len() + 1..MAGIC.len() + 5];
+    let iterations = u32::from_be_bytes(counts.try_into().expect("4 bytes"));
+    if iterations == 0 || iterations > MAX_ITERATIONS {
+        return Err(HashError::InvalidInput(format!(
+            "sealed file asks for {} PBKDF2 iterations",
+            iterations
+        )));
+    }
+    let salt = &sealed[MAGIC.len() + 5..HEADER_LEN - NONCE_LEN];
+    let nonce = &sealed[HEADER_LEN - NONCE_LEN..HEADER_LEN];
+    let (header, mut body) = sealed.split_at(HEADER_LEN);
+
+    let cipher = cipher(passphrase, salt, iterations)?;
+    let mut decryptor = DecryptorBE32::from_aead(cipher, nonce.into());
+    let refused = |_| {
+        HashError::InvalidInput(
+            "sealed file does not authenticate: wrong passphrase, or it was modified".to_string(),
+        )
+    };
+    let mut plaintext = Vec::with_capacity(body.len());
+    while body.len() > CHUNK_LEN + TAG_LEN {
+        let (chunk, rest) = body.split_at(CHUNK_LEN + TAG_LEN);
+        let msg = decryptor
+            .decrypt_next(Payload {
+                msg: chunk,
+                aad: header,
+            })
+            .map_err(refused)?;
+        plaintext.extend_from_slice(&msg);
+        body = rest;
+    }
+    let msg = decryptor
+        .decrypt_last(Payload {
+            msg: body,
+            aad: header,
+        })
+        .map_err(refused)?;
+    plaintext.extend_from_slice(&msg);
+    Ok(plaintext)
+}
+
+/// The AES-256-GCM cipher keyed from the passphrase
+fn cipher(passphrase: &[u8], salt: &[u8], iterations: u32) -> Result<Aes256Gcm> {
+    let key = kdf::pbkdf2(passphrase, salt, iterations, Algorithm::Sha256, 32)?;
+    Ok(Aes256Gcm::new_from_slice(&key).expect("32-byte key"))
+}
@@ -0,0 +1,344 @@
+//! Sigstore / cosign blob digests and signatures
+//!
+//! Sigstore tooling names a blob by its digest in `algorithm:hex` form
+//! (`sha256:b94d27b9...`), as in `hashedrekord` transparency log entries
+//! and `rekor-cli search --sha`. [`blob_digest`] and [`parse_blob_digest`]
+//! convert to and from that form.
+//!
+//! With the `sigstore` feature, [`verify_blob`] checks a `cosign sign-blob`
+//! signature (a `.sig` file plus the signing certificate or public key, or
+//! a bundle) against a blob. Only the ECDSA P-256 and P-384 signature is
+//! checked: the certificate chain, the signer's identity and the
+//! transparency log inclusion are not, so use `cosign verify-blob` where
+//! those matter.
+//!
+//! ```
+//! use hashing::sigstore::blob_digest;
+//! use hashing::Algorithm;
+//!
+//! let digest = hashing::fixed::hash_bytes_fixed::<hashing::fixed::Sha256>(b"hello world");
+//! assert_eq!(
+//!     blob_digest(Algorithm::Sha256, &digest).unwrap(),
+//!     "sha256:b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9"
+//! );
+//! ```
+
+use crate::{Algorithm, HashError, Result};
+
+/// Sigstore name of an algorithm, for those Rekor accepts
+pub fn digest_algorithm_name(algorithm: Algorithm) -> Option<&'static str> {
+    match algorithm {
+        Algorithm::Sha256 => Some("sha256"),
+        Algorithm::Sha384 => Some("sha384"),
+        Algorithm::Sha512 => Some("sha512"),
+        _ => None,
+    }
+}
+
+/// A raw digest in `algorithm:hex` form
+pub fn blob_digest(algorithm: Algorithm, digest: &[u8]) -> Result<String> {
+    let name = digest_algorithm_name(algorithm).ok_or_else(|| {
+        HashError::UnsupportedAlgorithm(format!(
+            "sigstore digests use sha256, sha384 or sha512, not {}",
+            algorithm.name()
+        ))
+    })?;
+    if digest.len() != algorithm.output_size() {
+        return Err(HashError::InvalidInput(format!(
+            "{} digest must be {} bytes, got {}",
+            name,
+            algorithm.output_size(),
+            digest.len()
+        )));
+    }
+    Ok(format!("{}:{}", name, hex::encode(digest)))
+}
+
+/// Algorithm and raw digest of an `algorithm:hex` string
+pub fn parse_blob_digest(value: &str) -> Result<(Algorithm, Vec<u8>)> {
+    let invalid = || HashError::InvalidInput(format!("invalid sigstore digest: {}", value));
+    let (name, hex_digest) = value.trim().split_once(':').ok_or_else(invalid)?;
+    let algorithm = match name.to_ascii_lowercase().as_str() {
+        "sha256" => Algorithm::Sha256,
+        "sha384" => Algorithm::Sha384,
+        "sha512" => Algorithm::Sha512,
+        _ => return Err(invalid()),
+    };
+    let digest = hex::decode(hex_digest).map_err(|_| invalid())?;
+    if digest.len() != algorithm.output_size() {
+        return Err(invalid());
+    }
+    Ok((algorithm, digest))
+}
+
+#[cfg(feature = "sigstore")]
+pub use verify::*;
+
+#[cfg(feature = "sigstore")]
+mod verify {
+    use crate::auth::{certificate_der, der_element};
+    use crate::builder::{digest_reader, DEFAULT_BUFFER_SIZE};
+    use crate::{Algorithm, HashError, Result};
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+    use p256::ecdsa::signature::hazmat::PrehashVerifier;
+    use p256::pkcs8::DecodePublicKey;
+    use serde_json::Value;
+    use std::fs::File;
+    use std::io::Read;
+    use std::path::Path;
+
+    /// A detached blob signature and the material to check it with
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct BlobSignature {
+        /// DER-encoded ECDSA signature
+        pub signature: Vec<u8>,
+        /// Signing certificate or public key, DER or PEM
+        pub key: Vec<u8>,
+        /// Digest of the blob recorded alongside the signature, if any
+        pub digest: Option<(Algorithm, Vec<u8>)>,
+    }
+
+    impl BlobSignature {
+        /// A signature from the contents of a `.sig` file (base64 or raw
+        /// DER) and a certificate or public key file (PEM, DER, or PEM
+        /// wrapped in base64 as older cosign versions write it)
+        pub fn from_detached(signature: &[u8], key: &[u8]) -> Result<Self> {
+            Ok(Self {
+                signature: decode_signature(signature)?,
+                key: unwrap_base64_pem(key),
+                digest: None,
+            })
+        }
+
+        /// A signature from a cosign bundle (`cosign sign-blob --bundle`) or
+        /// a Sigstore bundle (`.sigstore.json`)
+        pub fn from_bundle(json: &str) -> Result<Self> {
+            let bundle: Value = serde_json::from_str(json)
+                .map_err(|e| invalid(format!("invalid bundle JSON: {}", e)))?;
+            let text = |value: Option<&Value>, what: &str| {
+                value
+                    .and_then(Value::as_str)
+                    .map(str::to_string)
+                    .ok_or_else(|| invalid(format!("bundle has no {}", what)))
+            };
+
+            if let Some(signature) = bundle.get("messageSignature") {
+                let material = &bundle["verificationMaterial"];
+                let certificate = material["certificate"]["rawBytes"]
+                    .as_str()
+                    .or_else(|| {
+                        material["x509CertificateChain"]["certificates"][0]["rawBytes"].as_str()
+                    })
+                    .ok_or_else(|| invalid("bundle has no certificate".to_string()))?;
+                let digest = match (
+                    signature["messageDigest"]["algorithm"].as_str(),
+                    signature["messageDigest"]["digest"].as_str(),
+                ) {
+                    (Some(name), Some(digest)) => Some((
+                        bundle_algorithm(name)?,
+                        decode_base64(digest, "message digest")?,
+                    )),
+                    _ => None,
+                };
+                return Ok(Self {
+                    signature: decode_base64(
+                        &text(signature.get("signature"), "signature")?,
+                        "signature",
+                    )?,
+                    key: decode_base64(certificate, "certificate")?,
+                    digest,
+                });
+            }
+
+            let signature = text(bundle.get("base64Signature"), "base64Signature")?;
+            let key = text(bundle.get("cert"), "cert")?;
+            let digest = bundle["rekorBundle"]["Payload"]["body"]
+                .as_str()
+                .and_then(|body| STANDARD.decode(body).ok())
+                .and_then(|body| serde_json::from_slice::<Value>(&body).ok())
+                .and_then(|entry| {
+                    let hash = &entry["spec"]["data"]["hash"];
+                    let name = hash["algorithm"].as_str()?;
+                    let value = hex::decode(hash["value"].as_str()?).ok()?;
+                    Some((bundle_algorithm(name).ok()?, value))
+                });
+            Ok(Self {
+                signature: decode_base64(&signature, "signature")?,
+                key: unwrap_base64_pem(key.as_bytes()),
+                digest,
+            })
+        }
+    }
+
+    /// Outcome of [`verify_blob`]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum BlobVerification {
+        /// The signature is valid for the blob
+        Verified,
+        /// The digest recorded with the signature is not the blob's
+        DigestMismatch,
+        /// The signature does not match the blob and key
+        BadSignature,
+    }
+
+    /// Check a signature against a blob read from `reader`
+    pub fn verify_blob<R: Read>(
+        mut reader: R,
+        signature: &BlobSignature,
+    ) -> Result<BlobVerification> {
+        let key = VerifyingKey::parse(&signature.key)?;
+        let algorithm = key.digest_algorithm();
+
+        let digest = match &signature.digest {
+            // A recorded digest in another algorithm needs a second pass
+            Some((recorded_algorithm, recorded)) if *recorded_algorithm != algorithm => {
+                let mut blob = Vec::new();
+                reader.read_to_end(&mut blob)?;
+                if digest_reader(&blob[..], *recorded_algorithm, DEFAULT_BUFFER_SIZE)? != *recorded
+                {
+                    return Ok(BlobVerification::DigestMismatch);
+                }
+                digest_reader(&blob[..], algorithm, DEFAULT_BUFFER_SIZE)?
+            }
+            recorded => {
+                let digest = digest_reader(reader, algorithm, DEFAULT_BUFFER_SIZE)?;
+                if recorded
+                    .as_ref()
+                    .is_some_and(|(_, recorded)| *recorded != digest)
+                {
+                    return Ok(BlobVerification::DigestMismatch);
+                }
+                digest
+            }
+        };
+
+        Ok(if key.verify_prehash(&digest, &signature.signature) {
+            BlobVerification::Verified
+        } else {
+            BlobVerification::BadSignature
+        })
+    }
+
+    /// Check a signature against a blob stored in a file
+    pub fn verify_blob_file<P: AsRef<Path>>(
+        path: P,
+        signature: &BlobSignature,
+    ) -> Result<BlobVerification> {
+        verify_blob(File::open(path)?, signature)
+    }
+
+    enum VerifyingKey {
+        P256(p256::ecdsa::VerifyingKey),
+        P384(p384::ecdsa::VerifyingKey),
+    }
+
+    impl VerifyingKey {
+        fn parse(key: &[u8]) -> Result<Self> {
+            let spki = subject_public_key_info(key)?;
+            if let Ok(key) = p256::ecdsa::VerifyingKey::from_public_key_der(&spki) {
+                Ok(VerifyingKey::P256(key))
+            } else if let Ok(key) = p384::ecdsa::VerifyingKey::from_public_key_der(&spki) {
+                Ok(VerifyingKey::P384(key))
+            } else {
+                Err(HashError::UnsupportedAlgorithm(
+                    "only ECDSA P-256 and P-384 keys are supported".to_string(),
+                ))
+            }
+        }
+
+        fn digest_algorithm(&self) -> Algorithm {
+            match self {
+                VerifyingKey::P256(_) => Algorithm::Sha256,
+                VerifyingKey::P384(_) => Algorithm::Sha384,
+            }
+        }
+
+        fn verify_prehash(&self, digest: &[u8], signature: &[u8]) -> bool {
+            match self {
+                VerifyingKey::P256(key) => p256::ecdsa::Signature::from_der(signature)
+                    .is_ok_and(|signature| key.verify_prehash(digest, &signature).is_ok()),
+                VerifyingKey::P384(key) => p384::ecdsa::Signature::from_der(signature)
+                    .is_ok_and(|signature| key.verify_prehash(digest, &signature).is_ok()),
+            }
+        }
+    }
+
+    fn invalid(message: String) -> HashError {
+        HashError::InvalidInput(message)
+    }
+
+    fn decode_base64(value: &str, what: &str) -> Result<Vec<u8>> {
+        let value: String = value.chars().filter(|c| !c.is_whitespace()).collect();
+        STANDARD
+            .decode(value)
+            .map_err(|e| invalid(format!("invalid base64 {}: {}", what, e)))
+    }
+
+    /// Algorithm of a bundle's digest name (`sha256`, `SHA2_256`, ...)
+    fn bundle_algorithm(name: &str) -> Result<Algorithm> {
+        match name.to_ascii_uppercase().as_str() {
+            "SHA256" | "SHA2_256" => Ok(Algorithm::Sha256),
+            "SHA384" | "SHA2_384" => Ok(Algorithm::Sha384),
+            "SHA512" | "SHA2_512" => Ok(Algorithm::Sha512),
+            _ => Err(HashError::UnsupportedAlgorithm(format!(
+                "bundle digest algorithm {}",
+                name
+            ))),
+        }
+    }
+
+    /// A `.sig` file holds base64 text; accept raw DER as well
+    fn decode_signature(signature: &[u8]) -> Result<Vec<u8>> {
+        if signature.first() == Some(&0x30) {
+            return Ok(signature.to_vec());
+        }
+        decode_base64(&String::from_utf8_lossy(signature), "signature")
+    }
+
+    /// PEM that was base64-encoded once more, as in cosign bundles
+    fn unwrap_base64_pem(key: &[u8]) -> Vec<u8> {
+        let text = String::from_utf8_lossy(key);
+        if !text.contains("-----BEGIN") {
+            if let Ok(decoded) = decode_base64(&text, "key") {
+                if decoded.starts_with(b"-----BEGIN") {
+                    return decoded;
+                }
+            }
+        }
+        key.to_vec()
+    }
+
+    /// DER SubjectPublicKeyInfo of a certificate or public key (DER or PEM)
+    fn subject_public_key_info(key: &[u8]) -> Result<Vec<u8>> {
+        const PUBLIC_KEY_BEGIN: &str = "-----BEGIN PUBLIC KEY-----";
+        const PUBLIC_KEY_END: &str = "-----END PUBLIC KEY-----";
+
+        let text = String::from_utf8_lossy(key);
+        if let Some(start) = text.find(PUBLIC_KEY_BEGIN) {
+            let body = &text[start + PUBLIC_KEY_BEGIN.len()..];
+            let end = body
+                .find(PUBLIC_KEY_END)
+                .ok_or_else(|| invalid("unterminated PEM public key".to_string()))?;
+            return decode_base64(&body[..end], "public key");
+        }
+
+        let der = certificate_der(key)?;
+        certificate_spki(&der).map_or(Ok(der.clone()), |spki| Ok(spki.to_vec()))
+    }
+
+    /// The subjectPublicKeyInfo element of a DER certificate
+    fn certificate_spki(der: &[u8]) -> Option<&[u8]> {
+        let (certificate, _) = der_element(der, 0x30)?;
+        let (mut tbs, _) = der_element(certificate, 0x30)?;
+        if let Some((_, rest)) = der_element(tbs, 0xa0) {
+            tbs = rest;
+        }
+        // serialNumber, signature, issuer, validity, subject
+        for tag in [0x02, 0x30, 0x30, 0x30, 0x30] {
+            tbs = der_element(tbs, tag)?.1;
+        }
+        let (_, after) = der_element(tbs, 0x30)?;
+        Some(&tbs[..tbs.len() - after.len()])
+    }
+}
@@ -0,0 +1,211 @@
+//! Locality-sensitive hashing for near-duplicate detection
+//!
+//! Unlike cryptographic digests, these fingerprints change little when the
+//! input changes little:
+//!
+//! - [`MinHasher`] builds signatures whose agreement estimates the Jaccard
+//!   similarity of two sets, typically the [`word_shingles`] of documents.
+//! - [`simhash`] folds weighted features into 64 bits whose Hamming
+//!   distance tracks cosine similarity, small enough to index billions.
+//!
+//! ```
+//! use hashing::similarity::{word_shingles, MinHasher};
+//!
+//! let minhash = MinHasher::new(128).unwrap();
+//! let a = minhash.signature(word_shingles("the quick brown fox jumps over the lazy dog", 3));
+//! let b = minhash.signature(word_shingles("the quick brown fox jumped over the lazy dog", 3));
+//! let similarity = a.similarity(&b).unwrap();
+//! assert!(similarity > 0.2 && similarity < 0.8);
+//! ```
+
+use crate::builder::digest_words;
+use crate::{Algorithm, HashError, Result};
+
+/// Default algorithm for fingerprints; only its bit mixing matters
+pub const DEFAULT_ALGORITHM: Algorithm = Algorithm::Blake3;
+
+/// Overlapping runs of `k` whitespace-separated words, joined by single spaces
+///
+/// Texts shorter than `k` words yield the whole text as one shingle, so
+/// short documents still have a fingerprint.
+pub fn word_shingles(text: &str, k: usize) -> Vec<String> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let k = k.max(1);
+    if words.len() <= k {
+        return if words.is_empty() {
+            Vec::new()
+        } else {
+            vec![words.join(" ")]
+        };
+    }
+    words.windows(k).map(|window| window.join(" ")).collect()
+}
+
+/// Overlapping runs of `k` characters
+pub fn char_shingles(text: &str, k: usize) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let k = k.max(1);
+    if chars.len() <= k {
+        return if chars.is_empty() {
+            Vec::new()
+        } else {
+            vec![chars.iter().collect()]
+        };
+    }
+    chars
+        .windows(k)
+        .map(|window| window.iter().collect())
+        .collect()
+}
+
+/// Computes MinHash signatures of a fixed length
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MinHasher {
+    algorithm: Algorithm,
+    num_hashes: usize,
+}
+
+impl MinHasher {
+    /// Signatures of `num_hashes` values; the error of the similarity
+    /// estimate is about `1 / sqrt(num_hashes)`
+    pub fn new(num_hashes: usize) -> Result<Self> {
+        if num_hashes == 0 {
+            return Err(HashError::InvalidInput(
+                "MinHash needs at least one hash function".to_string(),
+            ));
+        }
+        Ok(Self {
+            algorithm: DEFAULT_ALGORITHM,
+            num_hashes,
+        })
+    }
+
+    /// Hash features with `algorithm` instead of the default
+    pub fn with_algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Signature of a set of features; duplicates do not matter
+    pub fn signature<I, T>(&self, features: I) -> MinHashSignature
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<[u8]>,
+    {
+        let mut minimums = vec![u64::MAX; self.num_hashes];
+        for feature in features {
+            // The i-th hash function is h1 + i * h2 (double hashing)
+            let (h1, h2) = digest_words(feature.as_ref(), self.algorithm);
+            for (i, minimum) in minimums.iter_mut().enumerate() {
+                let value = h1.wrapping_add((i as u64).wrapping_mul(h2 | 1));
+                *minimum = (*minimum).min(value);
+            }
+        }
+        MinHashSignature {
+            algorithm: self.algorithm,
+            values: minimums,
+        }
+    }
+}
+
+/// A MinHash signature produced by [`MinHasher::signature`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MinHashSignature {
+    algorithm: Algorithm,
+    values: Vec<u64>,
+}
+
+impl MinHashSignature {
+    /// Estimated Jaccard similarity (0.0 to 1.0) of the two feature sets
+    ///
+    /// Both signatures must come from identically configured hashers.
+    pub fn similarity(&self, other: &MinHashSignature) -> Result<f64> {
+        if self.algorithm != other.algorithm || self.values.len() != other.values.len() {
+            return Err(HashError::InvalidInput(
+                "MinHash signatures from different hashers cannot be compared".to_string(),
+            ));
+        }
+        let equal = self
+            .values
+            .iter()
+            .zip(&other.values)
+            .filter(|(a, b)| a == b)
+            .count();
+        Ok(equal as f64 / self.values.len() as f64)
+    }
+
+    /// The signature values
+    pub fn values(&self) -> &[u64] {
+        &self.values
+    }
+}
+
+/// A 64-bit SimHash fingerprint
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SimHash(pub u64);
+
+impl SimHash {
+    /// Number of differing bits (0 to 64)
+    pub fn hamming_distance(self, other: SimHash) -> u32 {
+        (self.0 ^ other.0).count_ones()
+    }
+
+    /// Fraction of matching bits (0.0 to 1.0)
+    pub fn similarity(self, other: SimHash) -> f64 {
+        1.0 - self.hamming_distance(other) as f64 / 64.0
+    }
+}
+
+impl std::fmt::Display for SimHash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+/// SimHash of equally weighted features
+///
+/// # Examples
+///
+/// ```
+/// use hashing::similarity::{simhash, word_shingles};
+/// use hashing::Algorithm;
+///
+/// let a = simhash(word_shingles("a b c d e f g h i j", 2), Algorithm::Blake3);
+/// let b = simhash(word_shingles("a b c d e f g h i k", 2), Algorithm::Blake3);
+/// assert!(a.hamming_distance(b) < 20);
+/// ```
+pub fn simhash<I, T>(features: I, algorithm: Algorithm) -> SimHash
+where
+    I: IntoIterator<Item = T>,
+    T: AsRef<[u8]>,
+{
+    simhash_weighted(
+        features.into_iter().map(|feature| (feature, 1.0)),
+        algorithm,
+    )
+}
+
+/// SimHash of features with individual weights (e.g. term frequencies)
+pub fn simhash_weighted<I, T>(features: I, algorithm: Algorithm) -> SimHash
+where
+    I: IntoIterator<Item = (T, f64)>,
+    T: AsRef<[u8]>,
+{
+    let mut totals = [0.0f64; 64];
+    for (feature, weight) in features {
+        let (hash, _) = digest_words(feature.as_ref(), algorithm);
+        for (bit, total) in totals.iter_mut().enumerate() {
+            if hash & (1 << bit) != 0 {
+                *total += weight;
+            } else {
+                *total -= weight;
+            }
+        }
+    }
+    let fingerprint = totals
+        .iter()
+        .enumerate()
+        .filter(|(_, &total)| total > 0.0)
+        .fold(0u64, |acc, (bit, _)| acc | (1 << bit));
+    SimHash(fingerprint)
+}
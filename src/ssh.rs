@@ -0,0 +1,255 @@
+//! Files on other hosts, over SSH
+//!
+//! [`RemotePath`] parses `sftp://[user@]host[:port]/path` (or `ssh://`);
+//! a path starting with `/~/` is relative to the remote home directory.
+//! With the `ssh` feature, [`open_remote`] streams such a file through the
+//! local hasher. It runs the `ssh` executable and `cat` on the far side, so
+//! the remote host needs nothing but an SSH server and a POSIX shell, and
+//! `~/.ssh/config`, the SSH agent and `known_hosts` apply as they do for
+//! any other `ssh` command.
+//!
+//! ```
+//! use hashing::ssh::RemotePath;
+//!
+//! let remote: RemotePath = "sftp://backup@nas:2222/srv/backups/db.dump".parse()?;
+//! assert_eq!(remote.user.as_deref(), Some("backup"));
+//! assert_eq!(remote.host, "nas");
+//! assert_eq!(remote.port, Some(2222));
+//! assert_eq!(remote.path, "/srv/backups/db.dump");
+//! # Ok::<(), hashing::HashError>(())
+//! ```
+
+use crate::{HashError, Result};
+use std::fmt;
+use std::str::FromStr;
+
+/// A file on a host reachable over SSH
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemotePath {
+    /// Login name, if not the one `ssh` would pick
+    pub user: Option<String>,
+    /// Host name or address, or an alias from `~/.ssh/config`
+    pub host: String,
+    /// Port, if not the one `ssh` would pick
+    pub port: Option<u16>,
+    /// Path on the host: absolute, or relative to the home directory
+    pub path: String,
+}
+
+impl RemotePath {
+    /// The command run on the remote host to read the file
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hashing::ssh::RemotePath;
+    ///
+    /// let remote: RemotePath = "sftp://nas/~/it's%20here.txt".parse()?;
+    /// assert_eq!(remote.command(), r"cat -- 'it'\''s here.txt'");
+    /// # Ok::<(), hashing::HashError>(())
+    /// ```
+    pub fn command(&self) -> String {
+        format!("cat -- '{}'", self.path.replace('\'', r"'\''"))
+    }
+}
+
+impl FromStr for RemotePath {
+    type Err = HashError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = |why: &str| {
+            HashError::InvalidInput(format!(
+                "invalid remote path {}: {} (expected sftp://[user@]host[:port]/path)",
+                s, why
+            ))
+        };
+        let rest = s
+            .strip_prefix("sftp://")
+            .or_else(|| s.strip_prefix("ssh://"))
+            .ok_or_else(|| invalid("not an sftp:// or ssh:// URL"))?;
+        let (authority, path) = match rest.find('/') {
+            Some(slash) => rest.split_at(slash),
+            None => return Err(invalid("no path")),
+        };
+        let (user, host_port) = match authority.rsplit_once('@') {
+            Some((user, host_port)) => (Some(percent_decode(user)?), host_port),
+            None => (None, authority),
+        };
+        let (host, port) = match host_port.strip_prefix('[') {
+            // An IPv6 address, as in [::1]:22
+            Some(bracketed) => {
+                let (host, after) = bracketed
+                    .split_once(']')
+                    .ok_or_else(|| invalid("unclosed ["))?;
+                (host, after.strip_prefix(':'))
+            }
+            None => match host_port.rsplit_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (host_port, None),
+            },
+        };
+        let port = match port {
+            Some(port) => Some(port.parse().map_err(|_| invalid("bad port"))?),
+            None => None,
+        };
+        let path = percent_decode(path)?;
+        let path = match path.strip_prefix("/~/") {
+            Some(relative) => relative.to_string(),
+            None => path,
+        };
+        if host.is_empty() {
+            return Err(invalid("no host"));
+        }
+        if path.is_empty() || path == "/" {
+            return Err(invalid("no path"));
+        }
+        Ok(Self {
+            user: user.filter(|user| !user.is_empty()),
+            host: host.to_string(),
+            port,
+            path,
+        })
+    }
+}
+
+impl fmt::Display for RemotePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "sftp://")?;
+        if let Some(ref user) = self.user {
+            write!(f, "{}@", user)?;
+        }
+        if self.host.contains(':') {
+            write!(f, "[{}]", self.host)?;
+        } else {
+            write!(f, "{}", self.host)?;
+        }
+        if let Some(port) = self.port {
+            write!(f, ":{}", port)?;
+        }
+        if self.path.starts_with('/') {
+            write!(f, "{}", self.path)
+        } else {
+            write!(f, "/~/{}", self.path)
+        }
+    }
+}
+
+/// Whether `input` looks like an `sftp://` or `ssh://` URL
+pub fn is_remote_url(input: &str) -> bool {
+    input.starts_with("sftp://") || input.starts_with("ssh://")
+}
+
+/// `text` with `%XX` escapes decoded
+fn percent_decode(text: &str) -> Result<String> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let byte = text
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| HashError::InvalidInput(format!("invalid %-escape in {}", text)))?;
+            decoded.push(byte);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded)
+        .map_err(|_| HashError::InvalidInput(format!("{} is not UTF-8 once decoded", text)))
+}
+
+#[cfg(feature = "ssh")]
+pub use remote::*;
+
+#[cfg(feature = "ssh")]
+mod remote {
+    use super::RemotePath;
+    use crate::{HashError, Result};
+    use std::io::{self, Read};
+    use std::process::{Child, ChildStdout, Command, Stdio};
+
+    /// A remote file being read through `ssh`
+    ///
+    /// Reaching the end of the file waits for `ssh`, so a connection
+    /// failure or a file that cannot be read fails the last read instead
+    /// of passing for an empty or short file.
+    pub struct RemoteFile {
+        child: Child,
+        stdout: ChildStdout,
+        finished: bool,
+    }
+
+    impl Read for RemoteFile {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.finished {
+                return Ok(0);
+            }
+            let count = self.stdout.read(buf)?;
+            if count == 0 && !buf.is_empty() {
+                self.finished = true;
+                let status = self.child.wait()?;
+                if !status.success() {
+                    // ssh itself exits with 255; anything else is cat's status
+                    let failed = match status.code() {
+                        Some(255) => "ssh failed",
+                        _ => "the remote file could not be read",
+                    };
+                    return Err(io::Error::new(
+                        io::ErrorKind::Other,
+                        format!("{} ({})", failed, status),
+                    ));
+                }
+            }
+            Ok(count)
+        }
+    }
+
+    impl Drop for RemoteFile {
+        fn drop(&mut self) {
+            if !self.finished {
+                let _ = self.child.kill();
+                let _ = self.child.wait();
+            }
+        }
+    }
+
+    /// Start reading `remote` through `ssh`
+    ///
+    /// `ssh` prompts for passwords and host keys on the terminal as usual;
+    /// its messages and those of the remote `cat` go to standard error.
+    ///
+    /// ```no_run
+    /// use hashing::ssh::{open_remote, RemotePath};
+    /// use hashing::{hash_reader, Algorithm};
+    ///
+    /// let remote: RemotePath = "sftp://nas/srv/backups/db.dump".parse()?;
+    /// let digest = hash_reader(open_remote(&remote)?, Algorithm::Sha256)?;
+    /// # Ok::<(), hashing::HashError>(())
+    /// ```
+    pub fn open_remote(remote: &RemotePath) -> Result<RemoteFile> {
+        let mut command = Command::new("ssh");
+        if let Some(port) = remote.port {
+            command.arg("-p").arg(port.to_string());
+        }
+        if let Some(ref user) = remote.user {
+            command.arg("-l").arg(user);
+        }
+        let mut child = command
+            .arg("--")
+            .arg(&remote.host)
+            .arg(remote.command())
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| HashError::InvalidInput(format!("failed to run ssh: {}", e)))?;
+        let stdout = child.stdout.take().expect("piped stdout");
+        Ok(RemoteFile {
+            child,
+            stdout,
+            finished: false,
+        })
+    }
+}
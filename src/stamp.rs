@@ -0,0 +1,101 @@
+//! Content-hash stamp files and depfiles for build systems
+//!
+//! Make and Ninja decide what to rebuild from modification times, so
+//! touching a file (a fresh checkout, a generator that rewrites identical
+//! output) triggers needless rebuilds. A stamp file records the digests of
+//! a rule's inputs and is only rewritten when one of them changes; rules
+//! that depend on the stamp instead of the inputs then rebuild on content
+//! changes alone. [`depfile`] lists the inputs in the Make syntax that
+//! Ninja's `depfile =` and GCC-style `-include` directives read, so the
+//! stamp is refreshed whenever an input is touched.
+//!
+//! ```no_run
+//! use hashing::stamp::{stamp_contents, update_stamp};
+//! use hashing::Algorithm;
+//!
+//! let contents = stamp_contents(&["src", "Cargo.toml"], Algorithm::Sha256)?;
+//! if update_stamp("build/inputs.stamp", &contents)? {
+//!     println!("inputs changed");
+//! }
+//! # Ok::<(), hashing::HashError>(())
+//! ```
+
+use crate::walk::{walk_files, SortOrder, WalkOptions};
+use crate::{hash_file, Algorithm, Result};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Files named by `paths`, with directories expanded recursively in name
+/// order
+pub fn input_files<P: AsRef<Path>>(paths: &[P]) -> Result<Vec<PathBuf>> {
+    let options = WalkOptions::new().with_sort(SortOrder::Name);
+    let mut files = Vec::new();
+    for path in paths {
+        let path = path.as_ref();
+        if path.is_dir() {
+            for file in walk_files(path, &options) {
+                files.push(file?);
+            }
+        } else {
+            files.push(path.to_path_buf());
+        }
+    }
+    Ok(files)
+}
+
+/// Stamp file contents for `paths`: one `<digest>  <path>` line per file,
+/// as `sha256sum` writes them
+pub fn stamp_contents<P: AsRef<Path>>(paths: &[P], algorithm: Algorithm) -> Result<String> {
+    let mut contents = String::new();
+    for file in input_files(paths)? {
+        let digest = hash_file(&file, algorithm)?;
+        contents.push_str(&format!("{}  {}\n", digest, file.display()));
+    }
+    Ok(contents)
+}
+
+/// Write `contents` to `path` unless it already holds exactly that
+///
+/// Returns whether the file was written, so its modification time only
+/// moves when the contents change.
+pub fn update_stamp<P: AsRef<Path>>(path: P, contents: &str) -> Result<bool> {
+    let path = path.as_ref();
+    match fs::read(path) {
+        Ok(existing) if existing == contents.as_bytes() => return Ok(false),
+        Ok(_) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+    fs::write(path, contents)?;
+    Ok(true)
+}
+
+/// A Make-syntax depfile stating that `target` depends on `dependencies`
+///
+/// Spaces, `#` and `$` in paths are escaped the way Make and Ninja expect.
+pub fn depfile<P: AsRef<Path>>(target: &Path, dependencies: &[P]) -> String {
+    let mut out = escape(target);
+    out.push(':');
+    for dependency in dependencies {
+        out.push_str(" \\\n  ");
+        out.push_str(&escape(dependency.as_ref()));
+    }
+    out.push('\n');
+    out
+}
+
+fn escape(path: &Path) -> String {
+    let mut out = String::new();
+    for c in path.to_string_lossy().chars() {
+        match c {
+            ' ' | '#' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '$' => out.push_str("$$"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
@@ -0,0 +1,227 @@
+//! Size and modification-time cache for repeated verification
+//!
+//! Nightly checks of a large tree re-hash files that have not been touched
+//! since the previous run. A [`StatCache`] remembers, for every file that
+//! verified, its size and modification time together with the digest it
+//! matched; while both are unchanged and the expected digest is the same,
+//! the file can be assumed to still match without reading it.
+//!
+//! This trusts file metadata: a change that preserves size and mtime (or a
+//! clock set backwards) goes unnoticed, so keep a periodic full check.
+//!
+//! The cache file is plain text, one file per line:
+//! `<size>\t<mtime>\t<algorithm>\t<digest>\t<path>`. In memory, paths are
+//! kept by directory, so a directory's path is stored once however many
+//! files it holds; paths that are not valid UTF-8 are never cached.
+
+use crate::{Algorithm, Result};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Files modified this recently are not recorded: a further change within
+/// the same timestamp tick would keep the recorded mtime
+const SETTLE_TIME: Duration = Duration::from_secs(2);
+
+/// Size and modification time of a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileStamp {
+    /// Size in bytes
+    pub size: u64,
+    /// Modification time since the Unix epoch
+    pub modified: Duration,
+}
+
+impl FileStamp {
+    /// Stamp of the file at `path`, following symlinks
+    pub fn of<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let metadata = fs::metadata(path)?;
+        let modified = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        Ok(Self {
+            size: metadata.len(),
+            modified,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CachedDigest {
+    stamp: FileStamp,
+    algorithm: Algorithm,
+    digest: Box<str>,
+}
+
+/// Digests known to match, by path
+#[derive(Debug, Clone, Default)]
+pub struct StatCache {
+    /// Files by directory, then by file name
+    dirs: HashMap<Box<str>, HashMap<Box<str>, CachedDigest>>,
+    len: usize,
+}
+
+impl StatCache {
+    /// An empty cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read a cache file; a missing file gives an empty cache and
+    /// malformed lines are skipped
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut cache = Self::new();
+        let mut reader = BufReader::new(file);
+        let mut line = String::new();
+        while reader.read_line(&mut line)? > 0 {
+            if let Some((path, cached)) = parse_line(line.trim_end_matches(['\r', '\n'])) {
+                cache.insert(path, cached);
+            }
+            line.clear();
+        }
+        Ok(cache)
+    }
+
+    /// Write the cache file, replacing it atomically
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let mut lines: Vec<String> = Vec::with_capacity(self.len);
+        for (dir, files) in &self.dirs {
+            for (name, cached) in files {
+                let file = Path::new(&**dir).join(&**name);
+                let Some(file) = file.to_str().filter(|file| !file.contains('\n')) else {
+                    continue;
+                };
+                lines.push(format!(
+                    "{}\t{}.{:09}\t{}\t{}\t{}\n",
+                    cached.stamp.size,
+                    cached.stamp.modified.as_secs(),
+                    cached.stamp.modified.subsec_nanos(),
+                    cached.algorithm.name(),
+                    cached.digest,
+                    file
+                ));
+            }
+        }
+        lines.sort();
+        let mut temp = path.as_os_str().to_owned();
+        temp.push(".tmp");
+        let mut writer = BufWriter::new(File::create(&temp)?);
+        for line in &lines {
+            writer.write_all(line.as_bytes())?;
+        }
+        writer.flush()?;
+        drop(writer);
+        fs::rename(&temp, path)?;
+        Ok(())
+    }
+
+    /// Number of cached files
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// True if no file is cached
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Whether `path` verified against `digest` with `algorithm` and still
+    /// has the same size and modification time
+    pub fn is_fresh(
+        &self,
+        path: &Path,
+        stamp: FileStamp,
+        algorithm: Algorithm,
+        digest: &str,
+    ) -> bool {
+        let Some((dir, name)) = split(path) else {
+            return false;
+        };
+        let cached = self.dirs.get(dir).and_then(|files| files.get(name));
+        cached.is_some_and(|cached| {
+            cached.stamp == stamp
+                && cached.algorithm == algorithm
+                && cached.digest.eq_ignore_ascii_case(digest)
+        })
+    }
+
+    /// Remember that `path`, as of `stamp`, matched `digest`
+    ///
+    /// Files modified in the last two seconds are forgotten instead.
+    pub fn record(&mut self, path: &Path, stamp: FileStamp, algorithm: Algorithm, digest: &str) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        if stamp.modified + SETTLE_TIME > now {
+            self.forget(path);
+            return;
+        }
+        let cached = CachedDigest {
+            stamp,
+            algorithm,
+            digest: digest.to_ascii_lowercase().into(),
+        };
+        self.insert(path, cached);
+    }
+
+    /// Drop whatever is cached for `path`
+    pub fn forget(&mut self, path: &Path) {
+        let Some((dir, name)) = split(path) else {
+            return;
+        };
+        let Some(files) = self.dirs.get_mut(dir) else {
+            return;
+        };
+        if files.remove(name).is_some() {
+            self.len -= 1;
+            if files.is_empty() {
+                self.dirs.remove(dir);
+            }
+        }
+    }
+
+    fn insert(&mut self, path: &Path, cached: CachedDigest) {
+        let Some((dir, name)) = split(path) else {
+            return;
+        };
+        let files = match self.dirs.get_mut(dir) {
+            Some(files) => files,
+            None => self.dirs.entry(dir.into()).or_default(),
+        };
+        if files.insert(name.into(), cached).is_none() {
+            self.len += 1;
+        }
+    }
+}
+
+/// Directory and file name of `path`, if both are UTF-8
+fn split(path: &Path) -> Option<(&str, &str)> {
+    let name = path.file_name()?.to_str()?;
+    let dir = path.parent().map_or(Some(""), Path::to_str)?;
+    Some((dir, name))
+}
+
+fn parse_line(line: &str) -> Option<(&Path, CachedDigest)> {
+    let mut fields = line.splitn(5, '\t');
+    let size = fields.next()?.parse().ok()?;
+    let (secs, nanos) = fields.next()?.split_once('.')?;
+    let modified = Duration::new(secs.parse().ok()?, nanos.parse().ok()?);
+    let algorithm = fields.next()?.parse().ok()?;
+    let digest = fields.next()?.into();
+    let path = Path::new(fields.next()?);
+    let cached = CachedDigest {
+        stamp: FileStamp { size, modified },
+        algorithm,
+        digest,
+    };
+    Some((path, cached))
+}
@@ -0,0 +1,180 @@
+//! Custom output lines from a template
+//!
+//! A [`Template`] is text with `{field}` placeholders, filled in from a
+//! [`HashResult`] for every line of output. It covers the many one-off
+//! line formats downstream tools expect without an exporter for each.
+//!
+//! | Placeholder | Value |
+//! |-------------|-------|
+//! | `{digest}` | The digest as printed elsewhere (lowercase hex) |
+//! | `{algo}`, `{algorithm}` | Algorithm name, e.g. `sha256` |
+//! | `{path}` | Input path; empty for strings |
+//! | `{name}` | File name of the input path |
+//! | `{size}` | Input size in bytes; empty when unknown |
+//! | `{type}` | Input type: `file` or `string` |
+//!
+//! `{{` and `}}` stand for literal braces, and the escapes `\t`, `\n`,
+//! `\r`, `\0` and `\\` are recognised, so a template passed from a shell
+//! in single quotes still yields tabs and newlines. Unknown placeholders and
+//! escapes are errors when the template is parsed, not when it is used.
+//!
+//! ```
+//! use hashing::template::Template;
+//! use hashing::{Algorithm, HashResult};
+//!
+//! let template = Template::parse(r"{path}\t{algo}\t{digest}\t{size}")?;
+//! let result = HashResult::new(Algorithm::Md5, "d41d8cd9".to_string(), "file")
+//!     .with_path("empty.txt");
+//! assert_eq!(template.render(&result, Some(0)), "empty.txt\tmd5\td41d8cd9\t0");
+//! # Ok::<(), hashing::HashError>(())
+//! ```
+
+use crate::{HashError, HashResult, Result};
+use std::fmt::Write;
+use std::path::Path;
+
+/// A value a placeholder stands for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    /// `{digest}`
+    Digest,
+    /// `{algo}` or `{algorithm}`
+    Algorithm,
+    /// `{path}`
+    Path,
+    /// `{name}`
+    Name,
+    /// `{size}`
+    Size,
+    /// `{type}`
+    Type,
+}
+
+impl Field {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "digest" => Field::Digest,
+            "algo" | "algorithm" => Field::Algorithm,
+            "path" => Field::Path,
+            "name" => Field::Name,
+            "size" => Field::Size,
+            "type" => Field::Type,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Field(Field),
+}
+
+/// A parsed output template
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+    /// Parse `text`, resolving escapes and checking placeholder names
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let rest = chars.as_str();
+                    let end = rest
+                        .find('}')
+                        .ok_or_else(|| invalid(format!("unclosed placeholder in {:?}", text)))?;
+                    let name = &rest[..end];
+                    let field = Field::from_name(name.trim()).ok_or_else(|| {
+                        invalid(format!(
+                            "unknown placeholder {{{}}} (expected digest, algo, path, name, \
+                             size or type)",
+                            name
+                        ))
+                    })?;
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(Part::Field(field));
+                    chars = rest[end + 1..].chars();
+                }
+                '}' => return Err(invalid("unmatched } (write }} for a literal brace)")),
+                '\\' => literal.push(match chars.next() {
+                    Some('t') => '\t',
+                    Some('n') => '\n',
+                    Some('r') => '\r',
+                    Some('0') => '\0',
+                    Some('\\') => '\\',
+                    Some(other) => return Err(invalid(format!("unknown escape \\{}", other))),
+                    None => return Err(invalid("template ends with a lone \\")),
+                }),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        Ok(Self { parts })
+    }
+
+    /// Fields the template uses, in order of appearance
+    pub fn fields(&self) -> impl Iterator<Item = Field> + '_ {
+        self.parts.iter().filter_map(|part| match part {
+            Part::Field(field) => Some(*field),
+            Part::Literal(_) => None,
+        })
+    }
+
+    /// Whether the template uses `field`
+    pub fn uses(&self, field: Field) -> bool {
+        self.fields().any(|used| used == field)
+    }
+
+    /// The line for `result`, whose input is `size` bytes long if known
+    pub fn render(&self, result: &HashResult, size: Option<u64>) -> String {
+        let mut line = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => line.push_str(text),
+                Part::Field(Field::Digest) => line.push_str(&result.digest),
+                Part::Field(Field::Algorithm) => line.push_str(&result.algorithm),
+                Part::Field(Field::Path) => {
+                    line.push_str(result.input_path.as_deref().unwrap_or_default())
+                }
+                Part::Field(Field::Name) => {
+                    let name = result
+                        .input_path
+                        .as_deref()
+                        .and_then(|path| Path::new(path).file_name())
+                        .map(|name| name.to_string_lossy());
+                    line.push_str(name.as_deref().unwrap_or_default());
+                }
+                Part::Field(Field::Size) => {
+                    if let Some(size) = size {
+                        // Writing to a String cannot fail
+                        let _ = write!(line, "{}", size);
+                    }
+                }
+                Part::Field(Field::Type) => line.push_str(&result.input_type),
+            }
+        }
+        line
+    }
+}
+
+fn invalid(message: impl Into<String>) -> HashError {
+    HashError::InvalidInput(format!("invalid template: {}", message.into()))
+}
@@ -0,0 +1,155 @@
+//! Digests of whole directory trees
+//!
+//! [`hash_dir`] hashes every file below a directory and returns the
+//! digests sorted by relative path. [`DirHash::merkle_root`] folds them
+//! into one digest for the whole tree that depends only on the relative
+//! paths and the file contents, not on where the tree lives, the order the
+//! file system lists it in or timestamps, so two copies of a tree can be
+//! compared by a single value.
+//!
+//! ```no_run
+//! use hashing::tree::hash_dir;
+//! use hashing::walk::{Glob, WalkOptions};
+//! use hashing::Algorithm;
+//!
+//! let options = WalkOptions::new().with_exclude(Glob::new("target")?);
+//! let tree = hash_dir("project", Algorithm::Sha256, &options)?;
+//! for file in &tree.files {
+//!     println!("{}  {}", file.digest, file.path);
+//! }
+//! println!("root {}", tree.merkle_root()?);
+//! # Ok::<(), hashing::HashError>(())
+//! ```
+//!
+//! # Merkle root
+//!
+//! The root follows the tree hash of RFC 6962 (Certificate Transparency),
+//! computed with the same algorithm as the files. Each file is a leaf
+//! `H(0x00 || len || path || digest)`, where `path` is the UTF-8 relative
+//! path with `/` separators and `len` its length in bytes as a big-endian
+//! `u64`. Leaves are ordered by path and paired up as
+//! `H(0x01 || left || right)`, the left side taking the largest power of
+//! two of leaves below the count. An empty tree has the digest of no bytes.
+
+use crate::builder::digest_bytes;
+use crate::walk::{relative_path, walk_files, WalkOptions};
+use crate::{hash_file_raw, Algorithm, Digest, Result};
+use std::path::Path;
+
+/// Prefix of leaf hashes, keeping them apart from node hashes
+const LEAF: u8 = 0x00;
+
+/// Prefix of node hashes
+const NODE: u8 = 0x01;
+
+/// One file of a hashed tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileDigest {
+    /// Path below the root, with `/` separators
+    pub path: String,
+    /// Digest of the file's contents
+    pub digest: Digest,
+}
+
+/// The files of a directory tree and their digests
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirHash {
+    /// Algorithm the files were hashed with
+    pub algorithm: Algorithm,
+    /// Every file hashed, sorted by path
+    pub files: Vec<FileDigest>,
+}
+
+impl DirHash {
+    /// One digest over all the paths and file digests; see the
+    /// [module documentation](self) for the construction
+    pub fn merkle_root(&self) -> Result<Digest> {
+        merkle_root(&self.files, self.algorithm)
+    }
+}
+
+/// Hash every file below `root` that `options` let through
+///
+/// Files are hashed one after another and the first that cannot be read
+/// fails the whole call; use [`crate::job::HashJob`] to carry on past
+/// unreadable files or hash on several threads.
+pub fn hash_dir<P: AsRef<Path>>(
+    root: P,
+    algorithm: Algorithm,
+    options: &WalkOptions,
+) -> Result<DirHash> {
+    let root = root.as_ref();
+    let mut files = Vec::new();
+    for path in walk_files(root, options) {
+        let path = path?;
+        files.push(FileDigest {
+            digest: hash_file_raw(&path, algorithm)?,
+            path: relative_path(root, &path),
+        });
+    }
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(DirHash { algorithm, files })
+}
+
+/// Merkle root over `files` with `algorithm`, in path order whatever order
+/// they are given in
+///
+/// # Examples
+///
+/// ```
+/// use hashing::tree::{merkle_root, FileDigest};
+/// use hashing::{hash_bytes_raw, Algorithm};
+///
+/// let file = |path: &str, contents: &[u8]| FileDigest {
+///     path: path.to_string(),
+///     digest: hash_bytes_raw(contents, Algorithm::Sha256).unwrap(),
+/// };
+/// let a = file("a.txt", b"one");
+/// let b = file("docs/b.txt", b"two");
+/// let root = merkle_root(&[a.clone(), b.clone()], Algorithm::Sha256)?;
+/// assert_eq!(root, merkle_root(&[b, a], Algorithm::Sha256)?);
+/// # Ok::<(), hashing::HashError>(())
+/// ```
+pub fn merkle_root(files: &[FileDigest], algorithm: Algorithm) -> Result<Digest> {
+    let mut sorted: Vec<&FileDigest> = files.iter().collect();
+    sorted.sort_by(|a, b| a.path.cmp(&b.path));
+    let leaves = sorted
+        .into_iter()
+        .map(|file| leaf(file, algorithm))
+        .collect::<Result<Vec<_>>>()?;
+    let root = if leaves.is_empty() {
+        digest_bytes(&[], algorithm)?
+    } else {
+        subtree(&leaves, algorithm)?
+    };
+    Ok(Digest::computed(algorithm, root))
+}
+
+fn leaf(file: &FileDigest, algorithm: Algorithm) -> Result<Vec<u8>> {
+    let path = file.path.as_bytes();
+    let mut data = Vec::with_capacity(9 + path.len() + file.digest.len());
+    data.push(LEAF);
+    data.extend_from_slice(&(path.len() as u64).to_be_bytes());
+    data.extend_from_slice(path);
+    data.extend_from_slice(file.digest.as_bytes());
+    digest_bytes(&data, algorithm)
+}
+
+/// Root of a non-empty run of leaf hashes
+fn subtree(leaves: &[Vec<u8>], algorithm: Algorithm) -> Result<Vec<u8>> {
+    if leaves.len() == 1 {
+        return Ok(leaves[0].clone());
+    }
+    // Largest power of two below the count
+    let mut split = 1;
+    while split * 2 < leaves.len() {
+        split *= 2;
+    }
+    let left = subtree(&leaves[..split], algorithm)?;
+    let right = subtree(&leaves[split..], algorithm)?;
+    let mut data = Vec::with_capacity(1 + left.len() + right.len());
+    data.push(NODE);
+    data.extend_from_slice(&left);
+    data.extend_from_slice(&right);
+    digest_bytes(&data, algorithm)
+}
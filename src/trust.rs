@@ -0,0 +1,115 @@
+//! Signed baselines kept apart from the data they describe
+//!
+//! A manifest stored next to the files it lists can be rewritten by
+//! whoever changes the files. The sturdier pattern keeps a snapshot of the
+//! manifest elsewhere (a USB key, a read-only share, an HTTPS server)
+//! together with a detached signature, and trusts it only if the signature
+//! was made by a key the verifier chose in advance. [`TrustStore`] holds
+//! those keys, by name, and `TrustStore::verify` tells which of them
+//! signed a manifest, if any.
+//!
+//! Signatures are the ones `cosign sign-blob --key` and
+//! `openssl dgst -sha256 -sign` write: DER ECDSA over P-256 or P-384,
+//! base64 or raw. Keys are PEM public keys or certificates. Verifying needs
+//! the `sigstore` feature.
+
+use crate::{HashError, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A public key a signed manifest may be verified with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrustedKey {
+    /// Name the key was added under, reported when it verifies
+    pub name: String,
+    /// PEM public key or certificate
+    pub key: Vec<u8>,
+}
+
+/// The keys trusted to sign manifests
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrustStore {
+    keys: Vec<TrustedKey>,
+}
+
+impl TrustStore {
+    /// A store that trusts no key
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trust the PEM public key or certificate `key` under `name`
+    pub fn add(&mut self, name: impl Into<String>, key: impl Into<Vec<u8>>) {
+        self.keys.push(TrustedKey {
+            name: name.into(),
+            key: key.into(),
+        });
+    }
+
+    /// Trust the key stored in the file at `path` under `name`
+    pub fn add_file<P: AsRef<Path>>(&mut self, name: impl Into<String>, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let key = fs::read(path).map_err(|e| {
+            HashError::InvalidInput(format!("cannot read trusted key {}: {}", path.display(), e))
+        })?;
+        self.add(name, key);
+        Ok(())
+    }
+
+    /// A store with every key of a name-to-file table, such as the
+    /// `[trust.keys]` table of the config file
+    pub fn from_files(keys: &BTreeMap<String, PathBuf>) -> Result<Self> {
+        let mut store = Self::new();
+        for (name, path) in keys {
+            store.add_file(name.as_str(), path)?;
+        }
+        Ok(store)
+    }
+
+    /// The trusted keys, in the order they were added
+    pub fn keys(&self) -> &[TrustedKey] {
+        &self.keys
+    }
+
+    /// Whether no key is trusted, in which case nothing can verify
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// The trusted key whose holder signed `manifest`, or `None` if no
+    /// trusted key verifies `signature` over it
+    ///
+    /// A trusted key that cannot be parsed is an error rather than a key
+    /// that does not match, so that a broken trust configuration does not
+    /// pass for a forged manifest.
+    ///
+    /// ```no_run
+    /// use hashing::trust::TrustStore;
+    ///
+    /// let mut trust = TrustStore::new();
+    /// trust.add_file("usb", "/media/usb/baseline.pub")?;
+    /// let manifest = std::fs::read("/media/usb/SHA256SUMS")?;
+    /// let signature = std::fs::read("/media/usb/SHA256SUMS.sig")?;
+    /// match trust.verify(&manifest, &signature)? {
+    ///     Some(key) => println!("signed by {}", key.name),
+    ///     None => println!("not signed by a trusted key"),
+    /// }
+    /// # Ok::<(), hashing::HashError>(())
+    /// ```
+    #[cfg(feature = "sigstore")]
+    pub fn verify(&self, manifest: &[u8], signature: &[u8]) -> Result<Option<&TrustedKey>> {
+        use crate::sigstore::{verify_blob, BlobSignature, BlobVerification};
+
+        for trusted in &self.keys {
+            let detached = BlobSignature::from_detached(signature, &trusted.key)?;
+            let verification = verify_blob(manifest, &detached).map_err(|e| {
+                HashError::InvalidInput(format!("trusted key {}: {}", trusted.name, e))
+            })?;
+            if verification == BlobVerification::Verified {
+                return Ok(Some(trusted));
+            }
+        }
+        Ok(None)
+    }
+}
@@ -0,0 +1,177 @@
+//! Human-readable sizes and throughput
+//!
+//! [`SizeFormat`] prints byte counts with binary (KiB, MiB, powers of 1024)
+//! or SI (kB, MB, powers of 1000) prefixes, using the decimal separator of
+//! the user's locale when built with [`SizeFormat::from_env`]:
+//!
+//! ```
+//! use hashing::units::{SizeFormat, SizeUnits};
+//! use std::time::Duration;
+//!
+//! let binary = SizeFormat::new();
+//! assert_eq!(binary.size(1536), "1.5 KiB");
+//! assert_eq!(binary.rate(3 << 20, Duration::from_secs(2)), "1.5 MiB/s");
+//!
+//! let si = SizeFormat::new().with_units(SizeUnits::Si).with_decimal_point(',');
+//! assert_eq!(si.size(1_500_000), "1,5 MB");
+//! assert_eq!(si.size(999), "999 B");
+//! ```
+
+use crate::{HashError, Result};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Environment variables consulted for the locale, most specific first
+const LOCALE_VARS: [&str; 3] = ["LC_ALL", "LC_NUMERIC", "LANG"];
+
+/// Languages that write a decimal comma
+const COMMA_LANGUAGES: &[&str] = &[
+    "az", "be", "bg", "ca", "cs", "da", "de", "el", "es", "et", "eu", "fi", "fr", "gl", "hr", "hu",
+    "id", "is", "it", "kk", "lt", "lv", "mk", "nb", "nl", "nn", "no", "pl", "pt", "ro", "ru", "sk",
+    "sl", "sq", "sr", "sv", "tr", "uk", "vi",
+];
+
+/// Unit prefixes for [`SizeFormat`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SizeUnits {
+    /// Powers of 1024: KiB, MiB, GiB, ...
+    #[default]
+    Binary,
+    /// Powers of 1000: kB, MB, GB, ...
+    Si,
+}
+
+impl SizeUnits {
+    fn base(self) -> f64 {
+        match self {
+            SizeUnits::Binary => 1024.0,
+            SizeUnits::Si => 1000.0,
+        }
+    }
+
+    fn prefixes(self) -> [&'static str; 6] {
+        match self {
+            SizeUnits::Binary => ["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"],
+            SizeUnits::Si => ["kB", "MB", "GB", "TB", "PB", "EB"],
+        }
+    }
+}
+
+impl fmt::Display for SizeUnits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SizeUnits::Binary => "binary",
+            SizeUnits::Si => "si",
+        })
+    }
+}
+
+impl FromStr for SizeUnits {
+    type Err = HashError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "binary" | "iec" => Ok(SizeUnits::Binary),
+            "si" | "decimal" => Ok(SizeUnits::Si),
+            _ => Err(HashError::InvalidInput(format!(
+                "unknown size units: {} (expected binary or si)",
+                s
+            ))),
+        }
+    }
+}
+
+/// Formats byte counts and rates for people
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeFormat {
+    units: SizeUnits,
+    decimal_point: char,
+}
+
+impl Default for SizeFormat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SizeFormat {
+    /// Binary units with a `.` decimal point
+    pub fn new() -> Self {
+        Self {
+            units: SizeUnits::Binary,
+            decimal_point: '.',
+        }
+    }
+
+    /// Binary units with the decimal point of the locale named by
+    /// `LC_ALL`, `LC_NUMERIC` or `LANG`
+    pub fn from_env() -> Self {
+        let locale = LOCALE_VARS
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty());
+        let decimal_point = locale.as_deref().map_or('.', decimal_point);
+        Self::new().with_decimal_point(decimal_point)
+    }
+
+    /// Use binary or SI prefixes
+    pub fn with_units(mut self, units: SizeUnits) -> Self {
+        self.units = units;
+        self
+    }
+
+    /// Separate the fractional digit with `decimal_point`
+    pub fn with_decimal_point(mut self, decimal_point: char) -> Self {
+        self.decimal_point = decimal_point;
+        self
+    }
+
+    /// Units in use
+    pub fn units(&self) -> SizeUnits {
+        self.units
+    }
+
+    /// `bytes` with one fractional digit and the largest fitting prefix,
+    /// e.g. `1.5 MiB`; counts below one kilo are printed exactly
+    pub fn size(&self, bytes: u64) -> String {
+        self.scaled(bytes as f64, "")
+    }
+
+    /// Throughput of `bytes` over `elapsed`, e.g. `120.4 MiB/s`
+    pub fn rate(&self, bytes: u64, elapsed: Duration) -> String {
+        let secs = elapsed.as_secs_f64();
+        let per_sec = if secs > 0.0 { bytes as f64 / secs } else { 0.0 };
+        self.scaled(per_sec, "/s")
+    }
+
+    fn scaled(&self, value: f64, suffix: &str) -> String {
+        let base = self.units.base();
+        if value < base {
+            return format!("{} B{}", value.round() as u64, suffix);
+        }
+        let mut value = value / base;
+        let mut prefix = 0;
+        // Move up while the rounded value would print as `1024.0`
+        while value >= base - 0.05 && prefix + 1 < self.units.prefixes().len() {
+            value /= base;
+            prefix += 1;
+        }
+        let number = format!("{:.1}", value).replace('.', &self.decimal_point.to_string());
+        format!("{} {}{}", number, self.units.prefixes()[prefix], suffix)
+    }
+}
+
+/// Decimal point used by `locale`, a POSIX locale name such as `de_DE.UTF-8`
+pub fn decimal_point(locale: &str) -> char {
+    let language = locale
+        .split(['_', '.', '@', '-'])
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    if COMMA_LANGUAGES.contains(&language.as_str()) {
+        ','
+    } else {
+        '.'
+    }
+}
@@ -0,0 +1,644 @@
+//! Directory walking for recursive hashing
+//!
+//! The walker yields regular files lazily so that callers can hash and emit
+//! results one at a time, keeping memory usage flat regardless of tree size.
+
+use crate::{HashError, Result};
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap};
+use std::fs::Metadata;
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+/// Order in which the entries of each directory are visited
+///
+/// Sorting is applied to the siblings within each directory, which keeps the
+/// walk streaming while still producing a deterministic order. Ties are
+/// always broken by file name so repeated scans of the same tree match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortOrder {
+    /// Filesystem order (fastest, not deterministic across systems)
+    #[default]
+    None,
+    /// Ascending by file name
+    Name,
+    /// Largest files first
+    Size,
+    /// Most recently modified first
+    Mtime,
+}
+
+impl SortOrder {
+    /// Compare two paths according to this order
+    ///
+    /// Metadata is read with `symlink_metadata`; entries whose metadata
+    /// cannot be read sort as if they were empty and infinitely old.
+    pub fn compare(&self, a: &Path, b: &Path) -> Ordering {
+        let meta_a = a.symlink_metadata().ok();
+        let meta_b = b.symlink_metadata().ok();
+        self.compare_with(a, meta_a.as_ref(), b, meta_b.as_ref())
+    }
+
+    fn compare_with(
+        &self,
+        a: &Path,
+        meta_a: Option<&Metadata>,
+        b: &Path,
+        meta_b: Option<&Metadata>,
+    ) -> Ordering {
+        let by_name = || a.file_name().cmp(&b.file_name());
+        match self {
+            SortOrder::None => Ordering::Equal,
+            SortOrder::Name => by_name(),
+            SortOrder::Size => {
+                let size = |m: Option<&Metadata>| m.map_or(0, |m| m.len());
+                size(meta_b).cmp(&size(meta_a)).then_with(by_name)
+            }
+            SortOrder::Mtime => {
+                let mtime = |m: Option<&Metadata>| m.and_then(|m| m.modified().ok());
+                mtime(meta_b).cmp(&mtime(meta_a)).then_with(by_name)
+            }
+        }
+    }
+}
+
+impl FromStr for SortOrder {
+    type Err = HashError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "none" => Ok(SortOrder::None),
+            "name" => Ok(SortOrder::Name),
+            "size" => Ok(SortOrder::Size),
+            "mtime" => Ok(SortOrder::Mtime),
+            _ => Err(HashError::InvalidInput(format!("unknown sort order: {}", s))),
+        }
+    }
+}
+
+/// Options controlling how a directory tree is walked
+#[derive(Debug, Clone, Default)]
+pub struct WalkOptions {
+    /// Follow symbolic links while walking
+    pub follow_links: bool,
+    /// Order in which directory entries are visited
+    pub sort: SortOrder,
+    /// Maximum depth below the root (1 = only the root's direct children)
+    pub max_depth: Option<usize>,
+    /// Skip files smaller than this many bytes
+    pub min_size: Option<u64>,
+    /// Skip files larger than this many bytes
+    pub max_size: Option<u64>,
+    /// Skip files not modified after this point in time
+    pub newer_than: Option<SystemTime>,
+    /// Do not descend into directories on other file systems (mount points)
+    pub one_file_system: bool,
+    /// Directories (and files) that are never entered or yielded; relative
+    /// paths are taken from the walk root
+    pub skip_paths: Vec<PathBuf>,
+    /// When set, only these files are yielded, and directories holding none
+    /// of them are not entered
+    pub only_paths: Option<BTreeSet<PathBuf>>,
+    /// When not empty, only files matching one of these are yielded
+    pub include: Vec<Glob>,
+    /// Files and directories matching one of these are never entered or
+    /// yielded
+    pub exclude: Vec<Glob>,
+}
+
+/// Pseudo file systems that should not be hashed when scanning from `/`
+pub const SYSTEM_SKIP_PATHS: &[&str] = &["/proc", "/sys", "/dev"];
+
+impl WalkOptions {
+    /// Create options with default settings
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Follow symbolic links while walking
+    pub fn with_follow_links(mut self, follow: bool) -> Self {
+        self.follow_links = follow;
+        self
+    }
+
+    /// Visit directory entries in the given order
+    pub fn with_sort(mut self, sort: SortOrder) -> Self {
+        self.sort = sort;
+        self
+    }
+
+    /// Limit how deep below the root the walk descends
+    pub fn with_max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Only yield files of at least `bytes` bytes
+    pub fn with_min_size(mut self, bytes: u64) -> Self {
+        self.min_size = Some(bytes);
+        self
+    }
+
+    /// Only yield files of at most `bytes` bytes
+    pub fn with_max_size(mut self, bytes: u64) -> Self {
+        self.max_size = Some(bytes);
+        self
+    }
+
+    /// Only yield files modified after `time`
+    pub fn with_newer_than(mut self, time: SystemTime) -> Self {
+        self.newer_than = Some(time);
+        self
+    }
+
+    /// Stay on the file system of the root directory
+    pub fn with_one_file_system(mut self, enabled: bool) -> Self {
+        self.one_file_system = enabled;
+        self
+    }
+
+    /// Never enter or yield `path`, which is resolved against the walk
+    /// root when relative
+    pub fn with_skip_path<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.skip_paths.push(path.into());
+        self
+    }
+
+    /// Skip the pseudo file systems listed in [`SYSTEM_SKIP_PATHS`]
+    pub fn with_system_skips(mut self) -> Self {
+        self.skip_paths
+            .extend(SYSTEM_SKIP_PATHS.iter().map(PathBuf::from));
+        self
+    }
+
+    /// Only yield the given files, such as the ones Git tracks
+    ///
+    /// Paths are compared as the walk produces them, so they must start
+    /// with the root exactly as it is passed to [`walk_files`].
+    pub fn with_only_paths<I, P>(mut self, paths: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        self.only_paths = Some(paths.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Only yield files whose path below the root matches `glob`; with
+    /// several, a file matching any of them is yielded
+    pub fn with_include(mut self, glob: Glob) -> Self {
+        self.include.push(glob);
+        self
+    }
+
+    /// Never enter or yield files and directories whose path below the
+    /// root matches `glob`
+    pub fn with_exclude(mut self, glob: Glob) -> Self {
+        self.exclude.push(glob);
+        self
+    }
+
+    /// Whether `path` is, or is a directory above, one of the only paths
+    fn is_selected(&self, path: &Path) -> bool {
+        match &self.only_paths {
+            // Paths below a directory sort right after it
+            Some(only) => only
+                .range::<Path, _>((Bound::Included(path), Bound::Unbounded))
+                .next()
+                .is_some_and(|first| first.starts_with(path)),
+            None => true,
+        }
+    }
+
+    /// Whether the globs keep the entry at `relative` (below the root) out
+    fn is_filtered(&self, relative: &str, is_dir: bool) -> bool {
+        if self.exclude.iter().any(|glob| glob.matches(relative)) {
+            return true;
+        }
+        // Directories are entered to look for matching files further down
+        !is_dir && !self.include.is_empty() && !self.include.iter().any(|g| g.matches(relative))
+    }
+
+    fn has_metadata_filters(&self) -> bool {
+        self.min_size.is_some() || self.max_size.is_some() || self.newer_than.is_some()
+    }
+
+    /// Whether a file with this metadata passes the size and time filters
+    pub fn accepts(&self, metadata: &Metadata) -> bool {
+        let len = metadata.len();
+        if self.min_size.is_some_and(|min| len < min) {
+            return false;
+        }
+        if self.max_size.is_some_and(|max| len > max) {
+            return false;
+        }
+        if let Some(threshold) = self.newer_than {
+            match metadata.modified() {
+                Ok(mtime) if mtime > threshold => {}
+                _ => return false,
+            }
+        }
+        true
+    }
+}
+
+/// Parse a size such as `512`, `10K`, `1.5M` or `4GiB` into bytes
+///
+/// Suffixes are binary multiples (K = 1024), matching common CLI tools.
+pub fn parse_size(s: &str) -> Result<u64> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| HashError::InvalidInput(format!("invalid size: {}", s)))?;
+    let multiplier: u64 = match unit.trim().to_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => 1 << 10,
+        "m" | "mb" | "mib" => 1 << 20,
+        "g" | "gb" | "gib" => 1 << 30,
+        "t" | "tb" | "tib" => 1 << 40,
+        _ => return Err(HashError::InvalidInput(format!("invalid size unit: {}", s))),
+    };
+    Ok((number * multiplier as f64) as u64)
+}
+
+/// Parse an age such as `30s`, `15m`, `12h`, `7d` or `2w` into a duration
+///
+/// An age too long to count in seconds is an error, not a wrapped value.
+pub fn parse_age(s: &str) -> Result<Duration> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| HashError::InvalidInput(format!("invalid age: {}", s)))?;
+    let seconds = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(HashError::InvalidInput(format!("invalid age unit: {}", s))),
+    };
+    number
+        .checked_mul(seconds)
+        .map(Duration::from_secs)
+        .ok_or_else(|| HashError::InvalidInput(format!("age out of range: {}", s)))
+}
+
+/// Identity of a file's content on disk (device and inode number)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FileId {
+    dev: u64,
+    ino: u64,
+}
+
+impl FileId {
+    /// Identity of a file that has more than one hard link
+    ///
+    /// Returns `None` for files with a single link (nothing to deduplicate)
+    /// and on platforms without stable inode numbers.
+    pub fn for_hard_link(metadata: &Metadata) -> Option<Self> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            if metadata.nlink() > 1 {
+                return Some(Self {
+                    dev: metadata.dev(),
+                    ino: metadata.ino(),
+                });
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = metadata;
+        None
+    }
+}
+
+/// Remembers the digest of every hard-linked file seen during a scan
+///
+/// Only files with more than one link are tracked, so memory use stays
+/// proportional to the number of hard links rather than to the tree size.
+#[derive(Debug, Default)]
+pub struct HardLinks {
+    seen: HashMap<FileId, (PathBuf, String)>,
+}
+
+impl HardLinks {
+    /// Create an empty tracker
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// First path and digest recorded for the same content, if any
+    pub fn lookup(&self, metadata: &Metadata) -> Option<(&Path, &str)> {
+        let id = FileId::for_hard_link(metadata)?;
+        self.seen
+            .get(&id)
+            .map(|(path, digest)| (path.as_path(), digest.as_str()))
+    }
+
+    /// Record the digest computed for a (possibly) hard-linked file
+    pub fn insert(&mut self, metadata: &Metadata, path: &Path, digest: &str) {
+        if let Some(id) = FileId::for_hard_link(metadata) {
+            self.seen
+                .entry(id)
+                .or_insert_with(|| (path.to_path_buf(), digest.to_string()));
+        }
+    }
+}
+
+/// A shell-style pattern matched against paths below a walked root
+///
+/// `*` matches any run of characters within one path component, `**` any
+/// run across components (`src/**/*.rs` also matches `src/main.rs`), `?`
+/// one character other than `/`, and `[abc]`, `[a-z]` or `[!a-z]` one
+/// character of a set. A backslash makes the next character literal.
+///
+/// A pattern without a `/` is matched against the last component, so
+/// `*.log` finds log files at any depth and `target` skips every directory
+/// of that name. A pattern with a `/` is matched against the whole path,
+/// with `/` separators, relative to the root; `/target` skips only the
+/// top-level one. A trailing `/` is ignored.
+///
+/// # Examples
+///
+/// ```
+/// use hashing::walk::Glob;
+///
+/// let logs = Glob::new("*.log").unwrap();
+/// assert!(logs.matches("app.log"));
+/// assert!(logs.matches("var/app.log"));
+///
+/// let sources = Glob::new("src/**/*.rs").unwrap();
+/// assert!(sources.matches("src/main.rs"));
+/// assert!(sources.matches("src/bin/hash.rs"));
+/// assert!(!sources.matches("tests/walk.rs"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Glob {
+    pattern: String,
+    tokens: Vec<GlobToken>,
+    whole_path: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum GlobToken {
+    Literal(char),
+    /// `?`
+    One,
+    /// `*`
+    Star,
+    /// `**` not followed by `/`
+    AnyPath,
+    /// `**/`: nothing, or any run of components each followed by `/`
+    AnyDirs,
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+impl Glob {
+    /// Parse `pattern`, failing on an unclosed `[` or a trailing backslash
+    pub fn new(pattern: &str) -> Result<Self> {
+        let body = pattern.strip_suffix('/').unwrap_or(pattern);
+        let trimmed = body.strip_prefix('/').unwrap_or(body);
+        let invalid = |reason: &str| {
+            HashError::InvalidInput(format!("invalid glob {:?}: {}", pattern, reason))
+        };
+        let mut tokens = Vec::new();
+        let mut chars = trimmed.chars().peekable();
+        while let Some(c) = chars.next() {
+            let token = match c {
+                '?' => GlobToken::One,
+                '*' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    if chars.peek() == Some(&'/') {
+                        chars.next();
+                        GlobToken::AnyDirs
+                    } else {
+                        GlobToken::AnyPath
+                    }
+                }
+                '*' => GlobToken::Star,
+                '\\' => GlobToken::Literal(chars.next().ok_or_else(|| invalid("trailing \\"))?),
+                '[' => {
+                    let negated = chars.next_if(|&c| c == '!' || c == '^').is_some();
+                    let mut ranges = Vec::new();
+                    loop {
+                        let start = match chars.next() {
+                            // A `]` right after the opening bracket is literal
+                            Some(']') if !ranges.is_empty() => break,
+                            Some(c) => c,
+                            None => return Err(invalid("unclosed [")),
+                        };
+                        let end = match chars.next_if_eq(&'-') {
+                            Some(_) => match chars.next() {
+                                Some(']') => {
+                                    ranges.push((start, start));
+                                    ranges.push(('-', '-'));
+                                    break;
+                                }
+                                Some(end) => end,
+                                None => return Err(invalid("unclosed [")),
+                            },
+                            None => start,
+                        };
+                        ranges.push((start, end));
+                    }
+                    GlobToken::Class { negated, ranges }
+                }
+                c => GlobToken::Literal(c),
+            };
+            tokens.push(token);
+        }
+        Ok(Self {
+            pattern: pattern.to_string(),
+            whole_path: body.contains('/'),
+            tokens,
+        })
+    }
+
+    /// The pattern as given
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Whether `path`, relative to the root and with `/` separators, matches
+    pub fn matches(&self, path: &str) -> bool {
+        let subject = if self.whole_path {
+            path
+        } else {
+            path.rsplit('/').next().unwrap_or(path)
+        };
+        let text: Vec<char> = subject.chars().collect();
+        glob_match(&self.tokens, &text)
+    }
+}
+
+impl FromStr for Glob {
+    type Err = HashError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::new(s)
+    }
+}
+
+fn glob_match(tokens: &[GlobToken], text: &[char]) -> bool {
+    let (token, rest) = match tokens.split_first() {
+        Some(split) => split,
+        None => return text.is_empty(),
+    };
+    match token {
+        GlobToken::Literal(c) => text.first() == Some(c) && glob_match(rest, &text[1..]),
+        GlobToken::One => text.first().is_some_and(|&c| c != '/') && glob_match(rest, &text[1..]),
+        GlobToken::Class { negated, ranges } => {
+            text.first().is_some_and(|&c| {
+                let listed = ranges.iter().any(|&(start, end)| start <= c && c <= end);
+                c != '/' && listed != *negated
+            }) && glob_match(rest, &text[1..])
+        }
+        GlobToken::Star => {
+            let component = text.iter().position(|&c| c == '/').unwrap_or(text.len());
+            (0..=component).any(|skip| glob_match(rest, &text[skip..]))
+        }
+        GlobToken::AnyPath => (0..=text.len()).any(|skip| glob_match(rest, &text[skip..])),
+        GlobToken::AnyDirs => (0..=text.len())
+            .any(|skip| (skip == 0 || text[skip - 1] == '/') && glob_match(rest, &text[skip..])),
+    }
+}
+
+/// `path` below `root`, with `/` separators
+pub fn relative_path(root: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    let parts: Vec<_> = relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect();
+    parts.join("/")
+}
+
+/// Lazy iterator over the regular files below a root directory
+pub struct WalkFiles {
+    inner: walkdir::IntoIter,
+    root: PathBuf,
+    options: WalkOptions,
+    /// The skip paths that lie inside the walk, relative to the root
+    skips: Vec<PathBuf>,
+}
+
+impl WalkFiles {
+    fn is_skipped(&self, entry: &walkdir::DirEntry) -> bool {
+        let relative = entry.path().strip_prefix(&self.root).unwrap_or(entry.path());
+        self.skips.iter().any(|skip| relative.starts_with(skip))
+    }
+
+    fn is_filtered(&self, entry: &walkdir::DirEntry) -> bool {
+        if self.options.include.is_empty() && self.options.exclude.is_empty() {
+            return false;
+        }
+        let relative = relative_path(&self.root, entry.path());
+        self.options
+            .is_filtered(&relative, entry.file_type().is_dir())
+    }
+}
+
+impl Iterator for WalkFiles {
+    type Item = Result<PathBuf>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.inner.next()? {
+                Ok(entry) => {
+                    if entry.depth() > 0
+                        && (self.is_skipped(&entry)
+                            || !self.options.is_selected(entry.path())
+                            || self.is_filtered(&entry))
+                    {
+                        if entry.file_type().is_dir() {
+                            self.inner.skip_current_dir();
+                        }
+                        continue;
+                    }
+                    if !entry.file_type().is_file() {
+                        continue;
+                    }
+                    if self.options.has_metadata_filters() {
+                        match entry.metadata() {
+                            Ok(metadata) if !self.options.accepts(&metadata) => continue,
+                            Ok(_) => {}
+                            Err(err) => return Some(Err(HashError::Io(err.into()))),
+                        }
+                    }
+                    return Some(Ok(entry.into_path()));
+                }
+                Err(err) => return Some(Err(HashError::Io(err.into()))),
+            }
+        }
+    }
+}
+
+/// Walk a directory tree, yielding every regular file below `root`
+///
+/// Errors for individual entries (e.g. permission denied) are yielded in
+/// place so that a single unreadable directory does not abort the scan.
+///
+/// # Examples
+///
+/// ```no_run
+/// use hashing::walk::{walk_files, WalkOptions};
+///
+/// for path in walk_files("some/dir", &WalkOptions::new()) {
+///     println!("{}", path.unwrap().display());
+/// }
+/// ```
+pub fn walk_files<P: AsRef<Path>>(root: P, options: &WalkOptions) -> WalkFiles {
+    let root = root.as_ref().to_path_buf();
+    let mut walker = walkdir::WalkDir::new(&root)
+        .follow_links(options.follow_links)
+        .same_file_system(options.one_file_system);
+    if let Some(depth) = options.max_depth {
+        walker = walker.max_depth(depth);
+    }
+    if options.sort != SortOrder::None {
+        let order = options.sort;
+        walker = walker.sort_by(move |a, b| {
+            let meta_a = a.metadata().ok();
+            let meta_b = b.metadata().ok();
+            order.compare_with(a.path(), meta_a.as_ref(), b.path(), meta_b.as_ref())
+        });
+    }
+    let skips = resolve_skips(&root, &options.skip_paths);
+    WalkFiles {
+        inner: walker.into_iter(),
+        root,
+        options: options.clone(),
+        skips,
+    }
+}
+
+/// Skip paths as paths below `root`, dropping those outside the walk
+///
+/// Relative skips are joined to the root. Both sides are canonicalized
+/// where the paths exist, so `/srv/data/cache`, `cache` and `./cache` all
+/// name the same directory of a walk from `/srv/data` or from `.`.
+fn resolve_skips(root: &Path, skips: &[PathBuf]) -> Vec<PathBuf> {
+    let canonical_root = root.canonicalize().ok();
+    skips
+        .iter()
+        .filter_map(|skip| {
+            let joined = root.join(skip);
+            let canonical = match (&canonical_root, joined.canonicalize()) {
+                (Some(root), Ok(path)) => path.strip_prefix(root).ok().map(Path::to_path_buf),
+                _ => None,
+            };
+            canonical.or_else(|| joined.strip_prefix(root).ok().map(Path::to_path_buf))
+        })
+        .collect()
+}
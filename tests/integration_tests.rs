@@ -104,6 +104,38 @@ fn test_large_file_hashing() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+#[test]
+fn test_hash_files_parallel() -> Result<(), Box<dyn std::error::Error>> {
+    use hashing::hash_files_parallel;
+
+    let dir = tempfile::tempdir()?;
+    let mut paths = Vec::new();
+    for i in 0..9 {
+        let path = dir.path().join(format!("{}.txt", i));
+        std::fs::write(&path, "x".repeat(i * 1000))?;
+        paths.push(path);
+    }
+    paths.insert(4, dir.path().join("missing.txt"));
+
+    for threads in [0, 1, 3] {
+        let results = hash_files_parallel(&paths, Algorithm::Sha256, threads);
+        assert_eq!(results.len(), paths.len());
+        for (path, result) in paths.iter().zip(&results) {
+            match result {
+                Ok(result) => {
+                    assert_eq!(result.digest, hash_file(path, Algorithm::Sha256)?);
+                    assert_eq!(result.input_path.as_deref(), Some(&*path.display().to_string()));
+                    assert_eq!(result.input_type, "file");
+                }
+                Err(_) => assert!(path.ends_with("missing.txt")),
+            }
+        }
+        assert!(results[4].is_err());
+    }
+    assert!(hash_files_parallel(&[], Algorithm::Sha256, 4).is_empty());
+    Ok(())
+}
+
 #[test]
 fn test_algorithm_parsing() {
     let test_cases = vec![
@@ -254,3 +286,4242 @@ fn test_keccak_family() {
         assert!(result.is_ok(), "Keccak algorithm {:?} failed", algorithm);
     }
 }
+
+#[test]
+fn test_bitcoin_composites() {
+    use hashing::fixed::{self, hash_bytes_fixed};
+
+    assert_eq!(
+        hash_string("hello", Algorithm::Sha256d).unwrap(),
+        "9595c9df90075148eb06860365df33584b75bff782a510c6cd4883a419833d50"
+    );
+    assert_eq!(
+        hash_string("", Algorithm::Hash160).unwrap(),
+        "b472a266d0bd89c13706a4132ccfb16f7c3b9fcb"
+    );
+    // HASH160 of the compressed secp256k1 generator point
+    let public_key =
+        hex::decode("0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798").unwrap();
+    assert_eq!(
+        hex::encode(hash_bytes_fixed::<fixed::Hash160>(&public_key)),
+        "751e76e8199196d454941c45d1b3a323f1433bd6"
+    );
+
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(b"hello").unwrap();
+    for algorithm in [Algorithm::Sha256d, Algorithm::Hash160] {
+        assert_eq!(
+            hash_file(file.path(), algorithm).unwrap(),
+            hash_string("hello", algorithm).unwrap()
+        );
+        assert!(hashing::hmac::hmac_bytes(b"key", b"hello", algorithm).is_err());
+    }
+    assert_eq!(Algorithm::from_str("double-sha256").unwrap(), Algorithm::Sha256d);
+    assert_eq!(Algorithm::from_str("HASH160").unwrap(), Algorithm::Hash160);
+}
+
+#[test]
+fn test_noncryptographic_checksums() {
+    use hashing::{hash_bytes, AlgorithmFamily, MultiHasher};
+
+    // Reference values from xxhsum and the CRC catalogue check strings
+    let cases = [
+        (Algorithm::Xxh32, "", "02cc5d05"),
+        (Algorithm::Xxh64, "", "ef46db3751d8e999"),
+        (Algorithm::Xxh3, "", "2d06800538d394c2"),
+        (Algorithm::Xxh128, "", "99aa06d3014798d86001c324468d497f"),
+        (Algorithm::Crc32, "123456789", "cbf43926"),
+        (Algorithm::Crc32c, "123456789", "e3069283"),
+    ];
+    for (algorithm, input, expected) in cases {
+        assert_eq!(hash_string(input, algorithm).unwrap(), expected, "{:?}", algorithm);
+        assert_eq!(expected.len(), algorithm.output_size() * 2);
+        assert_eq!(algorithm.family(), AlgorithmFamily::NonCryptographic);
+        assert!(!algorithm.is_cryptographic());
+        assert!(!hashing::hmac::supports(algorithm));
+        assert!(hashing::hmac::hmac_bytes(b"key", b"hello", algorithm).is_err());
+    }
+    assert!(Algorithm::Md5.is_cryptographic());
+
+    // Streaming across buffer boundaries matches the one-shot checksum
+    let data: Vec<u8> = (0..300_000u32).map(|i| (i % 251) as u8).collect();
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(&data).unwrap();
+    let algorithms = AlgorithmFamily::NonCryptographic.algorithms();
+    let multi = MultiHasher::new(&algorithms).hash_bytes(&data).unwrap();
+    for (algorithm, digest) in algorithms.iter().zip(multi) {
+        let expected = hash_bytes(&data, *algorithm).unwrap();
+        assert_eq!(hash_file(file.path(), *algorithm).unwrap(), expected);
+        assert_eq!(digest.to_hex(), expected);
+    }
+
+    assert_eq!(Algorithm::from_str("XXH3").unwrap(), Algorithm::Xxh3);
+    assert_eq!(Algorithm::from_str("xxh3-128").unwrap(), Algorithm::Xxh128);
+    assert_eq!(Algorithm::from_str("CRC-32C").unwrap(), Algorithm::Crc32c);
+    assert_eq!(
+        "non-cryptographic".parse::<AlgorithmFamily>().unwrap(),
+        AlgorithmFamily::NonCryptographic
+    );
+}
+
+#[test]
+fn test_variable_output_length() {
+    use hashing::{Digest, HashError, Hashing};
+
+    // FIPS 202 and RFC 7693 reference values
+    assert_eq!(
+        hash_string("", Algorithm::Shake128).unwrap(),
+        "7f9c2ba4e88f827d616045507605853ed73b8093f6efbc88eb1a6eacfa66ef26"
+    );
+    assert!(hash_string("", Algorithm::Shake256).unwrap().starts_with("46b9dd2b0ba88d13"));
+    assert_eq!(Algorithm::Shake256.output_size(), 64);
+    let blake2b_256 = Hashing::new()
+        .algorithm(Algorithm::Blake2b512)
+        .output_size(32)
+        .hash_string("abc")
+        .unwrap();
+    assert_eq!(
+        blake2b_256,
+        "bddd813c634239723171ef3fee98579b94964e3bb1cb3e427262c8c068d52319"
+    );
+    let mut full = Hashing::new().algorithm(Algorithm::Blake2b512).output_size(64);
+    assert_eq!(
+        full.hash_string("abc").unwrap(),
+        hash_string("abc", Algorithm::Blake2b512).unwrap()
+    );
+
+    // Extendable output: a longer digest extends the usual one, from
+    // memory or a file, with or without the self-check
+    let data: Vec<u8> = (0..300_000u32).map(|i| (i % 251) as u8).collect();
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(&data).unwrap();
+    for algorithm in [Algorithm::Blake3, Algorithm::Shake128, Algorithm::Shake256] {
+        let usual = hash_file(file.path(), algorithm).unwrap();
+        let long = Hashing::new().algorithm(algorithm).output_size(100).hash_file(file.path());
+        let long = long.unwrap();
+        assert_eq!(long.len(), 200);
+        assert!(long.starts_with(&usual), "{:?}", algorithm);
+        let checked = Hashing::new()
+            .algorithm(algorithm)
+            .output_size(100)
+            .paranoid(true)
+            .hash_bytes(&data)
+            .unwrap();
+        assert_eq!(checked, long);
+    }
+
+    assert_eq!(Algorithm::Sha256.output_sizes(), 32..=32);
+    assert_eq!(Algorithm::Blake2s256.output_sizes(), 1..=32);
+    assert!(Digest::new(Algorithm::Blake3, vec![0u8; 64]).is_ok());
+    assert!(Digest::new(Algorithm::Blake3, Vec::new()).is_err());
+    let err = Digest::new(Algorithm::Blake2b512, vec![0u8; 65]).unwrap_err();
+    assert!(err.to_string().contains("blake2b digests are 1 to 64 bytes long"));
+
+    let mut fixed = Hashing::new().algorithm(Algorithm::Sha256).output_size(16);
+    assert!(matches!(fixed.hash_string("abc"), Err(HashError::InvalidInput(_))));
+    let mut keyed = Hashing::new().algorithm(Algorithm::Blake2b512).output_size(32).key("k");
+    assert!(matches!(keyed.hash_string("abc"), Err(HashError::InvalidInput(_))));
+    assert!(!hashing::hmac::supports(Algorithm::Shake256));
+}
+
+#[test]
+fn test_keyed_blake() {
+    use blake2::digest::consts::U32;
+    use blake2::digest::Mac;
+    use blake2::{Blake2bMac, Blake2bMac512};
+    use hashing::{keyed, HashError, Hashing};
+
+    // First keyed answers of the BLAKE2 reference test vectors
+    let key: Vec<u8> = (0..64).collect();
+    let blake2b = Hashing::new()
+        .algorithm(Algorithm::Blake2b512)
+        .blake_key(key.clone())
+        .hash_bytes(b"")
+        .unwrap();
+    assert_eq!(
+        blake2b,
+        "10ebb67700b1868efb4417987acf4690ae9d972fb7a590c2f02871799aaa4786\
+         b5e996e8f0f4eb981fc214b005f42d2ff4233499391653df7aefcbc13fc51568"
+    );
+    let blake2s = Hashing::new()
+        .algorithm(Algorithm::Blake2s256)
+        .blake_key(&key[..32])
+        .hash_bytes(b"")
+        .unwrap();
+    assert_eq!(blake2s, "48a8997da407876b3d79c0d92325ad3b89cbb754d86ab71aee047ad345fd2c49");
+
+    // Streaming across block and buffer boundaries, at full and reduced length
+    let data: Vec<u8> = (0..300_000u32).map(|i| (i % 251) as u8).collect();
+    let mut mac = Blake2bMac512::new_from_slice(b"secret").unwrap();
+    mac.update(&data);
+    let expected = hex::encode(mac.finalize().into_bytes());
+    let mut hashing = Hashing::new().algorithm(Algorithm::Blake2b512).blake_key("secret");
+    assert_eq!(hashing.hash_bytes(&data).unwrap(), expected);
+    let mut mac = Blake2bMac::<U32>::new_from_slice(b"secret").unwrap();
+    mac.update(&data[..129]);
+    let short = Hashing::new()
+        .algorithm(Algorithm::Blake2b512)
+        .blake_key("secret")
+        .output_size(32)
+        .hash_bytes(&data[..129])
+        .unwrap();
+    assert_eq!(short, hex::encode(mac.finalize().into_bytes()));
+
+    let blake3_key = [0x42u8; 32];
+    let mut hashing = Hashing::new()
+        .algorithm(Algorithm::Blake3)
+        .blake_key(blake3_key)
+        .paranoid(true);
+    let expected = blake3::keyed_hash(&blake3_key, &data);
+    assert_eq!(hashing.hash_bytes(&data).unwrap(), expected.to_hex().as_str());
+    let long = Hashing::new()
+        .algorithm(Algorithm::Blake3)
+        .blake_key(blake3_key)
+        .output_size(64)
+        .hash_bytes(&data)
+        .unwrap();
+    assert!(long.starts_with(expected.to_hex().as_str()));
+
+    // The key replaces an HMAC key, and the keyed mode is not HMAC
+    let hmac = hashing::hmac::hmac_bytes(b"secret", b"abc", Algorithm::Blake2b512).unwrap();
+    let mut hashing = Hashing::new().algorithm(Algorithm::Blake2b512).key("x").blake_key("secret");
+    assert_ne!(hashing.hash_bytes(b"abc").unwrap(), hmac);
+
+    assert!(keyed::supports(Algorithm::Blake2s256));
+    assert!(!keyed::supports(Algorithm::Sha256));
+    assert_eq!(keyed::key_sizes(Algorithm::Blake2b512), Some(1..=64));
+    let mut sha256 = Hashing::new().algorithm(Algorithm::Sha256).blake_key("secret");
+    assert!(matches!(sha256.hash_bytes(b"abc"), Err(HashError::UnsupportedAlgorithm(_))));
+    let mut short_key = Hashing::new().algorithm(Algorithm::Blake3).blake_key("secret");
+    let err = short_key.hash_bytes(b"abc").unwrap_err();
+    assert!(err.to_string().contains("blake3 keys are exactly 32 bytes long, got 6"));
+    let mut long_key = Hashing::new().algorithm(Algorithm::Blake2s256).blake_key([0u8; 33]);
+    assert!(matches!(long_key.hash_bytes(b"abc"), Err(HashError::InvalidInput(_))));
+
+    // BLAKE3's key derivation mode, at any length
+    let context = "hashing integration tests 2026-10-16 derive_key";
+    let mut derive = Hashing::new().algorithm(Algorithm::Blake3).derive_key(context);
+    let derived = derive.hash_bytes_raw(&data).unwrap();
+    assert_eq!(derived.as_bytes(), blake3::derive_key(context, &data));
+    let mut long = Hashing::new()
+        .algorithm(Algorithm::Blake3)
+        .derive_key(context)
+        .output_size(48);
+    let long = long.hash_bytes_raw(&data).unwrap();
+    assert_eq!(&long.as_bytes()[..32], derived.as_bytes());
+    let mut blake2 = Hashing::new().algorithm(Algorithm::Blake2b512).derive_key(context);
+    assert!(matches!(blake2.hash_bytes(b"abc"), Err(HashError::UnsupportedAlgorithm(_))));
+}
+
+#[test]
+fn test_base58_encodings() {
+    use hashing::fixed::{self, hash_bytes_fixed};
+    use hashing::{Encoding, Hashing};
+
+    assert_eq!(Encoding::Base58.encode(b"").unwrap(), "");
+    assert_eq!(Encoding::Base58.encode(&[0, 0, 1]).unwrap(), "112");
+    assert_eq!(Encoding::Base58.encode(b"hello world").unwrap(), "StV1DL6CwTryKyV");
+
+    // Pay-to-pubkey-hash address of the compressed secp256k1 generator point
+    let public_key =
+        hex::decode("0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798").unwrap();
+    let mut payload = vec![0x00];
+    payload.extend_from_slice(&hash_bytes_fixed::<fixed::Hash160>(&public_key));
+    let address = Encoding::Base58Check.encode(&payload).unwrap();
+    assert_eq!(address, "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH");
+
+    let digest = Hashing::new()
+        .algorithm(Algorithm::Sha256)
+        .encoding(Encoding::Base58Check)
+        .hash_bytes(b"hello")
+        .unwrap();
+    let raw = hex::decode(hash_string("hello", Algorithm::Sha256).unwrap()).unwrap();
+    assert_eq!(digest, Encoding::Base58Check.encode(&raw).unwrap());
+    assert_ne!(digest, Encoding::Base58.encode(&raw).unwrap());
+
+    assert_eq!(Encoding::from_str("base58").unwrap(), Encoding::Base58);
+    assert_eq!(Encoding::from_str("base58check").unwrap(), Encoding::Base58Check);
+}
+
+#[test]
+fn test_base32_base64url_binary_encodings() {
+    use hashing::{hash_bytes_raw, hash_bytes_with_encoding, Encoding, HashError, Hashing};
+
+    // RFC 4648, section 10
+    let vectors = [("", ""), ("f", "MY======"), ("fo", "MZXQ===="), ("foobar", "MZXW6YTBOI======")];
+    for (data, base32) in vectors {
+        assert_eq!(Encoding::Base32.encode(data.as_bytes()).unwrap(), base32);
+    }
+    assert_eq!(Encoding::Base64Url.encode(&[0xfb, 0xff]).unwrap(), "-_8");
+    assert_eq!(Encoding::Base64.encode(&[0xfb, 0xff]).unwrap(), "+/8=");
+
+    let raw = hash_bytes_raw(b"hello", Algorithm::Sha256).unwrap();
+    for encoding in ["hex", "HEX", "base64", "base64url", "base32", "binary"] {
+        let encoding = Encoding::from_str(encoding).unwrap();
+        let encoded = hash_bytes_with_encoding(b"hello", Algorithm::Sha256, encoding).unwrap();
+        assert_eq!(encoded, encoding.encode_bytes(raw.as_bytes()));
+        if encoding.is_text() {
+            assert_eq!(String::from_utf8(encoded).unwrap(), raw.encode(encoding).unwrap());
+        } else {
+            assert_eq!(encoded, raw.as_bytes());
+            assert!(matches!(raw.encode(encoding), Err(HashError::InvalidInput(_))));
+        }
+    }
+
+    // The builder returns text, so it refuses binary before reading
+    let result = Hashing::new().encoding(Encoding::Binary).hash_string("hello");
+    assert!(matches!(result, Err(HashError::InvalidInput(_))));
+    let digest = Hashing::new().encoding(Encoding::Binary).hash_bytes_raw(b"hello").unwrap();
+    assert_eq!(digest, raw);
+}
+
+#[test]
+fn test_content_address_names() {
+    use hashing::naming::{ni_uri, onion_v3_address, parse_ni_uri, parse_onion_v3_address};
+
+    // RFC 6920, section 8.1
+    let digest = hex::decode(hash_string("Hello World!", Algorithm::Sha256).unwrap()).unwrap();
+    let uri = ni_uri(Algorithm::Sha256, &digest).unwrap();
+    assert_eq!(uri, "ni:///sha-256;f4OxZX_x_FO5LcGBSKHWXfwtSx-j1ncoSt3SABJtkGk");
+    assert_eq!(parse_ni_uri(&uri).unwrap(), (Algorithm::Sha256, digest.clone()));
+    let with_authority =
+        "ni://example.com/sha-256;f4OxZX_x_FO5LcGBSKHWXfwtSx-j1ncoSt3SABJtkGk?ct=text/plain";
+    assert_eq!(parse_ni_uri(with_authority).unwrap().1, digest);
+    assert!(ni_uri(Algorithm::Md5, &[0; 16]).is_err());
+    assert!(ni_uri(Algorithm::Sha256, &digest[..16]).is_err());
+    assert!(parse_ni_uri("ni:///sha-256;f4OxZX_x_FO5LcGBSKHWXfwtSx").is_err());
+
+    // The Tor Project's own onion service
+    let address = "2gzyxa5ihm7nsggfxnu52rck2vv4rvmdlkiu3zzui5du4xyclen53wid.onion";
+    let public_key = parse_onion_v3_address(address).unwrap();
+    assert_eq!(onion_v3_address(&public_key), address);
+    assert_eq!(parse_onion_v3_address(&address.to_uppercase()).unwrap(), public_key);
+    let tampered = address.replacen('2', "3", 1);
+    assert!(parse_onion_v3_address(&tampered).is_err());
+    assert!(parse_onion_v3_address("2gzyxa5ihm7nsggf.onion").is_err());
+}
+
+#[test]
+fn test_http_digest_fields() {
+    use hashing::http_digest::{
+        digest_header, digest_header_file, parse_digest_header, verify_digest_header,
+        verify_digest_header_file, DigestHeader,
+    };
+
+    // RFC 9530, appendix D
+    let body = br#"{"hello": "world"}"#;
+    let sha256 = "sha-256=:X48E9qOokqqrvdts8nOJRJN3OWDUoyWxBf7kbu9DBPE=:";
+    let value = digest_header(DigestHeader::ContentDigest, Algorithm::Sha256, body).unwrap();
+    assert_eq!(value, sha256);
+    assert_eq!(
+        digest_header(DigestHeader::Digest, Algorithm::Sha256, body).unwrap(),
+        "SHA-256=X48E9qOokqqrvdts8nOJRJN3OWDUoyWxBf7kbu9DBPE="
+    );
+    assert!(digest_header(DigestHeader::ReprDigest, Algorithm::Md5, body).is_err());
+    assert!(digest_header(DigestHeader::Digest, Algorithm::Md5, body).is_ok());
+
+    let sha512 = digest_header(DigestHeader::ReprDigest, Algorithm::Sha512, body).unwrap();
+    let both = format!("unixsum=30637, {}, {}", sha256, sha512);
+    let parsed = parse_digest_header(&both).unwrap();
+    assert_eq!(
+        parsed.iter().map(|(algorithm, _)| *algorithm).collect::<Vec<_>>(),
+        vec![Algorithm::Sha256, Algorithm::Sha512]
+    );
+    assert!(verify_digest_header(&both, body).unwrap());
+    let legacy = "SHA-256=X48E9qOokqqrvdts8nOJRJN3OWDUoyWxBf7kbu9DBPE=";
+    assert!(verify_digest_header(legacy, body).unwrap());
+    assert!(!verify_digest_header(sha256, b"tampered").unwrap());
+    assert!(verify_digest_header("unixsum=30637", body).is_err());
+    assert!(parse_digest_header("sha-256=:AAAA:").is_err());
+
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(body).unwrap();
+    file.flush().unwrap();
+    assert_eq!(
+        digest_header_file(DigestHeader::ContentDigest, Algorithm::Sha256, file.path()).unwrap(),
+        sha256
+    );
+    assert!(verify_digest_header_file(&sha512, file.path()).unwrap());
+}
+
+#[test]
+fn test_aws_payload_hash() {
+    use hashing::aws::{
+        payload_hash, payload_hash_file, payload_hash_reader, StreamingPayload,
+        EMPTY_PAYLOAD_SHA256,
+    };
+
+    assert_eq!(payload_hash(b""), EMPTY_PAYLOAD_SHA256);
+    assert_eq!(payload_hash_reader(std::io::empty()).unwrap(), EMPTY_PAYLOAD_SHA256);
+    assert_eq!(payload_hash(b"hello"), hash_string("hello", Algorithm::Sha256).unwrap());
+
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(b"hello").unwrap();
+    file.flush().unwrap();
+    assert_eq!(payload_hash_file(file.path()).unwrap(), payload_hash(b"hello"));
+
+    assert_eq!(StreamingPayload::Signed.header_value(), "STREAMING-AWS4-HMAC-SHA256-PAYLOAD");
+    assert_eq!(
+        StreamingPayload::UnsignedWithTrailer.header_value(),
+        "STREAMING-UNSIGNED-PAYLOAD-TRAILER"
+    );
+}
+
+#[test]
+fn test_cloud_object_urls() {
+    use hashing::cloud::{is_object_url, stored_checksums, ObjectUrl, Provider};
+
+    let url: ObjectUrl = "az://myaccount/backups/2024/db.dump".parse().unwrap();
+    assert_eq!(url.provider, Provider::Azure);
+    assert_eq!(url.account.as_deref(), Some("myaccount"));
+    assert_eq!(url.bucket, "backups");
+    assert_eq!(url.key, "2024/db.dump");
+    assert_eq!(url.to_string(), "az://myaccount/backups/2024/db.dump");
+    assert_eq!(
+        url.https_url(),
+        "https://myaccount.blob.core.windows.net/backups/2024/db.dump"
+    );
+    let url: ObjectUrl = "gs://data/reports/q1 final.csv".parse().unwrap();
+    assert_eq!(url.https_url(), "https://storage.googleapis.com/data/reports/q1%20final.csv");
+    for invalid in ["s3://bucket", "s3:///key", "az://account/container", "ftp://host/file"] {
+        assert!(invalid.parse::<ObjectUrl>().is_err(), "{}", invalid);
+    }
+    assert!(is_object_url("s3://bucket/key"));
+    assert!(!is_object_url("./s3/bucket"));
+
+    // S3 reports hello's MD5 as its ETag and the additional SHA-256 checksum in base64
+    let headers = [
+        ("etag", "\"5d41402abc4b2a76b9719d911017c592\""),
+        ("x-amz-checksum-sha256", "LPJNul+wow4m6DsqxbninhsWHlwfp0JecwQzYpOLmCQ="),
+    ];
+    let header = |name: &str| -> Vec<String> {
+        headers.iter().filter(|(n, _)| *n == name).map(|(_, v)| v.to_string()).collect()
+    };
+    let stored = stored_checksums(Provider::S3, header);
+    assert_eq!(stored.len(), 2);
+    assert_eq!(stored[0].algorithm, Algorithm::Md5);
+    assert_eq!(hex::encode(&stored[0].digest), hash_string("hello", Algorithm::Md5).unwrap());
+    assert_eq!(stored[1].header, "x-amz-checksum-sha256");
+    assert_eq!(hex::encode(&stored[1].digest), hash_string("hello", Algorithm::Sha256).unwrap());
+
+    // Multipart ETags and KMS-encrypted objects carry no MD5 of the contents
+    let multipart = |name: &str| -> Vec<String> {
+        match name {
+            "etag" => vec!["\"d41d8cd98f00b204e9800998ecf8427e-3\"".to_string()],
+            _ => Vec::new(),
+        }
+    };
+    assert!(stored_checksums(Provider::S3, multipart).is_empty());
+    let kms = |name: &str| -> Vec<String> {
+        match name {
+            "etag" => vec!["\"5d41402abc4b2a76b9719d911017c592\"".to_string()],
+            "x-amz-server-side-encryption" => vec!["aws:kms".to_string()],
+            _ => Vec::new(),
+        }
+    };
+    assert!(stored_checksums(Provider::S3, kms).is_empty());
+    let azure = |name: &str| -> Vec<String> {
+        match name {
+            "content-md5" => vec!["XUFAKrxLKna5cZ2REBfFkg==".to_string()],
+            _ => Vec::new(),
+        }
+    };
+    assert_eq!(stored_checksums(Provider::Azure, azure)[0].header, "Content-MD5");
+}
+
+#[test]
+fn test_ssh_remote_paths() {
+    use hashing::ssh::{is_remote_url, RemotePath};
+
+    let remote: RemotePath = "ssh://backup@[::1]:2222/srv/backups/db%20final.dump".parse().unwrap();
+    assert_eq!(remote.user.as_deref(), Some("backup"));
+    assert_eq!(remote.host, "::1");
+    assert_eq!(remote.port, Some(2222));
+    assert_eq!(remote.path, "/srv/backups/db final.dump");
+    assert_eq!(remote.to_string(), "sftp://backup@[::1]:2222/srv/backups/db final.dump");
+
+    // /~/ is the home directory, which is where a relative path starts
+    let remote: RemotePath = "sftp://nas/~/notes/it's.txt".parse().unwrap();
+    assert_eq!((remote.user.as_deref(), remote.port), (None, None));
+    assert_eq!(remote.path, "notes/it's.txt");
+    assert_eq!(remote.command(), r"cat -- 'notes/it'\''s.txt'");
+    assert_eq!(remote.to_string(), "sftp://nas/~/notes/it's.txt");
+
+    for invalid in ["sftp://host", "sftp://host/", "sftp://:22/x", "sftp://h:bad/x", "http://h/x"] {
+        assert!(invalid.parse::<RemotePath>().is_err(), "{}", invalid);
+    }
+    assert!(is_remote_url("sftp://nas/file"));
+    assert!(is_remote_url("ssh://nas/file"));
+    assert!(!is_remote_url("./sftp/nas"));
+}
+
+#[test]
+fn test_auth_protocol_hashes() {
+    use hashing::auth::{
+        assertion_signed_data, client_data_hash, end_point_algorithm, matches_rp_id, rp_id_hash,
+        tls_server_end_point,
+    };
+    use base64::Engine;
+
+    // Self-signed certificates: P-256 with ECDSA-SHA384 and ECDSA-SHA1, and Ed25519
+    let ecdsa_sha384 = concat!(
+        "-----BEGIN CERTIFICATE-----\n",
+        "MIIBhjCCASugAwIBAgIUU9wPdWymJsKHSrzN1z48LZzTab0wCgYIKoZIzj0EAwMw\n",
+        "FzEVMBMGA1UEAwwMaGFzaGluZy10ZXN0MCAXDTI2MTAxNjA4MzkzOFoYDzIxMjYw\n",
+        "OTIyMDgzOTM4WjAXMRUwEwYDVQQDDAxoYXNoaW5nLXRlc3QwWTATBgcqhkjOPQIB\n",
+        "BggqhkjOPQMBBwNCAARbJaBuunV7kO/kBXqRhtHa+TwLtuUqT5U7Gm2QuHRrwoD9\n",
+        "mhi4FPXOI0/QM/VXuQirEzTgEgDlcn777/ZpTi1Lo1MwUTAdBgNVHQ4EFgQU+IqX\n",
+        "X6Q3FYHqXwScOvUCYZz+y7MwHwYDVR0jBBgwFoAU+IqXX6Q3FYHqXwScOvUCYZz+\n",
+        "y7MwDwYDVR0TAQH/BAUwAwEB/zAKBggqhkjOPQQDAwNJADBGAiEAjrnqY8c/XBA8\n",
+        "VQCVf9KPNIGpIwrxxbyJSl7kInT35V0CIQCg+Brn1tlFC+mNkpA6OgvtHMjEO1oV\n",
+        "pSB2wmAc7ol+YA==\n",
+        "-----END CERTIFICATE-----\n",
+    );
+    let ecdsa_sha1 = concat!(
+        "-----BEGIN CERTIFICATE-----\n",
+        "MIIBhDCCASqgAwIBAgIUMGCbH89YoAm52V8D6P77htMZBRAwCQYHKoZIzj0EATAX\n",
+        "MRUwEwYDVQQDDAxoYXNoaW5nLXRlc3QwIBcNMjYxMDE2MDgzOTQxWhgPMjEyNjA5\n",
+        "MjIwODM5NDFaMBcxFTATBgNVBAMMDGhhc2hpbmctdGVzdDBZMBMGByqGSM49AgEG\n",
+        "CCqGSM49AwEHA0IABAqeI18cDXFI8y20Mut8rpHu25TpzkORe/VcLK/xX/XDTKLr\n",
+        "XcJ0a7mTLKYRdI+2tmF+UkE5QvUEsZxVkkQHsDijUzBRMB0GA1UdDgQWBBQIQlc6\n",
+        "HaAAUOBIXuo6iAXNSQSsYjAfBgNVHSMEGDAWgBQIQlc6HaAAUOBIXuo6iAXNSQSs\n",
+        "YjAPBgNVHRMBAf8EBTADAQH/MAkGByqGSM49BAEDSQAwRgIhAMnznyXxhQ+ET8Ty\n",
+        "oOrbXD6ZOjq440uMRK1ar9LeCFftAiEAlpXD04tMqOigwvgiMV62gh32y0X2ucC7\n",
+        "2BwhnIwYyPo=\n",
+        "-----END CERTIFICATE-----\n",
+    );
+    let ed25519 = concat!(
+        "-----BEGIN CERTIFICATE-----\n",
+        "MIIBRDCB96ADAgECAhQzV22rPI7XJfMilFfSW0wz0zHfjjAFBgMrZXAwFzEVMBMG\n",
+        "A1UEAwwMaGFzaGluZy10ZXN0MCAXDTI2MTAxNjA4Mzk0MVoYDzIxMjYwOTIyMDgz\n",
+        "OTQxWjAXMRUwEwYDVQQDDAxoYXNoaW5nLXRlc3QwKjAFBgMrZXADIQBFcPsoBWli\n",
+        "vCV5Tauvsoe551m8a1unXQSVU17KXp0sSqNTMFEwHQYDVR0OBBYEFFBaXO+AKvHy\n",
+        "pwWIVCkcIDJt4yMfMB8GA1UdIwQYMBaAFFBaXO+AKvHypwWIVCkcIDJt4yMfMA8G\n",
+        "A1UdEwEB/wQFMAMBAf8wBQYDK2VwA0EAZ2+kUKNpztUrDpYLA0Ew3+9rYoYTCWnp\n",
+        "Z0ef81ojtNTYX1L7KgP/p5qKqhCla8dNIkPE28PGWVtbK79GFIvuAQ==\n",
+        "-----END CERTIFICATE-----\n",
+    );
+
+    assert_eq!(end_point_algorithm(ecdsa_sha384.as_bytes()).unwrap(), Algorithm::Sha384);
+    assert_eq!(
+        hex::encode(tls_server_end_point(ecdsa_sha384.as_bytes()).unwrap()),
+        "0e48181d7e6e7a9273ab7548baeda0432a9ea3273b8a1d41c3e48cfeacddd87454e87dfdff43806528c1ef93c851f03f"
+    );
+    // SHA-1 signatures bind with SHA-256, whether given as PEM or DER
+    assert_eq!(end_point_algorithm(ecdsa_sha1.as_bytes()).unwrap(), Algorithm::Sha256);
+    let der = base64::engine::general_purpose::STANDARD
+        .decode(ecdsa_sha1.lines().filter(|l| !l.starts_with("-----")).collect::<String>())
+        .unwrap();
+    assert_eq!(
+        hex::encode(tls_server_end_point(&der).unwrap()),
+        "384c611bb61dc914740f222052d564974d698edb5b86ab3eff3f11b702c4c89e"
+    );
+    assert!(tls_server_end_point(ed25519.as_bytes()).is_err());
+    assert!(tls_server_end_point(b"not a certificate").is_err());
+
+    let client_data_json = br#"{"type":"webauthn.get","challenge":"AAEC"}"#;
+    assert_eq!(
+        client_data_hash(client_data_json).to_vec(),
+        hex::decode(hashing::hash_bytes(client_data_json, Algorithm::Sha256).unwrap()).unwrap()
+    );
+    let mut authenticator_data = rp_id_hash("example.com").to_vec();
+    authenticator_data.extend_from_slice(&[0x01, 0, 0, 0, 7]);
+    assert!(matches_rp_id(&authenticator_data, "example.com"));
+    assert!(!matches_rp_id(&authenticator_data, "example.org"));
+    let signed = assertion_signed_data(&authenticator_data, client_data_json);
+    assert_eq!(signed.len(), 37 + 32);
+    assert_eq!(&signed[..37], &authenticator_data[..]);
+}
+
+#[test]
+fn test_dkim_body_hash() {
+    use hashing::dkim::{body_hash, canonicalize_body, message_body, BodyCanonicalization};
+
+    // RFC 6376, appendix A
+    let message = b"From: Joe SixPack <joe@football.example.com>\r\n\
+Subject: Is dinner ready?\r\n\
+\r\n\
+Hi.\r\n\
+\r\n\
+We lost the game. Are you hungry yet?\r\n\
+\r\n\
+Joe.\r\n";
+    let body = message_body(message);
+    assert!(body.starts_with(b"Hi."));
+    assert_eq!(
+        body_hash(body, BodyCanonicalization::Simple, Algorithm::Sha256, None).unwrap(),
+        "2jUSOH9NhtVGCQWNr9BrIAPreKQjO6Sn7XIkfJVOzv8="
+    );
+
+    // Empty bodies differ between the two canonicalizations
+    let relaxed = BodyCanonicalization::Relaxed;
+    assert_eq!(canonicalize_body(b"", BodyCanonicalization::Simple), b"\r\n");
+    assert_eq!(canonicalize_body(b"\r\n \r\n", relaxed), b"");
+    assert_eq!(
+        body_hash(b"", relaxed, Algorithm::Sha256, None).unwrap(),
+        "47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU="
+    );
+
+    assert_eq!(canonicalize_body(b"a \t b \t\nc", relaxed), b"a b\r\nc\r\n");
+    let simple = canonicalize_body(b"a \t b\n\n\n", BodyCanonicalization::Simple);
+    assert_eq!(simple, b"a \t b\r\n");
+    assert_eq!(
+        body_hash(b"hello world\r\n", relaxed, Algorithm::Sha1, Some(5)).unwrap(),
+        body_hash(b"hello", BodyCanonicalization::Simple, Algorithm::Sha1, Some(5)).unwrap()
+    );
+    assert!(body_hash(b"hi", relaxed, Algorithm::Sha256, Some(100)).is_err());
+    assert!(body_hash(b"hi", relaxed, Algorithm::Md5, None).is_err());
+    let parsed: BodyCanonicalization = "relaxed/simple".parse().unwrap();
+    assert_eq!(parsed, BodyCanonicalization::Simple);
+}
+
+#[cfg(feature = "executable")]
+#[test]
+fn test_executable_digests() {
+    use hashing::executable::{
+        authenticode_digest, detect_format, elf_build_id, is_authenticode_signed, ExecutableFormat,
+    };
+
+    // A PE32+ skeleton: DOS header, PE signature, COFF and optional headers
+    let mut pe = vec![0u8; 0x200];
+    pe[..2].copy_from_slice(b"MZ");
+    pe[0x3c] = 0x40;
+    pe[0x40..0x44].copy_from_slice(b"PE\0\0");
+    let optional = 0x40 + 24;
+    pe[optional..optional + 2].copy_from_slice(&0x20bu16.to_le_bytes());
+    pe[optional + 108..optional + 112].copy_from_slice(&16u32.to_le_bytes());
+    for (i, byte) in pe[0x180..].iter_mut().enumerate() {
+        *byte = i as u8;
+    }
+    let checksum = optional + 64;
+    let certificate_entry = optional + 112 + 32;
+    let mut covered = pe[..checksum].to_vec();
+    covered.extend_from_slice(&pe[checksum + 4..certificate_entry]);
+    covered.extend_from_slice(&pe[certificate_entry + 8..]);
+    let unsigned = authenticode_digest(&pe, Algorithm::Sha256).unwrap();
+    assert_eq!(hex::encode(&unsigned), hashing::hash_bytes(&covered, Algorithm::Sha256).unwrap());
+    assert_eq!(detect_format(&pe), Some(ExecutableFormat::Pe));
+    assert!(!is_authenticode_signed(&pe).unwrap());
+
+    // Signing sets the checksum, the certificate entry and appends the table
+    let mut signed = pe.clone();
+    signed[checksum..checksum + 4].copy_from_slice(&0x1234u32.to_le_bytes());
+    signed[certificate_entry..certificate_entry + 4].copy_from_slice(&0x200u32.to_le_bytes());
+    signed[certificate_entry + 4..certificate_entry + 8].copy_from_slice(&16u32.to_le_bytes());
+    signed.extend_from_slice(&[0xee; 16]);
+    assert!(is_authenticode_signed(&signed).unwrap());
+    assert_eq!(authenticode_digest(&signed, Algorithm::Sha256).unwrap(), unsigned);
+    signed[0x1ff] ^= 1;
+    assert_ne!(authenticode_digest(&signed, Algorithm::Sha256).unwrap(), unsigned);
+    assert!(authenticode_digest(b"MZ", Algorithm::Sha256).is_err());
+
+    // An ELF64 skeleton with one PT_NOTE segment holding the build-id
+    let build_id: Vec<u8> = (1..=20).collect();
+    let mut elf = vec![0u8; 120];
+    elf[..4].copy_from_slice(b"\x7fELF");
+    elf[4] = 2;
+    elf[5] = 1;
+    elf[0x20..0x28].copy_from_slice(&64u64.to_le_bytes());
+    elf[0x36..0x38].copy_from_slice(&56u16.to_le_bytes());
+    elf[0x38..0x3a].copy_from_slice(&1u16.to_le_bytes());
+    elf[64..68].copy_from_slice(&4u32.to_le_bytes());
+    elf[72..80].copy_from_slice(&120u64.to_le_bytes());
+    elf[96..104].copy_from_slice(&36u64.to_le_bytes());
+    elf.extend_from_slice(&4u32.to_le_bytes());
+    elf.extend_from_slice(&20u32.to_le_bytes());
+    elf.extend_from_slice(&3u32.to_le_bytes());
+    elf.extend_from_slice(b"GNU\0");
+    elf.extend_from_slice(&build_id);
+    assert_eq!(detect_format(&elf), Some(ExecutableFormat::Elf));
+    assert_eq!(elf_build_id(&elf).unwrap(), Some(build_id));
+
+    // Hostile offsets are reported instead of wrapping around
+    let mut hostile = elf.clone();
+    hostile[0x20..0x28].copy_from_slice(&u64::MAX.to_le_bytes());
+    hostile[0x38..0x3a].copy_from_slice(&2u16.to_le_bytes());
+    assert!(matches!(elf_build_id(&hostile), Err(hashing::HashError::InvalidInput(_))));
+    let mut hostile = elf.clone();
+    hostile[72..80].copy_from_slice(&(u64::MAX - 4).to_le_bytes());
+    assert_eq!(elf_build_id(&hostile).unwrap(), None);
+    assert!(elf_build_id(&elf[..100]).is_err());
+
+    elf[64] = 1; // PT_LOAD: no notes left
+    assert_eq!(elf_build_id(&elf).unwrap(), None);
+    assert!(elf_build_id(b"\x7fELF").is_err());
+}
+
+#[cfg(feature = "archive")]
+#[test]
+fn test_jar_manifest_digests() {
+    use base64::Engine;
+    use hashing::fixed::{hash_bytes_fixed, Sha256};
+    use hashing::jar::{verify_jar, EntryStatus};
+    use std::io::Cursor;
+
+    let b64 = |data: &[u8]| {
+        base64::engine::general_purpose::STANDARD.encode(hash_bytes_fixed::<Sha256>(data))
+    };
+    let build = |class: &[u8], extra: bool| {
+        let main = "Manifest-Version: 1.0\r\nCreated-By: test\r\n\r\n".to_string();
+        let section = format!(
+            "Name: com/example/App.class\r\nSHA-256-Digest: {}\r\n\r\n",
+            b64(b"class bytes")
+        );
+        let gone = format!("Name: gone.txt\r\nSHA-256-Digest: {}\r\n\r\n", b64(b"x"));
+        let manifest = format!("{}{}{}", main, section, gone);
+        let signature = format!(
+            "Signature-Version: 1.0\r\nSHA-256-Digest-Manifest: {}\r\n\r\n\
+             Name: com/example/App.class\r\nSHA-256-Digest: {}\r\n\r\n",
+            b64(manifest.as_bytes()),
+            b64(section.as_bytes())
+        );
+        // Appending a section after signing leaves the whole-manifest
+        // digest stale, but the signed sections still match
+        let manifest = if extra {
+            format!("{}Name: late.txt\r\nSHA-256-Digest: {}\r\n\r\n", manifest, b64(b""))
+        } else {
+            manifest
+        };
+
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options = zip::write::FileOptions::default();
+        for (name, data) in [
+            ("META-INF/MANIFEST.MF", manifest.as_bytes()),
+            ("META-INF/CERT.SF", signature.as_bytes()),
+            ("META-INF/CERT.RSA", b"not checked".as_slice()),
+            ("com/example/App.class", class),
+            ("extra.txt", b"unsigned".as_slice()),
+            ("late.txt", b"".as_slice()),
+        ] {
+            writer.start_file(name, options).unwrap();
+            writer.write_all(data).unwrap();
+        }
+        writer.finish().unwrap()
+    };
+    let status = |report: &hashing::jar::JarVerification, name: &str| {
+        report
+            .entries
+            .iter()
+            .find(|check| check.name == name)
+            .map(|check| check.status)
+    };
+
+    let report = verify_jar(build(b"class bytes", false)).unwrap();
+    assert_eq!(status(&report, "com/example/App.class"), Some(EntryStatus::Verified));
+    assert_eq!(status(&report, "gone.txt"), Some(EntryStatus::Missing));
+    assert_eq!(status(&report, "extra.txt"), Some(EntryStatus::Unlisted));
+    assert_eq!(status(&report, "META-INF/CERT.RSA"), None);
+    assert_eq!(report.signature_files[0].name, "META-INF/CERT.SF");
+    assert_eq!(report.signature_files[0].status, EntryStatus::Verified);
+    assert!(!report.is_ok());
+
+    let report = verify_jar(build(b"tampered", true)).unwrap();
+    assert_eq!(status(&report, "com/example/App.class"), Some(EntryStatus::Mismatch));
+    assert_eq!(status(&report, "late.txt"), Some(EntryStatus::Verified));
+    assert_eq!(report.signature_files[0].status, EntryStatus::Verified);
+
+    assert!(verify_jar(Cursor::new(b"not a zip".to_vec())).is_err());
+}
+
+#[cfg(feature = "archive")]
+#[test]
+fn test_package_digests() {
+    use hashing::jar::EntryStatus;
+    use hashing::package::{detect_format, verify_package, PackageFormat};
+
+    let tar = |files: &[(&str, &[u8])]| {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (path, data) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, path, *data).unwrap();
+        }
+        builder.into_inner().unwrap()
+    };
+    let ar = |members: &[(&str, &[u8])]| {
+        let mut out = b"!<arch>\n".to_vec();
+        for (name, data) in members {
+            let header = format!(
+                "{:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`\n",
+                name,
+                0,
+                0,
+                0,
+                644,
+                data.len()
+            );
+            out.extend_from_slice(header.as_bytes());
+            out.extend_from_slice(data);
+            if data.len() % 2 == 1 {
+                out.push(b'\n');
+            }
+        }
+        out
+    };
+    let md5 = |data: &[u8]| hashing::hash_bytes(data, Algorithm::Md5).unwrap();
+
+    let md5sums = format!(
+        "{}  usr/bin/tool\n{}  usr/share/doc/gone\n",
+        md5(b"binary"),
+        md5(b"gone")
+    );
+    let control = tar(&[("./control", b"Package: tool\n"), ("./md5sums", md5sums.as_bytes())]);
+    let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    gz.write_all(&tar(&[("./usr/bin/tool", b"binary"), ("./etc/tool.conf", b"x")]))
+        .unwrap();
+    let data = gz.finish().unwrap();
+    let deb = ar(&[
+        ("debian-binary", b"2.0\n"),
+        ("control.tar", &control),
+        ("data.tar.gz", &data),
+    ]);
+
+    assert_eq!(detect_format(&deb), Some(PackageFormat::Deb));
+    let report = verify_package(&deb).unwrap();
+    let statuses: Vec<_> = report
+        .checks
+        .iter()
+        .map(|check| (check.name.as_str(), check.status))
+        .collect();
+    assert_eq!(
+        statuses,
+        [
+            ("usr/bin/tool", EntryStatus::Verified),
+            ("etc/tool.conf", EntryStatus::Unlisted),
+            ("usr/share/doc/gone", EntryStatus::Missing),
+        ]
+    );
+    assert!(!report.is_ok());
+
+    // An RPM with a signature header (header SHA-256, header+payload MD5)
+    // and a main header recording the payload SHA-256
+    let rpm_header = |entries: &[(u32, u32, Vec<u8>)]| {
+        let mut index = Vec::new();
+        let mut store = Vec::new();
+        for (tag, kind, value) in entries {
+            // BIN entries count bytes, the others count values
+            let count = if *kind == 7 { value.len() as u32 } else { 1 };
+            for field in [*tag, *kind, store.len() as u32, count] {
+                index.extend_from_slice(&field.to_be_bytes());
+            }
+            store.extend_from_slice(value);
+        }
+        let mut header = vec![0x8e, 0xad, 0xe8, 0x01, 0, 0, 0, 0];
+        header.extend_from_slice(&(entries.len() as u32).to_be_bytes());
+        header.extend_from_slice(&(store.len() as u32).to_be_bytes());
+        header.extend_from_slice(&index);
+        header.extend_from_slice(&store);
+        header
+    };
+    let payload = b"compressed cpio archive".to_vec();
+    let payload_sha256 = hashing::hash_bytes(&payload, Algorithm::Sha256).unwrap();
+    let main = rpm_header(&[
+        (5093, 4, 8u32.to_be_bytes().to_vec()),
+        (5092, 8, format!("{}\0", payload_sha256).into_bytes()),
+    ]);
+    let mut signed = main.clone();
+    signed.extend_from_slice(&payload);
+    let header_sha256 = hashing::hash_bytes(&main, Algorithm::Sha256).unwrap();
+    let md5_digest = hex::decode(md5(&signed)).unwrap();
+    let signature = rpm_header(&[
+        (273, 6, format!("{}\0", header_sha256).into_bytes()),
+        (1004, 7, md5_digest),
+    ]);
+
+    let mut rpm = vec![0xed, 0xab, 0xee, 0xdb];
+    rpm.resize(96, 0);
+    rpm.extend_from_slice(&signature);
+    rpm.resize((rpm.len() + 7) / 8 * 8, 0);
+    rpm.extend_from_slice(&signed);
+
+    assert_eq!(detect_format(&rpm), Some(PackageFormat::Rpm));
+    let report = verify_package(&rpm).unwrap();
+    let names: Vec<_> = report.checks.iter().map(|check| check.name.as_str()).collect();
+    assert_eq!(names, ["header SHA-256", "header+payload MD5", "payload SHA-256"]);
+    assert!(report.is_ok());
+
+    *rpm.last_mut().unwrap() ^= 1;
+    let report = verify_package(&rpm).unwrap();
+    let statuses: Vec<_> = report.checks.iter().map(|check| check.status).collect();
+    assert_eq!(
+        statuses,
+        [EntryStatus::Verified, EntryStatus::Mismatch, EntryStatus::Mismatch]
+    );
+    assert!(verify_package(b"plain text").is_err());
+}
+
+#[test]
+fn test_sbom_checksums() -> Result<(), Box<dyn std::error::Error>> {
+    use hashing::sbom::{fragment, scan_tree, verify_sbom, SbomFormat, SbomStatus};
+    use hashing::walk::WalkOptions;
+
+    let dir = tempfile::tempdir()?;
+    std::fs::create_dir(dir.path().join("lib"))?;
+    std::fs::write(dir.path().join("lib/b.so"), b"b")?;
+    std::fs::write(dir.path().join("a.txt"), b"hello world")?;
+
+    let algorithms = [Algorithm::Sha1, Algorithm::Sha256];
+    let files = scan_tree(dir.path(), &WalkOptions::new(), &algorithms)?;
+    let paths: Vec<_> = files.iter().map(|file| file.path.as_str()).collect();
+    assert_eq!(paths, ["a.txt", "lib/b.so"]);
+
+    let spdx = fragment(SbomFormat::Spdx, &files)?;
+    assert_eq!(spdx["files"][0]["fileName"], "./a.txt");
+    assert_eq!(spdx["files"][0]["checksums"][0]["algorithm"], "SHA1");
+    assert_eq!(
+        spdx["files"][0]["checksums"][0]["checksumValue"],
+        "2aae6c35c94fcfb415dbe95f408b9ce91ee846ed"
+    );
+    let cyclonedx = fragment(SbomFormat::CycloneDx, &files)?;
+    assert_eq!(cyclonedx["components"][1]["name"], "lib/b.so");
+    assert_eq!(cyclonedx["components"][1]["hashes"][1]["alg"], "SHA-256");
+    let sha224 = scan_tree(dir.path(), &WalkOptions::new(), &[Algorithm::Sha224])?;
+    assert!(fragment(SbomFormat::CycloneDx, &sha224).is_err());
+
+    let report = verify_sbom(&spdx.to_string(), dir.path())?;
+    assert_eq!(report.format, SbomFormat::Spdx);
+    assert!(report.is_ok());
+
+    // A full document from another tool, with nested components and an
+    // algorithm this crate does not implement
+    let document = r#"{
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "components": [{
+            "type": "library",
+            "name": "app",
+            "components": [
+                {"type": "file", "name": "a.txt", "hashes": [
+                    {"alg": "SHA-256", "content": "0000"}]},
+                {"type": "file", "name": "lib/b.so", "hashes": [
+                    {"alg": "BLAKE2b-256", "content": "00"}]},
+                {"type": "file", "name": "gone.bin", "hashes": []}
+            ]
+        }]
+    }"#;
+    let report = verify_sbom(document, dir.path())?;
+    let statuses: Vec<_> = report
+        .checks
+        .iter()
+        .map(|check| (check.path.as_str(), check.status))
+        .collect();
+    assert_eq!(
+        statuses,
+        [
+            ("a.txt", SbomStatus::Mismatch),
+            ("lib/b.so", SbomStatus::Unchecked),
+            ("gone.bin", SbomStatus::Missing),
+        ]
+    );
+    assert!(!report.is_ok());
+    assert!(verify_sbom("{}", dir.path()).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_attestation_subjects() -> Result<(), Box<dyn std::error::Error>> {
+    use hashing::attest::{subjects, Subject};
+
+    let dir = tempfile::tempdir()?;
+    std::fs::create_dir(dir.path().join("bin"))?;
+    std::fs::write(dir.path().join("bin/tool"), b"hello world")?;
+    std::fs::write(dir.path().join("README"), b"")?;
+
+    let algorithms = [Algorithm::Sha256, Algorithm::Sha512];
+    let subject = subjects(&[dir.path()], &algorithms)?;
+    assert_eq!(subject.len(), 2);
+    assert!(subject[0].name.ends_with("README"));
+    assert!(subject[1].name.ends_with("bin/tool"));
+    assert_eq!(
+        subject[1].digest["sha256"],
+        "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9"
+    );
+    assert_eq!(subject[1].digest.len(), 2);
+
+    let json = serde_json::to_value(&subject)?;
+    assert_eq!(json[0]["digest"]["sha256"], hashing::aws::EMPTY_PAYLOAD_SHA256);
+    let parsed: Vec<Subject> = serde_json::from_value(json)?;
+    assert_eq!(parsed, subject);
+
+    assert!(subjects(&[dir.path()], &[Algorithm::Blake3]).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_sigstore_blob_digests() {
+    use hashing::sigstore::{blob_digest, parse_blob_digest};
+
+    const HELLO_WORLD_SHA256: &str =
+        "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9";
+
+    let digest = hex::decode(HELLO_WORLD_SHA256).unwrap();
+    let value = blob_digest(Algorithm::Sha256, &digest).unwrap();
+    assert_eq!(value, format!("sha256:{}", HELLO_WORLD_SHA256));
+    assert_eq!(parse_blob_digest(&value).unwrap(), (Algorithm::Sha256, digest.clone()));
+    assert!(blob_digest(Algorithm::Md5, &digest[..16]).is_err());
+    assert!(blob_digest(Algorithm::Sha512, &digest).is_err());
+    assert!(parse_blob_digest("sha256:abcd").is_err());
+    assert!(parse_blob_digest(HELLO_WORLD_SHA256).is_err());
+}
+
+#[cfg(feature = "sigstore")]
+#[test]
+fn test_sigstore_blob_signatures() {
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+    use hashing::sigstore::{verify_blob, BlobSignature, BlobVerification};
+
+    const HELLO_WORLD_SHA256: &str =
+        "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9";
+
+    // Signatures made with `openssl dgst -sha256 -sign`, which is what
+    // `cosign sign-blob` writes: base64 of a DER ECDSA signature
+    const CERTIFICATE: &str = "\
+    -----BEGIN CERTIFICATE-----\n\
+    MIIBkDCCATWgAwIBAgIUBnx/5X2jbCz9gU7vyyIJEO9DxdYwCgYIKoZIzj0EAwIw\n\
+    HTEbMBkGA1UEAwwSc2lnbmVyQGV4YW1wbGUuY29tMB4XDTI2MTAxNjA5MDA1NVoX\n\
+    DTM2MTAxMzA5MDA1NVowHTEbMBkGA1UEAwwSc2lnbmVyQGV4YW1wbGUuY29tMFkw\n\
+    EwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAEvkzAxLgcaboW/3AYnbhECN8+GSlSUZCk\n\
+    8suwNvtBCB2jHywG1xM4T8i0hBvSKC/gX1p+aoS4kXdWHUZINvyEy6NTMFEwHQYD\n\
+    VR0OBBYEFIwfg8Wb4ytgtz+39QJce5eObDO1MB8GA1UdIwQYMBaAFIwfg8Wb4ytg\n\
+    tz+39QJce5eObDO1MA8GA1UdEwEB/wQFMAMBAf8wCgYIKoZIzj0EAwIDSQAwRgIh\n\
+    AJXNkAouql0TCl43aXAsLHG4DzHgRw2tBTnrYKq7LFcEAiEA5OZdZATbN2a4qD65\n\
+    XUxw59mKIwPhV/4hReHR5etU5EI=\n\
+    -----END CERTIFICATE-----\n\
+    ";
+    const PUBLIC_KEY_P384: &str = "\
+    -----BEGIN PUBLIC KEY-----\n\
+    MHYwEAYHKoZIzj0CAQYFK4EEACIDYgAEfuwgzOAM+50dHdcU8TebvFkHdBhpEoZT\n\
+    ZBKDlBsXQsXAec+A7ZM2BwN5YNUKEuGtN9TuVSL3RCmOPT8akSJcvl20UKVUaA/n\n\
+    ErXm9ZEeIeNH+JhDCio+cvLCyOVbkMQ9\n\
+    -----END PUBLIC KEY-----\n\
+    ";
+    const SIGNATURE: &str = concat!(
+        "MEUCIH/7978Ww+k6owxX++RPMWf8nGB0OagwMr+6Fu8E91ElAiEA0IWwHE+8+8nr",
+        "X7ujca1RDq1JTo/rxugHev86pD1Vij4="
+    );
+    const SIGNATURE_P384: &str = concat!(
+        "MGQCMAbOfpdoQrfnSTOIdQTxbe70rh6IGZcexL55D8BhRP3lHlSB9QBK1WSALBRe",
+        "5EvVlgIwCPflpBKo+zNmmraqU8C0uZgPr/o+UBe0JBhAZewI84JH/MmEvL9jFpBUNw8+Po4I"
+    );
+
+    let signature = BlobSignature::from_detached(SIGNATURE.as_bytes(), CERTIFICATE.as_bytes())
+        .unwrap();
+    let verify = |blob: &[u8], signature: &BlobSignature| verify_blob(blob, signature).unwrap();
+    assert_eq!(verify(b"hello world", &signature), BlobVerification::Verified);
+    assert_eq!(verify(b"hello world!", &signature), BlobVerification::BadSignature);
+
+    let signature =
+        BlobSignature::from_detached(SIGNATURE_P384.as_bytes(), PUBLIC_KEY_P384.as_bytes())
+            .unwrap();
+    assert_eq!(verify(b"hello world", &signature), BlobVerification::Verified);
+
+    // A `cosign sign-blob --bundle` bundle: base64 PEM certificate and a
+    // Rekor hashedrekord entry recording the blob's digest
+    let entry = serde_json::json!({
+        "kind": "hashedrekord",
+        "spec": {"data": {"hash": {"algorithm": "sha256", "value": HELLO_WORLD_SHA256}}}
+    });
+    let bundle = format!(
+        r#"{{"base64Signature":"{}","cert":"{}","rekorBundle":{{"Payload":{{"body":"{}"}}}}}}"#,
+        SIGNATURE,
+        STANDARD.encode(CERTIFICATE),
+        STANDARD.encode(entry.to_string())
+    );
+    let signature = BlobSignature::from_bundle(&bundle).unwrap();
+    assert_eq!(signature.digest.as_ref().unwrap().0, Algorithm::Sha256);
+    assert_eq!(verify(b"hello world", &signature), BlobVerification::Verified);
+    assert_eq!(verify(b"hello", &signature), BlobVerification::DigestMismatch);
+
+    assert!(BlobSignature::from_bundle("{}").is_err());
+    let signature = BlobSignature::from_detached(SIGNATURE.as_bytes(), b"not a key").unwrap();
+    assert!(verify_blob(&b"hello world"[..], &signature).is_err());
+}
+
+#[cfg(feature = "sigstore")]
+#[test]
+fn test_trust_store_signed_manifests() -> Result<(), Box<dyn std::error::Error>> {
+    use hashing::trust::TrustStore;
+
+    // `openssl dgst -sha256 -sign` over MANIFEST, verified by SIGNER
+    const MANIFEST: &[u8] =
+        b"b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9  hello.txt\n";
+    const SIGNATURE: &str = concat!(
+        "MEYCIQC5xjnExBU3mbhopNBhZKJZ5kcS90t+EV7VX3Pr2swlwAIhANlauCb+xhCy",
+        "FwA3BrPOr1kgAJbF0/RGdJ9AkCTaqGf+"
+    );
+    const SIGNER: &str = "\
+    -----BEGIN PUBLIC KEY-----\n\
+    MFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAEX+PS0IzEoMWn5OFH4tisdfzNHMRN\n\
+    dEmxDrJPRawM8m8Ja8vSG4oRuOE5MOP7D3wo4IPpfvP5EEozIr2g5kx2xQ==\n\
+    -----END PUBLIC KEY-----\n\
+    ";
+    const OTHER: &str = "\
+    -----BEGIN PUBLIC KEY-----\n\
+    MFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAEzIhAJGba0svvJ4c22c8vIlPLBC2C\n\
+    bF0BJUF0nArEQbPlXCtrBUIIfnp43lt5RrglMs7fzEg6sTbdiHI9peswvg==\n\
+    -----END PUBLIC KEY-----\n\
+    ";
+
+    let mut trust = TrustStore::new();
+    assert_eq!(trust.verify(MANIFEST, SIGNATURE.as_bytes())?, None);
+    trust.add("ops", OTHER);
+    assert_eq!(trust.verify(MANIFEST, SIGNATURE.as_bytes())?, None);
+
+    let dir = tempfile::tempdir()?;
+    std::fs::write(dir.path().join("usb.pub"), SIGNER)?;
+    trust.add_file("usb", dir.path().join("usb.pub"))?;
+    let signer = trust.verify(MANIFEST, SIGNATURE.as_bytes())?.expect("signed by usb");
+    assert_eq!(signer.name, "usb");
+
+    let mut modified = MANIFEST.to_vec();
+    modified[0] = b'c';
+    assert_eq!(trust.verify(&modified, SIGNATURE.as_bytes())?, None);
+
+    // A trusted key that is not a key is a configuration error
+    trust.add("broken", "not a key");
+    assert!(trust.verify(&modified, SIGNATURE.as_bytes()).is_err());
+    assert!(trust.add_file("missing", dir.path().join("missing.pub")).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_nix_hash_formats() {
+    use hashing::nix::{decode_nix32, encode_nix32, HashFormat, NixHash};
+
+    let hex = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+    let nix32 = "0mdqa9w1p6cmli6976v4wi0sw9r4p5prkj7lzfd1877wk11c9c73";
+    let sri = "sha256-47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=";
+
+    let digest = hex::decode(hex).unwrap();
+    assert_eq!(encode_nix32(&digest), nix32);
+    assert_eq!(decode_nix32(nix32).unwrap(), digest);
+
+    for text in [sri, &format!("sha256:{}", nix32), &format!("sha256:{}", hex)] {
+        let hash = NixHash::parse(text, None).unwrap();
+        assert_eq!(hash.algorithm, Algorithm::Sha256);
+        assert_eq!(hash.format(HashFormat::Hex), hex);
+        assert_eq!(hash.format(HashFormat::Nix32), nix32);
+        assert_eq!(hash.format(HashFormat::Sri), sri);
+    }
+    let bare = NixHash::parse(&sri[7..], Some(Algorithm::Sha256)).unwrap();
+    assert_eq!(bare.to_string(), sri);
+
+    let md5 = NixHash::parse("d41d8cd98f00b204e9800998ecf8427e", Some(Algorithm::Md5)).unwrap();
+    assert_eq!(md5.format(HashFormat::Sri), "md5-1B2M2Y8AsgTpgAmY7PhCfg==");
+    assert_eq!(
+        NixHash::parse(&md5.format(HashFormat::Nix32), Some(Algorithm::Md5)).unwrap(),
+        md5
+    );
+
+    // Bare digests need an algorithm, and a named one must agree
+    assert!(NixHash::parse(hex, None).is_err());
+    assert!(NixHash::parse(sri, Some(Algorithm::Sha512)).is_err());
+    assert!(NixHash::parse("sha256:abc", None).is_err());
+    assert!(NixHash::parse("blake3:abc", None).is_err());
+    // 'e' is not in the alphabet, and the top bits past 256 must be zero
+    assert!(decode_nix32(&nix32.replace('0', "e")).is_err());
+    assert!(decode_nix32(&nix32.replacen('0', "z", 1)).is_err());
+    assert_eq!("base16".parse::<HashFormat>().unwrap(), HashFormat::Hex);
+}
+
+#[test]
+fn test_convert_digest_formats() {
+    use hashing::convert::{convert_digest, format_digest, parse_digest, DigestFormat};
+
+    let hex = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+    let digest = hex::decode(hex).unwrap();
+    let all = [
+        DigestFormat::Hex,
+        DigestFormat::Base64,
+        DigestFormat::Base64Url,
+        DigestFormat::Nix32,
+        DigestFormat::Sri,
+        DigestFormat::Prefixed,
+        DigestFormat::Multihash,
+        DigestFormat::Ni,
+    ];
+    for format in all {
+        let text = format_digest(Algorithm::Sha256, &digest, format).unwrap();
+        let parsed = parse_digest(&text, Some(Algorithm::Sha256)).unwrap();
+        assert_eq!(parsed, (Algorithm::Sha256, digest.clone()), "{}", format);
+    }
+    assert_eq!(
+        format_digest(Algorithm::Sha256, &digest, DigestFormat::Multihash).unwrap(),
+        format!("1220{}", hex)
+    );
+
+    // Multihash in base58 and multibase form, no algorithm needed
+    let base58 = bs58::encode(hex::decode(format!("1220{}", hex)).unwrap()).into_string();
+    assert!(base58.starts_with("Qm"));
+    let prefixed = format!("sha256:{}", hex);
+    assert_eq!(convert_digest(&base58, None, DigestFormat::Prefixed).unwrap(), prefixed);
+    let multibase = format!("z{}", base58);
+    assert_eq!(convert_digest(&multibase, None, DigestFormat::Prefixed).unwrap(), prefixed);
+
+    let blake2b = hashing::hash_string("abc", Algorithm::Blake2b512).unwrap();
+    let multihash = convert_digest(
+        &format!("blake2b:{}", blake2b),
+        None,
+        DigestFormat::Multihash,
+    )
+    .unwrap();
+    assert!(multihash.starts_with("c0e40240"));
+    assert_eq!(
+        convert_digest(&multihash, None, DigestFormat::Hex).unwrap(),
+        blake2b
+    );
+
+    // Lengths are checked against the declared algorithm
+    assert!(parse_digest(hex, None).is_err());
+    assert!(parse_digest(hex, Some(Algorithm::Sha1)).is_err());
+    assert!(parse_digest("sha256:abcd", None).is_err());
+    assert!(parse_digest(&format!("sha1:{}", hex), None).is_err());
+    assert!(parse_digest(&format!("1214{}", &hex[..40]), None).is_err());
+    assert!(format_digest(Algorithm::Sha1, &digest, DigestFormat::Hex).is_err());
+    assert!(format_digest(Algorithm::Blake3, &digest, DigestFormat::Sri).is_err());
+    assert_eq!("MULTIHASH".parse::<DigestFormat>().unwrap(), DigestFormat::Multihash);
+}
+
+#[test]
+fn test_hashing_tee() {
+    use hashing::Hashing;
+
+    let data: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+    for algorithm in [Algorithm::Sha256, Algorithm::Blake3, Algorithm::Sha1] {
+        let mut copy = Vec::new();
+        let digest = Hashing::new()
+            .algorithm(algorithm)
+            .buffer_size(1000)
+            .hash_tee(&data[..], &mut copy)
+            .unwrap();
+        assert_eq!(copy, data);
+        assert_eq!(digest, hashing::hash_bytes(&data, algorithm).unwrap(), "{:?}", algorithm);
+    }
+
+    // Keyed hashing copies the input just the same
+    let mut copy = Vec::new();
+    let mac = Hashing::new()
+        .key(b"Jefe".to_vec())
+        .hash_tee(&b"payload"[..], &mut copy)
+        .unwrap();
+    assert_eq!(copy, b"payload");
+    assert_eq!(mac, hashing::hmac::hmac_bytes(b"Jefe", b"payload", Algorithm::Sha256).unwrap());
+
+    // A failing writer fails the hash
+    struct Full;
+    impl Write for Full {
+        fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::ErrorKind::WriteZero.into())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+    assert!(Hashing::new().hash_tee(&data[..], Full).is_err());
+}
+
+#[test]
+fn test_stamp_files() {
+    use hashing::stamp::{depfile, stamp_contents, update_stamp};
+    use std::fs;
+    use std::path::Path;
+
+    let dir = tempfile::tempdir().unwrap();
+    let inputs = dir.path().join("in");
+    fs::create_dir(&inputs).unwrap();
+    fs::write(inputs.join("b.txt"), "b").unwrap();
+    fs::write(inputs.join("a.txt"), "a").unwrap();
+
+    let contents = stamp_contents(&[&inputs], Algorithm::Sha256).unwrap();
+    let lines: Vec<&str> = contents.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(
+        lines[0],
+        format!(
+            "{}  {}",
+            hash_string("a", Algorithm::Sha256).unwrap(),
+            inputs.join("a.txt").display()
+        )
+    );
+
+    let stamp = dir.path().join("inputs.stamp");
+    assert!(update_stamp(&stamp, &contents).unwrap());
+    assert!(!update_stamp(&stamp, &contents).unwrap());
+    fs::write(inputs.join("a.txt"), "changed").unwrap();
+    let changed = stamp_contents(&[&inputs], Algorithm::Sha256).unwrap();
+    assert!(update_stamp(&stamp, &changed).unwrap());
+    assert_eq!(fs::read_to_string(&stamp).unwrap(), changed);
+
+    assert_eq!(
+        depfile(Path::new("out.stamp"), &["src dir/a#1", "cost$.txt"]),
+        "out.stamp: \\\n  src\\ dir/a\\#1 \\\n  cost$$.txt\n"
+    );
+}
+
+#[test]
+fn test_cache_key() {
+    use hashing::cachekey::{CacheKey, EntryKind, MissingInput};
+
+    let dir = tempfile::tempdir().unwrap();
+    let lock = dir.path().join("Cargo.lock");
+    let toolchain = dir.path().join("rust-toolchain.toml");
+    std::fs::write(&lock, "lock v1").unwrap();
+    std::fs::write(&toolchain, "[toolchain]").unwrap();
+    std::env::set_var("HASHING_TEST_CACHEKEY", "-C opt-level=3");
+
+    let key = |files: &[&std::path::Path]| {
+        let mut key = CacheKey::new()
+            .with_env("HASHING_TEST_CACHEKEY")
+            .with_env("HASHING_TEST_CACHEKEY_UNSET")
+            .with_literal("v2");
+        for file in files {
+            key = key.with_file(*file);
+        }
+        key
+    };
+
+    // Declaration order does not matter, contents do
+    let first = key(&[&lock, &toolchain]).compute().unwrap();
+    assert_eq!(first, key(&[&toolchain, &lock, &lock]).compute().unwrap());
+    assert_eq!(first.len(), 64);
+    std::fs::write(&lock, "lock v2").unwrap();
+    let second = key(&[&lock, &toolchain]).compute().unwrap();
+    assert_ne!(first, second);
+    assert_eq!(second, key(&[dir.path()]).compute().unwrap());
+
+    let entries = key(&[&lock]).entries().unwrap();
+    let kinds: Vec<EntryKind> = entries.iter().map(|entry| entry.kind).collect();
+    assert_eq!(kinds, [EntryKind::File, EntryKind::Env, EntryKind::Env, EntryKind::Literal]);
+    assert_eq!(entries[2].name, "HASHING_TEST_CACHEKEY_UNSET");
+    assert_eq!(entries[2].digest, None);
+    assert_eq!(
+        entries[3].digest.as_deref(),
+        Some(hash_string("v2", Algorithm::Sha256).unwrap().as_str())
+    );
+
+    // Missing-input policies
+    let missing = dir.path().join("missing.toml");
+    assert!(key(&[&lock, &missing]).compute().is_err());
+    let skipped = key(&[&lock, &missing]).with_missing(MissingInput::Skip).compute().unwrap();
+    assert_eq!(skipped, key(&[&lock]).compute().unwrap());
+    let marked = key(&[&lock, &missing]).with_missing(MissingInput::Mark).compute().unwrap();
+    assert_ne!(marked, skipped);
+
+    // Unset and empty variables differ
+    std::env::set_var("HASHING_TEST_CACHEKEY", "");
+    let empty = CacheKey::new().with_env("HASHING_TEST_CACHEKEY").compute().unwrap();
+    std::env::remove_var("HASHING_TEST_CACHEKEY");
+    let unset = CacheKey::new().with_env("HASHING_TEST_CACHEKEY").compute().unwrap();
+    assert_ne!(empty, unset);
+}
+
+#[test]
+fn test_walk_only_paths() {
+    use hashing::walk::{walk_files, SortOrder, WalkOptions};
+
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    std::fs::create_dir_all(root.join("src/nested")).unwrap();
+    std::fs::create_dir_all(root.join("target")).unwrap();
+    for name in ["src/a.rs", "src/nested/b.rs", "src-extra.rs", "target/out.bin"] {
+        std::fs::write(root.join(name), name).unwrap();
+    }
+
+    let options = WalkOptions::new()
+        .with_sort(SortOrder::Name)
+        .with_only_paths([root.join("src/nested/b.rs"), root.join("src-extra.rs")]);
+    let files: Vec<_> = walk_files(root, &options).map(|path| path.unwrap()).collect();
+    assert_eq!(files, [root.join("src/nested/b.rs"), root.join("src-extra.rs")]);
+
+    let none = WalkOptions::new().with_only_paths(Vec::<std::path::PathBuf>::new());
+    assert_eq!(walk_files(root, &none).count(), 0);
+}
+
+#[cfg(feature = "git")]
+#[test]
+fn test_git_file_selection() {
+    use hashing::git::{changed_files, tracked_files};
+    use std::process::Command;
+
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    let git = |args: &[&str]| {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(root)
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {:?}", args);
+    };
+
+    git(&["init", "-q"]);
+    std::fs::write(root.join(".gitignore"), "target/\n").unwrap();
+    std::fs::write(root.join("a.txt"), "a").unwrap();
+    std::fs::write(root.join("b.txt"), "b").unwrap();
+    std::fs::create_dir(root.join("target")).unwrap();
+    std::fs::write(root.join("target/build.o"), "obj").unwrap();
+
+    // Before the first commit everything not ignored counts as changed
+    let changed = changed_files(root, None).unwrap();
+    assert_eq!(changed, [root.join(".gitignore"), root.join("a.txt"), root.join("b.txt")]);
+
+    git(&["add", "."]);
+    git(&["commit", "-q", "-m", "initial"]);
+    let mut tracked = tracked_files(root).unwrap();
+    tracked.sort();
+    assert_eq!(tracked, [root.join(".gitignore"), root.join("a.txt"), root.join("b.txt")]);
+    assert!(changed_files(root, None).unwrap().is_empty());
+
+    std::fs::write(root.join("b.txt"), "changed").unwrap();
+    std::fs::write(root.join("new.txt"), "new").unwrap();
+    std::fs::remove_file(root.join("a.txt")).unwrap();
+    let changed = changed_files(root, None).unwrap();
+    assert_eq!(changed, [root.join("b.txt"), root.join("new.txt")]);
+    assert!(changed_files(root, Some("no-such-rev")).is_err());
+}
+
+#[test]
+fn test_pinned_digests() {
+    use hashing::pins::{PinStatus, Pins};
+
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    std::fs::create_dir_all(root.join("vendor")).unwrap();
+    std::fs::write(root.join("vendor/protoc"), "protoc 25.1").unwrap();
+    std::fs::write(root.join("Cargo.lock"), "lock v1").unwrap();
+
+    let lock = hash_string("lock v1", Algorithm::Sha256).unwrap();
+    let text = format!(
+        "[pins]\n\"Cargo.lock\" = \"{}\"\n\"gone.bin\" = \"sha256:{}\"\n",
+        lock, lock
+    );
+    let mut pins = Pins::from_toml(&text).unwrap();
+    pins.pin(root, "vendor/protoc", Algorithm::Blake3).unwrap();
+    assert!(pins.pins["vendor/protoc"].starts_with("blake3:"));
+
+    // Round trip through the file, then check in path order
+    let path = root.join(".hashpins.toml");
+    pins.save(&path).unwrap();
+    let pins = Pins::load(&path).unwrap();
+    let statuses = |pins: &Pins| -> Vec<PinStatus> {
+        pins.check(root).unwrap().iter().map(|check| check.status).collect()
+    };
+    assert_eq!(
+        statuses(&pins),
+        [PinStatus::Verified, PinStatus::Missing, PinStatus::Verified]
+    );
+
+    std::fs::write(root.join("vendor/protoc"), "protoc 25.2").unwrap();
+    assert_eq!(statuses(&pins)[2], PinStatus::Mismatch);
+
+    // Unparseable pins are errors, not mismatches
+    let broken = Pins::from_toml("[pins]\n\"Cargo.lock\" = \"sha256:nothex\"\n").unwrap();
+    assert!(broken.check(root).is_err());
+}
+
+#[test]
+fn test_release_files() {
+    use hashing::release::{scan_dist, sha256sums, sri_map, write_release_files};
+
+    let dir = tempfile::tempdir().unwrap();
+    let dist = dir.path();
+    std::fs::create_dir_all(dist.join("web")).unwrap();
+    std::fs::write(dist.join("app.tar.gz"), "archive").unwrap();
+    std::fs::write(dist.join("web/app.js"), "x()").unwrap();
+
+    let artifacts = scan_dist(dist).unwrap();
+    let names: Vec<&str> = artifacts.iter().map(|artifact| artifact.name.as_str()).collect();
+    assert_eq!(names, ["app.tar.gz", "web/app.js"]);
+    assert_eq!(artifacts[1].size, 3);
+    assert_eq!(artifacts[1].sha512, hash_string("x()", Algorithm::Sha512).unwrap());
+    assert_eq!(
+        sha256sums(&artifacts).lines().next().unwrap(),
+        format!("{}  app.tar.gz", hash_string("archive", Algorithm::Sha256).unwrap())
+    );
+
+    // Only web assets get an SRI entry
+    let sri = sri_map(&artifacts);
+    assert_eq!(sri.keys().collect::<Vec<_>>(), ["web/app.js"]);
+    assert!(sri["web/app.js"].starts_with("sha384-"));
+
+    // Written files are not picked up as artifacts on the next run
+    assert_eq!(write_release_files(dist, &artifacts).unwrap().len(), 4);
+    assert_eq!(scan_dist(dist).unwrap(), artifacts);
+}
+
+#[test]
+fn test_fill_package_manifest() {
+    use hashing::pkgmanifest::{fill_manifest, FieldStatus, ManifestFormat};
+
+    let dir = tempfile::tempdir().unwrap();
+    let arm = dir.path().join("tool-arm64.tar.gz");
+    let intel = dir.path().join("tool-x86_64.tar.gz");
+    std::fs::write(&arm, "arm").unwrap();
+    std::fs::write(&intel, "intel").unwrap();
+    let arm_sha = hash_string("arm", Algorithm::Sha256).unwrap();
+    let intel_sha = hash_string("intel", Algorithm::Sha256).unwrap();
+
+    let formula = "\
+  on_arm do
+    url \"https://example.com/tool-arm64.tar.gz\"
+    sha256 \"TODO\"
+  end
+  on_intel do
+    url \"https://example.com/tool-x86_64.tar.gz\"
+    sha256 \"\"
+  end
+  bottle do
+    sha256 cellar: :any, arm64_sonoma: \"abc\"
+  end
+";
+    let fill = fill_manifest(formula, ManifestFormat::Homebrew, &[&arm, &intel], false).unwrap();
+    assert!(fill.is_ok() && fill.changed());
+    assert_eq!(fill.fields.len(), 2);
+    assert!(fill.text.contains(&format!("    sha256 \"{}\"\n  end\n  on_intel", arm_sha)));
+    assert!(fill.text.contains(&format!("sha256 \"{}\"", intel_sha)));
+    assert!(fill.text.ends_with("arm64_sonoma: \"abc\"\n  end\n"));
+
+    // Filled values verify; a changed artifact is a mismatch unless forced
+    let again = fill_manifest(&fill.text, ManifestFormat::Homebrew, &[&arm], false).unwrap();
+    assert!(!again.changed());
+    let statuses: Vec<FieldStatus> = again.fields.iter().map(|field| field.status).collect();
+    assert_eq!(statuses, [FieldStatus::Verified, FieldStatus::Unmatched]);
+    std::fs::write(&arm, "arm v2").unwrap();
+    let stale = fill_manifest(&fill.text, ManifestFormat::Homebrew, &[&arm], false).unwrap();
+    assert!(!stale.is_ok());
+    assert_eq!(stale.text, fill.text);
+    let forced = fill_manifest(&fill.text, ManifestFormat::Homebrew, &[&arm], true).unwrap();
+    assert_eq!(forced.fields[0].status, FieldStatus::Updated);
+
+    // Scoop keeps an algorithm prefix, winget writes upper case
+    let scoop = r#"{
+    "url": "https://example.com/tool-x86_64.tar.gz",
+    "hash": "sha512:"
+}
+"#;
+    let fill = fill_manifest(scoop, ManifestFormat::Scoop, &[&intel], false).unwrap();
+    let sha512 = hash_string("intel", Algorithm::Sha512).unwrap();
+    assert!(fill.text.contains(&format!("\"hash\": \"sha512:{}\"", sha512)));
+    let winget = "\
+Installers:
+- InstallerUrl: https://example.com/tool-x86_64.tar.gz
+  InstallerSha256:
+";
+    let fill = fill_manifest(winget, ManifestFormat::Winget, &[&intel], false).unwrap();
+    assert!(fill.text.ends_with(&format!("InstallerSha256: {}\n", intel_sha.to_uppercase())));
+
+    // Every artifact must belong to some field
+    assert!(fill_manifest(winget, ManifestFormat::Winget, &[&intel, &arm], false).is_err());
+}
+
+#[test]
+fn test_verify_entries_in_parallel() {
+    use hashing::checksum::{verify_entries, CheckStatus, ChecksumEntry};
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut entries = Vec::new();
+    for i in 0..40 {
+        let name = format!("file{:02}", i);
+        // Larger files early on, so later ones tend to finish first
+        let contents = "x".repeat((40 - i) * 4096);
+        std::fs::write(dir.path().join(&name), &contents).unwrap();
+        let mut digest = hash_string(&contents, Algorithm::Sha256).unwrap();
+        if i % 10 == 3 {
+            digest = hash_string("other", Algorithm::Sha256).unwrap();
+        }
+        entries.push(ChecksumEntry { digest, path: name });
+    }
+    entries.push(ChecksumEntry {
+        digest: "00".repeat(32),
+        path: "gone".to_string(),
+    });
+
+    for jobs in [1, 8] {
+        let mut seen = Vec::new();
+        let record = |entry: &ChecksumEntry, status: &CheckStatus| {
+            seen.push((entry.path.clone(), status.clone()));
+        };
+        let summary = verify_entries(&entries, Algorithm::Sha256, dir.path(), jobs, record);
+        let paths: Vec<&String> = seen.iter().map(|(path, _)| path).collect();
+        let listed: Vec<&String> = entries.iter().map(|entry| &entry.path).collect();
+        assert_eq!(paths, listed);
+        assert_eq!(seen[3].1, CheckStatus::Failed);
+        assert_eq!(seen[40].1, CheckStatus::Missing);
+        assert_eq!((summary.ok, summary.failed, summary.missing), (36, 4, 1));
+        assert!(!summary.is_ok());
+    }
+}
+
+#[test]
+fn test_verify_entries_cached() {
+    use hashing::checksum::{verify_entries_cached, CheckStatus, ChecksumEntry};
+    use hashing::statcache::{FileStamp, StatCache};
+
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    let mut entries = Vec::new();
+    for name in ["a.bin", "b.bin", "c.bin"] {
+        std::fs::write(root.join(name), name).unwrap();
+        let digest = hash_string(name, Algorithm::Sha256).unwrap();
+        entries.push(ChecksumEntry { digest, path: name.to_string() });
+    }
+
+    // A cache file claiming a.bin and b.bin verified with their current stamps
+    let mut text = String::new();
+    for entry in &entries[..2] {
+        let path = root.join(&entry.path);
+        let stamp = FileStamp::of(&path).unwrap();
+        let (secs, nanos) = (stamp.modified.as_secs(), stamp.modified.subsec_nanos());
+        let fields = format!("{}\t{}.{:09}\tsha256", stamp.size, secs, nanos);
+        text.push_str(&format!("{}\t{}\t{}\n", fields, entry.digest, path.display()));
+    }
+    let cache_path = root.join("verify.cache");
+    std::fs::write(&cache_path, text).unwrap();
+
+    let run = |cache: &mut StatCache| {
+        let mut statuses = Vec::new();
+        let record = |_: &ChecksumEntry, status: &CheckStatus| statuses.push(status.clone());
+        let summary = verify_entries_cached(&entries, Algorithm::Sha256, root, 4, cache, record);
+        (statuses, summary)
+    };
+    let mut cache = StatCache::load(&cache_path).unwrap();
+    let (statuses, summary) = run(&mut cache);
+    assert_eq!(statuses, [CheckStatus::Cached, CheckStatus::Cached, CheckStatus::Ok]);
+    assert_eq!((summary.cached, summary.ok), (2, 1));
+    assert!(summary.is_ok());
+    // c.bin was just written, too recently to be trusted later
+    assert_eq!(cache.len(), 2);
+
+    // A changed size invalidates the entry, and a failure is forgotten
+    std::fs::write(root.join("b.bin"), "changed").unwrap();
+    let (statuses, summary) = run(&mut cache);
+    assert_eq!(statuses[1], CheckStatus::Failed);
+    assert!(!summary.is_ok());
+    assert_eq!(cache.len(), 1);
+
+    cache.save(&cache_path).unwrap();
+    assert_eq!(StatCache::load(&cache_path).unwrap().len(), 1);
+    assert!(StatCache::load(root.join("none.cache")).unwrap().is_empty());
+}
+
+#[test]
+fn test_progress_records() {
+    use hashing::progress::{ProgressEvent, ProgressRecord, ProgressTracker};
+    use hashing::Hashing;
+    use std::time::Duration;
+
+    let mut output = Vec::new();
+    let mut tracker = ProgressTracker::new(&mut output, Duration::ZERO);
+    for data in ["first", "second!"] {
+        tracker.start_file(format!("{}.txt", data));
+        Hashing::new()
+            .buffer_size(2)
+            .progress(|bytes| tracker.bytes(bytes))
+            .hash_string(data)
+            .unwrap();
+        tracker.finish_file();
+    }
+    tracker.fail_file(Some(std::path::Path::new("locked.txt")), "permission denied");
+    tracker.finish().unwrap();
+
+    let records: Vec<ProgressRecord> = String::from_utf8(output)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let error = records.iter().find(|r| r.event == ProgressEvent::Error).unwrap();
+    assert_eq!(error.current.as_deref(), Some("locked.txt"));
+    assert_eq!(error.message.as_deref(), Some("permission denied"));
+    let done = records.last().unwrap();
+    assert_eq!(done.event, ProgressEvent::Done);
+    assert_eq!((done.files_done, done.files_failed, done.bytes_done), (2, 1, 12));
+    assert_eq!(done.current, None);
+
+    // Byte counts within a file are reported as they grow
+    let during: Vec<u64> = records
+        .iter()
+        .filter(|r| r.current.as_deref() == Some("second!.txt"))
+        .map(|r| r.bytes_done)
+        .collect();
+    assert!(during.windows(2).all(|pair| pair[0] <= pair[1]));
+    assert!(during.len() > 2);
+}
+
+#[test]
+fn test_hash_file_with_progress() {
+    use hashing::progress::ProgressBar;
+    use hashing::{hash_file_with_progress, MultiHasher};
+    use std::time::Duration;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("big.bin");
+    std::fs::write(&path, vec![7u8; 100_000]).unwrap();
+    let mut calls = Vec::new();
+    let digest =
+        hash_file_with_progress(&path, Algorithm::Sha256, |done, total| calls.push((done, total)))
+            .unwrap();
+    assert_eq!(digest, hash_file(&path, Algorithm::Sha256).unwrap());
+    assert!(calls.len() > 1);
+    assert!(calls.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    assert_eq!(calls.last(), Some(&(100_000, 100_000)));
+    let missing = dir.path().join("none");
+    assert!(hash_file_with_progress(missing, Algorithm::Sha256, |_, _| ()).is_err());
+
+    // Several algorithms from one read report the same byte counts
+    let mut read = 0;
+    let digests = MultiHasher::new(&[Algorithm::Md5, Algorithm::Sha256])
+        .hash_file_with_progress(&path, |bytes| read = bytes)
+        .unwrap();
+    assert_eq!(read, 100_000);
+    assert_eq!(digests[1].to_hex(), digest);
+
+    // Without a size there is no percentage or time left, only what was read
+    let bar = ProgressBar::new(std::io::sink(), None);
+    assert_eq!(bar.line(3 << 30, Duration::from_secs(75)), "3.0 GiB  41.0 MiB/s  1:15");
+    let bar = ProgressBar::new(std::io::sink(), Some(100));
+    let done = bar.line(100, Duration::from_secs(3725));
+    assert!(done.starts_with("[========================] 100%"), "{}", done);
+    assert!(done.ends_with("1:02:05"), "{}", done);
+
+    let mut output = Vec::new();
+    let mut bar = ProgressBar::new(&mut output, Some(10));
+    bar.update(4);
+    bar.update(10);
+    bar.finish();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.starts_with('\r') && output.ends_with('\n'));
+    assert!(output.contains("100%"));
+}
+
+#[test]
+fn test_hash_and_verify_jobs() {
+    use hashing::checksum::{CheckStatus, ChecksumEntry};
+    use hashing::job::{HashJob, JobEvent, JobState, VerifyJob};
+    use hashing::walk::{SortOrder, WalkOptions};
+    use std::time::Duration;
+
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("a.txt"), "alpha").unwrap();
+    std::fs::write(dir.path().join("b.txt"), "x".repeat(100_000)).unwrap();
+
+    let job = HashJob::new(dir.path())
+        .with_walk_options(WalkOptions::new().with_sort(SortOrder::Name))
+        .with_progress_interval(Duration::ZERO)
+        .spawn();
+    let events: Vec<JobEvent> = job.events().iter().collect();
+    let hashed: Vec<(String, String)> = events
+        .iter()
+        .filter_map(|event| match event {
+            JobEvent::Hashed { path, digest, .. } => {
+                let name = path.file_name().unwrap().to_string_lossy().to_string();
+                Some((name, digest.clone()))
+            }
+            _ => None,
+        })
+        .collect();
+    let b_digest = hash_file(dir.path().join("b.txt"), Algorithm::Sha256).unwrap();
+    assert_eq!(hashed[0], ("a.txt".to_string(), hash_string("alpha", Algorithm::Sha256).unwrap()));
+    assert_eq!(hashed[1], ("b.txt".to_string(), b_digest.clone()));
+    assert!(events.iter().any(|e| matches!(e, JobEvent::Progress { bytes: 100_000, .. })));
+    let JobEvent::Finished(finished) = events.last().unwrap() else {
+        panic!("last event is not Finished");
+    };
+    assert_eq!(finished.state, JobState::Completed);
+    assert_eq!((finished.files_done, finished.bytes_done), (2, 100_005));
+    assert_eq!(job.wait().unwrap().state, JobState::Completed);
+
+    // Skipped files are neither hashed nor counted
+    let job = HashJob::new(dir.path()).with_skip([dir.path().join("a.txt")]).spawn();
+    assert_eq!(job.wait().unwrap().files_done, 1);
+
+    let entries = vec![
+        ChecksumEntry {
+            digest: b_digest.clone(),
+            path: "b.txt".to_string(),
+        },
+        ChecksumEntry {
+            digest: "00".repeat(32),
+            path: "a.txt".to_string(),
+        },
+    ];
+    let job = VerifyJob::new(entries).with_base(dir.path()).with_jobs(2).spawn();
+    let statuses: Vec<CheckStatus> = job
+        .events()
+        .iter()
+        .filter_map(|event| match event {
+            JobEvent::Checked { status, .. } => Some(status),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(statuses, [CheckStatus::Ok, CheckStatus::Failed]);
+    let status = job.wait().unwrap();
+    assert_eq!((status.files_done, status.files_failed), (1, 1));
+
+    // A streamed list reports line numbers, and a stat cache that says
+    // b.txt verified with its current size and mtime spares hashing it
+    let list = format!("{}  b.txt\nnot a checksum line\n", b_digest);
+    let b_path = dir.path().join("b.txt");
+    let stamp = hashing::statcache::FileStamp::of(&b_path).unwrap();
+    let (secs, nanos) = (stamp.modified.as_secs(), stamp.modified.subsec_nanos());
+    let cached = format!("{}\t{}.{:09}\tsha256\t{}", stamp.size, secs, nanos, b_digest);
+    let cache = dir.path().join("verify.cache");
+    std::fs::write(&cache, format!("{}\t{}\n", cached, b_path.display())).unwrap();
+    for (cache, first) in [(None, CheckStatus::Ok), (Some(&cache), CheckStatus::Cached)] {
+        let job = VerifyJob::from_reader(std::io::Cursor::new(list.clone())).with_base(dir.path());
+        let job = match cache {
+            Some(cache) => job.with_cache(cache),
+            None => job,
+        };
+        let checked: Vec<(usize, CheckStatus)> = job
+            .spawn()
+            .events()
+            .iter()
+            .filter_map(|event| match event {
+                JobEvent::Checked { line, status, .. } => Some((line, status)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(checked, [(1, first), (2, CheckStatus::Malformed)]);
+    }
+
+    // A cancelled job still ends with a Finished event
+    for i in 0..200 {
+        std::fs::write(dir.path().join(format!("many{}", i)), "x").unwrap();
+    }
+    let job = HashJob::new(dir.path()).spawn();
+    job.cancel();
+    let last = job.events().iter().last().unwrap();
+    let status = job.wait().unwrap();
+    assert_eq!(last, JobEvent::Finished(status.clone()));
+    if status.state == JobState::Cancelled {
+        assert!(status.files_done < 202);
+    }
+}
+
+#[test]
+fn test_merge_manifests() {
+    use hashing::manifest::{merge, Manifest, MergeOptions};
+
+    let windows = Manifest::parse("BBBB *.\\data\\b.bin\n# comment\nAAAA  a.bin\n").unwrap();
+    let unix = Manifest::parse("cccc  ./data//c.bin\naaaa  a.bin\n").unwrap();
+    let manifests = [windows, unix];
+
+    let all = merge(&manifests, MergeOptions::new()).unwrap();
+    assert_eq!(all.len(), 4);
+    assert_eq!(all.entries[0].path, "data/b.bin");
+    assert_eq!(all.entries[0].digest, "bbbb");
+
+    let clean = merge(&manifests, MergeOptions::new().with_dedupe(true).with_sort(true)).unwrap();
+    assert_eq!(clean.to_text(), "aaaa  a.bin\nbbbb  data/b.bin\ncccc  data/c.bin\n");
+
+    // The same path with another digest is a conflict, not a duplicate
+    let other = Manifest::parse("dddd  data\\b.bin\n").unwrap();
+    let err = merge(&[clean.clone(), other], MergeOptions::new()).unwrap_err();
+    assert!(err.to_string().contains("conflicting digests for data/b.bin"));
+
+    let longer = Manifest::parse("abcdef  e.bin\n").unwrap();
+    assert!(merge(&[clean, longer], MergeOptions::new()).is_err());
+    assert!(Manifest::parse("aaaa\n").is_err());
+}
+
+#[test]
+fn test_convert_checksum_lists() {
+    use hashing::manifest::{
+        algorithm_from_file_name, fill_sizes, read_records, write_records, ChecksumFormat,
+    };
+
+    let hello = hash_string("hello", Algorithm::Sha256).unwrap();
+    let hello_md5 = hash_string("hello", Algorithm::Md5).unwrap();
+    let hashdeep = format!(
+        "%%%% HASHDEEP-1.0\n%%%% size,md5,sha256,filename\n## comment\n5,{},{},dir/a, b.txt\n",
+        hello_md5,
+        hello.to_uppercase()
+    );
+    let records = read_records(&hashdeep, ChecksumFormat::Hashdeep, None).unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].path, "dir/a, b.txt");
+    assert_eq!(records[0].size, Some(5));
+    assert_eq!(records[0].digest("sha256"), Some(hello.as_str()));
+
+    // Both digests survive a round trip through JSON, the size does not
+    let json = write_records(&records, ChecksumFormat::Json, None).unwrap();
+    let back = read_records(&json, ChecksumFormat::Json, None).unwrap();
+    assert_eq!(back[0].digests, records[0].digests);
+    assert_eq!(back[0].size, None);
+
+    // One digest per line needs a choice
+    assert!(write_records(&records, ChecksumFormat::Checksum, None).is_err());
+    let sums = write_records(&records, ChecksumFormat::Checksum, Some("md5")).unwrap();
+    assert_eq!(sums, format!("{}  dir/a, b.txt\n", hello_md5));
+    let rewritten = write_records(&records, ChecksumFormat::Hashdeep, None).unwrap();
+    let expected = hashdeep.replace("## comment", "##");
+    assert_eq!(rewritten, expected.replace(&hello.to_uppercase(), &hello));
+
+    // Checksum files need sizes looked up before becoming hashdeep
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("a.txt"), "hello").unwrap();
+    let sums = format!("{}  a.txt\n", hello);
+    let mut records = read_records(&sums, ChecksumFormat::Checksum, Some("sha256")).unwrap();
+    assert!(write_records(&records, ChecksumFormat::Hashdeep, None).is_err());
+    fill_sizes(&mut records, dir.path()).unwrap();
+    let converted = write_records(&records, ChecksumFormat::Hashdeep, None).unwrap();
+    assert!(converted.ends_with(&format!("5,{},a.txt\n", hello)));
+
+    let sfv_file = "; comment\nfile one.bin 3610A686\n";
+    let sfv = read_records(sfv_file, ChecksumFormat::Sfv, None).unwrap();
+    assert_eq!(sfv[0].path, "file one.bin");
+    let sfv_text = write_records(&sfv, ChecksumFormat::Sfv, None).unwrap();
+    assert_eq!(sfv_text, "file one.bin 3610A686\n");
+    assert!(write_records(&records, ChecksumFormat::Sfv, None).is_err());
+
+    assert_eq!(ChecksumFormat::from_path("dist/SHA256SUMS"), ChecksumFormat::Checksum);
+    assert_eq!(ChecksumFormat::from_path("disc.sfv"), ChecksumFormat::Sfv);
+    assert_eq!(algorithm_from_file_name("dist/SHA256SUMS"), Some(Algorithm::Sha256));
+    assert_eq!(algorithm_from_file_name("app.tar.gz.md5"), Some(Algorithm::Md5));
+    assert_eq!(algorithm_from_file_name("md5sums.txt"), Some(Algorithm::Md5));
+    assert_eq!(algorithm_from_file_name("checksums.txt"), None);
+}
+
+#[test]
+fn test_split_manifest_parts() {
+    use hashing::manifest::{part_path, ManifestSplit, ROOT_PART};
+    use std::path::{Path, PathBuf};
+
+    let by_dir = ManifestSplit::TopDirectory;
+    assert_eq!(by_dir.part("/srv/data", "/srv/data/set-a/deep/x.bin", 0), "set-a");
+    assert_eq!(by_dir.part("/srv/data", "/srv/data/set-b", 1), ROOT_PART);
+    assert_eq!(by_dir.part("./data", "./data/set-a/x.bin", 2), "set-a");
+
+    let every = ManifestSplit::Every(2);
+    let parts: Vec<String> = (0..5).map(|i| every.part("data", "data/x", i)).collect();
+    assert_eq!(parts, ["0001", "0001", "0002", "0002", "0003"]);
+
+    assert_eq!(part_path("out/hashes.sha256", "set-a"), PathBuf::from("out/hashes-set-a.sha256"));
+    assert_eq!(part_path(Path::new("SHA256SUMS"), "0001"), PathBuf::from("SHA256SUMS-0001"));
+}
+
+#[test]
+fn test_verify_reader_streams() {
+    use hashing::checksum::{verify_entries, verify_reader, CheckStatus, ChecksumEntry, EntryRef};
+    use hashing::statcache::{FileStamp, StatCache};
+    use std::io::Cursor;
+
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    std::fs::create_dir(root.join("sub")).unwrap();
+    let mut entries = Vec::new();
+    let mut text = String::from("# generated\n");
+    // More entries than one batch holds, cycling over a few files
+    for i in 0..5000 {
+        let mut path = format!("sub/file{}", i % 7);
+        let mut digest = hash_string(&path, Algorithm::Sha256).unwrap();
+        if i % 1000 == 999 {
+            path = "sub/bad".to_string();
+            digest = hash_string("other", Algorithm::Sha256).unwrap();
+        }
+        text.push_str(&format!("{} *{}\n", digest, path));
+        entries.push(ChecksumEntry { digest, path });
+    }
+    text.push_str(&format!("{}  gone\n", "00".repeat(32)));
+    entries.push(ChecksumEntry { digest: "00".repeat(32), path: "gone".to_string() });
+    for i in 0..7 {
+        let path = format!("sub/file{}", i);
+        std::fs::write(root.join(&path), &path).unwrap();
+    }
+    std::fs::write(root.join("sub/bad"), "bad").unwrap();
+
+    let mut expected = Vec::new();
+    let record = |entry: &ChecksumEntry, status: &CheckStatus| {
+        expected.push((entry.clone(), status.clone()));
+    };
+    let listed = verify_entries(&entries, Algorithm::Sha256, root, 4, record);
+
+    let mut seen = Vec::new();
+    let record = |entry: EntryRef, status: &CheckStatus| {
+        seen.push((entry.line, entry.to_entry(), status.clone()));
+    };
+    let reader = Cursor::new(text.as_bytes());
+    let summary = verify_reader(reader, Algorithm::Sha256, root, 4, None, record).unwrap();
+    assert_eq!(summary, listed);
+    assert_eq!((summary.ok, summary.failed, summary.missing), (4995, 5, 1));
+    assert_eq!(seen.len(), expected.len());
+    for (i, ((line, entry, status), (listed, listed_status))) in
+        seen.iter().zip(&expected).enumerate()
+    {
+        assert_eq!(*line, i + 2);
+        assert_eq!((entry, status), (listed, listed_status));
+    }
+
+    // With a cache, files it vouches for are not read again
+    let mut cache_text = String::new();
+    for i in 0..7 {
+        let path = root.join(format!("sub/file{}", i));
+        let stamp = FileStamp::of(&path).unwrap();
+        let (secs, nanos) = (stamp.modified.as_secs(), stamp.modified.subsec_nanos());
+        let digest = hash_string(&format!("sub/file{}", i), Algorithm::Sha256).unwrap();
+        let fields = format!("{}\t{}.{:09}\tsha256", stamp.size, secs, nanos);
+        cache_text.push_str(&format!("{}\t{}\t{}\n", fields, digest, path.display()));
+    }
+    let cache_path = root.join("verify.cache");
+    std::fs::write(&cache_path, cache_text).unwrap();
+    let mut cache = StatCache::load(&cache_path).unwrap();
+    assert_eq!(cache.len(), 7);
+    let reader = Cursor::new(text.as_bytes());
+    let cache = Some(&mut cache);
+    let summary = verify_reader(reader, Algorithm::Sha256, root, 4, cache, |_, _| {}).unwrap();
+    assert_eq!((summary.cached, summary.ok, summary.failed), (4995, 0, 5));
+
+    // Lines that are not UTF-8 stop the check
+    let reader = Cursor::new(b"aaaa  a\n\xff\xfe  b\n".to_vec());
+    assert!(verify_reader(reader, Algorithm::Sha256, root, 1, None, |_, _| {}).is_err());
+}
+
+#[test]
+fn test_size_format() {
+    use hashing::units::{decimal_point, SizeFormat, SizeUnits};
+    use std::time::Duration;
+
+    let binary = SizeFormat::new();
+    assert_eq!(binary.size(0), "0 B");
+    assert_eq!(binary.size(1023), "1023 B");
+    assert_eq!(binary.size(1024), "1.0 KiB");
+    assert_eq!(binary.size(1024 * 1024 - 1), "1.0 MiB");
+    assert_eq!(binary.size(5 << 30), "5.0 GiB");
+    assert_eq!(binary.size(u64::MAX), "16.0 EiB");
+    assert_eq!(binary.rate(0, Duration::ZERO), "0 B/s");
+    assert_eq!(binary.rate(1 << 30, Duration::from_millis(500)), "2.0 GiB/s");
+
+    let si = SizeFormat::new().with_units(SizeUnits::Si);
+    assert_eq!(si.size(1000), "1.0 kB");
+    assert_eq!(si.size(2_500_000_000), "2.5 GB");
+    assert_eq!(si.rate(3_000_000, Duration::from_secs(2)), "1.5 MB/s");
+    assert_eq!(si.with_decimal_point(',').size(1_250_000), "1,2 MB");
+
+    assert_eq!("si".parse::<SizeUnits>().unwrap(), SizeUnits::Si);
+    assert_eq!("Binary".parse::<SizeUnits>().unwrap(), SizeUnits::Binary);
+    assert!("metric".parse::<SizeUnits>().is_err());
+    assert_eq!(decimal_point("de_DE.UTF-8"), ',');
+    assert_eq!(decimal_point("pt_BR"), ',');
+    assert_eq!(decimal_point("en_US.UTF-8"), '.');
+    assert_eq!(decimal_point("C"), '.');
+}
+
+#[test]
+fn test_build_info() {
+    use hashing::buildinfo::{build_info, cpu_features};
+
+    let info = build_info();
+    assert_eq!(info.name, "hashing");
+    assert!(!info.target.is_empty());
+    assert_eq!(info.algorithms.len(), Algorithm::all().len());
+    assert_eq!(info.features.contains(&"git"), cfg!(feature = "git"));
+    assert_eq!(info.cpu_features, cpu_features());
+    let backends: Vec<&str> = info.backends.iter().map(|b| b.algorithm).collect();
+    assert_eq!(backends, ["sha256", "sha512", "blake3"]);
+    assert_eq!(info.backend(Algorithm::Md5), "portable");
+    assert_eq!(info.backend(Algorithm::Sha224), info.backends[0].backend);
+    assert_eq!(info.backend(Algorithm::Sha512_256), info.backends[1].backend);
+    assert_eq!(info.backend(Algorithm::Blake3), info.backends[2].backend);
+
+    let json: serde_json::Value = serde_json::to_value(&info).unwrap();
+    assert_eq!(json["version"], env!("CARGO_PKG_VERSION"));
+    assert!(json["backends"][0]["backend"].is_string());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_format_plugin() {
+    use hashing::plugin::{list_plugins, FormatPlugin};
+    use std::collections::BTreeMap;
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let script = |name: &str, body: &str| {
+        let path = dir.path().join(name);
+        std::fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    };
+    let body = r#"echo "$HASH_PLUGIN_FORMAT $HASH_PLUGIN_PROTOCOL"; tr a-z A-Z"#;
+    let upper = script("upper.sh", body);
+    let failing = script("failing.sh", "cat >/dev/null; exit 4");
+
+    let configured = BTreeMap::from([
+        ("upper".to_string(), upper.clone()),
+        ("failing".to_string(), failing),
+    ]);
+    let plugin = FormatPlugin::find("upper", &configured).unwrap();
+    assert_eq!(plugin.program(), upper);
+
+    let results: Vec<HashResult> = ["a", "b"]
+        .iter()
+        .map(|input| {
+            let digest = hash_string(input, Algorithm::Md5).unwrap();
+            HashResult::new(Algorithm::Md5, digest, "string")
+        })
+        .collect();
+    let export = dir.path().join("export.txt");
+    plugin.render(&results, std::fs::File::create(&export).unwrap()).unwrap();
+    let text = std::fs::read_to_string(&export).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines[0], "upper 1");
+    assert_eq!(lines.len(), 3);
+    assert!(lines[1].contains(&results[0].digest.to_uppercase()));
+
+    let failing = FormatPlugin::find("failing", &configured).unwrap();
+    let output = std::fs::File::create(dir.path().join("failed.txt")).unwrap();
+    let err = failing.render(&results, output).unwrap_err();
+    assert!(err.to_string().contains("failing"));
+
+    assert!(FormatPlugin::find("../upper", &configured).is_err());
+    assert!(FormatPlugin::find("no-such-format-plugin", &BTreeMap::new()).is_err());
+    let listed = list_plugins(&configured);
+    assert!(listed.iter().any(|plugin| plugin.name() == "upper"));
+}
+
+#[test]
+fn test_output_template() {
+    use hashing::template::{Field, Template};
+
+    let result = HashResult::new(Algorithm::Sha256, "abcd".to_string(), "file")
+        .with_path("dir/report.pdf");
+    let template = Template::parse(r"{path}\t{algo}\t{digest}\t{size}").unwrap();
+    assert_eq!(template.render(&result, Some(42)), "dir/report.pdf\tsha256\tabcd\t42");
+    assert_eq!(template.render(&result, None), "dir/report.pdf\tsha256\tabcd\t");
+    assert!(template.uses(Field::Size));
+    assert!(!template.uses(Field::Name));
+
+    let template = Template::parse(r#"{{"{name}": "{ algorithm }:{digest}"}}\\{type}\n"#).unwrap();
+    let line = template.render(&result, None);
+    assert_eq!(line, "{\"report.pdf\": \"sha256:abcd\"}\\file\n");
+    let fields: Vec<Field> = template.fields().collect();
+    assert_eq!(fields, [Field::Name, Field::Algorithm, Field::Digest, Field::Type]);
+
+    let string = HashResult::new(Algorithm::Md5, "ef".to_string(), "string");
+    let template = Template::parse("[{path}][{name}] {digest}").unwrap();
+    assert_eq!(template.render(&string, Some(2)), "[][] ef");
+
+    for bad in ["{digest", "{hash}", "}", r"\q", "trailing\\"] {
+        assert!(Template::parse(bad).is_err(), "{:?} should not parse", bad);
+    }
+}
+
+#[test]
+fn test_algorithm_families() {
+    use hashing::AlgorithmFamily;
+
+    // Every algorithm belongs to exactly one family, listed in family order
+    let listed: Vec<Algorithm> = AlgorithmFamily::all()
+        .iter()
+        .flat_map(|family| family.algorithms())
+        .collect();
+    assert_eq!(listed, Algorithm::all());
+
+    assert_eq!(Algorithm::Sha512_256.family(), AlgorithmFamily::Sha2);
+    assert_eq!(Algorithm::Blake2s256.family(), AlgorithmFamily::Blake);
+    assert_eq!(Algorithm::Hash160.family(), AlgorithmFamily::Bitcoin);
+    assert_eq!(
+        AlgorithmFamily::Sha3.algorithms(),
+        vec![
+            Algorithm::Sha3_224,
+            Algorithm::Sha3_256,
+            Algorithm::Sha3_384,
+            Algorithm::Sha3_512,
+            Algorithm::Shake128,
+            Algorithm::Shake256
+        ]
+    );
+
+    assert_eq!("sha2".parse::<AlgorithmFamily>().unwrap(), AlgorithmFamily::Sha2);
+    assert_eq!("SHA-3".parse::<AlgorithmFamily>().unwrap(), AlgorithmFamily::Sha3);
+    assert_eq!(AlgorithmFamily::Blake.to_string(), "blake");
+    assert_eq!(AlgorithmFamily::Sha1.display_name(), "SHA-1");
+    assert!("whirlpool".parse::<AlgorithmFamily>().is_err());
+}
+
+#[test]
+fn test_paranoid_hashing() {
+    use hashing::job::{HashJob, JobEvent};
+    use hashing::Hashing;
+
+    // Sizes around BLAKE3's chunk and the read buffer exercise every split
+    let data: Vec<u8> = (0..70_000u32).map(|i| (i % 251) as u8).collect();
+    for len in [0, 1, 1024, 1025, 8192, 70_000] {
+        for algorithm in Algorithm::all() {
+            let digest = Hashing::new()
+                .algorithm(algorithm)
+                .paranoid(true)
+                .hash_bytes(&data[..len])
+                .unwrap();
+            assert_eq!(digest, hashing::hash_bytes(&data[..len], algorithm).unwrap());
+        }
+    }
+
+    let keyed = Hashing::new().key(b"key".to_vec()).paranoid(true).hash_string("msg").unwrap();
+    assert_eq!(keyed, Hashing::new().key(b"key".to_vec()).hash_string("msg").unwrap());
+
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("big.bin"), &data).unwrap();
+    let job = HashJob::new(dir.path())
+        .with_algorithm(Algorithm::Blake3)
+        .with_paranoid(true)
+        .spawn();
+    let digests: Vec<String> = job
+        .events()
+        .iter()
+        .filter_map(|event| match event {
+            JobEvent::Hashed { digest, .. } => Some(digest),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(digests, vec![hashing::hash_bytes(&data, Algorithm::Blake3).unwrap()]);
+}
+
+#[test]
+fn test_double_read() {
+    use hashing::job::{HashJob, JobEvent};
+    use hashing::{pagecache, Hashing};
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("archive.bin");
+    std::fs::write(&path, "x".repeat(50_000)).unwrap();
+    let expected = hash_file(&path, Algorithm::Sha256).unwrap();
+
+    let dropped = pagecache::drop_cached(&std::fs::File::open(&path).unwrap()).unwrap();
+    assert_eq!(dropped, cfg!(any(target_os = "linux", target_os = "android")));
+
+    let mut reads = Vec::new();
+    let digest = Hashing::new()
+        .double_read(true)
+        .progress(|bytes| reads.push(bytes))
+        .hash_file(&path)
+        .unwrap();
+    assert_eq!(digest, expected);
+    // Both reads report progress from the start of the file
+    assert_eq!(reads.iter().filter(|&&bytes| bytes == 50_000).count(), 2);
+
+    let job = HashJob::new(dir.path()).with_double_read(true).spawn();
+    let digests: Vec<String> = job
+        .events()
+        .iter()
+        .filter_map(|event| match event {
+            JobEvent::Hashed { digest, .. } => Some(digest),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(digests, vec![expected]);
+}
+
+#[test]
+fn test_uncached_reads() {
+    use hashing::job::{HashJob, JobEvent};
+    use hashing::pagecache::UncachedReader;
+    use hashing::Hashing;
+    use std::io::Read;
+
+    // More than two O_DIRECT blocks, ending mid-block
+    let data: Vec<u8> = (0..(5 << 19) + 123u32).map(|i| (i % 253) as u8).collect();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("cold.bin");
+    std::fs::write(&path, &data).unwrap();
+
+    let mut reader = UncachedReader::open(&path).unwrap();
+    let mut contents = Vec::new();
+    let mut piece = [0u8; 3000];
+    loop {
+        let count = reader.read(&mut piece).unwrap();
+        if count == 0 {
+            break;
+        }
+        contents.extend_from_slice(&piece[..count]);
+    }
+    assert!(contents == data);
+
+    let expected = hashing::hash_bytes(&data, Algorithm::Sha256).unwrap();
+    let digest = Hashing::new().uncached(true).double_read(true).hash_file(&path).unwrap();
+    assert_eq!(digest, expected);
+
+    let job = HashJob::new(dir.path()).with_uncached(true).spawn();
+    let digests: Vec<String> = job
+        .events()
+        .iter()
+        .filter_map(|event| match event {
+            JobEvent::Hashed { digest, .. } => Some(digest),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(digests, vec![expected]);
+}
+
+#[test]
+fn test_hash_reader() {
+    use hashing::hash_reader;
+    use std::io::{self, Read};
+
+    /// Hands out one byte per read, like a slow socket
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let Some((&first, rest)) = self.0.split_first() else {
+                return Ok(0);
+            };
+            buf[0] = first;
+            self.0 = rest;
+            Ok(1)
+        }
+    }
+
+    let data = "stream".repeat(3000);
+    for algorithm in Algorithm::all() {
+        let expected = hash_string(&data, algorithm).unwrap();
+        assert_eq!(hash_reader(data.as_bytes(), algorithm).unwrap(), expected);
+        assert_eq!(hash_reader(Trickle(data.as_bytes()), algorithm).unwrap(), expected);
+    }
+
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(data.as_bytes()).unwrap();
+    assert_eq!(
+        hash_file(file.path(), Algorithm::Blake3).unwrap(),
+        hash_reader(io::Cursor::new(&data), Algorithm::Blake3).unwrap()
+    );
+
+    // Read errors are reported, not hashed over
+    struct Broken;
+    impl Read for Broken {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::ConnectionReset, "reset"))
+        }
+    }
+    assert!(matches!(
+        hash_reader(Broken, Algorithm::Sha256),
+        Err(hashing::HashError::Io(_))
+    ));
+}
+
+#[test]
+fn test_read_ahead() {
+    use hashing::job::{HashJob, JobEvent};
+    use hashing::pagecache::{self, ReadAheadReader};
+    use hashing::Hashing;
+    use std::fs::File;
+    use std::io::Read;
+
+    let data: Vec<u8> = (0..300_000u32).map(|i| (i % 241) as u8).collect();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("nas.bin");
+    std::fs::write(&path, &data).unwrap();
+
+    let linux = cfg!(any(target_os = "linux", target_os = "android"));
+    let file = File::open(&path).unwrap();
+    assert_eq!(pagecache::advise_sequential(&file).unwrap(), linux);
+    assert_eq!(pagecache::prefetch(&file, 0, 4096).unwrap(), linux);
+
+    // Windows smaller and larger than the reads give the same bytes
+    for window in [0, 1000, 1 << 20] {
+        let mut reader = ReadAheadReader::new(File::open(&path).unwrap(), window);
+        let mut contents = Vec::new();
+        reader.read_to_end(&mut contents).unwrap();
+        assert!(contents == data);
+    }
+
+    let expected = hashing::hash_bytes(&data, Algorithm::Sha512).unwrap();
+    let digest = Hashing::new()
+        .algorithm(Algorithm::Sha512)
+        .read_ahead(64 << 10)
+        .hash_file(&path)
+        .unwrap();
+    assert_eq!(digest, expected);
+
+    let job = HashJob::new(dir.path())
+        .with_algorithm(Algorithm::Sha512)
+        .with_read_ahead(64 << 10)
+        .spawn();
+    let digests: Vec<String> = job
+        .events()
+        .iter()
+        .filter_map(|event| match event {
+            JobEvent::Hashed { digest, .. } => Some(digest),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(digests, vec![expected]);
+}
+
+#[test]
+fn test_raw_digest() {
+    use hashing::{hash_bytes_raw, hash_file_raw, Digest, Encoding, HashError, Hashing};
+
+    for algorithm in Algorithm::all() {
+        let digest = hash_bytes_raw(b"raw", algorithm).unwrap();
+        assert_eq!(digest.algorithm(), algorithm);
+        assert_eq!(digest.len(), algorithm.output_size());
+        assert_eq!(digest.to_hex(), hash_string("raw", algorithm).unwrap());
+        assert_eq!(digest.to_string(), digest.to_hex());
+    }
+
+    let digest = hash_bytes_raw(b"hello", Algorithm::Sha256).unwrap();
+    assert_eq!(digest.to_base64(), "LPJNul+wow4m6DsqxbninhsWHlwfp0JecwQzYpOLmCQ=");
+    assert_eq!(digest.encode(Encoding::HexUpper).unwrap(), digest.to_hex().to_uppercase());
+    assert_eq!(Digest::new(Algorithm::Sha256, digest.as_bytes()).unwrap(), digest);
+    assert!(matches!(
+        Digest::new(Algorithm::Sha1, digest.as_bytes()),
+        Err(HashError::InvalidInput(_))
+    ));
+
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(b"hello").unwrap();
+    assert_eq!(hash_file_raw(file.path(), Algorithm::Sha256).unwrap(), digest);
+
+    // The builder's raw digests ignore the encoding but keep the key
+    let mut hashing = Hashing::new().encoding(Encoding::Base64);
+    assert_eq!(hashing.hash_bytes_raw(b"hello").unwrap(), digest);
+    let mut keyed = Hashing::new().key("secret");
+    let mac: Vec<u8> = keyed.hash_file_raw(file.path()).unwrap().into();
+    assert_eq!(hex::encode(mac), keyed.hash_string("hello").unwrap());
+}
+
+#[test]
+fn test_hmac_hasher() {
+    use hashing::hmac::{hmac_bytes, hmac_string, supports, HmacHasher};
+    use hashing::HashError;
+
+    let data = "webhook payload ".repeat(100);
+    for algorithm in Algorithm::all() {
+        if !supports(algorithm) {
+            assert!(matches!(
+                HmacHasher::new(b"key", algorithm),
+                Err(HashError::UnsupportedAlgorithm(_))
+            ));
+            continue;
+        }
+        let expected = hmac_bytes(b"key", data.as_bytes(), algorithm).unwrap();
+        assert_eq!(hmac_string(b"key", &data, algorithm).unwrap(), expected);
+
+        let mut mac = HmacHasher::new(b"key", algorithm).unwrap();
+        for piece in data.as_bytes().chunks(7) {
+            mac.update(piece);
+        }
+        let digest = mac.finalize();
+        assert_eq!(digest.algorithm(), algorithm);
+        assert_eq!(digest.to_hex(), expected);
+    }
+
+    // RFC 4231 test case 2, written through io::Write
+    let mut mac = HmacHasher::new(b"Jefe", Algorithm::Sha512).unwrap();
+    std::io::copy(&mut &b"what do ya want for nothing?"[..], &mut mac).unwrap();
+    assert_eq!(
+        mac.finalize().to_hex(),
+        "164b7a7bfcf819e2e395fbe73b56e0a387bd64222e831fd610270cd7ea2505549758bf75c05a994a6d034f65f8f0e6fdcaeab1a34d4a6b4b636e070a38bce737"
+    );
+
+    let signature = hex::decode(hmac_string(b"key", "body", Algorithm::Sha256).unwrap()).unwrap();
+    let check = || {
+        let mut mac = HmacHasher::new(b"key", Algorithm::Sha256).unwrap();
+        mac.update(b"body");
+        mac
+    };
+    assert!(check().verify(&signature));
+    assert!(!check().verify(&signature[..16]));
+    let mut forged = signature.clone();
+    forged[31] ^= 1;
+    assert!(!check().verify(&forged));
+}
+
+#[cfg(feature = "perf")]
+#[test]
+fn test_measure_throughput() {
+    use hashing::perf::{fastest, measure_throughput, rank, Throughput};
+    use std::time::Duration;
+
+    let run = measure_throughput(Algorithm::Md5, 256 * 1024);
+    assert_eq!(run.algorithm, Algorithm::Md5);
+    assert_eq!(run.bytes, 256 * 1024);
+    assert!(run.bytes_per_second() > 0.0);
+    assert!(run.at_least(0.0));
+    assert!(!run.at_least(f64::INFINITY));
+
+    let candidates = [Algorithm::Sha256, Algorithm::Blake3, Algorithm::Sha3_512];
+    let ranked = rank(&candidates, 256 * 1024);
+    assert_eq!(ranked.len(), 3);
+    assert!(ranked
+        .windows(2)
+        .all(|pair| pair[0].bytes_per_second() >= pair[1].bytes_per_second()));
+    assert!(candidates.contains(&fastest(&candidates, 64 * 1024).unwrap().algorithm));
+    assert!(fastest(&[], 1024).is_none());
+
+    let empty = Throughput {
+        algorithm: Algorithm::Sha256,
+        bytes: 0,
+        elapsed: Duration::ZERO,
+    };
+    assert_eq!(empty.bytes_per_second(), 0.0);
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn test_mapped_blake3_files() -> Result<(), Box<dyn std::error::Error>> {
+    use hashing::mmap::{blake3_file, MMAP_THRESHOLD};
+    use hashing::{hash_bytes, Hashing};
+
+    // Several BLAKE3 chunks per thread, and a partial last chunk
+    let data: Vec<u8> = (0..3 * MMAP_THRESHOLD + 777).map(|i| (i % 251) as u8).collect();
+    let mut file = NamedTempFile::new()?;
+    file.write_all(&data)?;
+    file.flush()?;
+    let expected = hash_bytes(&data, Algorithm::Blake3)?;
+
+    assert_eq!(blake3_file(file.path())?.to_hex(), expected);
+    assert_eq!(hash_file(file.path(), Algorithm::Blake3)?, expected);
+    assert_eq!(Hashing::new().algorithm(Algorithm::Blake3).hash_file(file.path())?, expected);
+    #[cfg(feature = "rayon")]
+    {
+        use hashing::mmap::blake3_file_threads;
+
+        assert_eq!(blake3_file_threads(file.path(), 3)?.to_hex(), expected);
+        let digest = Hashing::new()
+            .algorithm(Algorithm::Blake3)
+            .threads(2)
+            .hash_file(file.path())?;
+        assert_eq!(digest, expected);
+    }
+
+    let mut small = NamedTempFile::new()?;
+    small.write_all(b"hello")?;
+    small.flush()?;
+    assert_eq!(blake3_file(small.path())?.to_hex(), hash_string("hello", Algorithm::Blake3)?);
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn test_async_hashing() -> Result<(), Box<dyn std::error::Error>> {
+    use hashing::{hash_bytes, hash_file_async, hash_reader_async, AsyncHasher};
+
+    let data: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+    let mut file = NamedTempFile::new()?;
+    file.write_all(&data)?;
+    file.flush()?;
+
+    let runtime = tokio::runtime::Builder::new_current_thread().build()?;
+    runtime.block_on(async {
+        let digest = hash_file_async(file.path(), Algorithm::Sha256).await?;
+        assert_eq!(digest, hash_bytes(&data, Algorithm::Sha256)?);
+        let digest = hash_reader_async(&data[..], Algorithm::Blake3).await?;
+        assert_eq!(digest, hash_bytes(&data, Algorithm::Blake3)?);
+
+        let mut hasher = AsyncHasher::new(Algorithm::Md5);
+        hasher.update(&data[..10]);
+        assert_eq!(hasher.update_reader(&data[10..]).await?, data.len() as u64 - 10);
+        assert_eq!(hasher.finalize().to_hex(), hash_bytes(&data, Algorithm::Md5)?);
+
+        let mut mac = AsyncHasher::hmac(b"key", Algorithm::Sha256)?;
+        mac.update_reader(&data[..]).await?;
+        let expected = hashing::hmac::hmac_bytes(b"key", &data, Algorithm::Sha256)?;
+        assert_eq!(mac.finalize().to_hex(), expected);
+        assert!(AsyncHasher::hmac(b"key", Algorithm::Blake3).is_err());
+        Ok::<(), hashing::HashError>(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn test_fastest_secure() {
+    use hashing::buildinfo::build_info;
+
+    let algorithm = Algorithm::fastest_secure();
+    assert_eq!(algorithm, Algorithm::fastest_secure());
+    // SHA-256 is only picked where it runs in hardware
+    let sha256_accelerated = build_info().backend(Algorithm::Sha256) != "portable"
+        || build_info().cpu_features.contains(&"sha2");
+    match algorithm {
+        Algorithm::Sha256 => assert!(sha256_accelerated),
+        other => assert_eq!(other, Algorithm::Blake3),
+    }
+}
+
+#[test]
+fn test_multi_hasher() {
+    use hashing::hmac::{hmac_bytes, supports};
+    use hashing::{HashError, MultiHasher};
+
+    // Several chunks, the last one partial
+    let data: Vec<u8> = (0..300_000u32).map(|i| (i % 251) as u8).collect();
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(&data).unwrap();
+
+    let algorithms = Algorithm::all();
+    for parallel in [false, true] {
+        let multi = MultiHasher::new(&algorithms).parallel(parallel);
+        assert_eq!(multi.algorithms(), &algorithms[..]);
+        let digests = multi.hash_file(file.path()).unwrap();
+        assert_eq!(digests.len(), algorithms.len());
+        for (digest, &algorithm) in digests.iter().zip(&algorithms) {
+            assert_eq!(digest.algorithm(), algorithm);
+            assert_eq!(digest.to_hex(), hash_file(file.path(), algorithm).unwrap());
+        }
+        assert_eq!(multi.hash_bytes(&data).unwrap(), digests);
+    }
+
+    let keyed: Vec<Algorithm> = algorithms.iter().copied().filter(|&a| supports(a)).collect();
+    let macs = MultiHasher::new(&keyed).key("key").parallel(true).hash_bytes(b"msg").unwrap();
+    for (mac, &algorithm) in macs.iter().zip(&keyed) {
+        assert_eq!(mac.to_hex(), hmac_bytes(b"key", b"msg", algorithm).unwrap());
+    }
+    assert!(matches!(
+        MultiHasher::new(&[Algorithm::Sha256, Algorithm::Blake3]).key("key").hash_bytes(b"msg"),
+        Err(HashError::UnsupportedAlgorithm(_))
+    ));
+    assert!(MultiHasher::new(&[]).hash_bytes(b"msg").unwrap().is_empty());
+
+    // A read error fails the whole hash, whichever mode reads
+    struct Broken;
+    impl std::io::Read for Broken {
+        fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::new(std::io::ErrorKind::ConnectionReset, "reset"))
+        }
+    }
+    for parallel in [false, true] {
+        let multi = MultiHasher::new(&algorithms).parallel(parallel);
+        assert!(matches!(multi.hash_reader(Broken), Err(HashError::Io(_))));
+    }
+}
+
+#[test]
+fn test_digest_parse_for() {
+    use hashing::checksum::{parse_line, validate_entries, verify_reader, CheckStatus};
+    use hashing::Digest;
+
+    let sha256 = hash_string("hello", Algorithm::Sha256).unwrap();
+    let digest = Digest::parse_for(&sha256, Algorithm::Sha256).unwrap();
+    assert_eq!(digest.algorithm(), Algorithm::Sha256);
+    assert_eq!(digest.to_hex(), sha256);
+    let pasted = format!("  0x{}\n", sha256.to_uppercase());
+    assert_eq!(Digest::parse_for(&pasted, Algorithm::Sha256).unwrap(), digest);
+
+    // A SHA-1 digest checked as SHA-256 names the likely algorithm
+    let sha1 = hash_string("hello", Algorithm::Sha1).unwrap();
+    let err = Digest::parse_for(&sha1, Algorithm::Sha256).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Invalid input: expected 64 hex characters for sha256, got 40 — did you mean sha1?"
+    );
+    // 64 hex characters fit several algorithms; the first listed is named
+    let err = Digest::parse_for(&sha256, Algorithm::Md5).unwrap_err();
+    assert!(err.to_string().contains("got 64 — did you mean sha256?"));
+    // No algorithm makes 3-character digests
+    let err = Digest::parse_for("abc", Algorithm::Md5).unwrap_err();
+    assert!(err.to_string().ends_with("expected 32 hex characters for md5, got 3"));
+    let bad = format!("{}g", &sha256[..63]);
+    let err = Digest::parse_for(&bad, Algorithm::Sha256).unwrap_err();
+    assert!(err.to_string().contains("invalid character 'g'"));
+
+    let line = format!("{}  hello.txt", sha1);
+    let entries = vec![parse_line(&line).unwrap()];
+    assert!(validate_entries(&entries, Algorithm::Sha1).is_ok());
+    let err = validate_entries(&entries, Algorithm::Sha256).unwrap_err();
+    assert!(err.to_string().contains("hello.txt: expected 64 hex characters"));
+
+    // The streaming verifier skips the offending line without hashing it,
+    // as `sha256sum -c` does, and still checks the rest
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("hello.txt"), "hello").unwrap();
+    let list = format!("{}  hello.txt\n{}\n{}\n", sha256, line, sha256);
+    let mut statuses = Vec::new();
+    let summary = verify_reader(list.as_bytes(), Algorithm::Sha256, dir.path(), 1, None, |e, s| {
+        statuses.push((e.line, s.clone()))
+    })
+    .unwrap();
+    assert_eq!(
+        statuses,
+        vec![(1, CheckStatus::Ok), (2, CheckStatus::Malformed), (3, CheckStatus::Malformed)]
+    );
+    assert_eq!((summary.ok, summary.malformed), (1, 2));
+    assert!(summary.is_ok());
+}
+
+#[test]
+fn test_checksum_bsd_lines() {
+    use hashing::checksum::{parse_line, tagged_algorithm, verify_reader, CheckStatus};
+
+    let sha512 = hash_string("hello", Algorithm::Sha512).unwrap();
+    let line = format!("SHA512 (my file (1).txt) = {}", sha512);
+    let entry = parse_line(&line).unwrap();
+    assert_eq!(entry.path, "my file (1).txt");
+    assert_eq!(entry.digest, sha512);
+    assert_eq!(tagged_algorithm(&line), Some(Algorithm::Sha512));
+    let blake2b = format!("BLAKE2b (a.txt) = {}", hash_string("", Algorithm::Blake2b512).unwrap());
+    assert_eq!(tagged_algorithm(&blake2b), Some(Algorithm::Blake2b512));
+    assert_eq!(tagged_algorithm("WHIRLPOOL (a.txt) = 00"), None);
+
+    // Coreutils lines whose path looks like a tag keep their meaning
+    let plain = format!("{}  SHA512 (x) = y", sha512);
+    assert_eq!(parse_line(&plain).unwrap().path, "SHA512 (x) = y");
+    assert_eq!(tagged_algorithm(&plain), None);
+
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("my file (1).txt"), "hello").unwrap();
+    std::fs::write(dir.path().join("b.txt"), "changed").unwrap();
+    let list = format!("{}\r\nSHA512 (b.txt) = {}\n", line, sha512);
+    let mut seen = Vec::new();
+    let summary = verify_reader(list.as_bytes(), Algorithm::Sha512, dir.path(), 2, None, |e, s| {
+        seen.push((e.path.to_string(), s.clone()))
+    })
+    .unwrap();
+    assert_eq!((summary.ok, summary.failed), (1, 1));
+    seen.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(seen[0], ("b.txt".to_string(), CheckStatus::Failed));
+    assert_eq!(seen[1], ("my file (1).txt".to_string(), CheckStatus::Ok));
+}
+
+#[test]
+fn test_walk_globs() {
+    use hashing::walk::{walk_files, Glob, WalkOptions};
+
+    let glob = |pattern: &str| Glob::new(pattern).unwrap();
+    assert!(glob("*.rs").matches("src/bin/hash.rs"));
+    assert!(!glob("*.rs").matches("src/lib.rsx"));
+    assert!(glob("src/*.rs").matches("src/lib.rs"));
+    assert!(!glob("src/*.rs").matches("src/bin/hash.rs"));
+    assert!(glob("src/**/*.rs").matches("src/lib.rs"));
+    assert!(glob("**/target").matches("a/b/target"));
+    assert!(glob("/target/").matches("target"));
+    assert!(!glob("/target").matches("crate/target"));
+    assert!(glob("file?.[ch]").matches("file1.c"));
+    assert!(!glob("file?.[!ch]").matches("file1.c"));
+    assert!(glob("[]x-]").matches("-"));
+    assert!(glob("\\*").matches("*"));
+    assert!(!glob("\\*").matches("a"));
+    assert!(Glob::new("[abc").is_err());
+    assert!(Glob::new("oops\\").is_err());
+
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    std::fs::create_dir_all(root.join("src/bin")).unwrap();
+    std::fs::create_dir_all(root.join("target/debug")).unwrap();
+    std::fs::write(root.join("src/lib.rs"), "lib").unwrap();
+    std::fs::write(root.join("src/bin/hash.rs"), "bin").unwrap();
+    std::fs::write(root.join("src/notes.txt"), "notes").unwrap();
+    std::fs::write(root.join("target/debug/build.rs"), "out").unwrap();
+    let walk = |options: &WalkOptions| {
+        let mut paths: Vec<String> = walk_files(root, options)
+            .map(|path| hashing::walk::relative_path(root, &path.unwrap()))
+            .collect();
+        paths.sort();
+        paths
+    };
+
+    // Includes keep directories open; excludes close them
+    let options = WalkOptions::new()
+        .with_include(glob("*.rs"))
+        .with_exclude(glob("target"));
+    assert_eq!(walk(&options), ["src/bin/hash.rs", "src/lib.rs"]);
+    let options = WalkOptions::new().with_exclude(glob("src/*"));
+    assert_eq!(walk(&options), ["target/debug/build.rs"]);
+}
+
+#[test]
+fn test_hash_dir_merkle_root() {
+    use hashing::tree::{merkle_root, FileDigest};
+    use hashing::walk::WalkOptions;
+    use hashing::{hash_bytes_raw, hash_dir, Sha2Digest};
+
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("docs")).unwrap();
+    std::fs::write(dir.path().join("b.txt"), "two").unwrap();
+    std::fs::write(dir.path().join("a.txt"), "one").unwrap();
+    std::fs::write(dir.path().join("docs/c.txt"), "three").unwrap();
+
+    let tree = hash_dir(dir.path(), Algorithm::Sha256, &WalkOptions::new()).unwrap();
+    let paths: Vec<&str> = tree.files.iter().map(|file| file.path.as_str()).collect();
+    assert_eq!(paths, ["a.txt", "b.txt", "docs/c.txt"]);
+    assert_eq!(tree.files[0].digest, hash_bytes_raw(b"one", Algorithm::Sha256).unwrap());
+
+    // RFC 6962 shape: the first two leaves pair up, the third joins at the top
+    let leaf = |path: &str, contents: &str| {
+        let mut hasher = sha2::Sha256::new();
+        hasher.update([0u8]);
+        hasher.update((path.len() as u64).to_be_bytes());
+        hasher.update(path);
+        hasher.update(sha2::Sha256::digest(contents));
+        hasher.finalize()
+    };
+    let node = |left: &[u8], right: &[u8]| {
+        let mut hasher = sha2::Sha256::new();
+        hasher.update([1u8]);
+        hasher.update(left);
+        hasher.update(right);
+        hasher.finalize()
+    };
+    let ab = node(&leaf("a.txt", "one"), &leaf("b.txt", "two"));
+    let expected = node(&ab, &leaf("docs/c.txt", "three"));
+    let root = tree.merkle_root().unwrap();
+    assert_eq!(root.as_bytes(), expected.as_slice());
+
+    // The same tree elsewhere has the same root; a renamed file does not
+    let copy = tempfile::tempdir().unwrap();
+    std::fs::create_dir(copy.path().join("docs")).unwrap();
+    std::fs::write(copy.path().join("a.txt"), "one").unwrap();
+    std::fs::write(copy.path().join("b.txt"), "two").unwrap();
+    std::fs::write(copy.path().join("docs/c.txt"), "three").unwrap();
+    let copied = hash_dir(copy.path(), Algorithm::Sha256, &WalkOptions::new()).unwrap();
+    assert_eq!(copied.merkle_root().unwrap(), root);
+    std::fs::rename(copy.path().join("b.txt"), copy.path().join("b2.txt")).unwrap();
+    let renamed = hash_dir(copy.path(), Algorithm::Sha256, &WalkOptions::new()).unwrap();
+    assert_ne!(renamed.merkle_root().unwrap(), root);
+
+    let single = FileDigest {
+        path: "a.txt".to_string(),
+        digest: hash_bytes_raw(b"one", Algorithm::Sha256).unwrap(),
+    };
+    let root = merkle_root(&[single], Algorithm::Sha256).unwrap();
+    assert_eq!(root.as_bytes(), leaf("a.txt", "one").as_slice());
+    let empty = merkle_root(&[], Algorithm::Sha256).unwrap();
+    assert_eq!(empty, hash_bytes_raw(b"", Algorithm::Sha256).unwrap());
+}
+
+#[test]
+fn test_strip_trailing_newline() {
+    use hashing::newline::{strip_trailing, strip_trailing_bytes, StripTrailingNewline};
+    use std::io::Read;
+
+    assert_eq!(strip_trailing("hello\n"), "hello");
+    assert_eq!(strip_trailing("hello\r\n"), "hello");
+    // Only the one line ending a shell or editor adds goes
+    assert_eq!(strip_trailing("hello\n\n"), "hello\n");
+    assert_eq!(strip_trailing("hello\r"), "hello");
+    assert_eq!(strip_trailing("hello\r\r"), "hello\r");
+    assert_eq!(strip_trailing("a\nb"), "a\nb");
+    assert_eq!(strip_trailing("\n"), "");
+    assert_eq!(strip_trailing_bytes(b"\xff\r\n"), b"\xff");
+
+    // A pipe handing over at most `.1` bytes per read
+    struct Trickle<'a>(&'a [u8], usize);
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let count = self.1.min(buf.len()).min(self.0.len());
+            buf[..count].copy_from_slice(&self.0[..count]);
+            self.0 = &self.0[count..];
+            Ok(count)
+        }
+    }
+
+    // The reader agrees with the slice version however the input is split
+    let read_all = |data: &[u8], chunk: usize| {
+        let mut out = Vec::new();
+        StripTrailingNewline::new(Trickle(data, chunk))
+            .read_to_end(&mut out)
+            .unwrap();
+        out
+    };
+    let cases: [&[u8]; 8] = [
+        b"", b"\n", b"\r\n", b"\r", b"a\r\nb\r\n", b"a\n\n", b"x\r\r\n", b"ab",
+    ];
+    for data in cases {
+        for chunk in 1..=3 {
+            assert_eq!(read_all(data, chunk), strip_trailing_bytes(data), "{:?}", data);
+        }
+    }
+    let large = vec![b'z'; 20_000];
+    let mut with_newline = large.clone();
+    with_newline.extend_from_slice(b"\r\n");
+    assert_eq!(read_all(&with_newline, 7000), large);
+}
+
+#[test]
+fn test_inspect_file() {
+    use hashing::inspect::{detect_type, inspect_file, inspect_reader};
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("archive.tar");
+    let mut data = vec![0u8; 1024];
+    data[..8].copy_from_slice(b"file.txt");
+    data[257..262].copy_from_slice(b"ustar");
+    data[1000..].fill(0xaa);
+    std::fs::write(&path, &data).unwrap();
+
+    let algorithms = [Algorithm::Md5, Algorithm::Blake3];
+    let report = inspect_file(&path, &algorithms, 32).unwrap();
+    assert_eq!(report.size, 1024);
+    assert_eq!(report.file_type, "tar archive");
+    assert_eq!(report.head, &data[..32]);
+    assert_eq!(report.tail, &data[992..]);
+    assert_eq!(report.tail_offset(), 992);
+    for (digest, algorithm) in report.digests.iter().zip(algorithms) {
+        assert_eq!(digest.to_hex(), hashing::hash_bytes(&data, algorithm).unwrap());
+    }
+
+    // A reader handing out a few bytes at a time keeps the same ends
+    struct Trickle<'a>(&'a [u8]);
+    impl std::io::Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let count = buf.len().min(7).min(self.0.len());
+            buf[..count].copy_from_slice(&self.0[..count]);
+            self.0 = &self.0[count..];
+            Ok(count)
+        }
+    }
+    assert_eq!(inspect_reader(Trickle(&data), &algorithms, 32).unwrap(), report);
+
+    // Shorter than the preview: both ends are the whole file
+    let short = inspect_reader(&b"#!/bin/sh\n"[..], &[], 64).unwrap();
+    assert_eq!((short.file_type, short.head.len()), ("script", 10));
+    assert_eq!(short.tail, short.head);
+    assert!(short.digests.is_empty());
+
+    assert_eq!(detect_type(b"!<arch>\ndebian-binary   "), "Debian package");
+    assert_eq!(detect_type(b"!<arch>\nlibfoo.o/"), "ar archive");
+    assert_eq!(detect_type(b"RIFF\0\0\0\0WEBPVP8 "), "WebP image");
+    assert_eq!(detect_type(&"é".as_bytes()[..1]), "UTF-8 text");
+}
+
+#[test]
+fn test_pbkdf2_and_sealed_manifests() {
+    use hashing::kdf::pbkdf2;
+    use hashing::seal::{is_sealed, open, SealWriter, MAGIC};
+    use std::io::Write;
+
+    // RFC 7914, section 11
+    let key = pbkdf2(b"passwd", b"salt", 1, Algorithm::Sha256, 64).unwrap();
+    assert_eq!(
+        hex::encode(key),
+        "55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc\
+         49ca9cccf179b645991664b39d77ef317c71b845b1e30bd509112041d3a19783"
+    );
+    let key = pbkdf2(b"password", b"NaCl", 1000, Algorithm::Sha512, 40).unwrap();
+    assert_eq!(hex::encode(&key[..8]), "6d3d85ab0b2085ce");
+    assert_eq!(key.len(), 40);
+    assert!(pbkdf2(b"p", b"s", 0, Algorithm::Sha256, 32).is_err());
+    assert!(pbkdf2(b"p", b"s", 1, Algorithm::Md5, 16).is_err());
+
+    let line = |i: usize| {
+        let digest = hash_string(&i.to_string(), Algorithm::Sha256).unwrap();
+        format!("{}  file{}.txt\n", digest, i)
+    };
+    let manifest: String = (0..200).map(line).collect();
+    let seal = |chunk: usize| {
+        let mut writer = SealWriter::with_iterations(Vec::new(), b"hunter2", 1000).unwrap();
+        for piece in manifest.as_bytes().chunks(chunk) {
+            writer.write_all(piece).unwrap();
+        }
+        writer.finish().unwrap()
+    };
+    let sealed = seal(7);
+    assert!(is_sealed(&sealed));
+    assert!(!sealed.windows(9).any(|w| w == b"file1.txt"));
+    assert_eq!(open(&sealed, b"hunter2").unwrap(), manifest.as_bytes());
+    // A fresh salt every time: the same manifest never seals the same way
+    let again = seal(4096);
+    assert_ne!(again, sealed);
+    assert_eq!(open(&again, b"hunter2").unwrap(), manifest.as_bytes());
+
+    assert!(open(&sealed, b"hunter3").is_err());
+    for position in [MAGIC.len() + 2, 40, sealed.len() / 2, sealed.len() - 1] {
+        let mut tampered = sealed.clone();
+        tampered[position] ^= 1;
+        assert!(open(&tampered, b"hunter2").is_err(), "byte {} changed", position);
+    }
+    assert!(open(&sealed[..sealed.len() - 1], b"hunter2").is_err());
+    assert!(open(manifest.as_bytes(), b"hunter2").is_err());
+    assert!(SealWriter::with_iterations(Vec::new(), b"", 1000).is_err());
+
+    // Larger plaintexts span several 64 KiB chunks, each with its own tag
+    for len in [0, 65_536, 65_537, 200_000] {
+        let data: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+        let mut writer = SealWriter::with_iterations(Vec::new(), b"hunter2", 1000).unwrap();
+        writer.write_all(&data).unwrap();
+        let sealed = writer.finish().unwrap();
+        assert_eq!(open(&sealed, b"hunter2").unwrap(), data, "{} bytes", len);
+        if len > 65_536 {
+            // Dropping the last chunk must not pass for a shorter manifest
+            let cut = sealed.len() - (len % 65_536 + 16);
+            assert!(open(&sealed[..cut], b"hunter2").is_err(), "{} bytes", len);
+        }
+    }
+}
+
+#[test]
+fn test_walk_files_recursive() -> Result<(), Box<dyn std::error::Error>> {
+    use hashing::walk::{walk_files, WalkOptions};
+
+    let dir = tempfile::tempdir()?;
+    std::fs::create_dir(dir.path().join("nested"))?;
+    std::fs::write(dir.path().join("a.txt"), b"a")?;
+    std::fs::write(dir.path().join("nested").join("b.txt"), b"b")?;
+
+    let mut files = walk_files(dir.path(), &WalkOptions::new())
+        .collect::<Result<Vec<_>, _>>()?;
+    files.sort();
+
+    assert_eq!(files.len(), 2);
+    assert!(files[0].ends_with("a.txt"));
+    assert!(files[1].ends_with("nested/b.txt"));
+    Ok(())
+}
+
+#[test]
+fn test_hash_result_json_line() -> Result<(), Box<dyn std::error::Error>> {
+    let result = HashResult::new(Algorithm::Sha256, "abcd1234".to_string(), "file")
+        .with_path("dir/file.txt");
+
+    let line = result.to_json_line()?;
+    assert!(!line.contains('\n'));
+
+    let parsed: HashResult = serde_json::from_str(&line)?;
+    assert_eq!(parsed.input_path.as_deref(), Some("dir/file.txt"));
+    Ok(())
+}
+
+#[test]
+fn test_journal_resume() -> Result<(), Box<dyn std::error::Error>> {
+    use hashing::journal::Journal;
+    use std::path::Path;
+
+    let dir = tempfile::tempdir()?;
+    let journal_path = dir.path().join("scan.journal");
+
+    {
+        let mut journal = Journal::open(&journal_path)?;
+        assert!(journal.is_empty());
+        journal.record(Path::new("data/a.bin"))?;
+        journal.record(Path::new("data/b.bin"))?;
+    }
+
+    let journal = Journal::open(&journal_path)?;
+    assert_eq!(journal.len(), 2);
+    assert!(journal.is_completed(Path::new("data/a.bin")));
+    assert!(!journal.is_completed(Path::new("data/c.bin")));
+
+    // Line breaks and backslashes in names survive the round trip
+    let odd = [
+        Path::new("data/two\nlines.bin"),
+        Path::new("data/two\nlines\r.bin"),
+        Path::new("data/back\\slash\\n.bin"),
+    ];
+    {
+        let mut journal = Journal::open(&journal_path)?;
+        for path in odd {
+            journal.record(path)?;
+        }
+    }
+    let journal = Journal::open(&journal_path)?;
+    assert_eq!(journal.len(), 5);
+    for path in odd {
+        assert!(journal.is_completed(path), "{:?}", path);
+    }
+    assert!(!journal.is_completed(Path::new("data/two")));
+
+    #[cfg(unix)]
+    {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let latin1 = Path::new(OsStr::from_bytes(b"data/caf\xe9.bin"));
+        Journal::open(&journal_path)?.record(latin1)?;
+        assert!(Journal::open(&journal_path)?.is_completed(latin1));
+    }
+
+    std::fs::write(&journal_path, "data/bad\\q\n")?;
+    assert!(Journal::open(&journal_path).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_walk_sorted_by_size() -> Result<(), Box<dyn std::error::Error>> {
+    use hashing::walk::{walk_files, SortOrder, WalkOptions};
+
+    let dir = tempfile::tempdir()?;
+    std::fs::write(dir.path().join("small"), b"1")?;
+    std::fs::write(dir.path().join("large"), b"1234567890")?;
+    std::fs::write(dir.path().join("b_medium"), b"12345")?;
+    std::fs::write(dir.path().join("a_medium"), b"12345")?;
+
+    let options = WalkOptions::new().with_sort(SortOrder::Size);
+    let names: Vec<String> = walk_files(dir.path(), &options)
+        .map(|p| p.unwrap().file_name().unwrap().to_string_lossy().into_owned())
+        .collect();
+
+    assert_eq!(names, vec!["large", "a_medium", "b_medium", "small"]);
+    assert!(SortOrder::from_str("bogus").is_err());
+    Ok(())
+}
+
+#[test]
+fn test_walk_depth_and_size_filters() -> Result<(), Box<dyn std::error::Error>> {
+    use hashing::walk::{parse_size, walk_files, WalkOptions};
+
+    let dir = tempfile::tempdir()?;
+    std::fs::create_dir(dir.path().join("nested"))?;
+    std::fs::write(dir.path().join("tiny"), b"1")?;
+    std::fs::write(dir.path().join("big"), vec![0u8; 2048])?;
+    std::fs::write(dir.path().join("nested").join("deep"), vec![0u8; 2048])?;
+
+    let shallow = WalkOptions::new().with_max_depth(1).with_min_size(parse_size("1K")?);
+    let files = walk_files(dir.path(), &shallow).collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(files.len(), 1);
+    assert!(files[0].ends_with("big"));
+
+    let small_only = WalkOptions::new().with_max_size(10);
+    let files = walk_files(dir.path(), &small_only).collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(files.len(), 1);
+    assert!(files[0].ends_with("tiny"));
+
+    assert_eq!(parse_size("1.5M")?, 1536 * 1024);
+    assert!(parse_size("12parsecs").is_err());
+    Ok(())
+}
+
+#[test]
+fn test_parse_age_range() {
+    use hashing::walk::parse_age;
+    use hashing::HashError;
+    use std::time::Duration;
+
+    assert_eq!(parse_age("2w").unwrap(), Duration::from_secs(14 * 24 * 60 * 60));
+    assert!(matches!(parse_age("300000000000000d"), Err(HashError::InvalidInput(_))));
+    assert!(matches!(parse_age("99999999999999999999"), Err(HashError::InvalidInput(_))));
+
+    // Ages that overflow, or reach back before the epoch, are usage errors
+    let dir = tempfile::tempdir().unwrap();
+    for (flag, age) in [
+        ("--newer-than", "300000000000000d"),
+        ("--newer-than", "200000000000000d"),
+        ("--timeout", "300000000000000d"),
+    ] {
+        let output = run_hash(dir.path(), &["-r", ".", flag, age]);
+        assert_eq!(output.status.code(), Some(2), "{} {}", flag, age);
+    }
+}
+
+#[test]
+fn test_walk_skip_paths() -> Result<(), Box<dyn std::error::Error>> {
+    use hashing::walk::{walk_files, WalkOptions};
+
+    let dir = tempfile::tempdir()?;
+    std::fs::create_dir(dir.path().join("cache"))?;
+    std::fs::write(dir.path().join("keep.txt"), b"keep")?;
+    std::fs::write(dir.path().join("cache").join("skip.txt"), b"skip")?;
+
+    let options = WalkOptions::new()
+        .with_one_file_system(true)
+        .with_skip_path(dir.path().join("cache"));
+    let files = walk_files(dir.path(), &options).collect::<Result<Vec<_>, _>>()?;
+
+    assert_eq!(files.len(), 1);
+    assert!(files[0].ends_with("keep.txt"));
+
+    // Relative skips are resolved against the root, not the working directory
+    for skip in ["cache", "./cache", "cache/"] {
+        let options = WalkOptions::new().with_skip_path(skip);
+        let files = walk_files(dir.path(), &options).collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(files, vec![dir.path().join("keep.txt")], "--skip {}", skip);
+    }
+    // A skip outside the walk leaves it alone
+    let options = WalkOptions::new().with_skip_path(dir.path().join("elsewhere"));
+    assert_eq!(walk_files(dir.path(), &options).count(), 2);
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_hard_link_tracking() -> Result<(), Box<dyn std::error::Error>> {
+    use hashing::walk::HardLinks;
+
+    let dir = tempfile::tempdir()?;
+    let original = dir.path().join("original");
+    let link = dir.path().join("link");
+    let other = dir.path().join("other");
+    std::fs::write(&original, b"shared content")?;
+    std::fs::hard_link(&original, &link)?;
+    std::fs::write(&other, b"shared content")?;
+
+    let mut links = HardLinks::new();
+    let digest = hash_file(&original, Algorithm::Sha256)?;
+    links.insert(&std::fs::metadata(&original)?, &original, &digest);
+
+    let (first, reused) = links.lookup(&std::fs::metadata(&link)?).unwrap();
+    assert_eq!(first, original.as_path());
+    assert_eq!(reused, digest);
+
+    // Identical content in a separate inode is not a hard link
+    assert!(links.lookup(&std::fs::metadata(&other)?).is_none());
+    Ok(())
+}
+
+#[test]
+fn test_hash_file_with_timeout() -> Result<(), Box<dyn std::error::Error>> {
+    use hashing::hash_file_with_timeout;
+    use std::time::Duration;
+
+    let mut temp_file = NamedTempFile::new()?;
+    temp_file.write_all(b"timeout test")?;
+    temp_file.flush()?;
+
+    let timeout = Duration::from_secs(10);
+    let digest = hash_file_with_timeout(temp_file.path(), Algorithm::Sha256, timeout)?;
+    assert_eq!(digest, hash_string("timeout test", Algorithm::Sha256)?);
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_hash_file_timeout_on_fifo() -> Result<(), Box<dyn std::error::Error>> {
+    use hashing::{hash_file_with_timeout, HashError};
+    use std::time::Duration;
+
+    let dir = tempfile::tempdir()?;
+    let fifo = dir.path().join("fifo");
+    let status = std::process::Command::new("mkfifo").arg(&fifo).status()?;
+    assert!(status.success());
+
+    // Opening a FIFO with no writer blocks forever
+    let result = hash_file_with_timeout(&fifo, Algorithm::Sha256, Duration::from_millis(100));
+    assert!(matches!(result, Err(HashError::Timeout(_))));
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_hash_file_timeout_stops_the_worker() -> Result<(), Box<dyn std::error::Error>> {
+    use hashing::{hash_file_with_timeout, HashError};
+    use std::io::Write;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    let dir = tempfile::tempdir()?;
+    let fifo = dir.path().join("fifo");
+    let status = std::process::Command::new("mkfifo").arg(&fifo).status()?;
+    assert!(status.success());
+
+    // A writer that never finishes, until nobody reads the FIFO any more
+    let (done, stopped) = mpsc::channel();
+    let path = fifo.clone();
+    std::thread::spawn(move || {
+        let mut writer = std::fs::OpenOptions::new().write(true).open(path).unwrap();
+        while writer.write_all(&[0; 4096]).is_ok() {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        done.send(()).unwrap();
+    });
+
+    let result = hash_file_with_timeout(&fifo, Algorithm::Sha256, Duration::from_millis(200));
+    assert!(matches!(result, Err(HashError::Timeout(_))));
+    // The abandoned worker stops reading and closes the FIFO
+    assert!(stopped.recv_timeout(Duration::from_secs(10)).is_ok());
+    Ok(())
+}
+
+#[test]
+fn test_checksum_line_parsing() {
+    use hashing::checksum::{parse_line, ChecksumEntry};
+
+    let entry = |digest: &str, path: &str| ChecksumEntry {
+        digest: digest.to_string(),
+        path: path.to_string(),
+    };
+
+    assert_eq!(parse_line("abcd  file.iso"), Some(entry("abcd", "file.iso")));
+    assert_eq!(parse_line("abcd *file.iso\r\n"), Some(entry("abcd", "file.iso")));
+    assert_eq!(parse_line("abcd  name with spaces"), Some(entry("abcd", "name with spaces")));
+    assert_eq!(parse_line("abcd"), Some(entry("abcd", "")));
+    assert_eq!(parse_line("# comment"), None);
+    assert_eq!(parse_line("   "), None);
+
+    // coreutils escapes names holding a backslash or a line break
+    assert_eq!(parse_line("\\abcd  we\\\\ird.txt"), Some(entry("abcd", "we\\ird.txt")));
+    assert_eq!(parse_line("\\abcd  new\\nline"), Some(entry("abcd", "new\nline")));
+    assert_eq!(parse_line("\\MD5 (a\\\\b) = abcd"), Some(entry("abcd", "a\\b")));
+    assert_eq!(parse_line("\\abcd  stray\\x"), Some(entry("abcd", "")));
+}
+
+/// Runs the `hash` binary in `dir` with `args`, feeding it nothing on stdin
+fn run_hash(dir: &std::path::Path, args: &[&str]) -> std::process::Output {
+    std::process::Command::new(env!("CARGO_BIN_EXE_hash"))
+        .args(args)
+        .current_dir(dir)
+        .stdin(std::process::Stdio::null())
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_check_reads_coreutils_lists() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("a.txt"), "x").unwrap();
+    std::fs::write(dir.path().join("b c.txt"), "y").unwrap();
+
+    // `sha256sum a.txt 'b c.txt'` and `sha256sum --tag` from coreutils 9.1
+    let plain = "2d711642b726b04401627ca9fbac32f5c8530fb1903cc4db02258717921a4881  a.txt\n\
+                 a1fce4363854ff888cff4b8e7875d600c2682390412a8cf79b37d0b11148b0fa  b c.txt\n";
+    let tagged = "SHA256 (a.txt) = \
+                  2d711642b726b04401627ca9fbac32f5c8530fb1903cc4db02258717921a4881\n\
+                  SHA256 (b c.txt) = \
+                  a1fce4363854ff888cff4b8e7875d600c2682390412a8cf79b37d0b11148b0fa\n";
+    for list in [plain, tagged] {
+        std::fs::write(dir.path().join("SUMS"), list).unwrap();
+        let output = run_hash(dir.path(), &["--check", "SUMS"]);
+        assert_eq!(output.status.code(), Some(0));
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "a.txt: OK\nb c.txt: OK\n");
+    }
+
+    // `hash check` takes several lists, like `sha256sum -c`
+    std::fs::write(dir.path().join("PLAIN"), plain).unwrap();
+    let output = run_hash(dir.path(), &["check", "PLAIN", "SUMS"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8_lossy(&output.stdout).lines().count(), 4);
+
+    std::fs::write(dir.path().join("b c.txt"), "changed").unwrap();
+    let output = run_hash(dir.path(), &["check", "PLAIN", "SUMS"]);
+    assert_eq!(output.status.code(), Some(1));
+    let output = run_hash(dir.path(), &["--check", "SUMS"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "a.txt: OK\nb c.txt: FAILED\n");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("1 computed checksum did NOT match"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_check_reads_escaped_names() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("we\\ird.txt"), "x").unwrap();
+    std::fs::write(dir.path().join("new\nline"), "y").unwrap();
+
+    // `sha256sum 'we\ird.txt' 'new<LF>line'` from coreutils 9.1
+    let list = "\\2d711642b726b04401627ca9fbac32f5c8530fb1903cc4db02258717921a4881  we\\\\ird.txt\n\
+                \\a1fce4363854ff888cff4b8e7875d600c2682390412a8cf79b37d0b11148b0fa  new\\nline\n";
+    std::fs::write(dir.path().join("SUMS"), list).unwrap();
+    let output = run_hash(dir.path(), &["--check", "SUMS"]);
+    assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stderr));
+    // As coreutils prints them: only the name with a line break is escaped
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout, "we\\ird.txt: OK\n\\new\\nline: OK\n");
+}
+
+#[test]
+fn test_check_skips_improperly_formatted_lines() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("a.txt"), "x").unwrap();
+    let list = "2d711642b726b04401627ca9fbac32f5c8530fb1903cc4db02258717921a4881  a.txt\n\
+                garbage line\n";
+    std::fs::write(dir.path().join("SUMS"), list).unwrap();
+
+    let output = run_hash(dir.path(), &["--check", "SUMS"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "a.txt: OK\n");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("1 line is improperly formatted"));
+    assert!(!stderr.contains("SUMS: 2"));
+
+    let output = run_hash(dir.path(), &["--check", "SUMS", "--strict", "--warn"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("SUMS: 2: improperly formatted SHA256 checksum line"));
+
+    std::fs::write(dir.path().join("SUMS"), "garbage line\n").unwrap();
+    let output = run_hash(dir.path(), &["--check", "SUMS"]);
+    assert_ne!(output.status.code(), Some(0));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("no properly formatted checksum lines found"));
+}
+
+#[test]
+fn test_expected_digest_from_checksum_file() -> Result<(), Box<dyn std::error::Error>> {
+    use hashing::checksum::{expected_digest, read_source};
+
+    let dir = tempfile::tempdir()?;
+    let sums = dir.path().join("SHA256SUMS");
+    std::fs::write(&sums, "1111  app-linux.tar.gz\n2222 *app-windows.zip\n")?;
+
+    let contents = read_source(sums.to_str().unwrap())?;
+    assert_eq!(expected_digest(&contents, "dist/app-windows.zip")?, "2222");
+    assert!(expected_digest(&contents, "app-macos.dmg").is_err());
+    Ok(())
+}
+
+#[test]
+fn test_normalize_expected_digest() {
+    use hashing::checksum::{digests_match, normalize_expected};
+
+    let digest = hash_string("hello", Algorithm::Sha256).unwrap();
+    let pasted = [
+        format!("  {}\n", digest),
+        digest.to_uppercase(),
+        format!("0x{}", digest),
+        format!("sha256:{}", digest),
+        format!("SHA-256={}", digest.to_uppercase()),
+    ];
+    for expected in &pasted {
+        assert!(digests_match(expected, &digest), "failed to match {:?}", expected);
+    }
+
+    assert_eq!(normalize_expected("AB:CD:EF").hex, "abcdef");
+    assert_eq!(normalize_expected("md5:abcd").algorithm, Some(Algorithm::Md5));
+    assert!(!digests_match("deadbeef", &digest));
+}
+
+#[test]
+fn test_hmac_known_vectors() {
+    use hashing::hmac::{hmac_bytes, hmac_file};
+
+    // RFC 2202 / RFC 4231 test case 2
+    let key = b"Jefe";
+    let data = b"what do ya want for nothing?";
+    assert_eq!(
+        hmac_bytes(key, data, Algorithm::Md5).unwrap(),
+        "750c783e6ab0b503eaa86e310a5db738"
+    );
+    assert_eq!(
+        hmac_bytes(key, data, Algorithm::Sha1).unwrap(),
+        "effcdf6ae5eb2fa2d27416d5f184df9c259a7c79"
+    );
+    assert_eq!(
+        hmac_bytes(key, data, Algorithm::Sha256).unwrap(),
+        "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+    );
+
+    // Keys longer than the block size are hashed first
+    let long_key = [0xaa; 131];
+    let long_data = b"Test Using Larger Than Block-Size Key - Hash Key First";
+    assert_eq!(
+        hmac_bytes(&long_key, long_data, Algorithm::Sha256).unwrap(),
+        "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+    );
+    assert_eq!(
+        hmac_bytes(&long_key[..80], long_data, Algorithm::Sha1).unwrap(),
+        "aa4ae5e15272d00e95705637ce8a3b55ed402112"
+    );
+
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(data).unwrap();
+    file.flush().unwrap();
+    assert_eq!(
+        hmac_file(key, file.path(), Algorithm::Sha256).unwrap(),
+        hmac_bytes(key, data, Algorithm::Sha256).unwrap()
+    );
+
+    assert!(hmac_bytes(key, data, Algorithm::Blake3).is_err());
+}
+
+#[test]
+fn test_key_sources() -> Result<(), Box<dyn std::error::Error>> {
+    use hashing::key::KeySource;
+
+    let dir = tempfile::tempdir()?;
+    let key_path = dir.path().join("hmac.key");
+    std::fs::write(&key_path, b"secret\n")?;
+
+    // Key files are used verbatim, trailing newline included
+    let source = KeySource::File(key_path.clone());
+    assert_eq!(source.load()?, b"secret\n");
+
+    assert_eq!(KeySource::Hex("DEADbeef".to_string()).load()?, [0xde, 0xad, 0xbe, 0xef]);
+    assert!(KeySource::Hex("xyz".to_string()).load().is_err());
+    assert!(KeySource::Env("HASHING_TEST_UNSET_KEY".to_string()).load().is_err());
+    assert_eq!(KeySource::Text("secret".to_string()).load()?, b"secret");
+    assert!(KeySource::Text(String::new()).load().is_err());
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        std::fs::set_permissions(&key_path, std::fs::Permissions::from_mode(0o644))?;
+        assert!(source.permission_warning().is_some());
+        std::fs::set_permissions(&key_path, std::fs::Permissions::from_mode(0o600))?;
+        assert!(source.permission_warning().is_none());
+    }
+    Ok(())
+}
+
+#[test]
+fn test_hashing_builder() {
+    use hashing::{hmac::hmac_bytes, Encoding, Hashing};
+
+    let data = vec![7u8; 100_000];
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(&data).unwrap();
+    file.flush().unwrap();
+
+    for algorithm in Algorithm::all() {
+        let digest = Hashing::new()
+            .algorithm(algorithm)
+            .buffer_size(1000)
+            .hash_file(file.path())
+            .unwrap();
+        assert_eq!(digest, hash_file(file.path(), algorithm).unwrap(), "{:?}", algorithm);
+    }
+
+    let mut reported = Vec::new();
+    let digest = Hashing::new()
+        .algorithm(Algorithm::Blake3)
+        .encoding(Encoding::HexUpper)
+        .buffer_size(1 << 15)
+        .progress(|bytes| reported.push(bytes))
+        .hash_file(file.path())
+        .unwrap();
+    assert_eq!(digest, hash_file(file.path(), Algorithm::Blake3).unwrap().to_uppercase());
+    assert_eq!(reported.last(), Some(&(data.len() as u64)));
+    assert!(reported.windows(2).all(|w| w[0] < w[1]));
+
+    let mac = Hashing::new().key(b"Jefe".to_vec()).hash_bytes(b"payload").unwrap();
+    assert_eq!(mac, hmac_bytes(b"Jefe", b"payload", Algorithm::Sha256).unwrap());
+}
+
+#[test]
+fn test_fixed_size_digests() {
+    use hashing::fixed::{self, hash_bytes_fixed, FixedDigest};
+
+    fn check<A: FixedDigest>(data: &[u8]) {
+        let fixed = hash_bytes_fixed::<A>(data);
+        let expected = hashing::hash_bytes(data, A::ALGORITHM).unwrap();
+        assert_eq!(hex::encode(fixed), expected, "{:?}", A::ALGORITHM);
+    }
+
+    let data = b"fixed size digests";
+    check::<fixed::Md5>(data);
+    check::<fixed::Sha1>(data);
+    check::<fixed::Sha256>(data);
+    check::<fixed::Sha512_224>(data);
+    check::<fixed::Sha3_384>(data);
+    check::<fixed::Blake2b512>(data);
+    check::<fixed::Blake3>(data);
+    check::<fixed::Keccak256>(data);
+
+    let digest: [u8; 64] = hash_bytes_fixed::<fixed::Sha512>(data);
+    assert_eq!(digest.len(), 64);
+}
+
+#[test]
+fn test_alias_registry_and_config() {
+    use hashing::config::Config;
+
+    for name in ["sha-256", "SHA_256", "SHA2-256", "Sha256"] {
+        assert_eq!(Algorithm::from_str(name).unwrap(), Algorithm::Sha256, "{}", name);
+    }
+    assert_eq!(Algorithm::from_str("b3").unwrap(), Algorithm::Blake3);
+
+    let config = Config::from_toml("[aliases]\nfast = \"b3\"\nLegacy = \"md5\"\n").unwrap();
+    let registry = config.alias_registry().unwrap();
+    assert_eq!(registry.resolve("fast").unwrap(), Algorithm::Blake3);
+    assert_eq!(registry.resolve("legacy").unwrap(), Algorithm::Md5);
+    assert_eq!(registry.resolve("sha-1").unwrap(), Algorithm::Sha1);
+    assert!(registry.resolve("slow").is_err());
+
+    // User aliases cannot redefine built-in names or point nowhere
+    let shadowing = Config::from_toml("[aliases]\nsha256 = \"md5\"\n").unwrap();
+    assert!(shadowing.alias_registry().is_err());
+    let dangling = Config::from_toml("[aliases]\nx = \"sha9\"\n").unwrap();
+    assert!(dangling.alias_registry().is_err());
+    assert!(Config::from_toml("[aliases\n").is_err());
+
+    let config = Config::from_toml("[trust.keys]\nusb = \"/media/usb/baseline.pub\"\n").unwrap();
+    assert_eq!(config.trust.keys["usb"], std::path::Path::new("/media/usb/baseline.pub"));
+    assert!(Config::default().trust.keys.is_empty());
+}
+
+#[test]
+fn test_combine_digests() {
+    use hashing::combine::{combine_ordered, combine_unordered};
+
+    let digests: Vec<Vec<u8>> = ["a", "b", "c"]
+        .iter()
+        .map(|s| hex::decode(hash_string(s, Algorithm::Sha256).unwrap()).unwrap())
+        .collect();
+    let reversed: Vec<Vec<u8>> = digests.iter().rev().cloned().collect();
+
+    let ordered = combine_ordered(&digests, Algorithm::Sha256).unwrap();
+    assert_eq!(ordered.len(), 32);
+    assert_eq!(ordered, combine_ordered(&digests, Algorithm::Sha256).unwrap());
+    assert_ne!(ordered, combine_ordered(&reversed, Algorithm::Sha256).unwrap());
+    assert_eq!(combine_ordered(&digests, Algorithm::Blake3).unwrap().len(), 32);
+
+    // Moving a byte between neighbours must change the result
+    let split_a = [vec![1u8, 2], vec![3u8]];
+    let split_b = [vec![1u8], vec![2u8, 3]];
+    assert_ne!(
+        combine_ordered(&split_a, Algorithm::Sha256).unwrap(),
+        combine_ordered(&split_b, Algorithm::Sha256).unwrap()
+    );
+
+    let unordered = combine_unordered(&digests).unwrap();
+    assert_eq!(unordered, combine_unordered(&reversed).unwrap());
+    assert!(combine_unordered::<Vec<u8>>(&[]).is_err());
+    assert!(combine_unordered(&[vec![0u8; 32], vec![0u8; 20]]).is_err());
+}
+
+#[test]
+fn test_hash_set_is_order_independent() {
+    use hashing::hash_set;
+
+    let rows = vec![b"alice,42".to_vec(), b"bob,17".to_vec(), b"carol,99".to_vec()];
+    let mut shuffled = rows.clone();
+    shuffled.rotate_left(1);
+
+    let digest = hash_set(&rows, Algorithm::Sha256).unwrap();
+    assert_eq!(digest.len(), 64);
+    assert_eq!(digest, hash_set(&shuffled, Algorithm::Sha256).unwrap());
+
+    // Multiset semantics: duplicates and membership both matter
+    let pair = hash_set(["a", "a"], Algorithm::Sha256).unwrap();
+    assert_ne!(pair, hash_set(["a"], Algorithm::Sha256).unwrap());
+    assert_ne!(digest, hash_set(&rows[..2], Algorithm::Sha256).unwrap());
+    assert_ne!(digest, hash_set(&rows, Algorithm::Blake3).unwrap());
+    assert_eq!(hash_set(Vec::<&str>::new(), Algorithm::Sha256).unwrap().len(), 64);
+}
+
+#[cfg(feature = "serde-hash")]
+#[test]
+fn test_hash_serialize_canonical() {
+    use hashing::canonical::{hash_serialize, to_canonical_bytes, CanonicalFormat};
+    use std::collections::BTreeMap;
+
+    #[derive(serde::Serialize)]
+    struct Record {
+        name: &'static str,
+        tags: Vec<&'static str>,
+        score: f64,
+        count: u32,
+    }
+
+    #[derive(serde::Serialize)]
+    struct Reordered {
+        count: u32,
+        score: f64,
+        tags: Vec<&'static str>,
+        name: &'static str,
+    }
+
+    let record = Record { name: "é", tags: vec!["x"], score: 1.5, count: 1000 };
+    let reordered = Reordered { count: 1000, score: 1.5, tags: vec!["x"], name: "é" };
+
+    let json = to_canonical_bytes(&record, CanonicalFormat::Json).unwrap();
+    assert_eq!(
+        String::from_utf8(json).unwrap(),
+        r#"{"count":1000,"name":"é","score":1.5,"tags":["x"]}"#
+    );
+
+    for format in [CanonicalFormat::Json, CanonicalFormat::Cbor] {
+        assert_eq!(
+            hash_serialize(&record, Algorithm::Sha256, format).unwrap(),
+            hash_serialize(&reordered, Algorithm::Sha256, format).unwrap()
+        );
+    }
+
+    // CBOR map keys are ordered by their encoding: shorter keys first
+    let mut map = BTreeMap::new();
+    map.insert("aa", 1u8);
+    map.insert("b", 2u8);
+    let cbor = to_canonical_bytes(&map, CanonicalFormat::Cbor).unwrap();
+    assert_eq!(cbor, [0xa2, 0x61, b'b', 0x02, 0x62, b'a', b'a', 0x01]);
+}
+
+#[cfg(feature = "serde-hash")]
+#[test]
+fn test_canonical_documents_across_formats() {
+    use hashing::canonical::{canonicalize_document, hash_document, CanonicalFormat};
+
+    let json = br#"{"name": "svc", "ports": [80, 443], "ratio": 0.25, "meta": {"z": true, "a": null}}"#;
+    let reordered = br#"{"meta": {"a": null, "z": true}, "ratio": 0.25, "ports": [80, 443], "name": "svc"}"#;
+
+    for format in [CanonicalFormat::Json, CanonicalFormat::Cbor, CanonicalFormat::MessagePack] {
+        let a = hash_document(json, CanonicalFormat::Json, format, Algorithm::Sha256).unwrap();
+        let b = hash_document(reordered, CanonicalFormat::Json, format, Algorithm::Sha256).unwrap();
+        assert_eq!(a, b, "{:?}", format);
+
+        // Round-tripping through the binary format yields the same canonical bytes
+        let encoded = canonicalize_document(json, CanonicalFormat::Json, format).unwrap();
+        let again = canonicalize_document(&encoded, format, format).unwrap();
+        assert_eq!(encoded, again, "{:?}", format);
+    }
+
+    // A CBOR document and its JSON twin hash identically under canonical JSON
+    let cbor = canonicalize_document(json, CanonicalFormat::Json, CanonicalFormat::Cbor).unwrap();
+    assert_eq!(
+        hash_document(&cbor, CanonicalFormat::Cbor, CanonicalFormat::Json, Algorithm::Sha256).unwrap(),
+        hash_document(json, CanonicalFormat::Json, CanonicalFormat::Json, Algorithm::Sha256).unwrap()
+    );
+
+    // MessagePack uses the shortest integer encoding and sorted keys
+    let msgpack = canonicalize_document(
+        br#"{"b": 1, "a": 300}"#,
+        CanonicalFormat::Json,
+        CanonicalFormat::MessagePack,
+    )
+    .unwrap();
+    assert_eq!(msgpack, [0x82, 0xa1, b'a', 0xcd, 0x01, 0x2c, 0xa1, b'b', 0x01]);
+
+    let invalid = canonicalize_document(b"{not json", CanonicalFormat::Json, CanonicalFormat::Json);
+    assert!(invalid.is_err());
+}
+
+#[test]
+fn test_hash_csv_columns() {
+    use hashing::hmac::hmac_bytes;
+    use hashing::tabular::{hash_csv_columns, ColumnSelector, CsvHashOptions};
+
+    let input = "id,email,plan\n1,ann@example.com,pro\n2,,free\n3,\"bob, jr@example.com\",pro\n";
+    let run = |options: &CsvHashOptions| {
+        let mut output = Vec::new();
+        let rows = hash_csv_columns(input.as_bytes(), &mut output, options).unwrap();
+        (rows, String::from_utf8(output).unwrap())
+    };
+
+    let options = CsvHashOptions::new(Algorithm::Sha256)
+        .with_column("email".parse().unwrap())
+        .with_column("1".parse().unwrap());
+    let (rows, output) = run(&options);
+    assert_eq!(rows, 3);
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines[0], "id,email,plan");
+    assert_eq!(
+        lines[1],
+        format!(
+            "{},{},pro",
+            hash_string("1", Algorithm::Sha256).unwrap(),
+            hash_string("ann@example.com", Algorithm::Sha256).unwrap()
+        )
+    );
+    // Empty cells are kept, quoted fields are hashed unquoted
+    assert!(lines[2].ends_with(",,free"));
+    assert!(lines[3].contains(&hash_string("bob, jr@example.com", Algorithm::Sha256).unwrap()));
+
+    // A salt keys every digest
+    let salted = options.clone().with_salt(b"pepper".to_vec());
+    let (_, output) = run(&salted);
+    let mac = hmac_bytes(b"pepper", b"ann@example.com", Algorithm::Sha256).unwrap();
+    assert!(output.lines().nth(1).unwrap().contains(&mac));
+
+    // TSV without a header, by position only
+    let tsv = CsvHashOptions::new(Algorithm::Md5)
+        .with_delimiter(b'\t')
+        .with_headers(false)
+        .with_column(ColumnSelector::Index(1));
+    let mut output = Vec::new();
+    hash_csv_columns("a\tb\n".as_bytes(), &mut output, &tsv).unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        format!("a\t{}\n", hash_string("b", Algorithm::Md5).unwrap())
+    );
+
+    let by_name = tsv.with_column(ColumnSelector::Name("b".to_string()));
+    assert!(hash_csv_columns("a\tb\n".as_bytes(), Vec::new(), &by_name).is_err());
+    let missing = CsvHashOptions::new(Algorithm::Sha256).with_column("phone".parse().unwrap());
+    assert!(hash_csv_columns(input.as_bytes(), Vec::new(), &missing).is_err());
+    assert!("0".parse::<ColumnSelector>().is_err());
+}
+
+#[test]
+fn test_pseudonymize() {
+    use hashing::hmac::hmac_bytes;
+    use hashing::pseudonym::{pseudonymize, Pseudonymizer};
+
+    for algorithm in Algorithm::all() {
+        let digest = hashing::hash_bytes(b"size", algorithm).unwrap();
+        assert_eq!(digest.len(), algorithm.output_size() * 2, "{:?}", algorithm);
+    }
+
+    let mac = hmac_bytes(b"salt", b"ann@example.com", Algorithm::Sha256).unwrap();
+    let token = pseudonymize("ann@example.com", b"salt", Algorithm::Sha256, 20).unwrap();
+    assert_eq!(token, mac[..20]);
+    assert_ne!(token, pseudonymize("ann@example.com", b"pepper", Algorithm::Sha256, 20).unwrap());
+
+    let tokens = Pseudonymizer::new(b"salt".to_vec(), Algorithm::Sha256, 64).unwrap();
+    let batch = tokens.tokens(["ann@example.com", "bob@example.com"]).unwrap();
+    assert_eq!(batch[0], mac);
+    assert_eq!(batch[1], tokens.token("bob@example.com").unwrap());
+
+    let mut output = Vec::new();
+    let count = tokens
+        .tokenize_lines("ann@example.com\r\n\nbob@example.com".as_bytes(), &mut output)
+        .unwrap();
+    assert_eq!(count, 2);
+    assert_eq!(String::from_utf8(output).unwrap(), format!("{}\n\n{}\n", batch[0], batch[1]));
+
+    assert!(Pseudonymizer::new(Vec::new(), Algorithm::Sha256, 16).is_err());
+    assert!(Pseudonymizer::new(b"salt".to_vec(), Algorithm::Blake3, 16).is_err());
+    assert!(Pseudonymizer::new(b"salt".to_vec(), Algorithm::Md5, 0).is_err());
+    assert!(Pseudonymizer::new(b"salt".to_vec(), Algorithm::Md5, 33).is_err());
+    assert!(!format!("{:?}", tokens).contains("salt"));
+}
+
+#[test]
+fn test_hash_ring() {
+    use hashing::ring::HashRing;
+
+    let mut ring = HashRing::new(Algorithm::Sha256).with_virtual_nodes(100);
+    assert!(ring.node_for("key").is_none());
+    for node in ["a", "b", "c", "d"] {
+        assert!(ring.add(node.to_string()));
+    }
+    assert!(!ring.add("a".to_string()));
+    assert_eq!(ring.len(), 4);
+
+    // Placement is deterministic and independent of insertion order
+    let mut reversed = HashRing::new(Algorithm::Sha256).with_virtual_nodes(100);
+    for node in ["d", "c", "b", "a"] {
+        reversed.add(node.to_string());
+    }
+    for i in 0..200 {
+        let key = format!("user:{}", i);
+        assert_eq!(ring.node_for(&key), reversed.node_for(&key));
+    }
+
+    let shares = ring.ownership();
+    assert!((shares.values().sum::<f64>() - 1.0).abs() < 1e-9);
+    assert!(shares.values().all(|&share| share > 0.1 && share < 0.4));
+
+    // Adding a fifth node only moves keys onto that node
+    let mut grown = ring.clone();
+    grown.add("e".to_string());
+    let stats = ring.rebalance_stats(&grown);
+    assert!((stats.moved - stats.after["e"]).abs() < 1e-9);
+    assert!(stats.moved > 0.1 && stats.moved < 0.3);
+    for i in 0..200 {
+        let key = format!("user:{}", i);
+        let owner = grown.node_for(&key).unwrap();
+        assert!(owner == "e" || owner == ring.node_for(&key).unwrap());
+    }
+
+    assert!(grown.remove(&"e".to_string()));
+    assert_eq!(ring.rebalance_stats(&grown).moved, 0.0);
+
+    let mut single = HashRing::new(Algorithm::Blake3).with_virtual_nodes(1);
+    single.add(7u32);
+    assert_eq!(single.node_for("anything"), Some(&7));
+    assert_eq!(single.ownership()[&7], 1.0);
+}
+
+#[test]
+fn test_bloom_filter_and_hyperloglog() {
+    use hashing::probabilistic::{BloomFilter, HyperLogLog};
+
+    let mut filter = BloomFilter::new(1000, 0.01).unwrap();
+    assert_eq!(filter.hash_count(), 7);
+    for i in 0..1000 {
+        filter.insert(format!("member-{}", i));
+    }
+    assert!((0..1000).all(|i| filter.contains(format!("member-{}", i))));
+    let false_positives = (0..10_000)
+        .filter(|i| filter.contains(format!("other-{}", i)))
+        .count();
+    assert!(false_positives < 300, "{} false positives", false_positives);
+    assert!(filter.false_positive_rate() < 0.03);
+
+    let mut other = BloomFilter::new(1000, 0.01).unwrap();
+    other.insert("extra");
+    filter.union(&other).unwrap();
+    assert!(filter.contains("extra"));
+    let sha = BloomFilter::new(1000, 0.01).unwrap().with_algorithm(Algorithm::Sha256);
+    assert!(filter.union(&sha).is_err());
+    assert!(BloomFilter::new(0, 0.01).is_err());
+    assert!(BloomFilter::new(10, 1.5).is_err());
+
+    let mut a = HyperLogLog::new(12).unwrap();
+    let mut b = HyperLogLog::new(12).unwrap();
+    assert_eq!(a.count(), 0);
+    for i in 0..50_000 {
+        a.insert(format!("{}", i));
+        b.insert(format!("{}", i + 25_000));
+    }
+    let estimate = a.count() as f64;
+    assert!((estimate - 50_000.0).abs() / 50_000.0 < 0.05, "{}", estimate);
+    a.merge(&b).unwrap();
+    let merged = a.count() as f64;
+    assert!((merged - 75_000.0).abs() / 75_000.0 < 0.05, "{}", merged);
+
+    let mut small = HyperLogLog::new(10).unwrap().with_algorithm(Algorithm::Sha256);
+    for i in 0..100 {
+        small.insert([i as u8]);
+        small.insert([i as u8]);
+    }
+    assert!((95..=105).contains(&small.count()));
+    assert!(a.merge(&small).is_err());
+    assert!(HyperLogLog::new(3).is_err());
+}
+
+#[test]
+fn test_minhash_and_simhash() {
+    use hashing::similarity::{
+        char_shingles, simhash, simhash_weighted, word_shingles, MinHasher,
+    };
+
+    assert_eq!(word_shingles("a  b\tc d", 3), ["a b c", "b c d"]);
+    assert_eq!(word_shingles("short text", 3), ["short text"]);
+    assert!(word_shingles("   ", 2).is_empty());
+    assert_eq!(char_shingles("héllo", 4), ["héll", "éllo"]);
+
+    let base: String = (0..200).map(|i| format!("word{} ", i)).collect();
+    let edited = base.replace("word100 ", "changed ");
+    let unrelated: String = (0..200).map(|i| format!("other{} ", i)).collect();
+
+    let minhash = MinHasher::new(256).unwrap();
+    let a = minhash.signature(word_shingles(&base, 3));
+    let b = minhash.signature(word_shingles(&edited, 3));
+    let c = minhash.signature(word_shingles(&unrelated, 3));
+    assert_eq!(a.similarity(&a).unwrap(), 1.0);
+    // True Jaccard similarity is 195/201
+    assert!(a.similarity(&b).unwrap() > 0.9);
+    assert!(a.similarity(&c).unwrap() < 0.05);
+    assert_eq!(a, minhash.signature(word_shingles(&base, 3)));
+
+    let other = MinHasher::new(256).unwrap().with_algorithm(Algorithm::Sha256);
+    assert!(a.similarity(&other.signature(word_shingles(&base, 3))).is_err());
+    assert!(MinHasher::new(0).is_err());
+
+    let a = simhash(word_shingles(&base, 3), Algorithm::Blake3);
+    let b = simhash(word_shingles(&edited, 3), Algorithm::Blake3);
+    let c = simhash(word_shingles(&unrelated, 3), Algorithm::Blake3);
+    assert!(a.hamming_distance(b) < a.hamming_distance(c));
+    assert!(a.similarity(b) > 0.85);
+    assert_eq!(a.to_string().len(), 16);
+
+    // A dominant weight decides the fingerprint
+    let heavy = simhash_weighted([("x", 100.0), ("y", 1.0)], Algorithm::Blake3);
+    assert_eq!(heavy, simhash(["x"], Algorithm::Blake3));
+}
+
+#[test]
+fn test_delta_signatures() {
+    use hashing::delta::{DeltaOp, Signature, SignatureFormat, Signer};
+
+    // Pseudo-random bytes, so no block repeats by chance
+    let mut state = 1u32;
+    let old: Vec<u8> = (0..10_000)
+        .map(|_| {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            (state >> 16) as u8
+        })
+        .collect();
+    // Insert, delete and change bytes away from block boundaries
+    let mut new = old[..1000].to_vec();
+    new.extend_from_slice(b"inserted");
+    new.extend_from_slice(&old[1000..4000]);
+    new.extend_from_slice(&old[4100..7000]);
+    new.push(0xff);
+    new.extend_from_slice(&old[7001..]);
+
+    let rebuild = |ops: &[DeltaOp]| {
+        let mut rebuilt = Vec::new();
+        for op in ops {
+            match *op {
+                DeltaOp::Copy { offset, len } => {
+                    rebuilt.extend_from_slice(&old[offset as usize..(offset + len) as usize])
+                }
+                DeltaOp::Literal { offset, len } => {
+                    rebuilt.extend_from_slice(&new[offset as usize..(offset + len) as usize])
+                }
+            }
+        }
+        rebuilt
+    };
+    for format in [SignatureFormat::RabinKarpBlake2, SignatureFormat::Blake2] {
+        let signer = Signer::new(256).unwrap().with_format(format);
+        let signature = signer.signature(&old[..]).unwrap();
+        assert_eq!(signature.blocks.len(), 40);
+
+        let ops = signature.delta(&new[..]).unwrap();
+        assert_eq!(rebuild(&ops), new);
+        // Each edit costs the blocks it touches: one around the insertion
+        // and the change, two around the deletion
+        let literal: u64 = ops
+            .iter()
+            .map(|op| match op {
+                DeltaOp::Literal { len, .. } => *len,
+                DeltaOp::Copy { .. } => 0,
+            })
+            .sum();
+        assert_eq!(literal, (256 + 8) + (2 * 256 - 100) + 256, "{:?}", ops);
+
+        // Up to the short last block (10000 = 39 * 256 + 16)
+        assert_eq!(ops.last(), Some(&DeltaOp::Copy { offset: 7168, len: 2832 }));
+        assert_eq!(signature.delta(&old[..]).unwrap(), [DeltaOp::Copy { offset: 0, len: 10_000 }]);
+        // Data shorter than a block is searched for the short last block too
+        let tail = [b"xyz", &old[9984..]].concat();
+        let ops = signature.delta(&tail[..]).unwrap();
+        assert_eq!(
+            ops,
+            [DeltaOp::Literal { offset: 0, len: 3 }, DeltaOp::Copy { offset: 9984, len: 16 }]
+        );
+
+        let bytes = signature.to_bytes();
+        assert_eq!(bytes.len(), 12 + 40 * 36);
+        assert_eq!(u32::from_be_bytes(bytes[..4].try_into().unwrap()), format.magic());
+        assert_eq!(Signature::read_from(&bytes[..]).unwrap(), signature);
+    }
+
+    // Everything is new when nothing matches, and nothing is sent for nothing
+    let signature = Signer::new(64).unwrap().signature(&old[..]).unwrap();
+    let ops = signature.delta(&b"unrelated"[..]).unwrap();
+    assert_eq!(ops, [DeltaOp::Literal { offset: 0, len: 9 }]);
+    assert!(signature.delta(&b""[..]).unwrap().is_empty());
+    assert!(Signer::new(64).unwrap().signature(&b""[..]).unwrap().blocks.is_empty());
+
+    // Blocks edited in place
+    let mut edited = old.clone();
+    edited[300] ^= 1;
+    edited.extend_from_slice(b"appended");
+    let short = Signer::new(256).unwrap().with_strong_len(8).unwrap();
+    let changed = short
+        .signature(&old[..])
+        .unwrap()
+        .changed_blocks(&short.signature(&edited[..]).unwrap())
+        .unwrap();
+    assert_eq!(changed, [1, 39]);
+    let other = Signer::new(128).unwrap().signature(&old[..]).unwrap();
+    assert!(signature.changed_blocks(&other).is_err());
+
+    let mut md4 = signature.to_bytes();
+    md4[..4].copy_from_slice(&0x7273_0136u32.to_be_bytes());
+    assert!(Signature::read_from(&md4[..]).is_err());
+    let bytes = signature.to_bytes();
+    assert!(Signature::read_from(&bytes[..bytes.len() - 1]).is_err());
+    assert!(Signature::read_from(&bytes[..8]).is_err());
+    assert!(Signer::new(0).is_err());
+    assert!(Signer::new(64).unwrap().with_strong_len(33).is_err());
+}
+
+#[test]
+fn test_seed_plan() {
+    use hashing::delta::Signer;
+
+    let mut state = 7u32;
+    let seed: Vec<u8> = (0..10_000)
+        .map(|_| {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            (state >> 16) as u8
+        })
+        .collect();
+    // The published file: the seed with an insertion, a deletion and a change
+    let mut target = seed[..1000].to_vec();
+    target.extend_from_slice(b"inserted");
+    target.extend_from_slice(&seed[1000..4000]);
+    target.extend_from_slice(&seed[4100..7000]);
+    target.push(0xff);
+    target.extend_from_slice(&seed[7001..]);
+    let len = target.len() as u64;
+
+    let signature = Signer::new(256).unwrap().signature(&target[..]).unwrap();
+    let plan = signature.seed_plan(&seed[..], len).unwrap();
+    // Only the blocks around each edit are fetched; the rest moved
+    assert_eq!(plan.fetch_ranges(), [768..1024, 3840..4096, 6656..6912]);
+    assert_eq!(plan.reused_bytes(), len - 3 * 256);
+    assert_eq!(plan.sources[4], Some(1016));
+    assert_eq!(plan.sources[38], Some(9820));
+    assert!(!plan.is_complete());
+
+    // Reused blocks and fetched ranges rebuild the target
+    let mut rebuilt = vec![0u8; target.len()];
+    for (i, source) in plan.sources.iter().enumerate() {
+        if let Some(offset) = *source {
+            let start = i * 256;
+            let end = (start + 256).min(target.len());
+            let offset = offset as usize;
+            rebuilt[start..end].copy_from_slice(&seed[offset..offset + end - start]);
+        }
+    }
+    for range in plan.fetch_ranges() {
+        let range = range.start as usize..range.end as usize;
+        rebuilt[range.clone()].copy_from_slice(&target[range]);
+    }
+    assert_eq!(rebuilt, target);
+
+    // Identical blocks all come from the one copy in the seed
+    let zeros = Signer::new(64).unwrap().signature(&[0u8; 256][..]).unwrap();
+    let plan = zeros.seed_plan(&[&[1u8; 10][..], &[0u8; 64]].concat()[..], 256).unwrap();
+    assert_eq!(plan.sources, [Some(10); 4]);
+    assert!(plan.is_complete());
+    assert!(plan.fetch_ranges().is_empty());
+
+    // Nothing to reuse from an empty seed, and the length must fit
+    let plan = signature.seed_plan(&b""[..], len).unwrap();
+    assert_eq!(plan.fetch_ranges(), vec![0..len]);
+    assert!(signature.seed_plan(&seed[..], len + 256).is_err());
+}
+
+#[test]
+fn test_chunk_index() {
+    use hashing::chunk::{ChunkIndex, ChunkIndexFormat, Chunker};
+    use hashing::hash_bytes;
+
+    let mut state = 3u32;
+    let data: Vec<u8> = (0..1_000_000)
+        .map(|_| {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            (state >> 16) as u8
+        })
+        .collect();
+    let chunker = Chunker::new(4096).unwrap();
+    let index = chunker.index(&data[..]).unwrap();
+    assert_eq!(index.total_len(), 1_000_000);
+    // Sizes stay within the limits and average out near the target
+    let count = index.chunks.len() as u64;
+    assert!((1_000_000 / 8192..=1_000_000 / 2048).contains(&count), "{} chunks", count);
+    for pair in index.chunks.windows(2) {
+        assert_eq!(pair[0].offset + pair[0].len, pair[1].offset);
+        assert!((1024..=16384).contains(&pair[0].len));
+    }
+    let first = &data[..index.chunks[0].len as usize];
+    assert_eq!(index.chunks[0].digest.to_hex(), hash_bytes(first, Algorithm::Sha256).unwrap());
+
+    // Chunks carry their data, and the same chunks come out again
+    let rebuilt: Vec<u8> = chunker
+        .chunks(&data[..])
+        .flat_map(|chunk| chunk.unwrap().1)
+        .collect();
+    assert_eq!(rebuilt, data);
+    assert_eq!(chunker.index(&data[..]).unwrap(), index);
+
+    // An insertion in the middle only changes the chunks around it
+    let mut edited = data.clone();
+    edited.splice(500_000..500_000, b"inserted".iter().copied());
+    let new = chunker.index(&edited[..]).unwrap();
+    let missing = new.missing_from(&index);
+    assert!(!missing.is_empty() && missing.len() <= 2, "{} new chunks", missing.len());
+    assert!(missing.iter().all(|chunk| chunk.offset <= 500_000 + 16384));
+
+    // Repeats are stored once
+    let twice = [&data[..], &data[..]].concat();
+    let doubled = chunker.index(&twice[..]).unwrap();
+    assert!(doubled.unique_len() < index.total_len() + 20_000);
+    assert!(doubled.unique_chunks() < doubled.chunks.len());
+
+    // The text format reads back; the others are written
+    assert_eq!(ChunkIndex::parse(&index.to_text()).unwrap(), index);
+    let mut json = Vec::new();
+    index.write_to(&mut json, ChunkIndexFormat::Json).unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+    assert_eq!(json["chunks"].as_array().unwrap().len(), index.chunks.len());
+    assert_eq!(json["avg_size"], 4096);
+    let mut csv = Vec::new();
+    index.write_to(&mut csv, ChunkIndexFormat::Csv).unwrap();
+    assert!(String::from_utf8(csv).unwrap().starts_with("digest,offset,length\n"));
+
+    let blake3 = chunker.with_algorithm(Algorithm::Blake3).index(&data[..100]).unwrap();
+    assert_eq!(blake3.chunks.len(), 1);
+    let text = blake3.to_text();
+    assert!(text.starts_with("# chunk-index algorithm=blake3 min=1024 avg=4096 max=16384\n"));
+    assert!(ChunkIndex::parse(&text.replace(" 0 100", " 1 100")).is_err());
+    assert!(ChunkIndex::parse("abc 0 1\n").is_err());
+    assert!(chunker.index(&b""[..]).unwrap().chunks.is_empty());
+    assert!(Chunker::new(100).is_err());
+    assert!(chunker.with_limits(8192, 16384).is_err());
+}
+
+#[test]
+fn test_commitment() {
+    use hashing::commitment::{commit, generate_nonce, verify_commitment, Commitment, Opening};
+
+    let nonce = generate_nonce().unwrap();
+    assert_eq!(nonce.len(), 32);
+    assert_ne!(nonce, generate_nonce().unwrap());
+
+    let (commitment, opening) = commit(b"bid: 100", nonce, Algorithm::Sha256).unwrap();
+    assert!(verify_commitment(&commitment, &opening));
+
+    // Round trip through the published hex form
+    let published = Commitment::from_hex(&commitment.to_string(), Algorithm::Sha256).unwrap();
+    assert!(verify_commitment(&published, &opening));
+    assert!(Commitment::from_hex(&commitment.to_string(), Algorithm::Sha512).is_err());
+
+    let changed = Opening {
+        value: b"bid: 101".to_vec(),
+        ..opening.clone()
+    };
+    assert!(!verify_commitment(&commitment, &changed));
+
+    // Moving bytes between nonce and value does not open the commitment
+    let mut shifted = opening.clone();
+    shifted.value.insert(0, shifted.nonce.pop().unwrap());
+    assert!(!verify_commitment(&commitment, &shifted));
+
+    // Same value, different nonce, different commitment
+    let (other, _) = commit(b"bid: 100", [7u8; 16], Algorithm::Blake3).unwrap();
+    assert_ne!(other.as_bytes(), commitment.as_bytes());
+    assert!(commit(b"x", [0u8; 15], Algorithm::Sha256).is_err());
+    assert!(!format!("{:?}", opening).contains("100"));
+}
+
+#[test]
+fn test_chain_log() {
+    use hashing::chain::{verify_records, ChainLog};
+
+    let mut log = ChainLog::new(Algorithm::Sha256);
+    assert!(log.head().is_none());
+    log.append_at("first", 100).unwrap();
+    log.append_at("second", 100).unwrap();
+    let head = log.append_at("third", 200).unwrap().digest.clone();
+    assert_eq!(log.head(), Some(head.as_str()));
+    assert_eq!(log.records()[0].prev, "0".repeat(64));
+    assert_eq!(log.records()[1].prev, log.records()[0].digest);
+    assert!(log.verify().is_ok());
+    assert!(log.append_at("backwards", 150).is_err());
+
+    // Any edit, removal or reordering breaks verification
+    let mut edited = log.records().to_vec();
+    edited[1].entry = "tampered".to_string();
+    assert!(verify_records(&edited, Algorithm::Sha256).is_err());
+    let mut removed = log.records().to_vec();
+    removed.remove(1);
+    assert!(verify_records(&removed, Algorithm::Sha256).is_err());
+    let mut swapped = log.records().to_vec();
+    swapped.swap(0, 1);
+    assert!(verify_records(&swapped, Algorithm::Sha256).is_err());
+    assert!(verify_records(log.records(), Algorithm::Sha3_256).is_err());
+
+    // File-backed logs survive reopening and reject tampered files
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("audit.jsonl");
+    {
+        let mut log = ChainLog::open(&path, Algorithm::Blake3).unwrap();
+        log.append("opened").unwrap();
+        log.append("closed").unwrap();
+    }
+    let mut log = ChainLog::open(&path, Algorithm::Blake3).unwrap();
+    assert_eq!(log.len(), 2);
+    log.append("reopened").unwrap();
+    assert_eq!(ChainLog::open(&path, Algorithm::Blake3).unwrap().len(), 3);
+
+    let text = std::fs::read_to_string(&path).unwrap();
+    std::fs::write(&path, text.replace("closed", "CLOSED")).unwrap();
+    assert!(ChainLog::open(&path, Algorithm::Blake3).is_err());
+}
+
+#[test]
+fn test_ethereum_addresses() {
+    use hashing::ethereum::{
+        address_from_public_key, is_checksum_address, parse_address, to_checksum_address,
+    };
+
+    // Test vectors from EIP-55
+    for address in [
+        "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+        "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+        "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+        "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+    ] {
+        assert_eq!(to_checksum_address(&address.to_lowercase()).unwrap(), address);
+        assert_eq!(to_checksum_address(&address[2..].to_uppercase()).unwrap(), address);
+        assert!(is_checksum_address(address));
+        assert!(!is_checksum_address(&address.to_lowercase()));
+    }
+
+    // One flipped letter case breaks the checksum
+    assert!(parse_address("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD").is_err());
+    assert!(parse_address("0x5aaeb6053f3e94c9b9a09f33669435e7ef1beae").is_err());
+
+    // The secp256k1 generator point, i.e. the public key of private key 1
+    let public_key = hex::decode(
+        "0479be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798\
+         483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8",
+    )
+    .unwrap();
+    let address = "0x7E5F4552091A69125d5DfCb7b8C2659029395Bdf";
+    assert_eq!(address_from_public_key(&public_key).unwrap(), address);
+    assert_eq!(address_from_public_key(&public_key[1..]).unwrap(), address);
+    assert!(address_from_public_key(&public_key[..33]).is_err());
+}